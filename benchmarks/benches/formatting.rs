@@ -59,6 +59,12 @@ fn bench_formatting(c: &mut criterion::Criterion) {
                     matcher.format(option.clone());
                 })
             });
+
+            let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+            let mut formatter = conf.matching_words.formatter(&analyzer);
+            group.bench_function(&format!("{}-reused-analyzer", conf.name), |b| {
+                b.iter(|| formatter.format(conf.text, option.clone()))
+            });
         }
         group.finish();
     }
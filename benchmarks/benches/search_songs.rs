@@ -145,6 +145,11 @@ fn bench_songs(c: &mut criterion::Criterion) {
             filter: Some("released-timestamp <= 946728000"), // year 2000
             ..BASE_CONF
         },
+        utils::Conf {
+            group_name: "basic filter: =",
+            filter: Some("released-timestamp = 946728000"), // January 1st 2000
+            ..BASE_CONF
+        },
         utils::Conf {
             group_name: "basic filter: TO",
             filter: Some("released-timestamp 946728000 TO 1262347200"), // year 2000 to 2010
@@ -230,7 +230,7 @@ impl Performer for DocumentAddition {
         println!("Adding {} documents to the index.", reader.len());
 
         let mut txn = index.env.write_txn()?;
-        let config = milli::update::IndexerConfig { log_every_n: Some(100), ..Default::default() };
+        let config = IndexerConfig::builder().log_every_n(100).build()?;
         let update_method = if self.update_documents {
             IndexDocumentsMethod::UpdateDocuments
         } else {
@@ -477,7 +477,7 @@ impl Performer for SettingsUpdate {
     fn perform(self, index: milli::Index) -> Result<()> {
         let mut txn = index.env.write_txn()?;
 
-        let config = IndexerConfig { log_every_n: Some(100), ..Default::default() };
+        let config = IndexerConfig::builder().log_every_n(100).build()?;
 
         let mut update = milli::update::Settings::new(&mut txn, &index, &config);
 
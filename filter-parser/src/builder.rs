@@ -0,0 +1,253 @@
+//! A typed builder to compose filters without formatting and parsing strings.
+//!
+//! ```
+//! use filter_parser::FilterCondition;
+//!
+//! let horror = FilterCondition::field("genre").equal("horror");
+//! let comedy = FilterCondition::field("genre").equal("comedy");
+//! let filter = FilterCondition::field("price").greater_than(10).and(horror.or(comedy));
+//! ```
+//!
+//! The values are never parsed as filter expressions, they can contain quotes,
+//! spaces or operators without any escaping. The strings are considered quoted,
+//! `equal("true")` builds a condition on a string while `equal(true)` builds one on a boolean.
+
+use std::fmt::Display;
+use std::ops::Not;
+
+use nom::Slice;
+
+use crate::{Condition, FilterCondition, Span, Token};
+
+/// A value that can be given to the [`FieldCondition`] methods.
+pub trait FilterValue: Display {
+    /// Whether the value would be written between quotes in a filter expression.
+    fn is_quoted(&self) -> bool;
+}
+
+impl FilterValue for str {
+    fn is_quoted(&self) -> bool {
+        true
+    }
+}
+
+impl FilterValue for String {
+    fn is_quoted(&self) -> bool {
+        true
+    }
+}
+
+impl FilterValue for char {
+    fn is_quoted(&self) -> bool {
+        true
+    }
+}
+
+impl<T: FilterValue + ?Sized> FilterValue for &T {
+    fn is_quoted(&self) -> bool {
+        (**self).is_quoted()
+    }
+}
+
+macro_rules! impl_unquoted_filter_value {
+    ($($ty:ty),*) => {
+        $(
+            impl FilterValue for $ty {
+                fn is_quoted(&self) -> bool {
+                    false
+                }
+            }
+        )*
+    };
+}
+
+impl_unquoted_filter_value!(
+    bool, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64
+);
+
+/// A field on which a condition can be applied, see [`FilterCondition::field`].
+#[derive(Debug, Clone)]
+pub struct FieldCondition<'a> {
+    fid: Token<'a>,
+}
+
+impl<'a> FieldCondition<'a> {
+    pub fn equal(self, value: impl FilterValue) -> FilterCondition<'a> {
+        self.condition(Condition::Equal(value_token(value)))
+    }
+
+    pub fn not_equal(self, value: impl FilterValue) -> FilterCondition<'a> {
+        self.condition(Condition::NotEqual(value_token(value)))
+    }
+
+    pub fn greater_than(self, value: impl FilterValue) -> FilterCondition<'a> {
+        self.condition(Condition::GreaterThan(value_token(value)))
+    }
+
+    pub fn greater_than_or_equal(self, value: impl FilterValue) -> FilterCondition<'a> {
+        self.condition(Condition::GreaterThanOrEqual(value_token(value)))
+    }
+
+    pub fn lower_than(self, value: impl FilterValue) -> FilterCondition<'a> {
+        self.condition(Condition::LowerThan(value_token(value)))
+    }
+
+    pub fn lower_than_or_equal(self, value: impl FilterValue) -> FilterCondition<'a> {
+        self.condition(Condition::LowerThanOrEqual(value_token(value)))
+    }
+
+    /// The equivalent of the `field from TO to` expression, both bounds are included.
+    pub fn between(self, from: impl FilterValue, to: impl FilterValue) -> FilterCondition<'a> {
+        self.condition(Condition::Between { from: value_token(from), to: value_token(to) })
+    }
+
+    /// The equivalent of the `field EXACT phrase` expression.
+    pub fn exact(self, phrase: impl FilterValue) -> FilterCondition<'a> {
+        self.condition(Condition::Exact(value_token(phrase)))
+    }
+
+    fn condition(self, op: Condition<'a>) -> FilterCondition<'a> {
+        FilterCondition::Condition { fid: self.fid, op }
+    }
+}
+
+impl<'a> FilterCondition<'a> {
+    /// Starts a condition on the given field.
+    pub fn field(name: &'a str) -> FieldCondition<'a> {
+        FieldCondition { fid: Span::new_extra(name, name).into() }
+    }
+
+    /// The equivalent of the `_geoRadius(lat, lng, radius)` expression.
+    pub fn geo_radius(lat: f64, lng: f64, radius: f64) -> FilterCondition<'a> {
+        FilterCondition::GeoLowerThan {
+            point: [value_token(lat), value_token(lng)],
+            radius: value_token(radius),
+        }
+    }
+
+    pub fn and(self, other: FilterCondition<'a>) -> FilterCondition<'a> {
+        FilterCondition::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: FilterCondition<'a>) -> FilterCondition<'a> {
+        FilterCondition::Or(Box::new(self), Box::new(other))
+    }
+}
+
+impl<'a> Not for FilterCondition<'a> {
+    type Output = FilterCondition<'a>;
+
+    fn not(self) -> Self::Output {
+        self.negate()
+    }
+}
+
+/// Creates a token that doesn't come from an input, its value is stored as is.
+fn value_token<'a>(value: impl FilterValue) -> Token<'a> {
+    // Like the parsed ones, the quoted tokens are preceded by a quote in their input.
+    let span = if value.is_quoted() {
+        Span::new_extra("\"", "\"").slice(1..)
+    } else {
+        Span::new_extra("", "")
+    };
+    Token::new(span, Some(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_like_parse() {
+        let test_case = [
+            (FilterCondition::field("channel").equal("Ponce"), "channel = Ponce"),
+            (FilterCondition::field("subscribers").not_equal(12), "subscribers != 12"),
+            (FilterCondition::field("subscribers").greater_than(1000), "subscribers > 1000"),
+            (FilterCondition::field("subscribers").lower_than_or_equal(1.5), "subscribers <= 1.5"),
+            (FilterCondition::field("subscribers").between(100, 1000), "subscribers 100 TO 1000"),
+            (
+                FilterCondition::field("channel")
+                    .equal("Ponce")
+                    .and(FilterCondition::field("subscribers").greater_than(1000)),
+                "channel = Ponce AND subscribers > 1000",
+            ),
+            (
+                FilterCondition::field("channel")
+                    .equal("Ponce")
+                    .or(FilterCondition::field("channel").equal("Tamo")),
+                "channel = Ponce OR channel = Tamo",
+            ),
+            (!FilterCondition::field("subscribers").greater_than(1000), "subscribers <= 1000"),
+            (FilterCondition::geo_radius(12., 13., 14.), "_geoRadius(12, 13, 14)"),
+            (FilterCondition::field("title").exact("the hobbit"), "title EXACT 'the hobbit'"),
+            (!FilterCondition::field("title").exact("dune"), "NOT title EXACT dune"),
+        ];
+
+        for (built, expression) in test_case {
+            let parsed = FilterCondition::parse(expression).unwrap().unwrap();
+            assert_eq!(format!("{:?}", strip(&built)), format!("{:?}", strip(&parsed)));
+        }
+    }
+
+    #[test]
+    fn values_are_not_parsed() {
+        let condition = FilterCondition::field("title").equal(r#"a "quoted" \ OR b = c"#);
+        match condition {
+            FilterCondition::Condition { fid, op: Condition::Equal(value) } => {
+                assert_eq!(fid.value(), "title");
+                assert_eq!(value.value(), r#"a "quoted" \ OR b = c"#);
+            }
+            condition => panic!("unexpected condition {:?}", condition),
+        }
+    }
+
+    #[test]
+    fn strings_are_not_booleans() {
+        let string = FilterCondition::field("available").equal("true");
+        let boolean = FilterCondition::field("available").equal(true);
+        assert_ne!(string, boolean);
+        assert_eq!(string, FilterCondition::field("available").equal(String::from("true")));
+        assert_eq!(boolean, FilterCondition::field("available").equal(true));
+    }
+
+    /// Returns the values of the tokens of a condition, to compare
+    /// the built conditions with the parsed ones.
+    fn strip(condition: &FilterCondition) -> String {
+        use FilterCondition::*;
+
+        match condition {
+            Condition { fid, op } => {
+                let op = match op {
+                    crate::Condition::GreaterThan(v) => format!("> {}", number(v)),
+                    crate::Condition::GreaterThanOrEqual(v) => format!(">= {}", number(v)),
+                    crate::Condition::Equal(v) => format!("= {}", number(v)),
+                    crate::Condition::NotEqual(v) => format!("!= {}", number(v)),
+                    crate::Condition::LowerThan(v) => format!("< {}", number(v)),
+                    crate::Condition::LowerThanOrEqual(v) => format!("<= {}", number(v)),
+                    crate::Condition::Between { from, to } => {
+                        format!("{} TO {}", number(from), number(to))
+                    }
+                    crate::Condition::Exact(v) => format!("EXACT {}", v.value()),
+                    crate::Condition::NotExact(v) => format!("NOT EXACT {}", v.value()),
+                };
+                format!("{} {}", fid.value(), op)
+            }
+            Or(a, b) => format!("({}) OR ({})", strip(a), strip(b)),
+            And(a, b) => format!("({}) AND ({})", strip(a), strip(b)),
+            GeoLowerThan { point: [lat, lng], radius } => {
+                format!("_geoRadius({}, {}, {})", number(lat), number(lng), number(radius))
+            }
+            GeoGreaterThan { point: [lat, lng], radius } => {
+                format!("NOT _geoRadius({}, {}, {})", number(lat), number(lng), number(radius))
+            }
+        }
+    }
+
+    /// Normalizes the numbers as `12` and `12.0` are written differently.
+    fn number(token: &Token) -> String {
+        match token.parse::<f64>() {
+            Ok(number) => number.to_string(),
+            Err(_) => token.value().to_string(),
+        }
+    }
+}
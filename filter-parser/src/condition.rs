@@ -1,14 +1,15 @@
 //! BNF grammar:
 //!
 //! ```text
-//! condition      = value ("==" | ">" ...) value
+//! condition      = value ("==" | ">" ... | "EXACT") value
 //! to             = value value TO value
 //! ```
 
 use nom::branch::alt;
 use nom::bytes::complete::tag;
+use nom::character::complete::multispace1;
 use nom::combinator::cut;
-use nom::sequence::tuple;
+use nom::sequence::{terminated, tuple};
 use Condition::*;
 
 use crate::{parse_value, FilterCondition, IResult, Span, Token};
@@ -21,7 +22,13 @@ pub enum Condition<'a> {
     NotEqual(Token<'a>),
     LowerThan(Token<'a>),
     LowerThanOrEqual(Token<'a>),
-    Between { from: Token<'a>, to: Token<'a> },
+    Between {
+        from: Token<'a>,
+        to: Token<'a>,
+    },
+    /// The phrase is exactly the full value of the attribute.
+    Exact(Token<'a>),
+    NotExact(Token<'a>),
 }
 
 impl<'a> Condition<'a> {
@@ -36,13 +43,17 @@ impl<'a> Condition<'a> {
             LowerThan(n) => (GreaterThanOrEqual(n), None),
             LowerThanOrEqual(n) => (GreaterThan(n), None),
             Between { from, to } => (LowerThan(from), Some(GreaterThan(to))),
+            Exact(s) => (NotExact(s), None),
+            NotExact(s) => (Exact(s), None),
         }
     }
 }
 
-/// condition      = value ("==" | ">" ...) value
+/// condition      = value ("==" | ">" ... | "EXACT") value
 pub fn parse_condition(input: Span) -> IResult<FilterCondition> {
-    let operator = alt((tag("<="), tag(">="), tag("!="), tag("<"), tag(">"), tag("=")));
+    // `EXACT` must be followed by a whitespace so that values like `EXACTLY` aren't split.
+    let exact = terminated(tag("EXACT"), multispace1);
+    let operator = alt((tag("<="), tag(">="), tag("!="), tag("<"), tag(">"), tag("="), exact));
     let (input, (fid, op, value)) = tuple((parse_value, operator, cut(parse_value)))(input)?;
 
     let condition = match *op.fragment() {
@@ -52,6 +63,7 @@ pub fn parse_condition(input: Span) -> IResult<FilterCondition> {
         "<" => FilterCondition::Condition { fid, op: LowerThan(value) },
         ">" => FilterCondition::Condition { fid, op: GreaterThan(value) },
         "=" => FilterCondition::Condition { fid, op: Equal(value) },
+        "EXACT" => FilterCondition::Condition { fid, op: Exact(value) },
         _ => unreachable!(),
     };
 
@@ -7,12 +7,13 @@
 //! and            = not (~ "AND" not)*
 //! not            = ("NOT" ~ not) | primary
 //! primary        = (WS* ~ "("  expression ")" ~ WS*) | geoRadius | condition | to
-//! condition      = value ("==" | ">" ...) value
+//! condition      = value ("==" | ">" ... | "EXACT") value
 //! to             = value value TO value
-//! value          = WS* ~ ( word | singleQuoted | doubleQuoted) ~ WS*
+//! value          = WS* ~ ( word | singleQuoted | doubleQuoted | rawString) ~ WS*
 //! singleQuoted   = "'" .* all but quotes "'"
 //! doubleQuoted   = "\"" .* all but double quotes "\""
-//! word           = (alphanumeric | _ | - | .)+
+//! rawString      = "r" ~ "#"{n} ~ "\"" .* "\"" ~ "#"{n}
+//! word           = (alphanumeric | _ | - | . | [ | ])+
 //! geoRadius      = WS* ~ "_geoRadius(" ~ WS* ~ float ~ WS* ~ "," ~ WS* ~ float ~ WS* ~ "," float ~ WS* ~ ")"
 //! ```
 //!
@@ -35,6 +36,7 @@
 //! ```
 //!
 
+mod builder;
 mod condition;
 mod error;
 mod value;
@@ -43,6 +45,7 @@ use std::fmt::Debug;
 use std::ops::Deref;
 use std::str::FromStr;
 
+pub use builder::{FieldCondition, FilterValue};
 pub use condition::{parse_condition, parse_to, Condition};
 use error::{cut_with_err, NomErrorExt};
 pub use error::{Error, ErrorKind};
@@ -80,7 +83,15 @@ impl<'a> Deref for Token<'a> {
 
 impl<'a> PartialEq for Token<'a> {
     fn eq(&self, other: &Self) -> bool {
+        // The tokens that are not coming from an input only differ by their values.
+        let same_values = match (&self.value, &other.value) {
+            (Some(value), Some(other)) => value == other,
+            _ => true,
+        };
+        // `true` is a boolean while `"true"` is a string.
         self.span.fragment() == other.span.fragment()
+            && same_values
+            && self.is_quoted() == other.is_quoted()
     }
 }
 
@@ -93,6 +104,14 @@ impl<'a> Token<'a> {
         self.value.as_ref().map_or(&self.span, |value| value)
     }
 
+    /// Returns `true` if the token was written between quotes in the original input,
+    /// e.g. `"true"` is a string while `true` is a boolean. The tokens built from
+    /// strings by the [`FieldCondition`] are quoted, the other built tokens are not.
+    pub fn is_quoted(&self) -> bool {
+        let offset = self.span.location_offset();
+        self.span.extra.get(..offset).is_some_and(|before| before.ends_with(['"', '\'']))
+    }
+
     pub fn as_external_error(&self, error: impl std::error::Error) -> Error<'a> {
         Error::new_from_external(self.span, error)
     }
@@ -102,7 +121,7 @@ impl<'a> Token<'a> {
         T: FromStr,
         T::Err: std::error::Error,
     {
-        self.span.parse().map_err(|e| self.as_external_error(e))
+        self.value().parse().map_err(|e| self.as_external_error(e))
     }
 }
 
@@ -280,8 +299,8 @@ pub mod tests {
         // if the string is empty we still need to return 1 for the line number
         let lines = before.is_empty().then(|| 1).unwrap_or_else(|| before.lines().count());
         let offset = before.chars().count();
-        // the extra field is not checked in the tests so we can set it to nothing
-        unsafe { Span::new_from_raw_offset(offset, lines as u32, value, "") }.into()
+        // the extra field is only read to know if the token is quoted
+        unsafe { Span::new_from_raw_offset(offset, lines as u32, value, before) }.into()
     }
 
     #[test]
@@ -319,6 +338,13 @@ pub mod tests {
                     op: Condition::Equal(rtok("channel = \"", "Mister Mv")),
                 },
             ),
+            (
+                r#"path = r"C:\dir\""#,
+                Fc::Condition {
+                    fid: rtok("", "path"),
+                    op: Condition::Equal(rtok(r#"path = r""#, r"C:\dir\")),
+                },
+            ),
             (
                 "'dog race' = Borzoi",
                 Fc::Condition {
@@ -450,6 +476,20 @@ pub mod tests {
                     .into(),
                 ),
             ),
+            (
+                "title EXACT \"the hobbit\"",
+                Fc::Condition {
+                    fid: rtok("", "title"),
+                    op: Condition::Exact(rtok("title EXACT \"", "the hobbit")),
+                },
+            ),
+            (
+                "NOT title EXACT dune",
+                Fc::Condition {
+                    fid: rtok("NOT ", "title"),
+                    op: Condition::NotExact(rtok("NOT title EXACT ", "dune")),
+                },
+            ),
             (
                 "_geoRadius(12, 13, 14)",
                 Fc::GeoLowerThan {
@@ -572,6 +612,26 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn quoted_values() {
+        fn is_quoted(condition: FilterCondition) -> bool {
+            match condition {
+                FilterCondition::Condition { op: Condition::Equal(value), .. } => value.is_quoted(),
+                condition => panic!("unexpected condition {:?}", condition),
+            }
+        }
+        let parse = |input| FilterCondition::parse(input).unwrap().unwrap();
+
+        assert!(!is_quoted(parse("available = true")));
+        assert!(!is_quoted(parse("available=false")));
+        assert!(is_quoted(parse("available = \"true\"")));
+        assert!(is_quoted(parse("available = 'true'")));
+        assert!(is_quoted(parse("available = r\"true\"")));
+        assert!(is_quoted(parse("available = \"\"")));
+        assert!(!is_quoted(FilterCondition::field("available").equal(true)));
+        assert!(is_quoted(FilterCondition::field("available").equal("true")));
+    }
+
     #[test]
     fn error() {
         use FilterCondition as Fc;
@@ -585,6 +645,7 @@ pub mod tests {
             ("OR", "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO` or `_geoRadius` at `OR`."),
             ("AND", "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO` or `_geoRadius` at `AND`."),
             ("channel Ponce", "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO` or `_geoRadius` at `channel Ponce`."),
+            ("title EXACTLY dune", "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO` or `_geoRadius` at `title EXACTLY dune`."),
             ("channel = Ponce OR", "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO` or `_geoRadius` but instead got nothing."),
             ("_geoRadius", "The `_geoRadius` filter expects three arguments: `_geoRadius(latitude, longitude, radius)`."),
             ("_geoRadius = 12", "The `_geoRadius` filter expects three arguments: `_geoRadius(latitude, longitude, radius)`."),
@@ -48,7 +48,30 @@ fn quoted_by(quote: char, input: Span) -> IResult<Token> {
     ))
 }
 
-/// value          = WS* ~ ( word | singleQuoted | doubleQuoted) ~ WS*
+/// Parse a raw string, its content is kept as is and never unescaped.
+///
+/// rawString      = "r" ~ "#"{n} ~ "\"" .* "\"" ~ "#"{n}
+fn raw_string(raw: Span) -> IResult<Token> {
+    let (input, _) = char('r')(raw)?;
+    let (input, hashes) = take_while(|c| c == '#')(input)?;
+    let (input, _) = char('"')(input)?;
+
+    // Once the opening delimiter is found we know we are in a raw string, we
+    // then search for the first double quote followed by the same number of hashes.
+    let closing_delimiter = format!("\"{}", hashes.fragment());
+    match input.fragment().find(&closing_delimiter) {
+        Some(idx) => {
+            let (rem, output) = input.take_split(idx);
+            Ok((rem.slice(closing_delimiter.len()..), output.into()))
+        }
+        None => Err(nom::Err::Failure(Error::new_from_kind(
+            raw,
+            ErrorKind::MissingClosingDelimiter('"'),
+        ))),
+    }
+}
+
+/// value          = WS* ~ ( word | singleQuoted | doubleQuoted | rawString) ~ WS*
 pub fn parse_value<'a>(input: Span<'a>) -> IResult<Token<'a>> {
     // to get better diagnostic message we are going to strip the left whitespaces from the input right now
     let (input, _) = take_while(char::is_whitespace)(input)?;
@@ -71,7 +94,7 @@ pub fn parse_value<'a>(input: Span<'a>) -> IResult<Token<'a>> {
         _ => (),
     }
 
-    // word           = (alphanumeric | _ | - | .)+
+    // word           = (alphanumeric | _ | - | . | [ | ])+
     let word = |input: Span<'a>| -> IResult<Token<'a>> {
         take_while1(is_value_component)(input).map(|(s, t)| (s, t.into()))
     };
@@ -89,6 +112,8 @@ pub fn parse_value<'a>(input: Span<'a>) -> IResult<Token<'a>> {
         alt((
             delimited(char('\''), cut(|input| quoted_by('\'', input)), cut(char('\''))),
             delimited(char('"'), cut(|input| quoted_by('"', input)), cut(char('"'))),
+            // must be tried before the words as a raw string starts with an `r`
+            raw_string,
             word,
         )),
         multispace0,
@@ -111,7 +136,7 @@ pub fn parse_value<'a>(input: Span<'a>) -> IResult<Token<'a>> {
 }
 
 fn is_value_component(c: char) -> bool {
-    c.is_alphanumeric() || ['_', '-', '.'].contains(&c)
+    c.is_alphanumeric() || ['_', '-', '.', '[', ']'].contains(&c)
 }
 
 fn is_syntax_component(c: char) -> bool {
@@ -132,6 +157,7 @@ pub mod test {
             (".private", rtok("", ".private")),
             ("I-love-kebab", rtok("", "I-love-kebab")),
             ("but_snakes_is_also_good", rtok("", "but_snakes_is_also_good")),
+            ("authors[0]", rtok("", "authors[0]")),
             ("parens(", rtok("", "parens")),
             ("parens)", rtok("", "parens")),
             ("not!", rtok("", "not")),
@@ -250,6 +276,14 @@ pub mod test {
             (r#"'Hello \\\'world\\\''"#, r#"Hello \\'world\\'"#, true),
             (r#"'I\'m "super" tamo'"#, r#"I'm "super" tamo"#, true),
             (r#"'\'\''"#, r#"''"#, true),
+            // raw strings
+            (r#"r"C:\dir\""#, r#"C:\dir\"#, false),
+            (r#"r"say \"hi""#, r#"say \"#, false),
+            (r##"r#"say "hi""#"##, r#"say "hi""#, false),
+            (r###"r##"a "# in it"##"###, r##"a "# in it"##, false),
+            (r#"r"""#, "", false),
+            ("r", "r", false),
+            ("raw", "raw", false),
         ];
 
         for (input, expected, escaped) in test_case {
@@ -281,6 +315,8 @@ pub mod test {
             ("🦀 AND crab = truc", "🦀"),
             ("🦀_in_name", "🦀_in_name"),
             (" (name = ...", ""),
+            (r#"r"unterminated"#, r#"r"unterminated"#),
+            (r##"r#"unterminated""##, r##"r#"unterminated""##),
         ];
 
         for (input, expected) in test_case {
@@ -314,16 +314,23 @@ async fn main() -> anyhow::Result<()> {
     let jobs = opt.indexer.indexing_jobs.unwrap_or(0);
     let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
 
-    let config = IndexerConfig {
-        max_nb_chunks: opt.indexer.max_nb_chunks,
-        chunk_compression_level: opt.indexer.chunk_compression_level,
-        max_positions_per_attributes: opt.indexer.max_positions_per_attributes,
-        thread_pool: Some(pool),
-        log_every_n: Some(opt.indexer.log_every_n),
-        max_memory: Some(opt.indexer.max_memory.get_bytes() as usize),
-        chunk_compression_type: opt.indexer.chunk_compression_type.unwrap_or(CompressionType::None),
-        ..Default::default()
-    };
+    let mut config = IndexerConfig::builder()
+        .thread_pool(pool)
+        .log_every_n(opt.indexer.log_every_n)
+        .max_memory(opt.indexer.max_memory.get_bytes() as usize)
+        .chunk_compression_type(
+            opt.indexer.chunk_compression_type.unwrap_or(CompressionType::None),
+        );
+    if let Some(max_nb_chunks) = opt.indexer.max_nb_chunks {
+        config = config.max_nb_chunks(max_nb_chunks);
+    }
+    if let Some(level) = opt.indexer.chunk_compression_level {
+        config = config.chunk_compression_level(level);
+    }
+    if let Some(max_positions) = opt.indexer.max_positions_per_attributes {
+        config = config.max_positions_per_attributes(max_positions);
+    }
+    let config = config.build()?;
 
     GLOBAL_CONFIG.set(config).unwrap();
 
@@ -779,7 +786,7 @@ async fn main() -> anyhow::Result<()> {
                 search.sort_criteria(vec![sort.parse().map_err(SortError::from).unwrap()]);
             }
 
-            let SearchResult { matching_words, candidates, documents_ids } =
+            let SearchResult { matching_words, candidates, documents_ids, .. } =
                 search.execute().unwrap();
 
             let number_of_candidates = candidates.len();
@@ -384,6 +384,7 @@ fn biggest_value_sizes(index: &Index, rtxn: &heed::RoTxn, limit: usize) -> anyho
         word_position_docids,
         word_prefix_position_docids,
         field_id_word_count_docids,
+        docid_field_id_word_count: _,
         facet_id_f64_docids,
         facet_id_string_docids,
         exact_word_docids,
@@ -391,6 +392,8 @@ fn biggest_value_sizes(index: &Index, rtxn: &heed::RoTxn, limit: usize) -> anyho
         field_id_docid_facet_f64s: _,
         field_id_docid_facet_strings: _,
         documents,
+        vectors: _,
+        ..
     } = index;
 
     let main_name = "main";
@@ -983,6 +986,7 @@ fn size_of_databases(index: &Index, rtxn: &heed::RoTxn, names: Vec<String>) -> a
         word_position_docids,
         word_prefix_position_docids,
         field_id_word_count_docids,
+        docid_field_id_word_count: _,
         facet_id_f64_docids,
         facet_id_string_docids,
         field_id_docid_facet_f64s,
@@ -990,6 +994,8 @@ fn size_of_databases(index: &Index, rtxn: &heed::RoTxn, names: Vec<String>) -> a
         exact_word_prefix_docids,
         exact_word_docids,
         documents,
+        vectors: _,
+        ..
     } = index;
 
     let names = if names.is_empty() {
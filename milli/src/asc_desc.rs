@@ -15,6 +15,7 @@ use crate::{CriterionError, Error, UserError};
 pub enum AscDescError {
     InvalidLatitude,
     InvalidLongitude,
+    InvalidMaxDistance,
     InvalidSyntax { name: String },
     ReservedKeyword { name: String },
 }
@@ -28,6 +29,9 @@ impl fmt::Display for AscDescError {
             Self::InvalidLongitude => {
                 write!(f, "Longitude must be contained between -180 and 180 degrees.",)
             }
+            Self::InvalidMaxDistance => {
+                write!(f, "The maximum distance must be a positive number of meters.",)
+            }
             Self::InvalidSyntax { name } => {
                 write!(f, "Invalid syntax for the asc/desc parameter: expected expression ending by `:asc` or `:desc`, found `{}`.", name)
             }
@@ -45,7 +49,9 @@ impl fmt::Display for AscDescError {
 impl From<AscDescError> for CriterionError {
     fn from(error: AscDescError) -> Self {
         match error {
-            AscDescError::InvalidLatitude | AscDescError::InvalidLongitude => {
+            AscDescError::InvalidLatitude
+            | AscDescError::InvalidLongitude
+            | AscDescError::InvalidMaxDistance => {
                 CriterionError::ReservedNameForSort { name: "_geoPoint".to_string() }
             }
             AscDescError::InvalidSyntax { name } => CriterionError::InvalidName { name },
@@ -64,6 +70,9 @@ impl From<AscDescError> for CriterionError {
 pub enum Member {
     Field(String),
     Geo([f64; 2]),
+    /// A geo point and a maximum distance in meters, the documents farther than this distance
+    /// are not sorted and are all put in a last bucket.
+    GeoWithin([f64; 2], f64),
 }
 
 impl FromStr for Member {
@@ -72,21 +81,28 @@ impl FromStr for Member {
     fn from_str(text: &str) -> Result<Member, Self::Err> {
         match text.strip_prefix("_geoPoint(").and_then(|text| text.strip_suffix(")")) {
             Some(point) => {
-                let (lat, lng) = point
-                    .split_once(',')
-                    .ok_or_else(|| AscDescError::ReservedKeyword { name: text.to_string() })
-                    .and_then(|(lat, lng)| {
-                        lat.trim()
-                            .parse()
-                            .and_then(|lat| lng.trim().parse().map(|lng| (lat, lng)))
-                            .map_err(|_| AscDescError::ReservedKeyword { name: text.to_string() })
-                    })?;
+                let parameters = point
+                    .split(',')
+                    .map(|parameter| parameter.trim().parse::<f64>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| AscDescError::ReservedKeyword { name: text.to_string() })?;
+                let (lat, lng, max_distance) = match parameters.as_slice() {
+                    [lat, lng] => (*lat, *lng, None),
+                    [lat, lng, max_distance] => (*lat, *lng, Some(*max_distance)),
+                    _ => return Err(AscDescError::ReservedKeyword { name: text.to_string() }),
+                };
                 if !(-90.0..=90.0).contains(&lat) {
                     return Err(AscDescError::InvalidLatitude)?;
                 } else if !(-180.0..=180.0).contains(&lng) {
                     return Err(AscDescError::InvalidLongitude)?;
                 }
-                Ok(Member::Geo([lat, lng]))
+                match max_distance {
+                    Some(max_distance) if max_distance.is_finite() && max_distance >= 0.0 => {
+                        Ok(Member::GeoWithin([lat, lng], max_distance))
+                    }
+                    Some(_) => Err(AscDescError::InvalidMaxDistance),
+                    None => Ok(Member::Geo([lat, lng])),
+                }
             }
             None => {
                 if is_reserved_keyword(text) || text.starts_with("_geoRadius(") {
@@ -103,6 +119,9 @@ impl fmt::Display for Member {
         match self {
             Member::Field(name) => f.write_str(name),
             Member::Geo([lat, lng]) => write!(f, "_geoPoint({}, {})", lat, lng),
+            Member::GeoWithin([lat, lng], max_distance) => {
+                write!(f, "_geoPoint({}, {}, {})", lat, lng, max_distance)
+            }
         }
     }
 }
@@ -111,13 +130,13 @@ impl Member {
     pub fn field(&self) -> Option<&str> {
         match self {
             Member::Field(field) => Some(field),
-            Member::Geo(_) => None,
+            Member::Geo(_) | Member::GeoWithin(..) => None,
         }
     }
 
     pub fn geo_point(&self) -> Option<&[f64; 2]> {
         match self {
-            Member::Geo(point) => Some(point),
+            Member::Geo(point) | Member::GeoWithin(point, _) => Some(point),
             Member::Field(_) => None,
         }
     }
@@ -160,8 +179,10 @@ pub enum SortError {
     InvalidLatitude,
     #[error("{}", AscDescError::InvalidLongitude)]
     InvalidLongitude,
+    #[error("{}", AscDescError::InvalidMaxDistance)]
+    InvalidMaxDistance,
     #[error("Invalid syntax for the geo parameter: expected expression formated like \
-                    `_geoPoint(latitude, longitude)` and ending by `:asc` or `:desc`, found `{name}`.")]
+                    `_geoPoint(latitude, longitude)` or `_geoPoint(latitude, longitude, max_distance)` and ending by `:asc` or `:desc`, found `{name}`.")]
     BadGeoPointUsage { name: String },
     #[error("Invalid syntax for the sort parameter: expected expression ending by `:asc` or `:desc`, found `{name}`.")]
     InvalidName { name: String },
@@ -180,6 +201,7 @@ impl From<AscDescError> for SortError {
         match error {
             AscDescError::InvalidLatitude => SortError::InvalidLatitude,
             AscDescError::InvalidLongitude => SortError::InvalidLongitude,
+            AscDescError::InvalidMaxDistance => SortError::InvalidMaxDistance,
             AscDescError::InvalidSyntax { name } => SortError::InvalidName { name },
             AscDescError::ReservedKeyword { name } if name.starts_with("_geoPoint") => {
                 SortError::BadGeoPointUsage { name }
@@ -227,6 +249,9 @@ mod tests {
             ("_geoPoint(-90, -180.0000000000):asc", Asc(Geo([-90., -180.]))),
             ("_geoPoint(42.0002, 59.895):desc", Desc(Geo([42.0002, 59.895]))),
             ("_geoPoint(42., 59.):desc", Desc(Geo([42., 59.]))),
+            ("_geoPoint(35, 85, 75):asc", Asc(GeoWithin([35., 85.], 75.))),
+            ("_geoPoint(35, 85, 0):desc", Desc(GeoWithin([35., 85.], 0.))),
+            ("_geoPoint(-35.5 , 85.25 , 1500.5):asc", Asc(GeoWithin([-35.5, 85.25], 1500.5))),
             ("truc(12, 13):desc", Desc(Field(S("truc(12, 13)")))),
         ];
 
@@ -266,7 +291,13 @@ mod tests {
                 "_geoPoint(42,12 , 59,598):desc",
                 ReservedKeyword { name: S("_geoPoint(42,12 , 59,598)") },
             ),
-            ("_geoPoint(35, 85, 75):asc", ReservedKeyword { name: S("_geoPoint(35, 85, 75)") }),
+            (
+                "_geoPoint(35, 85, 75, 12):asc",
+                ReservedKeyword { name: S("_geoPoint(35, 85, 75, 12)") },
+            ),
+            ("_geoPoint(35, 85, -75):asc", InvalidMaxDistance),
+            ("_geoPoint(35, 85, inf):asc", InvalidMaxDistance),
+            ("_geoPoint(35, 200, 75):asc", InvalidLongitude),
             ("_geoPoint(18):asc", ReservedKeyword { name: S("_geoPoint(18)") }),
             ("_geoPoint(200, 200):asc", InvalidLatitude),
             ("_geoPoint(90.000001, 0):asc", InvalidLatitude),
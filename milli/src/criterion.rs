@@ -22,6 +22,11 @@ pub enum CriterionError {
 `{name}` can only be used for filtering at search time"
     )]
     ReservedNameForFilter { name: String },
+    #[error(
+        "`{name}` ranking rule has invalid parameters. \
+Valid ranking rules with parameters are `words(min_matched=<number>)` and `typo(max=<number>)`."
+    )]
+    InvalidParameters { name: String },
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -31,6 +36,12 @@ pub enum Criterion {
     Words,
     /// Sorted by increasing number of typos.
     Typo,
+    /// Sorted by decreasing number of matched query terms, like `Words`, but the documents
+    /// matching less than the given number of query terms are not returned.
+    WordsMinMatched(usize),
+    /// Sorted by increasing number of typos, like `Typo`, but the documents
+    /// matching with more than the given number of typos are not returned.
+    TypoMax(u8),
     /// Sorted by increasing distance between matched query terms.
     Proximity,
     /// Documents with quey words contained in more important
@@ -68,17 +79,49 @@ impl FromStr for Criterion {
             "attribute" => Ok(Criterion::Attribute),
             "sort" => Ok(Criterion::Sort),
             "exactness" => Ok(Criterion::Exactness),
-            text => match AscDesc::from_str(text)? {
-                AscDesc::Asc(Member::Field(field)) => Ok(Criterion::Asc(field)),
-                AscDesc::Desc(Member::Field(field)) => Ok(Criterion::Desc(field)),
-                AscDesc::Asc(Member::Geo(_)) | AscDesc::Desc(Member::Geo(_)) => {
-                    Err(CriterionError::ReservedNameForSort { name: "_geoPoint".to_string() })?
+            text => match split_parameters(text) {
+                Some(("words", parameters)) => {
+                    let min_matched = parse_parameter(text, parameters, "min_matched")?;
+                    Ok(Criterion::WordsMinMatched(min_matched))
                 }
+                Some(("typo", parameters)) => {
+                    let max = parse_parameter(text, parameters, "max")?;
+                    Ok(Criterion::TypoMax(max))
+                }
+                _ => match AscDesc::from_str(text)? {
+                    AscDesc::Asc(Member::Field(field)) => Ok(Criterion::Asc(field)),
+                    AscDesc::Desc(Member::Field(field)) => Ok(Criterion::Desc(field)),
+                    AscDesc::Asc(Member::Geo(_) | Member::GeoWithin(..))
+                    | AscDesc::Desc(Member::Geo(_) | Member::GeoWithin(..)) => {
+                        Err(CriterionError::ReservedNameForSort { name: "_geoPoint".to_string() })?
+                    }
+                },
             },
         }
     }
 }
 
+/// Splits a ranking rule with parameters, e.g. `words(min_matched=2)`,
+/// into its name and its parameters.
+fn split_parameters(text: &str) -> Option<(&str, &str)> {
+    let (name, parameters) = text.split_once('(')?;
+    let parameters = parameters.strip_suffix(')')?;
+    Some((name.trim(), parameters))
+}
+
+/// Parses the single `key=value` parameter of a ranking rule.
+fn parse_parameter<T: FromStr>(
+    text: &str,
+    parameters: &str,
+    key: &str,
+) -> Result<T, CriterionError> {
+    let invalid = || CriterionError::InvalidParameters { name: text.to_string() };
+    match parameters.split_once('=') {
+        Some((name, value)) if name.trim() == key => value.trim().parse().map_err(|_| invalid()),
+        _ => Err(invalid()),
+    }
+}
+
 pub fn default_criteria() -> Vec<Criterion> {
     vec![
         Criterion::Words,
@@ -97,6 +140,8 @@ impl fmt::Display for Criterion {
         match self {
             Words => f.write_str("words"),
             Typo => f.write_str("typo"),
+            WordsMinMatched(min_matched) => write!(f, "words(min_matched={})", min_matched),
+            TypoMax(max) => write!(f, "typo(max={})", max),
             Proximity => f.write_str("proximity"),
             Attribute => f.write_str("attribute"),
             Sort => f.write_str("sort"),
@@ -123,6 +168,9 @@ mod tests {
             ("attribute", Criterion::Attribute),
             ("sort", Criterion::Sort),
             ("exactness", Criterion::Exactness),
+            ("words(min_matched=2)", Criterion::WordsMinMatched(2)),
+            ("words( min_matched = 0 )", Criterion::WordsMinMatched(0)),
+            ("typo(max=1)", Criterion::TypoMax(1)),
             ("price:asc", Criterion::Asc(S("price"))),
             ("price:desc", Criterion::Desc(S("price"))),
             ("price:asc:desc", Criterion::Desc(S("price:asc"))),
@@ -153,6 +201,11 @@ mod tests {
             ("price:aasc", InvalidName { name: S("price:aasc") }),
             ("price:asc and desc", InvalidName { name: S("price:asc and desc") }),
             ("price:asc:truc", InvalidName { name: S("price:asc:truc") }),
+            ("words()", InvalidParameters { name: S("words()") }),
+            ("words(max=2)", InvalidParameters { name: S("words(max=2)") }),
+            ("words(min_matched=two)", InvalidParameters { name: S("words(min_matched=two)") }),
+            ("typo(max=-1)", InvalidParameters { name: S("typo(max=-1)") }),
+            ("typo(max=1", InvalidName { name: S("typo(max=1") }),
             ("_geo:asc", ReservedName { name: S("_geo") }),
             ("_geoDistance:asc", ReservedName { name: S("_geoDistance") }),
             ("_geoPoint:asc", ReservedNameForSort { name: S("_geoPoint") }),
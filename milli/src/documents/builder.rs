@@ -1,8 +1,9 @@
 use std::collections::BTreeMap;
 use std::io;
-use std::io::{Cursor, Write};
+use std::io::{BufRead, Cursor, Write};
 
 use byteorder::{BigEndian, WriteBytesExt};
+use serde::de::DeserializeSeed;
 use serde::Deserializer;
 use serde_json::Value;
 
@@ -93,41 +94,86 @@ impl<W: io::Write + io::Seek> DocumentBatchBuilder<W> {
         de.deserialize_any(&mut visitor).map_err(Error::JsonError)?
     }
 
-    /// Creates a builder from a reader of CSV documents.
+    /// Extends the builder with newline-delimited JSON documents from a reader,
+    /// one JSON object by line.
     ///
-    /// Since all fields in a csv documents are guaranteed to be ordered, we are able to perform
-    /// optimisations, and extending from another CSV is not allowed.
+    /// The documents are read one line at a time, the memory used only depends on the size
+    /// of the largest document. The empty lines are ignored and the errors report the line
+    /// of the invalid document.
+    pub fn extend_from_ndjson<R: io::Read>(&mut self, reader: R) -> Result<(), Error> {
+        let mut reader = io::BufReader::new(reader);
+        let mut buffer = String::new();
+        let mut line = 0;
+
+        loop {
+            buffer.clear();
+            if reader.read_line(&mut buffer)? == 0 {
+                return Ok(());
+            }
+            line += 1;
+            if buffer.trim().is_empty() {
+                continue;
+            }
+
+            let mut visitor = DocumentVisitor {
+                inner: &mut self.inner,
+                index: &mut self.index,
+                obkv_buffer: &mut self.obkv_buffer,
+                value_buffer: &mut self.value_buffer,
+                values: &mut self.values,
+                count: &mut self.count,
+            };
+
+            // The visitor is used as a seed to only accept an object, not an array of them,
+            // and the end of the line must not contain anything else.
+            let mut de = serde_json::Deserializer::from_str(&buffer);
+            let result = (&mut visitor).deserialize(&mut de).and_then(|result| {
+                de.end()?;
+                Ok(result)
+            });
+            match result {
+                Ok(result) => result?,
+                Err(error) => return Err(Error::NdJsonError { error, line }),
+            }
+        }
+    }
+
+    /// Creates a builder from a reader of CSV documents, see [`Self::extend_from_csv`].
     pub fn from_csv<R: io::Read>(reader: R, writer: W) -> Result<Self, Error> {
         let mut this = Self::new(writer)?;
-        // Ensure that this is the first and only addition made with this builder
-        debug_assert!(this.index.is_empty());
+        this.extend_from_csv(reader)?;
+        Ok(this)
+    }
 
+    /// Extends the builder with CSV documents from a reader.
+    ///
+    /// The first line is the header, the type of a column can follow its name after a colon,
+    /// e.g. `price:number`, the columns without a type are strings. The empty values are `null`.
+    /// The errors report the line of the invalid value in the CSV.
+    pub fn extend_from_csv<R: io::Read>(&mut self, reader: R) -> Result<(), Error> {
         let mut records = csv::Reader::from_reader(reader);
 
-        let headers = records
+        // The fields ids are not in the order of the columns when the builder
+        // already contains documents with some of these fields.
+        let headers: Vec<_> = records
             .headers()?
             .into_iter()
             .map(parse_csv_header)
-            .map(|(k, t)| (this.index.insert(&k), t))
-            .collect::<BTreeMap<_, _>>();
-
-        for (i, record) in records.into_records().enumerate() {
-            let record = record?;
-            this.obkv_buffer.clear();
-            let mut writer = obkv::KvWriter::new(&mut this.obkv_buffer);
-            for (value, (fid, ty)) in record.into_iter().zip(headers.iter()) {
+            .map(|(k, t)| (self.index.insert(&k), t))
+            .collect();
+
+        let mut record = csv::StringRecord::new();
+        while records.read_record(&mut record)? {
+            let line = record.position().map_or(0, |position| position.line() as usize);
+            self.values.clear();
+            for (value, (fid, ty)) in record.iter().zip(headers.iter()) {
                 let value = match ty {
                     AllowedType::Number => {
                         if value.trim().is_empty() {
                             Value::Null
                         } else {
                             value.trim().parse::<f64>().map(Value::from).map_err(|error| {
-                                Error::ParseFloat {
-                                    error,
-                                    // +1 for the header offset.
-                                    line: i + 1,
-                                    value: value.to_string(),
-                                }
+                                Error::ParseFloat { error, line, value: value.to_string() }
                             })?
                         }
                     }
@@ -139,19 +185,25 @@ impl<W: io::Write + io::Seek> DocumentBatchBuilder<W> {
                         }
                     }
                 };
+                self.values.insert(*fid, value);
+            }
 
-                this.value_buffer.clear();
-                serde_json::to_writer(Cursor::new(&mut this.value_buffer), &value)?;
-                writer.insert(*fid, &this.value_buffer)?;
+            // The values are written in the order of their fields ids.
+            self.obkv_buffer.clear();
+            let mut writer = obkv::KvWriter::new(&mut self.obkv_buffer);
+            for (fid, value) in &self.values {
+                self.value_buffer.clear();
+                serde_json::to_writer(Cursor::new(&mut self.value_buffer), value)?;
+                writer.insert(*fid, &self.value_buffer)?;
             }
 
-            this.inner.write_u32::<BigEndian>(this.obkv_buffer.len() as u32)?;
-            this.inner.write_all(&this.obkv_buffer)?;
+            self.inner.write_u32::<BigEndian>(self.obkv_buffer.len() as u32)?;
+            self.inner.write_all(&self.obkv_buffer)?;
 
-            this.count += 1;
+            self.count += 1;
         }
 
-        Ok(this)
+        Ok(())
     }
 }
 
@@ -529,6 +581,89 @@ mod test {
         );
     }
 
+    #[test]
+    fn extend_from_csv_and_json() {
+        let mut cursor = Cursor::new(Vec::new());
+        let mut builder = DocumentBatchBuilder::new(&mut cursor).unwrap();
+
+        let json = json!({ "city": "Paris", "pop": 2161000 });
+        builder.extend_from_json(Cursor::new(serde_json::to_vec(&json).unwrap())).unwrap();
+
+        // the columns are not in the order of the fields ids given by the json document.
+        let csv = "pop:number,country,city\n4628910,\"United States\",\"Boston\"\n";
+        builder.extend_from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(builder.len(), 2);
+
+        builder.finish().unwrap();
+        cursor.set_position(0);
+
+        let mut reader = DocumentBatchReader::from_reader(cursor).unwrap();
+        let (index, doc) = reader.next_document_with_index().unwrap().unwrap();
+        assert_eq!(obkv_to_value(&doc, index), json);
+        let (index, doc) = reader.next_document_with_index().unwrap().unwrap();
+        assert_eq!(
+            obkv_to_value(&doc, index),
+            json!({ "city": "Boston", "country": "United States", "pop": 4628910.0 })
+        );
+        assert!(reader.next_document_with_index().unwrap().is_none());
+    }
+
+    #[test]
+    fn bad_number_line() {
+        let documents = "city,pop:number\nParis,2161000\n\"Boston\nMA\",4628910\nLyon,many\n";
+
+        let mut buf = Vec::new();
+        match DocumentBatchBuilder::from_csv(documents.as_bytes(), Cursor::new(&mut buf)) {
+            Err(Error::ParseFloat { line, value, .. }) => {
+                // the header and the quoted line break are counted.
+                assert_eq!(line, 5);
+                assert_eq!(value, "many");
+            }
+            _ => panic!("the number must not be parsed"),
+        }
+    }
+
+    #[test]
+    fn extend_from_ndjson() {
+        let ndjson =
+            "{ \"id\": 1, \"name\": \"foo\" }\n\n  {\"id\":2,\"tags\":[\"a\"]}  \r\n{ \"id\": 3 }";
+
+        let mut cursor = Cursor::new(Vec::new());
+        let mut builder = DocumentBatchBuilder::new(&mut cursor).unwrap();
+        builder.extend_from_ndjson(ndjson.as_bytes()).unwrap();
+        assert_eq!(builder.len(), 3);
+        builder.finish().unwrap();
+        cursor.set_position(0);
+
+        let mut reader = DocumentBatchReader::from_reader(cursor).unwrap();
+        let (index, doc) = reader.next_document_with_index().unwrap().unwrap();
+        assert_eq!(obkv_to_value(&doc, index), json!({ "id": 1, "name": "foo" }));
+        let (index, doc) = reader.next_document_with_index().unwrap().unwrap();
+        assert_eq!(obkv_to_value(&doc, index), json!({ "id": 2, "tags": ["a"] }));
+        let (index, doc) = reader.next_document_with_index().unwrap().unwrap();
+        assert_eq!(obkv_to_value(&doc, index), json!({ "id": 3 }));
+        assert!(reader.next_document_with_index().unwrap().is_none());
+    }
+
+    #[test]
+    fn bad_ndjson_line() {
+        let invalid = [
+            "{ \"id\": 1 }\n\n{ \"id\": 2 ",
+            "{ \"id\": 1 }\n\n[{ \"id\": 2 }]",
+            "{ \"id\": 1 }\n\n{ \"id\": 2 } { \"id\": 3 }",
+        ];
+
+        for ndjson in invalid.iter() {
+            let mut buf = Vec::new();
+            let mut builder = DocumentBatchBuilder::new(Cursor::new(&mut buf)).unwrap();
+            match builder.extend_from_ndjson(ndjson.as_bytes()) {
+                // the empty line is counted.
+                Err(Error::NdJsonError { line, .. }) => assert_eq!(line, 3),
+                _ => panic!("the line {:?} must not be parsed", ndjson),
+            }
+        }
+    }
+
     #[test]
     fn bad_type_in_header() {
         let documents = r#"city,country:number,pop
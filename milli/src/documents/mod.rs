@@ -104,6 +104,7 @@ pub enum Error {
     InvalidDocumentFormat,
     Custom(String),
     JsonError(serde_json::Error),
+    NdJsonError { error: serde_json::Error, line: usize },
     CsvError(csv::Error),
     Serialize(bincode::Error),
     Io(io::Error),
@@ -143,6 +144,9 @@ impl fmt::Display for Error {
             Error::Custom(s) => write!(f, "Unexpected serialization error: {}", s),
             Error::InvalidDocumentFormat => f.write_str("Invalid document addition format."),
             Error::JsonError(err) => write!(f, "Couldn't serialize document value: {}", err),
+            Error::NdJsonError { error, line } => {
+                write!(f, "Error parsing the document at line {}: {}", line, error)
+            }
             Error::Io(e) => write!(f, "{}", e),
             Error::DocumentTooLarge => f.write_str("Provided document is too large (>2Gib)"),
             Error::Serialize(e) => write!(f, "{}", e),
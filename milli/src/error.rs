@@ -27,6 +27,8 @@ pub enum Error {
 
 #[derive(Error, Debug)]
 pub enum InternalError {
+    #[error("The indexation has been aborted.")]
+    AbortedIndexation,
     #[error("{}", HeedError::DatabaseClosing)]
     DatabaseClosing,
     #[error("Missing {} in the {db_name} database.", key.unwrap_or("key"))]
@@ -36,6 +38,8 @@ pub enum InternalError {
     #[error("Missing {key} in the field id mapping.")]
     FieldIdMappingMissingEntry { key: FieldId },
     #[error(transparent)]
+    DocumentsBatch(#[from] crate::documents::Error),
+    #[error(transparent)]
     Fst(#[from] fst::Error),
     #[error("Invalid compression type have been specified to grenad.")]
     GrenadInvalidCompressionType,
@@ -51,6 +55,8 @@ pub enum InternalError {
     SerdeJson(#[from] serde_json::Error),
     #[error(transparent)]
     Serialization(#[from] SerializationError),
+    #[error("The extraction of an update that already returned has been stopped.")]
+    StoppedExtraction,
     #[error(transparent)]
     Store(#[from] MdbError),
     #[error(transparent)]
@@ -85,16 +91,30 @@ pub enum FieldIdMapMissingEntry {
 pub enum UserError {
     #[error("A document cannot contain more than 65,535 fields.")]
     AttributeLimitReached,
+    #[error(
+        "Attribute `{field}` is not filterable or sortable, its facet values cannot be renamed."
+    )]
+    AttributeNotFaceted { field: String },
     #[error(transparent)]
     CriterionError(#[from] CriterionError),
     #[error("Maximum number of documents reached.")]
     DocumentLimitReached,
+    #[error("The internal id `{internal_id}` of the document `{document_id}` is already used by another document.")]
+    InternalDocumentIdAlreadyUsed { document_id: String, internal_id: DocumentId },
+    #[error("Document `{document_id}` contains more than {limit} fields, the limit is reached at the `{field}` field.")]
+    DocumentFieldsLimitReached { document_id: String, field: String, limit: usize },
+    #[error("Document `{document_id}` is larger than {limit} bytes, the limit is reached at the `{field}` field.")]
+    DocumentSizeLimitReached { document_id: String, field: String, limit: usize },
+    #[error("Document `{document_id}` contains more than {limit} words, the limit is reached at the `{field}` field.")]
+    DocumentWordsLimitReached { document_id: String, field: String, limit: usize },
     #[error(
         "Document identifier `{}` is invalid. \
 A document identifier can be of type integer or string, \
 only composed of alphanumeric characters (a-z A-Z 0-9), hyphens (-) and underscores (_).", .document_id.to_string()
     )]
     InvalidDocumentId { document_id: Value },
+    #[error("Invalid indexer configuration: {0}")]
+    InvalidIndexerConfig(String),
     #[error("Invalid facet distribution, the fields `{}` are not set as filterable.",
         .invalid_facets_name.iter().map(AsRef::as_ref).collect::<Vec<_>>().join(", ")
      )]
@@ -103,6 +123,8 @@ only composed of alphanumeric characters (a-z A-Z 0-9), hyphens (-) and undersco
     InvalidGeoField(#[from] GeoError),
     #[error("{0}")]
     InvalidFilter(String),
+    #[error("Could not parse the vector in the document with the id: `{document_id}`. Was expecting an array of numbers but instead got `{value}`.")]
+    InvalidVector { document_id: Value, value: Value },
     #[error("Attribute `{}` is not sortable. {}",
         .field,
         match .valid_fields.is_empty() {
@@ -113,12 +135,32 @@ only composed of alphanumeric characters (a-z A-Z 0-9), hyphens (-) and undersco
         }
     )]
     InvalidSortableAttribute { field: String, valid_fields: BTreeSet<String> },
+    #[error("Attribute `{}` cannot be used in a ranking rule. {}",
+        .field,
+        match .valid_fields.is_empty() {
+            true => "This index does not have configured sortable, filterable or ranking rule attributes.".to_string(),
+            false => format!("Available attributes are: `{}`.",
+                    valid_fields.iter().map(AsRef::as_ref).collect::<Vec<_>>().join(", ")
+                ),
+        }
+    )]
+    InvalidRankingRuleAttribute { field: String, valid_fields: BTreeSet<String> },
+    #[error("The boost of the attribute `{field}` is invalid, it must be a positive number but found `{boost}`.")]
+    InvalidFieldBoost { field: String, boost: f32 },
+    #[error("The exact attribute `{field}` is invalid, the nested fields must be separated by single dots, e.g. `author.name`.")]
+    InvalidExactAttribute { field: String },
+    #[error("The hashed attribute `{field}` is invalid, only the top-level attributes can be hashed, e.g. `contact` instead of `contact.email`.")]
+    InvalidHashedAttribute { field: String },
+    #[error("The rescoring function returned {received} scores for {expected} documents.")]
+    InvalidRescoringScores { expected: usize, received: usize },
     #[error("The sort ranking rule must be specified in the ranking rules settings to use the sort parameter at search time.")]
     SortRankingRuleMissing,
     #[error("The database file is in an invalid state.")]
     InvalidStoreFile,
     #[error("Maximum database size has been reached.")]
     MaxDatabaseSizeReached,
+    #[error("Maximum number of concurrent readers has been reached. Consider increasing the `max_readers` of the index or closing the long-lived read transactions.")]
+    MaxReadersReached,
     #[error("Document doesn't have a `{}` attribute: `{}`.", .primary_key, serde_json::to_string(.document).unwrap())]
     MissingDocumentId { primary_key: String, document: Object },
     #[error("The primary key inference process failed because the engine did not find any fields containing `id` substring in their name. If your document identifier does not contain any `id` substring, you can set the primary key of the index.")]
@@ -127,6 +169,8 @@ only composed of alphanumeric characters (a-z A-Z 0-9), hyphens (-) and undersco
     NoSpaceLeftOnDevice,
     #[error("Index already has a primary key: `{0}`.")]
     PrimaryKeyCannotBeChanged(String),
+    #[error("The primary key `{0}` cannot be deleted from the documents.")]
+    PrimaryKeyCannotBeDeleted(String),
     #[error(transparent)]
     SerdeJson(serde_json::Error),
     #[error(transparent)]
@@ -147,6 +191,8 @@ pub enum GeoError {
     BadLatitude { document_id: Value, value: Value },
     #[error("Could not parse longitude in the document with the id: `{document_id}`. Was expecting a number but instead got `{value}`.")]
     BadLongitude { document_id: Value, value: Value },
+    #[error("Could not parse the GeoJSON `_geo` field in the document with the id: `{document_id}`. Was expecting a `Point`, or an array of them, with `[longitude, latitude]` coordinates but instead got `{value}`.")]
+    BadGeoJson { document_id: Value, value: Value },
 }
 
 /// A little macro helper to autogenerate From implementation that needs two `Into`.
@@ -175,6 +221,7 @@ macro_rules! error_from_sub_error {
 
 error_from_sub_error! {
     FieldIdMapMissingEntry => InternalError,
+    crate::documents::Error => InternalError,
     fst::Error => InternalError,
     str::Utf8Error => InternalError,
     ThreadPoolBuildError => InternalError,
@@ -217,6 +264,7 @@ impl From<HeedError> for Error {
         match error {
             HeedError::Io(error) => Error::from(error),
             HeedError::Mdb(MdbError::MapFull) => UserError(MaxDatabaseSizeReached),
+            HeedError::Mdb(MdbError::ReadersFull) => UserError(MaxReadersReached),
             HeedError::Mdb(MdbError::Invalid) => UserError(InvalidStoreFile),
             HeedError::Mdb(error) => InternalError(Store(error)),
             HeedError::Encoding => InternalError(Serialization(Encoding { db_name: None })),
@@ -1,11 +1,15 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 use std::{fmt, str};
 
-use fst::map::IndexedValue;
+use fst::map::{IndexedValue, OpBuilder};
 use fst::{IntoStreamer, Streamer};
 
+use crate::error::SerializationError;
+use crate::index::db_name;
+use crate::{Error, Result};
+
 const DELETED_ID: u64 = u64::MAX;
 
 pub struct ExternalDocumentsIds<'a> {
@@ -100,6 +104,12 @@ impl<'a> ExternalDocumentsIds<'a> {
         map
     }
 
+    /// Returns an iterator over the internal ids of the documents sorted by their external ids,
+    /// in the lexicographic order of the fst.
+    pub fn into_sorted_internal_ids(self) -> SortedInternalIds<'a> {
+        SortedInternalIds { external_documents_ids: self, last_external_id: None }
+    }
+
     fn merge_soft_into_hard(&mut self) -> fst::Result<()> {
         if self.soft.len() >= self.hard.len() / 2 {
             let union_op = self.hard.op().add(&self.soft).r#union();
@@ -138,6 +148,47 @@ impl Default for ExternalDocumentsIds<'static> {
     }
 }
 
+/// An iterator over the internal ids of the documents sorted by their external ids.
+///
+/// The hard and soft maps are merged lazily, every call to `next` resumes both of them
+/// right after the last returned external id.
+pub struct SortedInternalIds<'a> {
+    external_documents_ids: ExternalDocumentsIds<'a>,
+    last_external_id: Option<Vec<u8>>,
+}
+
+impl Iterator for SortedInternalIds<'_> {
+    type Item = Result<u32>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ExternalDocumentsIds { hard, soft } = &self.external_documents_ids;
+        loop {
+            let (mut hard_range, mut soft_range) = (hard.range(), soft.range());
+            if let Some(last_external_id) = &self.last_external_id {
+                hard_range = hard_range.gt(last_external_id);
+                soft_range = soft_range.gt(last_external_id);
+            }
+
+            let mut iter = OpBuilder::new().add(hard_range).add(soft_range).r#union();
+            let (external_id, marked_docids) = iter.next()?;
+            self.last_external_id = Some(external_id.to_vec());
+
+            match indexed_last_value(marked_docids) {
+                Some(DELETED_ID) => continue,
+                Some(id) => match u32::try_from(id) {
+                    Ok(id) => return Some(Ok(id)),
+                    Err(_) => return Some(Err(decoding_error())),
+                },
+                None => return Some(Err(decoding_error())),
+            }
+        }
+    }
+}
+
+fn decoding_error() -> Error {
+    SerializationError::Decoding { db_name: Some(db_name::MAIN) }.into()
+}
+
 /// Returns the value of the `IndexedValue` with the highest _index_.
 fn indexed_last_value(indexed_values: &[IndexedValue]) -> Option<u64> {
     indexed_values.iter().copied().max_by_key(|iv| iv.index).map(|iv| iv.value)
@@ -214,4 +265,24 @@ mod tests {
         external_documents_ids.insert_ids(&new_ids).unwrap();
         assert_eq!(external_documents_ids.get("30"), Some(2));
     }
+
+    #[test]
+    fn sorted_internal_ids() {
+        let mut external_documents_ids = ExternalDocumentsIds::default();
+
+        let new_ids =
+            fst::Map::from_iter(vec![("a", 1), ("c", 3), ("d", 4), ("f", 6), ("g", 7), ("h", 8)])
+                .unwrap();
+        external_documents_ids.insert_ids(&new_ids).unwrap();
+
+        // The soft map is smaller than half of the hard one and is not merged into it.
+        let new_ids = fst::Map::from_iter(vec![("b", 2)]).unwrap();
+        external_documents_ids.insert_ids(&new_ids).unwrap();
+        let del_ids = fst::Set::from_iter(vec!["d"]).unwrap();
+        external_documents_ids.delete_ids(del_ids).unwrap();
+        assert!(!external_documents_ids.soft.is_empty());
+
+        let ids: Result<Vec<_>> = external_documents_ids.into_sorted_internal_ids().collect();
+        assert_eq!(ids.unwrap(), vec![1, 2, 3, 6, 7, 8]);
+    }
 }
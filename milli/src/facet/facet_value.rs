@@ -5,6 +5,7 @@ use serde::{Serialize, Serializer};
 pub enum FacetValue {
     String(String),
     Number(OrderedFloat<f64>),
+    Bool(bool),
 }
 
 impl From<String> for FacetValue {
@@ -37,6 +38,12 @@ impl From<i64> for FacetValue {
     }
 }
 
+impl From<bool> for FacetValue {
+    fn from(boolean: bool) -> FacetValue {
+        FacetValue::Bool(boolean)
+    }
+}
+
 /// We implement Serialize ourselves because we need to always serialize it as a string,
 /// JSON object keys must be strings not numbers.
 // TODO remove this impl and convert them into string, by hand, when required.
@@ -51,6 +58,7 @@ impl Serialize for FacetValue {
                 let string = number.to_string();
                 serializer.serialize_str(&string)
             }
+            FacetValue::Bool(boolean) => serializer.serialize_str(&boolean.to_string()),
         }
     }
 }
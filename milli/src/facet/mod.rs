@@ -1,6 +1,8 @@
 mod facet_type;
 mod facet_value;
+mod number_coercion;
 pub mod value_encoding;
 
 pub use self::facet_type::FacetType;
 pub use self::facet_value::FacetValue;
+pub use self::number_coercion::NumberCoercion;
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+/// Defines whether the string values of a faceted attribute that look like numbers are
+/// also indexed as numbers, e.g. to be filtered with a numeric range.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberCoercion {
+    /// The strings are only indexed as strings, `"01234"` stays the string `01234`.
+    Off,
+    /// Only the strings that are the exact representation of a number are also indexed
+    /// as numbers, `"1234"` and `"12.5"` are but `"01234"`, `"+12"` or `"1e3"` are not.
+    Strict,
+    /// All the strings that can be parsed as a number are also indexed as numbers,
+    /// `"01234"` is indexed as the string `01234` and the number `1234`.
+    Lenient,
+}
+
+impl NumberCoercion {
+    /// Returns the number a string value must also be indexed as, if any.
+    pub fn coerce(&self, value: &str) -> Option<f64> {
+        if *self == NumberCoercion::Off {
+            return None;
+        }
+
+        let value = value.trim();
+        let number = value.parse::<f64>().ok().filter(|number| number.is_finite())?;
+        match self {
+            NumberCoercion::Strict if number.to_string() != value => None,
+            _ => Some(number),
+        }
+    }
+}
+
+impl Default for NumberCoercion {
+    fn default() -> NumberCoercion {
+        NumberCoercion::Off
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coerce_numbers() {
+        assert_eq!(NumberCoercion::Off.coerce("1234"), None);
+
+        assert_eq!(NumberCoercion::Strict.coerce("1234"), Some(1234.0));
+        assert_eq!(NumberCoercion::Strict.coerce(" 12.5 "), Some(12.5));
+        assert_eq!(NumberCoercion::Strict.coerce("-3"), Some(-3.0));
+        assert_eq!(NumberCoercion::Strict.coerce("01234"), None);
+        assert_eq!(NumberCoercion::Strict.coerce("+12"), None);
+        assert_eq!(NumberCoercion::Strict.coerce("1e3"), None);
+        assert_eq!(NumberCoercion::Strict.coerce("12.50"), None);
+        assert_eq!(NumberCoercion::Strict.coerce("hello"), None);
+
+        assert_eq!(NumberCoercion::Lenient.coerce("01234"), Some(1234.0));
+        assert_eq!(NumberCoercion::Lenient.coerce("1e3"), Some(1000.0));
+        assert_eq!(NumberCoercion::Lenient.coerce("inf"), None);
+        assert_eq!(NumberCoercion::Lenient.coerce("hello"), None);
+    }
+}
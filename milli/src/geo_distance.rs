@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+use crate::distance_between_two_points;
+
+/// The mean radius of the Earth, in meters.
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// The formula used to compute the distances of the `_geoRadius` filters
+/// and of the maximum distance of the `_geoPoint` sorts.
+///
+/// The documents are always sorted in the exact distance order, only the distances compared
+/// to a radius or to a maximum distance depend on the formula.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GeoDistanceFormula {
+    /// The great-circle distance on a sphere, accurate to about 0.5% anywhere on the Earth.
+    Haversine,
+    /// The euclidean distance on an equirectangular projection centered on the two points,
+    /// much faster to compute. The error stays under 0.1% for distances of a few tens of
+    /// kilometers away from the poles, e.g. in a city, but grows with the distance and the
+    /// latitude: it must not be used for continental distances.
+    Planar,
+}
+
+impl Default for GeoDistanceFormula {
+    fn default() -> GeoDistanceFormula {
+        GeoDistanceFormula::Haversine
+    }
+}
+
+impl GeoDistanceFormula {
+    /// Returns the distance in meters between two points, each one being
+    /// a latitude and a longitude in degrees.
+    pub fn distance(&self, a: &[f64; 2], b: &[f64; 2]) -> f64 {
+        match self {
+            GeoDistanceFormula::Haversine => distance_between_two_points(a, b),
+            GeoDistanceFormula::Planar => planar_distance(a, b),
+        }
+    }
+}
+
+fn planar_distance(a: &[f64; 2], b: &[f64; 2]) -> f64 {
+    let (lat_a, lat_b) = (a[0].to_radians(), b[0].to_radians());
+    let mut delta_lng = (b[1] - a[1]).abs();
+    // the shortest path can cross the antimeridian
+    if delta_lng > 180.0 {
+        delta_lng = 360.0 - delta_lng;
+    }
+
+    let x = delta_lng.to_radians() * ((lat_a + lat_b) / 2.0).cos();
+    let y = lat_b - lat_a;
+    EARTH_RADIUS * x.hypot(y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn planar_distance_is_close_to_haversine() {
+        // the Eiffel tower, the Louvre pyramid and the Sacré-Cœur in Paris
+        let eiffel_tower = [48.858_37, 2.294_481];
+        let louvre = [48.861_02, 2.335_851];
+        let sacre_coeur = [48.886_705, 2.343_104];
+
+        for (a, b) in [(eiffel_tower, louvre), (eiffel_tower, sacre_coeur), (louvre, sacre_coeur)] {
+            let haversine = GeoDistanceFormula::Haversine.distance(&a, &b);
+            let planar = GeoDistanceFormula::Planar.distance(&a, &b);
+            assert!((haversine - planar).abs() / haversine < 0.001, "{} {}", haversine, planar);
+        }
+
+        let a = [0.0, 179.9];
+        let b = [0.0, -179.9];
+        let planar = GeoDistanceFormula::Planar.distance(&a, &b);
+        assert!((planar - 22_239.0).abs() < 10.0, "{}", planar);
+        assert_eq!(GeoDistanceFormula::Planar.distance(&a, &a), 0.0);
+    }
+}
@@ -0,0 +1,49 @@
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Hashes all the scalar values, in arrays and objects too, the values of a hashed field
+/// are only stored and indexed hashed. The `null` values are kept as is.
+pub(crate) fn hash_json_value(salt: &str, value: &Value) -> Value {
+    match value {
+        Value::Null => Value::Null,
+        Value::Bool(boolean) => Value::String(hash_facet_value(salt, &boolean.to_string())),
+        Value::Number(number) => Value::String(hash_facet_value(salt, &number.to_string())),
+        Value::String(string) => Value::String(hash_facet_value(salt, string)),
+        Value::Array(values) => values.iter().map(|value| hash_json_value(salt, value)).collect(),
+        Value::Object(object) => Value::Object(
+            object.iter().map(|(key, value)| (key.clone(), hash_json_value(salt, value))).collect(),
+        ),
+    }
+}
+
+/// Returns the salted SHA-256 of the value in hexadecimal, the value is normalized like
+/// the facet strings so that a filter matches it regardless of its case.
+pub(crate) fn hash_facet_value(salt: &str, value: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(value.trim().to_lowercase().as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn hash_values() {
+        let hash = hash_facet_value("salt", "Kevin ");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, hash_facet_value("salt", "kevin"));
+        assert_ne!(hash, hash_facet_value("pepper", "kevin"));
+
+        let value = json!({ "name": "kevin", "ids": [12, null], "admin": true });
+        let expected = json!({
+            "name": hash_facet_value("salt", "kevin"),
+            "ids": [hash_facet_value("salt", "12"), null],
+            "admin": hash_facet_value("salt", "true"),
+        });
+        assert_eq!(hash_json_value("salt", &value), expected);
+    }
+}
@@ -0,0 +1,28 @@
+use std::borrow::Cow;
+
+use crate::{try_split_array_at, DocumentId, FieldId};
+
+pub struct DocIdFieldIdCodec;
+
+impl<'a> heed::BytesDecode<'a> for DocIdFieldIdCodec {
+    type DItem = (DocumentId, FieldId);
+
+    fn bytes_decode(bytes: &'a [u8]) -> Option<Self::DItem> {
+        let (docid_bytes, bytes) = try_split_array_at(bytes)?;
+        let docid = u32::from_be_bytes(docid_bytes);
+        let (field_id_bytes, _nothing) = try_split_array_at(bytes)?;
+        let field_id = u16::from_be_bytes(field_id_bytes);
+        Some((docid, field_id))
+    }
+}
+
+impl<'a> heed::BytesEncode<'a> for DocIdFieldIdCodec {
+    type EItem = (DocumentId, FieldId);
+
+    fn bytes_encode((docid, field_id): &Self::EItem) -> Option<Cow<[u8]>> {
+        let mut bytes = Vec::with_capacity(4 + 2);
+        bytes.extend_from_slice(&docid.to_be_bytes());
+        bytes.extend_from_slice(&field_id.to_be_bytes());
+        Some(Cow::Owned(bytes))
+    }
+}
@@ -0,0 +1,30 @@
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::mem::size_of;
+
+pub struct F32VectorCodec;
+
+impl<'a> heed::BytesDecode<'a> for F32VectorCodec {
+    type DItem = Vec<f32>;
+
+    fn bytes_decode(bytes: &'a [u8]) -> Option<Self::DItem> {
+        if bytes.len() % size_of::<f32>() != 0 {
+            return None;
+        }
+
+        let vector = bytes
+            .chunks_exact(size_of::<f32>())
+            .map(|bytes| f32::from_be_bytes(bytes.try_into().unwrap()))
+            .collect();
+        Some(vector)
+    }
+}
+
+impl<'a> heed::BytesEncode<'a> for F32VectorCodec {
+    type EItem = [f32];
+
+    fn bytes_encode(vector: &Self::EItem) -> Option<Cow<[u8]>> {
+        let bytes = vector.iter().flat_map(|value| value.to_be_bytes()).collect();
+        Some(Cow::Owned(bytes))
+    }
+}
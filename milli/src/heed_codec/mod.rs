@@ -1,4 +1,6 @@
 mod beu32_str_codec;
+mod docid_field_id_codec;
+mod f32_vector_codec;
 pub mod facet;
 mod field_id_word_count_codec;
 mod obkv_codec;
@@ -8,6 +10,8 @@ mod str_beu32_codec;
 mod str_str_u8_codec;
 
 pub use self::beu32_str_codec::BEU32StrCodec;
+pub use self::docid_field_id_codec::DocIdFieldIdCodec;
+pub use self::f32_vector_codec::F32VectorCodec;
 pub use self::field_id_word_count_codec::FieldIdWordCountCodec;
 pub use self::obkv_codec::ObkvCodec;
 pub use self::roaring_bitmap::{BoRoaringBitmapCodec, CboRoaringBitmapCodec, RoaringBitmapCodec};
@@ -1,26 +1,33 @@
 use std::borrow::Cow;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::mem::size_of;
+use std::num::{NonZeroU32, NonZeroU8};
 use std::path::Path;
+use std::sync::Arc;
 
 use heed::flags::Flags;
 use heed::types::*;
 use heed::{Database, PolyDatabase, RoTxn, RwTxn};
 use roaring::RoaringBitmap;
 use rstar::RTree;
+use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
-use crate::error::{InternalError, UserError};
+use crate::error::{FieldIdMapMissingEntry, InternalError, UserError};
+use crate::facet::{FacetValue, NumberCoercion};
 use crate::fields_ids_map::FieldsIdsMap;
 use crate::heed_codec::facet::{
-    FacetLevelValueF64Codec, FacetStringLevelZeroCodec, FacetStringLevelZeroValueCodec,
-    FieldDocIdFacetF64Codec, FieldDocIdFacetStringCodec,
+    FacetLevelValueF64Codec, FacetLevelValueU32Codec, FacetStringLevelZeroCodec,
+    FacetStringLevelZeroValueCodec, FieldDocIdFacetF64Codec, FieldDocIdFacetStringCodec,
 };
+use crate::search::{word_derivations_with_transposition, DfaCache, WordDerivationsCache};
 use crate::{
-    default_criteria, BEU32StrCodec, BoRoaringBitmapCodec, CboRoaringBitmapCodec, Criterion,
-    DocumentId, ExternalDocumentsIds, FacetDistribution, FieldDistribution, FieldId,
-    FieldIdWordCountCodec, GeoPoint, ObkvCodec, Result, RoaringBitmapCodec, RoaringBitmapLenCodec,
-    Search, StrBEU32Codec, StrStrU8Codec, BEU32,
+    default_criteria, is_faceted, BEU32StrCodec, BoRoaringBitmapCodec, CboRoaringBitmapCodec,
+    Criterion, DocIdFieldIdCodec, DocumentId, ExternalDocumentsIds, F32VectorCodec,
+    FacetDistribution, FieldDistribution, FieldId, FieldIdWordCountCodec, Filter,
+    GeoDistanceFormula, GeoPoint, NormalizerOptions, ObkvCodec, QueryRewrite, Result,
+    RoaringBitmapCodec, RoaringBitmapLenCodec, Search, StrBEU32Codec, StrStrU8Codec,
+    WordBoundaries, BEU32,
 };
 
 pub const DEFAULT_MIN_WORD_LEN_ONE_TYPO: u8 = 5;
@@ -30,8 +37,11 @@ pub mod main_key {
     pub const CRITERIA_KEY: &str = "criteria";
     pub const DISPLAYED_FIELDS_KEY: &str = "displayed-fields";
     pub const DISTINCT_FIELD_KEY: &str = "distinct-field-key";
+    pub const DISTINCT_FIELDS_KEY: &str = "distinct-fields";
+    pub const BOOLEAN_FACETED_DOCUMENTS_IDS_PREFIX: &str = "boolean-faceted-documents-ids";
     pub const DOCUMENTS_IDS_KEY: &str = "documents-ids";
     pub const HIDDEN_FACETED_FIELDS_KEY: &str = "hidden-faceted-fields";
+    pub const HIGH_CARDINALITY_FACETED_FIELDS_KEY: &str = "high-cardinality-faceted-fields";
     pub const FILTERABLE_FIELDS_KEY: &str = "filterable-fields";
     pub const SORTABLE_FIELDS_KEY: &str = "sortable-fields";
     pub const FIELD_DISTRIBUTION_KEY: &str = "fields-distribution";
@@ -43,18 +53,40 @@ pub mod main_key {
     pub const PRIMARY_KEY_KEY: &str = "primary-key";
     pub const SEARCHABLE_FIELDS_KEY: &str = "searchable-fields";
     pub const SOFT_EXTERNAL_DOCUMENTS_IDS_KEY: &str = "soft-external-documents-ids";
+    pub const SOFT_DELETED_DOCUMENTS_IDS_KEY: &str = "soft-deleted-documents-ids";
     pub const STOP_WORDS_KEY: &str = "stop-words";
     pub const STRING_FACETED_DOCUMENTS_IDS_PREFIX: &str = "string-faceted-documents-ids";
     pub const SYNONYMS_KEY: &str = "synonyms";
     pub const WORDS_FST_KEY: &str = "words-fst";
     pub const WORDS_PREFIXES_FST_KEY: &str = "words-prefixes-fst";
+    pub const WORDS_PREFIXES_FST_PARAMETERS_KEY: &str = "words-prefixes-fst-parameters";
+    pub const DELETED_WORDS_FST_KEY: &str = "deleted-words-fst";
     pub const CREATED_AT_KEY: &str = "created-at";
     pub const UPDATED_AT_KEY: &str = "updated-at";
+    pub const GENERATION_KEY: &str = "generation";
     pub const AUTHORIZE_TYPOS: &str = "authorize-typos";
     pub const ONE_TYPO_WORD_LEN: &str = "one-typo-word-len";
     pub const TWO_TYPOS_WORD_LEN: &str = "two-typos-word-len";
     pub const EXACT_WORDS: &str = "exact-words";
     pub const EXACT_ATTRIBUTES: &str = "exact-attributes";
+    pub const EXACTNESS_EXCLUDED_ATTRIBUTES: &str = "exactness-excluded-attributes";
+    pub const HASHED_FIELDS: &str = "hashed-fields";
+    pub const HASHED_FIELDS_SALT: &str = "hashed-fields-salt";
+    pub const PREVIOUSLY_HASHED_FIELDS: &str = "previously-hashed-fields";
+    pub const UNHASHED_DOCUMENTS_IDS_PREFIX: &str = "unhashed-documents-ids-";
+    pub const TRANSPOSITION_COUNTS_AS_ONE_TYPO: &str = "transposition-counts-as-one-typo";
+    pub const DISABLE_TYPOS_ON_NUMBERS: &str = "disable-typos-on-numbers";
+    pub const PHRASES_WITHIN_ATTRIBUTE: &str = "phrases-within-attribute";
+    pub const ATTRIBUTES_POSITIONS_LIMITS: &str = "attributes-positions-limits";
+    pub const NUMBER_COERCIONS: &str = "number-coercions";
+    pub const POSITIONAL_ARRAY_FIELDS: &str = "positional-array-fields";
+    pub const CONTENT_HASH_FIELDS: &str = "content-hash-fields";
+    pub const WORD_BOUNDARIES: &str = "word-boundaries";
+    pub const NORMALIZER_OPTIONS: &str = "normalizer-options";
+    pub const GEO_DISTANCE_FORMULA: &str = "geo-distance-formula";
+    pub const QUERY_REWRITES: &str = "query-rewrites";
+    pub const WORD_POSITION_PRECISION: &str = "word-position-precision";
+    pub const PENDING_PREFIX_DATABASES_UPDATE: &str = "pending-prefix-databases-update";
 }
 
 pub mod db_name {
@@ -69,11 +101,13 @@ pub mod db_name {
     pub const WORD_POSITION_DOCIDS: &str = "word-position-docids";
     pub const WORD_PREFIX_POSITION_DOCIDS: &str = "word-prefix-position-docids";
     pub const FIELD_ID_WORD_COUNT_DOCIDS: &str = "field-id-word-count-docids";
+    pub const DOCID_FIELD_ID_WORD_COUNT: &str = "docid-field-id-word-count";
     pub const FACET_ID_F64_DOCIDS: &str = "facet-id-f64-docids";
     pub const FACET_ID_STRING_DOCIDS: &str = "facet-id-string-docids";
     pub const FIELD_ID_DOCID_FACET_F64S: &str = "field-id-docid-facet-f64s";
     pub const FIELD_ID_DOCID_FACET_STRINGS: &str = "field-id-docid-facet-strings";
     pub const DOCUMENTS: &str = "documents";
+    pub const VECTORS: &str = "vectors";
 }
 
 #[derive(Clone)]
@@ -108,6 +142,8 @@ pub struct Index {
     pub word_position_docids: Database<StrBEU32Codec, CboRoaringBitmapCodec>,
     /// Maps the field id and the word count with the docids that corresponds to it.
     pub field_id_word_count_docids: Database<FieldIdWordCountCodec, CboRoaringBitmapCodec>,
+    /// Maps the document id and the field id with the number of words in this field.
+    pub docid_field_id_word_count: Database<DocIdFieldIdCodec, OwnedType<BEU32>>,
     /// Maps the position of a word prefix with all the docids where this prefix appears.
     pub word_prefix_position_docids: Database<StrBEU32Codec, CboRoaringBitmapCodec>,
 
@@ -123,13 +159,26 @@ pub struct Index {
 
     /// Maps the document id to the document as an obkv store.
     pub documents: Database<OwnedType<BEU32>, ObkvCodec>,
+
+    /// Maps the document id and the field id of a `_vectors` field with the stored vector.
+    pub vectors: Database<DocIdFieldIdCodec, F32VectorCodec>,
+
+    /// The levenshtein automatons built by the last searches, shared by the clones of the index.
+    pub(crate) dfa_cache: Arc<DfaCache>,
 }
 
 impl Index {
+    /// Opens or creates the index at the given path.
+    ///
+    /// Every read transaction, and therefore every search running concurrently, uses a reader
+    /// slot of the environment. There are `options.max_readers` slots, 126 by default, a read
+    /// transaction can't be opened when all of them are used and a
+    /// [`UserError::MaxReadersReached`] error is returned.
+    /// See [`Index::new_with_max_readers`] to change the number of slots.
     pub fn new<P: AsRef<Path>>(mut options: heed::EnvOpenOptions, path: P) -> Result<Index> {
         use db_name::*;
 
-        options.max_dbs(16);
+        options.max_dbs(18);
         unsafe { options.flag(Flags::MdbAlwaysFreePages) };
 
         let env = options.open(path)?;
@@ -144,6 +193,7 @@ impl Index {
             env.create_database(Some(WORD_PREFIX_PAIR_PROXIMITY_DOCIDS))?;
         let word_position_docids = env.create_database(Some(WORD_POSITION_DOCIDS))?;
         let field_id_word_count_docids = env.create_database(Some(FIELD_ID_WORD_COUNT_DOCIDS))?;
+        let docid_field_id_word_count = env.create_database(Some(DOCID_FIELD_ID_WORD_COUNT))?;
         let word_prefix_position_docids = env.create_database(Some(WORD_PREFIX_POSITION_DOCIDS))?;
         let facet_id_f64_docids = env.create_database(Some(FACET_ID_F64_DOCIDS))?;
         let facet_id_string_docids = env.create_database(Some(FACET_ID_STRING_DOCIDS))?;
@@ -151,6 +201,7 @@ impl Index {
         let field_id_docid_facet_strings =
             env.create_database(Some(FIELD_ID_DOCID_FACET_STRINGS))?;
         let documents = env.create_database(Some(DOCUMENTS))?;
+        let vectors = env.create_database(Some(VECTORS))?;
 
         Index::initialize_creation_dates(&env, main)?;
 
@@ -167,14 +218,28 @@ impl Index {
             word_position_docids,
             word_prefix_position_docids,
             field_id_word_count_docids,
+            docid_field_id_word_count,
             facet_id_f64_docids,
             facet_id_string_docids,
             field_id_docid_facet_f64s,
             field_id_docid_facet_strings,
             documents,
+            vectors,
+            dfa_cache: Arc::new(DfaCache::default()),
         })
     }
 
+    /// Opens or creates the index at the given path with `max_readers` reader slots,
+    /// at most `max_readers` read transactions can be opened concurrently.
+    pub fn new_with_max_readers<P: AsRef<Path>>(
+        mut options: heed::EnvOpenOptions,
+        path: P,
+        max_readers: u32,
+    ) -> Result<Index> {
+        options.max_readers(max_readers);
+        Index::new(options, path)
+    }
+
     fn initialize_creation_dates(env: &heed::Env, main: PolyDatabase) -> heed::Result<()> {
         let mut txn = env.write_txn()?;
         // The db was just created, we update its metadata with the relevant information.
@@ -202,6 +267,9 @@ impl Index {
     }
 
     /// Create a read transaction to be able to read the index.
+    ///
+    /// Fails with a `ReadersFull` error when all the reader slots are used, it is converted
+    /// into a [`UserError::MaxReadersReached`] error.
     pub fn read_txn(&self) -> heed::Result<RoTxn> {
         self.env.read_txn()
     }
@@ -239,6 +307,41 @@ impl Index {
             .unwrap_or_default())
     }
 
+    /// Writes the documents ids that are deleted but not yet purged from the databases.
+    pub(crate) fn put_soft_deleted_documents_ids(
+        &self,
+        wtxn: &mut RwTxn,
+        docids: &RoaringBitmap,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, RoaringBitmapCodec>(
+            wtxn,
+            main_key::SOFT_DELETED_DOCUMENTS_IDS_KEY,
+            docids,
+        )
+    }
+
+    /// Returns the documents ids that are deleted but still in the databases, they are not part
+    /// of the [`documents_ids`](Self::documents_ids) and are ignored by the searches.
+    pub fn soft_deleted_documents_ids(&self, rtxn: &RoTxn) -> heed::Result<RoaringBitmap> {
+        Ok(self
+            .main
+            .get::<_, Str, RoaringBitmapCodec>(rtxn, main_key::SOFT_DELETED_DOCUMENTS_IDS_KEY)?
+            .unwrap_or_default())
+    }
+
+    /// Removes the soft deleted documents from documents ids read from the databases,
+    /// where they stay until they are purged.
+    pub(crate) fn remove_soft_deleted_documents(
+        &self,
+        rtxn: &RoTxn,
+        docids: &mut RoaringBitmap,
+    ) -> heed::Result<()> {
+        if !docids.is_empty() {
+            *docids -= self.soft_deleted_documents_ids(rtxn)?;
+        }
+        Ok(())
+    }
+
     /// Returns the number of documents indexed in the database.
     pub fn number_of_documents(&self, rtxn: &RoTxn) -> Result<u64> {
         let count =
@@ -498,6 +601,255 @@ impl Index {
         }
     }
 
+    /* attributes positions limits */
+
+    /// Writes the maximum number of positions indexed for the given attributes.
+    pub(crate) fn put_attributes_positions_limits(
+        &self,
+        wtxn: &mut RwTxn,
+        limits: &BTreeMap<String, u32>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::ATTRIBUTES_POSITIONS_LIMITS, limits)
+    }
+
+    /// Deletes the attributes positions limits, all the positions of every attribute are indexed.
+    pub(crate) fn delete_attributes_positions_limits(
+        &self,
+        wtxn: &mut RwTxn,
+    ) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::ATTRIBUTES_POSITIONS_LIMITS)
+    }
+
+    /// Returns the maximum number of positions indexed for the attributes, only the first
+    /// words of these attributes can match a query, the documents are still stored entirely.
+    pub fn attributes_positions_limits(&self, rtxn: &RoTxn) -> heed::Result<BTreeMap<String, u32>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::ATTRIBUTES_POSITIONS_LIMITS)?
+            .unwrap_or_default())
+    }
+
+    /// Identical to `attributes_positions_limits`, but returns ids instead.
+    pub fn attributes_positions_limits_ids(&self, rtxn: &RoTxn) -> Result<HashMap<FieldId, u32>> {
+        let limits = self.attributes_positions_limits(rtxn)?;
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        Ok(limits
+            .into_iter()
+            .filter_map(|(name, limit)| fields_ids_map.id(&name).map(|id| (id, limit)))
+            .collect())
+    }
+
+    /* number coercions */
+
+    /// Writes the number coercion policy of the given faceted attributes.
+    pub(crate) fn put_number_coercions(
+        &self,
+        wtxn: &mut RwTxn,
+        coercions: &BTreeMap<String, NumberCoercion>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::NUMBER_COERCIONS, coercions)
+    }
+
+    /// Deletes the number coercion policies, no string is indexed as a number.
+    pub(crate) fn delete_number_coercions(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::NUMBER_COERCIONS)
+    }
+
+    /// Returns the number coercion policy of the faceted attributes, the string values of
+    /// the other attributes are never indexed as numbers.
+    pub fn number_coercions(&self, rtxn: &RoTxn) -> heed::Result<BTreeMap<String, NumberCoercion>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::NUMBER_COERCIONS)?
+            .unwrap_or_default())
+    }
+
+    /// Identical to `number_coercions`, but returns ids instead.
+    pub fn number_coercions_ids(&self, rtxn: &RoTxn) -> Result<HashMap<FieldId, NumberCoercion>> {
+        let coercions = self.number_coercions(rtxn)?;
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        Ok(coercions
+            .into_iter()
+            .filter_map(|(name, coercion)| fields_ids_map.id(&name).map(|id| (id, coercion)))
+            .collect())
+    }
+
+    /* word boundaries */
+
+    /// Writes the boundaries on which the words are split in addition to the separators.
+    pub(crate) fn put_word_boundaries(
+        &self,
+        wtxn: &mut RwTxn,
+        boundaries: &WordBoundaries,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::WORD_BOUNDARIES, boundaries)
+    }
+
+    /// Deletes the word boundaries, the words are only split on the separators.
+    pub(crate) fn delete_word_boundaries(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::WORD_BOUNDARIES)
+    }
+
+    /// Returns the boundaries on which the words of the documents and queries are split.
+    pub fn word_boundaries(&self, rtxn: &RoTxn) -> heed::Result<WordBoundaries> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::WORD_BOUNDARIES)?
+            .unwrap_or_default())
+    }
+
+    /* normalizer options */
+
+    /// Writes the language specific normalizations applied to the words.
+    pub(crate) fn put_normalizer_options(
+        &self,
+        wtxn: &mut RwTxn,
+        options: &NormalizerOptions,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::NORMALIZER_OPTIONS, options)
+    }
+
+    /// Deletes the normalizer options, only the normalizations of the analyzer are applied.
+    pub(crate) fn delete_normalizer_options(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::NORMALIZER_OPTIONS)
+    }
+
+    /// Returns the language specific normalizations applied to the words.
+    pub fn normalizer_options(&self, rtxn: &RoTxn) -> heed::Result<NormalizerOptions> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::NORMALIZER_OPTIONS)?
+            .unwrap_or_default())
+    }
+
+    /* geo distance formula */
+
+    /// Writes the formula used to compute the distances of the geo filters and sorts.
+    pub(crate) fn put_geo_distance_formula(
+        &self,
+        wtxn: &mut RwTxn,
+        formula: GeoDistanceFormula,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::GEO_DISTANCE_FORMULA, &formula)
+    }
+
+    /// Deletes the geo distance formula, the haversine formula is used.
+    pub(crate) fn delete_geo_distance_formula(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::GEO_DISTANCE_FORMULA)
+    }
+
+    /// Returns the formula used to compute the distances of the geo filters and sorts.
+    pub fn geo_distance_formula(&self, rtxn: &RoTxn) -> heed::Result<GeoDistanceFormula> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::GEO_DISTANCE_FORMULA)?
+            .unwrap_or_default())
+    }
+
+    /* query rewrites */
+
+    /// Writes the query rewriting rules, by normalized pattern.
+    pub(crate) fn put_query_rewrites(
+        &self,
+        wtxn: &mut RwTxn,
+        rewrites: &BTreeMap<String, QueryRewrite>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::QUERY_REWRITES, rewrites)
+    }
+
+    pub(crate) fn delete_query_rewrites(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::QUERY_REWRITES)
+    }
+
+    /// Returns the query rewriting rules by pattern, the words of the patterns
+    /// and of the rewritten queries are normalized and separated by a space.
+    pub fn query_rewrites(&self, rtxn: &RoTxn) -> heed::Result<BTreeMap<String, QueryRewrite>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::QUERY_REWRITES)?
+            .unwrap_or_default())
+    }
+
+    /* word position precision */
+
+    /// Writes the precision of the positions of the word position databases.
+    pub(crate) fn put_word_position_precision(
+        &self,
+        wtxn: &mut RwTxn,
+        precision: NonZeroU32,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, OwnedType<u32>>(
+            wtxn,
+            main_key::WORD_POSITION_PRECISION,
+            &precision.get(),
+        )
+    }
+
+    /// Deletes the word position precision, the exact positions are indexed.
+    pub(crate) fn delete_word_position_precision(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::WORD_POSITION_PRECISION)
+    }
+
+    /// Returns the precision of the positions of the word position databases, the positions
+    /// in an attribute are rounded down to a multiple of it. Defaults to 1, the exact positions.
+    pub fn word_position_precision(&self, rtxn: &RoTxn) -> heed::Result<NonZeroU32> {
+        let precision =
+            self.main.get::<_, Str, OwnedType<u32>>(rtxn, main_key::WORD_POSITION_PRECISION)?;
+        Ok(precision.and_then(NonZeroU32::new).unwrap_or_else(|| NonZeroU32::new(1).unwrap()))
+    }
+
+    /* positional array fields */
+
+    /// Writes the fields for which the position of the values in arrays is indexed.
+    pub(crate) fn put_positional_array_fields(
+        &self,
+        wtxn: &mut RwTxn,
+        fields: &HashSet<String>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::POSITIONAL_ARRAY_FIELDS, fields)
+    }
+
+    /// Deletes the positional array fields, the position of the values in arrays is forgotten.
+    pub(crate) fn delete_positional_array_fields(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::POSITIONAL_ARRAY_FIELDS)
+    }
+
+    /// Returns the fields for which the position of the values in arrays is indexed, the
+    /// value at position `i` of the `authors` field is indexed as the `authors[i]` field.
+    pub fn positional_array_fields(&self, rtxn: &RoTxn) -> heed::Result<HashSet<String>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::POSITIONAL_ARRAY_FIELDS)?
+            .unwrap_or_default())
+    }
+
+    /* content hash fields */
+
+    /// Writes the fields whose values are hashed together in the content hash of the documents.
+    pub(crate) fn put_content_hash_fields(
+        &self,
+        wtxn: &mut RwTxn,
+        fields: &HashSet<String>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::CONTENT_HASH_FIELDS, fields)
+    }
+
+    /// Deletes the content hash fields, the content hash of the documents is not computed anymore.
+    pub(crate) fn delete_content_hash_fields(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::CONTENT_HASH_FIELDS)
+    }
+
+    /// Returns the fields whose values are hashed together in the [`CONTENT_HASH_FIELD`] of
+    /// the documents, the documents with the same hash can be deduplicated at search time.
+    ///
+    /// [`CONTENT_HASH_FIELD`]: crate::CONTENT_HASH_FIELD
+    pub fn content_hash_fields(&self, rtxn: &RoTxn) -> heed::Result<HashSet<String>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::CONTENT_HASH_FIELDS)?
+            .unwrap_or_default())
+    }
+
     /* filterable fields */
 
     /// Writes the filterable fields names in the database.
@@ -537,6 +889,35 @@ impl Index {
         Ok(fields_ids)
     }
 
+    /// Returns the filterable fields names along with the types of the values
+    /// indexed for them, e.g. to know which operators a filter on a field can use.
+    ///
+    /// A filterable field that is not part of any document yet has no types.
+    pub fn filterable_fields_with_types(
+        &self,
+        rtxn: &RoTxn,
+    ) -> Result<BTreeMap<String, FilterableFieldTypes>> {
+        let fields = self.filterable_fields(rtxn)?;
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+
+        let mut fields_types = BTreeMap::new();
+        for name in fields {
+            let mut types = FilterableFieldTypes::default();
+            if name == "_geo" {
+                types.geo = !self.geo_faceted_documents_ids(rtxn)?.is_empty();
+            } else if let Some(field_id) = fields_ids_map.id(&name) {
+                types.number = !self.number_faceted_documents_ids(rtxn, field_id)?.is_empty();
+                types.string = !self.string_faceted_documents_ids(rtxn, field_id)?.is_empty();
+                types.boolean =
+                    !self.boolean_faceted_documents_ids(rtxn, field_id, true)?.is_empty()
+                        || !self.boolean_faceted_documents_ids(rtxn, field_id, false)?.is_empty();
+            }
+            fields_types.insert(name, types);
+        }
+
+        Ok(fields_types)
+    }
+
     /* sortable fields */
 
     /// Writes the sortable fields names in the database.
@@ -602,15 +983,46 @@ impl Index {
         Ok(fields_ids)
     }
 
+    /// Writes the fields that are no longer faceted by the wildcard as they have too many values.
+    pub(crate) fn put_high_cardinality_faceted_fields(
+        &self,
+        wtxn: &mut RwTxn,
+        fields: &HashSet<String>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(
+            wtxn,
+            main_key::HIGH_CARDINALITY_FACETED_FIELDS_KEY,
+            fields,
+        )
+    }
+
+    /// Deletes the fields that are no longer faceted by the wildcard.
+    pub(crate) fn delete_high_cardinality_faceted_fields(
+        &self,
+        wtxn: &mut RwTxn,
+    ) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::HIGH_CARDINALITY_FACETED_FIELDS_KEY)
+    }
+
+    /// Returns the fields that are no longer faceted by the wildcard as they have too many values,
+    /// see [`IndexerConfig::max_values_per_wildcard_facet`](crate::update::IndexerConfig).
+    pub fn high_cardinality_faceted_fields(&self, rtxn: &RoTxn) -> heed::Result<HashSet<String>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::HIGH_CARDINALITY_FACETED_FIELDS_KEY)?
+            .unwrap_or_default())
+    }
+
     /* faceted documents ids */
 
     /// Returns the user defined faceted fields names.
     ///
     /// The user faceted fields are the union of all the filterable, sortable, distinct, and Asc/Desc fields.
+    /// They can contain the [`ALL_FIELDS_FACET`](crate::ALL_FIELDS_FACET) wildcard.
     pub fn user_defined_faceted_fields(&self, rtxn: &RoTxn) -> Result<HashSet<String>> {
         let filterable_fields = self.filterable_fields(rtxn)?;
         let sortable_fields = self.sortable_fields(rtxn)?;
-        let distinct_field = self.distinct_field(rtxn)?;
+        let distinct_fields = self.distinct_fields(rtxn)?;
         let asc_desc_fields =
             self.criteria(rtxn)?.into_iter().filter_map(|criterion| match criterion {
                 Criterion::Asc(field) | Criterion::Desc(field) => Some(field),
@@ -620,9 +1032,7 @@ impl Index {
         let mut faceted_fields = filterable_fields;
         faceted_fields.extend(sortable_fields);
         faceted_fields.extend(asc_desc_fields);
-        if let Some(field) = distinct_field {
-            faceted_fields.insert(field.to_owned());
-        }
+        faceted_fields.extend(distinct_fields);
 
         Ok(faceted_fields)
     }
@@ -712,6 +1122,32 @@ impl Index {
         }
     }
 
+    /// Writes the documents ids that are faceted with this boolean under this field id.
+    pub(crate) fn put_boolean_faceted_documents_ids(
+        &self,
+        wtxn: &mut RwTxn,
+        field_id: FieldId,
+        value: bool,
+        docids: &RoaringBitmap,
+    ) -> heed::Result<()> {
+        let key = boolean_faceted_documents_ids_key(field_id, value);
+        self.main.put::<_, ByteSlice, RoaringBitmapCodec>(wtxn, &key, docids)
+    }
+
+    /// Retrieve all the documents ids that faceted with this boolean under this field id.
+    pub fn boolean_faceted_documents_ids(
+        &self,
+        rtxn: &RoTxn,
+        field_id: FieldId,
+        value: bool,
+    ) -> heed::Result<RoaringBitmap> {
+        let key = boolean_faceted_documents_ids_key(field_id, value);
+        match self.main.get::<_, ByteSlice, RoaringBitmapCodec>(rtxn, &key)? {
+            Some(docids) => Ok(docids),
+            None => Ok(RoaringBitmap::new()),
+        }
+    }
+
     /* distinct field */
 
     pub(crate) fn put_distinct_field(
@@ -730,6 +1166,32 @@ impl Index {
         self.main.delete::<_, Str>(wtxn, main_key::DISTINCT_FIELD_KEY)
     }
 
+    /// Writes the fields on which the documents are collapsed together, the documents
+    /// sharing the same tuple of values on these fields are considered duplicates.
+    pub(crate) fn put_composite_distinct_fields(
+        &self,
+        wtxn: &mut RwTxn,
+        fields: &[String],
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::DISTINCT_FIELDS_KEY, &fields)
+    }
+
+    pub(crate) fn delete_composite_distinct_fields(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::DISTINCT_FIELDS_KEY)
+    }
+
+    /// Returns the fields on which the documents are collapsed together, either the
+    /// composite distinct fields or the distinct field alone, empty if there is none.
+    pub fn distinct_fields(&self, rtxn: &RoTxn) -> heed::Result<Vec<String>> {
+        match self
+            .main
+            .get::<_, Str, SerdeJson<Vec<String>>>(rtxn, main_key::DISTINCT_FIELDS_KEY)?
+        {
+            Some(fields) => Ok(fields),
+            None => Ok(self.distinct_field(rtxn)?.map(String::from).into_iter().collect()),
+        }
+    }
+
     /* criteria */
 
     pub(crate) fn put_criteria(
@@ -763,6 +1225,9 @@ impl Index {
     }
 
     /// Returns the FST which is the words dictionary of the engine.
+    ///
+    /// The FST is not copied, it borrows the memory-mapped database and is only valid
+    /// for the lifetime of the transaction, read it once per transaction.
     pub fn words_fst<'t>(&self, rtxn: &'t RoTxn) -> Result<fst::Set<Cow<'t, [u8]>>> {
         match self.main.get::<_, Str, ByteSlice>(rtxn, main_key::WORDS_FST_KEY)? {
             Some(bytes) => Ok(fst::Set::new(bytes)?.map_data(Cow::Borrowed)?),
@@ -837,6 +1302,8 @@ impl Index {
     }
 
     /// Returns the FST which is the words prefixes dictionnary of the engine.
+    ///
+    /// Like the words FST, it borrows the memory-mapped database without copying it.
     pub fn words_prefixes_fst<'t>(&self, rtxn: &'t RoTxn) -> Result<fst::Set<Cow<'t, [u8]>>> {
         match self.main.get::<_, Str, ByteSlice>(rtxn, main_key::WORDS_PREFIXES_FST_KEY)? {
             Some(bytes) => Ok(fst::Set::new(bytes)?.map_data(Cow::Borrowed)?),
@@ -844,6 +1311,68 @@ impl Index {
         }
     }
 
+    /// Writes the threshold and the maximum prefix length the words prefixes FST was built with.
+    pub(crate) fn put_words_prefixes_fst_parameters(
+        &self,
+        wtxn: &mut RwTxn,
+        threshold: u32,
+        max_prefix_length: usize,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeBincode<(u32, usize)>>(
+            wtxn,
+            main_key::WORDS_PREFIXES_FST_PARAMETERS_KEY,
+            &(threshold, max_prefix_length),
+        )
+    }
+
+    pub(crate) fn delete_words_prefixes_fst_parameters(
+        &self,
+        wtxn: &mut RwTxn,
+    ) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::WORDS_PREFIXES_FST_PARAMETERS_KEY)
+    }
+
+    /// Returns the threshold and the maximum prefix length the words prefixes FST was built
+    /// with, `None` if they are unknown.
+    pub(crate) fn words_prefixes_fst_parameters(
+        &self,
+        rtxn: &RoTxn,
+    ) -> heed::Result<Option<(u32, usize)>> {
+        self.main.get::<_, Str, SerdeBincode<(u32, usize)>>(
+            rtxn,
+            main_key::WORDS_PREFIXES_FST_PARAMETERS_KEY,
+        )
+    }
+
+    /* deleted words fst */
+
+    /// Writes the FST of the words removed from the words FST since the words prefixes FST
+    /// was built, the prefixes of these words must be counted again.
+    pub(crate) fn put_deleted_words_fst<A: AsRef<[u8]>>(
+        &self,
+        wtxn: &mut RwTxn,
+        fst: &fst::Set<A>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, ByteSlice>(
+            wtxn,
+            main_key::DELETED_WORDS_FST_KEY,
+            fst.as_fst().as_bytes(),
+        )
+    }
+
+    pub(crate) fn delete_deleted_words_fst(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::DELETED_WORDS_FST_KEY)
+    }
+
+    /// Returns the FST of the words removed from the words FST since the words prefixes FST
+    /// was built.
+    pub(crate) fn deleted_words_fst<'t>(&self, rtxn: &'t RoTxn) -> Result<fst::Set<Cow<'t, [u8]>>> {
+        match self.main.get::<_, Str, ByteSlice>(rtxn, main_key::DELETED_WORDS_FST_KEY)? {
+            Some(bytes) => Ok(fst::Set::new(bytes)?.map_data(Cow::Borrowed)?),
+            None => Ok(fst::Set::default().map_data(Cow::Owned)?),
+        }
+    }
+
     /* word documents count */
 
     /// Returns the number of documents ids associated with the given word,
@@ -852,12 +1381,58 @@ impl Index {
         self.word_docids.remap_data_type::<RoaringBitmapLenCodec>().get(rtxn, word)
     }
 
-    /* documents */
-
-    /// Returns a [`Vec`] of the requested documents. Returns an error if a document is missing.
-    pub fn documents<'t>(
+    /// Returns the words of the index that are at most `max_typos` typos away from the given
+    /// word, the closest and most frequent words first, e.g. to suggest corrections.
+    ///
+    /// The typos are counted like during a search, with the transposition setting of the index,
+    /// the word must therefore be normalized like the query words.
+    pub fn closest_words(
         &self,
-        rtxn: &'t RoTxn,
+        rtxn: &RoTxn,
+        word: &str,
+        max_typos: u8,
+        limit: usize,
+    ) -> Result<Vec<ClosestWord>> {
+        if word.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let words_fst = self.words_fst(rtxn)?;
+        let transposition_cost_one = self.transposition_counts_as_one_typo(rtxn)?;
+        let mut cache = WordDerivationsCache::new();
+        let derivations = word_derivations_with_transposition(
+            word,
+            false,
+            max_typos.min(2),
+            transposition_cost_one,
+            &words_fst,
+            Some(&self.dfa_cache),
+            &mut cache,
+        )?;
+
+        let mut words = Vec::with_capacity(derivations.len());
+        for (derived_word, typos) in derivations {
+            let documents_count = self.word_documents_count(rtxn, derived_word)?.unwrap_or(0);
+            words.push(ClosestWord { word: derived_word.clone(), typos: *typos, documents_count });
+        }
+
+        words.sort_unstable_by(|a, b| {
+            a.typos
+                .cmp(&b.typos)
+                .then_with(|| b.documents_count.cmp(&a.documents_count))
+                .then_with(|| a.word.cmp(&b.word))
+        });
+        words.truncate(limit);
+
+        Ok(words)
+    }
+
+    /* documents */
+
+    /// Returns a [`Vec`] of the requested documents. Returns an error if a document is missing.
+    pub fn documents<'t>(
+        &self,
+        rtxn: &'t RoTxn,
         ids: impl IntoIterator<Item = DocumentId>,
     ) -> Result<Vec<(DocumentId, obkv::KvReaderU16<'t>)>> {
         let mut documents = Vec::new();
@@ -878,11 +1453,151 @@ impl Index {
         &self,
         rtxn: &'t RoTxn,
     ) -> Result<impl Iterator<Item = heed::Result<(DocumentId, obkv::KvReaderU16<'t>)>>> {
+        let soft_deleted = self.soft_deleted_documents_ids(rtxn)?;
         Ok(self
             .documents
             .iter(rtxn)?
             // we cast the BEU32 to a DocumentId
-            .map(|document| document.map(|(id, obkv)| (id.get(), obkv))))
+            .map(|document| document.map(|(id, obkv)| (id.get(), obkv)))
+            // the soft deleted documents are still stored until they are purged
+            .filter(move |document| !matches!(document, Ok((id, _)) if soft_deleted.contains(*id))))
+    }
+
+    /// Returns an iterator over all the documents in the index, sorted by their external ids.
+    /// The external ids and the documents are lazily read which makes it suitable for exports
+    /// and listings.
+    pub fn all_documents_sorted_by_external_id<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+    ) -> Result<impl Iterator<Item = Result<(DocumentId, obkv::KvReaderU16<'t>)>> + 't> {
+        let ids = self.external_documents_ids(rtxn)?.into_sorted_internal_ids();
+        let documents = self.documents;
+        Ok(ids.map(move |id| {
+            let id = id?;
+            let kv = documents
+                .get(rtxn, &BEU32::new(id))?
+                .ok_or(UserError::UnknownInternalDocumentId { document_id: id })?;
+            Ok((id, kv))
+        }))
+    }
+
+    /// Returns the documents matching the optional filter in the internal ids order,
+    /// skipping the first `offset` ones and returning at most `limit` of them.
+    pub fn browse_documents<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+        offset: usize,
+        limit: usize,
+        filter: Option<&Filter>,
+    ) -> Result<Vec<(DocumentId, obkv::KvReaderU16<'t>)>> {
+        let mut candidates = self.documents_ids(rtxn)?;
+        if let Some(filter) = filter {
+            candidates &= filter.evaluate(rtxn, self)?;
+        }
+        self.documents(rtxn, candidates.into_iter().skip(offset).take(limit))
+    }
+
+    /// Returns the vectors stored in the `_vectors` fields of the given document.
+    pub fn document_vectors(
+        &self,
+        rtxn: &RoTxn,
+        docid: DocumentId,
+    ) -> Result<BTreeMap<String, Vec<f32>>> {
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        let mut vectors = BTreeMap::new();
+        let iter = self
+            .vectors
+            .remap_key_type::<ByteSlice>()
+            .prefix_iter(rtxn, &docid.to_be_bytes())?
+            .remap_key_type::<DocIdFieldIdCodec>();
+        for result in iter {
+            let ((_docid, field_id), vector) = result?;
+            let name = fields_ids_map.name(field_id).ok_or(FieldIdMapMissingEntry::FieldId {
+                field_id,
+                process: "Index::document_vectors",
+            })?;
+            vectors.insert(name.to_string(), vector);
+        }
+        Ok(vectors)
+    }
+
+    /// Returns the number of words in the given field of the given document.
+    pub fn document_field_word_count(
+        &self,
+        rtxn: &RoTxn,
+        docid: DocumentId,
+        field_id: FieldId,
+    ) -> heed::Result<Option<u32>> {
+        let count = self.docid_field_id_word_count.get(rtxn, &(docid, field_id))?;
+        Ok(count.map(|count| count.get()))
+    }
+
+    /// Returns the number of words in each searchable field of the given document.
+    pub fn document_word_counts(
+        &self,
+        rtxn: &RoTxn,
+        docid: DocumentId,
+    ) -> heed::Result<BTreeMap<FieldId, u32>> {
+        let mut word_counts = BTreeMap::new();
+        let iter = self
+            .docid_field_id_word_count
+            .remap_key_type::<ByteSlice>()
+            .prefix_iter(rtxn, &docid.to_be_bytes())?
+            .remap_key_type::<DocIdFieldIdCodec>();
+        for result in iter {
+            let ((_docid, field_id), count) = result?;
+            word_counts.insert(field_id, count.get());
+        }
+        Ok(word_counts)
+    }
+
+    /// Returns the normalized facet values stored for the given field of the given document,
+    /// the numbers first and then the strings, in the order of the facet databases.
+    ///
+    /// A field that doesn't exist or isn't faceted has no facet values.
+    pub fn facet_values_of(
+        &self,
+        rtxn: &RoTxn,
+        docid: DocumentId,
+        field: &str,
+    ) -> Result<Vec<FacetValue>> {
+        let field_id = match self.fields_ids_map(rtxn)?.id(field) {
+            Some(field_id) => field_id,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut prefix = Vec::with_capacity(size_of::<FieldId>() + size_of::<DocumentId>());
+        prefix.extend_from_slice(&field_id.to_be_bytes());
+        prefix.extend_from_slice(&docid.to_be_bytes());
+
+        let mut values = Vec::new();
+        let iter = self
+            .field_id_docid_facet_f64s
+            .remap_key_type::<ByteSlice>()
+            .prefix_iter(rtxn, &prefix)?
+            .remap_key_type::<FieldDocIdFacetF64Codec>();
+        for result in iter {
+            let ((_, _, value), ()) = result?;
+            values.push(FacetValue::from(value));
+        }
+
+        let iter = self
+            .field_id_docid_facet_strings
+            .remap_key_type::<ByteSlice>()
+            .prefix_iter(rtxn, &prefix)?
+            .remap_key_type::<FieldDocIdFacetStringCodec>();
+        for result in iter {
+            let ((_, _, normalized_value), _original_value) = result?;
+            values.push(FacetValue::from(normalized_value));
+        }
+
+        for value in [false, true] {
+            if self.boolean_faceted_documents_ids(rtxn, field_id, value)?.contains(docid) {
+                values.push(FacetValue::from(value));
+            }
+        }
+
+        Ok(values)
     }
 
     pub fn facets_distribution<'a>(&'a self, rtxn: &'a RoTxn) -> FacetDistribution<'a> {
@@ -915,14 +1630,34 @@ impl Index {
             })?)
     }
 
+    /// Updates the last updated time and increments the generation of the index,
+    /// every write to the index goes through this method.
     pub(crate) fn set_updated_at(
         &self,
         wtxn: &mut RwTxn,
         time: &OffsetDateTime,
     ) -> heed::Result<()> {
+        let generation = self.generation(wtxn)?.wrapping_add(1);
+        self.main.put::<_, Str, OwnedType<u64>>(wtxn, main_key::GENERATION_KEY, &generation)?;
         self.main.put::<_, Str, SerdeJson<OffsetDateTime>>(wtxn, main_key::UPDATED_AT_KEY, &time)
     }
 
+    /// Returns the generation of the index, a counter incremented by every write to the index.
+    ///
+    /// A reader, e.g. a cache of search results or a read replica, can keep the generation it
+    /// computed its results on and later check with [`Index::changes_since`] whether these
+    /// results are still valid, once an update committed. Unlike the last updated time, the
+    /// generation can't go backward when the clock of the system changes.
+    pub fn generation(&self, rtxn: &RoTxn) -> heed::Result<u64> {
+        Ok(self.main.get::<_, Str, OwnedType<u64>>(rtxn, main_key::GENERATION_KEY)?.unwrap_or(0))
+    }
+
+    /// Returns whether the index was modified after the given generation, i.e. whether
+    /// the results computed on this generation of the index may be stale.
+    pub fn changes_since(&self, rtxn: &RoTxn, generation: u64) -> heed::Result<bool> {
+        Ok(self.generation(rtxn)? != generation)
+    }
+
     pub fn authorize_typos(&self, txn: &RoTxn) -> heed::Result<bool> {
         // It is not possible to put a bool in heed with OwnedType, so we put a u8 instead. We
         // identify 0 as being false, and anything else as true. The absence of a value is true,
@@ -942,10 +1677,8 @@ impl Index {
         Ok(())
     }
 
+    /// Returns the minimum number of characters of a query word to accept one typo.
     pub fn min_word_len_one_typo(&self, txn: &RoTxn) -> heed::Result<u8> {
-        // It is not possible to put a bool in heed with OwnedType, so we put a u8 instead. We
-        // identify 0 as being false, and anything else as true. The absence of a value is true,
-        // because by default, we authorize typos.
         Ok(self
             .main
             .get::<_, Str, OwnedType<u8>>(txn, main_key::ONE_TYPO_WORD_LEN)?
@@ -953,17 +1686,12 @@ impl Index {
     }
 
     pub(crate) fn put_min_word_len_one_typo(&self, txn: &mut RwTxn, val: u8) -> heed::Result<()> {
-        // It is not possible to put a bool in heed with OwnedType, so we put a u8 instead. We
-        // identify 0 as being false, and anything else as true. The absence of a value is true,
-        // because by default, we authorize typos.
         self.main.put::<_, Str, OwnedType<u8>>(txn, main_key::ONE_TYPO_WORD_LEN, &val)?;
         Ok(())
     }
 
+    /// Returns the minimum number of characters of a query word to accept two typos.
     pub fn min_word_len_two_typos(&self, txn: &RoTxn) -> heed::Result<u8> {
-        // It is not possible to put a bool in heed with OwnedType, so we put a u8 instead. We
-        // identify 0 as being false, and anything else as true. The absence of a value is true,
-        // because by default, we authorize typos.
         Ok(self
             .main
             .get::<_, Str, OwnedType<u8>>(txn, main_key::TWO_TYPOS_WORD_LEN)?
@@ -971,13 +1699,106 @@ impl Index {
     }
 
     pub(crate) fn put_min_word_len_two_typos(&self, txn: &mut RwTxn, val: u8) -> heed::Result<()> {
-        // It is not possible to put a bool in heed with OwnedType, so we put a u8 instead. We
-        // identify 0 as being false, and anything else as true. The absence of a value is true,
-        // because by default, we authorize typos.
         self.main.put::<_, Str, OwnedType<u8>>(txn, main_key::TWO_TYPOS_WORD_LEN, &val)?;
         Ok(())
     }
 
+    /// Returns `true` if swapping two adjacent characters must be counted as a single typo,
+    /// `false` if it must be counted as two (a deletion and an insertion).
+    pub fn transposition_counts_as_one_typo(&self, txn: &RoTxn) -> heed::Result<bool> {
+        // The absence of a value is true, this is the historical behavior of the engine.
+        match self
+            .main
+            .get::<_, Str, OwnedType<u8>>(txn, main_key::TRANSPOSITION_COUNTS_AS_ONE_TYPO)?
+        {
+            Some(0) => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    pub(crate) fn put_transposition_counts_as_one_typo(
+        &self,
+        txn: &mut RwTxn,
+        flag: bool,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, OwnedType<u8>>(
+            txn,
+            main_key::TRANSPOSITION_COUNTS_AS_ONE_TYPO,
+            &(flag as u8),
+        )?;
+        Ok(())
+    }
+
+    /// Returns `true` if the query words only composed of digits must be matched without typos.
+    pub fn disable_typos_on_numbers(&self, txn: &RoTxn) -> heed::Result<bool> {
+        // The absence of a value is false, by default we authorize typos on numbers.
+        match self.main.get::<_, Str, OwnedType<u8>>(txn, main_key::DISABLE_TYPOS_ON_NUMBERS)? {
+            Some(0) | None => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    pub(crate) fn put_disable_typos_on_numbers(
+        &self,
+        txn: &mut RwTxn,
+        flag: bool,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, OwnedType<u8>>(
+            txn,
+            main_key::DISABLE_TYPOS_ON_NUMBERS,
+            &(flag as u8),
+        )?;
+        Ok(())
+    }
+
+    /// Returns `true` if the words of a phrase must follow each other in a single attribute,
+    /// a phrase can otherwise match the end of an attribute and the start of the next one.
+    pub fn phrases_within_attribute(&self, txn: &RoTxn) -> heed::Result<bool> {
+        // The absence of a value is false, this is the historical behavior of the engine.
+        match self.main.get::<_, Str, OwnedType<u8>>(txn, main_key::PHRASES_WITHIN_ATTRIBUTE)? {
+            Some(0) | None => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    pub(crate) fn put_phrases_within_attribute(
+        &self,
+        txn: &mut RwTxn,
+        flag: bool,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, OwnedType<u8>>(
+            txn,
+            main_key::PHRASES_WITHIN_ATTRIBUTE,
+            &(flag as u8),
+        )?;
+        Ok(())
+    }
+
+    /* pending prefix databases update */
+
+    /// Returns `true` if some document additions deferred the update of the prefix databases,
+    /// the prefix databases must then be rebuilt from scratch by the next update.
+    pub fn pending_prefix_databases_update(&self, txn: &RoTxn) -> heed::Result<bool> {
+        match self
+            .main
+            .get::<_, Str, OwnedType<u8>>(txn, main_key::PENDING_PREFIX_DATABASES_UPDATE)?
+        {
+            Some(0) | None => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    pub(crate) fn put_pending_prefix_databases_update(&self, txn: &mut RwTxn) -> heed::Result<()> {
+        self.main.put::<_, Str, OwnedType<u8>>(txn, main_key::PENDING_PREFIX_DATABASES_UPDATE, &1)
+    }
+
+    pub(crate) fn delete_pending_prefix_databases_update(
+        &self,
+        txn: &mut RwTxn,
+    ) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(txn, main_key::PENDING_PREFIX_DATABASES_UPDATE)
+    }
+
     /// List the words on which typo are not allowed
     pub fn exact_words<'t>(&self, txn: &'t RoTxn) -> Result<fst::Set<Cow<'t, [u8]>>> {
         match self.main.get::<_, Str, ByteSlice>(txn, main_key::EXACT_WORDS)? {
@@ -1007,11 +1828,12 @@ impl Index {
             .unwrap_or_default())
     }
 
-    /// Returns the list of exact attributes field ids.
+    /// Returns the ids of the exact attributes and of the fields nested in them, e.g. the
+    /// `author.name` field for the `author` attribute. The `*` attribute matches every field.
     pub fn exact_attributes_ids(&self, txn: &RoTxn) -> Result<HashSet<FieldId>> {
         let attrs = self.exact_attributes(txn)?;
         let fid_map = self.fields_ids_map(txn)?;
-        Ok(attrs.iter().filter_map(|attr| fid_map.id(attr)).collect())
+        Ok(fid_map.iter().filter(|(_, name)| is_faceted(name, &attrs)).map(|(id, _)| id).collect())
     }
 
     /// Writes the exact attributes to the database.
@@ -1025,19 +1847,426 @@ impl Index {
         self.main.delete::<_, Str>(txn, main_key::EXACT_ATTRIBUTES)?;
         Ok(())
     }
+
+    /// Returns the attributes that are ignored by the exactness criterion.
+    pub fn exactness_excluded_attributes<'t>(&self, txn: &'t RoTxn) -> Result<Vec<&'t str>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeBincode<Vec<&str>>>(txn, main_key::EXACTNESS_EXCLUDED_ATTRIBUTES)?
+            .unwrap_or_default())
+    }
+
+    /// Returns the field ids of the attributes that are ignored by the exactness criterion.
+    pub fn exactness_excluded_attributes_ids(&self, txn: &RoTxn) -> Result<HashSet<FieldId>> {
+        let attrs = self.exactness_excluded_attributes(txn)?;
+        let fid_map = self.fields_ids_map(txn)?;
+        Ok(attrs.iter().filter_map(|attr| fid_map.id(attr)).collect())
+    }
+
+    /// Writes the attributes that are ignored by the exactness criterion.
+    pub(crate) fn put_exactness_excluded_attributes(
+        &self,
+        txn: &mut RwTxn,
+        attrs: &[&str],
+    ) -> Result<()> {
+        self.main.put::<_, Str, SerdeBincode<&[&str]>>(
+            txn,
+            main_key::EXACTNESS_EXCLUDED_ATTRIBUTES,
+            &attrs,
+        )?;
+        Ok(())
+    }
+
+    /// Clears the attributes that are ignored by the exactness criterion.
+    pub(crate) fn delete_exactness_excluded_attributes(&self, txn: &mut RwTxn) -> Result<()> {
+        self.main.delete::<_, Str>(txn, main_key::EXACTNESS_EXCLUDED_ATTRIBUTES)?;
+        Ok(())
+    }
+
+    /// Returns the hashed fields: fields whose values are only stored and indexed hashed.
+    pub fn hashed_fields<'t>(&self, txn: &'t RoTxn) -> Result<Vec<&'t str>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeBincode<Vec<&str>>>(txn, main_key::HASHED_FIELDS)?
+            .unwrap_or_default())
+    }
+
+    /// Returns the list of hashed fields ids.
+    pub fn hashed_fields_ids(&self, txn: &RoTxn) -> Result<HashSet<FieldId>> {
+        let attrs = self.hashed_fields(txn)?;
+        let fid_map = self.fields_ids_map(txn)?;
+        Ok(attrs.iter().filter_map(|attr| fid_map.id(attr)).collect())
+    }
+
+    /// Writes the hashed fields to the database.
+    pub(crate) fn put_hashed_fields(&self, txn: &mut RwTxn, attrs: &[&str]) -> Result<()> {
+        self.main.put::<_, Str, SerdeBincode<&[&str]>>(txn, main_key::HASHED_FIELDS, &attrs)?;
+        Ok(())
+    }
+
+    /// Clears the hashed fields from the store, the values already hashed stay hashed.
+    pub(crate) fn delete_hashed_fields(&self, txn: &mut RwTxn) -> Result<()> {
+        self.main.delete::<_, Str>(txn, main_key::HASHED_FIELDS)?;
+        Ok(())
+    }
+
+    /// Returns the salt mixed with the values of the hashed fields, it is generated once
+    /// and must never change for the hashes of the stored values to stay comparable.
+    pub(crate) fn hashed_fields_salt<'t>(&self, txn: &'t RoTxn) -> heed::Result<Option<&'t str>> {
+        self.main.get::<_, Str, Str>(txn, main_key::HASHED_FIELDS_SALT)
+    }
+
+    /// Writes the salt of the hashed fields.
+    pub(crate) fn put_hashed_fields_salt(&self, txn: &mut RwTxn, salt: &str) -> heed::Result<()> {
+        self.main.put::<_, Str, Str>(txn, main_key::HASHED_FIELDS_SALT, salt)
+    }
+
+    /// Returns the fields that have been hashed at least once, their stored values
+    /// may already be hashed even if they are no longer hashed.
+    pub(crate) fn previously_hashed_fields<'t>(&self, txn: &'t RoTxn) -> Result<Vec<&'t str>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeBincode<Vec<&str>>>(txn, main_key::PREVIOUSLY_HASHED_FIELDS)?
+            .unwrap_or_default())
+    }
+
+    /// Writes the fields that have been hashed at least once.
+    pub(crate) fn put_previously_hashed_fields(
+        &self,
+        txn: &mut RwTxn,
+        attrs: &[&str],
+    ) -> Result<()> {
+        self.main.put::<_, Str, SerdeBincode<&[&str]>>(
+            txn,
+            main_key::PREVIOUSLY_HASHED_FIELDS,
+            &attrs,
+        )?;
+        Ok(())
+    }
+
+    /// Returns the documents whose values of a previously hashed field are stored in plain
+    /// text, they were added since it is no longer hashed. The values of the others are hashed.
+    pub(crate) fn unhashed_documents_ids(
+        &self,
+        txn: &RoTxn,
+        field: &str,
+    ) -> heed::Result<RoaringBitmap> {
+        let key = format!("{}{}", main_key::UNHASHED_DOCUMENTS_IDS_PREFIX, field);
+        Ok(self.main.get::<_, Str, RoaringBitmapCodec>(txn, &key)?.unwrap_or_default())
+    }
+
+    /// Writes the documents whose values of a previously hashed field are stored in plain text.
+    pub(crate) fn put_unhashed_documents_ids(
+        &self,
+        txn: &mut RwTxn,
+        field: &str,
+        docids: &RoaringBitmap,
+    ) -> heed::Result<()> {
+        let key = format!("{}{}", main_key::UNHASHED_DOCUMENTS_IDS_PREFIX, field);
+        self.main.put::<_, Str, RoaringBitmapCodec>(txn, &key, docids)
+    }
+
+    /// Deletes the documents whose values of a field are stored in plain text,
+    /// once the values of this field are all hashed again.
+    pub(crate) fn delete_unhashed_documents_ids(
+        &self,
+        txn: &mut RwTxn,
+        field: &str,
+    ) -> heed::Result<bool> {
+        let key = format!("{}{}", main_key::UNHASHED_DOCUMENTS_IDS_PREFIX, field);
+        self.main.delete::<_, Str>(txn, &key)
+    }
+
+    /* verification */
+
+    /// Cross-checks the invariants that must hold between the different databases
+    /// of this index and returns a report of the broken ones.
+    ///
+    /// This is meant to help debugging a suspected corruption and must read the whole
+    /// documents database, it is therefore slow on big indexes.
+    pub fn verify(&self, rtxn: &RoTxn) -> Result<IndexVerificationReport> {
+        use fst::Streamer;
+
+        let mut report = IndexVerificationReport::default();
+        let documents_ids = self.documents_ids(rtxn)?;
+        // The soft deleted documents are still stored until they are purged.
+        let stored_ids = &documents_ids | &self.soft_deleted_documents_ids(rtxn)?;
+
+        // Every document id must have a stored document, and the other way around.
+        let mut stored_documents_ids = RoaringBitmap::new();
+        for result in self.documents.remap_data_type::<DecodeIgnore>().iter(rtxn)? {
+            let (docid, ()) = result?;
+            stored_documents_ids.insert(docid.get());
+        }
+        report.missing_documents = &documents_ids - &stored_documents_ids;
+        report.unlisted_documents = &stored_documents_ids - &stored_ids;
+
+        // The external ids must point to existing documents and be the primary key
+        // value of the documents they point to.
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        let primary_key_id =
+            self.primary_key(rtxn)?.and_then(|primary_key| fields_ids_map.id(primary_key));
+        let mut reachable_documents_ids = RoaringBitmap::new();
+        for (external_id, docid) in self.external_documents_ids(rtxn)?.to_hash_map() {
+            if !documents_ids.contains(docid) {
+                report.dangling_external_ids.push(external_id);
+                continue;
+            }
+            reachable_documents_ids.insert(docid);
+
+            let document = self.documents.get(rtxn, &BEU32::new(docid))?;
+            let value = match (document, primary_key_id) {
+                (Some(document), Some(primary_key_id)) => document.get(primary_key_id),
+                _ => None,
+            };
+            if let Some(value) = value {
+                let value: serde_json::Value =
+                    serde_json::from_slice(value).map_err(InternalError::SerdeJson)?;
+                let matches = match value {
+                    serde_json::Value::String(string) => string == external_id,
+                    otherwise => otherwise.to_string() == external_id,
+                };
+                if !matches {
+                    report.mismatched_external_ids.push(external_id);
+                }
+            }
+        }
+        report.dangling_external_ids.sort_unstable();
+        report.mismatched_external_ids.sort_unstable();
+        report.documents_without_external_id = &documents_ids - &reachable_documents_ids;
+
+        // The faceted documents ids must be a subset of the documents ids.
+        for (field_id, name) in fields_ids_map.iter() {
+            let mut faceted = self.number_faceted_documents_ids(rtxn, field_id)?;
+            faceted |= self.string_faceted_documents_ids(rtxn, field_id)?;
+            faceted |= self.boolean_faceted_documents_ids(rtxn, field_id, true)?;
+            faceted |= self.boolean_faceted_documents_ids(rtxn, field_id, false)?;
+            faceted -= &stored_ids;
+            if !faceted.is_empty() {
+                report.invalid_faceted_documents.insert(name.to_string(), faceted);
+            }
+        }
+
+        // The words of the words FST must all exist in the word docids databases.
+        let words_fst = self.words_fst(rtxn)?;
+        let mut stream = words_fst.stream();
+        while let Some(word) = stream.next() {
+            let word = std::str::from_utf8(word)?;
+            let exists = self.word_docids.remap_data_type::<DecodeIgnore>().get(rtxn, word)?;
+            let exact_exists =
+                self.exact_word_docids.remap_data_type::<DecodeIgnore>().get(rtxn, word)?;
+            if exists.is_none() && exact_exists.is_none() {
+                report.missing_words.push(word.to_string());
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Lists the prefixes of the prefix databases that are not part of the words prefixes FST,
+    /// e.g. the leftovers of an aborted update, see [`crate::update::DeleteStalePrefixes`].
+    pub fn stale_prefixes(&self, rtxn: &RoTxn) -> Result<StalePrefixes> {
+        let prefixes_fst = self.words_prefixes_fst(rtxn)?;
+        let mut stale = StalePrefixes::default();
+
+        let databases = [
+            (self.word_prefix_docids, &mut stale.word_prefix_docids),
+            (self.exact_word_prefix_docids, &mut stale.exact_word_prefix_docids),
+        ];
+        for (db, stale_prefixes) in databases {
+            for result in db.remap_data_type::<DecodeIgnore>().iter(rtxn)? {
+                let (prefix, ()) = result?;
+                if !prefixes_fst.contains(prefix) {
+                    stale_prefixes.insert(prefix.to_string());
+                }
+            }
+        }
+
+        let db = self.word_prefix_pair_proximity_docids.remap_data_type::<DecodeIgnore>();
+        for result in db.iter(rtxn)? {
+            let ((_word, prefix, _proximity), ()) = result?;
+            if !prefixes_fst.contains(prefix) {
+                stale.word_prefix_pair_proximity_docids.insert(prefix.to_string());
+            }
+        }
+
+        let db = self.word_prefix_position_docids.remap_data_type::<DecodeIgnore>();
+        for result in db.iter(rtxn)? {
+            let ((prefix, _position), ()) = result?;
+            if !prefixes_fst.contains(prefix) {
+                stale.word_prefix_position_docids.insert(prefix.to_string());
+            }
+        }
+
+        Ok(stale)
+    }
+
+    /* warm up */
+
+    /// Reads the pages of the index used by most of the searches to load them in the page
+    /// cache of the OS, the first searches after opening a cold index then don't wait for
+    /// the disk. Returns the number of bytes read.
+    pub fn warm_up(&self, rtxn: &RoTxn, effort: WarmUpEffort) -> Result<u64> {
+        let mut read_bytes = 0;
+
+        for key in [main_key::WORDS_FST_KEY, main_key::WORDS_PREFIXES_FST_KEY] {
+            if let Some(bytes) = self.main.get::<_, Str, ByteSlice>(rtxn, key)? {
+                read_bytes += touch_pages(bytes);
+            }
+        }
+
+        if effort >= WarmUpEffort::FacetLevels {
+            for field_id in self.faceted_fields_ids(rtxn)? {
+                let left = (field_id, 1, f64::MIN, f64::MIN);
+                let right = (field_id, u8::MAX, f64::MAX, f64::MAX);
+                let db = self.facet_id_f64_docids.remap_data_type::<ByteSlice>();
+                for result in db.range(rtxn, &(left..=right))? {
+                    let (_, bytes) = result?;
+                    read_bytes += touch_pages(bytes);
+                }
+
+                let left = (field_id, NonZeroU8::new(1).unwrap(), u32::MIN, u32::MIN);
+                let right = (field_id, NonZeroU8::new(u8::MAX).unwrap(), u32::MAX, u32::MAX);
+                let db =
+                    self.facet_id_string_docids.remap_types::<FacetLevelValueU32Codec, ByteSlice>();
+                for result in db.range(rtxn, &(left..=right))? {
+                    let (_, bytes) = result?;
+                    read_bytes += touch_pages(bytes);
+                }
+            }
+        }
+
+        if effort >= WarmUpEffort::WordsDocids {
+            for db in [
+                self.word_docids,
+                self.exact_word_docids,
+                self.word_prefix_docids,
+                self.exact_word_prefix_docids,
+            ] {
+                for result in db.remap_data_type::<ByteSlice>().iter(rtxn)? {
+                    let (_, bytes) = result?;
+                    read_bytes += touch_pages(bytes);
+                }
+            }
+        }
+
+        Ok(read_bytes)
+    }
+}
+
+/// The parts of the index read by [`Index::warm_up`], each effort reads the parts of the
+/// previous ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum WarmUpEffort {
+    /// The words and prefixes FSTs, used by every search with a query.
+    Fsts,
+    /// The facet levels above the level zero, used by the filters on ranges.
+    FacetLevels,
+    /// The documents ids of the words and prefixes, large on big indexes.
+    WordsDocids,
+}
+
+/// Reads a byte of every page of the memory mapped bytes, returns the number of bytes.
+fn touch_pages(bytes: &[u8]) -> u64 {
+    const PAGE_SIZE: usize = 4096;
+    for byte in bytes.iter().step_by(PAGE_SIZE).chain(bytes.last()) {
+        // safety: the reference is valid, the volatile read is only there to not be optimized out.
+        unsafe { std::ptr::read_volatile(byte) };
+    }
+    bytes.len() as u64
+}
+
+/// The key of the documents ids faceted with a boolean, the prefix followed
+/// by the field id and the boolean as a byte.
+fn boolean_faceted_documents_ids_key(field_id: FieldId, value: bool) -> Vec<u8> {
+    let prefix = main_key::BOOLEAN_FACETED_DOCUMENTS_IDS_PREFIX.as_bytes();
+    let mut key = Vec::with_capacity(prefix.len() + size_of::<FieldId>() + 1);
+    key.extend_from_slice(prefix);
+    key.extend_from_slice(&field_id.to_be_bytes());
+    key.push(value as u8);
+    key
+}
+
+/// The broken invariants found by [`Index::verify`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IndexVerificationReport {
+    /// The documents ids that don't have a stored document.
+    pub missing_documents: RoaringBitmap,
+    /// The stored documents that are not part of the documents ids.
+    pub unlisted_documents: RoaringBitmap,
+    /// The external ids that point to an unknown document id.
+    pub dangling_external_ids: Vec<String>,
+    /// The external ids that are not the primary key of the document they point to.
+    pub mismatched_external_ids: Vec<String>,
+    /// The documents ids that are not reachable from any external id.
+    pub documents_without_external_id: RoaringBitmap,
+    /// The faceted documents ids that are not part of the documents ids, by field.
+    pub invalid_faceted_documents: BTreeMap<String, RoaringBitmap>,
+    /// The words of the words FST that are missing from the word docids databases.
+    pub missing_words: Vec<String>,
+}
+
+impl IndexVerificationReport {
+    /// Returns `true` if no broken invariant was found.
+    pub fn is_valid(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// The types of the values indexed for a filterable field,
+/// returned by [`Index::filterable_fields_with_types`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilterableFieldTypes {
+    /// Some documents have numbers in this field.
+    pub number: bool,
+    /// Some documents have strings in this field.
+    pub string: bool,
+    /// Some documents have booleans in this field.
+    pub boolean: bool,
+    /// Some documents have a geo point, only set for the `_geo` field.
+    pub geo: bool,
+}
+
+/// A word of the index returned by [`Index::closest_words`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClosestWord {
+    pub word: String,
+    /// The number of typos between the requested word and this word.
+    pub typos: u8,
+    /// The number of documents that contain this word.
+    pub documents_count: u64,
+}
+
+/// The prefixes found by [`Index::stale_prefixes`] in each prefix database.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StalePrefixes {
+    pub word_prefix_docids: BTreeSet<String>,
+    pub exact_word_prefix_docids: BTreeSet<String>,
+    pub word_prefix_pair_proximity_docids: BTreeSet<String>,
+    pub word_prefix_position_docids: BTreeSet<String>,
+}
+
+impl StalePrefixes {
+    /// Returns `true` if no stale prefix was found.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
 }
 
 #[cfg(test)]
 pub(crate) mod tests {
     use std::ops::Deref;
 
+    use big_s::S;
     use heed::EnvOpenOptions;
-    use maplit::btreemap;
+    use maplit::{btreemap, hashset};
     use tempfile::TempDir;
 
+    use crate::facet::FacetValue;
+    use crate::index::FilterableFieldTypes;
     use crate::index::{DEFAULT_MIN_WORD_LEN_ONE_TYPO, DEFAULT_MIN_WORD_LEN_TWO_TYPOS};
-    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig};
-    use crate::Index;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+    use crate::{DocumentId, Filter, Index, WarmUpEffort, BEU32};
 
     pub(crate) struct TempIndex {
         inner: Index,
@@ -1184,4 +2413,369 @@ pub(crate) mod tests {
         assert_eq!(index.min_word_len_one_typo(&txn).unwrap(), 3);
         assert_eq!(index.min_word_len_two_typos(&txn).unwrap(), 15);
     }
+
+    #[test]
+    fn put_and_retrieve_transposition_counts_as_one_typo() {
+        let index = TempIndex::new();
+        let mut txn = index.write_txn().unwrap();
+        // default value is true
+        assert!(index.transposition_counts_as_one_typo(&txn).unwrap());
+        index.put_transposition_counts_as_one_typo(&mut txn, false).unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        assert!(!index.transposition_counts_as_one_typo(&txn).unwrap());
+    }
+
+    #[test]
+    fn generation_tracks_the_writes() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let rtxn = index.read_txn().unwrap();
+        let generation = index.generation(&rtxn).unwrap();
+        assert!(!index.changes_since(&rtxn, generation).unwrap());
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "id": 1, "name": "kevin" }]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.changes_since(&rtxn, generation).unwrap());
+        let generation = index.generation(&rtxn).unwrap();
+        drop(rtxn);
+
+        // an aborted update doesn't change the generation.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("name") });
+        builder.execute(|_| ()).unwrap();
+        wtxn.abort().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(!index.changes_since(&rtxn, generation).unwrap());
+    }
+
+    #[test]
+    fn reader_slots_exhaustion() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new_with_max_readers(options, &path, 1).unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let error = crate::Error::from(index.read_txn().unwrap_err());
+        assert!(matches!(error, crate::Error::UserError(crate::UserError::MaxReadersReached)));
+
+        // the slot is released with the transaction.
+        drop(rtxn);
+        index.read_txn().unwrap();
+    }
+
+    #[test]
+    fn warm_up_reads_the_index() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.warm_up(&rtxn, WarmUpEffort::WordsDocids).unwrap(), 0);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("age") });
+        builder.execute(|_| ()).unwrap();
+        let content = documents!([
+            { "id": 1, "name": "kevin", "age": 20 },
+            { "id": 2, "name": "kevina", "age": 30 },
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let fsts = index.warm_up(&rtxn, WarmUpEffort::Fsts).unwrap();
+        assert!(fsts > 0);
+        let words_docids = index.warm_up(&rtxn, WarmUpEffort::WordsDocids).unwrap();
+        assert!(words_docids > fsts);
+    }
+
+    #[test]
+    fn verify_index_invariants() {
+        let index = TempIndex::new();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "name": "kevin" },
+            { "id": "2", "name": "bob" },
+        ]);
+        let config = IndexerConfig::default();
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let report = index.verify(&rtxn).unwrap();
+        assert!(report.is_valid(), "{:?}", report);
+        drop(rtxn);
+
+        // We corrupt the index by removing a document and a word.
+        let mut wtxn = index.write_txn().unwrap();
+        index.documents.delete(&mut wtxn, &BEU32::new(0)).unwrap();
+        index.word_docids.delete(&mut wtxn, "bob").unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let report = index.verify(&rtxn).unwrap();
+        assert!(!report.is_valid());
+        assert_eq!(report.missing_documents.iter().collect::<Vec<_>>(), vec![0]);
+        assert_eq!(report.missing_words, vec![S("bob")]);
+        assert!(report.dangling_external_ids.is_empty());
+    }
+
+    #[test]
+    fn all_documents_sorted_by_external_id() {
+        let index = TempIndex::new();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": "c", "name": "kevin" },
+            { "id": "a", "name": "bob" },
+            { "id": "b", "name": "alice" },
+        ]);
+        let config = IndexerConfig::default();
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // we add a document in a second batch so that it lives in the soft fst.
+        let content = documents!([{ "id": "aa", "name": "tamo" }]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let id_field = fields_ids_map.id("id").unwrap();
+        let ids: Vec<_> = index
+            .all_documents_sorted_by_external_id(&rtxn)
+            .unwrap()
+            .map(|result| {
+                let (_docid, obkv) = result.unwrap();
+                serde_json::from_slice::<String>(obkv.get(id_field).unwrap()).unwrap()
+            })
+            .collect();
+        assert_eq!(ids, vec![S("a"), S("aa"), S("b"), S("c")]);
+    }
+
+    #[test]
+    fn browse_documents() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("age") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 1, "name": "kevin", "age": 20 },
+            { "id": 2, "name": "bob", "age": 30 },
+            { "id": 3, "name": "alice", "age": 40 },
+            { "id": 4, "name": "tamo", "age": 50 },
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let docids = |documents: Vec<(DocumentId, obkv::KvReaderU16)>| {
+            documents.into_iter().map(|(id, _)| id).collect::<Vec<_>>()
+        };
+
+        let documents = index.browse_documents(&rtxn, 1, 2, None).unwrap();
+        assert_eq!(docids(documents), vec![1, 2]);
+
+        let filter = Filter::from_str("age >= 30").unwrap().unwrap();
+        let documents = index.browse_documents(&rtxn, 0, 10, Some(&filter)).unwrap();
+        assert_eq!(docids(documents), vec![1, 2, 3]);
+
+        let documents = index.browse_documents(&rtxn, 2, 10, Some(&filter)).unwrap();
+        assert_eq!(docids(documents), vec![3]);
+    }
+
+    #[test]
+    fn closest_words() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "text": "hello hullo" },
+            { "id": 2, "text": "hallo help" },
+            { "id": 3, "text": "hallo world" },
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let words = |word, max_typos, limit| {
+            let words = index.closest_words(&rtxn, word, max_typos, limit).unwrap();
+            words.into_iter().map(|w| (w.word, w.typos, w.documents_count)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(words("hello", 0, 10), vec![(S("hello"), 0, 1)]);
+        assert_eq!(
+            words("hello", 1, 10),
+            vec![(S("hello"), 0, 1), (S("hallo"), 1, 2), (S("hullo"), 1, 1)]
+        );
+        assert_eq!(
+            words("hello", 2, 10),
+            vec![(S("hello"), 0, 1), (S("hallo"), 1, 2), (S("hullo"), 1, 1), (S("help"), 2, 1)]
+        );
+        assert_eq!(words("hello", 2, 2), vec![(S("hello"), 0, 1), (S("hallo"), 1, 2)]);
+        assert!(words("", 2, 10).is_empty());
+    }
+
+    #[test]
+    fn facet_values_of() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("genres"), S("year") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 1, "title": "Dune", "genres": [" Science Fiction", "Adventure"], "year": 1965 },
+            { "id": 2, "title": "Emma", "genres": "Romance" },
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let values = index.facet_values_of(&rtxn, 0, "genres").unwrap();
+        assert_eq!(
+            values,
+            vec![FacetValue::from("adventure"), FacetValue::from("science fiction")]
+        );
+        let values = index.facet_values_of(&rtxn, 0, "year").unwrap();
+        assert_eq!(values, vec![FacetValue::from(1965.0)]);
+        let values = index.facet_values_of(&rtxn, 1, "genres").unwrap();
+        assert_eq!(values, vec![FacetValue::from("romance")]);
+
+        // the fields that are unknown, not faceted or missing from the document have no values
+        assert!(index.facet_values_of(&rtxn, 1, "year").unwrap().is_empty());
+        assert!(index.facet_values_of(&rtxn, 0, "title").unwrap().is_empty());
+        assert!(index.facet_values_of(&rtxn, 0, "unknown").unwrap().is_empty());
+    }
+
+    #[test]
+    fn filterable_fields_with_types() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(
+            hashset! { S("genres"), S("year"), S("release"), S("_geo"), S("unknown") },
+        );
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 1, "genres": ["Science Fiction", "Adventure"], "year": 1965, "release": "1965" },
+            { "id": 2, "genres": "Romance", "release": 1815, "_geo": { "lat": 51.5, "lng": -0.1 } },
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let types = index.filterable_fields_with_types(&rtxn).unwrap();
+        let string = FilterableFieldTypes { string: true, ..Default::default() };
+        let number = FilterableFieldTypes { number: true, ..Default::default() };
+        let geo = FilterableFieldTypes { geo: true, ..Default::default() };
+        let both = FilterableFieldTypes { number: true, string: true, ..Default::default() };
+        assert_eq!(
+            types,
+            btreemap! {
+                S("_geo") => geo,
+                S("genres") => string,
+                S("release") => both,
+                S("unknown") => FilterableFieldTypes::default(),
+                S("year") => number,
+            }
+        );
+    }
 }
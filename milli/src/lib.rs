@@ -7,15 +7,24 @@ mod error;
 mod external_documents_ids;
 pub mod facet;
 mod fields_ids_map;
+mod geo_distance;
+mod hashed_fields;
 pub mod heed_codec;
 pub mod index;
+mod normalization;
+pub mod posting_lists;
 pub mod proximity;
+mod query_rewrite;
 mod search;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 pub mod update;
+mod word_boundaries;
 
 use std::collections::{BTreeMap, HashMap};
 use std::convert::{TryFrom, TryInto};
 use std::hash::BuildHasherDefault;
+use std::num::NonZeroU32;
 
 pub use filter_parser::{Condition, FilterCondition};
 use fxhash::{FxHasher32, FxHasher64};
@@ -28,18 +37,28 @@ pub use self::criterion::{default_criteria, Criterion, CriterionError};
 pub use self::error::{
     Error, FieldIdMapMissingEntry, InternalError, SerializationError, UserError,
 };
-pub use self::external_documents_ids::ExternalDocumentsIds;
+pub use self::external_documents_ids::{ExternalDocumentsIds, SortedInternalIds};
 pub use self::fields_ids_map::FieldsIdsMap;
+pub use self::geo_distance::GeoDistanceFormula;
 pub use self::heed_codec::{
     BEU32StrCodec, BoRoaringBitmapCodec, BoRoaringBitmapLenCodec, CboRoaringBitmapCodec,
-    CboRoaringBitmapLenCodec, FieldIdWordCountCodec, ObkvCodec, RoaringBitmapCodec,
-    RoaringBitmapLenCodec, StrBEU32Codec, StrStrU8Codec,
+    CboRoaringBitmapLenCodec, DocIdFieldIdCodec, F32VectorCodec, FieldIdWordCountCodec, ObkvCodec,
+    RoaringBitmapCodec, RoaringBitmapLenCodec, StrBEU32Codec, StrStrU8Codec,
 };
-pub use self::index::Index;
+pub use self::index::{
+    ClosestWord, FilterableFieldTypes, Index, IndexVerificationReport, StalePrefixes, WarmUpEffort,
+};
+pub use self::normalization::NormalizerOptions;
+pub use self::query_rewrite::QueryRewrite;
 pub use self::search::{
-    FacetDistribution, Filter, FormatOptions, MatchBounds, MatcherBuilder, MatchingWord,
-    MatchingWords, Search, SearchResult,
+    word_derivations, FacetDistribution, FacetDistributionResult, FacetDistributionSampling,
+    FacetNumberIter, FacetStringIter, Filter, FormatOptions, MandatoryFilter, MatchBounds,
+    MatcherBuilder, MatcherFormatter, MatchingWord, MatchingWords, NormalizedQuery,
+    NumberComparison, Operation, OrderBy, PlaceholderSearchCache, QueryToken, QueryTokenization,
+    QueryTreeStats, RestrictedIndex, ScoreDetails, Search, SearchObserver, SearchResult,
+    SearchSession, SearchSettingsSnapshot, WordDerivationsCache, WordPairProximity,
 };
+pub use self::word_boundaries::WordBoundaries;
 
 pub type Result<T> = std::result::Result<T, error::Error>;
 
@@ -77,6 +96,19 @@ pub fn absolute_from_relative_position(field_id: FieldId, relative: RelativePosi
     (field_id as u32) << 16 | (relative as u32)
 }
 
+// Round the relative part of an absolute word position down to a multiple of the precision,
+// the positions are stored like this in the word position databases.
+pub fn round_position(absolute: Position, precision: NonZeroU32) -> Position {
+    match precision.get() {
+        1 => absolute,
+        precision => {
+            let (field_id, relative) = relative_from_absolute_position(absolute);
+            let relative = relative as u32 / precision * precision;
+            absolute_from_relative_position(field_id, relative as u16)
+        }
+    }
+}
+
 /// Transform a raw obkv store into a JSON Object.
 pub fn obkv_to_json(
     displayed_fields: &[FieldId],
@@ -186,6 +218,13 @@ pub fn lat_lng_to_xyz(coord: &[f64; 2]) -> [f64; 3] {
     [x, y, z]
 }
 
+/// The faceted field that facets every field of the documents, e.g. for exploratory datasets.
+pub const ALL_FIELDS_FACET: &str = "*";
+
+/// The faceted field derived from the content hash fields, it stores the hash of the values of
+/// these fields so that the documents with the same content can be deduplicated at search time.
+pub const CONTENT_HASH_FIELD: &str = "_contentHash";
+
 /// Returns `true` if the field match one of the faceted fields.
 /// See the function [`is_faceted_by`] below to see what “matching” means.
 pub fn is_faceted(field: &str, faceted_fields: impl IntoIterator<Item = impl AsRef<str>>) -> bool {
@@ -203,6 +242,10 @@ pub fn is_faceted(field: &str, faceted_fields: impl IntoIterator<Item = impl AsR
 /// assert!(is_faceted_by("animaux.chien.race.bouvier bernois.fourrure.couleur", "animaux.chien.race.bouvier bernois"));
 /// assert!(is_faceted_by("animaux.chien.race.bouvier bernois.fourrure.couleur", "animaux.chien.race.bouvier bernois.fourrure"));
 /// assert!(is_faceted_by("animaux.chien.race.bouvier bernois.fourrure.couleur", "animaux.chien.race.bouvier bernois.fourrure.couleur"));
+/// assert!(is_faceted_by("animaux[0]", "animaux"));
+/// assert!(is_faceted_by("animaux[0].chien", "animaux"));
+/// // -- the wildcard facets every field
+/// assert!(is_faceted_by("animaux.chien", "*"));
 ///
 /// // -- the wrongs
 /// assert!(!is_faceted_by("chien", "chat"));
@@ -217,10 +260,35 @@ pub fn is_faceted(field: &str, faceted_fields: impl IntoIterator<Item = impl AsR
 /// assert!(!is_faceted_by("animaux.chien", "animaux.ch"));
 /// assert!(!is_faceted_by("animaux.chien", "animaux.chi"));
 /// assert!(!is_faceted_by("animaux.chien", "animaux.chie"));
+/// assert!(!is_faceted_by("animaux[0]", "animaux[1]"));
 /// ```
 pub fn is_faceted_by(field: &str, facet: &str) -> bool {
-    field.starts_with(facet)
-        && field[facet.len()..].chars().next().map(|c| c == '.').unwrap_or(true)
+    facet == ALL_FIELDS_FACET
+        || field.starts_with(facet)
+            && field[facet.len()..].chars().next().map(|c| c == '.' || c == '[').unwrap_or(true)
+}
+
+/// Returns `true` if the field stores a vector, i.e. it is the `_vectors` field or one of its sub-fields.
+pub fn is_vector_field(field: &str) -> bool {
+    is_faceted_by(field, "_vectors")
+}
+
+/// Returns the name of the field derived from the position of a value in an array,
+/// e.g. `authors[0]` for the first value of the `authors` field.
+pub fn array_position_field_name(field: &str, position: usize) -> String {
+    format!("{}[{}]", field, position)
+}
+
+/// Returns `true` if the field is derived from the position of a value in one of the
+/// positional array fields, e.g. `authors[0]` or `authors[0].name` for the `authors` field.
+pub fn is_array_position_field(
+    field: &str,
+    positional_fields: impl IntoIterator<Item = impl AsRef<str>>,
+) -> bool {
+    positional_fields.into_iter().any(|positional| {
+        let positional = positional.as_ref();
+        field.starts_with(positional) && field[positional.len()..].starts_with('[')
+    })
 }
 
 #[cfg(test)]
@@ -0,0 +1,82 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+/// The language specific normalizations applied to the words, in addition to the ones of the
+/// analyzer, both when the documents are indexed and when the queries are analyzed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NormalizerOptions {
+    /// Removes the Arabic diacritics (harakat) and the tatweel, `كَتَبَ` gives `كتب`.
+    pub arabic_diacritics: bool,
+    /// Folds the German sharp s into a double s, `straße` gives `strasse`.
+    pub german_eszett: bool,
+    /// Folds the Greek final sigma into a regular sigma, `λόγος` gives `λόγοσ`.
+    pub greek_final_sigma: bool,
+}
+
+impl NormalizerOptions {
+    pub fn is_enabled(&self) -> bool {
+        self.arabic_diacritics || self.german_eszett || self.greek_final_sigma
+    }
+
+    /// Applies the enabled normalizations to an analyzed word.
+    ///
+    /// The pinned analyzer doesn't expose its normalizers, the words are therefore
+    /// normalized after the analysis, the word is borrowed when it is left unchanged.
+    pub fn normalize<'a>(&self, word: &'a str) -> Cow<'a, str> {
+        if !word.chars().any(|c| self.is_normalized(c)) {
+            return Cow::Borrowed(word);
+        }
+
+        let mut normalized = String::with_capacity(word.len());
+        for c in word.chars() {
+            match c {
+                c if self.arabic_diacritics && is_arabic_diacritic(c) => (),
+                'ß' | 'ẞ' if self.german_eszett => normalized.push_str("ss"),
+                'ς' if self.greek_final_sigma => normalized.push('σ'),
+                c => normalized.push(c),
+            }
+        }
+
+        Cow::Owned(normalized)
+    }
+
+    fn is_normalized(&self, c: char) -> bool {
+        (self.arabic_diacritics && is_arabic_diacritic(c))
+            || (self.german_eszett && matches!(c, 'ß' | 'ẞ'))
+            || (self.greek_final_sigma && c == 'ς')
+    }
+}
+
+/// The tatweel, the harakat and the superscript alef.
+fn is_arabic_diacritic(c: char) -> bool {
+    matches!(c, '\u{0640}' | '\u{064B}'..='\u{065F}' | '\u{0670}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_words() {
+        let all = NormalizerOptions {
+            arabic_diacritics: true,
+            german_eszett: true,
+            greek_final_sigma: true,
+        };
+        assert_eq!(all.normalize("كَتَبَ"), "كتب");
+        assert_eq!(all.normalize("كـــتب"), "كتب");
+        assert_eq!(all.normalize("straße"), "strasse");
+        assert_eq!(all.normalize("λόγος"), "λόγοσ");
+        assert!(matches!(all.normalize("hello"), Cow::Borrowed("hello")));
+
+        let greek = NormalizerOptions { greek_final_sigma: true, ..Default::default() };
+        assert_eq!(greek.normalize("λόγος"), "λόγοσ");
+        assert_eq!(greek.normalize("straße"), "straße");
+
+        let none = NormalizerOptions::default();
+        assert!(!none.is_enabled());
+        assert_eq!(none.normalize("كَتَبَ"), "كَتَبَ");
+    }
+}
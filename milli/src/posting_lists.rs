@@ -0,0 +1,204 @@
+//! Exports the posting lists of an index in the portable Roaring format, the serialization
+//! format shared by the Roaring implementations (C, Java, Go...), so that other systems can
+//! consume them without knowing the codecs used to store them in LMDB.
+//!
+//! An export is a sequence of records, one by database entry, in the order of the keys:
+//! the length of the key as a big-endian `u32`, the raw LMDB key, the length of the bitmap
+//! as a big-endian `u32` and the bitmap in the portable format.
+
+use std::io::{self, Read, Write};
+
+use heed::types::ByteSlice;
+use heed::{BytesDecode, Database};
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+
+use crate::{Index, Result};
+
+/// The databases of an index that map a key to a posting list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PostingListsDatabase {
+    WordDocids,
+    ExactWordDocids,
+    WordPrefixDocids,
+    ExactWordPrefixDocids,
+    WordPairProximityDocids,
+    WordPrefixPairProximityDocids,
+    WordPositionDocids,
+    WordPrefixPositionDocids,
+    FieldIdWordCountDocids,
+    FacetIdF64Docids,
+}
+
+/// Serializes a bitmap in the portable Roaring format.
+pub fn to_portable_bytes(bitmap: &RoaringBitmap) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(bitmap.serialized_size());
+    // Writing into a vector never fails.
+    bitmap.serialize_into(&mut bytes).unwrap();
+    bytes
+}
+
+/// Deserializes a bitmap written in the portable Roaring format, e.g. by another system.
+pub fn from_portable_bytes(bytes: &[u8]) -> io::Result<RoaringBitmap> {
+    RoaringBitmap::deserialize_from(bytes)
+}
+
+/// Writes all the posting lists of the database into the writer, returns the number of records.
+pub fn export_posting_lists<W: Write>(
+    index: &Index,
+    rtxn: &heed::RoTxn,
+    database: PostingListsDatabase,
+    writer: W,
+) -> Result<u64> {
+    use PostingListsDatabase::*;
+
+    match database {
+        WordDocids => export_database(rtxn, index.word_docids.remap_key_type(), writer),
+        ExactWordDocids => export_database(rtxn, index.exact_word_docids.remap_key_type(), writer),
+        WordPrefixDocids => {
+            export_database(rtxn, index.word_prefix_docids.remap_key_type(), writer)
+        }
+        ExactWordPrefixDocids => {
+            export_database(rtxn, index.exact_word_prefix_docids.remap_key_type(), writer)
+        }
+        WordPairProximityDocids => {
+            export_database(rtxn, index.word_pair_proximity_docids.remap_key_type(), writer)
+        }
+        WordPrefixPairProximityDocids => {
+            export_database(rtxn, index.word_prefix_pair_proximity_docids.remap_key_type(), writer)
+        }
+        WordPositionDocids => {
+            export_database(rtxn, index.word_position_docids.remap_key_type(), writer)
+        }
+        WordPrefixPositionDocids => {
+            export_database(rtxn, index.word_prefix_position_docids.remap_key_type(), writer)
+        }
+        FieldIdWordCountDocids => {
+            export_database(rtxn, index.field_id_word_count_docids.remap_key_type(), writer)
+        }
+        FacetIdF64Docids => {
+            export_database(rtxn, index.facet_id_f64_docids.remap_key_type(), writer)
+        }
+    }
+}
+
+fn export_database<C, W>(
+    rtxn: &heed::RoTxn,
+    db: Database<ByteSlice, C>,
+    mut writer: W,
+) -> Result<u64>
+where
+    C: for<'a> BytesDecode<'a, DItem = RoaringBitmap>,
+    W: Write,
+{
+    let mut count = 0;
+    let mut buffer = Vec::new();
+    for result in db.iter(rtxn)? {
+        let (key, bitmap) = result?;
+        buffer.clear();
+        bitmap.serialize_into(&mut buffer)?;
+
+        writer.write_all(&(key.len() as u32).to_be_bytes())?;
+        writer.write_all(key)?;
+        writer.write_all(&(buffer.len() as u32).to_be_bytes())?;
+        writer.write_all(&buffer)?;
+        count += 1;
+    }
+
+    writer.flush()?;
+    Ok(count)
+}
+
+/// Reads the records of an export, the raw keys and their posting lists.
+pub struct PostingListsReader<R> {
+    reader: R,
+}
+
+impl<R: Read> PostingListsReader<R> {
+    pub fn new(reader: R) -> PostingListsReader<R> {
+        PostingListsReader { reader }
+    }
+
+    fn read_record(&mut self) -> io::Result<Option<(Vec<u8>, RoaringBitmap)>> {
+        let mut len = [0; 4];
+        match self.reader.read_exact(&mut len) {
+            Ok(()) => (),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        let mut key = vec![0; u32::from_be_bytes(len) as usize];
+        self.reader.read_exact(&mut key)?;
+        self.reader.read_exact(&mut len)?;
+        let mut bytes = vec![0; u32::from_be_bytes(len) as usize];
+        self.reader.read_exact(&mut bytes)?;
+
+        Ok(Some((key, from_portable_bytes(&bytes)?)))
+    }
+}
+
+impl<R: Read> Iterator for PostingListsReader<R> {
+    type Item = io::Result<(Vec<u8>, RoaringBitmap)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::tests::TempIndex;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig};
+
+    #[test]
+    fn export_and_read_posting_lists() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "kevin" },
+            { "id": 1, "name": "kevina" },
+            { "id": 2, "name": "kevin bob" },
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut export = Vec::new();
+        let count =
+            export_posting_lists(&index, &rtxn, PostingListsDatabase::WordDocids, &mut export)
+                .unwrap();
+        assert_eq!(count, index.word_docids.len(&rtxn).unwrap() as u64);
+
+        let records: Vec<_> =
+            PostingListsReader::new(export.as_slice()).collect::<io::Result<_>>().unwrap();
+        assert_eq!(records.len() as u64, count);
+        let (_, kevin) = records.iter().find(|(key, _)| key == b"kevin").unwrap();
+        assert_eq!(kevin, &index.word_docids.get(&rtxn, "kevin").unwrap().unwrap());
+
+        // The word position database stores its posting lists with another codec.
+        let mut export = Vec::new();
+        let count = export_posting_lists(
+            &index,
+            &rtxn,
+            PostingListsDatabase::WordPositionDocids,
+            &mut export,
+        )
+        .unwrap();
+        let records = PostingListsReader::new(export.as_slice());
+        assert_eq!(records.count() as u64, count);
+
+        let bitmap: RoaringBitmap = (0..3).collect();
+        assert_eq!(from_portable_bytes(&to_portable_bytes(&bitmap)).unwrap(), bitmap);
+    }
+}
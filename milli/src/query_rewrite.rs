@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+/// What a query rewriting rule does to the words of the query matching its pattern.
+///
+/// The rules are applied before the query tree is built, the patterns and the rewritten
+/// queries are normalized like the synonyms, e.g. `"TV"` matches the query words `tv`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QueryRewrite {
+    /// Replaces the words matching the pattern by these words, an empty query removes them.
+    Replace(String),
+    /// Keeps the words matching the pattern and also matches the documents containing
+    /// these words as a phrase instead, e.g. `"tv"` also matches `"television"`.
+    /// Only the patterns of up to three words are augmented.
+    Augment(String),
+}
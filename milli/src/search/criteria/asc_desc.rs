@@ -1,5 +1,8 @@
+use std::collections::{BinaryHeap, HashMap};
 use std::mem::take;
+use std::ops::Bound::{Included, Unbounded};
 
+use heed::types::DecodeIgnore;
 use itertools::Itertools;
 use log::debug;
 use ordered_float::OrderedFloat;
@@ -7,9 +10,9 @@ use roaring::RoaringBitmap;
 
 use super::{Criterion, CriterionParameters, CriterionResult};
 use crate::search::criteria::{resolve_query_tree, CriteriaBuilder};
-use crate::search::facet::{FacetNumberIter, FacetStringIter};
+use crate::search::facet::{FacetNumberIter, FacetNumberRange, FacetStringIter};
 use crate::search::query_tree::Operation;
-use crate::{FieldId, Index, Result};
+use crate::{DocumentId, FieldId, Index, Result};
 
 /// Threshold on the number of candidates that will make
 /// the system to choose between one algorithm or another.
@@ -20,12 +23,17 @@ pub struct AscDesc<'t> {
     rtxn: &'t heed::RoTxn<'t>,
     field_name: String,
     field_id: Option<FieldId>,
+    /// The values given at search time, used in place of the facet values of the field.
+    external_values: Option<&'t HashMap<DocumentId, f64>>,
     is_ascending: bool,
     query_tree: Option<Operation>,
     candidates: Box<dyn Iterator<Item = heed::Result<RoaringBitmap>> + 't>,
     allowed_candidates: RoaringBitmap,
     bucket_candidates: RoaringBitmap,
     faceted_candidates: RoaringBitmap,
+    /// The number of documents the search needs, when known the first documents are found
+    /// without iterating over the facet levels value by value.
+    top_k: Option<usize>,
     parent: Box<dyn Criterion + 't>,
 }
 
@@ -48,6 +56,55 @@ impl<'t> AscDesc<'t> {
         Self::new(index, rtxn, parent, field_name, false)
     }
 
+    /// Sorts the documents by increasing values given at search time instead of the facet
+    /// values of a field, the documents without value are returned last.
+    pub fn external_asc(
+        index: &'t Index,
+        rtxn: &'t heed::RoTxn,
+        parent: Box<dyn Criterion + 't>,
+        name: String,
+        values: &'t HashMap<DocumentId, f64>,
+    ) -> Self {
+        Self::new_external(index, rtxn, parent, name, values, true)
+    }
+
+    /// Sorts the documents by decreasing values given at search time instead of the facet
+    /// values of a field, the documents without value are returned last.
+    pub fn external_desc(
+        index: &'t Index,
+        rtxn: &'t heed::RoTxn,
+        parent: Box<dyn Criterion + 't>,
+        name: String,
+        values: &'t HashMap<DocumentId, f64>,
+    ) -> Self {
+        Self::new_external(index, rtxn, parent, name, values, false)
+    }
+
+    fn new_external(
+        index: &'t Index,
+        rtxn: &'t heed::RoTxn,
+        parent: Box<dyn Criterion + 't>,
+        name: String,
+        values: &'t HashMap<DocumentId, f64>,
+        is_ascending: bool,
+    ) -> Self {
+        AscDesc {
+            index,
+            rtxn,
+            field_name: name,
+            field_id: None,
+            external_values: Some(values),
+            is_ascending,
+            query_tree: None,
+            candidates: Box::new(std::iter::empty()),
+            allowed_candidates: RoaringBitmap::new(),
+            faceted_candidates: values.keys().copied().collect(),
+            bucket_candidates: RoaringBitmap::new(),
+            top_k: None,
+            parent,
+        }
+    }
+
     fn new(
         index: &'t Index,
         rtxn: &'t heed::RoTxn,
@@ -71,15 +128,24 @@ impl<'t> AscDesc<'t> {
             rtxn,
             field_name,
             field_id,
+            external_values: None,
             is_ascending,
             query_tree: None,
             candidates: Box::new(std::iter::empty()),
             allowed_candidates: RoaringBitmap::new(),
             faceted_candidates,
             bucket_candidates: RoaringBitmap::new(),
+            top_k: None,
             parent,
         })
     }
+
+    /// Only the first `limit` documents will be read, this must only be set when the parent
+    /// criteria return all the candidates in a single bucket, e.g. when the sort is first.
+    pub fn top_k(mut self, limit: Option<usize>) -> Self {
+        self.top_k = limit;
+        self
+    }
 }
 
 impl<'t> Criterion for AscDesc<'t> {
@@ -115,7 +181,8 @@ impl<'t> Criterion for AscDesc<'t> {
                         let mut candidates = match (&self.query_tree, candidates) {
                             (_, Some(candidates)) => candidates,
                             (Some(qt), None) => {
-                                let context = CriteriaBuilder::new(&self.rtxn, &self.index)?;
+                                let mut context = CriteriaBuilder::new(&self.rtxn, &self.index)?;
+                                context.words_position_limit(params.words_position_limit)?;
                                 resolve_query_tree(&context, qt, params.wdcache)?
                             }
                             (None, None) => self.index.documents_ids(self.rtxn)?,
@@ -135,15 +202,24 @@ impl<'t> Criterion for AscDesc<'t> {
                         }
 
                         self.allowed_candidates = &candidates - params.excluded_candidates;
-                        self.candidates = match self.field_id {
-                            Some(field_id) => facet_ordered(
+                        self.candidates = match (self.external_values, self.field_id) {
+                            (Some(values), _) => Box::new(
+                                external_ordered_iter(
+                                    values,
+                                    self.is_ascending,
+                                    candidates & &self.faceted_candidates,
+                                )
+                                .map(Ok),
+                            ),
+                            (None, Some(field_id)) => facet_ordered(
                                 self.index,
                                 self.rtxn,
                                 field_id,
                                 self.is_ascending,
                                 candidates & &self.faceted_candidates,
+                                self.top_k,
                             )?,
-                            None => Box::new(std::iter::empty()),
+                            (None, None) => Box::new(std::iter::empty()),
                         };
                     }
                     None => return Ok(None),
@@ -166,15 +242,22 @@ impl<'t> Criterion for AscDesc<'t> {
 /// Returns an iterator over groups of the given candidates in ascending or descending order.
 ///
 /// It will either use an iterative or a recursive method on the whole facet database depending
-/// on the number of candidates to rank.
+/// on the number of candidates to rank. When only the first `top_k` documents are needed they
+/// are found first and the remaining candidates are ordered afterward, only if requested.
 fn facet_ordered<'t>(
     index: &'t Index,
     rtxn: &'t heed::RoTxn,
     field_id: FieldId,
     is_ascending: bool,
     candidates: RoaringBitmap,
+    top_k: Option<usize>,
 ) -> Result<Box<dyn Iterator<Item = heed::Result<RoaringBitmap>> + 't>> {
-    if candidates.len() <= CANDIDATES_THRESHOLD {
+    if let Some(limit) = top_k.filter(|_| candidates.len() > CANDIDATES_THRESHOLD) {
+        let (groups, remaining) =
+            top_k_facet_number_groups(index, rtxn, field_id, is_ascending, candidates, limit)?;
+        let remaining = facet_ordered(index, rtxn, field_id, is_ascending, remaining, None)?;
+        Ok(Box::new(groups.into_iter().map(Ok).chain(remaining)))
+    } else if candidates.len() <= CANDIDATES_THRESHOLD {
         let number_iter = iterative_facet_number_ordered_iter(
             index,
             rtxn,
@@ -206,6 +289,81 @@ fn facet_ordered<'t>(
     }
 }
 
+/// Returns the groups of candidates with the lowest, or highest, facet numbers until at least
+/// `limit` documents are found, along with the remaining candidates.
+///
+/// The facet levels are explored best-first with a heap: a group of a level is only expanded
+/// when its bound is the best one left, the groups that can't contain the first documents
+/// are never read.
+fn top_k_facet_number_groups(
+    index: &Index,
+    rtxn: &heed::RoTxn,
+    field_id: FieldId,
+    is_ascending: bool,
+    mut candidates: RoaringBitmap,
+    limit: usize,
+) -> heed::Result<(Vec<RoaringBitmap>, RoaringBitmap)> {
+    let db = index.facet_id_f64_docids;
+    let highest_level = db
+        .remap_data_type::<DecodeIgnore>()
+        .get_lower_than_or_equal_to(rtxn, &(field_id, u8::MAX, f64::MAX, f64::MAX))?
+        .and_then(|((id, level, _, _), _)| if id == field_id { Some(level) } else { None });
+    let highest_level = match highest_level {
+        Some(level) => level,
+        None => return Ok((Vec::new(), candidates)),
+    };
+
+    // The heap is a max-heap, the groups are ordered by their left bound negated
+    // when ascending and by their right bound when descending.
+    let key = |left: f64, right: f64| OrderedFloat(if is_ascending { -left } else { right });
+    let mut groups = Vec::new();
+    let mut heap = BinaryHeap::new();
+    for result in FacetNumberRange::new(rtxn, db, field_id, highest_level, Unbounded, Unbounded)? {
+        let ((_, level, left, right), docids) = result?;
+        let docids = docids & &candidates;
+        if !docids.is_empty() {
+            heap.push((key(left, right), groups.len()));
+            groups.push(Some((level, left, right, docids)));
+        }
+    }
+
+    let mut top = Vec::new();
+    let mut found = 0;
+    while found < limit {
+        let (level, left, right, mut docids) = match heap.pop() {
+            Some((_, i)) => match groups[i].take() {
+                Some(group) => group,
+                None => continue,
+            },
+            None => break,
+        };
+
+        // The documents with multiple values have maybe already been returned.
+        docids &= &candidates;
+        if docids.is_empty() {
+            continue;
+        }
+
+        if level == 0 {
+            candidates -= &docids;
+            found += docids.len() as usize;
+            top.push(docids);
+        } else {
+            let (left, right) = (Included(left), Included(right));
+            for result in FacetNumberRange::new(rtxn, db, field_id, level - 1, left, right)? {
+                let ((_, level, left, right), sub_docids) = result?;
+                let sub_docids = sub_docids & &docids;
+                if !sub_docids.is_empty() {
+                    heap.push((key(left, right), groups.len()));
+                    groups.push(Some((level, left, right, sub_docids)));
+                }
+            }
+        }
+    }
+
+    Ok((top, candidates))
+}
+
 /// Fetch the whole list of candidates facet number values one by one and order them by it.
 ///
 /// This function is fast when the amount of candidates to rank is small.
@@ -287,3 +445,109 @@ fn iterative_facet_string_ordered_iter<'t>(
 
     Ok(vec.into_iter())
 }
+
+/// Orders the candidates by the values given at search time.
+fn external_ordered_iter(
+    values: &HashMap<DocumentId, f64>,
+    is_ascending: bool,
+    candidates: RoaringBitmap,
+) -> impl Iterator<Item = RoaringBitmap> {
+    let mut docids_values: Vec<_> = candidates
+        .iter()
+        .filter_map(|docid| values.get(&docid).map(|value| (docid, OrderedFloat(*value))))
+        .collect();
+    docids_values.sort_unstable_by_key(|(_, v)| *v);
+    let iter = docids_values.into_iter();
+    let iter = if is_ascending {
+        Box::new(iter) as Box<dyn Iterator<Item = _>>
+    } else {
+        Box::new(iter.rev())
+    };
+
+    // The itertools GroupBy iterator doesn't provide an owned version, we are therefore
+    // required to collect the result into an owned collection (a Vec).
+    let vec: Vec<_> = iter
+        .group_by(|(_, v)| *v)
+        .into_iter()
+        .map(|(_, ids)| ids.map(|(id, _)| id).collect())
+        .collect();
+
+    vec.into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use maplit::hashset;
+
+    use super::*;
+    use crate::documents::{DocumentBatchBuilder, DocumentBatchReader};
+    use crate::index::tests::TempIndex;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+
+    #[test]
+    fn top_k_facet_number_groups_are_ordered() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_sortable_fields(hashset! { S("price") });
+        builder.execute(|_| ()).unwrap();
+
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        let mut batch = DocumentBatchBuilder::new(&mut cursor).unwrap();
+        for id in 0..300 {
+            let document = match id % 10 {
+                0 => serde_json::json!({ "id": id }),
+                1 => serde_json::json!({ "id": id, "price": [id % 50, (id * 3) % 50] }),
+                _ => serde_json::json!({ "id": id, "price": (id * 7) % 50 }),
+            };
+            batch.extend_from_json(serde_json::to_vec(&document).unwrap().as_slice()).unwrap();
+        }
+        batch.finish().unwrap();
+        cursor.set_position(0);
+        let content = DocumentBatchReader::from_reader(cursor).unwrap();
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let field_id = index.fields_ids_map(&rtxn).unwrap().id("price").unwrap();
+        let candidates = index.documents_ids(&rtxn).unwrap();
+        for is_ascending in [true, false] {
+            let facet_fn = if is_ascending {
+                FacetNumberIter::new_reducing
+            } else {
+                FacetNumberIter::new_reverse_reducing
+            };
+            let (groups, remaining) = top_k_facet_number_groups(
+                &index,
+                &rtxn,
+                field_id,
+                is_ascending,
+                candidates.clone(),
+                20,
+            )
+            .unwrap();
+            assert!(groups.iter().map(|docids| docids.len()).sum::<u64>() >= 20);
+
+            let expected: Vec<_> = facet_fn(&rtxn, &index, field_id, candidates.clone())
+                .unwrap()
+                .map(|result| result.unwrap().1)
+                .take(groups.len())
+                .collect();
+            assert_eq!(groups, expected);
+
+            let returned = groups.iter().fold(RoaringBitmap::new(), |acc, docids| acc | docids);
+            assert_eq!(remaining, &candidates - &returned);
+        }
+    }
+}
@@ -9,8 +9,10 @@ use roaring::RoaringBitmap;
 use super::{resolve_query_tree, Context, Criterion, CriterionParameters, CriterionResult};
 use crate::search::criteria::Query;
 use crate::search::query_tree::{Operation, QueryKind};
-use crate::search::{build_dfa, word_derivations, WordDerivationsCache};
-use crate::Result;
+use crate::search::{
+    build_dfa_with_transposition, word_derivations_with_transposition, WordDerivationsCache,
+};
+use crate::{absolute_from_relative_position, relative_from_absolute_position, FieldId, Result};
 
 /// To be able to divide integers by the number of words in the query
 /// we want to find a multiplier that allow us to divide by any number between 1 and 10.
@@ -30,10 +32,21 @@ pub struct Attribute<'t> {
     parent: Box<dyn Criterion + 't>,
     linear_buckets: Option<btree_map::IntoIter<u64, RoaringBitmap>>,
     set_buckets: Option<BinaryHeap<Branch<'t>>>,
+    fields_boosts: HashMap<FieldId, f32>,
 }
 
 impl<'t> Attribute<'t> {
     pub fn new(ctx: &'t dyn Context<'t>, parent: Box<dyn Criterion + 't>) -> Self {
+        Self::with_fields_boosts(ctx, parent, HashMap::new())
+    }
+
+    /// Creates an attribute criterion that ranks the fields according to their boosts,
+    /// the positions of the words are moved in the attribute matching the boost of their field.
+    pub fn with_fields_boosts(
+        ctx: &'t dyn Context<'t>,
+        parent: Box<dyn Criterion + 't>,
+        fields_boosts: HashMap<FieldId, f32>,
+    ) -> Self {
         Attribute {
             ctx,
             state: None,
@@ -41,6 +54,7 @@ impl<'t> Attribute<'t> {
             parent,
             linear_buckets: None,
             set_buckets: None,
+            fields_boosts,
         }
     }
 }
@@ -64,7 +78,11 @@ impl<'t> Criterion for Attribute<'t> {
                     }));
                 }
                 Some((query_tree, flattened_query_tree, mut allowed_candidates)) => {
-                    let found_candidates = if allowed_candidates.len() < CANDIDATES_THRESHOLD {
+                    // The set algorithm iterates over the positions in the order they are stored,
+                    // it can't reorder the attributes so the boosts always use the linear algorithm.
+                    let found_candidates = if allowed_candidates.len() < CANDIDATES_THRESHOLD
+                        || !self.fields_boosts.is_empty()
+                    {
                         let linear_buckets = match self.linear_buckets.as_mut() {
                             Some(linear_buckets) => linear_buckets,
                             None => {
@@ -72,6 +90,8 @@ impl<'t> Criterion for Attribute<'t> {
                                     self.ctx,
                                     &flattened_query_tree,
                                     &allowed_candidates,
+                                    &self.fields_boosts,
+                                    self.ctx.transposition_cost_one(),
                                 )?;
                                 self.linear_buckets.get_or_insert(new_buckets.into_iter())
                             }
@@ -199,17 +219,30 @@ impl<'t> QueryPositionIterator<'t> {
                         let iter = ctx.word_position_iterator(word, in_prefix_cache)?;
                         inner.push(iter.peekable());
                     } else {
-                        for (word, _) in word_derivations(&word, true, 0, ctx.words_fst(), wdcache)?
-                        {
+                        for (word, _) in word_derivations_with_transposition(
+                            &word,
+                            true,
+                            0,
+                            ctx.transposition_cost_one(),
+                            ctx.words_fst(),
+                            ctx.dfa_cache(),
+                            wdcache,
+                        )? {
                             let iter = ctx.word_position_iterator(&word, in_prefix_cache)?;
                             inner.push(iter.peekable());
                         }
                     }
                 }
                 QueryKind::Tolerant { typo, word } => {
-                    for (word, _) in
-                        word_derivations(&word, query.prefix, *typo, ctx.words_fst(), wdcache)?
-                    {
+                    for (word, _) in word_derivations_with_transposition(
+                        &word,
+                        query.prefix,
+                        *typo,
+                        ctx.transposition_cost_one(),
+                        ctx.words_fst(),
+                        ctx.dfa_cache(),
+                        wdcache,
+                    )? {
                         let iter = ctx.word_position_iterator(&word, in_prefix_cache)?;
                         inner.push(iter.peekable());
                     }
@@ -454,11 +487,20 @@ fn initialize_linear_buckets(
     ctx: &dyn Context,
     branches: &FlattenedQueryTree,
     allowed_candidates: &RoaringBitmap,
+    fields_boosts: &HashMap<FieldId, f32>,
+    transposition_cost_one: bool,
 ) -> Result<BTreeMap<u64, RoaringBitmap>> {
     fn compute_candidate_rank(
         branches: &FlattenedQueryTree,
         words_positions: HashMap<String, RoaringBitmap>,
+        fields_boosts: &HashMap<FieldId, f32>,
+        transposition_cost_one: bool,
     ) -> u64 {
+        let best_position = |positions: &RoaringBitmap| match fields_boosts.is_empty() {
+            true => positions.iter().next(),
+            false => positions.iter().map(|pos| boosted_position(pos, fields_boosts)).min(),
+        };
+
         let mut min_rank = u64::max_value();
         for branch in branches {
             let branch_len = branch.len();
@@ -470,21 +512,22 @@ fn initialize_linear_buckets(
                     let current_position = match kind {
                         QueryKind::Exact { word, .. } => {
                             if *prefix {
-                                word_derivations(word, true, 0, &words_positions)
-                                    .flat_map(|positions| positions.iter().next())
+                                word_derivations(word, true, 0, &words_positions, true)
+                                    .flat_map(best_position)
                                     .min()
                             } else {
-                                words_positions
-                                    .get(word)
-                                    .map(|positions| positions.iter().next())
-                                    .flatten()
+                                words_positions.get(word).and_then(best_position)
                             }
                         }
-                        QueryKind::Tolerant { typo, word } => {
-                            word_derivations(word, *prefix, *typo, &words_positions)
-                                .flat_map(|positions| positions.iter().next())
-                                .min()
-                        }
+                        QueryKind::Tolerant { typo, word } => word_derivations(
+                            word,
+                            *prefix,
+                            *typo,
+                            &words_positions,
+                            transposition_cost_one,
+                        )
+                        .flat_map(best_position)
+                        .min(),
                     };
 
                     match (position, current_position) {
@@ -508,8 +551,11 @@ fn initialize_linear_buckets(
                 branch_rank.sort_unstable();
                 // because several words in same query can't match all a the position 0,
                 // we substract the word index to the position.
-                let branch_rank: u64 =
-                    branch_rank.into_iter().enumerate().map(|(i, r)| r - i as u64).sum();
+                let branch_rank: u64 = branch_rank
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, r)| r.saturating_sub(i as u64))
+                    .sum();
                 // here we do the means of the words of the branch
                 min_rank =
                     min_rank.min(branch_rank * LCM_10_FIRST_NUMBERS as u64 / branch_len as u64);
@@ -524,8 +570,9 @@ fn initialize_linear_buckets(
         is_prefix: bool,
         max_typo: u8,
         words_positions: &'a HashMap<String, RoaringBitmap>,
+        transposition_cost_one: bool,
     ) -> impl Iterator<Item = &'a RoaringBitmap> {
-        let dfa = build_dfa(word, max_typo, is_prefix);
+        let dfa = build_dfa_with_transposition(word, max_typo, is_prefix, transposition_cost_one);
         words_positions.iter().filter_map(move |(document_word, positions)| {
             use levenshtein_automata::Distance;
             match dfa.eval(document_word) {
@@ -538,13 +585,33 @@ fn initialize_linear_buckets(
     let mut candidates = BTreeMap::new();
     for docid in allowed_candidates {
         let words_positions = ctx.docid_words_positions(docid)?;
-        let rank = compute_candidate_rank(branches, words_positions);
+        let rank = compute_candidate_rank(
+            branches,
+            words_positions,
+            fields_boosts,
+            transposition_cost_one,
+        );
         candidates.entry(rank).or_insert_with(RoaringBitmap::new).insert(docid);
     }
 
     Ok(candidates)
 }
 
+/// Moves the position in the attribute corresponding to the boost of its field, the attribute
+/// `n` boosted by `b` becomes the attribute `(n + 1) / b - 1`, e.g. the third attribute boosted
+/// by 3 becomes the first one. The position in the attribute is kept.
+fn boosted_position(position: u32, fields_boosts: &HashMap<FieldId, f32>) -> u32 {
+    let (field_id, relative) = relative_from_absolute_position(position);
+    match fields_boosts.get(&field_id) {
+        Some(boost) => {
+            let attribute = ((field_id as f32 + 1.0) / boost - 1.0).round();
+            let attribute = attribute.max(0.0).min(FieldId::MAX as f32) as FieldId;
+            absolute_from_relative_position(attribute, relative)
+        }
+        None => position,
+    }
+}
+
 // TODO can we keep refs of Query
 fn flatten_query_tree(query_tree: &Operation) -> FlattenedQueryTree {
     use crate::search::criteria::Operation::{And, Or, Phrase};
@@ -578,8 +645,10 @@ fn flatten_query_tree(query_tree: &Operation) -> FlattenedQueryTree {
                 }
             }
             Phrase(words) => {
+                // the gaps of the phrase are stop words, they aren't indexed.
                 let queries = words
                     .iter()
+                    .flatten()
                     .map(|word| vec![Query { prefix: false, kind: QueryKind::exact(word.clone()) }])
                     .collect();
                 vec![queries]
@@ -653,4 +722,18 @@ mod tests {
         let result = flatten_query_tree(&query_tree);
         assert_eq!(expected, result);
     }
+
+    #[test]
+    fn boost_positions() {
+        let boosts: HashMap<_, _> = vec![(0, 0.5), (2, 3.0), (4, 1.5)].into_iter().collect();
+
+        let position = absolute_from_relative_position(0, 12);
+        assert_eq!(boosted_position(position, &boosts), absolute_from_relative_position(1, 12));
+        let position = absolute_from_relative_position(1, 12);
+        assert_eq!(boosted_position(position, &boosts), position);
+        let position = absolute_from_relative_position(2, 12);
+        assert_eq!(boosted_position(position, &boosts), absolute_from_relative_position(0, 12));
+        let position = absolute_from_relative_position(4, 12);
+        assert_eq!(boosted_position(position, &boosts), absolute_from_relative_position(2, 12));
+    }
 }
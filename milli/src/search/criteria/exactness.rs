@@ -7,9 +7,10 @@ use log::debug;
 use roaring::RoaringBitmap;
 
 use crate::search::criteria::{
-    resolve_query_tree, Context, Criterion, CriterionParameters, CriterionResult,
+    resolve_phrase, resolve_query_tree, Context, Criterion, CriterionParameters, CriterionResult,
 };
 use crate::search::query_tree::{Operation, PrimitiveQueryPart};
+use crate::search::{word_derivations_with_transposition, WordDerivationsCache};
 use crate::{absolute_from_relative_position, FieldId, Result};
 
 pub struct Exactness<'t> {
@@ -19,6 +20,8 @@ pub struct Exactness<'t> {
     bucket_candidates: RoaringBitmap,
     parent: Box<dyn Criterion + 't>,
     query: Vec<ExactQueryPart>,
+    /// The last word of the query when it is a prefix, i.e. the word being typed.
+    prefix: Option<String>,
 }
 
 impl<'t> Exactness<'t> {
@@ -32,6 +35,11 @@ impl<'t> Exactness<'t> {
             query.push(ExactQueryPart::from_primitive_query_part(ctx, part)?);
         }
 
+        let prefix = match primitive_query.last() {
+            Some(PrimitiveQueryPart::Word(word, true)) => Some(word.clone()),
+            _ => None,
+        };
+
         Ok(Exactness {
             ctx,
             query_tree: None,
@@ -39,6 +47,7 @@ impl<'t> Exactness<'t> {
             bucket_candidates: RoaringBitmap::new(),
             parent,
             query,
+            prefix,
         })
     }
 }
@@ -61,7 +70,13 @@ impl<'t> Criterion for Exactness<'t> {
                     self.query_tree = None;
                 }
                 Some(state) => {
-                    let (candidates, state) = resolve_state(self.ctx, take(state), &self.query)?;
+                    let (candidates, state) = resolve_state(
+                        self.ctx,
+                        take(state),
+                        &self.query,
+                        self.prefix.as_deref(),
+                        params.wdcache,
+                    )?;
                     self.state = state;
 
                     return Ok(Some(CriterionResult {
@@ -124,6 +139,9 @@ enum State {
     ExactAttribute(RoaringBitmap),
     /// Extract the documents that have an attribute that starts with exactly the query.
     AttributeStartsWith(RoaringBitmap),
+    /// Extract the documents that have an attribute that starts with the query,
+    /// the last word of the query only matching as a prefix.
+    AttributeStartsWithPrefix(RoaringBitmap),
     /// Rank the remaining documents by the number of exact words contained.
     ExactWords(RoaringBitmap),
     Remainings(Vec<RoaringBitmap>),
@@ -138,6 +156,7 @@ impl State {
         match self {
             Self::ExactAttribute(candidates)
             | Self::AttributeStartsWith(candidates)
+            | Self::AttributeStartsWithPrefix(candidates)
             | Self::ExactWords(candidates) => *candidates -= lhs,
             Self::Remainings(candidates_array) => {
                 candidates_array.iter_mut().for_each(|candidates| *candidates -= lhs);
@@ -150,6 +169,7 @@ impl State {
         match self {
             Self::ExactAttribute(candidates)
             | Self::AttributeStartsWith(candidates)
+            | Self::AttributeStartsWithPrefix(candidates)
             | Self::ExactWords(candidates) => candidates.is_empty(),
             Self::Remainings(candidates_array) => {
                 candidates_array.iter().all(RoaringBitmap::is_empty)
@@ -169,13 +189,15 @@ fn resolve_state(
     ctx: &dyn Context,
     state: State,
     query: &[ExactQueryPart],
+    prefix: Option<&str>,
+    wdcache: &mut WordDerivationsCache,
 ) -> Result<(RoaringBitmap, Option<State>)> {
     use State::*;
     match state {
         ExactAttribute(mut allowed_candidates) => {
             let mut candidates = RoaringBitmap::new();
             if let Ok(query_len) = u8::try_from(query.len()) {
-                let attributes_ids = ctx.searchable_fields_ids()?;
+                let attributes_ids = ctx.exactness_fields_ids()?;
                 for id in attributes_ids {
                     if let Some(attribute_allowed_docids) =
                         ctx.field_id_word_count_docids(id, query_len)?
@@ -197,18 +219,40 @@ fn resolve_state(
         }
         AttributeStartsWith(mut allowed_candidates) => {
             let mut candidates = RoaringBitmap::new();
-            let attributes_ids = ctx.searchable_fields_ids()?;
+            let attributes_ids = ctx.exactness_fields_ids()?;
             for id in attributes_ids {
                 let attribute_candidates_array = attribute_start_with_docids(ctx, id, query)?;
                 candidates |= intersection_of(attribute_candidates_array.iter().collect());
             }
 
+            // only keep allowed candidates
+            candidates &= &allowed_candidates;
+            // remove current candidates from allowed candidates
+            allowed_candidates -= &candidates;
+            // the documents in which the last word is only matched as a prefix rank below.
+            match prefix {
+                Some(_) => Ok((candidates, Some(AttributeStartsWithPrefix(allowed_candidates)))),
+                None => Ok((candidates, Some(ExactWords(allowed_candidates)))),
+            }
+        }
+        AttributeStartsWithPrefix(mut allowed_candidates) => {
+            let mut candidates = RoaringBitmap::new();
+            if let (Some(prefix), Some((_, query))) = (prefix, query.split_last()) {
+                let attributes_ids = ctx.exactness_fields_ids()?;
+                for id in attributes_ids {
+                    let attribute_candidates_array =
+                        attribute_start_with_prefix_docids(ctx, id, query, prefix, wdcache)?;
+                    candidates |= intersection_of(attribute_candidates_array.iter().collect());
+                }
+            }
+
             // only keep allowed candidates
             candidates &= &allowed_candidates;
             // remove current candidates from allowed candidates
             allowed_candidates -= &candidates;
             Ok((candidates, Some(ExactWords(allowed_candidates))))
         }
+        // The word docids aren't split by attribute, the excluded attributes still count here.
         ExactWords(mut allowed_candidates) => {
             let number_of_part = query.len();
             let mut parts_candidates_array = Vec::with_capacity(number_of_part);
@@ -224,22 +268,7 @@ fn resolve_state(
                             }
                         }
                     }
-                    // compute intersection on pair of words with a proximity of 0.
-                    Phrase(phrase) => {
-                        let mut bitmaps = Vec::with_capacity(phrase.len().saturating_sub(1));
-                        for words in phrase.windows(2) {
-                            if let [left, right] = words {
-                                match ctx.word_pair_proximity_docids(left, right, 0)? {
-                                    Some(docids) => bitmaps.push(docids),
-                                    None => {
-                                        bitmaps.clear();
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                        candidates |= intersection_of(bitmaps.iter().collect());
-                    }
+                    Phrase(phrase) => candidates |= resolve_phrase(ctx, phrase)?,
                 }
                 parts_candidates_array.push(candidates);
             }
@@ -311,7 +340,11 @@ fn attribute_start_with_docids(
             }
             Phrase(phrase) => {
                 for word in phrase {
-                    let wc = ctx.word_position_docids(word, pos)?;
+                    // the stop words aren't indexed but still take a position.
+                    let wc = match word {
+                        Some(word) => ctx.word_position_docids(word, pos)?,
+                        None => None,
+                    };
                     if let Some(word_candidates) = wc {
                         attribute_candidates_array.push(word_candidates);
                     }
@@ -324,6 +357,50 @@ fn attribute_start_with_docids(
     Ok(attribute_candidates_array)
 }
 
+/// Returns the documents in which the attribute starts with the query parts followed
+/// by a word starting with the prefix.
+fn attribute_start_with_prefix_docids(
+    ctx: &dyn Context,
+    attribute_id: FieldId,
+    query: &[ExactQueryPart],
+    prefix: &str,
+    wdcache: &mut WordDerivationsCache,
+) -> Result<Vec<RoaringBitmap>> {
+    let mut attribute_candidates_array = attribute_start_with_docids(ctx, attribute_id, query)?;
+    // the prefix is expected at the position following the query parts
+    let positions: u32 = query
+        .iter()
+        .map(|part| match part {
+            ExactQueryPart::Synonyms(_) => 1,
+            ExactQueryPart::Phrase(phrase) => phrase.len() as u32,
+        })
+        .sum();
+    let pos = absolute_from_relative_position(attribute_id, 0) + positions;
+
+    let prefix_candidates = if ctx.in_prefix_cache(prefix) {
+        ctx.word_prefix_position_docids(prefix, pos)?.unwrap_or_default()
+    } else {
+        let mut candidates = RoaringBitmap::new();
+        for (word, _typo) in word_derivations_with_transposition(
+            prefix,
+            true,
+            0,
+            ctx.transposition_cost_one(),
+            ctx.words_fst(),
+            ctx.dfa_cache(),
+            wdcache,
+        )? {
+            if let Some(word_candidates) = ctx.word_position_docids(word, pos)? {
+                candidates |= word_candidates;
+            }
+        }
+        candidates
+    };
+    attribute_candidates_array.push(prefix_candidates);
+
+    Ok(attribute_candidates_array)
+}
+
 fn intersection_of(mut rbs: Vec<&RoaringBitmap>) -> RoaringBitmap {
     rbs.sort_unstable_by_key(|rb| rb.len());
     let mut iter = rbs.into_iter();
@@ -335,7 +412,8 @@ fn intersection_of(mut rbs: Vec<&RoaringBitmap>) -> RoaringBitmap {
 
 #[derive(Debug, Clone)]
 pub enum ExactQueryPart {
-    Phrase(Vec<String>),
+    /// The words of the phrase, the stop words are gaps.
+    Phrase(Vec<Option<String>>),
     Synonyms(Vec<String>),
 }
 
@@ -351,7 +429,8 @@ impl ExactQueryPart {
                         let mut synonyms: Vec<_> = synonyms
                             .into_iter()
                             .filter_map(|mut array| {
-                                // keep 1 word synonyms only.
+                                // keep 1 word synonyms only, the stop words aren't indexed.
+                                array.retain(|word| !ctx.is_stop_word(word));
                                 match array.pop() {
                                     Some(word) if array.is_empty() => Some(word),
                                     _ => None,
@@ -364,7 +443,13 @@ impl ExactQueryPart {
                     None => ExactQueryPart::Synonyms(vec![word.clone()]),
                 }
             }
-            PrimitiveQueryPart::Phrase(phrase) => ExactQueryPart::Phrase(phrase.clone()),
+            PrimitiveQueryPart::Phrase(phrase) => {
+                let phrase = phrase
+                    .iter()
+                    .map(|word| (!ctx.is_stop_word(word)).then(|| word.clone()))
+                    .collect();
+                ExactQueryPart::Phrase(phrase)
+            }
         };
 
         Ok(part)
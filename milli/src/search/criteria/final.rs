@@ -1,9 +1,12 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use log::debug;
 use roaring::RoaringBitmap;
 
 use super::{resolve_query_tree, Context, Criterion, CriterionParameters, CriterionResult};
 use crate::search::query_tree::Operation;
-use crate::search::WordDerivationsCache;
+use crate::search::{ScoreDetails, WordDerivationsCache};
 use crate::Result;
 
 /// The result of a call to the fetcher.
@@ -21,19 +24,52 @@ pub struct Final<'t> {
     ctx: &'t dyn Context<'t>,
     parent: Box<dyn Criterion + 't>,
     wdcache: WordDerivationsCache,
+    words_position_limit: Option<u32>,
     returned_candidates: RoaringBitmap,
+    /// The names of the criteria and the number of buckets each one returned.
+    scores: Option<(Vec<String>, Rc<RefCell<Vec<usize>>>)>,
 }
 
 impl<'t> Final<'t> {
-    pub fn new(ctx: &'t dyn Context<'t>, parent: Box<dyn Criterion + 't>) -> Final<'t> {
+    pub fn new(
+        ctx: &'t dyn Context<'t>,
+        parent: Box<dyn Criterion + 't>,
+        wdcache: WordDerivationsCache,
+        words_position_limit: Option<u32>,
+    ) -> Final<'t> {
         Final {
             ctx,
             parent,
-            wdcache: WordDerivationsCache::new(),
+            wdcache,
+            words_position_limit,
             returned_candidates: RoaringBitmap::new(),
+            scores: None,
         }
     }
 
+    /// Reads the number of buckets returned by the criteria, counted by the criteria themselves.
+    pub(super) fn with_scores(
+        mut self,
+        names: Vec<String>,
+        counts: Rc<RefCell<Vec<usize>>>,
+    ) -> Final<'t> {
+        self.scores = Some((names, counts));
+        self
+    }
+
+    /// The score details of the candidates of the last returned bucket,
+    /// `None` if the scores are not computed.
+    pub fn score_details(&self) -> Option<ScoreDetails> {
+        let (names, counts) = self.scores.as_ref()?;
+        let counts = counts.borrow();
+        let ranks = names
+            .iter()
+            .zip(counts.iter())
+            .map(|(name, count)| (name.clone(), count.saturating_sub(1)))
+            .collect();
+        Some(ScoreDetails { ranks })
+    }
+
     #[logging_timer::time("Final::{}")]
     pub fn next(&mut self, excluded_candidates: &RoaringBitmap) -> Result<Option<FinalResult>> {
         debug!("Final iteration");
@@ -42,6 +78,7 @@ impl<'t> Final<'t> {
             wdcache: &mut self.wdcache,
             // returned_candidates is merged with excluded_candidates to avoid duplicas
             excluded_candidates: &excluded_candidates,
+            words_position_limit: self.words_position_limit,
         };
 
         match self.parent.next(&mut criterion_parameters)? {
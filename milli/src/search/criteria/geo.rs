@@ -5,7 +5,7 @@ use rstar::RTree;
 
 use super::{Criterion, CriterionParameters, CriterionResult};
 use crate::search::criteria::{resolve_query_tree, CriteriaBuilder};
-use crate::{lat_lng_to_xyz, GeoPoint, Index, Result};
+use crate::{lat_lng_to_xyz, GeoDistanceFormula, GeoPoint, Index, Result};
 
 pub struct Geo<'t> {
     index: &'t Index,
@@ -17,6 +17,10 @@ pub struct Geo<'t> {
     bucket_candidates: RoaringBitmap,
     rtree: Option<RTree<GeoPoint>>,
     point: [f64; 2],
+    /// The distance, in meters, after which the documents are not sorted anymore.
+    max_distance: Option<f64>,
+    formula: GeoDistanceFormula,
+    geo_faceted_candidates: RoaringBitmap,
 }
 
 impl<'t> Geo<'t> {
@@ -46,9 +50,11 @@ impl<'t> Geo<'t> {
         ascending: bool,
     ) -> Result<Self> {
         let candidates = Box::new(iter::empty());
-        let allowed_candidates = index.geo_faceted_documents_ids(rtxn)?;
+        let geo_faceted_candidates = index.geo_faceted_documents_ids(rtxn)?;
+        let allowed_candidates = geo_faceted_candidates.clone();
         let bucket_candidates = RoaringBitmap::new();
         let rtree = index.geo_rtree(rtxn)?;
+        let formula = index.geo_distance_formula(rtxn)?;
 
         Ok(Self {
             index,
@@ -60,8 +66,18 @@ impl<'t> Geo<'t> {
             bucket_candidates,
             rtree,
             point,
+            max_distance: None,
+            formula,
+            geo_faceted_candidates,
         })
     }
+
+    /// Only sorts the documents that are at most `max_distance` meters away from the point,
+    /// the farther documents are returned in a last bucket.
+    pub fn max_distance(mut self, max_distance: f64) -> Self {
+        self.max_distance = Some(max_distance);
+        self
+    }
 }
 
 impl Criterion for Geo<'_> {
@@ -90,7 +106,8 @@ impl Criterion for Geo<'_> {
                         let mut candidates = match (&query_tree, candidates) {
                             (_, Some(candidates)) => candidates,
                             (Some(qt), None) => {
-                                let context = CriteriaBuilder::new(&self.rtxn, &self.index)?;
+                                let mut context = CriteriaBuilder::new(&self.rtxn, &self.index)?;
+                                context.words_position_limit(params.words_position_limit)?;
                                 resolve_query_tree(&context, qt, params.wdcache)?
                             }
                             (None, None) => self.index.documents_ids(self.rtxn)?,
@@ -112,8 +129,10 @@ impl Criterion for Geo<'_> {
                         self.candidates = match rtree {
                             Some(rtree) => geo_point(
                                 rtree,
-                                self.allowed_candidates.clone(),
+                                &self.allowed_candidates & &self.geo_faceted_candidates,
                                 self.point,
+                                self.max_distance,
+                                self.formula,
                                 self.ascending,
                             ),
                             None => Box::new(std::iter::empty()),
@@ -129,13 +148,20 @@ impl Criterion for Geo<'_> {
 fn geo_point(
     rtree: &RTree<GeoPoint>,
     mut candidates: RoaringBitmap,
-    point: [f64; 2],
+    base_point: [f64; 2],
+    max_distance: Option<f64>,
+    formula: GeoDistanceFormula,
     ascending: bool,
 ) -> Box<dyn Iterator<Item = RoaringBitmap>> {
-    let point = lat_lng_to_xyz(&point);
+    let xyz_base_point = lat_lng_to_xyz(&base_point);
 
     let mut results = Vec::new();
-    for point in rtree.nearest_neighbor_iter(&point) {
+    for point in rtree.nearest_neighbor_iter(&xyz_base_point) {
+        if let Some(max_distance) = max_distance {
+            if formula.distance(&base_point, &point.data.1) > max_distance {
+                break;
+            }
+        }
         if candidates.remove(point.data.0) {
             results.push(std::iter::once(point.data.0).collect());
             if candidates.is_empty() {
@@ -144,9 +170,66 @@ fn geo_point(
         }
     }
 
+    // The documents farther than the maximum distance are all in the last bucket.
+    let farther = Some(candidates).filter(|candidates| !candidates.is_empty());
     if ascending {
-        Box::new(results.into_iter())
+        Box::new(results.into_iter().chain(farther))
     } else {
-        Box::new(results.into_iter().rev())
+        Box::new(results.into_iter().rev().chain(farther))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use maplit::hashset;
+
+    use crate::index::tests::TempIndex;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+    use crate::AscDesc;
+
+    #[test]
+    fn geo_sort_with_max_distance() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_sortable_fields(hashset! { S("_geo") });
+        builder.execute(|_| ()).unwrap();
+
+        // The documents are respectively at about 55, 222, 11 and 333 kilometers of (0, 0).
+        let content = documents!([
+            { "id": 0, "_geo": { "lat": 0, "lng": 0.5 } },
+            { "id": 1, "_geo": { "lat": 0, "lng": 2 } },
+            { "id": 2, "_geo": { "lat": 0, "lng": 0.1 } },
+            { "id": 3, "_geo": { "lat": 0, "lng": 3 } },
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let sort = |sort: &str| {
+            let sort: AscDesc = sort.parse().unwrap();
+            let result = index.search(&rtxn).sort_criteria(vec![sort]).execute().unwrap();
+            result.documents_ids
+        };
+
+        assert_eq!(sort("_geoPoint(0, 0):asc"), vec![2, 0, 1, 3]);
+        assert_eq!(sort("_geoPoint(0, 0):desc"), vec![3, 1, 0, 2]);
+
+        // the documents farther than 100 kilometers are returned last, in the same bucket
+        assert_eq!(sort("_geoPoint(0, 0, 100000):asc"), vec![2, 0, 1, 3]);
+        assert_eq!(sort("_geoPoint(0, 0, 100000):desc"), vec![0, 2, 1, 3]);
+        assert_eq!(sort("_geoPoint(0, 0, 0):desc"), vec![0, 1, 2, 3]);
+        assert_eq!(sort("_geoPoint(0, 3, 150000):asc"), vec![3, 1, 0, 2]);
     }
 }
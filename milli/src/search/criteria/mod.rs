@@ -1,5 +1,8 @@
 use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::rc::Rc;
 
 use roaring::RoaringBitmap;
 
@@ -13,8 +16,13 @@ use self::typo::Typo;
 use self::words::Words;
 use super::query_tree::{Operation, PrimitiveQueryPart, Query, QueryKind};
 use crate::search::criteria::geo::Geo;
-use crate::search::{word_derivations, WordDerivationsCache};
-use crate::{AscDesc as AscDescName, DocumentId, FieldId, Index, Member, Result};
+use crate::search::{
+    word_derivations_with_transposition, DfaCache, SearchObserver, WordDerivationsCache,
+};
+use crate::{
+    absolute_from_relative_position, relative_from_absolute_position, round_position,
+    AscDesc as AscDescName, DocumentId, FieldId, Index, Member, Result, MAX_POSITION_PER_ATTRIBUTE,
+};
 
 mod asc_desc;
 mod attribute;
@@ -48,6 +56,8 @@ pub struct CriterionResult {
 pub struct CriterionParameters<'a> {
     wdcache: &'a mut WordDerivationsCache,
     excluded_candidates: &'a RoaringBitmap,
+    /// The number of first positions of the attributes the query words must appear in.
+    words_position_limit: Option<u32>,
 }
 
 /// Either a set of candidates that defines the candidates
@@ -71,6 +81,24 @@ pub trait Context<'c> {
     fn exact_word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>>;
     fn word_prefix_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>>;
     fn exact_word_prefix_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>>;
+    /// Returns the union of the documents ids of the word, or of the prefix if it is in the
+    /// prefix cache, in the word and, if `with_exact` is set, in the exact word databases.
+    fn all_word_docids(
+        &self,
+        word: &str,
+        in_prefix_cache: bool,
+        with_exact: bool,
+    ) -> heed::Result<RoaringBitmap> {
+        let (docids, exact_docids) = match in_prefix_cache {
+            true if with_exact => {
+                (self.word_prefix_docids(word)?, self.exact_word_prefix_docids(word)?)
+            }
+            true => (self.word_prefix_docids(word)?, None),
+            false if with_exact => (self.word_docids(word)?, self.exact_word_docids(word)?),
+            false => (self.word_docids(word)?, None),
+        };
+        Ok(docids.unwrap_or_default() | exact_docids.unwrap_or_default())
+    }
     fn word_pair_proximity_docids(
         &self,
         left: &str,
@@ -89,19 +117,51 @@ pub trait Context<'c> {
         &self,
         docid: DocumentId,
     ) -> heed::Result<HashMap<String, RoaringBitmap>>;
+    fn docid_word_positions(
+        &self,
+        docid: DocumentId,
+        word: &str,
+    ) -> heed::Result<Option<RoaringBitmap>> {
+        Ok(self.docid_words_positions(docid)?.remove(word))
+    }
+    /// Whether the words of a phrase must follow each other in a single attribute.
+    fn phrases_within_attribute(&self) -> bool {
+        false
+    }
+    /// Whether swapping two adjacent characters of a word counts as one typo.
+    fn transposition_cost_one(&self) -> bool {
+        true
+    }
+    /// The levenshtein automatons shared with the previous searches of the index, if any.
+    fn dfa_cache(&self) -> Option<&DfaCache> {
+        None
+    }
     fn word_position_iterator(
         &self,
         word: &str,
         in_prefix_cache: bool,
     ) -> heed::Result<Box<dyn Iterator<Item = heed::Result<((&'c str, u32), RoaringBitmap)>> + 'c>>;
     fn synonyms(&self, word: &str) -> heed::Result<Option<Vec<Vec<String>>>>;
+    /// Whether the word is a stop word of the index, stop words aren't indexed.
+    fn is_stop_word(&self, _word: &str) -> bool {
+        false
+    }
     fn searchable_fields_ids(&self) -> Result<Vec<FieldId>>;
+    /// The searchable fields that are taken into account by the exactness criterion.
+    fn exactness_fields_ids(&self) -> Result<Vec<FieldId>>;
     fn field_id_word_count_docids(
         &self,
         field_id: FieldId,
         word_count: u8,
     ) -> heed::Result<Option<RoaringBitmap>>;
+    /// Returns the documents in which the word is at this position, the position is rounded
+    /// down like the positions stored in the word position databases.
     fn word_position_docids(&self, word: &str, pos: u32) -> heed::Result<Option<RoaringBitmap>>;
+    fn word_prefix_position_docids(
+        &self,
+        prefix: &str,
+        pos: u32,
+    ) -> heed::Result<Option<RoaringBitmap>>;
 }
 
 pub struct CriteriaBuilder<'t> {
@@ -109,6 +169,21 @@ pub struct CriteriaBuilder<'t> {
     index: &'t Index,
     words_fst: fst::Set<Cow<'t, [u8]>>,
     words_prefixes_fst: fst::Set<Cow<'t, [u8]>>,
+    transposition_cost_one: bool,
+    stop_words: Option<fst::Set<&'t [u8]>>,
+    /// The number of first positions and the searchable fields the query words must appear in.
+    words_position_limit: Option<(u32, Vec<FieldId>)>,
+    /// The number of documents the search needs, used to find the first sorted documents.
+    top_k: Option<usize>,
+    /// Whether the criteria record the bucket they return, see [`Final::score_details`].
+    compute_scores: bool,
+    /// Whether the exact word databases contain any word, they are not read otherwise.
+    has_exact_words: bool,
+    phrases_within_attribute: bool,
+    /// The precision of the positions stored in the word position databases.
+    word_position_precision: NonZeroU32,
+    /// The documents ids of the words already resolved by the criteria of this search.
+    all_word_docids_cache: RefCell<HashMap<(String, bool, bool), RoaringBitmap>>,
 }
 
 impl<'c> Context<'c> for CriteriaBuilder<'c> {
@@ -117,19 +192,45 @@ impl<'c> Context<'c> for CriteriaBuilder<'c> {
     }
 
     fn word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>> {
-        self.index.word_docids.get(self.rtxn, &word)
+        let docids = self.index.word_docids.get(self.rtxn, &word)?;
+        self.keep_first_positions_docids(word, false, docids)
     }
 
     fn exact_word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>> {
-        self.index.exact_word_docids.get(self.rtxn, &word)
+        let docids = self.index.exact_word_docids.get(self.rtxn, &word)?;
+        self.keep_first_positions_docids(word, false, docids)
     }
 
     fn word_prefix_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>> {
-        self.index.word_prefix_docids.get(self.rtxn, &word)
+        let docids = self.index.word_prefix_docids.get(self.rtxn, &word)?;
+        self.keep_first_positions_docids(word, true, docids)
     }
 
     fn exact_word_prefix_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>> {
-        self.index.exact_word_prefix_docids.get(self.rtxn, &word)
+        let docids = self.index.exact_word_prefix_docids.get(self.rtxn, &word)?;
+        self.keep_first_positions_docids(word, true, docids)
+    }
+
+    fn all_word_docids(
+        &self,
+        word: &str,
+        in_prefix_cache: bool,
+        with_exact: bool,
+    ) -> heed::Result<RoaringBitmap> {
+        let key = (word.to_string(), in_prefix_cache, with_exact && self.has_exact_words);
+        if let Some(docids) = self.all_word_docids_cache.borrow().get(&key) {
+            return Ok(docids.clone());
+        }
+
+        let (docids, exact_docids) = match (in_prefix_cache, key.2) {
+            (true, true) => (self.word_prefix_docids(word)?, self.exact_word_prefix_docids(word)?),
+            (true, false) => (self.word_prefix_docids(word)?, None),
+            (false, true) => (self.word_docids(word)?, self.exact_word_docids(word)?),
+            (false, false) => (self.word_docids(word)?, None),
+        };
+        let docids = docids.unwrap_or_default() | exact_docids.unwrap_or_default();
+        self.all_word_docids_cache.borrow_mut().insert(key, docids.clone());
+        Ok(docids)
     }
 
     fn word_pair_proximity_docids(
@@ -172,6 +273,26 @@ impl<'c> Context<'c> for CriteriaBuilder<'c> {
         Ok(words_positions)
     }
 
+    fn docid_word_positions(
+        &self,
+        docid: DocumentId,
+        word: &str,
+    ) -> heed::Result<Option<RoaringBitmap>> {
+        self.index.docid_word_positions.get(self.rtxn, &(docid, word))
+    }
+
+    fn phrases_within_attribute(&self) -> bool {
+        self.phrases_within_attribute
+    }
+
+    fn transposition_cost_one(&self) -> bool {
+        self.transposition_cost_one
+    }
+
+    fn dfa_cache(&self) -> Option<&DfaCache> {
+        Some(&self.index.dfa_cache)
+    }
+
     fn word_position_iterator(
         &self,
         word: &str,
@@ -197,6 +318,10 @@ impl<'c> Context<'c> for CriteriaBuilder<'c> {
         self.index.words_synonyms(self.rtxn, &[word])
     }
 
+    fn is_stop_word(&self, word: &str) -> bool {
+        self.stop_words.as_ref().map_or(false, |stop_words| stop_words.contains(word))
+    }
+
     fn searchable_fields_ids(&self) -> Result<Vec<FieldId>> {
         match self.index.searchable_fields_ids(self.rtxn)? {
             Some(searchable_fields_ids) => Ok(searchable_fields_ids),
@@ -204,6 +329,13 @@ impl<'c> Context<'c> for CriteriaBuilder<'c> {
         }
     }
 
+    fn exactness_fields_ids(&self) -> Result<Vec<FieldId>> {
+        let excluded = self.index.exactness_excluded_attributes_ids(self.rtxn)?;
+        let mut fields_ids = self.searchable_fields_ids()?;
+        fields_ids.retain(|id| !excluded.contains(id));
+        Ok(fields_ids)
+    }
+
     fn field_id_word_count_docids(
         &self,
         field_id: FieldId,
@@ -214,16 +346,102 @@ impl<'c> Context<'c> for CriteriaBuilder<'c> {
     }
 
     fn word_position_docids(&self, word: &str, pos: u32) -> heed::Result<Option<RoaringBitmap>> {
-        let key = (word, pos);
+        let key = (word, round_position(pos, self.word_position_precision));
         self.index.word_position_docids.get(self.rtxn, &key)
     }
+
+    fn word_prefix_position_docids(
+        &self,
+        prefix: &str,
+        pos: u32,
+    ) -> heed::Result<Option<RoaringBitmap>> {
+        let key = (prefix, round_position(pos, self.word_position_precision));
+        self.index.word_prefix_position_docids.get(self.rtxn, &key)
+    }
 }
 
 impl<'t> CriteriaBuilder<'t> {
     pub fn new(rtxn: &'t heed::RoTxn<'t>, index: &'t Index) -> Result<Self> {
         let words_fst = index.words_fst(rtxn)?;
         let words_prefixes_fst = index.words_prefixes_fst(rtxn)?;
-        Ok(Self { rtxn, index, words_fst, words_prefixes_fst })
+        let transposition_cost_one = index.transposition_counts_as_one_typo(rtxn)?;
+        let stop_words = index.stop_words(rtxn)?;
+        let has_exact_words = !index.exact_word_docids.is_empty(rtxn)?;
+        let phrases_within_attribute = index.phrases_within_attribute(rtxn)?;
+        let word_position_precision = index.word_position_precision(rtxn)?;
+        Ok(Self {
+            rtxn,
+            index,
+            words_fst,
+            words_prefixes_fst,
+            transposition_cost_one,
+            stop_words,
+            words_position_limit: None,
+            top_k: None,
+            compute_scores: false,
+            has_exact_words,
+            phrases_within_attribute,
+            word_position_precision,
+            all_word_docids_cache: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Only matches the query words that appear in the first `limit` positions of a
+    /// searchable attribute, e.g. to only match the headlines of long attributes.
+    pub fn words_position_limit(&mut self, limit: Option<u32>) -> Result<()> {
+        self.words_position_limit = match limit {
+            Some(limit) => Some((limit, self.searchable_fields_ids()?)),
+            None => None,
+        };
+        // The documents ids already resolved didn't take the new limit into account.
+        self.all_word_docids_cache.get_mut().clear();
+        Ok(())
+    }
+
+    /// The number of documents the search needs, the offset included. When the sort is the
+    /// first criterion to split the candidates the first documents are found in one pass.
+    pub fn top_k(&mut self, limit: Option<usize>) {
+        self.top_k = limit;
+    }
+
+    /// Makes each criterion record the rank of the bucket it returns, to retrieve
+    /// the score details of the documents.
+    pub fn compute_scores(&mut self, value: bool) {
+        self.compute_scores = value;
+    }
+
+    /// Keeps the documents in which the word, or the prefix, appears in the first positions
+    /// of a searchable attribute, according to the words position limit.
+    fn keep_first_positions_docids(
+        &self,
+        word: &str,
+        is_prefix: bool,
+        docids: Option<RoaringBitmap>,
+    ) -> heed::Result<Option<RoaringBitmap>> {
+        let (limit, fields_ids, mut docids) = match (&self.words_position_limit, docids) {
+            (Some((limit, fields_ids)), Some(docids)) => (*limit, fields_ids, docids),
+            (_, docids) => return Ok(docids),
+        };
+
+        let db = match is_prefix {
+            true => self.index.word_prefix_position_docids,
+            false => self.index.word_position_docids,
+        };
+
+        let mut first_positions_docids = RoaringBitmap::new();
+        if let Some(last) = limit.min(MAX_POSITION_PER_ATTRIBUTE).checked_sub(1) {
+            for &field_id in fields_ids {
+                let left = (word, absolute_from_relative_position(field_id, 0));
+                let right = (word, absolute_from_relative_position(field_id, last as u16));
+                for result in db.range(self.rtxn, &(left..=right))? {
+                    let (_, positions_docids) = result?;
+                    first_positions_docids |= positions_docids;
+                }
+            }
+        }
+
+        docids &= first_positions_docids;
+        Ok(Some(docids))
     }
 
     pub fn build(
@@ -232,33 +450,87 @@ impl<'t> CriteriaBuilder<'t> {
         primitive_query: Option<Vec<PrimitiveQueryPart>>,
         filtered_candidates: Option<RoaringBitmap>,
         sort_criteria: Option<Vec<AscDescName>>,
+        criteria: Vec<crate::Criterion>,
+        external_sort_keys: &'t HashMap<String, HashMap<DocumentId, f64>>,
+        fields_boosts: &HashMap<FieldId, f32>,
+        observer: Option<&'t dyn SearchObserver>,
     ) -> Result<Final<'t>> {
         use crate::criterion::Criterion as Name;
 
         let primitive_query = primitive_query.unwrap_or_default();
+        let has_query = query_tree.is_some();
+        let mut top_k = self.top_k;
 
         let mut criterion =
             Box::new(Initial::new(query_tree, filtered_candidates)) as Box<dyn Criterion>;
-        for name in self.index.criteria(&self.rtxn)? {
+        let mut scores = match self.compute_scores {
+            true => Some((Vec::new(), Rc::new(RefCell::new(Vec::new())))),
+            false => None,
+        };
+        for name in criteria {
+            let criterion_name = name.to_string();
+            // Without a query the criteria before the sort return all the candidates in a single
+            // bucket, only the first sort criterion is able to find the first documents directly.
+            if has_query && !matches!(name, Name::Sort | Name::Asc(_) | Name::Desc(_)) {
+                top_k = None;
+            }
             criterion = match name {
                 Name::Words => Box::new(Words::new(self, criterion)),
                 Name::Typo => Box::new(Typo::new(self, criterion)),
+                Name::WordsMinMatched(min_matched) => Box::new(Words::with_min_matched(
+                    self,
+                    criterion,
+                    &primitive_query,
+                    min_matched,
+                )),
+                Name::TypoMax(max_typos) => {
+                    Box::new(Typo::with_max_typos(self, criterion, max_typos))
+                }
                 Name::Sort => match sort_criteria {
                     Some(ref sort_criteria) => {
                         for asc_desc in sort_criteria {
+                            let top_k = top_k.take();
                             criterion = match asc_desc {
-                                AscDescName::Asc(Member::Field(field)) => Box::new(AscDesc::asc(
-                                    &self.index,
-                                    &self.rtxn,
-                                    criterion,
-                                    field.to_string(),
-                                )?),
-                                AscDescName::Desc(Member::Field(field)) => Box::new(AscDesc::desc(
-                                    &self.index,
-                                    &self.rtxn,
-                                    criterion,
-                                    field.to_string(),
-                                )?),
+                                AscDescName::Asc(Member::Field(field))
+                                    if external_sort_keys.contains_key(field) =>
+                                {
+                                    Box::new(AscDesc::external_asc(
+                                        &self.index,
+                                        &self.rtxn,
+                                        criterion,
+                                        field.to_string(),
+                                        &external_sort_keys[field],
+                                    ))
+                                }
+                                AscDescName::Desc(Member::Field(field))
+                                    if external_sort_keys.contains_key(field) =>
+                                {
+                                    Box::new(AscDesc::external_desc(
+                                        &self.index,
+                                        &self.rtxn,
+                                        criterion,
+                                        field.to_string(),
+                                        &external_sort_keys[field],
+                                    ))
+                                }
+                                AscDescName::Asc(Member::Field(field)) => Box::new(
+                                    AscDesc::asc(
+                                        &self.index,
+                                        &self.rtxn,
+                                        criterion,
+                                        field.to_string(),
+                                    )?
+                                    .top_k(top_k),
+                                ),
+                                AscDescName::Desc(Member::Field(field)) => Box::new(
+                                    AscDesc::desc(
+                                        &self.index,
+                                        &self.rtxn,
+                                        criterion,
+                                        field.to_string(),
+                                    )?
+                                    .top_k(top_k),
+                                ),
                                 AscDescName::Asc(Member::Geo(point)) => Box::new(Geo::asc(
                                     &self.index,
                                     &self.rtxn,
@@ -271,6 +543,18 @@ impl<'t> CriteriaBuilder<'t> {
                                     criterion,
                                     point.clone(),
                                 )?),
+                                AscDescName::Asc(Member::GeoWithin(point, max_distance)) => {
+                                    Box::new(
+                                        Geo::asc(&self.index, &self.rtxn, criterion, *point)?
+                                            .max_distance(*max_distance),
+                                    )
+                                }
+                                AscDescName::Desc(Member::GeoWithin(point, max_distance)) => {
+                                    Box::new(
+                                        Geo::desc(&self.index, &self.rtxn, criterion, *point)?
+                                            .max_distance(*max_distance),
+                                    )
+                                }
                             };
                         }
                         criterion
@@ -278,18 +562,78 @@ impl<'t> CriteriaBuilder<'t> {
                     None => criterion,
                 },
                 Name::Proximity => Box::new(Proximity::new(self, criterion)),
+                Name::Attribute if !fields_boosts.is_empty() => {
+                    Box::new(Attribute::with_fields_boosts(self, criterion, fields_boosts.clone()))
+                }
                 Name::Attribute => Box::new(Attribute::new(self, criterion)),
                 Name::Exactness => Box::new(Exactness::new(self, criterion, &primitive_query)?),
-                Name::Asc(field) => {
-                    Box::new(AscDesc::asc(&self.index, &self.rtxn, criterion, field)?)
-                }
-                Name::Desc(field) => {
-                    Box::new(AscDesc::desc(&self.index, &self.rtxn, criterion, field)?)
-                }
+                Name::Asc(field) => Box::new(
+                    AscDesc::asc(&self.index, &self.rtxn, criterion, field)?.top_k(top_k.take()),
+                ),
+                Name::Desc(field) => Box::new(
+                    AscDesc::desc(&self.index, &self.rtxn, criterion, field)?.top_k(top_k.take()),
+                ),
             };
+
+            if let Some(observer) = observer {
+                criterion =
+                    Box::new(Observed { name: criterion_name.clone(), inner: criterion, observer });
+            }
+
+            if let Some((names, counts)) = &mut scores {
+                let level = names.len();
+                names.push(criterion_name);
+                counts.borrow_mut().push(0);
+                criterion = Box::new(Scored { level, inner: criterion, counts: counts.clone() });
+            }
+        }
+
+        let wdcache = WordDerivationsCache::new();
+        let words_position_limit = self.words_position_limit.as_ref().map(|(limit, _)| *limit);
+        let mut final_criterion = Final::new(self, criterion, wdcache, words_position_limit);
+        if let Some((names, counts)) = scores {
+            final_criterion = final_criterion.with_scores(names, counts);
+        }
+        Ok(final_criterion)
+    }
+}
+
+/// Reports the buckets returned by a criterion to the search observer.
+struct Observed<'t> {
+    name: String,
+    inner: Box<dyn Criterion + 't>,
+    observer: &'t dyn SearchObserver,
+}
+
+impl<'t> Criterion for Observed<'t> {
+    fn next(&mut self, params: &mut CriterionParameters) -> Result<Option<CriterionResult>> {
+        let result = self.inner.next(params)?;
+        if let Some(result) = &result {
+            let candidates = result.candidates.as_ref().map(RoaringBitmap::len);
+            self.observer.criterion_bucket(&self.name, candidates);
         }
+        Ok(result)
+    }
+}
+
+/// Counts the buckets returned by a criterion since its parent returned its last bucket,
+/// the counts of the criteria are shared and indexed by their level in the criteria.
+struct Scored<'t> {
+    level: usize,
+    inner: Box<dyn Criterion + 't>,
+    counts: Rc<RefCell<Vec<usize>>>,
+}
 
-        Ok(Final::new(self, criterion))
+impl<'t> Criterion for Scored<'t> {
+    fn next(&mut self, params: &mut CriterionParameters) -> Result<Option<CriterionResult>> {
+        let result = self.inner.next(params)?;
+        if result.is_some() {
+            let mut counts = self.counts.borrow_mut();
+            counts[self.level] += 1;
+            // the children criteria start ranking a new bucket
+            counts[self.level + 1..].iter_mut().for_each(|count| *count = 0);
+        }
+        Ok(result)
     }
 }
 
@@ -326,43 +670,7 @@ pub fn resolve_query_tree<'t>(
                 }
                 Ok(candidates)
             }
-            Phrase(words) => {
-                let mut candidates = RoaringBitmap::new();
-                let mut first_iter = true;
-                let winsize = words.len().min(7);
-
-                for win in words.windows(winsize) {
-                    // Get all the documents with the matching distance for each word pairs.
-                    let mut bitmaps = Vec::with_capacity(winsize.pow(2));
-                    for (offset, s1) in win.iter().enumerate() {
-                        for (dist, s2) in win.iter().skip(offset).enumerate() {
-                            match ctx.word_pair_proximity_docids(s1, s2, dist as u8 + 1)? {
-                                Some(m) => bitmaps.push(m),
-                                // If there are no document for this distance, there will be no
-                                // results for the phrase query.
-                                None => return Ok(RoaringBitmap::new()),
-                            }
-                        }
-                    }
-
-                    // We sort the bitmaps so that we perform the small intersections first, which is faster.
-                    bitmaps.sort_unstable_by(|a, b| a.len().cmp(&b.len()));
-
-                    for bitmap in bitmaps {
-                        if first_iter {
-                            candidates = bitmap;
-                            first_iter = false;
-                        } else {
-                            candidates &= bitmap;
-                        }
-                        // There will be no match, return early
-                        if candidates.is_empty() {
-                            break;
-                        }
-                    }
-                }
-                Ok(candidates)
-            }
+            Phrase(words) => resolve_phrase(ctx, words),
             Or(_, ops) => {
                 let mut candidates = RoaringBitmap::new();
                 for op in ops {
@@ -378,6 +686,115 @@ pub fn resolve_query_tree<'t>(
     resolve_operation(ctx, query_tree, wdcache)
 }
 
+/// Returns the documents in which the words of the phrase follow each other.
+///
+/// The pairs of words of every window of the phrase are resolved independently and can
+/// therefore be found in different attributes, the positions of the words are checked
+/// when the index forbids it. The gaps of the phrase are stop words, they aren't indexed
+/// but still count in the distance between the words surrounding them.
+pub fn resolve_phrase(ctx: &dyn Context, words: &[Option<String>]) -> Result<RoaringBitmap> {
+    let mut candidates = RoaringBitmap::new();
+    let mut first_iter = true;
+    let winsize = words.len().min(7);
+
+    for win in words.windows(winsize) {
+        // Get all the documents with the matching distance for each word pairs.
+        let mut bitmaps = Vec::with_capacity(winsize.pow(2));
+        for (offset, s1) in win.iter().enumerate() {
+            let s1 = match s1 {
+                Some(s1) => s1,
+                None => continue,
+            };
+            for (dist, s2) in win.iter().skip(offset + 1).enumerate() {
+                let s2 = match s2 {
+                    Some(s2) => s2,
+                    None => continue,
+                };
+                match ctx.word_pair_proximity_docids(s1, s2, dist as u8 + 1)? {
+                    Some(m) => bitmaps.push(m),
+                    // If there are no document for this distance, there will be no
+                    // results for the phrase query.
+                    None => return Ok(RoaringBitmap::new()),
+                }
+            }
+        }
+
+        // We sort the bitmaps so that we perform the small intersections first, which is faster.
+        bitmaps.sort_unstable_by(|a, b| a.len().cmp(&b.len()));
+
+        for bitmap in bitmaps {
+            if first_iter {
+                candidates = bitmap;
+                first_iter = false;
+            } else {
+                candidates &= bitmap;
+            }
+            // There will be no match, return early
+            if candidates.is_empty() {
+                break;
+            }
+        }
+    }
+
+    if ctx.phrases_within_attribute() && !candidates.is_empty() {
+        candidates = phrase_within_attribute_docids(ctx, words, candidates)?;
+    }
+
+    Ok(candidates)
+}
+
+/// Keeps the candidates in which all the words of the phrase follow each other in one attribute.
+fn phrase_within_attribute_docids(
+    ctx: &dyn Context,
+    words: &[Option<String>],
+    candidates: RoaringBitmap,
+) -> Result<RoaringBitmap> {
+    // The positions of the words are relative to the first word that isn't a gap.
+    let words: Vec<_> = words
+        .iter()
+        .enumerate()
+        .filter_map(|(i, word)| word.as_ref().map(|word| (i, word)))
+        .collect();
+    let ((first_offset, first_word), words) = match words.split_first() {
+        Some((first, words)) => (*first, words),
+        None => return Ok(RoaringBitmap::new()),
+    };
+
+    let mut docids = RoaringBitmap::new();
+    'documents: for docid in candidates {
+        // The positions at which the phrase starts, that are still valid.
+        let mut starts = match ctx.docid_word_positions(docid, first_word)? {
+            Some(positions) => positions,
+            None => continue,
+        };
+
+        for (i, word) in words {
+            let positions = match ctx.docid_word_positions(docid, word)? {
+                Some(positions) => positions,
+                None => continue 'documents,
+            };
+            starts = starts
+                .into_iter()
+                .filter(|&start| match start.checked_add((i - first_offset) as u32) {
+                    Some(position) => {
+                        positions.contains(position)
+                            && relative_from_absolute_position(start).0
+                                == relative_from_absolute_position(position).0
+                    }
+                    None => false,
+                })
+                .collect();
+            if starts.is_empty() {
+                continue 'documents;
+            }
+        }
+
+        docids.insert(docid);
+    }
+
+    Ok(docids)
+}
+
 fn all_word_pair_proximity_docids<T: AsRef<str>, U: AsRef<str>>(
     ctx: &dyn Context,
     left_words: &[(T, u8)],
@@ -402,43 +819,42 @@ fn query_docids(
     wdcache: &mut WordDerivationsCache,
 ) -> Result<RoaringBitmap> {
     match &query.kind {
+        // only add the exact docids if the word hasn't been derived
         QueryKind::Exact { word, original_typo } => {
             if query.prefix && ctx.in_prefix_cache(&word) {
-                let mut docids = ctx.word_prefix_docids(&word)?.unwrap_or_default();
-                // only add the exact docids if the word hasn't been derived
-                if *original_typo == 0 {
-                    docids |= ctx.exact_word_prefix_docids(&word)?.unwrap_or_default();
-                }
-                Ok(docids)
+                Ok(ctx.all_word_docids(&word, true, *original_typo == 0)?)
             } else if query.prefix {
-                let words = word_derivations(&word, true, 0, ctx.words_fst(), wdcache)?;
+                let words = word_derivations_with_transposition(
+                    &word,
+                    true,
+                    0,
+                    ctx.transposition_cost_one(),
+                    ctx.words_fst(),
+                    ctx.dfa_cache(),
+                    wdcache,
+                )?;
                 let mut docids = RoaringBitmap::new();
                 for (word, _typo) in words {
-                    docids |= ctx.word_docids(&word)?.unwrap_or_default();
-                    // only add the exact docids if the word hasn't been derived
-                    if *original_typo == 0 {
-                        docids |= ctx.exact_word_docids(&word)?.unwrap_or_default();
-                    }
+                    docids |= ctx.all_word_docids(&word, false, *original_typo == 0)?;
                 }
                 Ok(docids)
             } else {
-                let mut docids = ctx.word_docids(&word)?.unwrap_or_default();
-                // only add the exact docids if the word hasn't been derived
-                if *original_typo == 0 {
-                    docids |= ctx.exact_word_docids(&word)?.unwrap_or_default();
-                }
-                Ok(docids)
+                Ok(ctx.all_word_docids(&word, false, *original_typo == 0)?)
             }
         }
         QueryKind::Tolerant { typo, word } => {
-            let words = word_derivations(&word, query.prefix, *typo, ctx.words_fst(), wdcache)?;
+            let words = word_derivations_with_transposition(
+                &word,
+                query.prefix,
+                *typo,
+                ctx.transposition_cost_one(),
+                ctx.words_fst(),
+                ctx.dfa_cache(),
+                wdcache,
+            )?;
             let mut docids = RoaringBitmap::new();
             for (word, typo) in words {
-                let mut current_docids = ctx.word_docids(&word)?.unwrap_or_default();
-                if *typo == 0 {
-                    current_docids |= ctx.exact_word_docids(&word)?.unwrap_or_default()
-                }
-                docids |= current_docids;
+                docids |= ctx.all_word_docids(&word, false, *typo == 0)?;
             }
             Ok(docids)
         }
@@ -470,7 +886,15 @@ fn query_pair_proximity_docids(
                 )? {
                     Some(docids) => Ok(docids),
                     None => {
-                        let r_words = word_derivations(&right, true, 0, ctx.words_fst(), wdcache)?;
+                        let r_words = word_derivations_with_transposition(
+                            &right,
+                            true,
+                            0,
+                            ctx.transposition_cost_one(),
+                            ctx.words_fst(),
+                            ctx.dfa_cache(),
+                            wdcache,
+                        )?;
                         all_word_pair_proximity_docids(ctx, &[(left, 0)], &r_words, proximity)
                     }
                 }
@@ -481,8 +905,16 @@ fn query_pair_proximity_docids(
             }
         }
         (QueryKind::Tolerant { typo, word: left }, QueryKind::Exact { word: right, .. }) => {
-            let l_words =
-                word_derivations(&left, false, *typo, ctx.words_fst(), wdcache)?.to_owned();
+            let l_words = word_derivations_with_transposition(
+                &left,
+                false,
+                *typo,
+                ctx.transposition_cost_one(),
+                ctx.words_fst(),
+                ctx.dfa_cache(),
+                wdcache,
+            )?
+            .to_owned();
             if prefix {
                 let mut docids = RoaringBitmap::new();
                 for (left, _) in l_words {
@@ -493,8 +925,15 @@ fn query_pair_proximity_docids(
                     )? {
                         Some(docids) => Ok(docids),
                         None => {
-                            let r_words =
-                                word_derivations(&right, true, 0, ctx.words_fst(), wdcache)?;
+                            let r_words = word_derivations_with_transposition(
+                                &right,
+                                true,
+                                0,
+                                ctx.transposition_cost_one(),
+                                ctx.words_fst(),
+                                ctx.dfa_cache(),
+                                wdcache,
+                            )?;
                             all_word_pair_proximity_docids(ctx, &[(left, 0)], &r_words, proximity)
                         }
                     }?;
@@ -506,16 +945,40 @@ fn query_pair_proximity_docids(
             }
         }
         (QueryKind::Exact { word: left, .. }, QueryKind::Tolerant { typo, word: right }) => {
-            let r_words = word_derivations(&right, prefix, *typo, ctx.words_fst(), wdcache)?;
+            let r_words = word_derivations_with_transposition(
+                &right,
+                prefix,
+                *typo,
+                ctx.transposition_cost_one(),
+                ctx.words_fst(),
+                ctx.dfa_cache(),
+                wdcache,
+            )?;
             all_word_pair_proximity_docids(ctx, &[(left, 0)], &r_words, proximity)
         }
         (
             QueryKind::Tolerant { typo: l_typo, word: left },
             QueryKind::Tolerant { typo: r_typo, word: right },
         ) => {
-            let l_words =
-                word_derivations(&left, false, *l_typo, ctx.words_fst(), wdcache)?.to_owned();
-            let r_words = word_derivations(&right, prefix, *r_typo, ctx.words_fst(), wdcache)?;
+            let l_words = word_derivations_with_transposition(
+                &left,
+                false,
+                *l_typo,
+                ctx.transposition_cost_one(),
+                ctx.words_fst(),
+                ctx.dfa_cache(),
+                wdcache,
+            )?
+            .to_owned();
+            let r_words = word_derivations_with_transposition(
+                &right,
+                prefix,
+                *r_typo,
+                ctx.transposition_cost_one(),
+                ctx.words_fst(),
+                ctx.dfa_cache(),
+                wdcache,
+            )?;
             all_word_pair_proximity_docids(ctx, &l_words, &r_words, proximity)
         }
     }
@@ -631,6 +1094,10 @@ pub mod test {
             todo!()
         }
 
+        fn exactness_fields_ids(&self) -> Result<Vec<FieldId>> {
+            todo!()
+        }
+
         fn word_position_docids(
             &self,
             _word: &str,
@@ -639,6 +1106,14 @@ pub mod test {
             todo!()
         }
 
+        fn word_prefix_position_docids(
+            &self,
+            _prefix: &str,
+            _pos: u32,
+        ) -> heed::Result<Option<RoaringBitmap>> {
+            todo!()
+        }
+
         fn field_id_word_count_docids(
             &self,
             _field_id: FieldId,
@@ -761,4 +1236,54 @@ pub mod test {
             }
         }
     }
+
+    #[test]
+    fn all_word_docids_with_exact_words() {
+        use big_s::S;
+        use maplit::hashset;
+
+        use crate::index::tests::TempIndex;
+        use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_exact_attributes(hashset! { S("code") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "name": "hello", "code": "world" },
+            { "id": 1, "name": "world", "code": "hello" },
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let ctx = CriteriaBuilder::new(&rtxn, &index).unwrap();
+        assert!(ctx.has_exact_words);
+        let word_docids = ctx.word_docids("world").unwrap().unwrap();
+        let exact_word_docids = ctx.exact_word_docids("world").unwrap().unwrap();
+        assert_eq!(ctx.all_word_docids("world", false, false).unwrap(), word_docids);
+        assert_eq!(
+            ctx.all_word_docids("world", false, true).unwrap(),
+            &word_docids | &exact_word_docids
+        );
+        // the second resolution comes from the cache
+        assert_eq!(ctx.all_word_docids_cache.borrow().len(), 2);
+        assert_eq!(
+            ctx.all_word_docids("world", false, true).unwrap(),
+            &word_docids | &exact_word_docids
+        );
+        assert_eq!(ctx.all_word_docids_cache.borrow().len(), 2);
+    }
 }
@@ -6,11 +6,11 @@ use log::debug;
 use roaring::RoaringBitmap;
 
 use super::{
-    query_docids, query_pair_proximity_docids, resolve_query_tree, Context, Criterion,
-    CriterionParameters, CriterionResult,
+    query_docids, query_pair_proximity_docids, resolve_phrase, resolve_query_tree, Context,
+    Criterion, CriterionParameters, CriterionResult,
 };
 use crate::search::query_tree::{maximum_proximity, Operation, Query, QueryKind};
-use crate::search::{build_dfa, WordDerivationsCache};
+use crate::search::{build_dfa_with_transposition, WordDerivationsCache};
 use crate::{Position, Result};
 
 type Cache = HashMap<(Operation, u8), Vec<(Query, Query, RoaringBitmap)>>;
@@ -90,6 +90,7 @@ impl<'t> Criterion for Proximity<'t> {
                                 self.ctx,
                                 query_tree,
                                 allowed_candidates,
+                                self.ctx.transposition_cost_one(),
                             )?;
                             self.plane_sweep_cache = Some(cache.into_iter());
 
@@ -187,27 +188,17 @@ fn resolve_candidates<'t>(
             Phrase(words) => {
                 if proximity == 0 {
                     let most_left = words
-                        .first()
+                        .iter()
+                        .find_map(Option::as_ref)
                         .map(|w| Query { prefix: false, kind: QueryKind::exact(w.clone()) });
                     let most_right = words
-                        .last()
+                        .iter()
+                        .rev()
+                        .find_map(Option::as_ref)
                         .map(|w| Query { prefix: false, kind: QueryKind::exact(w.clone()) });
-                    let mut candidates = None;
-                    for slice in words.windows(2) {
-                        let (left, right) = (&slice[0], &slice[1]);
-                        match ctx.word_pair_proximity_docids(left, right, 1)? {
-                            Some(pair_docids) => match candidates.as_mut() {
-                                Some(candidates) => *candidates &= pair_docids,
-                                None => candidates = Some(pair_docids),
-                            },
-                            None => {
-                                candidates = None;
-                                break;
-                            }
-                        }
-                    }
-                    match (most_left, most_right, candidates) {
-                        (Some(l), Some(r), Some(c)) => vec![(l, r, c)],
+                    let candidates = resolve_phrase(ctx, words)?;
+                    match (most_left, most_right) {
+                        (Some(l), Some(r)) if !candidates.is_empty() => vec![(l, r, candidates)],
                         _otherwise => Default::default(),
                     }
                 } else {
@@ -342,15 +333,14 @@ fn resolve_plane_sweep_candidates(
     ctx: &dyn Context,
     query_tree: &Operation,
     allowed_candidates: &RoaringBitmap,
+    transposition_cost_one: bool,
 ) -> Result<BTreeMap<u8, RoaringBitmap>> {
     /// FIXME may be buggy with query like "new new york"
     fn plane_sweep(
         groups_positions: Vec<Vec<(Position, u8, Position)>>,
-        consecutive: bool,
     ) -> Result<Vec<(Position, u8, Position)>> {
         fn compute_groups_proximity(
             groups: &[(usize, (Position, u8, Position))],
-            consecutive: bool,
         ) -> Option<(Position, u8, Position)> {
             // take the inner proximity of the first group as initial
             let (_, (_, mut proximity, _)) = groups.first()?;
@@ -389,12 +379,7 @@ fn resolve_plane_sweep_candidates(
                 }
             }
 
-            // if groups should be consecutives, we will only accept groups with a proximity of 0
-            if !consecutive || proximity == 0 {
-                Some((*left_most_pos, proximity, *right_most_pos))
-            } else {
-                None
-            }
+            Some((*left_most_pos, proximity, *right_most_pos))
         }
 
         let groups_len = groups_positions.len();
@@ -432,7 +417,7 @@ fn resolve_plane_sweep_candidates(
             // If p > r, then the interval [l, r] is minimal and
             // we insert it into the heap according to its size.
             if p.map_or(true, |p| p.1 > rightmost.1) {
-                if let Some(group) = compute_groups_proximity(&current, consecutive) {
+                if let Some(group) = compute_groups_proximity(&current) {
                     output.push(group);
                 }
             }
@@ -468,6 +453,7 @@ fn resolve_plane_sweep_candidates(
         query_tree: &'a Operation,
         rocache: &mut HashMap<&'a Operation, Vec<(Position, u8, Position)>>,
         words_positions: &HashMap<String, RoaringBitmap>,
+        transposition_cost_one: bool,
     ) -> Result<Vec<(Position, u8, Position)>> {
         use Operation::{And, Or, Phrase};
 
@@ -479,26 +465,48 @@ fn resolve_plane_sweep_candidates(
             And(ops) => {
                 let mut groups_positions = Vec::with_capacity(ops.len());
                 for operation in ops {
-                    let positions = resolve_operation(operation, rocache, words_positions)?;
+                    let positions = resolve_operation(
+                        operation,
+                        rocache,
+                        words_positions,
+                        transposition_cost_one,
+                    )?;
                     groups_positions.push(positions);
                 }
-                plane_sweep(groups_positions, false)?
+                plane_sweep(groups_positions)?
             }
             Phrase(words) => {
-                let mut groups_positions = Vec::with_capacity(words.len());
-                for word in words {
+                // The gaps of the phrase aren't indexed, the phrase starts at the positions
+                // where every word is found at its offset in the phrase.
+                let mut starts: Option<RoaringBitmap> = None;
+                for (offset, word) in words.iter().enumerate() {
+                    let word = match word {
+                        Some(word) => word,
+                        None => continue,
+                    };
                     let positions = match words_positions.get(word) {
-                        Some(positions) => positions.iter().map(|p| (p, 0, p)).collect(),
+                        Some(positions) => positions,
                         None => return Ok(vec![]),
                     };
-                    groups_positions.push(positions);
+                    let word_starts: RoaringBitmap =
+                        positions.iter().filter_map(|p| p.checked_sub(offset as u32)).collect();
+                    starts = Some(match starts {
+                        Some(starts) => starts & word_starts,
+                        None => word_starts,
+                    });
                 }
-                plane_sweep(groups_positions, true)?
+                let last_offset = words.len().saturating_sub(1) as u32;
+                starts.unwrap_or_default().iter().map(|p| (p, 0, p + last_offset)).collect()
             }
             Or(_, ops) => {
                 let mut result = Vec::new();
                 for op in ops {
-                    result.extend(resolve_operation(op, rocache, words_positions)?)
+                    result.extend(resolve_operation(
+                        op,
+                        rocache,
+                        words_positions,
+                        transposition_cost_one,
+                    )?)
                 }
 
                 result.sort_unstable();
@@ -509,7 +517,7 @@ fn resolve_plane_sweep_candidates(
                 match kind {
                     QueryKind::Exact { word, .. } => {
                         if *prefix {
-                            let iter = word_derivations(word, true, 0, &words_positions)
+                            let iter = word_derivations(word, true, 0, &words_positions, true)
                                 .flat_map(|positions| positions.iter().map(|p| (p, 0, p)));
                             result.extend(iter);
                         } else if let Some(positions) = words_positions.get(word) {
@@ -517,8 +525,14 @@ fn resolve_plane_sweep_candidates(
                         }
                     }
                     QueryKind::Tolerant { typo, word } => {
-                        let iter = word_derivations(word, *prefix, *typo, &words_positions)
-                            .flat_map(|positions| positions.iter().map(|p| (p, 0, p)));
+                        let iter = word_derivations(
+                            word,
+                            *prefix,
+                            *typo,
+                            &words_positions,
+                            transposition_cost_one,
+                        )
+                        .flat_map(|positions| positions.iter().map(|p| (p, 0, p)));
                         result.extend(iter);
                     }
                 }
@@ -537,8 +551,9 @@ fn resolve_plane_sweep_candidates(
         is_prefix: bool,
         max_typo: u8,
         words_positions: &'a HashMap<String, RoaringBitmap>,
+        transposition_cost_one: bool,
     ) -> impl Iterator<Item = &'a RoaringBitmap> {
-        let dfa = build_dfa(word, max_typo, is_prefix);
+        let dfa = build_dfa_with_transposition(word, max_typo, is_prefix, transposition_cost_one);
         words_positions.iter().filter_map(move |(document_word, positions)| {
             use levenshtein_automata::Distance;
             match dfa.eval(document_word) {
@@ -553,8 +568,12 @@ fn resolve_plane_sweep_candidates(
     for docid in allowed_candidates {
         let words_positions = ctx.docid_words_positions(docid)?;
         resolve_operation_cache.clear();
-        let positions =
-            resolve_operation(query_tree, &mut resolve_operation_cache, &words_positions)?;
+        let positions = resolve_operation(
+            query_tree,
+            &mut resolve_operation_cache,
+            &words_positions,
+            transposition_cost_one,
+        )?;
         let best_proximity = positions.into_iter().min_by_key(|(_, proximity, _)| *proximity);
         let best_proximity = best_proximity.map(|(_, proximity, _)| proximity).unwrap_or(7);
         candidates.entry(best_proximity).or_insert_with(RoaringBitmap::new).insert(docid);
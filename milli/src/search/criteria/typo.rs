@@ -6,11 +6,11 @@ use log::debug;
 use roaring::RoaringBitmap;
 
 use super::{
-    query_docids, resolve_query_tree, Candidates, Context, Criterion, CriterionParameters,
-    CriterionResult,
+    query_docids, resolve_phrase, resolve_query_tree, Candidates, Context, Criterion,
+    CriterionParameters, CriterionResult,
 };
 use crate::search::query_tree::{maximum_typo, Operation, Query, QueryKind};
-use crate::search::{word_derivations, WordDerivationsCache};
+use crate::search::{word_derivations_with_transposition, DfaCache, WordDerivationsCache};
 use crate::Result;
 
 /// Maximum number of typo for a word of any length.
@@ -24,6 +24,8 @@ pub struct Typo<'t> {
     bucket_candidates: Option<RoaringBitmap>,
     parent: Box<dyn Criterion + 't>,
     candidates_cache: HashMap<(Operation, u8), RoaringBitmap>,
+    /// The maximum number of typos a returned document can have.
+    max_typos: Option<u8>,
 }
 
 impl<'t> Typo<'t> {
@@ -35,8 +37,19 @@ impl<'t> Typo<'t> {
             bucket_candidates: None,
             parent,
             candidates_cache: HashMap::new(),
+            max_typos: None,
         }
     }
+
+    /// Creates a typo criterion that doesn't return the documents
+    /// matching the query with more than `max_typos` typos.
+    pub fn with_max_typos(
+        ctx: &'t dyn Context<'t>,
+        parent: Box<dyn Criterion + 't>,
+        max_typos: u8,
+    ) -> Self {
+        Typo { max_typos: Some(max_typos), ..Typo::new(ctx, parent) }
+    }
 }
 
 impl<'t> Criterion for Typo<'t> {
@@ -70,6 +83,8 @@ impl<'t> Criterion for Typo<'t> {
                     let new_query_tree = match self.typos {
                         typos if typos < MAX_TYPOS_PER_WORD => alterate_query_tree(
                             &fst,
+                            self.ctx.transposition_cost_one(),
+                            self.ctx.dfa_cache(),
                             query_tree.clone(),
                             self.typos,
                             params.wdcache,
@@ -79,6 +94,8 @@ impl<'t> Criterion for Typo<'t> {
                             // we keep the altered query tree
                             *query_tree = alterate_query_tree(
                                 &fst,
+                                self.ctx.transposition_cost_one(),
+                                self.ctx.dfa_cache(),
                                 query_tree.clone(),
                                 self.typos,
                                 params.wdcache,
@@ -154,6 +171,10 @@ impl<'t> Criterion for Typo<'t> {
                         };
 
                         let maximum_typos = maximum_typo(&query_tree) as u8;
+                        let maximum_typos = match self.max_typos {
+                            Some(max_typos) => maximum_typos.min(max_typos),
+                            None => maximum_typos,
+                        };
                         self.state = Some((maximum_typos, query_tree, candidates));
                         self.typos = 0;
                     }
@@ -182,12 +203,16 @@ impl<'t> Criterion for Typo<'t> {
 /// query will only be replaced by exact query with up to `number_typos` maximum typos.
 fn alterate_query_tree(
     words_fst: &fst::Set<Cow<[u8]>>,
+    transposition_cost_one: bool,
+    dfa_cache: Option<&DfaCache>,
     mut query_tree: Operation,
     number_typos: u8,
     wdcache: &mut WordDerivationsCache,
 ) -> Result<Operation> {
     fn recurse(
         words_fst: &fst::Set<Cow<[u8]>>,
+        transposition_cost_one: bool,
+        dfa_cache: Option<&DfaCache>,
         operation: &mut Operation,
         number_typos: u8,
         wdcache: &mut WordDerivationsCache,
@@ -195,9 +220,9 @@ fn alterate_query_tree(
         use Operation::{And, Or, Phrase};
 
         match operation {
-            And(ops) | Or(_, ops) => {
-                ops.iter_mut().try_for_each(|op| recurse(words_fst, op, number_typos, wdcache))
-            }
+            And(ops) | Or(_, ops) => ops.iter_mut().try_for_each(|op| {
+                recurse(words_fst, transposition_cost_one, dfa_cache, op, number_typos, wdcache)
+            }),
             // Because Phrases don't allow typos, no alteration can be done.
             Phrase(_words) => return Ok(()),
             Operation::Query(q) => {
@@ -211,7 +236,15 @@ fn alterate_query_tree(
                         });
                     } else {
                         let typo = *typo.min(&number_typos);
-                        let words = word_derivations(word, q.prefix, typo, words_fst, wdcache)?;
+                        let words = word_derivations_with_transposition(
+                            word,
+                            q.prefix,
+                            typo,
+                            transposition_cost_one,
+                            words_fst,
+                            dfa_cache,
+                            wdcache,
+                        )?;
                         let queries = words
                             .iter()
                             .map(|(word, typo)| {
@@ -234,7 +267,7 @@ fn alterate_query_tree(
         }
     }
 
-    recurse(words_fst, &mut query_tree, number_typos, wdcache)?;
+    recurse(words_fst, transposition_cost_one, dfa_cache, &mut query_tree, number_typos, wdcache)?;
     Ok(query_tree)
 }
 
@@ -256,27 +289,7 @@ fn resolve_candidates<'t>(
 
         match query_tree {
             And(ops) => mdfs(ctx, ops, number_typos, cache, wdcache),
-            Phrase(words) => {
-                let mut candidates = RoaringBitmap::new();
-                let mut first_loop = true;
-                for slice in words.windows(2) {
-                    let (left, right) = (&slice[0], &slice[1]);
-                    match ctx.word_pair_proximity_docids(left, right, 1)? {
-                        Some(pair_docids) => {
-                            if pair_docids.is_empty() {
-                                return Ok(RoaringBitmap::new());
-                            } else if first_loop {
-                                candidates = pair_docids;
-                                first_loop = false;
-                            } else {
-                                candidates &= pair_docids;
-                            }
-                        }
-                        None => return Ok(RoaringBitmap::new()),
-                    }
-                }
-                Ok(candidates)
-            }
+            Phrase(words) => resolve_phrase(ctx, words),
             Or(_, ops) => {
                 let mut candidates = RoaringBitmap::new();
                 for op in ops {
@@ -358,6 +371,7 @@ mod test {
         let mut criterion_parameters = CriterionParameters {
             wdcache: &mut WordDerivationsCache::new(),
             excluded_candidates: &RoaringBitmap::new(),
+            words_position_limit: None,
         };
 
         let parent = Initial::new(query_tree, facet_candidates);
@@ -393,6 +407,7 @@ mod test {
         let mut criterion_parameters = CriterionParameters {
             wdcache: &mut WordDerivationsCache::new(),
             excluded_candidates: &RoaringBitmap::new(),
+            words_position_limit: None,
         };
         let parent = Initial::new(Some(query_tree), facet_candidates);
         let mut criteria = Typo::new(&context, Box::new(parent));
@@ -473,6 +488,7 @@ mod test {
         let mut criterion_parameters = CriterionParameters {
             wdcache: &mut WordDerivationsCache::new(),
             excluded_candidates: &RoaringBitmap::new(),
+            words_position_limit: None,
         };
         let parent = Initial::new(query_tree, Some(facet_candidates.clone()));
         let mut criteria = Typo::new(&context, Box::new(parent));
@@ -517,6 +533,7 @@ mod test {
         let mut criterion_parameters = CriterionParameters {
             wdcache: &mut WordDerivationsCache::new(),
             excluded_candidates: &RoaringBitmap::new(),
+            words_position_limit: None,
         };
         let parent = Initial::new(Some(query_tree), Some(facet_candidates.clone()));
         let mut criteria = Typo::new(&context, Box::new(parent));
@@ -4,7 +4,7 @@ use log::debug;
 use roaring::RoaringBitmap;
 
 use super::{resolve_query_tree, Context, Criterion, CriterionParameters, CriterionResult};
-use crate::search::query_tree::Operation;
+use crate::search::query_tree::{Operation, PrimitiveQueryPart};
 use crate::Result;
 
 pub struct Words<'t> {
@@ -13,6 +13,8 @@ pub struct Words<'t> {
     candidates: Option<RoaringBitmap>,
     bucket_candidates: Option<RoaringBitmap>,
     filtered_candidates: Option<RoaringBitmap>,
+    /// The number of the least matching query trees that must be skipped.
+    skipped_query_trees: usize,
     parent: Box<dyn Criterion + 't>,
 }
 
@@ -25,8 +27,28 @@ impl<'t> Words<'t> {
             bucket_candidates: None,
             parent,
             filtered_candidates: None,
+            skipped_query_trees: 0,
         }
     }
+
+    /// Creates a words criterion that doesn't return the documents matching less than
+    /// `min_matched` query terms, a phrase counts as one term. When the query is made of
+    /// less than `min_matched` terms the documents must match all of them.
+    pub fn with_min_matched(
+        ctx: &'t dyn Context<'t>,
+        parent: Box<dyn Criterion + 't>,
+        primitive_query: &[PrimitiveQueryPart],
+        min_matched: usize,
+    ) -> Self {
+        // The least matching query tree contains all the phrases or the first word,
+        // every following query tree contains one more term.
+        let number_phrases = primitive_query.iter().filter(|part| part.is_phrase()).count();
+        let least_matched = number_phrases + (number_phrases == 0) as usize;
+
+        let mut words = Words::new(ctx, parent);
+        words.skipped_query_trees = min_matched.saturating_sub(least_matched);
+        words
+    }
 }
 
 impl<'t> Criterion for Words<'t> {
@@ -72,7 +94,7 @@ impl<'t> Criterion for Words<'t> {
                         filtered_candidates,
                         bucket_candidates,
                     }) => {
-                        self.query_trees = explode_query_tree(query_tree);
+                        self.query_trees = explode_query_tree(query_tree, self.skipped_query_trees);
                         self.candidates = candidates;
                         self.filtered_candidates = filtered_candidates;
 
@@ -102,9 +124,15 @@ impl<'t> Criterion for Words<'t> {
     }
 }
 
-fn explode_query_tree(query_tree: Operation) -> Vec<Operation> {
+/// Explodes the optional words of the query tree, skipping the `skip` least matching
+/// query trees but always keeping the one that matches all of the query terms.
+fn explode_query_tree(query_tree: Operation, skip: usize) -> Vec<Operation> {
     match query_tree {
-        Operation::Or(true, ops) => ops,
+        Operation::Or(true, mut ops) => {
+            let skip = skip.min(ops.len().saturating_sub(1));
+            ops.drain(..skip);
+            ops
+        }
         otherwise => vec![otherwise],
     }
 }
@@ -0,0 +1,64 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use levenshtein_automata::DFA;
+
+use super::build_dfa_with_transposition;
+
+/// The maximum number of automatons kept by a cache.
+const CAPACITY: usize = 256;
+
+/// The key of an automaton: the word, the number of typos, whether it is a prefix
+/// and whether a transposition costs one typo.
+type DfaKey = (String, u8, bool, bool);
+
+/// Keeps the levenshtein automatons built for the last queries of an index, interactive
+/// searches repeat almost all the terms of the previous query between keystrokes.
+///
+/// The cache is bounded, the oldest automatons are evicted first.
+#[derive(Default)]
+pub(crate) struct DfaCache {
+    entries: Mutex<DfaEntries>,
+}
+
+#[derive(Default)]
+struct DfaEntries {
+    dfas: HashMap<DfaKey, Arc<DFA>>,
+    insertion_order: VecDeque<DfaKey>,
+}
+
+impl DfaCache {
+    /// Returns the same automaton as `build_dfa_with_transposition`, but shares it
+    /// with the previous queries that derived the same word.
+    pub fn get_or_build(
+        &self,
+        word: &str,
+        typos: u8,
+        is_prefix: bool,
+        transposition_cost_one: bool,
+    ) -> Arc<DFA> {
+        let key = (word.to_string(), typos, is_prefix, transposition_cost_one);
+        if let Some(dfa) = self.entries.lock().unwrap_or_else(|e| e.into_inner()).dfas.get(&key) {
+            return dfa.clone();
+        }
+
+        // The automaton is built without holding the lock.
+        let dfa =
+            Arc::new(build_dfa_with_transposition(word, typos, is_prefix, transposition_cost_one));
+
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(dfa) = entries.dfas.get(&key) {
+            return dfa.clone();
+        }
+
+        if entries.insertion_order.len() >= CAPACITY {
+            if let Some(oldest) = entries.insertion_order.pop_front() {
+                entries.dfas.remove(&oldest);
+            }
+        }
+
+        entries.insertion_order.push_back(key.clone());
+        entries.dfas.insert(key, dfa.clone());
+        dfa
+    }
+}
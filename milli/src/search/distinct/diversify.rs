@@ -0,0 +1,122 @@
+use std::collections::VecDeque;
+
+use super::facet_distinct::{facet_number_values, facet_string_values};
+use crate::{DocumentId, FieldId, Index, Result};
+
+/// The maximum number of documents that can be pushed back while looking
+/// for a document with a value different from the previous one.
+const MAX_DEFERRED_DOCUMENTS: usize = 100;
+
+/// The value of a document used to diversify the results, only the first
+/// value of a multi-valued field is considered.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiversityValue {
+    String(String),
+    Number(f64),
+}
+
+/// Reorders the documents of a bucket so that two consecutive documents don't share the same
+/// value on the diversity field whenever possible. Unlike a distinct, no document is removed:
+/// the documents sharing the value of the previous document are pushed back, in the order they
+/// were ranked, until a document with another value is returned.
+pub struct Diversify<'a, I> {
+    documents: I,
+    field_id: Option<FieldId>,
+    index: &'a Index,
+    txn: &'a heed::RoTxn<'a>,
+    last_value: Option<DiversityValue>,
+    deferred: VecDeque<(DocumentId, Option<DiversityValue>)>,
+}
+
+impl<'a, I> Diversify<'a, I>
+where
+    I: Iterator<Item = Result<DocumentId>>,
+{
+    /// Diversifies the documents on the given field, the documents are returned
+    /// unchanged if there is no field. The last value is the one of the document
+    /// returned just before, e.g. by the previous bucket.
+    pub fn new(
+        documents: I,
+        field_id: Option<FieldId>,
+        last_value: Option<DiversityValue>,
+        index: &'a Index,
+        txn: &'a heed::RoTxn<'a>,
+    ) -> Self {
+        Diversify { documents, field_id, index, txn, last_value, deferred: VecDeque::new() }
+    }
+
+    /// Returns the value of the last document returned.
+    pub fn into_last_value(self) -> Option<DiversityValue> {
+        self.last_value
+    }
+
+    fn document_value(&self, field_id: FieldId, id: DocumentId) -> Result<Option<DiversityValue>> {
+        if let Some(item) = facet_string_values(id, field_id, self.index, self.txn)?.next() {
+            let ((_, _, value), _) = item?;
+            return Ok(Some(DiversityValue::String(value.to_string())));
+        }
+
+        match facet_number_values(id, field_id, self.index, self.txn)?.next() {
+            Some(item) => {
+                let ((_, _, value), _) = item?;
+                Ok(Some(DiversityValue::Number(value)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Whether the value is the same as the one of the last document returned,
+    /// documents without value are never considered as repeated.
+    fn is_repeated(&self, value: &Option<DiversityValue>) -> bool {
+        value.is_some() && *value == self.last_value
+    }
+
+    fn next_inner(&mut self) -> Result<Option<DocumentId>> {
+        let field_id = match self.field_id {
+            Some(field_id) => field_id,
+            None => return self.documents.next().transpose(),
+        };
+
+        // The documents that were pushed back are returned first, as soon as they are allowed.
+        let position = self.deferred.iter().position(|(_, value)| !self.is_repeated(value));
+        if let Some((id, value)) = position.and_then(|pos| self.deferred.remove(pos)) {
+            self.last_value = value;
+            return Ok(Some(id));
+        }
+
+        while self.deferred.len() < MAX_DEFERRED_DOCUMENTS {
+            let id = match self.documents.next().transpose()? {
+                Some(id) => id,
+                None => break,
+            };
+
+            let value = self.document_value(field_id, id)?;
+            if self.is_repeated(&value) {
+                self.deferred.push_back((id, value));
+            } else {
+                self.last_value = value;
+                return Ok(Some(id));
+            }
+        }
+
+        // There is no other value left, the repeated documents are returned in order.
+        match self.deferred.pop_front() {
+            Some((id, value)) => {
+                self.last_value = value;
+                Ok(Some(id))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl<I> Iterator for Diversify<'_, I>
+where
+    I: Iterator<Item = Result<DocumentId>>,
+{
+    type Item = Result<DocumentId>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_inner().transpose()
+    }
+}
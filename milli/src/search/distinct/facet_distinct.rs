@@ -21,21 +21,39 @@ const DOCID_SIZE: usize = size_of::<DocumentId>();
 /// care to keep the document we are currently on, and remove it from the excluded list. The next
 /// iterations will never contain any occurence of a document with the same distinct value as a
 /// document from previous iterations.
+///
+/// With several distinct attributes, only the documents sharing a value with the current document
+/// on every one of these attributes, i.e. the same tuple of values, are excluded.
+///
+/// When deduplicating, the documents sharing the content hash of the current document are
+/// also excluded, whatever their values on the distinct attributes.
 pub struct FacetDistinct<'a> {
-    distinct: FieldId,
+    distinct: Vec<FieldId>,
+    content_hash: Option<FieldId>,
     index: &'a Index,
     txn: &'a heed::RoTxn<'a>,
 }
 
 impl<'a> FacetDistinct<'a> {
     pub fn new(distinct: FieldId, index: &'a Index, txn: &'a heed::RoTxn<'a>) -> Self {
-        Self { distinct, index, txn }
+        Self::composite(vec![distinct], index, txn)
+    }
+
+    /// Collapses the documents on the tuple of values of the given attributes.
+    pub fn composite(distinct: Vec<FieldId>, index: &'a Index, txn: &'a heed::RoTxn<'a>) -> Self {
+        Self { distinct, content_hash: None, index, txn }
+    }
+
+    /// Also collapses the documents sharing the same value on the given content hash field.
+    pub fn deduplicate(self, content_hash: FieldId) -> Self {
+        Self { content_hash: Some(content_hash), ..self }
     }
 }
 
 pub struct FacetDistinctIter<'a> {
     candidates: RoaringBitmap,
-    distinct: FieldId,
+    distinct: Vec<FieldId>,
+    content_hash: Option<FieldId>,
     excluded: RoaringBitmap,
     index: &'a Index,
     iter_offset: usize,
@@ -43,49 +61,67 @@ pub struct FacetDistinctIter<'a> {
 }
 
 impl<'a> FacetDistinctIter<'a> {
-    fn facet_string_docids(&self, key: &str) -> heed::Result<Option<RoaringBitmap>> {
+    fn facet_string_docids(
+        &self,
+        field_id: FieldId,
+        key: &str,
+    ) -> heed::Result<Option<RoaringBitmap>> {
         self.index
             .facet_id_string_docids
-            .get(self.txn, &(self.distinct, key))
+            .get(self.txn, &(field_id, key))
             .map(|result| result.map(|(_original, docids)| docids))
     }
 
-    fn facet_number_docids(&self, key: f64) -> heed::Result<Option<RoaringBitmap>> {
+    fn facet_number_docids(
+        &self,
+        field_id: FieldId,
+        key: f64,
+    ) -> heed::Result<Option<RoaringBitmap>> {
         // get facet docids on level 0
-        self.index.facet_id_f64_docids.get(self.txn, &(self.distinct, 0, key, key))
+        self.index.facet_id_f64_docids.get(self.txn, &(field_id, 0, key, key))
     }
 
-    fn distinct_string(&mut self, id: DocumentId) -> Result<()> {
-        let iter = facet_string_values(id, self.distinct, self.index, self.txn)?;
+    /// Returns the documents that share at least one value with the given document on the field.
+    fn same_values_docids(&self, field_id: FieldId, id: DocumentId) -> Result<RoaringBitmap> {
+        let mut docids = RoaringBitmap::new();
 
-        for item in iter {
+        for item in facet_string_values(id, field_id, self.index, self.txn)? {
             let ((_, _, value), _) = item?;
-            let facet_docids =
-                self.facet_string_docids(value)?.ok_or(InternalError::DatabaseMissingEntry {
+            docids |= self.facet_string_docids(field_id, value)?.ok_or(
+                InternalError::DatabaseMissingEntry {
                     db_name: db_name::FACET_ID_STRING_DOCIDS,
                     key: None,
-                })?;
-            self.excluded |= facet_docids;
+                },
+            )?;
         }
 
-        self.excluded.remove(id);
-
-        Ok(())
-    }
-
-    fn distinct_number(&mut self, id: DocumentId) -> Result<()> {
-        let iter = facet_number_values(id, self.distinct, self.index, self.txn)?;
-
-        for item in iter {
+        for item in facet_number_values(id, field_id, self.index, self.txn)? {
             let ((_, _, value), _) = item?;
-            let facet_docids =
-                self.facet_number_docids(value)?.ok_or(InternalError::DatabaseMissingEntry {
+            docids |= self.facet_number_docids(field_id, value)?.ok_or(
+                InternalError::DatabaseMissingEntry {
                     db_name: db_name::FACET_ID_F64_DOCIDS,
                     key: None,
-                })?;
-            self.excluded |= facet_docids;
+                },
+            )?;
         }
 
+        Ok(docids)
+    }
+
+    fn distinct(&mut self, id: DocumentId) -> Result<()> {
+        let mut duplicates: Option<RoaringBitmap> = None;
+        for &field_id in &self.distinct {
+            let docids = self.same_values_docids(field_id, id)?;
+            duplicates = Some(match duplicates {
+                Some(duplicates) => duplicates & docids,
+                None => docids,
+            });
+        }
+
+        self.excluded |= duplicates.unwrap_or_default();
+        if let Some(field_id) = self.content_hash {
+            self.excluded |= self.same_values_docids(field_id, id)?;
+        }
         self.excluded.remove(id);
 
         Ok(())
@@ -102,8 +138,7 @@ impl<'a> FacetDistinctIter<'a> {
         match candidates_iter.next() {
             Some(id) => {
                 // We distinct the document id on its facet strings and facet numbers.
-                self.distinct_string(id)?;
-                self.distinct_number(id)?;
+                self.distinct(id)?;
 
                 // The first document of each iteration is kept, since the next call to
                 // `difference_with` will filter out all the documents for that facet value. By
@@ -123,7 +158,7 @@ fn facet_values_prefix_key(distinct: FieldId, id: DocumentId) -> [u8; FID_SIZE +
     concat_arrays!(distinct.to_be_bytes(), id.to_be_bytes())
 }
 
-fn facet_number_values<'a>(
+pub(super) fn facet_number_values<'a>(
     id: DocumentId,
     distinct: FieldId,
     index: &Index,
@@ -140,7 +175,7 @@ fn facet_number_values<'a>(
     Ok(iter)
 }
 
-fn facet_string_values<'a>(
+pub(super) fn facet_string_values<'a>(
     id: DocumentId,
     distinct: FieldId,
     index: &Index,
@@ -177,7 +212,8 @@ impl<'a> Distinct for FacetDistinct<'a> {
     fn distinct(&mut self, candidates: RoaringBitmap, excluded: RoaringBitmap) -> Self::Iter {
         FacetDistinctIter {
             candidates,
-            distinct: self.distinct,
+            distinct: self.distinct.clone(),
+            content_hash: self.content_hash,
             excluded,
             index: self.index,
             iter_offset: 0,
@@ -1,6 +1,8 @@
+mod diversify;
 mod facet_distinct;
 mod noop_distinct;
 
+pub use diversify::{Diversify, DiversityValue};
 pub use facet_distinct::FacetDistinct;
 pub use noop_distinct::NoopDistinct;
 use roaring::RoaringBitmap;
@@ -1,8 +1,11 @@
+use std::cmp::Reverse;
 use std::collections::{BTreeMap, HashSet};
 use std::ops::Bound::Unbounded;
 use std::{fmt, mem};
 
 use heed::types::ByteSlice;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use roaring::RoaringBitmap;
 
 use crate::error::UserError;
@@ -10,23 +13,103 @@ use crate::facet::FacetType;
 use crate::heed_codec::facet::{
     FacetStringLevelZeroCodec, FieldDocIdFacetF64Codec, FieldDocIdFacetStringCodec,
 };
-use crate::search::facet::{FacetNumberIter, FacetNumberRange, FacetStringIter};
+use crate::search::facet::{FacetNumberIter, FacetNumberRange, FacetStringIter, MandatoryFilter};
 use crate::{FieldId, Index, Result};
 
 /// Threshold on the number of candidates that will make
 /// the system to choose between one algorithm or another.
 const CANDIDATES_THRESHOLD: u64 = 3000;
 
+/// The number of times a value must be seen in a sample
+/// for its extrapolated count to be considered reliable.
+const MIN_SAMPLED_OCCURRENCES: u64 = 30;
+
+/// The seed used to draw the samples, the same candidates always give the same sample.
+const SAMPLE_SEED: u64 = 0x5eed;
+
+/// The facet distribution along with a flag indicating if
+/// some facet values were dropped to respect the values limit.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FacetDistributionResult {
+    pub distribution: BTreeMap<String, BTreeMap<String, u64>>,
+    pub truncated: bool,
+    /// Defined when the counts were extrapolated from a sample of the candidates.
+    pub sampling: Option<FacetDistributionSampling>,
+}
+
+/// Describes the sample used to extrapolate the counts of a facet distribution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FacetDistributionSampling {
+    /// The number of candidates the counts were computed on.
+    pub sample_size: u64,
+    /// The number of candidates the counts were extrapolated to.
+    pub candidates: u64,
+    /// `false` if a returned value was seen too few times in the sample
+    /// for its extrapolated count to be reliable.
+    pub confident: bool,
+}
+
+/// The order of the values of a facet distribution, see [`FacetDistribution::order_by`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderBy {
+    /// The most frequent values first, the values with the same count sorted lexicographically.
+    Count,
+    /// The values sorted lexicographically, i.e. by their bytes.
+    Lexicographic,
+}
+
+impl Default for OrderBy {
+    fn default() -> OrderBy {
+        OrderBy::Count
+    }
+}
+
+/// Computes the number of documents associated with each value of the faceted fields.
+///
+/// A document is counted once per distinct value of a facet, even if the value appears
+/// multiple times in it, e.g. `{ "variants": [{ "color": "red" }, { "color": "red" }] }`
+/// counts once for the `red` value of the `variants.color` facet. The values of an array
+/// of objects and of a key containing a dot, like `{ "variants.color": "red" }`, are
+/// flattened under the same facet name. The facet strings are compared once normalized,
+/// only one of their original spellings is returned. Finally, the counts of a number and
+/// a string sharing the same representation, like `1` and `"1"`, are summed as they are
+/// returned under the same key. The booleans are always returned as `true` and `false`,
+/// whatever the spelling of the strings of the facet.
 pub struct FacetDistribution<'a> {
     facets: Option<HashSet<String>>,
     candidates: Option<RoaringBitmap>,
+    max_values_per_facet: Option<usize>,
+    order_by: OrderBy,
+    sample_size: Option<u64>,
+    mandatory_filter: Option<MandatoryFilter<'a>>,
     rtxn: &'a heed::RoTxn<'a>,
     index: &'a Index,
 }
 
 impl<'a> FacetDistribution<'a> {
     pub fn new(rtxn: &'a heed::RoTxn, index: &'a Index) -> FacetDistribution<'a> {
-        FacetDistribution { facets: None, candidates: None, rtxn, index }
+        FacetDistribution {
+            facets: None,
+            candidates: None,
+            max_values_per_facet: None,
+            order_by: OrderBy::default(),
+            sample_size: None,
+            mandatory_filter: None,
+            rtxn,
+            index,
+        }
+    }
+
+    /// Creates a facet distribution that only counts the documents matching the mandatory
+    /// filter, see [`RestrictedIndex`](crate::RestrictedIndex).
+    pub(crate) fn restricted(
+        rtxn: &'a heed::RoTxn,
+        index: &'a Index,
+        mandatory_filter: MandatoryFilter<'a>,
+    ) -> FacetDistribution<'a> {
+        let mut distribution = FacetDistribution::new(rtxn, index);
+        distribution.mandatory_filter = Some(mandatory_filter);
+        distribution
     }
 
     pub fn facets<I: IntoIterator<Item = A>, A: AsRef<str>>(&mut self, names: I) -> &mut Self {
@@ -39,8 +122,54 @@ impl<'a> FacetDistribution<'a> {
         self
     }
 
+    /// Limits the number of values returned for each facet to the `max` first ones in the
+    /// order defined by [`FacetDistribution::order_by`], the most frequent ones by default.
+    ///
+    /// The memory used while iterating on the facet databases is bounded too: once a facet
+    /// accumulates twice this number of values only the first ones are kept. The values
+    /// are never dropped when the facets of the candidates are read one by one, as a
+    /// dropped value could be seen again in a later candidate and be undercounted.
+    pub fn max_values_per_facet(&mut self, max: usize) -> &mut Self {
+        self.max_values_per_facet = Some(max);
+        self
+    }
+
+    /// Computes the counts on a uniform sample of `size` candidates, when there are more
+    /// candidates than that, and extrapolates them to all the candidates.
+    ///
+    /// It is an alternative to the exact counting for the very large sets of candidates,
+    /// the sampling is described by [`FacetDistributionResult::sampling`].
+    pub fn sample(&mut self, size: u64) -> &mut Self {
+        self.sample_size = Some(size);
+        self
+    }
+
+    /// Defines which values are kept when the number of values is limited, the most
+    /// frequent ones by default, and the order of [`FacetDistribution::execute_ordered`].
+    pub fn order_by(&mut self, order_by: OrderBy) -> &mut Self {
+        self.order_by = order_by;
+        self
+    }
+
+    /// Reduces the distribution to its first values once it grows too much.
+    fn spill<K: Ord, V>(
+        &self,
+        distribution: &mut BTreeMap<K, V>,
+        count: impl Fn(&V) -> u64,
+        name: impl for<'e> Fn(&'e K, &'e V) -> &'e str,
+        truncated: &mut bool,
+    ) {
+        if let Some(max) = self.max_values_per_facet {
+            if distribution.len() > max.saturating_mul(2) {
+                *truncated |= retain_top_values(distribution, max, self.order_by, count, name);
+            }
+        }
+    }
+
     /// There is a small amount of candidates OR we ask for facet string values so we
     /// decide to iterate over the facet values of each one of them, one by one.
+    ///
+    /// The distribution is never spilled here, a value can appear in any candidate.
     fn facet_distribution_from_documents(
         &self,
         field_id: FieldId,
@@ -89,10 +218,9 @@ impl<'a> FacetDistribution<'a> {
                     }
                 }
 
-                let iter = normalized_distribution
-                    .into_iter()
-                    .map(|(_normalized, (original, count))| (original.to_string(), count));
-                distribution.extend(iter);
+                for (_normalized, (original, count)) in normalized_distribution {
+                    *distribution.entry(original.to_string()).or_insert(0) += count;
+                }
             }
         }
 
@@ -106,6 +234,7 @@ impl<'a> FacetDistribution<'a> {
         field_id: FieldId,
         candidates: &RoaringBitmap,
         distribution: &mut BTreeMap<String, u64>,
+        truncated: &mut bool,
     ) -> heed::Result<()> {
         let iter =
             FacetNumberIter::new_non_reducing(self.rtxn, self.index, field_id, candidates.clone())?;
@@ -114,7 +243,8 @@ impl<'a> FacetDistribution<'a> {
             let (value, mut docids) = result?;
             docids &= candidates;
             if !docids.is_empty() {
-                distribution.insert(value.to_string(), docids.len());
+                *distribution.entry(value.to_string()).or_insert(0) += docids.len();
+                self.spill(distribution, |count| *count, |value, _| value.as_str(), truncated);
             }
         }
 
@@ -126,6 +256,7 @@ impl<'a> FacetDistribution<'a> {
         field_id: FieldId,
         candidates: &RoaringBitmap,
         distribution: &mut BTreeMap<String, u64>,
+        truncated: &mut bool,
     ) -> heed::Result<()> {
         let iter =
             FacetStringIter::new_non_reducing(self.rtxn, self.index, field_id, candidates.clone())?;
@@ -134,7 +265,8 @@ impl<'a> FacetDistribution<'a> {
             let (_normalized, original, mut docids) = result?;
             docids &= candidates;
             if !docids.is_empty() {
-                distribution.insert(original.to_string(), docids.len());
+                *distribution.entry(original.to_string()).or_insert(0) += docids.len();
+                self.spill(distribution, |count| *count, |value, _| value.as_str(), truncated);
             }
         }
 
@@ -146,15 +278,27 @@ impl<'a> FacetDistribution<'a> {
     fn facet_values_from_raw_facet_database(
         &self,
         field_id: FieldId,
+        truncated: &mut bool,
     ) -> heed::Result<BTreeMap<String, u64>> {
         let mut distribution = BTreeMap::new();
+        // The soft deleted documents are still in the databases until they are purged.
+        let soft_deleted = self.index.soft_deleted_documents_ids(self.rtxn)?;
+        let documents_count = |docids: RoaringBitmap| match soft_deleted.is_empty() {
+            true => docids.len(),
+            false => (docids - &soft_deleted).len(),
+        };
 
         let db = self.index.facet_id_f64_docids;
         let range = FacetNumberRange::new(self.rtxn, db, field_id, 0, Unbounded, Unbounded)?;
 
         for result in range {
             let ((_, _, value, _), docids) = result?;
-            distribution.insert(value.to_string(), docids.len());
+            let count = documents_count(docids);
+            if count == 0 {
+                continue;
+            }
+            distribution.insert(value.to_string(), count);
+            self.spill(&mut distribution, |count| *count, |value, _| value.as_str(), truncated);
         }
 
         let iter = self
@@ -167,22 +311,36 @@ impl<'a> FacetDistribution<'a> {
         let mut normalized_distribution = BTreeMap::new();
         for result in iter {
             let ((_, normalized_value), (original_value, docids)) = result?;
-            normalized_distribution.insert(normalized_value, (original_value, docids.len()));
+            let count = documents_count(docids);
+            if count == 0 {
+                continue;
+            }
+            normalized_distribution.insert(normalized_value, (original_value, count));
+            self.spill(
+                &mut normalized_distribution,
+                |(_, count)| *count,
+                |_, (original, _)| *original,
+                truncated,
+            );
         }
 
-        let iter = normalized_distribution
-            .into_iter()
-            .map(|(_normalized, (original, count))| (original.to_string(), count));
-        distribution.extend(iter);
+        for (_normalized, (original, count)) in normalized_distribution {
+            *distribution.entry(original.to_string()).or_insert(0) += count;
+        }
 
         Ok(distribution)
     }
 
-    fn facet_values(&self, field_id: FieldId) -> heed::Result<BTreeMap<String, u64>> {
+    fn facet_values(
+        &self,
+        field_id: FieldId,
+        candidates: Option<&RoaringBitmap>,
+        truncated: &mut bool,
+    ) -> heed::Result<BTreeMap<String, u64>> {
         use FacetType::{Number, String};
 
-        match self.candidates {
-            Some(ref candidates) => {
+        let mut distribution = match candidates {
+            Some(candidates) => {
                 // Classic search, candidates were specified, we must return facet values only related
                 // to those candidates. We also enter here for facet strings for performance reasons.
                 let mut distribution = BTreeMap::new();
@@ -204,20 +362,70 @@ impl<'a> FacetDistribution<'a> {
                         field_id,
                         candidates,
                         &mut distribution,
+                        truncated,
                     )?;
                     self.facet_strings_distribution_from_facet_levels(
                         field_id,
                         candidates,
                         &mut distribution,
+                        truncated,
                     )?;
                 }
-                Ok(distribution)
+                distribution
+            }
+            None => self.facet_values_from_raw_facet_database(field_id, truncated)?,
+        };
+
+        // There are only two booleans, they are counted from their documents ids.
+        for value in [false, true] {
+            let mut docids =
+                self.index.boolean_faceted_documents_ids(self.rtxn, field_id, value)?;
+            match candidates {
+                Some(candidates) => docids &= candidates,
+                None => self.index.remove_soft_deleted_documents(self.rtxn, &mut docids)?,
+            }
+            if !docids.is_empty() {
+                *distribution.entry(value.to_string()).or_insert(0) += docids.len();
             }
-            None => self.facet_values_from_raw_facet_database(field_id),
         }
+
+        if let Some(max) = self.max_values_per_facet {
+            *truncated |= retain_top_values(
+                &mut distribution,
+                max,
+                self.order_by,
+                |count| *count,
+                |value, _| value.as_str(),
+            );
+        }
+
+        Ok(distribution)
     }
 
     pub fn execute(&self) -> Result<BTreeMap<String, BTreeMap<String, u64>>> {
+        self.execute_with_truncation().map(|result| result.distribution)
+    }
+
+    /// Computes the facet distribution and returns the values of each facet
+    /// in the order defined by [`FacetDistribution::order_by`].
+    pub fn execute_ordered(&self) -> Result<BTreeMap<String, Vec<(String, u64)>>> {
+        let distribution = self.execute()?;
+        Ok(distribution
+            .into_iter()
+            .map(|(name, values)| {
+                // the values are already sorted lexicographically
+                let mut values: Vec<_> = values.into_iter().collect();
+                if self.order_by == OrderBy::Count {
+                    values.sort_by_key(|(_, count)| Reverse(*count));
+                }
+                (name, values)
+            })
+            .collect())
+    }
+
+    /// Computes the facet distribution and reports if some values were dropped
+    /// to respect the limit defined by [`FacetDistribution::max_values_per_facet`].
+    pub fn execute_with_truncation(&self) -> Result<FacetDistributionResult> {
         let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
         let filterable_fields = self.index.filterable_fields(self.rtxn)?;
 
@@ -239,25 +447,142 @@ impl<'a> FacetDistribution<'a> {
             None => filterable_fields,
         };
 
+        // The candidates are always intersected with the mandatory filter.
+        let restricted_candidates = match self.mandatory_filter {
+            Some(ref mandatory_filter) => {
+                let docids = mandatory_filter.evaluate(self.rtxn, self.index)?;
+                match self.candidates {
+                    Some(ref candidates) => Some(candidates & docids),
+                    None => Some(docids),
+                }
+            }
+            None => None,
+        };
+        let candidates = restricted_candidates.as_ref().or(self.candidates.as_ref());
+
+        let sample = match self.sample_size {
+            Some(size) => {
+                let candidates_len = match candidates {
+                    Some(candidates) => candidates.len(),
+                    None => self.index.number_of_documents(self.rtxn)?,
+                };
+                if candidates_len > size {
+                    let sample = match candidates {
+                        Some(candidates) => sample_candidates(candidates, size),
+                        None => sample_candidates(&self.index.documents_ids(self.rtxn)?, size),
+                    };
+                    Some((sample, candidates_len))
+                } else {
+                    None
+                }
+            }
+            None => None,
+        };
+
         let mut distribution = BTreeMap::new();
+        let mut truncated = false;
+        let mut confident = true;
         for (fid, name) in fields_ids_map.iter() {
             if crate::is_faceted(name, &fields) {
-                let values = self.facet_values(fid)?;
+                let values = match sample {
+                    Some((ref sample, candidates_len)) => {
+                        let mut values = self.facet_values(fid, Some(sample), &mut truncated)?;
+                        for count in values.values_mut() {
+                            confident &= *count >= MIN_SAMPLED_OCCURRENCES;
+                            *count = extrapolate(*count, sample.len(), candidates_len);
+                        }
+                        values
+                    }
+                    None => self.facet_values(fid, candidates, &mut truncated)?,
+                };
                 distribution.insert(name.to_string(), values);
             }
         }
 
-        Ok(distribution)
+        let sampling = sample.map(|(sample, candidates)| FacetDistributionSampling {
+            sample_size: sample.len(),
+            candidates,
+            confident,
+        });
+
+        Ok(FacetDistributionResult { distribution, truncated, sampling })
+    }
+}
+
+/// Draws a uniform sample of `size` documents ids from the candidates.
+fn sample_candidates(candidates: &RoaringBitmap, size: u64) -> RoaringBitmap {
+    let mut rng = StdRng::seed_from_u64(SAMPLE_SEED);
+    let mut positions =
+        rand::seq::index::sample(&mut rng, candidates.len() as usize, size as usize).into_vec();
+    positions.sort_unstable();
+
+    // The positions are sorted, we only need to skip the candidates between them.
+    let mut iter = candidates.iter();
+    let mut previous = 0;
+    let mut sample = RoaringBitmap::new();
+    for position in positions {
+        if let Some(docid) = iter.nth(position - previous) {
+            sample.insert(docid);
+        }
+        previous = position + 1;
+    }
+
+    sample
+}
+
+/// Extrapolates a count computed on a sample to all the candidates, rounded to the nearest.
+fn extrapolate(count: u64, sample_size: u64, candidates: u64) -> u64 {
+    ((count as u128 * candidates as u128 + sample_size as u128 / 2) / sample_size as u128) as u64
+}
+
+/// Only keeps the `limit` first entries in the given order: the ones with the highest counts,
+/// the ties being broken by keeping the smallest keys, or the ones with the smallest names.
+/// Returns `true` if some entries were removed.
+fn retain_top_values<K: Ord, V>(
+    distribution: &mut BTreeMap<K, V>,
+    limit: usize,
+    order_by: OrderBy,
+    count: impl Fn(&V) -> u64,
+    name: impl for<'e> Fn(&'e K, &'e V) -> &'e str,
+) -> bool {
+    if distribution.len() <= limit {
+        return false;
     }
+
+    let mut entries: Vec<_> = mem::take(distribution).into_iter().collect();
+    match order_by {
+        // the sort is stable, the entries with the same count stay sorted by key
+        OrderBy::Count => entries.sort_by_key(|(_, value)| Reverse(count(value))),
+        OrderBy::Lexicographic => {
+            entries.sort_by(|(ka, va), (kb, vb)| name(ka, va).cmp(name(kb, vb)))
+        }
+    }
+    entries.truncate(limit);
+    distribution.extend(entries);
+
+    true
 }
 
 impl fmt::Debug for FacetDistribution<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let FacetDistribution { facets, candidates, rtxn: _, index: _ } = self;
+        let FacetDistribution {
+            facets,
+            candidates,
+            max_values_per_facet,
+            order_by,
+            sample_size,
+            mandatory_filter,
+            rtxn: _,
+            index: _,
+        } = self;
 
         f.debug_struct("FacetDistribution")
             .field("facets", facets)
             .field("candidates", candidates)
+            .field("max_values_per_facet", max_values_per_facet)
+            .field("order_by", order_by)
+            .field("sample_size", sample_size)
+            .field("mandatory_filter", mandatory_filter)
             .finish()
     }
 }
@@ -5,6 +5,7 @@ use heed::types::{ByteSlice, DecodeIgnore};
 use heed::{Database, LazyDecode, RoRange, RoRevRange};
 use roaring::RoaringBitmap;
 
+use super::{is_above, is_below};
 use crate::heed_codec::facet::FacetLevelValueF64Codec;
 use crate::heed_codec::CboRoaringBitmapCodec;
 use crate::{FieldId, Index};
@@ -112,12 +113,25 @@ impl<'t> Iterator for FacetNumberRevRange<'t> {
     }
 }
 
+/// An iterator that is used to explore the facet numbers level by level,
+/// it will only return the facet numbers that are associated with the
+/// candidates documents ids given, in ascending or descending order.
+///
+/// ```ignore
+/// let iter = FacetNumberIter::new_reducing(&rtxn, &index, field_id, candidates)?
+///     .bounds(Included(10.0), Excluded(20.0));
+/// for result in iter {
+///     let (value, docids) = result?;
+/// }
+/// ```
 pub struct FacetNumberIter<'t> {
     rtxn: &'t heed::RoTxn<'t>,
     db: Database<FacetLevelValueF64Codec, CboRoaringBitmapCodec>,
     field_id: FieldId,
     level_iters: Vec<(RoaringBitmap, Either<FacetNumberRange<'t>, FacetNumberRevRange<'t>>)>,
     must_reduce: bool,
+    left: Bound<f64>,
+    right: Bound<f64>,
 }
 
 impl<'t> FacetNumberIter<'t> {
@@ -135,7 +149,15 @@ impl<'t> FacetNumberIter<'t> {
         let highest_iter =
             FacetNumberRange::new(rtxn, db, field_id, highest_level, Unbounded, Unbounded)?;
         let level_iters = vec![(documents_ids, Left(highest_iter))];
-        Ok(FacetNumberIter { rtxn, db, field_id, level_iters, must_reduce: true })
+        Ok(FacetNumberIter {
+            rtxn,
+            db,
+            field_id,
+            level_iters,
+            must_reduce: true,
+            left: Unbounded,
+            right: Unbounded,
+        })
     }
 
     /// Create a `FacetNumberIter` that will iterate on the different facet entries in reverse
@@ -152,7 +174,15 @@ impl<'t> FacetNumberIter<'t> {
         let highest_iter =
             FacetNumberRevRange::new(rtxn, db, field_id, highest_level, Unbounded, Unbounded)?;
         let level_iters = vec![(documents_ids, Right(highest_iter))];
-        Ok(FacetNumberIter { rtxn, db, field_id, level_iters, must_reduce: true })
+        Ok(FacetNumberIter {
+            rtxn,
+            db,
+            field_id,
+            level_iters,
+            must_reduce: true,
+            left: Unbounded,
+            right: Unbounded,
+        })
     }
 
     /// Create a `FacetNumberIter` that will iterate on the different facet entries
@@ -170,7 +200,23 @@ impl<'t> FacetNumberIter<'t> {
         let highest_iter =
             FacetNumberRange::new(rtxn, db, field_id, highest_level, Unbounded, Unbounded)?;
         let level_iters = vec![(documents_ids, Left(highest_iter))];
-        Ok(FacetNumberIter { rtxn, db, field_id, level_iters, must_reduce: false })
+        Ok(FacetNumberIter {
+            rtxn,
+            db,
+            field_id,
+            level_iters,
+            must_reduce: false,
+            left: Unbounded,
+            right: Unbounded,
+        })
+    }
+
+    /// Only returns the facet values within the given bounds, a document is returned
+    /// for its first value within the bounds when the iterator is reducing.
+    pub fn bounds(mut self, left: Bound<f64>, right: Bound<f64>) -> FacetNumberIter<'t> {
+        self.left = left;
+        self.right = right;
+        self
     }
 
     fn highest_level<X>(
@@ -194,6 +240,7 @@ impl<'t> Iterator for FacetNumberIter<'t> {
 
     fn next(&mut self) -> Option<Self::Item> {
         'outer: loop {
+            let is_bounded = !matches!((self.left, self.right), (Unbounded, Unbounded));
             let (documents_ids, last) = self.level_iters.last_mut()?;
             let is_ascending = last.is_left();
             for result in last {
@@ -206,13 +253,38 @@ impl<'t> Iterator for FacetNumberIter<'t> {
 
                 match result {
                     Ok(((_fid, level, left, right), mut docids)) => {
+                        // The groups are sorted, once a group is past the bounds all the next
+                        // ones are too. The groups before the bounds are skipped.
+                        let past_bounds = match is_ascending {
+                            true => !is_below(left, self.right),
+                            false => !is_above(right, self.left),
+                        };
+                        if past_bounds {
+                            break;
+                        }
+                        if !is_above(right, self.left) || !is_below(left, self.right) {
+                            continue;
+                        }
+
+                        // A group partially within the bounds may contain documents that are
+                        // only returned by a next group, it can't reduce the documents ids.
+                        let within_bounds =
+                            is_above(left, self.left) && is_below(right, self.right);
+
                         docids &= &*documents_ids;
                         if !docids.is_empty() {
-                            if self.must_reduce {
+                            if self.must_reduce && within_bounds {
                                 *documents_ids -= &docids;
                             }
 
                             if level == 0 {
+                                // The groups partially within the bounds didn't reduce
+                                // their documents ids.
+                                if self.must_reduce && is_bounded {
+                                    for (level_documents_ids, _) in &mut self.level_iters {
+                                        *level_documents_ids -= &docids;
+                                    }
+                                }
                                 return Some(Ok((left, docids)));
                             }
 
@@ -134,6 +134,7 @@ use heed::types::{ByteSlice, DecodeIgnore};
 use heed::{Database, LazyDecode, RoRange, RoRevRange};
 use roaring::RoaringBitmap;
 
+use super::{is_above, is_below};
 use crate::heed_codec::facet::{
     FacetLevelValueU32Codec, FacetStringLevelZeroCodec, FacetStringLevelZeroValueCodec,
     FacetStringZeroBoundsValueCodec,
@@ -406,15 +407,22 @@ type EitherStringRevRange<'t> =
 /// An iterator that is used to explore the facet strings level by level,
 /// it will only return facets strings that are associated with the
 /// candidates documents ids given.
+///
+/// It yields the normalized facet string, the original one and the documents ids.
 pub struct FacetStringIter<'t> {
     rtxn: &'t heed::RoTxn<'t>,
     db: Database<ByteSlice, ByteSlice>,
     field_id: FieldId,
     level_iters: Vec<(RoaringBitmap, Either<EitherStringRange<'t>, EitherStringRevRange<'t>>)>,
     must_reduce: bool,
+    left: Bound<String>,
+    right: Bound<String>,
 }
 
 impl<'t> FacetStringIter<'t> {
+    /// Create a `FacetStringIter` that will iterate on the different facet entries
+    /// (facet value + documents ids) and that will reduce the given documents ids
+    /// while iterating on the different facet levels.
     pub fn new_reducing(
         rtxn: &'t heed::RoTxn,
         index: &'t Index,
@@ -429,9 +437,14 @@ impl<'t> FacetStringIter<'t> {
             field_id,
             level_iters: vec![(documents_ids, Left(highest_iter))],
             must_reduce: true,
+            left: Unbounded,
+            right: Unbounded,
         })
     }
 
+    /// Create a `FacetStringIter` that will iterate on the different facet entries in reverse
+    /// (facet value + documents ids) and that will reduce the given documents ids
+    /// while iterating on the different facet levels.
     pub fn new_reverse_reducing(
         rtxn: &'t heed::RoTxn,
         index: &'t Index,
@@ -446,9 +459,15 @@ impl<'t> FacetStringIter<'t> {
             field_id,
             level_iters: vec![(documents_ids, Right(highest_reverse_iter))],
             must_reduce: true,
+            left: Unbounded,
+            right: Unbounded,
         })
     }
 
+    /// Create a `FacetStringIter` that will iterate on the different facet entries
+    /// (facet value + documents ids) and that will not reduce the given documents ids
+    /// while iterating on the different facet levels, possibly returning multiple times
+    /// a document id associated with multiple facet values.
     pub fn new_non_reducing(
         rtxn: &'t heed::RoTxn,
         index: &'t Index,
@@ -463,9 +482,22 @@ impl<'t> FacetStringIter<'t> {
             field_id,
             level_iters: vec![(documents_ids, Left(highest_iter))],
             must_reduce: false,
+            left: Unbounded,
+            right: Unbounded,
         })
     }
 
+    /// Only returns the facet values within the given bounds, a document is returned
+    /// for its first value within the bounds when the iterator is reducing.
+    ///
+    /// The bounds are normalized like the facet values, i.e. trimmed and lowercased.
+    pub fn bounds(mut self, left: Bound<&str>, right: Bound<&str>) -> FacetStringIter<'t> {
+        let normalize = |value: &str| value.trim().to_lowercase();
+        self.left = map_bound(left, normalize);
+        self.right = map_bound(right, normalize);
+        self
+    }
+
     fn highest_level<X, Y>(
         rtxn: &'t heed::RoTxn,
         db: Database<X, Y>,
@@ -541,6 +573,7 @@ impl<'t> Iterator for FacetStringIter<'t> {
 
     fn next(&mut self) -> Option<Self::Item> {
         'outer: loop {
+            let is_bounded = !matches!((&self.left, &self.right), (Unbounded, Unbounded));
             let (documents_ids, last) = self.level_iters.last_mut()?;
             let is_ascending = last.is_left();
 
@@ -562,9 +595,33 @@ impl<'t> Iterator for FacetStringIter<'t> {
                     for result in group {
                         match result {
                             Ok(((level, left, right), (string_bounds, mut docids))) => {
+                                // Only the groups of the level 1 know the strings they cover,
+                                // the groups of the higher levels are always explored.
+                                let within_bounds = match string_bounds {
+                                    Some((left, right)) => {
+                                        let past_bounds = match is_ascending {
+                                            true => !is_below(left, as_str(&self.right)),
+                                            false => !is_above(right, as_str(&self.left)),
+                                        };
+                                        if past_bounds {
+                                            break;
+                                        }
+                                        if !is_above(right, as_str(&self.left))
+                                            || !is_below(left, as_str(&self.right))
+                                        {
+                                            continue;
+                                        }
+                                        is_above(left, as_str(&self.left))
+                                            && is_below(right, as_str(&self.right))
+                                    }
+                                    None => !is_bounded,
+                                };
+
                                 docids &= &*documents_ids;
                                 if !docids.is_empty() {
-                                    if self.must_reduce {
+                                    // A group partially within the bounds may contain documents
+                                    // that are only returned by a next group.
+                                    if self.must_reduce && within_bounds {
                                         *documents_ids -= &docids;
                                     }
 
@@ -632,10 +689,30 @@ impl<'t> Iterator for FacetStringIter<'t> {
                     for result in zero_level {
                         match result {
                             Ok((normalized, original, mut docids)) => {
+                                let past_bounds = match is_ascending {
+                                    true => !is_below(normalized, as_str(&self.right)),
+                                    false => !is_above(normalized, as_str(&self.left)),
+                                };
+                                if past_bounds {
+                                    break;
+                                }
+                                if !is_above(normalized, as_str(&self.left))
+                                    || !is_below(normalized, as_str(&self.right))
+                                {
+                                    continue;
+                                }
+
                                 docids &= &*documents_ids;
                                 if !docids.is_empty() {
                                     if self.must_reduce {
                                         *documents_ids -= &docids;
+                                        // The groups partially within the bounds didn't reduce
+                                        // their documents ids.
+                                        if is_bounded {
+                                            for (level_documents_ids, _) in &mut self.level_iters {
+                                                *level_documents_ids -= &docids;
+                                            }
+                                        }
                                     }
                                     return Some(Ok((normalized, original, docids)));
                                 }
@@ -650,3 +727,19 @@ impl<'t> Iterator for FacetStringIter<'t> {
         }
     }
 }
+
+fn map_bound<F: Fn(&str) -> String>(bound: Bound<&str>, f: F) -> Bound<String> {
+    match bound {
+        Included(value) => Included(f(value)),
+        Excluded(value) => Excluded(f(value)),
+        Unbounded => Unbounded,
+    }
+}
+
+fn as_str(bound: &Bound<String>) -> Bound<&str> {
+    match bound {
+        Included(value) => Included(value.as_str()),
+        Excluded(value) => Excluded(value.as_str()),
+        Unbounded => Unbounded,
+    }
+}
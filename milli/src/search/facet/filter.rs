@@ -1,29 +1,113 @@
 use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::fmt::{Debug, Display};
-use std::ops::Bound::{self, Excluded, Included};
-use std::ops::Deref;
+use std::ops::Bound::{self, Excluded, Included, Unbounded};
 
 use either::Either;
 pub use filter_parser::{Condition, Error as FPError, FilterCondition, Span, Token};
 use heed::types::DecodeIgnore;
 use log::debug;
+use meilisearch_tokenizer::{Analyzer, AnalyzerConfig, TokenKind};
 use roaring::RoaringBitmap;
 
 use super::FacetNumberRange;
-use crate::error::{Error, UserError};
+use crate::error::{Error, InternalError, UserError};
+use crate::facet::NumberCoercion;
+use crate::hashed_fields::hash_facet_value;
 use crate::heed_codec::facet::{
     FacetLevelValueF64Codec, FacetStringLevelZeroCodec, FacetStringLevelZeroValueCodec,
 };
+use crate::update::{json_to_string, process_tokens};
 use crate::{
-    distance_between_two_points, lat_lng_to_xyz, CboRoaringBitmapCodec, FieldId, Index, Result,
+    absolute_from_relative_position, lat_lng_to_xyz, round_position, CboRoaringBitmapCodec,
+    FieldId, Index, Result, BEU32,
 };
 
 /// The maximum number of filters the filter AST can process.
 const MAX_FILTER_DEPTH: usize = 2000;
 
+/// The reserved attribute to filter on the internal documents ids.
+const DOCID_ATTRIBUTE: &str = "_docid";
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Filter<'a> {
     condition: FilterCondition<'a>,
+    number_comparison: NumberComparison,
+}
+
+/// Defines how the numbers of a filter are compared with the facet numbers of the documents.
+#[derive(Debug, Clone, Copy)]
+pub enum NumberComparison {
+    /// The numbers are compared as floats, `price = 10` matches `10` and `10.0`.
+    ///
+    /// Two numbers are considered equal when their difference is lower than or equal
+    /// to the epsilon, the bounds of the range operators are widened accordingly.
+    Float { epsilon: f64 },
+    /// The numbers of the filter must be integers and are compared exactly,
+    /// `price = 10.5` is rejected instead of silently matching nothing.
+    Integer,
+}
+
+impl NumberComparison {
+    fn epsilon(&self) -> f64 {
+        match self {
+            NumberComparison::Float { epsilon } => epsilon.abs(),
+            NumberComparison::Integer => 0.0,
+        }
+    }
+
+    /// Parses the number of the given token, making sure it respects the comparison mode.
+    fn parse<'t>(&self, token: &Token<'t>) -> std::result::Result<f64, FPError<'t>> {
+        let number: f64 = token.parse()?;
+        match self {
+            NumberComparison::Integer if number.fract() != 0.0 => {
+                Err(token.as_external_error(FilterError::NotAnInteger(number)))
+            }
+            _ => Ok(number),
+        }
+    }
+}
+
+impl PartialEq for NumberComparison {
+    fn eq(&self, other: &NumberComparison) -> bool {
+        match (self, other) {
+            // The epsilons are compared bit by bit for the equality to be reflexive.
+            (NumberComparison::Float { epsilon: a }, NumberComparison::Float { epsilon: b }) => {
+                a.to_bits() == b.to_bits()
+            }
+            (NumberComparison::Integer, NumberComparison::Integer) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for NumberComparison {}
+
+impl Default for NumberComparison {
+    fn default() -> NumberComparison {
+        NumberComparison::Float { epsilon: 0.0 }
+    }
+}
+
+/// A filter that can't be removed from the reads it is attached to, see [`RestrictedIndex`].
+///
+/// It is meant to hold the tenant or the access restrictions of a user, the documents
+/// it doesn't match are never returned whatever the other filters of the search.
+///
+/// [`RestrictedIndex`]: crate::RestrictedIndex
+#[derive(Debug, Clone, PartialEq)]
+pub struct MandatoryFilter<'a> {
+    filter: Filter<'a>,
+}
+
+impl<'a> MandatoryFilter<'a> {
+    pub fn new(filter: Filter<'a>) -> MandatoryFilter<'a> {
+        MandatoryFilter { filter }
+    }
+
+    pub(crate) fn evaluate(&self, rtxn: &heed::RoTxn, index: &Index) -> Result<RoaringBitmap> {
+        self.filter.evaluate(rtxn, index)
+    }
 }
 
 #[derive(Debug)]
@@ -32,6 +116,7 @@ enum FilterError<'a> {
     BadGeo(&'a str),
     BadGeoLat(f64),
     BadGeoLng(f64),
+    NotAnInteger(f64),
     Reserved(&'a str),
     TooDeep,
 }
@@ -70,10 +155,25 @@ impl<'a> Display for FilterError<'a> {
             Self::BadGeo(keyword) => write!(f, "`{}` is a reserved keyword and thus can't be used as a filter expression. Use the _geoRadius(latitude, longitude, distance) built-in rule to filter on _geo field coordinates.", keyword),
             Self::BadGeoLat(lat) => write!(f, "Bad latitude `{}`. Latitude must be contained between -90 and 90 degrees. ", lat),
             Self::BadGeoLng(lng) => write!(f, "Bad longitude `{}`. Longitude must be contained between -180 and 180 degrees. ", lng),
+            Self::NotAnInteger(number) => write!(f, "Bad number `{}`. Numbers must be integers when compared as integers.", number),
         }
     }
 }
 
+/// Returns the boolean of an unquoted `true` or `false` value, they only match the JSON
+/// booleans of the documents while the quoted ones, like `"true"`, only match the strings.
+fn boolean_value(token: &Token) -> Option<bool> {
+    if token.is_quoted() {
+        None
+    } else if token.value().eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if token.value().eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
 impl<'a> From<FPError<'a>> for Error {
     fn from(error: FPError<'a>) -> Self {
         Self::UserError(UserError::InvalidFilter(error.to_string()))
@@ -137,7 +237,7 @@ impl<'a> Filter<'a> {
             return Err(token.as_external_error(FilterError::TooDeep).into());
         }
 
-        Ok(ands.map(|ands| Self { condition: ands }))
+        Ok(ands.map(Self::from))
     }
 
     pub fn from_str(expression: &'a str) -> Result<Option<Self>> {
@@ -151,7 +251,18 @@ impl<'a> Filter<'a> {
             return Err(token.as_external_error(FilterError::TooDeep).into());
         }
 
-        Ok(Some(Self { condition }))
+        Ok(Some(Self::from(condition)))
+    }
+
+    /// Defines how the numbers of this filter are compared, as floats by default.
+    pub fn number_comparison(&mut self, number_comparison: NumberComparison) -> &mut Self {
+        self.number_comparison = number_comparison;
+        self
+    }
+
+    /// Returns a filter evaluating the given sub-condition with the same settings.
+    fn sub_filter(&self, condition: &FilterCondition<'a>) -> Self {
+        Filter { condition: condition.clone(), number_comparison: self.number_comparison }
     }
 }
 
@@ -271,32 +382,67 @@ impl<'a> Filter<'a> {
         strings_db: heed::Database<FacetStringLevelZeroCodec, FacetStringLevelZeroValueCodec>,
         field_id: FieldId,
         operator: &Condition<'a>,
+        number_comparison: NumberComparison,
     ) -> Result<RoaringBitmap> {
         // Make sure we always bound the ranges with the field id and the level,
         // as the facets values are all in the same database and prefixed by the
         // field id and the level.
 
+        let epsilon = number_comparison.epsilon();
         let (left, right) = match operator {
-            Condition::GreaterThan(val) => (Excluded(val.parse()?), Included(f64::MAX)),
-            Condition::GreaterThanOrEqual(val) => (Included(val.parse()?), Included(f64::MAX)),
-            Condition::LowerThan(val) => (Included(f64::MIN), Excluded(val.parse()?)),
-            Condition::LowerThanOrEqual(val) => (Included(f64::MIN), Included(val.parse()?)),
-            Condition::Between { from, to } => (Included(from.parse()?), Included(to.parse()?)),
+            Condition::GreaterThan(val) => {
+                (Excluded(number_comparison.parse(val)? + epsilon), Included(f64::MAX))
+            }
+            Condition::GreaterThanOrEqual(val) => {
+                (Included(number_comparison.parse(val)? - epsilon), Included(f64::MAX))
+            }
+            Condition::LowerThan(val) => {
+                (Included(f64::MIN), Excluded(number_comparison.parse(val)? - epsilon))
+            }
+            Condition::LowerThanOrEqual(val) => {
+                (Included(f64::MIN), Included(number_comparison.parse(val)? + epsilon))
+            }
+            Condition::Between { from, to } => (
+                Included(number_comparison.parse(from)? - epsilon),
+                Included(number_comparison.parse(to)? + epsilon),
+            ),
             Condition::Equal(val) => {
-                let (_original_value, string_docids) =
-                    strings_db.get(rtxn, &(field_id, &val.to_lowercase()))?.unwrap_or_default();
-                let number = val.parse::<f64>().ok();
+                if let Some(boolean) = boolean_value(val) {
+                    return Ok(index.boolean_faceted_documents_ids(rtxn, field_id, boolean)?);
+                }
+                let (_original_value, string_docids) = strings_db
+                    .get(rtxn, &(field_id, &val.value().to_lowercase()))?
+                    .unwrap_or_default();
+                // Only the values that look like numbers must respect the comparison mode,
+                // the other ones are strings. When the field has a number coercion policy,
+                // only the lenient one also compares `01234` or `1e3` with the numbers.
+                let number = match val.parse::<f64>() {
+                    Ok(_) => match index.number_coercions_ids(rtxn)?.get(&field_id) {
+                        Some(NumberCoercion::Lenient) | None => Some(number_comparison.parse(val)?),
+                        Some(NumberCoercion::Strict) | Some(NumberCoercion::Off) => {
+                            match NumberCoercion::Strict.coerce(val.value()) {
+                                Some(_) => Some(number_comparison.parse(val)?),
+                                None => None,
+                            }
+                        }
+                    },
+                    Err(_) => None,
+                };
                 let number_docids = match number {
+                    // An exact value is a single key of the level 0, we get it directly
+                    // instead of iterating over a range, e.g. for the id-like fields.
+                    Some(n) if epsilon == 0.0 && n.is_finite() => {
+                        numbers_db.get(rtxn, &(field_id, 0, n, n))?.unwrap_or_default()
+                    }
                     Some(n) => {
-                        let n = Included(n);
                         let mut output = RoaringBitmap::new();
                         Self::explore_facet_number_levels(
                             rtxn,
                             numbers_db,
                             field_id,
                             0,
-                            n,
-                            n,
+                            Included(n - epsilon),
+                            Included(n + epsilon),
                             &mut output,
                         )?;
                         output
@@ -313,11 +459,26 @@ impl<'a> Filter<'a> {
                     RoaringBitmap::new()
                 };
                 let all_strings_ids = index.string_faceted_documents_ids(rtxn, field_id)?;
+                let all_booleans_ids = index.boolean_faceted_documents_ids(rtxn, field_id, true)?
+                    | index.boolean_faceted_documents_ids(rtxn, field_id, false)?;
                 let operator = Condition::Equal(val.clone());
                 let docids = Self::evaluate_operator(
-                    rtxn, index, numbers_db, strings_db, field_id, &operator,
+                    rtxn,
+                    index,
+                    numbers_db,
+                    strings_db,
+                    field_id,
+                    &operator,
+                    number_comparison,
                 )?;
-                return Ok((all_numbers_ids | all_strings_ids) - docids);
+                return Ok((all_numbers_ids | all_strings_ids | all_booleans_ids) - docids);
+            }
+            Condition::Exact(phrase) => {
+                return Self::evaluate_exact_phrase(rtxn, index, field_id, phrase);
+            }
+            Condition::NotExact(phrase) => {
+                let docids = Self::evaluate_exact_phrase(rtxn, index, field_id, phrase)?;
+                return Ok(index.documents_ids(rtxn)? - docids);
             }
         };
 
@@ -346,23 +507,203 @@ impl<'a> Filter<'a> {
         }
     }
 
+    /// The values of the hashed fields are only stored hashed,
+    /// they can only be compared for equality with the hash of the value of the filter.
+    fn evaluate_hashed_operator(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        field_id: FieldId,
+        operator: &Condition<'a>,
+    ) -> Result<RoaringBitmap> {
+        match operator {
+            Condition::Equal(val) => {
+                let salt = index.hashed_fields_salt(rtxn)?.unwrap_or_default();
+                let hash = hash_facet_value(salt, val.value());
+                let (_original_value, docids) =
+                    index.facet_id_string_docids.get(rtxn, &(field_id, &hash))?.unwrap_or_default();
+                Ok(docids)
+            }
+            Condition::NotEqual(val) => {
+                let operator = Condition::Equal(val.clone());
+                let docids = Self::evaluate_hashed_operator(rtxn, index, field_id, &operator)?;
+                Ok(index.string_faceted_documents_ids(rtxn, field_id)? - docids)
+            }
+            _ => Ok(RoaringBitmap::new()),
+        }
+    }
+
+    /// Returns the documents in which the phrase is exactly the full value of the attribute,
+    /// the attribute must also be searchable for its words and their positions to be indexed.
+    fn evaluate_exact_phrase(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        field_id: FieldId,
+        phrase: &Token,
+    ) -> Result<RoaringBitmap> {
+        // The phrase is analyzed with the stop words of the index, then normalized and split
+        // on the word boundaries like the attributes values to find the same positions.
+        let stop_words = index.stop_words(rtxn)?;
+        let word_boundaries = index.word_boundaries(rtxn)?;
+        let normalizer_options = index.normalizer_options(rtxn)?;
+        let mut config = AnalyzerConfig::default();
+        if let Some(ref stop_words) = stop_words {
+            config.stop_words(stop_words);
+        }
+        let analyzer = Analyzer::<Vec<u8>>::new(config);
+        let value = phrase.value();
+        let analyzed = analyzer.analyze(value);
+
+        // The positions are looked up rounded like they are stored in the database.
+        let precision = index.word_position_precision(rtxn)?;
+        let mut candidates: Option<RoaringBitmap> = None;
+        let mut words = Vec::new();
+        let mut split_positions = 0;
+        for (position, token) in process_tokens(analyzed.tokens()) {
+            let original = value.get(token.byte_start..token.byte_end).unwrap_or("");
+            let normalized = normalizer_options.normalize(token.text().trim());
+            let split = word_boundaries.split(original.trim(), &normalized);
+            let split_count = split.len();
+
+            for (offset, word) in split.into_iter().enumerate() {
+                if word.is_empty() {
+                    continue;
+                }
+
+                let position = match u16::try_from(position + split_positions + offset) {
+                    Ok(position) => absolute_from_relative_position(field_id, position),
+                    Err(_) => return Ok(RoaringBitmap::new()),
+                };
+                let rounded = round_position(position, precision);
+                let docids =
+                    index.word_position_docids.get(rtxn, &(word, rounded))?.unwrap_or_default();
+                candidates = Some(match candidates {
+                    Some(candidates) => candidates & docids,
+                    None => docids,
+                });
+                words.push((word.to_string(), position));
+            }
+
+            split_positions += split_count - 1;
+        }
+
+        // The stop words are neither indexed nor counted in the words of the attribute.
+        let count_stop_words = |text: &str| {
+            analyzer
+                .analyze(text)
+                .tokens()
+                .filter(|token| token.kind == TokenKind::StopWord)
+                .count()
+        };
+        let phrase_stop_words = count_stop_words(value);
+
+        // The attribute must not contain any other word than the ones of the phrase.
+        let mut docids = RoaringBitmap::new();
+        'candidates: for docid in candidates.unwrap_or_default() {
+            let count = index.docid_field_id_word_count.get(rtxn, &(docid, field_id))?;
+            if count.map(|count| count.get() as usize) != Some(words.len()) {
+                continue;
+            }
+
+            // The positions of the words only constrain the stop words preceding them,
+            // the attribute is read to make sure that no stop word follows the phrase.
+            if stop_words.is_some() {
+                let document = index.documents.get(rtxn, &BEU32::new(docid))?;
+                let value = match document.and_then(|document| document.get(field_id)) {
+                    Some(bytes) => {
+                        serde_json::from_slice(bytes).map_err(InternalError::SerdeJson)?
+                    }
+                    None => continue,
+                };
+                let mut buffer = String::new();
+                let text = json_to_string(&value, &mut buffer).unwrap_or_default();
+                if count_stop_words(text) != phrase_stop_words {
+                    continue;
+                }
+            }
+
+            // The rounded positions don't tell the order of the words, the exact ones do.
+            if precision.get() > 1 {
+                for (word, position) in &words {
+                    let positions =
+                        index.docid_word_positions.get(rtxn, &(docid, word.as_str()))?;
+                    if !positions.map_or(false, |positions| positions.contains(*position)) {
+                        continue 'candidates;
+                    }
+                }
+            }
+
+            docids.insert(docid);
+        }
+
+        Ok(docids)
+    }
+
+    /// Evaluates a condition on the internal documents ids, the `_docid` reserved attribute.
+    fn evaluate_docid_operator(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        operator: &Condition<'a>,
+    ) -> Result<RoaringBitmap> {
+        let range: (Bound<u32>, Bound<u32>) = match operator {
+            Condition::GreaterThan(val) => (Excluded(val.parse()?), Unbounded),
+            Condition::GreaterThanOrEqual(val) => (Included(val.parse()?), Unbounded),
+            Condition::LowerThan(val) => (Unbounded, Excluded(val.parse()?)),
+            Condition::LowerThanOrEqual(val) => (Unbounded, Included(val.parse()?)),
+            Condition::Between { from, to } => (Included(from.parse()?), Included(to.parse()?)),
+            Condition::Equal(val) | Condition::Exact(val) => {
+                let docid = val.parse()?;
+                (Included(docid), Included(docid))
+            }
+            Condition::NotEqual(val) | Condition::NotExact(val) => {
+                let docids =
+                    Self::evaluate_docid_operator(rtxn, index, &Condition::Equal(val.clone()))?;
+                return Ok(index.documents_ids(rtxn)? - docids);
+            }
+        };
+
+        let mut docids = RoaringBitmap::new();
+        docids.insert_range(range);
+        Ok(docids & index.documents_ids(rtxn)?)
+    }
+
     pub fn evaluate(&self, rtxn: &heed::RoTxn, index: &Index) -> Result<RoaringBitmap> {
+        let mut docids = self.inner_evaluate(rtxn, index)?;
+        index.remove_soft_deleted_documents(rtxn, &mut docids)?;
+        Ok(docids)
+    }
+
+    fn inner_evaluate(&self, rtxn: &heed::RoTxn, index: &Index) -> Result<RoaringBitmap> {
         let numbers_db = index.facet_id_f64_docids;
         let strings_db = index.facet_id_string_docids;
 
         match &self.condition {
+            FilterCondition::Condition { fid, op } if fid.value() == DOCID_ATTRIBUTE => {
+                Self::evaluate_docid_operator(rtxn, index, op)
+            }
             FilterCondition::Condition { fid, op } => {
                 let filterable_fields = index.filterable_fields(rtxn)?;
 
                 if crate::is_faceted(fid.value(), &filterable_fields) {
                     let field_ids_map = index.fields_ids_map(rtxn)?;
                     if let Some(fid) = field_ids_map.id(fid.value()) {
-                        Self::evaluate_operator(rtxn, index, numbers_db, strings_db, fid, &op)
+                        if index.hashed_fields_ids(rtxn)?.contains(&fid) {
+                            Self::evaluate_hashed_operator(rtxn, index, fid, &op)
+                        } else {
+                            Self::evaluate_operator(
+                                rtxn,
+                                index,
+                                numbers_db,
+                                strings_db,
+                                fid,
+                                &op,
+                                self.number_comparison,
+                            )
+                        }
                     } else {
                         return Ok(RoaringBitmap::new());
                     }
                 } else {
-                    match *fid.deref() {
+                    match fid.value() {
                         attribute @ "_geo" => {
                             return Err(fid.as_external_error(FilterError::BadGeo(attribute)))?;
                         }
@@ -384,13 +725,13 @@ impl<'a> Filter<'a> {
                 }
             }
             FilterCondition::Or(lhs, rhs) => {
-                let lhs = Self::evaluate(&(lhs.as_ref().clone()).into(), rtxn, index)?;
-                let rhs = Self::evaluate(&(rhs.as_ref().clone()).into(), rtxn, index)?;
+                let lhs = self.sub_filter(lhs).inner_evaluate(rtxn, index)?;
+                let rhs = self.sub_filter(rhs).inner_evaluate(rtxn, index)?;
                 Ok(lhs | rhs)
             }
             FilterCondition::And(lhs, rhs) => {
-                let lhs = Self::evaluate(&(lhs.as_ref().clone()).into(), rtxn, index)?;
-                let rhs = Self::evaluate(&(rhs.as_ref().clone()).into(), rtxn, index)?;
+                let lhs = self.sub_filter(lhs).inner_evaluate(rtxn, index)?;
+                let rhs = self.sub_filter(rhs).inner_evaluate(rtxn, index)?;
                 Ok(lhs & rhs)
             }
             FilterCondition::GeoLowerThan { point, radius } => {
@@ -414,12 +755,11 @@ impl<'a> Filter<'a> {
                     };
 
                     let xyz_base_point = lat_lng_to_xyz(&base_point);
+                    let formula = index.geo_distance_formula(rtxn)?;
 
                     let result = rtree
                         .nearest_neighbor_iter(&xyz_base_point)
-                        .take_while(|point| {
-                            distance_between_two_points(&base_point, &point.data.1) < radius
-                        })
+                        .take_while(|point| formula.distance(&base_point, &point.data.1) < radius)
                         .map(|point| point.data.0)
                         .collect();
 
@@ -432,12 +772,12 @@ impl<'a> Filter<'a> {
                 }
             }
             FilterCondition::GeoGreaterThan { point, radius } => {
-                let result = Self::evaluate(
-                    &FilterCondition::GeoLowerThan { point: point.clone(), radius: radius.clone() }
-                        .into(),
-                    rtxn,
-                    index,
-                )?;
+                let result = self
+                    .sub_filter(&FilterCondition::GeoLowerThan {
+                        point: point.clone(),
+                        radius: radius.clone(),
+                    })
+                    .inner_evaluate(rtxn, index)?;
                 let geo_faceted_doc_ids = index.geo_faceted_documents_ids(rtxn)?;
                 Ok(geo_faceted_doc_ids - result)
             }
@@ -447,22 +787,25 @@ impl<'a> Filter<'a> {
 
 impl<'a> From<FilterCondition<'a>> for Filter<'a> {
     fn from(fc: FilterCondition<'a>) -> Self {
-        Self { condition: fc }
+        Self { condition: fc, number_comparison: NumberComparison::default() }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use std::fmt::Write;
+    use std::num::NonZeroU32;
 
     use big_s::S;
     use either::Either;
     use heed::EnvOpenOptions;
-    use maplit::hashset;
+    use maplit::{btreemap, btreeset, hashset};
 
     use super::*;
-    use crate::update::{IndexerConfig, Settings};
-    use crate::Index;
+    use crate::update::{
+        DeleteDocuments, IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings,
+    };
+    use crate::{Index, RestrictedIndex};
 
     #[test]
     fn empty_db() {
@@ -491,6 +834,369 @@ mod tests {
         assert!(bitmap.is_empty());
     }
 
+    #[test]
+    fn docid_filter_and_candidates() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("price") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "price": 10 },
+            { "id": 1, "price": 20 },
+            { "id": 2, "price": 30 },
+            { "id": 3, "price": 40 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let evaluate = |filter: &str| {
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            filter.evaluate(&rtxn, &index).map(|docids| docids.iter().collect::<Vec<_>>())
+        };
+
+        // The `_docid` attribute doesn't need to be filterable.
+        assert_eq!(evaluate("_docid >= 2").unwrap(), vec![2, 3]);
+        assert_eq!(evaluate("_docid < 1").unwrap(), vec![0]);
+        assert_eq!(evaluate("_docid 1 TO 2").unwrap(), vec![1, 2]);
+        assert_eq!(evaluate("_docid != 1").unwrap(), vec![0, 2, 3]);
+        assert_eq!(evaluate("_docid = 1 OR price = 40").unwrap(), vec![1, 3]);
+        assert!(evaluate("_docid = -1").is_err());
+
+        let mut search = index.search(&rtxn);
+        search.candidates(vec![0, 1, 3].into_iter().collect());
+        search.filter(Filter::from_str("price > 10").unwrap().unwrap());
+        let crate::SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![1, 3]);
+    }
+
+    #[test]
+    fn restricted_search() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("tenant"), S("price") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "tenant": "kevin", "title": "hello", "price": 10 },
+            { "id": 1, "tenant": "bob", "title": "hello", "price": 10 },
+            { "id": 2, "tenant": "kevin", "title": "hello", "price": 20 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let tenant = FilterCondition::field("tenant").equal("kevin");
+        let restricted = RestrictedIndex::new(&index, MandatoryFilter::new(Filter::from(tenant)));
+        let rtxn = restricted.read_txn().unwrap();
+
+        let mut search = restricted.search(&rtxn);
+        search.query("hello");
+        let crate::SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 2]);
+
+        // Neither the filter nor the candidates can widen the mandatory filter.
+        let mut search = restricted.search(&rtxn);
+        search.filter(Filter::from_str("tenant = bob OR price = 10").unwrap().unwrap());
+        search.candidates(vec![0, 1].into_iter().collect());
+        let crate::SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0]);
+
+        // The facet distributions and the browsed documents are restricted too.
+        let distribution = restricted.facets_distribution(&rtxn).facets(vec!["tenant"]).execute();
+        assert_eq!(distribution.unwrap()["tenant"], btreemap! { S("kevin") => 2 });
+
+        let filter = Filter::from_str("price = 10").unwrap().unwrap();
+        let documents = restricted.browse_documents(&rtxn, 0, 10, None).unwrap();
+        assert_eq!(documents.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![0, 2]);
+        let documents = restricted.browse_documents(&rtxn, 0, 10, Some(&filter)).unwrap();
+        assert_eq!(documents.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![0]);
+        drop(rtxn);
+
+        let session = restricted.search_session().unwrap();
+        assert_eq!(session.search().execute().unwrap().documents_ids, vec![0, 2]);
+        let distribution = session.facets_distribution().facets(vec!["tenant"]).execute().unwrap();
+        assert_eq!(distribution["tenant"], btreemap! { S("kevin") => 2 });
+    }
+
+    #[test]
+    fn built_filter() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("title"), S("price") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "the \"best\" OR price = 0", "price": 10 },
+            { "id": 1, "title": "the best", "price": 20 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let condition = FilterCondition::field("title").equal("the \"best\" OR price = 0");
+        let docids = Filter::from(condition).evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0]);
+
+        let condition = !FilterCondition::field("price").between(5, 15);
+        let docids = Filter::from(condition).evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn number_comparison() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("price") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "price": 10 },
+            { "id": 1, "price": 10.0 },
+            { "id": 2, "price": 10.5 },
+            { "id": 3, "price": 0.1 + 0.2 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let evaluate = |filter: &str, number_comparison| {
+            let mut filter = Filter::from_str(filter).unwrap().unwrap();
+            filter.number_comparison(number_comparison);
+            filter.evaluate(&rtxn, &index).map(|docids| docids.iter().collect::<Vec<_>>())
+        };
+
+        let exact = NumberComparison::default();
+        assert_eq!(evaluate("price = 10", exact).unwrap(), vec![0, 1]);
+        assert_eq!(evaluate("price = 10.0", exact).unwrap(), vec![0, 1]);
+        assert!(evaluate("price = 0.3", exact).unwrap().is_empty());
+
+        let float = NumberComparison::Float { epsilon: 1e-9 };
+        assert_eq!(evaluate("price = 0.3", float).unwrap(), vec![3]);
+        assert_eq!(evaluate("price != 0.3", float).unwrap(), vec![0, 1, 2]);
+        assert!(evaluate("price < 0.3", float).unwrap().is_empty());
+        assert_eq!(evaluate("price <= 0.3", float).unwrap(), vec![3]);
+        assert_eq!(evaluate("price 0.3 TO 10", float).unwrap(), vec![0, 1, 3]);
+
+        let integer = NumberComparison::Integer;
+        assert_eq!(evaluate("price = 10", integer).unwrap(), vec![0, 1]);
+        assert_eq!(evaluate("price > 10", integer).unwrap(), vec![2]);
+        let error = evaluate("price = 10.5", integer).unwrap_err();
+        assert!(error.to_string().contains("Numbers must be integers"), "{}", error);
+        assert!(evaluate("price >= 0.3", integer).is_err());
+
+        let mut filter = Filter::from_str("price = 10").unwrap().unwrap();
+        assert_eq!(filter, Filter::from_str("price = 10").unwrap().unwrap());
+        filter.number_comparison(NumberComparison::Float { epsilon: f64::NAN });
+        assert_eq!(filter, filter.clone());
+        assert_ne!(filter, Filter::from_str("price = 10").unwrap().unwrap());
+    }
+
+    #[test]
+    fn exact_number_equality() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("code") });
+        builder.execute(|_| ()).unwrap();
+
+        // Enough values to be grouped in several facet levels.
+        let documents: Vec<_> = (0..1000)
+            .map(|id| serde_json::json!({ "id": id, "code": id * 3 - 1500 }))
+            .chain(Some(serde_json::json!({ "id": 1000, "code": "-3" })))
+            .collect();
+        let content = documents!(documents);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let evaluate = |filter: &str, number_comparison| {
+            let mut filter = Filter::from_str(filter).unwrap().unwrap();
+            filter.number_comparison(number_comparison);
+            filter.evaluate(&rtxn, &index).map(|docids| docids.iter().collect::<Vec<_>>())
+        };
+
+        let exact = NumberComparison::default();
+        assert_eq!(evaluate("code = 0", exact).unwrap(), vec![500]);
+        assert_eq!(evaluate("code = -0", exact).unwrap(), vec![500]);
+        assert_eq!(evaluate("code = 1497", exact).unwrap(), vec![999]);
+        assert_eq!(evaluate("code = -1500.0", exact).unwrap(), vec![0]);
+        assert_eq!(evaluate("code = -3", exact).unwrap(), vec![499, 1000]);
+        assert!(evaluate("code = 1", exact).unwrap().is_empty());
+        assert!(evaluate("code = 1500", exact).unwrap().is_empty());
+        assert_eq!(evaluate("code != 0", exact).unwrap().len(), 1000);
+
+        let integer = NumberComparison::Integer;
+        assert_eq!(evaluate("code = 3", integer).unwrap(), vec![501]);
+        let float = NumberComparison::Float { epsilon: 1.0 };
+        assert_eq!(evaluate("code = 4", float).unwrap(), vec![501]);
+    }
+
+    #[test]
+    fn number_coercion_equality() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("zip"), S("code") });
+        builder.set_number_coercions(btreemap! {
+            S("zip") => NumberCoercion::Strict,
+        });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "zip": 1234, "code": 1234 },
+            { "id": 1, "zip": "01234", "code": "01234" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let evaluate = |filter: &str| {
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            filter.evaluate(&rtxn, &index).unwrap().iter().collect::<Vec<_>>()
+        };
+
+        // `01234` isn't the number `1234` with the strict policy.
+        assert_eq!(evaluate("zip = 01234"), vec![1]);
+        assert_eq!(evaluate("zip = 1234"), vec![0]);
+        assert_eq!(evaluate("zip != 01234"), vec![0]);
+
+        // Without a policy the value is compared with both the strings and the numbers.
+        assert_eq!(evaluate("code = 01234"), vec![0, 1]);
+    }
+
+    #[test]
+    fn boolean_equality() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("available") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "available": true },
+            { "id": 1, "available": false },
+            { "id": 2, "available": "true" },
+            { "id": 3, "available": [true, "soon"] },
+            { "id": 4 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let evaluate = |filter: &str| {
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            filter.evaluate(&rtxn, &index).unwrap().iter().collect::<Vec<_>>()
+        };
+
+        // the unquoted values are booleans, the quoted ones are strings
+        assert_eq!(evaluate("available = true"), vec![0, 3]);
+        assert_eq!(evaluate("available = FALSE"), vec![1]);
+        assert_eq!(evaluate("available = \"true\""), vec![2]);
+        assert_eq!(evaluate("available = 'false'"), Vec::<u32>::new());
+        assert_eq!(evaluate("available != true"), vec![1, 2]);
+        assert_eq!(evaluate("NOT available = \"true\""), vec![0, 1, 3]);
+        let filter: Filter = FilterCondition::field("available").equal(false).into();
+        assert_eq!(filter.evaluate(&rtxn, &index).unwrap().iter().collect::<Vec<_>>(), vec![1]);
+        let filter: Filter = FilterCondition::field("available").equal("true").into();
+        assert_eq!(filter.evaluate(&rtxn, &index).unwrap().iter().collect::<Vec<_>>(), vec![2]);
+
+        let distribution = index.facets_distribution(&rtxn).execute().unwrap();
+        let expected = btreemap! { S("false") => 1, S("soon") => 1, S("true") => 3 };
+        assert_eq!(distribution["available"], expected);
+
+        let mut candidates = RoaringBitmap::new();
+        candidates.insert(1);
+        candidates.insert(3);
+        let distribution =
+            index.facets_distribution(&rtxn).candidates(candidates).execute().unwrap();
+        let expected = btreemap! { S("false") => 1, S("soon") => 1, S("true") => 1 };
+        assert_eq!(distribution["available"], expected);
+
+        let types = index.filterable_fields_with_types(&rtxn).unwrap();
+        assert!(types["available"].boolean && types["available"].string);
+        drop(rtxn);
+
+        // the deleted documents are removed from the booleans
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.delete_external_id("0");
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let filter = Filter::from_str("available = true").unwrap().unwrap();
+        assert_eq!(filter.evaluate(&rtxn, &index).unwrap().iter().collect::<Vec<_>>(), vec![3]);
+        assert!(index.verify(&rtxn).unwrap().is_valid());
+    }
+
     #[test]
     fn from_array() {
         // Simple array with Left
@@ -653,6 +1359,111 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn exact_phrase() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("title") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "The Hobbit" },
+            { "id": 1, "title": "The Hobbit, or There and Back Again" },
+            { "id": 2, "title": "the hobbit" },
+            { "id": 3, "title": "Hobbit the" }
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let filter = Filter::from_str("title EXACT \"the hobbit\"").unwrap().unwrap();
+        let docids = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0, 2]);
+
+        let filter = Filter::from_str("NOT title EXACT \"the hobbit\"").unwrap().unwrap();
+        let docids = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![1, 3]);
+
+        let filter = Filter::from_str("title EXACT hobbit").unwrap().unwrap();
+        let docids = filter.evaluate(&rtxn, &index).unwrap();
+        assert!(docids.is_empty());
+        drop(rtxn);
+
+        // The rounded positions must not match the words in another order.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_word_position_precision(NonZeroU32::new(8).unwrap());
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let filter = Filter::from_str("title EXACT \"the hobbit\"").unwrap().unwrap();
+        let docids = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0, 2]);
+    }
+
+    #[test]
+    fn exact_phrase_with_stop_words() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("title") });
+        builder.set_stop_words(btreeset! { S("the") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "The Hobbit" },
+            { "id": 1, "title": "Hobbit" },
+            { "id": 2, "title": "Hobbit the" }
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // The stop words are not indexed but they still shift the positions of the next words.
+        let rtxn = index.read_txn().unwrap();
+        let filter = Filter::from_str("title EXACT \"the hobbit\"").unwrap().unwrap();
+        let docids = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0]);
+
+        let filter = Filter::from_str("title EXACT hobbit").unwrap().unwrap();
+        let docids = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![1]);
+
+        let filter = Filter::from_str("title EXACT \"hobbit the\"").unwrap().unwrap();
+        let docids = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![2]);
+    }
+
     #[test]
     fn filter_depth() {
         // generates a big (2 MiB) filter with too much of ORs.
@@ -1,9 +1,31 @@
-pub use self::facet_distribution::FacetDistribution;
+use std::ops::Bound::{self, Excluded, Included, Unbounded};
+
+pub use self::facet_distribution::{
+    FacetDistribution, FacetDistributionResult, FacetDistributionSampling, OrderBy,
+};
 pub use self::facet_number::{FacetNumberIter, FacetNumberRange, FacetNumberRevRange};
 pub use self::facet_string::FacetStringIter;
-pub use self::filter::Filter;
+pub use self::filter::{Filter, MandatoryFilter, NumberComparison};
 
 mod facet_distribution;
 mod facet_number;
 mod facet_string;
 mod filter;
+
+/// Whether the value is within the lower bound of a range.
+fn is_above<T: PartialOrd>(value: T, bound: Bound<T>) -> bool {
+    match bound {
+        Included(bound) => value >= bound,
+        Excluded(bound) => value > bound,
+        Unbounded => true,
+    }
+}
+
+/// Whether the value is within the upper bound of a range.
+fn is_below<T: PartialOrd>(value: T, bound: Bound<T>) -> bool {
+    match bound {
+        Included(bound) => value <= bound,
+        Excluded(bound) => value < bound,
+        Unbounded => true,
+    }
+}
@@ -6,7 +6,7 @@ use std::ops::{Index, IndexMut};
 use levenshtein_automata::{Distance, DFA};
 use meilisearch_tokenizer::Token;
 
-use crate::search::build_dfa;
+use crate::search::build_dfa_with_transposition;
 
 type IsPrefix = bool;
 
@@ -92,7 +92,18 @@ impl PartialEq for MatchingWord {
 
 impl MatchingWord {
     pub fn new(word: String, typo: u8, prefix: IsPrefix) -> Self {
-        let dfa = build_dfa(&word, typo, prefix);
+        MatchingWord::with_transposition_cost_one(word, typo, prefix, true)
+    }
+
+    /// Creates a term where swapping two adjacent characters is either counted
+    /// as one typo (`true`) or as two typos (`false`), like the query words are derived.
+    pub fn with_transposition_cost_one(
+        word: String,
+        typo: u8,
+        prefix: IsPrefix,
+        transposition_cost_one: bool,
+    ) -> Self {
+        let dfa = build_dfa_with_transposition(&word, typo, prefix, transposition_cost_one);
 
         Self { dfa, word, typo, prefix }
     }
@@ -330,6 +341,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn matching_word_transposition_cost() {
+        let token = Token {
+            kind: TokenKind::Word,
+            word: Cow::Borrowed("wrold"),
+            byte_start: 0,
+            char_index: 0,
+            byte_end: "wrold".len(),
+            char_map: None,
+        };
+
+        let matching_word = MatchingWord::new("world".to_string(), 1, false);
+        assert_eq!(matching_word.match_token(&token), Some(5));
+
+        let matching_word =
+            MatchingWord::with_transposition_cost_one("world".to_string(), 1, false, false);
+        assert_eq!(matching_word.match_token(&token), None);
+    }
+
     #[test]
     fn matching_words() {
         let matching_words = vec![
@@ -1,8 +1,10 @@
 use std::borrow::Cow;
+use std::mem;
 
 use matching_words::{MatchType, PartialMatch, PrimitiveWordId};
 pub use matching_words::{MatchingWord, MatchingWords};
 use meilisearch_tokenizer::token::{SeparatorKind, Token};
+use meilisearch_tokenizer::Analyzer;
 
 pub mod matching_words;
 
@@ -61,6 +63,52 @@ impl MatcherBuilder {
             matches: None,
         }
     }
+
+    /// Returns a formatter that uses the given analyzer to tokenize the texts and reuses
+    /// the same tokens buffer for all of them, this avoids reallocating the tokens when
+    /// formatting every field of every document of a search response.
+    pub fn formatter<'m, 'a, A: AsRef<[u8]>>(
+        &'m self,
+        analyzer: &'m Analyzer<'a, A>,
+    ) -> MatcherFormatter<'m, 'a, A> {
+        MatcherFormatter { builder: self, analyzer, tokens: Vec::new() }
+    }
+}
+
+/// Analyzes and formats many texts with the same `MatcherBuilder` and `Analyzer`.
+pub struct MatcherFormatter<'m, 'a, A> {
+    builder: &'m MatcherBuilder,
+    analyzer: &'m Analyzer<'a, A>,
+    tokens: Vec<Token<'static>>,
+}
+
+impl<'m, 'a, A: AsRef<[u8]>> MatcherFormatter<'m, 'a, A> {
+    /// Analyzes the text and returns the bounds of the matches.
+    pub fn matches(&mut self, text: &str) -> Vec<MatchBounds> {
+        let analyzed = self.analyzer.analyze(text);
+        let mut tokens = recycle_tokens(mem::take(&mut self.tokens));
+        tokens.extend(analyzed.tokens());
+        let matches = self.builder.build(&tokens, text).matches();
+        self.tokens = recycle_tokens(tokens);
+        matches
+    }
+
+    /// Analyzes the text and returns it formatted according to the given options.
+    pub fn format(&mut self, text: &str, format_options: FormatOptions) -> String {
+        let analyzed = self.analyzer.analyze(text);
+        let mut tokens = recycle_tokens(mem::take(&mut self.tokens));
+        tokens.extend(analyzed.tokens());
+        let formatted = self.builder.build(&tokens, text).format(format_options).into_owned();
+        self.tokens = recycle_tokens(tokens);
+        formatted
+    }
+}
+
+/// Empties the vector and reuses its allocation to store tokens of another lifetime.
+fn recycle_tokens<'a, 'b>(mut tokens: Vec<Token<'a>>) -> Vec<Token<'b>> {
+    tokens.clear();
+    // The vector is empty, the closure is never called and the allocation is reused.
+    tokens.into_iter().map(|_| -> Token<'b> { unreachable!() }).collect()
 }
 
 #[derive(Copy, Clone, Default)]
@@ -518,6 +566,28 @@ mod tests {
         assert_eq!(&matcher.format(format_options), &text);
     }
 
+    #[test]
+    fn formatter_reuses_analyzer() {
+        let matching_words = matching_words();
+
+        let builder = MatcherBuilder::from_matching_words(matching_words);
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+        let mut formatter = builder.formatter(&analyzer);
+
+        let format_options = FormatOptions { highlight: true, crop: None };
+
+        let text = "Natalie risk her future to build a world with the boy she loves.";
+        let analyzed = analyzer.analyze(&text);
+        let tokens: Vec<_> = analyzed.tokens().collect();
+        let mut matcher = builder.build(&tokens[..], text);
+        assert_eq!(formatter.format(text, format_options), matcher.format(format_options));
+
+        // The tokens buffer of the previous text is reused for this one.
+        let text = "A quick brown fox can not jump 32 feet, right? Brr, it is cold!";
+        assert_eq!(formatter.format(text, format_options), text);
+        assert!(formatter.matches(text).is_empty());
+    }
+
     #[test]
     fn format_highlight() {
         let matching_words = matching_words();
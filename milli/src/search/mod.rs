@@ -1,52 +1,92 @@
 use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::collections::hash_map::{Entry, HashMap};
+use std::collections::BTreeMap;
 use std::fmt;
 use std::mem::take;
 use std::result::Result as StdResult;
 use std::str::Utf8Error;
+use std::sync::Arc;
 use std::time::Instant;
 
-use distinct::{Distinct, DocIter, FacetDistinct, NoopDistinct};
+use distinct::{Distinct, Diversify, DocIter, FacetDistinct, NoopDistinct};
 use fst::automaton::Str;
 use fst::{Automaton, IntoStreamer, Streamer};
 use levenshtein_automata::{LevenshteinAutomatonBuilder as LevBuilder, DFA};
 use log::debug;
-use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
+use meilisearch_tokenizer::{Analyzer, AnalyzerConfig, TokenKind};
 use once_cell::sync::Lazy;
 use roaring::bitmap::RoaringBitmap;
+use time::OffsetDateTime;
+use whatlang::{Lang, Script};
 
-pub use self::facet::{FacetDistribution, FacetNumberIter, Filter};
+pub(crate) use self::dfa_cache::DfaCache;
+pub use self::facet::{
+    FacetDistribution, FacetDistributionResult, FacetDistributionSampling, FacetNumberIter,
+    FacetStringIter, Filter, MandatoryFilter, NumberComparison, OrderBy,
+};
 use self::fst_utils::{Complement, Intersection, StartsWith, Union};
 pub use self::matches::{
-    FormatOptions, MatchBounds, Matcher, MatcherBuilder, MatchingWord, MatchingWords,
+    FormatOptions, MatchBounds, Matcher, MatcherBuilder, MatcherFormatter, MatchingWord,
+    MatchingWords,
 };
-use self::query_tree::QueryTreeBuilder;
+pub use self::observer::{QueryTreeStats, SearchObserver};
+use self::placeholder_cache::PlaceholderKey;
+pub use self::placeholder_cache::PlaceholderSearchCache;
+pub use self::query_tree::Operation;
+use self::query_tree::{PrimitiveQueryPart, QueryTreeBuilder};
+pub use self::restricted::RestrictedIndex;
+pub use self::session::SearchSession;
 use crate::error::UserError;
+use crate::proximity::positions_proximity;
 use crate::search::criteria::r#final::{Final, FinalResult};
-use crate::{AscDesc, Criterion, DocumentId, Index, Member, Result};
+use crate::{AscDesc, Criterion, DocumentId, FieldId, Index, Member, Result, CONTENT_HASH_FIELD};
 
 // Building these factories is not free.
 static LEVDIST0: Lazy<LevBuilder> = Lazy::new(|| LevBuilder::new(0, true));
 static LEVDIST1: Lazy<LevBuilder> = Lazy::new(|| LevBuilder::new(1, true));
 static LEVDIST2: Lazy<LevBuilder> = Lazy::new(|| LevBuilder::new(2, true));
+// The same factories where a transposition costs two typos.
+static LEVDIST1_NO_TRANSPOSITION: Lazy<LevBuilder> = Lazy::new(|| LevBuilder::new(1, false));
+static LEVDIST2_NO_TRANSPOSITION: Lazy<LevBuilder> = Lazy::new(|| LevBuilder::new(2, false));
 
 mod criteria;
+mod dfa_cache;
 mod distinct;
 mod facet;
 mod fst_utils;
 mod matches;
+mod observer;
+mod placeholder_cache;
 mod query_tree;
+mod restricted;
+mod session;
 
 pub struct Search<'a> {
     query: Option<String>,
     // this should be linked to the String in the query
-    filter: Option<Filter<'a>>,
+    filters: Vec<Filter<'a>>,
+    candidates: Option<RoaringBitmap>,
+    mandatory_filter: Option<MandatoryFilter<'a>>,
     offset: usize,
     limit: usize,
     sort_criteria: Option<Vec<AscDesc>>,
+    ranking_rules: Option<Vec<Criterion>>,
+    external_sort_keys: HashMap<String, HashMap<DocumentId, f64>>,
+    fields_boosts: HashMap<String, f32>,
+    diversify_by: Option<String>,
+    deduplicate: bool,
     optional_words: bool,
     authorize_typos: bool,
     words_limit: usize,
+    words_position_limit: Option<u32>,
+    retrieve_vectors: bool,
+    retrieve_settings: bool,
+    compute_scores: bool,
+    observer: Option<&'a dyn SearchObserver>,
+    placeholder_cache: Option<&'a PlaceholderSearchCache>,
+    /// The function re-ranking the first documents and the factor of documents it receives.
+    rescorer: Option<(&'a dyn Fn(&[DocumentId]) -> Vec<f64>, usize)>,
     rtxn: &'a heed::RoTxn<'a>,
     index: &'a Index,
 }
@@ -55,18 +95,44 @@ impl<'a> Search<'a> {
     pub fn new(rtxn: &'a heed::RoTxn, index: &'a Index) -> Search<'a> {
         Search {
             query: None,
-            filter: None,
+            filters: Vec::new(),
+            candidates: None,
+            mandatory_filter: None,
             offset: 0,
             limit: 20,
             sort_criteria: None,
+            ranking_rules: None,
+            external_sort_keys: HashMap::new(),
+            fields_boosts: HashMap::new(),
+            diversify_by: None,
+            deduplicate: false,
             optional_words: true,
             authorize_typos: true,
             words_limit: 10,
+            words_position_limit: None,
+            retrieve_vectors: false,
+            retrieve_settings: false,
+            compute_scores: false,
+            observer: None,
+            placeholder_cache: None,
+            rescorer: None,
             rtxn,
             index,
         }
     }
 
+    /// Creates a search that only returns the documents matching the mandatory filter,
+    /// the filters and candidates of the search are always intersected with it.
+    pub(crate) fn restricted(
+        rtxn: &'a heed::RoTxn,
+        index: &'a Index,
+        mandatory_filter: MandatoryFilter<'a>,
+    ) -> Search<'a> {
+        let mut search = Search::new(rtxn, index);
+        search.mandatory_filter = Some(mandatory_filter);
+        search
+    }
+
     pub fn query(&mut self, query: impl Into<String>) -> &mut Search<'a> {
         self.query = Some(query.into());
         self
@@ -87,6 +153,50 @@ impl<'a> Search<'a> {
         self
     }
 
+    /// Overrides the ranking rules of the index for this search only,
+    /// the index settings are left untouched.
+    pub fn ranking_rules(&mut self, criteria: Vec<Criterion>) -> &mut Search<'a> {
+        self.ranking_rules = Some(criteria);
+        self
+    }
+
+    /// Registers values computed outside of the index, e.g. a real-time popularity, that
+    /// the sort criteria of this search can use under the given name, e.g. `popularity:desc`.
+    /// These names don't need to be sortable fields and take precedence over them.
+    pub fn external_sort_key(
+        &mut self,
+        name: impl Into<String>,
+        values: HashMap<DocumentId, f64>,
+    ) -> &mut Search<'a> {
+        self.external_sort_keys.insert(name.into(), values);
+        self
+    }
+
+    /// Multiplies the importance of the given fields for the attribute criterion of this
+    /// search only, a field with a boost of `2.0` ranks as if it was moved twice as close to
+    /// the first searchable attribute, a boost lower than `1.0` moves the field away from it.
+    pub fn boost_fields<S: AsRef<str>>(&mut self, boosts: &[(S, f32)]) -> &mut Search<'a> {
+        self.fields_boosts =
+            boosts.iter().map(|(field, boost)| (field.as_ref().to_string(), *boost)).collect();
+        self
+    }
+
+    /// Avoids returning consecutive documents sharing the same value on the given faceted
+    /// field, e.g. the same brand, whenever the documents of a bucket allow it. The documents
+    /// are only reordered inside the buckets of the ranking rules, none of them is removed.
+    pub fn diversify_by(&mut self, field: impl Into<String>) -> &mut Search<'a> {
+        self.diversify_by = Some(field.into());
+        self
+    }
+
+    /// Only returns the first of the documents sharing the same content hash, i.e. the same
+    /// values on the content hash fields, e.g. the mirrors of a crawled page. Nothing is
+    /// deduplicated when the index has no content hash fields.
+    pub fn deduplicate(&mut self, value: bool) -> &mut Search<'a> {
+        self.deduplicate = value;
+        self
+    }
+
     pub fn optional_words(&mut self, value: bool) -> &mut Search<'a> {
         self.optional_words = value;
         self
@@ -97,13 +207,93 @@ impl<'a> Search<'a> {
         self
     }
 
+    /// Limits the number of words and phrases of the query, the ones beyond the limit are
+    /// ignored and reported in [`SearchResult::ignored_words`], 10 by default.
     pub fn words_limit(&mut self, value: usize) -> &mut Search<'a> {
         self.words_limit = value;
         self
     }
 
+    /// Only matches the query words found in the first `limit` positions of the searchable
+    /// attributes, e.g. to only match the headline of the descriptions.
+    pub fn words_position_limit(&mut self, limit: u32) -> &mut Search<'a> {
+        self.words_position_limit = Some(limit);
+        self
+    }
+
+    /// Returns the vectors stored in the `_vectors` fields of the returned documents.
+    pub fn retrieve_vectors(&mut self, value: bool) -> &mut Search<'a> {
+        self.retrieve_vectors = value;
+        self
+    }
+
+    /// Returns the state of the settings used by the search, see [`SearchSettingsSnapshot`].
+    pub fn retrieve_settings(&mut self, value: bool) -> &mut Search<'a> {
+        self.retrieve_settings = value;
+        self
+    }
+
+    /// Returns the rank of the bucket of each criterion the returned documents were found in,
+    /// see [`ScoreDetails`]. A search computing the scores doesn't use the placeholder cache.
+    pub fn compute_scores(&mut self, value: bool) -> &mut Search<'a> {
+        self.compute_scores = value;
+        self
+    }
+
+    /// Reports the statistics and timings of the search to the observer.
+    pub fn observer(&mut self, observer: &'a dyn SearchObserver) -> &mut Search<'a> {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Reuses the documents ordered by a previous identical placeholder search,
+    /// the cache is only used by the searches without a query nor candidates.
+    pub fn placeholder_cache(&mut self, cache: &'a PlaceholderSearchCache) -> &mut Search<'a> {
+        self.placeholder_cache = Some(cache);
+        self
+    }
+
+    /// Re-ranks the first `(offset + limit) * factor` documents found by the ranking rules,
+    /// e.g. with a machine learning model, before the requested page is returned.
+    ///
+    /// The function receives the documents ids in the order of the ranking rules and must
+    /// return one score for each of them. The documents are sorted by decreasing score,
+    /// the ties keep the order of the ranking rules and the `NaN` scores are ranked last.
+    pub fn rescore(
+        &mut self,
+        factor: usize,
+        scorer: &'a dyn Fn(&[DocumentId]) -> Vec<f64>,
+    ) -> &mut Search<'a> {
+        self.rescorer = Some((scorer, factor.max(1)));
+        self
+    }
+
+    /// Only returns the documents matching the filter, it replaces the previous filters.
     pub fn filter(&mut self, condition: Filter<'a>) -> &mut Search<'a> {
-        self.filter = Some(condition);
+        self.filters = vec![condition];
+        self
+    }
+
+    /// Adds a filter to the filters of the search, the returned documents must match all of them.
+    ///
+    /// It allows a layer to append its own conditions, e.g. a tenant or a soft-delete flag,
+    /// to the filter of a user without parsing it again.
+    pub fn add_filter(&mut self, condition: Filter<'a>) -> &mut Search<'a> {
+        self.filters.push(condition);
+        self
+    }
+
+    /// Only returns the documents matching all the filters, it replaces the previous filters.
+    /// An empty list of filters doesn't filter the documents.
+    pub fn filters(&mut self, conditions: Vec<Filter<'a>>) -> &mut Search<'a> {
+        self.filters = conditions;
+        self
+    }
+
+    /// Restricts the search to the given documents ids, e.g. a precomputed set of
+    /// the documents a user is allowed to see. It is intersected with the filter.
+    pub fn candidates(&mut self, candidates: RoaringBitmap) -> &mut Search<'a> {
+        self.candidates = Some(candidates);
         self
     }
 
@@ -113,44 +303,343 @@ impl<'a> Search<'a> {
         Ok(self.authorize_typos && index_authorizes_typos)
     }
 
+    fn query_tree_builder(&self) -> Result<QueryTreeBuilder<'a>> {
+        let mut builder = QueryTreeBuilder::new(self.rtxn, self.index);
+        builder.optional_words(self.optional_words);
+        builder.authorize_typos(self.is_typo_authorized()?);
+        builder.words_limit(self.words_limit);
+        Ok(builder)
+    }
+
+    /// Returns the details of how the query is analyzed and transformed into a query tree,
+    /// this is useful to trace relevancy issues back to the tokenization of the query.
+    pub fn query_tokenization(&self) -> Result<QueryTokenization> {
+        let query = match self.query.as_ref() {
+            Some(query) => query,
+            None => return Ok(QueryTokenization::default()),
+        };
+
+        let mut config = AnalyzerConfig::default();
+        let stop_words = self.index.stop_words(self.rtxn)?;
+        if let Some(ref stop_words) = stop_words {
+            config.stop_words(stop_words);
+        }
+        let analyzer = Analyzer::new(config);
+        let result = analyzer.analyze(query);
+
+        let tokens: Vec<_> = result
+            .tokens()
+            .map(|token| QueryToken {
+                word: token.text().to_string(),
+                kind: token.kind,
+                byte_start: token.byte_start,
+                byte_end: token.byte_end,
+            })
+            .collect();
+
+        let builder = self.query_tree_builder()?;
+        let (query_tree, primitive_query) = match builder.build(query, result.tokens())? {
+            Some((query_tree, primitive_query, _)) => (Some(query_tree), primitive_query),
+            None => (None, Vec::new()),
+        };
+
+        let stop_words = tokens.iter().filter(|token| token.kind == TokenKind::StopWord);
+        let removed_stop_words =
+            removed_stop_words(stop_words.map(|token| token.word.as_str()), &primitive_query);
+
+        let detected = whatlang::detect(query);
+
+        Ok(QueryTokenization {
+            tokens,
+            script: detected.as_ref().map(|info| info.script()),
+            language: detected.as_ref().map(|info| info.lang()),
+            removed_stop_words,
+            query_tree,
+        })
+    }
+
+    /// Describes how the query was understood: the words searched for once the stop words
+    /// are removed and the unknown words corrected, and the n-grams found in the index.
+    fn normalize_query(
+        &self,
+        query_tree: &Operation,
+        primitive_query: &[PrimitiveQueryPart],
+        removed_stop_words: Vec<String>,
+    ) -> Result<NormalizedQuery> {
+        let words_fst = self.index.words_fst(self.rtxn)?;
+        let transposition_cost_one = self.index.transposition_counts_as_one_typo(self.rtxn)?;
+        let mut wdcache = WordDerivationsCache::new();
+
+        let mut leaves = Vec::new();
+        query_tree.collect_queries(&mut leaves);
+
+        let mut words = Vec::with_capacity(primitive_query.len());
+        let mut corrections = Vec::new();
+        for part in primitive_query {
+            match part {
+                PrimitiveQueryPart::Phrase(phrase) => {
+                    words.push(format!("\"{}\"", phrase.join(" ")))
+                }
+                PrimitiveQueryPart::Word(word, is_prefix) => {
+                    let typo = leaves
+                        .iter()
+                        .filter(|query| query.kind.word() == word)
+                        .map(|query| query.kind.typo())
+                        .max()
+                        .unwrap_or(0);
+                    // Only the words that are not in the index are corrected.
+                    if *is_prefix || typo == 0 || words_fst.contains(word) {
+                        words.push(word.clone());
+                        continue;
+                    }
+
+                    let mut best = None;
+                    for (derivation, _) in word_derivations_with_transposition(
+                        word,
+                        false,
+                        typo,
+                        transposition_cost_one,
+                        &words_fst,
+                        Some(&self.index.dfa_cache),
+                        &mut wdcache,
+                    )? {
+                        let count = match self.index.word_docids.get(self.rtxn, derivation)? {
+                            Some(docids) => docids.len(),
+                            None => continue,
+                        };
+                        if best.as_ref().map_or(true, |(_, best_count)| count > *best_count) {
+                            best = Some((derivation.clone(), count));
+                        }
+                    }
+
+                    match best {
+                        Some((correction, _)) => {
+                            words.push(correction.clone());
+                            corrections.push((word.clone(), correction));
+                        }
+                        None => words.push(word.clone()),
+                    }
+                }
+            }
+        }
+
+        // The query tree also searches for the concatenations of up to three consecutive words.
+        let mut ngrams = Vec::new();
+        for ngram_len in 2..=3 {
+            for window in primitive_query.windows(ngram_len) {
+                let ngram: Option<String> = window
+                    .iter()
+                    .map(|part| match part {
+                        PrimitiveQueryPart::Word(word, _) => Some(word.as_str()),
+                        PrimitiveQueryPart::Phrase(_) => None,
+                    })
+                    .collect();
+                match ngram {
+                    Some(ngram) if words_fst.contains(&ngram) => ngrams.push(ngram),
+                    _ => (),
+                }
+            }
+        }
+
+        Ok(NormalizedQuery { query: words.join(" "), removed_stop_words, corrections, ngrams })
+    }
+
+    /// Returns the proximities found in the given document between every pair of consecutive
+    /// query words, to explain in which bucket the proximity criterion placed the document.
+    ///
+    /// The words are looked up exactly in the document, except the last word of the query
+    /// which is looked up as a prefix if it is one, derivations with typos are ignored.
+    pub fn document_proximities(&self, docid: DocumentId) -> Result<Vec<WordPairProximity>> {
+        let query = match self.query.as_ref() {
+            Some(query) => query,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut config = AnalyzerConfig::default();
+        let stop_words = self.index.stop_words(self.rtxn)?;
+        if let Some(ref stop_words) = stop_words {
+            config.stop_words(stop_words);
+        }
+        let analyzer = Analyzer::new(config);
+        let result = analyzer.analyze(query);
+
+        let builder = self.query_tree_builder()?;
+        let primitive_query = match builder.build(query, result.tokens())? {
+            Some((_, primitive_query, _)) => primitive_query,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut words = Vec::new();
+        for part in primitive_query {
+            match part {
+                PrimitiveQueryPart::Word(word, is_prefix) => words.push((word, is_prefix)),
+                PrimitiveQueryPart::Phrase(phrase) => {
+                    words.extend(phrase.into_iter().map(|word| (word, false)))
+                }
+            }
+        }
+
+        let mut positions = Vec::with_capacity(words.len());
+        for (word, is_prefix) in &words {
+            let mut word_positions = RoaringBitmap::new();
+            if *is_prefix {
+                let iter = self
+                    .index
+                    .docid_word_positions
+                    .prefix_iter(self.rtxn, &(docid, word.as_str()))?;
+                for result in iter {
+                    let (_, found_positions) = result?;
+                    word_positions |= found_positions;
+                }
+            } else if let Some(found_positions) =
+                self.index.docid_word_positions.get(self.rtxn, &(docid, word.as_str()))?
+            {
+                word_positions = found_positions;
+            }
+            positions.push(word_positions);
+        }
+
+        let proximities = words
+            .windows(2)
+            .zip(positions.windows(2))
+            .map(|(words, positions)| {
+                let proximity = positions[0]
+                    .iter()
+                    .flat_map(|lhs| positions[1].iter().map(move |rhs| (lhs, rhs)))
+                    .map(|(lhs, rhs)| positions_proximity(lhs, rhs))
+                    .min();
+                WordPairProximity { left: words[0].0.clone(), right: words[1].0.clone(), proximity }
+            })
+            .collect();
+
+        Ok(proximities)
+    }
+
     pub fn execute(&self) -> Result<SearchResult> {
+        let search_before = Instant::now();
+
+        let placeholder_cache = match self.placeholder_cache {
+            Some(cache) if self.is_cacheable_placeholder() => {
+                Some((cache, self.placeholder_key()?))
+            }
+            _ => None,
+        };
+
+        if let Some((cache, key)) = &placeholder_cache {
+            if let Some((documents_ids, candidates)) = cache.get(key, self.offset, self.limit) {
+                debug!("placeholder search found in the cache");
+                let settings = match self.retrieve_settings {
+                    true => {
+                        let criteria = match &self.ranking_rules {
+                            Some(ranking_rules) => ranking_rules.clone(),
+                            None => self.index.criteria(self.rtxn)?,
+                        };
+                        Some(self.settings_snapshot(criteria)?)
+                    }
+                    false => None,
+                };
+                let result = SearchResult {
+                    documents_vectors: self.documents_vectors(&documents_ids)?,
+                    documents_ids,
+                    candidates,
+                    settings,
+                    ..Default::default()
+                };
+                if let Some(observer) = self.observer {
+                    observer.search_finished(
+                        result.documents_ids.len(),
+                        result.candidates.len(),
+                        search_before.elapsed(),
+                    );
+                }
+                return Ok(result);
+            }
+        }
+
         // We create the query tree by spliting the query into tokens.
         let before = Instant::now();
-        let (query_tree, primitive_query, matching_words) = match self.query.as_ref() {
-            Some(query) => {
-                let mut builder = QueryTreeBuilder::new(self.rtxn, self.index);
-                builder.optional_words(self.optional_words);
-
-                builder.authorize_typos(self.is_typo_authorized()?);
-
-                builder.words_limit(self.words_limit);
-                // We make sure that the analyzer is aware of the stop words
-                // this ensures that the query builder is able to properly remove them.
-                let mut config = AnalyzerConfig::default();
-                let stop_words = self.index.stop_words(self.rtxn)?;
-                if let Some(ref stop_words) = stop_words {
-                    config.stop_words(stop_words);
+        let mut ignored_words = Vec::new();
+        let (query_tree, primitive_query, matching_words, normalized_query, negative_query_tree) =
+            match self.query.as_ref() {
+                Some(query) => {
+                    let builder = self.query_tree_builder()?;
+                    // We make sure that the analyzer is aware of the stop words
+                    // this ensures that the query builder is able to properly remove them.
+                    let mut config = AnalyzerConfig::default();
+                    let stop_words = self.index.stop_words(self.rtxn)?;
+                    if let Some(ref stop_words) = stop_words {
+                        config.stop_words(stop_words);
+                    }
+                    let analyzer = Analyzer::new(config);
+                    let result = analyzer.analyze(query);
+                    let negative_qt = builder.build_negative(query, result.tokens())?;
+                    ignored_words = builder.ignored_words(query, result.tokens())?;
+                    match builder.build(query, result.tokens())? {
+                        Some((qt, pq, mw)) => {
+                            let stop_words: Vec<_> = result
+                                .tokens()
+                                .filter(|token| token.kind == TokenKind::StopWord)
+                                .map(|token| token.text().to_string())
+                                .collect();
+                            let removed =
+                                removed_stop_words(stop_words.iter().map(String::as_str), &pq);
+                            let normalized_query = self.normalize_query(&qt, &pq, removed)?;
+                            (Some(qt), Some(pq), Some(mw), Some(normalized_query), negative_qt)
+                        }
+                        None => (None, None, None, None, negative_qt),
+                    }
                 }
-                let analyzer = Analyzer::new(config);
-                let result = analyzer.analyze(query);
-                let tokens = result.tokens();
-                builder
-                    .build(tokens)?
-                    .map_or((None, None, None), |(qt, pq, mw)| (Some(qt), Some(pq), Some(mw)))
-            }
-            None => (None, None, None),
-        };
+                None => (None, None, None, None, None),
+            };
 
         debug!("query tree: {:?} took {:.02?}", query_tree, before.elapsed());
+        if let Some(observer) = self.observer {
+            observer
+                .query_tree_built(query_tree.as_ref().map(QueryTreeStats::new), before.elapsed());
+        }
 
         // We create the original candidates with the facet conditions results.
         let before = Instant::now();
-        let filtered_candidates = match &self.filter {
-            Some(condition) => Some(condition.evaluate(self.rtxn, self.index)?),
+        let mut filtered_candidates: Option<RoaringBitmap> = None;
+        for condition in &self.filters {
+            // The filters are intersected, there is no need to evaluate the others once empty.
+            if filtered_candidates.as_ref().map_or(false, RoaringBitmap::is_empty) {
+                break;
+            }
+            let docids = condition.evaluate(self.rtxn, self.index)?;
+            filtered_candidates = match filtered_candidates {
+                Some(filtered) => Some(filtered & docids),
+                None => Some(docids),
+            };
+        }
+        let filtered_candidates = match (filtered_candidates, &self.candidates) {
+            (Some(filtered), Some(candidates)) => Some(filtered & candidates),
+            (None, Some(candidates)) => Some(candidates.clone()),
+            (filtered, None) => filtered,
+        };
+        let filtered_candidates = match &self.mandatory_filter {
+            Some(mandatory_filter) => {
+                let docids = mandatory_filter.evaluate(self.rtxn, self.index)?;
+                match filtered_candidates {
+                    Some(filtered) => Some(filtered & docids),
+                    None => Some(docids),
+                }
+            }
+            None => filtered_candidates,
+        };
+        // The soft deleted documents are still in the databases until they are purged.
+        let soft_deleted = self.index.soft_deleted_documents_ids(self.rtxn)?;
+        let filtered_candidates = match filtered_candidates {
+            Some(filtered) => Some(filtered - soft_deleted),
+            None if !soft_deleted.is_empty() => Some(self.index.documents_ids(self.rtxn)?),
             None => None,
         };
 
         debug!("facet candidates: {:?} took {:.02?}", filtered_candidates, before.elapsed());
+        if let Some(observer) = self.observer {
+            let candidates = filtered_candidates.as_ref().map(RoaringBitmap::len);
+            observer.filters_evaluated(candidates, before.elapsed());
+        }
 
         // We check that we are allowed to use the sort criteria, we check
         // that they are declared in the sortable fields.
@@ -158,13 +647,14 @@ impl<'a> Search<'a> {
             let sortable_fields = self.index.sortable_fields(self.rtxn)?;
             for asc_desc in sort_criteria {
                 match asc_desc.member() {
+                    Member::Field(ref field) if self.external_sort_keys.contains_key(field) => (),
                     Member::Field(ref field) if !crate::is_faceted(field, &sortable_fields) => {
                         return Err(UserError::InvalidSortableAttribute {
                             field: field.to_string(),
                             valid_fields: sortable_fields.into_iter().collect(),
                         })?
                     }
-                    Member::Geo(_) if !sortable_fields.contains("_geo") => {
+                    Member::Geo(_) | Member::GeoWithin(..) if !sortable_fields.contains("_geo") => {
                         return Err(UserError::InvalidSortableAttribute {
                             field: "_geo".to_string(),
                             valid_fields: sortable_fields.into_iter().collect(),
@@ -175,47 +665,260 @@ impl<'a> Search<'a> {
             }
         }
 
+        // We check that the fields of the overriding ranking rules are faceted,
+        // the asc/desc ranking rules can only be computed on faceted fields.
+        let criteria = match &self.ranking_rules {
+            Some(ranking_rules) => {
+                let faceted_fields = self.index.faceted_fields(self.rtxn)?;
+                for criterion in ranking_rules {
+                    match criterion.field_name() {
+                        Some(field) if !crate::is_faceted(field, &faceted_fields) => {
+                            return Err(UserError::InvalidRankingRuleAttribute {
+                                field: field.to_string(),
+                                valid_fields: faceted_fields.into_iter().collect(),
+                            })?
+                        }
+                        _ => (),
+                    }
+                }
+                ranking_rules.clone()
+            }
+            None => self.index.criteria(self.rtxn)?,
+        };
+
         // We check that the sort ranking rule exists and throw an
         // error if we try to use it and that it doesn't.
-        let sort_ranking_rule_missing = !self.index.criteria(self.rtxn)?.contains(&Criterion::Sort);
+        let sort_ranking_rule_missing = !criteria.contains(&Criterion::Sort);
         let empty_sort_criteria = self.sort_criteria.as_ref().map_or(true, |s| s.is_empty());
         if sort_ranking_rule_missing && !empty_sort_criteria {
             return Err(UserError::SortRankingRuleMissing.into());
         }
 
-        let criteria_builder = criteria::CriteriaBuilder::new(self.rtxn, self.index)?;
+        let settings = match self.retrieve_settings {
+            true => Some(self.settings_snapshot(criteria.clone())?),
+            false => None,
+        };
+
+        // We check that the boosts are valid and only keep the ones of the known fields.
+        let mut fields_boosts = HashMap::new();
+        if !self.fields_boosts.is_empty() {
+            let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
+            for (field, &boost) in &self.fields_boosts {
+                if !boost.is_finite() || boost <= 0.0 {
+                    return Err(UserError::InvalidFieldBoost { field: field.clone(), boost }.into());
+                }
+                if let Some(field_id) = fields_ids_map.id(field) {
+                    fields_boosts.insert(field_id, boost);
+                }
+            }
+        }
+
+        // We check that the diversity field is faceted, the values of the documents are
+        // read from the facet databases.
+        let diversity = match &self.diversify_by {
+            Some(field) => {
+                let faceted_fields = self.index.faceted_fields(self.rtxn)?;
+                if !crate::is_faceted(field, &faceted_fields) {
+                    return Err(UserError::InvalidRankingRuleAttribute {
+                        field: field.to_string(),
+                        valid_fields: faceted_fields.into_iter().collect(),
+                    })?;
+                }
+                self.index.fields_ids_map(self.rtxn)?.id(field)
+            }
+            None => None,
+        };
+
+        let mut criteria_builder = criteria::CriteriaBuilder::new(self.rtxn, self.index)?;
+        criteria_builder.words_position_limit(self.words_position_limit)?;
+        criteria_builder.top_k(Some(self.offset.saturating_add(self.limit)));
+        criteria_builder.compute_scores(self.compute_scores);
+
+        // The documents containing a negated word are removed before the ranking.
+        let filtered_candidates = match negative_query_tree {
+            Some(negative_query_tree) => {
+                let mut wdcache = WordDerivationsCache::new();
+                let excluded = criteria::resolve_query_tree(
+                    &criteria_builder,
+                    &negative_query_tree,
+                    &mut wdcache,
+                )?;
+                let candidates = match filtered_candidates {
+                    Some(filtered) => filtered,
+                    None => self.index.documents_ids(self.rtxn)?,
+                };
+                Some(candidates - excluded)
+            }
+            None => filtered_candidates,
+        };
+        // The filtered candidates are all the documents the criteria can return.
+        let universe = filtered_candidates.clone();
         let criteria = criteria_builder.build(
             query_tree,
             primitive_query,
             filtered_candidates,
             self.sort_criteria.clone(),
+            criteria,
+            &self.external_sort_keys,
+            &fields_boosts,
+            self.observer,
         )?;
 
-        match self.index.distinct_field(self.rtxn)? {
-            None => self.perform_sort(NoopDistinct, matching_words.unwrap_or_default(), criteria),
-            Some(name) => {
-                let field_ids_map = self.index.fields_ids_map(self.rtxn)?;
-                match field_ids_map.id(name) {
-                    Some(fid) => {
-                        let distinct = FacetDistinct::new(fid, self.index, self.rtxn);
-                        self.perform_sort(distinct, matching_words.unwrap_or_default(), criteria)
+        // A cached placeholder search computes all the documents up to the requested page,
+        // a rescored search computes them too, multiplied by the rescoring factor.
+        let (offset, limit) = match (&placeholder_cache, self.rescorer) {
+            (Some(_), _) => (0, self.offset.saturating_add(self.limit)),
+            (None, Some((_, factor))) => {
+                (0, self.offset.saturating_add(self.limit).saturating_mul(factor))
+            }
+            (None, None) => (self.offset, self.limit),
+        };
+
+        let matching_words = matching_words.unwrap_or_default();
+        let distinct_fields = self.index.distinct_fields(self.rtxn)?;
+        let field_ids_map = self.index.fields_ids_map(self.rtxn)?;
+        let content_hash =
+            if self.deduplicate { field_ids_map.id(CONTENT_HASH_FIELD) } else { None };
+        let mut result = if distinct_fields.is_empty() && content_hash.is_none() {
+            self.perform_sort(
+                NoopDistinct,
+                diversity,
+                matching_words,
+                criteria,
+                universe,
+                offset,
+                limit,
+            )?
+        } else {
+            let fids: Option<Vec<_>> =
+                distinct_fields.iter().map(|name| field_ids_map.id(name)).collect();
+            match fids {
+                Some(fids) => {
+                    let mut distinct = FacetDistinct::composite(fids, self.index, self.rtxn);
+                    if let Some(content_hash) = content_hash {
+                        distinct = distinct.deduplicate(content_hash);
                     }
-                    None => Ok(SearchResult::default()),
+                    self.perform_sort(
+                        distinct,
+                        diversity,
+                        matching_words,
+                        criteria,
+                        universe,
+                        offset,
+                        limit,
+                    )?
                 }
+                None => SearchResult::default(),
+            }
+        };
+
+        if let Some((cache, key)) = placeholder_cache {
+            let exhaustive = result.documents_ids.len() < limit;
+            cache.insert(key, result.documents_ids.clone(), result.candidates.clone(), exhaustive);
+            result.documents_ids.drain(..self.offset.min(result.documents_ids.len()));
+        }
+
+        if let Some((scorer, _)) = self.rescorer {
+            rescore_documents(scorer, &mut result.documents_ids)?;
+            result.documents_ids.drain(..self.offset.min(result.documents_ids.len()));
+            result.documents_ids.truncate(self.limit);
+            if self.compute_scores {
+                let mut scores: HashMap<_, _> =
+                    take(&mut result.documents_scores).into_iter().collect();
+                result.documents_scores = result
+                    .documents_ids
+                    .iter()
+                    .filter_map(|docid| scores.remove(docid).map(|score| (*docid, score)))
+                    .collect();
             }
         }
+
+        result.documents_vectors = self.documents_vectors(&result.documents_ids)?;
+        result.settings = settings;
+        result.normalized_query = normalized_query;
+        result.ignored_words = ignored_words;
+        if let Some(observer) = self.observer {
+            observer.search_finished(
+                result.documents_ids.len(),
+                result.candidates.len(),
+                search_before.elapsed(),
+            );
+        }
+
+        Ok(result)
+    }
+
+    /// Whether the search is a placeholder search that only depends on the index and can be cached.
+    fn is_cacheable_placeholder(&self) -> bool {
+        self.query.is_none()
+            && self.candidates.is_none()
+            && self.external_sort_keys.is_empty()
+            && self.rescorer.is_none()
+            && !self.compute_scores
+    }
+
+    /// Identifies this placeholder search on the current state of the index.
+    fn placeholder_key(&self) -> Result<PlaceholderKey> {
+        let search = format!(
+            "{:?} {:?} {:?} {:?} {:?} {:?}",
+            self.filters,
+            self.mandatory_filter,
+            self.sort_criteria,
+            self.ranking_rules,
+            self.diversify_by,
+            self.deduplicate
+        );
+        Ok(PlaceholderKey { search, generation: self.index.generation(self.rtxn)? })
+    }
+
+    /// Returns the vectors of the given documents if they must be retrieved.
+    fn documents_vectors(
+        &self,
+        documents_ids: &[DocumentId],
+    ) -> Result<Vec<BTreeMap<String, Vec<f32>>>> {
+        if self.retrieve_vectors {
+            documents_ids
+                .iter()
+                .map(|docid| self.index.document_vectors(self.rtxn, *docid))
+                .collect()
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Returns the state of the settings used by this search.
+    fn settings_snapshot(&self, criteria: Vec<Criterion>) -> Result<SearchSettingsSnapshot> {
+        let stop_words_checksum = match self.index.stop_words(self.rtxn)? {
+            Some(stop_words) => fxhash::hash64(stop_words.as_fst().as_bytes()),
+            None => 0,
+        };
+
+        Ok(SearchSettingsSnapshot {
+            updated_at: self.index.updated_at(self.rtxn)?,
+            criteria,
+            authorize_typos: self.is_typo_authorized()?,
+            min_word_len_one_typo: self.index.min_word_len_one_typo(self.rtxn)?,
+            min_word_len_two_typos: self.index.min_word_len_two_typos(self.rtxn)?,
+            stop_words_checksum,
+        })
     }
 
     fn perform_sort<D: Distinct>(
         &self,
         mut distinct: D,
+        diversity: Option<FieldId>,
         matching_words: MatchingWords,
         mut criteria: Final,
+        universe: Option<RoaringBitmap>,
+        mut offset: usize,
+        limit: usize,
     ) -> Result<SearchResult> {
-        let mut offset = self.offset;
         let mut initial_candidates = RoaringBitmap::new();
         let mut excluded_candidates = RoaringBitmap::new();
+        let mut ranked_candidates = RoaringBitmap::new();
         let mut documents_ids = Vec::new();
+        let mut documents_scores = Vec::new();
+        let mut last_value = None;
 
         while let Some(FinalResult { candidates, bucket_candidates, .. }) =
             criteria.next(&excluded_candidates)?
@@ -223,29 +926,50 @@ impl<'a> Search<'a> {
             debug!("Number of candidates found {}", candidates.len());
 
             let excluded = take(&mut excluded_candidates);
+            ranked_candidates |= &candidates;
 
             let mut candidates = distinct.distinct(candidates, excluded);
 
             initial_candidates |= bucket_candidates;
 
+            let mut diversified =
+                Diversify::new(candidates.by_ref(), diversity, last_value, self.index, self.rtxn);
+
             if offset != 0 {
-                let discarded = candidates.by_ref().take(offset).count();
+                let discarded = diversified.by_ref().take(offset).count();
                 offset = offset.saturating_sub(discarded);
             }
 
-            for candidate in candidates.by_ref().take(self.limit - documents_ids.len()) {
-                documents_ids.push(candidate?);
+            let score_details = criteria.score_details();
+            for candidate in diversified.by_ref().take(limit - documents_ids.len()) {
+                let docid = candidate?;
+                documents_ids.push(docid);
+                if let Some(score_details) = &score_details {
+                    documents_scores.push((docid, score_details.clone()));
+                }
             }
-            if documents_ids.len() == self.limit {
+            if documents_ids.len() == limit {
                 break;
             }
+            last_value = diversified.into_last_value();
             excluded_candidates = candidates.into_excluded();
+
+            // Once all the documents the criteria can return are ranked, the next
+            // buckets are empty, e.g. when the filter only matches a few documents.
+            if universe.as_ref().map_or(false, |universe| universe.is_subset(&ranked_candidates)) {
+                break;
+            }
         }
 
         Ok(SearchResult {
             matching_words,
             candidates: initial_candidates - excluded_candidates,
             documents_ids,
+            documents_scores,
+            documents_vectors: Vec::new(),
+            settings: None,
+            normalized_query: None,
+            ignored_words: Vec::new(),
         })
     }
 }
@@ -254,25 +978,53 @@ impl fmt::Debug for Search<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Search {
             query,
-            filter,
+            filters,
+            candidates,
+            mandatory_filter,
             offset,
             limit,
             sort_criteria,
+            ranking_rules,
+            external_sort_keys,
+            fields_boosts,
+            diversify_by,
+            deduplicate,
             optional_words,
             authorize_typos,
             words_limit,
+            words_position_limit,
+            retrieve_vectors,
+            retrieve_settings,
+            compute_scores,
+            observer,
+            placeholder_cache,
+            rescorer,
             rtxn: _,
             index: _,
         } = self;
         f.debug_struct("Search")
             .field("query", query)
-            .field("filter", filter)
+            .field("filters", filters)
+            .field("candidates", candidates)
+            .field("mandatory_filter", mandatory_filter)
             .field("offset", offset)
             .field("limit", limit)
             .field("sort_criteria", sort_criteria)
+            .field("ranking_rules", ranking_rules)
+            .field("external_sort_keys", &external_sort_keys.keys().collect::<Vec<_>>())
+            .field("fields_boosts", fields_boosts)
+            .field("diversify_by", diversify_by)
+            .field("deduplicate", deduplicate)
             .field("optional_words", optional_words)
             .field("authorize_typos", authorize_typos)
             .field("words_limit", words_limit)
+            .field("words_position_limit", words_position_limit)
+            .field("retrieve_vectors", retrieve_vectors)
+            .field("retrieve_settings", retrieve_settings)
+            .field("compute_scores", compute_scores)
+            .field("observer", &observer.is_some())
+            .field("placeholder_cache", &placeholder_cache.is_some())
+            .field("rescoring_factor", &rescorer.map(|(_, factor)| factor))
             .finish()
     }
 }
@@ -281,12 +1033,145 @@ impl fmt::Debug for Search<'_> {
 pub struct SearchResult {
     pub matching_words: MatchingWords,
     pub candidates: RoaringBitmap,
-    // TODO those documents ids should be associated with their criteria scores.
     pub documents_ids: Vec<DocumentId>,
+    /// The score details of the returned documents, in the same order as the documents ids.
+    /// Only filled when the scores are computed, see [`Search::compute_scores`].
+    pub documents_scores: Vec<(DocumentId, ScoreDetails)>,
+    /// The vectors of the returned documents by field name, in the same order as the documents ids.
+    /// Only filled when the vectors are retrieved, see [`Search::retrieve_vectors`].
+    pub documents_vectors: Vec<BTreeMap<String, Vec<f32>>>,
+    /// The state of the settings used by the search.
+    /// Only filled when the settings are retrieved, see [`Search::retrieve_settings`].
+    pub settings: Option<SearchSettingsSnapshot>,
+    /// How the query was understood, `None` if there was no word to search for.
+    pub normalized_query: Option<NormalizedQuery>,
+    /// The words of the query that were ignored because they are beyond the words limit,
+    /// see [`Search::words_limit`].
+    pub ignored_words: Vec<String>,
 }
 
-pub type WordDerivationsCache = HashMap<(String, bool, u8), Vec<(String, u8)>>;
+/// How the query was understood by the search, e.g. to display "showing results for ...".
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct NormalizedQuery {
+    /// The words searched for without the removed stop words and with the corrections,
+    /// the phrases are quoted.
+    pub query: String,
+    /// The stop words that were removed from the query.
+    pub removed_stop_words: Vec<String>,
+    /// The words that are not in the index and their most frequent typo derivation.
+    pub corrections: Vec<(String, String)>,
+    /// The concatenations of consecutive query words that exist in the index.
+    pub ngrams: Vec<String>,
+}
+
+/// Why a document ranked where it did: the rank of the bucket it was found in for each criterion.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ScoreDetails {
+    /// The names of the criteria, in the order of the ranking rules, and the ranks of the
+    /// buckets, 0 being the best bucket of the criterion among the documents that are
+    /// equal for the previous criteria. A sort with several fields is a single criterion.
+    pub ranks: Vec<(String, usize)>,
+}
+
+/// Sorts the documents by the decreasing scores returned by the rescoring function.
+fn rescore_documents(
+    scorer: &dyn Fn(&[DocumentId]) -> Vec<f64>,
+    documents_ids: &mut Vec<DocumentId>,
+) -> Result<()> {
+    if documents_ids.is_empty() {
+        return Ok(());
+    }
+
+    let scores = scorer(documents_ids);
+    if scores.len() != documents_ids.len() {
+        let (expected, received) = (documents_ids.len(), scores.len());
+        return Err(UserError::InvalidRescoringScores { expected, received }.into());
+    }
+
+    let mut scored: Vec<_> = documents_ids
+        .iter()
+        .copied()
+        .zip(scores.into_iter().map(|s| if s.is_nan() { f64::NEG_INFINITY } else { s }))
+        .collect();
+    // The sort is stable, the ties keep the order of the ranking rules.
+    scored.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+    documents_ids.clear();
+    documents_ids.extend(scored.into_iter().map(|(docid, _)| docid));
+
+    Ok(())
+}
+
+/// The stop words that were kept are the ones that appear in the primitive query,
+/// either because they are the last (prefix) word or because they are in a phrase.
+fn removed_stop_words<'a>(
+    stop_words: impl Iterator<Item = &'a str>,
+    primitive_query: &[PrimitiveQueryPart],
+) -> Vec<String> {
+    let kept_words: Vec<&str> = primitive_query
+        .iter()
+        .flat_map(|part| match part {
+            PrimitiveQueryPart::Word(word, _) => vec![word.as_str()],
+            PrimitiveQueryPart::Phrase(words) => words.iter().map(String::as_str).collect(),
+        })
+        .collect();
+    stop_words.filter(|word| !kept_words.contains(word)).map(str::to_string).collect()
+}
+
+/// The state of the search-relevant settings at the time of a search, distributed callers
+/// can compare it between their replicas to detect a search made on stale settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchSettingsSnapshot {
+    /// The last time the index was updated.
+    pub updated_at: OffsetDateTime,
+    /// The ranking rules used by the search, including the ones overridden at search time.
+    pub criteria: Vec<Criterion>,
+    /// Whether the typos were authorized, by both the index and the search.
+    pub authorize_typos: bool,
+    pub min_word_len_one_typo: u8,
+    pub min_word_len_two_typos: u8,
+    /// A checksum of the stop words, `0` if there are no stop words.
+    pub stop_words_checksum: u64,
+}
 
+/// The details of the analysis of a query, from the raw tokens to the query tree.
+#[derive(Debug, Default, Clone)]
+pub struct QueryTokenization {
+    /// All the tokens produced by the analyzer, separators included.
+    pub tokens: Vec<QueryToken>,
+    /// The script detected for the whole query, if any.
+    pub script: Option<Script>,
+    /// The language detected for the whole query, if any.
+    pub language: Option<Lang>,
+    /// The stop words that were removed from the query before building the query tree.
+    pub removed_stop_words: Vec<String>,
+    /// The query tree built from the tokens, `None` if there was no word to search for.
+    pub query_tree: Option<Operation>,
+}
+
+/// The proximity found in a document between two consecutive query words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordPairProximity {
+    pub left: String,
+    pub right: String,
+    /// The smallest proximity found between the two words in the document,
+    /// `None` if one of the words doesn't appear in the document.
+    pub proximity: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryToken {
+    pub word: String,
+    pub kind: TokenKind,
+    pub byte_start: usize,
+    pub byte_end: usize,
+}
+
+/// The derivations of a word keyed by the word, whether it is a prefix, the maximum number
+/// of typos and whether a transposition costs one typo.
+pub type WordDerivationsCache = HashMap<(String, bool, u8, bool), Vec<(String, u8)>>;
+
+/// Returns the words of the fst within `max_typo` typos of `word`,
+/// a transposition of two adjacent characters counting as one typo.
 pub fn word_derivations<'c>(
     word: &str,
     is_prefix: bool,
@@ -294,7 +1179,28 @@ pub fn word_derivations<'c>(
     fst: &fst::Set<Cow<[u8]>>,
     cache: &'c mut WordDerivationsCache,
 ) -> StdResult<&'c [(String, u8)], Utf8Error> {
-    match cache.entry((word.to_string(), is_prefix, max_typo)) {
+    word_derivations_with_transposition(word, is_prefix, max_typo, true, fst, None, cache)
+}
+
+/// Same as [`word_derivations`] but a transposition counts as two typos when
+/// `transposition_cost_one` is `false`, the automatons are taken from the `dfa_cache` if any.
+pub(crate) fn word_derivations_with_transposition<'c>(
+    word: &str,
+    is_prefix: bool,
+    max_typo: u8,
+    transposition_cost_one: bool,
+    fst: &fst::Set<Cow<[u8]>>,
+    dfa_cache: Option<&DfaCache>,
+    cache: &'c mut WordDerivationsCache,
+) -> StdResult<&'c [(String, u8)], Utf8Error> {
+    // The automatons are shared with the previous searches of the index when possible.
+    let shared_dfa = |typos| match dfa_cache {
+        Some(dfa_cache) => dfa_cache.get_or_build(word, typos, is_prefix, transposition_cost_one),
+        None => {
+            Arc::new(build_dfa_with_transposition(word, typos, is_prefix, transposition_cost_one))
+        }
+    };
+    match cache.entry((word.to_string(), is_prefix, max_typo, transposition_cost_one)) {
         Entry::Occupied(entry) => Ok(entry.into_mut()),
         Entry::Vacant(entry) => {
             let mut derived_words = Vec::new();
@@ -312,10 +1218,10 @@ pub fn word_derivations<'c>(
                 }
             } else {
                 if max_typo == 1 {
-                    let dfa = build_dfa(word, 1, is_prefix);
+                    let dfa = shared_dfa(1);
                     let starts = StartsWith(Str::new(get_first(word)));
                     let mut stream =
-                        fst.search_with_state(Intersection(starts, &dfa)).into_stream();
+                        fst.search_with_state(Intersection(starts, &*dfa)).into_stream();
 
                     while let Some((word, state)) = stream.next() {
                         let word = std::str::from_utf8(word)?;
@@ -324,9 +1230,10 @@ pub fn word_derivations<'c>(
                     }
                 } else {
                     let starts = StartsWith(Str::new(get_first(word)));
-                    let first = Intersection(build_dfa(word, 1, is_prefix), Complement(&starts));
-                    let second_dfa = build_dfa(word, 2, is_prefix);
-                    let second = Intersection(&second_dfa, &starts);
+                    let first_dfa = shared_dfa(1);
+                    let first = Intersection(&*first_dfa, Complement(&starts));
+                    let second_dfa = shared_dfa(2);
+                    let second = Intersection(&*second_dfa, &starts);
                     let automaton = Union(first, &second);
 
                     let mut stream = fst.search_with_state(automaton).into_stream();
@@ -359,10 +1266,23 @@ fn get_first(s: &str) -> &str {
 }
 
 pub fn build_dfa(word: &str, typos: u8, is_prefix: bool) -> DFA {
-    let lev = match typos {
-        0 => &LEVDIST0,
-        1 => &LEVDIST1,
-        _ => &LEVDIST2,
+    build_dfa_with_transposition(word, typos, is_prefix, true)
+}
+
+/// Builds a DFA where a transposition of two adjacent characters counts
+/// as one typo if `transposition_cost_one` is `true` and two typos otherwise.
+pub fn build_dfa_with_transposition(
+    word: &str,
+    typos: u8,
+    is_prefix: bool,
+    transposition_cost_one: bool,
+) -> DFA {
+    let lev = match (typos, transposition_cost_one) {
+        (0, _) => &LEVDIST0,
+        (1, true) => &LEVDIST1,
+        (1, false) => &LEVDIST1_NO_TRANSPOSITION,
+        (_, true) => &LEVDIST2,
+        (_, false) => &LEVDIST2_NO_TRANSPOSITION,
     };
 
     if is_prefix {
@@ -374,8 +1294,12 @@ pub fn build_dfa(word: &str, typos: u8, is_prefix: bool) -> DFA {
 
 #[cfg(test)]
 mod test {
+    use big_s::S;
+    use maplit::{btreeset, hashset};
+
     use super::*;
     use crate::index::tests::TempIndex;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
 
     #[test]
     fn test_is_authorized_typos() {
@@ -402,10 +1326,787 @@ mod test {
         assert!(!search.is_typo_authorized().unwrap());
     }
 
+    #[test]
+    fn test_query_tokenization() {
+        let index = TempIndex::new();
+        let mut txn = index.write_txn().unwrap();
+        let config = IndexerConfig::default();
+        let mut builder = Settings::new(&mut txn, &index, &config);
+        builder.set_stop_words(btreeset! { S("the") });
+        builder.execute(|_| ()).unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        assert!(search.query_tokenization().unwrap().query_tree.is_none());
+
+        search.query("the quick brown fox");
+        let tokenization = search.query_tokenization().unwrap();
+        assert_eq!(tokenization.removed_stop_words, vec![S("the")]);
+        let words: Vec<_> =
+            tokenization.tokens.iter().filter(|t| t.kind == TokenKind::Word).collect();
+        assert_eq!(words.len(), 3);
+        assert_eq!(words[0].word, "quick");
+        assert_eq!(&"the quick brown fox"[words[0].byte_start..words[0].byte_end], "quick");
+        assert!(tokenization.query_tree.is_some());
+    }
+
+    #[test]
+    fn test_settings_snapshot() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        assert!(search.execute().unwrap().settings.is_none());
+
+        search.retrieve_settings(true);
+        let settings = search.execute().unwrap().settings.unwrap();
+        assert_eq!(settings.criteria, crate::default_criteria());
+        assert!(settings.authorize_typos);
+        assert_eq!(settings.stop_words_checksum, 0);
+
+        search.authorize_typos(false).ranking_rules(vec![Criterion::Words]);
+        let settings = search.execute().unwrap().settings.unwrap();
+        assert_eq!(settings.criteria, vec![Criterion::Words]);
+        assert!(!settings.authorize_typos);
+        drop(txn);
+
+        let mut txn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut txn, &index, &config);
+        builder.set_stop_words(btreeset! { S("the") });
+        builder.execute(|_| ()).unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        search.retrieve_settings(true);
+        let new_settings = search.execute().unwrap().settings.unwrap();
+        assert_ne!(new_settings.stop_words_checksum, 0);
+        assert!(new_settings.updated_at >= settings.updated_at);
+    }
+
+    #[test]
+    fn test_document_proximities() {
+        let index = TempIndex::new();
+        let mut txn = index.write_txn().unwrap();
+        let config = IndexerConfig::default();
+        let content = documents!([
+            { "id": 0, "text": "the quick brown fox jumps", "other": "dog" }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        assert!(search.document_proximities(0).unwrap().is_empty());
+
+        search.query("quick fox do");
+        let proximities = search.document_proximities(0).unwrap();
+        assert_eq!(
+            proximities,
+            vec![
+                WordPairProximity { left: S("quick"), right: S("fox"), proximity: Some(2) },
+                // the words are in different attributes
+                WordPairProximity {
+                    left: S("fox"),
+                    right: S("do"),
+                    proximity: Some(crate::proximity::MAX_DISTANCE)
+                },
+            ]
+        );
+
+        search.query("fox cat");
+        let proximities = search.document_proximities(0).unwrap();
+        assert_eq!(
+            proximities,
+            vec![WordPairProximity { left: S("fox"), right: S("cat"), proximity: None }]
+        );
+    }
+
+    #[test]
+    fn test_search_observer() {
+        use std::cell::RefCell;
+        use std::time::Duration;
+
+        #[derive(Default)]
+        struct Recorder {
+            query_tree: RefCell<Option<QueryTreeStats>>,
+            buckets: RefCell<Vec<(String, Option<u64>)>>,
+            finished: RefCell<Option<(usize, u64)>>,
+        }
+
+        impl SearchObserver for Recorder {
+            fn query_tree_built(&self, stats: Option<QueryTreeStats>, _elapsed: Duration) {
+                *self.query_tree.borrow_mut() = stats;
+            }
+
+            fn criterion_bucket(&self, criterion: &str, candidates: Option<u64>) {
+                self.buckets.borrow_mut().push((criterion.to_string(), candidates));
+            }
+
+            fn search_finished(&self, returned: usize, candidates: u64, _elapsed: Duration) {
+                *self.finished.borrow_mut() = Some((returned, candidates));
+            }
+        }
+
+        let index = TempIndex::new();
+        let mut txn = index.write_txn().unwrap();
+        let config = IndexerConfig::default();
+        let content = documents!([
+            { "id": 0, "text": "hello world" },
+            { "id": 1, "text": "hello" },
+            { "id": 2, "text": "world" }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        let recorder = Recorder::default();
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        search.query("hello world").ranking_rules(vec![Criterion::Words]).observer(&recorder);
+        let result = search.execute().unwrap();
+
+        let stats = recorder.query_tree.borrow().clone().unwrap();
+        assert!(stats.queries >= 2);
+        assert_eq!(
+            *recorder.finished.borrow(),
+            Some((result.documents_ids.len(), result.candidates.len()))
+        );
+        let buckets = recorder.buckets.borrow();
+        assert!(!buckets.is_empty());
+        assert!(buckets.iter().all(|(name, _)| name == "words"));
+    }
+
+    #[test]
+    fn test_placeholder_search_cache() {
+        use std::cell::Cell;
+
+        #[derive(Default)]
+        struct BucketsCounter(Cell<usize>);
+
+        impl SearchObserver for BucketsCounter {
+            fn criterion_bucket(&self, _criterion: &str, _candidates: Option<u64>) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut txn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut txn, &index, &config);
+        builder.set_sortable_fields(hashset! { S("age") });
+        builder.execute(|_| ()).unwrap();
+        let content = documents!([
+            { "id": 0, "age": 30 },
+            { "id": 1, "age": 10 },
+            { "id": 2, "age": 40 },
+            { "id": 3, "age": 20 }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        let cache = PlaceholderSearchCache::new();
+        let sorted_search = |offset, limit, counter: &BucketsCounter| {
+            let txn = index.read_txn().unwrap();
+            let mut search = Search::new(&txn, &index);
+            search
+                .sort_criteria(vec![AscDesc::Desc(Member::Field(S("age")))])
+                .offset(offset)
+                .limit(limit)
+                .placeholder_cache(&cache)
+                .observer(counter);
+            search.execute().unwrap().documents_ids
+        };
+
+        let counter = BucketsCounter::default();
+        assert_eq!(sorted_search(0, 2, &counter), vec![2, 0]);
+        assert!(counter.0.get() > 0);
+
+        // the second page is in the cached prefix, the criteria are not evaluated.
+        let counter = BucketsCounter::default();
+        assert_eq!(sorted_search(1, 1, &counter), vec![0]);
+        assert_eq!(counter.0.get(), 0);
+
+        // the third page is further away than the cached prefix.
+        let counter = BucketsCounter::default();
+        assert_eq!(sorted_search(2, 2, &counter), vec![3, 1]);
+        assert!(counter.0.get() > 0);
+
+        // a write to the index invalidates the cached searches.
+        let mut txn = index.write_txn().unwrap();
+        let content = documents!([{ "id": 4, "age": 50 }]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        let counter = BucketsCounter::default();
+        assert_eq!(sorted_search(0, 2, &counter), vec![4, 2]);
+        assert!(counter.0.get() > 0);
+    }
+
+    #[test]
+    fn test_diversify_by() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut txn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut txn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("brand") });
+        builder.execute(|_| ()).unwrap();
+        let content = documents!([
+            { "id": 0, "brand": "apple" },
+            { "id": 1, "brand": "apple" },
+            { "id": 2, "brand": "apple" },
+            { "id": 3, "brand": "samsung" },
+            { "id": 4, "brand": "samsung" }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        assert_eq!(search.execute().unwrap().documents_ids, vec![0, 1, 2, 3, 4]);
+
+        // the documents are alternated while both brands are available.
+        search.diversify_by("brand");
+        assert_eq!(search.execute().unwrap().documents_ids, vec![0, 3, 1, 4, 2]);
+
+        search.offset(1).limit(2);
+        assert_eq!(search.execute().unwrap().documents_ids, vec![3, 1]);
+
+        let mut search = Search::new(&txn, &index);
+        search.diversify_by("id");
+        assert!(matches!(
+            search.execute(),
+            Err(crate::Error::UserError(UserError::InvalidRankingRuleAttribute { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_deduplicate() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut txn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut txn, &index, &config);
+        builder.set_content_hash_fields(hashset! { S("title"), S("body") });
+        builder.execute(|_| ()).unwrap();
+        let content = documents!([
+            { "id": 0, "url": "a.com", "title": "hello", "body": "world" },
+            { "id": 1, "url": "b.com", "title": "goodbye", "body": "world" },
+            { "id": 2, "url": "mirror.a.com", "body": "world", "title": "hello" },
+            { "id": 3, "url": "c.com", "title": "hello" },
+            { "id": 4, "url": "d.com" }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        assert_eq!(search.execute().unwrap().documents_ids, vec![0, 1, 2, 3, 4]);
+
+        // the mirror of the first document is dropped, the documents without any
+        // content hash field are never duplicates.
+        search.deduplicate(true);
+        assert_eq!(search.execute().unwrap().documents_ids, vec![0, 1, 3, 4]);
+        search.query("hello");
+        assert_eq!(search.execute().unwrap().documents_ids, vec![0, 3]);
+        drop(txn);
+
+        // the documents are not deduplicated anymore once the setting is reset.
+        let mut txn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut txn, &index, &config);
+        builder.reset_content_hash_fields();
+        builder.execute(|_| ()).unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        search.deduplicate(true);
+        assert_eq!(search.execute().unwrap().documents_ids, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_add_filter() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut txn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut txn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("tenant"), S("deleted"), S("price") });
+        builder.execute(|_| ()).unwrap();
+        let content = documents!([
+            { "id": 0, "tenant": "a", "deleted": false, "price": 10 },
+            { "id": 1, "tenant": "a", "deleted": true, "price": 20 },
+            { "id": 2, "tenant": "b", "deleted": false, "price": 30 },
+            { "id": 3, "tenant": "a", "deleted": false, "price": 40 }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        let user_filter = Filter::from_str("price < 35").unwrap().unwrap();
+        let tenant = Filter::from_str("tenant = a").unwrap().unwrap();
+        let not_deleted = Filter::from_str("deleted = false").unwrap().unwrap();
+
+        // the filters are all intersected
+        let mut search = Search::new(&txn, &index);
+        search.filter(user_filter.clone());
+        search.add_filter(tenant.clone()).add_filter(not_deleted.clone());
+        assert_eq!(search.execute().unwrap().documents_ids, vec![0]);
+
+        let mut search = Search::new(&txn, &index);
+        search.filters(vec![tenant, not_deleted]);
+        assert_eq!(search.execute().unwrap().documents_ids, vec![0, 3]);
+
+        // filter replaces the previous filters and an empty list doesn't filter anything
+        search.filter(user_filter);
+        assert_eq!(search.execute().unwrap().documents_ids, vec![0, 1, 2]);
+        search.filters(Vec::new());
+        assert_eq!(search.execute().unwrap().documents_ids, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_negative_words() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut txn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "title": "the dog and the cat" },
+            { "id": 1, "title": "the dog" },
+            { "id": 2, "title": "the cat" },
+            { "id": 3, "title": "a well-behaved dog" }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        search.query("dog -cat");
+        let mut documents_ids = search.execute().unwrap().documents_ids;
+        documents_ids.sort_unstable();
+        assert_eq!(documents_ids, vec![1, 3]);
+
+        // a query with only negated words excludes the documents from all of them
+        search.query("-dog");
+        assert_eq!(search.execute().unwrap().documents_ids, vec![2]);
+
+        // a hyphen inside of a word doesn't negate it
+        search.query("well-behaved");
+        assert_eq!(search.execute().unwrap().documents_ids, vec![3]);
+    }
+
+    #[test]
+    fn test_negative_stop_words() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut txn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut txn, &index, &config);
+        builder.set_stop_words(btreeset! { S("the") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "the dog and the cat" },
+            { "id": 1, "title": "a dog" },
+            { "id": 2, "title": "the cat" },
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        // the negated stop words are ignored and reported like the other removed stop words
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        search.query("dog -the -cat");
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1]);
+        assert_eq!(result.normalized_query.unwrap().removed_stop_words, vec![S("the")]);
+    }
+
+    #[test]
+    fn test_compute_scores() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut txn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "title": "hello world" },
+            { "id": 1, "title": "hello" },
+            { "id": 2, "title": "hello wirld" }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        search.query("hello world");
+        search.ranking_rules(vec![Criterion::Words, Criterion::Typo]);
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0, 2, 1]);
+        assert!(result.documents_scores.is_empty());
+
+        search.compute_scores(true);
+        let result = search.execute().unwrap();
+        let score =
+            |words, typo| ScoreDetails { ranks: vec![(S("words"), words), (S("typo"), typo)] };
+        let expected = vec![(0, score(0, 0)), (2, score(0, 1)), (1, score(1, 0))];
+        assert_eq!(result.documents_ids, vec![0, 2, 1]);
+        assert_eq!(result.documents_scores, expected);
+    }
+
+    #[test]
+    fn test_rescore() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut txn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "kevin" },
+            { "id": 1, "name": "kevin" },
+            { "id": 2, "name": "kevin" },
+            { "id": 3, "name": "kevin" },
+            { "id": 4, "name": "kevin" }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        let by_docid =
+            |docids: &[DocumentId]| -> Vec<f64> { docids.iter().map(|&id| id as f64).collect() };
+
+        // only the first (offset + limit) * factor documents are rescored
+        let mut search = Search::new(&txn, &index);
+        search.query("kevin").limit(2).rescore(2, &by_docid);
+        assert_eq!(search.execute().unwrap().documents_ids, vec![3, 2]);
+        search.offset(1);
+        assert_eq!(search.execute().unwrap().documents_ids, vec![3, 2]);
+
+        // the ties keep the order of the ranking rules and NaN is ranked last
+        let scores = |docids: &[DocumentId]| -> Vec<f64> {
+            docids.iter().map(|&id| if id == 0 { f64::NAN } else { 1.0 }).collect()
+        };
+        let mut search = Search::new(&txn, &index);
+        search.rescore(1, &scores);
+        assert_eq!(search.execute().unwrap().documents_ids, vec![1, 2, 3, 4, 0]);
+
+        let missing_scores = |_: &[DocumentId]| vec![1.0];
+        let mut search = Search::new(&txn, &index);
+        search.rescore(1, &missing_scores);
+        assert!(matches!(
+            search.execute(),
+            Err(crate::Error::UserError(UserError::InvalidRescoringScores {
+                expected: 5,
+                received: 1
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_words_position_limit() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut txn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "text": "breaking news about the city council" },
+            { "id": 1, "text": "the city council discussed the breaking news" },
+            { "id": 2, "text": "nothing to see here" }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        search.query("breaking news ");
+        let mut documents_ids = search.execute().unwrap().documents_ids;
+        documents_ids.sort_unstable();
+        assert_eq!(documents_ids, vec![0, 1]);
+
+        // only the first two words of the attributes can be matched.
+        search.words_position_limit(2);
+        assert_eq!(search.execute().unwrap().documents_ids, vec![0]);
+
+        search.query("city ");
+        assert!(search.execute().unwrap().documents_ids.is_empty());
+
+        search.query("the ");
+        assert_eq!(search.execute().unwrap().documents_ids, vec![1]);
+    }
+
+    #[test]
+    fn test_normalized_query() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut txn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut txn, &index, &config);
+        builder.set_stop_words(btreeset! { S("the") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "text": "the brown fox" },
+            { "id": 1, "text": "helloworld and the fox" },
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        search.query("the brovn hello world fox");
+        let result = search.execute().unwrap();
+        let normalized = result.normalized_query.unwrap();
+        assert_eq!(normalized.query, "brown hello world fox");
+        assert_eq!(normalized.removed_stop_words, vec![S("the")]);
+        assert_eq!(normalized.corrections, vec![(S("brovn"), S("brown"))]);
+        assert_eq!(normalized.ngrams, vec![S("helloworld")]);
+
+        search.query("\"brown fox\"");
+        let normalized = search.execute().unwrap().normalized_query.unwrap();
+        assert_eq!(normalized.query, "\"brown fox\"");
+        assert!(normalized.corrections.is_empty());
+
+        search.query("");
+        assert!(search.execute().unwrap().normalized_query.is_none());
+    }
+
+    #[test]
+    fn test_ignored_words() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut txn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut txn, &index, &config);
+        builder.set_stop_words(btreeset! { S("the") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "text": "the brown fox jumps" },
+            { "id": 1, "text": "the brown dog" },
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        search.query("the brown dog -fox");
+        assert!(search.execute().unwrap().ignored_words.is_empty());
+
+        // the stop words don't count, the negated words are never ignored
+        search.words_limit(1);
+        let result = search.execute().unwrap();
+        assert_eq!(result.ignored_words, vec![S("dog")]);
+        assert_eq!(result.documents_ids, vec![1]);
+
+        search.query("the brown \"fox jumps\" dog").words_limit(2);
+        let result = search.execute().unwrap();
+        assert_eq!(result.ignored_words, vec![S("dog")]);
+        assert_eq!(result.documents_ids, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_phrase_with_stop_words() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut txn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut txn, &index, &config);
+        builder.set_stop_words(btreeset! { S("the"), S("of") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "the hobbit" },
+            { "id": 1, "title": "the statue of liberty" },
+            { "id": 2, "title": "a statue of the liberty" },
+            { "id": 3, "title": "statue liberty" },
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        search.query("\"the hobbit\"");
+        assert_eq!(search.execute().unwrap().documents_ids, vec![0]);
+
+        // the stop words aren't indexed but keep the distance between the words
+        search.query("\"statue of liberty\"");
+        assert_eq!(search.execute().unwrap().documents_ids, vec![1]);
+
+        search.query("\"statue of the liberty\"");
+        assert_eq!(search.execute().unwrap().documents_ids, vec![2]);
+    }
+
+    #[test]
+    fn test_limit_keeps_the_ranking() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut txn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut txn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("kind") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "the quick brown fox", "kind": "a" },
+            { "id": 1, "title": "quick fox", "kind": "b" },
+            { "id": 2, "title": "the brown fox is quick", "kind": "a" },
+            { "id": 3, "title": "a quikc brown fox", "kind": "a" },
+            { "id": 4, "title": "fox", "kind": "b" },
+            { "id": 5, "title": "brown quick fox", "kind": "a" },
+            { "id": 6, "title": "the lazy dog", "kind": "b" },
+            { "id": 7, "title": "quick brown", "kind": "b" },
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        for filter in [None, Some("kind = a")] {
+            let search = |offset, limit| {
+                let mut search = Search::new(&txn, &index);
+                search.query("quick brown fox").offset(offset).limit(limit);
+                if let Some(filter) = filter {
+                    search.filter(Filter::from_str(filter).unwrap().unwrap());
+                }
+                search.execute().unwrap()
+            };
+
+            // the documents of every bucket are ranked the same way whatever the page
+            let SearchResult { documents_ids: ranking, candidates, .. } = search(0, 20);
+            for offset in 0..=ranking.len() {
+                for limit in 1..=ranking.len() {
+                    let end = ranking.len().min(offset + limit);
+                    assert_eq!(search(offset, limit).documents_ids, &ranking[offset..end]);
+                }
+            }
+
+            // the search stops once the filtered documents are ranked, they are all candidates
+            if filter.is_some() {
+                assert_eq!(ranking.len(), 4);
+                assert_eq!(candidates, [0, 2, 3, 5].iter().copied().collect::<RoaringBitmap>());
+            }
+        }
+    }
+
+    #[test]
+    fn test_exactness_prefix_last_word() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut txn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut txn, &index, &config);
+        builder.set_criteria(vec![S("exactness")]);
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "text": "a big apple" },
+            { "id": 1, "text": "big apple" },
+            { "id": 2, "text": "a big app" },
+            { "id": 3, "text": "big app store" },
+            { "id": 4, "text": "big app" },
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        // the last word typed matching as a full word ranks first, then the attributes
+        // starting with the query in which it only matches as a prefix.
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        search.query("big app");
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![4, 3, 1, 2, 0]);
+    }
+
+    #[test]
+    fn test_boost_fields() {
+        let index = TempIndex::new();
+        let mut txn = index.write_txn().unwrap();
+        let config = IndexerConfig::default();
+        let content = documents!([
+            { "id": 0, "title": "hello", "body": "the world" },
+            { "id": 1, "title": "the world", "body": "hello" }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut txn, &index, &config, IndexDocumentsConfig::default(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        txn.commit().unwrap();
+
+        let txn = index.read_txn().unwrap();
+        let mut search = Search::new(&txn, &index);
+        search.query("hello").ranking_rules(vec![Criterion::Words, Criterion::Attribute]);
+        assert_eq!(search.execute().unwrap().documents_ids, vec![0, 1]);
+
+        search.boost_fields(&[("body", 3.0)]);
+        assert_eq!(search.execute().unwrap().documents_ids, vec![1, 0]);
+
+        search.boost_fields(&[("body", 1.0), ("unknown", 2.0)]);
+        assert_eq!(search.execute().unwrap().documents_ids, vec![0, 1]);
+
+        search.boost_fields(&[("title", -1.0)]);
+        let error = search.execute().unwrap_err();
+        assert!(matches!(error, crate::Error::UserError(UserError::InvalidFieldBoost { .. })));
+    }
+
     #[test]
     fn test_one_typos_tolerance() {
         let fst = fst::Set::from_iter(["zealand"].iter()).unwrap().map_data(Cow::Owned).unwrap();
-        let mut cache = HashMap::new();
+        let mut cache = WordDerivationsCache::new();
         let found = word_derivations("zealend", false, 1, &fst, &mut cache).unwrap();
 
         assert_eq!(found, &[("zealand".to_string(), 1)]);
@@ -414,7 +2115,7 @@ mod test {
     #[test]
     fn test_one_typos_first_letter() {
         let fst = fst::Set::from_iter(["zealand"].iter()).unwrap().map_data(Cow::Owned).unwrap();
-        let mut cache = HashMap::new();
+        let mut cache = WordDerivationsCache::new();
         let found = word_derivations("sealand", false, 1, &fst, &mut cache).unwrap();
 
         assert_eq!(found, &[]);
@@ -423,7 +2124,7 @@ mod test {
     #[test]
     fn test_two_typos_tolerance() {
         let fst = fst::Set::from_iter(["zealand"].iter()).unwrap().map_data(Cow::Owned).unwrap();
-        let mut cache = HashMap::new();
+        let mut cache = WordDerivationsCache::new();
         let found = word_derivations("zealemd", false, 2, &fst, &mut cache).unwrap();
 
         assert_eq!(found, &[("zealand".to_string(), 2)]);
@@ -432,16 +2133,34 @@ mod test {
     #[test]
     fn test_two_typos_first_letter() {
         let fst = fst::Set::from_iter(["zealand"].iter()).unwrap().map_data(Cow::Owned).unwrap();
-        let mut cache = HashMap::new();
+        let mut cache = WordDerivationsCache::new();
         let found = word_derivations("sealand", false, 2, &fst, &mut cache).unwrap();
 
         assert_eq!(found, &[("zealand".to_string(), 2)]);
     }
 
+    #[test]
+    fn test_transposition_cost() {
+        let fst = fst::Set::from_iter(["zealand"].iter()).unwrap().map_data(Cow::Owned).unwrap();
+        let mut cache = WordDerivationsCache::new();
+        let found = word_derivations("zaeland", false, 1, &fst, &mut cache).unwrap();
+        assert_eq!(found, &[("zealand".to_string(), 1)]);
+
+        // the derivations of the same word are cached separately for each transposition cost.
+        let found =
+            word_derivations_with_transposition("zaeland", false, 1, false, &fst, None, &mut cache)
+                .unwrap();
+        assert_eq!(found, &[]);
+        let found =
+            word_derivations_with_transposition("zaeland", false, 2, false, &fst, None, &mut cache)
+                .unwrap();
+        assert_eq!(found, &[("zealand".to_string(), 2)]);
+    }
+
     #[test]
     fn test_prefix() {
         let fst = fst::Set::from_iter(["zealand"].iter()).unwrap().map_data(Cow::Owned).unwrap();
-        let mut cache = HashMap::new();
+        let mut cache = WordDerivationsCache::new();
         let found = word_derivations("ze", true, 0, &fst, &mut cache).unwrap();
 
         assert_eq!(found, &[("zealand".to_string(), 0)]);
@@ -450,7 +2169,7 @@ mod test {
     #[test]
     fn test_bad_prefix() {
         let fst = fst::Set::from_iter(["zealand"].iter()).unwrap().map_data(Cow::Owned).unwrap();
-        let mut cache = HashMap::new();
+        let mut cache = WordDerivationsCache::new();
         let found = word_derivations("se", true, 0, &fst, &mut cache).unwrap();
 
         assert_eq!(found, &[]);
@@ -459,9 +2178,50 @@ mod test {
     #[test]
     fn test_prefix_with_typo() {
         let fst = fst::Set::from_iter(["zealand"].iter()).unwrap().map_data(Cow::Owned).unwrap();
-        let mut cache = HashMap::new();
+        let mut cache = WordDerivationsCache::new();
         let found = word_derivations("zae", true, 1, &fst, &mut cache).unwrap();
 
         assert_eq!(found, &[("zealand".to_string(), 1)]);
     }
+
+    #[test]
+    fn test_dfas_are_shared_between_queries() {
+        let dfa_cache = DfaCache::default();
+        let first = dfa_cache.get_or_build("zealand", 1, true, true);
+        let second = dfa_cache.get_or_build("zealand", 1, true, true);
+        assert!(Arc::ptr_eq(&first, &second));
+
+        let other = dfa_cache.get_or_build("zealand", 1, false, true);
+        assert!(!Arc::ptr_eq(&first, &other));
+
+        // another cache doesn't share its automatons.
+        let third = DfaCache::default().get_or_build("zealand", 1, true, true);
+        assert!(!Arc::ptr_eq(&first, &third));
+
+        // the derivations found with a shared automaton are the same.
+        let fst = fst::Set::from_iter(["zealand"].iter()).unwrap().map_data(Cow::Owned).unwrap();
+        let found = word_derivations_with_transposition(
+            "zealamd",
+            false,
+            1,
+            true,
+            &fst,
+            Some(&dfa_cache),
+            &mut WordDerivationsCache::new(),
+        )
+        .unwrap()
+        .to_vec();
+        let found_again = word_derivations_with_transposition(
+            "zealamd",
+            false,
+            1,
+            true,
+            &fst,
+            Some(&dfa_cache),
+            &mut WordDerivationsCache::new(),
+        )
+        .unwrap();
+        assert_eq!(found, found_again);
+        assert_eq!(found, vec![("zealand".to_string(), 1)]);
+    }
 }
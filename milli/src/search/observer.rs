@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use super::query_tree::Operation;
+
+/// Observes the execution of the searches, e.g. to ship search telemetry
+/// without parsing the debug logs. Every method does nothing by default.
+pub trait SearchObserver {
+    /// Called once the query tree is built, `stats` is `None` if there is no query tree.
+    fn query_tree_built(&self, _stats: Option<QueryTreeStats>, _duration: Duration) {}
+
+    /// Called once the filters are evaluated, with the number of candidates allowed
+    /// by them or `None` if the search is not filtered.
+    fn filters_evaluated(&self, _candidates: Option<u64>, _duration: Duration) {}
+
+    /// Called every time a criterion returns a bucket, with the name of the criterion
+    /// and the number of candidates in the bucket, `None` if they were not computed yet.
+    fn criterion_bucket(&self, _criterion: &str, _candidates: Option<u64>) {}
+
+    /// Called once the search is done, with the number of returned documents,
+    /// the total number of candidates and the time taken by the whole search.
+    fn search_finished(&self, _documents: usize, _candidates: u64, _duration: Duration) {}
+}
+
+/// Statistics about the query tree of a search.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct QueryTreeStats {
+    /// The number of words and phrases that are searched for, derivations included.
+    pub queries: usize,
+    /// The number of operations in the tree, queries included.
+    pub operations: usize,
+    /// The maximum depth of the tree.
+    pub depth: usize,
+}
+
+impl QueryTreeStats {
+    pub(crate) fn new(query_tree: &Operation) -> QueryTreeStats {
+        match query_tree {
+            Operation::And(ops) | Operation::Or(_, ops) => {
+                let mut stats = QueryTreeStats::default();
+                for op in ops {
+                    let child = QueryTreeStats::new(op);
+                    stats.queries += child.queries;
+                    stats.operations += child.operations;
+                    stats.depth = stats.depth.max(child.depth);
+                }
+                stats.operations += 1;
+                stats.depth += 1;
+                stats
+            }
+            Operation::Phrase(_) | Operation::Query(_) => {
+                QueryTreeStats { queries: 1, operations: 1, depth: 1 }
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use roaring::RoaringBitmap;
+
+use crate::DocumentId;
+
+/// The default number of placeholder searches kept by a cache.
+const DEFAULT_CAPACITY: usize = 64;
+
+/// Caches the ordered documents ids of the placeholder searches, the searches without a query,
+/// for each combination of filters, sort and ranking rules. Landing pages that always list the
+/// same documents skip the evaluation of the filters and criteria entirely.
+///
+/// Only the prefix of the ordered documents that was requested is kept, it is extended when a
+/// search asks for documents further away. The entries are invalidated by any write to the index.
+pub struct PlaceholderSearchCache {
+    capacity: usize,
+    entries: Mutex<HashMap<String, CachedPlaceholder>>,
+}
+
+struct CachedPlaceholder {
+    generation: u64,
+    documents_ids: Vec<DocumentId>,
+    candidates: RoaringBitmap,
+    /// Whether the documents ids contain all the documents returned by the search.
+    exhaustive: bool,
+}
+
+/// Identifies a placeholder search on a given state of the index.
+pub(crate) struct PlaceholderKey {
+    pub search: String,
+    pub generation: u64,
+}
+
+impl PlaceholderSearchCache {
+    pub fn new() -> PlaceholderSearchCache {
+        PlaceholderSearchCache::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// Creates a cache that keeps at most `capacity` different placeholder searches.
+    pub fn with_capacity(capacity: usize) -> PlaceholderSearchCache {
+        PlaceholderSearchCache { capacity, entries: Mutex::new(HashMap::new()) }
+    }
+
+    /// Removes all the cached searches.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    /// Returns the page of documents ids and the candidates of the search if the cached prefix
+    /// of the search, computed on the same state of the index, is long enough.
+    pub(crate) fn get(
+        &self,
+        key: &PlaceholderKey,
+        offset: usize,
+        limit: usize,
+    ) -> Option<(Vec<DocumentId>, RoaringBitmap)> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = entries.get(&key.search).filter(|e| e.generation == key.generation)?;
+        if !entry.exhaustive && entry.documents_ids.len() < offset.saturating_add(limit) {
+            return None;
+        }
+
+        let documents_ids = entry.documents_ids.iter().copied().skip(offset).take(limit).collect();
+        Some((documents_ids, entry.candidates.clone()))
+    }
+
+    pub(crate) fn insert(
+        &self,
+        key: PlaceholderKey,
+        documents_ids: Vec<DocumentId>,
+        candidates: RoaringBitmap,
+        exhaustive: bool,
+    ) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        // Only the entries computed on the state of the index of the new entry are kept.
+        entries.retain(|_, entry| entry.generation == key.generation);
+        if entries.len() >= self.capacity && !entries.contains_key(&key.search) {
+            return;
+        }
+
+        let entry =
+            CachedPlaceholder { generation: key.generation, documents_ids, candidates, exhaustive };
+        entries.insert(key.search, entry);
+    }
+}
+
+impl Default for PlaceholderSearchCache {
+    fn default() -> PlaceholderSearchCache {
+        PlaceholderSearchCache::new()
+    }
+}
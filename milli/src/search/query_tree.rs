@@ -1,15 +1,17 @@
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::{cmp, fmt, mem};
 
 use fst::Set;
 use meilisearch_tokenizer::token::SeparatorKind;
 use meilisearch_tokenizer::tokenizer::TokenStream;
 use meilisearch_tokenizer::TokenKind;
+use once_cell::unsync::OnceCell;
 use roaring::RoaringBitmap;
 use slice_group_by::GroupBy;
 
 use crate::search::matches::matching_words::{MatchingWord, PrimitiveWordId};
-use crate::{Index, MatchingWords, Result};
+use crate::{Index, MatchingWords, NormalizerOptions, QueryRewrite, Result, WordBoundaries};
 
 type IsOptionalWord = bool;
 type IsPrefix = bool;
@@ -17,8 +19,9 @@ type IsPrefix = bool;
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub enum Operation {
     And(Vec<Operation>),
-    // serie of consecutive non prefix and exact words
-    Phrase(Vec<String>),
+    // serie of consecutive non prefix and exact words,
+    // the stop words are not indexed and leave a gap in the serie.
+    Phrase(Vec<Option<String>>),
     Or(IsOptionalWord, Vec<Operation>),
     Query(Query),
 }
@@ -67,11 +70,30 @@ impl Operation {
         }
     }
 
-    fn phrase(mut words: Vec<String>) -> Self {
-        if words.len() == 1 {
-            Self::Query(Query { prefix: false, kind: QueryKind::exact(words.pop().unwrap()) })
-        } else {
-            Self::Phrase(words)
+    fn phrase(mut words: Vec<Option<String>>) -> Self {
+        // the gaps surrounding the phrase don't constrain the positions of its words.
+        while let Some(None) = words.last() {
+            words.pop();
+        }
+        let start = words.iter().position(Option::is_some).unwrap_or(words.len());
+        words.drain(..start);
+
+        match words.as_mut_slice() {
+            [Some(word)] => {
+                Self::Query(Query { prefix: false, kind: QueryKind::exact(mem::take(word)) })
+            }
+            _ => Self::Phrase(words),
+        }
+    }
+
+    /// Collects all the queries, the leaves, of the tree.
+    pub fn collect_queries<'a>(&'a self, queries: &mut Vec<&'a Query>) {
+        match self {
+            Operation::And(ops) | Operation::Or(_, ops) => {
+                ops.iter().for_each(|op| op.collect_queries(queries))
+            }
+            Operation::Phrase(_) => (),
+            Operation::Query(query) => queries.push(query),
         }
     }
 
@@ -144,6 +166,10 @@ impl fmt::Debug for Query {
 trait Context {
     fn word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>>;
     fn synonyms<S: AsRef<str>>(&self, words: &[S]) -> heed::Result<Option<Vec<Vec<String>>>>;
+    /// Returns the phrases that also match these words, see [`QueryRewrite::Augment`].
+    fn augmentations<S: AsRef<str>>(&self, _words: &[S]) -> heed::Result<Vec<Vec<String>>> {
+        Ok(Vec::new())
+    }
     fn word_documents_count(&self, word: &str) -> heed::Result<Option<u64>> {
         match self.word_docids(word)? {
             Some(rb) => Ok(Some(rb.len())),
@@ -153,6 +179,16 @@ trait Context {
     /// Returns the minimum word len for 1 and 2 typos.
     fn min_word_len_for_typo(&self) -> heed::Result<(u8, u8)>;
     fn exact_words(&self) -> crate::Result<fst::Set<Cow<[u8]>>>;
+    /// Returns `true` if the words only composed of digits must not be derived with typos.
+    fn disable_typos_on_numbers(&self) -> heed::Result<bool>;
+    /// Returns `true` if swapping two adjacent characters counts as one typo.
+    fn transposition_cost_one(&self) -> heed::Result<bool> {
+        Ok(true)
+    }
+    /// Returns `true` if the word is a stop word of the index, stop words aren't indexed.
+    fn is_stop_word(&self, _word: &str) -> crate::Result<bool> {
+        Ok(false)
+    }
 }
 
 /// The query tree builder is the interface to build a query tree.
@@ -162,6 +198,10 @@ pub struct QueryTreeBuilder<'a> {
     optional_words: bool,
     authorize_typos: bool,
     words_limit: Option<usize>,
+    /// The exact words FST is read once, the query tree reads it for every word.
+    exact_words: OnceCell<fst::Set<Cow<'a, [u8]>>>,
+    /// The stop words FST is read once, the phrases are checked word by word.
+    stop_words: OnceCell<Option<fst::Set<&'a [u8]>>>,
 }
 
 impl<'a> Context for QueryTreeBuilder<'a> {
@@ -173,6 +213,16 @@ impl<'a> Context for QueryTreeBuilder<'a> {
         self.index.words_synonyms(self.rtxn, words)
     }
 
+    fn augmentations<S: AsRef<str>>(&self, words: &[S]) -> heed::Result<Vec<Vec<String>>> {
+        let pattern: Vec<_> = words.iter().map(AsRef::as_ref).collect();
+        match self.index.query_rewrites(self.rtxn)?.remove(&pattern.join(" ")) {
+            Some(QueryRewrite::Augment(query)) if !query.is_empty() => {
+                Ok(vec![query.split(' ').map(str::to_string).collect()])
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
     fn word_documents_count(&self, word: &str) -> heed::Result<Option<u64>> {
         self.index.word_documents_count(self.rtxn, word)
     }
@@ -184,7 +234,21 @@ impl<'a> Context for QueryTreeBuilder<'a> {
     }
 
     fn exact_words(&self) -> crate::Result<fst::Set<Cow<[u8]>>> {
-        self.index.exact_words(self.rtxn)
+        let exact_words = self.exact_words.get_or_try_init(|| self.index.exact_words(self.rtxn))?;
+        Ok(exact_words.clone())
+    }
+
+    fn disable_typos_on_numbers(&self) -> heed::Result<bool> {
+        self.index.disable_typos_on_numbers(self.rtxn)
+    }
+
+    fn transposition_cost_one(&self) -> heed::Result<bool> {
+        self.index.transposition_counts_as_one_typo(self.rtxn)
+    }
+
+    fn is_stop_word(&self, word: &str) -> crate::Result<bool> {
+        let stop_words = self.stop_words.get_or_try_init(|| self.index.stop_words(self.rtxn))?;
+        Ok(stop_words.as_ref().map_or(false, |stop_words| stop_words.contains(word)))
     }
 }
 
@@ -192,7 +256,15 @@ impl<'a> QueryTreeBuilder<'a> {
     /// Create a `QueryTreeBuilder` from a heed ReadOnly transaction `rtxn`
     /// and an Index `index`.
     pub fn new(rtxn: &'a heed::RoTxn<'a>, index: &'a Index) -> Self {
-        Self { rtxn, index, optional_words: true, authorize_typos: true, words_limit: None }
+        Self {
+            rtxn,
+            index,
+            optional_words: true,
+            authorize_typos: true,
+            words_limit: None,
+            exact_words: OnceCell::new(),
+            stop_words: OnceCell::new(),
+        }
     }
 
     /// if `optional_words` is set to `false` the query tree will be
@@ -227,12 +299,26 @@ impl<'a> QueryTreeBuilder<'a> {
     /// - if `authorize_typos` is set to `false` the query tree will be generated
     ///   forcing all query words to match documents without any typo
     ///   (the criterion `typo` will be ignored)
+    ///
+    /// The `text` is the analyzed query, the words are split on the word boundaries of the index.
     pub fn build(
         &self,
+        text: &str,
         query: TokenStream,
     ) -> Result<Option<(Operation, PrimitiveQuery, MatchingWords)>> {
         let stop_words = self.index.stop_words(self.rtxn)?;
-        let primitive_query = create_primitive_query(query, stop_words, self.words_limit);
+        let word_boundaries = self.index.word_boundaries(self.rtxn)?;
+        let normalizer_options = self.index.normalizer_options(self.rtxn)?;
+        let query_rewrites = self.index.query_rewrites(self.rtxn)?;
+        let (primitive_query, _, _) = create_primitive_query(
+            text,
+            query,
+            stop_words,
+            word_boundaries,
+            normalizer_options,
+            self.words_limit,
+        );
+        let primitive_query = rewrite_primitive_query(primitive_query, &query_rewrites);
         if !primitive_query.is_empty() {
             let qt = create_query_tree(
                 self,
@@ -247,6 +333,49 @@ impl<'a> QueryTreeBuilder<'a> {
             Ok(None)
         }
     }
+
+    /// Returns the words of the query beyond the `words_limit`, that are ignored by
+    /// [`QueryTreeBuilder::build`], to warn that a truncated query was searched.
+    pub fn ignored_words(&self, text: &str, query: TokenStream) -> Result<Vec<String>> {
+        let stop_words = self.index.stop_words(self.rtxn)?;
+        let word_boundaries = self.index.word_boundaries(self.rtxn)?;
+        let normalizer_options = self.index.normalizer_options(self.rtxn)?;
+        let (_, _, ignored_words) = create_primitive_query(
+            text,
+            query,
+            stop_words,
+            word_boundaries,
+            normalizer_options,
+            self.words_limit,
+        );
+        Ok(ignored_words)
+    }
+
+    /// Build the negated branch of the query tree, the words prefixed by a minus sign
+    /// (e.g. `-word`), the documents containing any of these words must be excluded.
+    ///
+    /// The negated words are exact words, they are neither prefixes nor typo tolerant.
+    /// The negated stop words are ignored like the other stop words of the query.
+    pub fn build_negative(&self, text: &str, query: TokenStream) -> Result<Option<Operation>> {
+        let word_boundaries = self.index.word_boundaries(self.rtxn)?;
+        let normalizer_options = self.index.normalizer_options(self.rtxn)?;
+        let (_, negative_words, _) =
+            create_primitive_query(text, query, None, word_boundaries, normalizer_options, None);
+        Ok(create_negative_query_tree(negative_words))
+    }
+}
+
+/// Create the negated branch of the query tree, an `Or` of the exact negated words.
+fn create_negative_query_tree(negative_words: Vec<String>) -> Option<Operation> {
+    if negative_words.is_empty() {
+        return None;
+    }
+
+    let operations = negative_words
+        .into_iter()
+        .map(|word| Operation::Query(Query { prefix: false, kind: QueryKind::exact(word) }))
+        .collect();
+    Some(Operation::or(false, operations))
 }
 
 /// Split the word depending on the frequency of subwords in the database documents.
@@ -278,12 +407,14 @@ pub struct TypoConfig<'a> {
     pub word_len_one_typo: u8,
     pub word_len_two_typo: u8,
     pub exact_words: fst::Set<Cow<'a, [u8]>>,
+    pub disable_on_numbers: bool,
 }
 
 /// Return the `QueryKind` of a word depending on `authorize_typos`
 /// and the provided word length.
 fn typos<'a>(word: String, authorize_typos: bool, config: TypoConfig<'a>) -> QueryKind {
-    if authorize_typos && !config.exact_words.contains(&word) {
+    let is_disabled_number = config.disable_on_numbers && word.chars().all(|c| c.is_ascii_digit());
+    if authorize_typos && !is_disabled_number && !config.exact_words.contains(&word) {
         let count = word.chars().count().min(u8::MAX as usize) as u8;
         if count < config.word_len_one_typo {
             QueryKind::exact(word)
@@ -298,24 +429,49 @@ fn typos<'a>(word: String, authorize_typos: bool, config: TypoConfig<'a>) -> Que
 }
 
 /// Fetch synonyms from the `Context` for the provided word
-/// and create the list of operations for the query tree
-fn synonyms(ctx: &impl Context, word: &[&str]) -> heed::Result<Option<Vec<Operation>>> {
+/// and create the list of operations for the query tree,
+/// the phrases augmenting the word are added to the synonyms.
+///
+/// The synonyms of multiple words are phrases, e.g. `nyc` matches
+/// the documents containing `new york city` but not `york new city`.
+fn synonyms(ctx: &impl Context, word: &[&str]) -> Result<Option<Vec<Operation>>> {
     let synonyms = ctx.synonyms(word)?;
+    let augmentations = ctx.augmentations(word)?;
+
+    let mut operations = match synonyms {
+        Some(synonyms) => {
+            let phrases = synonyms.into_iter().map(|synonym| phrase(ctx, synonym));
+            Some(phrases.collect::<Result<Vec<_>>>()?)
+        }
+        None => None,
+    };
+
+    if !augmentations.is_empty() {
+        let phrases = augmentations.into_iter().map(|words| phrase(ctx, words));
+        let phrases = phrases.collect::<Result<Vec<_>>>()?;
+        operations.get_or_insert_with(Vec::new).extend(phrases);
+    }
 
-    Ok(synonyms.map(|synonyms| {
-        synonyms
-            .into_iter()
-            .map(|synonym| {
-                let words = synonym
-                    .into_iter()
-                    .map(|word| {
-                        Operation::Query(Query { prefix: false, kind: QueryKind::exact(word) })
-                    })
-                    .collect();
-                Operation::and(words)
-            })
-            .collect()
-    }))
+    Ok(operations)
+}
+
+/// Creates the phrase of the quoted words or of a synonym, the stop words aren't indexed
+/// and are replaced by gaps that still count as a position between the other words.
+fn phrase(ctx: &impl Context, words: Vec<String>) -> Result<Operation> {
+    let mut stop_words = Vec::with_capacity(words.len());
+    for word in &words {
+        stop_words.push(ctx.is_stop_word(word)?);
+    }
+
+    // a phrase only made of stop words is kept as is, there is nothing to search around.
+    let only_stop_words = stop_words.iter().all(|is_stop_word| *is_stop_word);
+    let words = words
+        .into_iter()
+        .zip(stop_words)
+        .map(|(word, is_stop_word)| (only_stop_words || !is_stop_word).then(|| word))
+        .collect();
+
+    Ok(Operation::phrase(words))
 }
 
 /// Main function that creates the final query tree from the primitive query.
@@ -339,12 +495,21 @@ fn create_query_tree(
             PrimitiveQueryPart::Word(word, prefix) => {
                 let mut children = synonyms(ctx, &[&word])?.unwrap_or_default();
                 if let Some((left, right)) = split_best_frequency(ctx, &word)? {
-                    children.push(Operation::Phrase(vec![left.to_string(), right.to_string()]));
+                    children.push(Operation::Phrase(vec![
+                        Some(left.to_string()),
+                        Some(right.to_string()),
+                    ]));
                 }
                 let (word_len_one_typo, word_len_two_typo) = ctx.min_word_len_for_typo()?;
                 let exact_words = ctx.exact_words()?;
-                let config =
-                    TypoConfig { max_typos: 2, word_len_one_typo, word_len_two_typo, exact_words };
+                let disable_on_numbers = ctx.disable_typos_on_numbers()?;
+                let config = TypoConfig {
+                    max_typos: 2,
+                    word_len_one_typo,
+                    word_len_two_typo,
+                    exact_words,
+                    disable_on_numbers,
+                };
                 children.push(Operation::Query(Query {
                     prefix,
                     kind: typos(word, authorize_typos, config),
@@ -352,7 +517,7 @@ fn create_query_tree(
                 Ok(Operation::or(false, children))
             }
             // create a CONSECUTIVE operation wrapping all word in the phrase
-            PrimitiveQueryPart::Phrase(words) => Ok(Operation::phrase(words)),
+            PrimitiveQueryPart::Phrase(words) => phrase(ctx, words),
         }
     }
 
@@ -397,11 +562,13 @@ fn create_query_tree(
                             let (word_len_one_typo, word_len_two_typo) =
                                 ctx.min_word_len_for_typo()?;
                             let exact_words = ctx.exact_words()?;
+                            let disable_on_numbers = ctx.disable_typos_on_numbers()?;
                             let config = TypoConfig {
                                 max_typos: 1,
                                 word_len_one_typo,
                                 word_len_two_typo,
                                 exact_words,
+                                disable_on_numbers,
                             };
                             let query = Query {
                                 prefix: is_prefix,
@@ -502,12 +669,26 @@ fn create_matching_words(
 
                 let (word_len_one_typo, word_len_two_typo) = ctx.min_word_len_for_typo()?;
                 let exact_words = ctx.exact_words()?;
-                let config =
-                    TypoConfig { max_typos: 2, word_len_one_typo, word_len_two_typo, exact_words };
+                let disable_on_numbers = ctx.disable_typos_on_numbers()?;
+                let config = TypoConfig {
+                    max_typos: 2,
+                    word_len_one_typo,
+                    word_len_two_typo,
+                    exact_words,
+                    disable_on_numbers,
+                };
 
+                let transposition_cost_one = ctx.transposition_cost_one()?;
                 let matching_word = match typos(word, authorize_typos, config) {
                     QueryKind::Exact { word, .. } => MatchingWord::new(word, 0, prefix),
-                    QueryKind::Tolerant { typo, word } => MatchingWord::new(word, typo, prefix),
+                    QueryKind::Tolerant { typo, word } => {
+                        MatchingWord::with_transposition_cost_one(
+                            word,
+                            typo,
+                            prefix,
+                            transposition_cost_one,
+                        )
+                    }
                 };
                 matching_words.push((vec![matching_word], vec![id]));
             }
@@ -580,18 +761,26 @@ fn create_matching_words(
                             let (word_len_one_typo, word_len_two_typo) =
                                 ctx.min_word_len_for_typo()?;
                             let exact_words = ctx.exact_words()?;
+                            let disable_on_numbers = ctx.disable_typos_on_numbers()?;
                             let config = TypoConfig {
                                 max_typos: 1,
                                 word_len_one_typo,
                                 word_len_two_typo,
                                 exact_words,
+                                disable_on_numbers,
                             };
+                            let transposition_cost_one = ctx.transposition_cost_one()?;
                             let matching_word = match typos(word, authorize_typos, config) {
                                 QueryKind::Exact { word, .. } => {
                                     MatchingWord::new(word, 0, is_prefix)
                                 }
                                 QueryKind::Tolerant { typo, word } => {
-                                    MatchingWord::new(word, typo, is_prefix)
+                                    MatchingWord::with_transposition_cost_one(
+                                        word,
+                                        typo,
+                                        is_prefix,
+                                        transposition_cost_one,
+                                    )
                                 }
                             };
                             matching_words.push((vec![matching_word], ids));
@@ -616,14 +805,14 @@ fn create_matching_words(
 
 pub type PrimitiveQuery = Vec<PrimitiveQueryPart>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum PrimitiveQueryPart {
     Phrase(Vec<String>),
     Word(String, IsPrefix),
 }
 
 impl PrimitiveQueryPart {
-    fn is_phrase(&self) -> bool {
+    pub fn is_phrase(&self) -> bool {
         matches!(self, Self::Phrase(_))
     }
 
@@ -641,41 +830,78 @@ impl PrimitiveQueryPart {
 
 /// Create primitive query from tokenized query string,
 /// the primitive query is an intermediate state to build the query tree.
+///
+/// The words prefixed by a minus sign are not part of the primitive query,
+/// they are returned separately as the negated words, but for the stop words
+/// that are ignored. The words beyond the
+/// words limit are returned as the ignored words.
 fn create_primitive_query(
+    text: &str,
     query: TokenStream,
     stop_words: Option<Set<&[u8]>>,
+    word_boundaries: WordBoundaries,
+    normalizer_options: NormalizerOptions,
     words_limit: Option<usize>,
-) -> PrimitiveQuery {
+) -> (PrimitiveQuery, Vec<String>, Vec<String>) {
     let mut primitive_query = Vec::new();
+    let mut negative_words = Vec::new();
+    let mut ignored_words = Vec::new();
     let mut phrase = Vec::new();
     let mut quoted = false;
+    // a minus sign only negates a word when it is preceded by a whitespace or
+    // starts the query, e.g. `-word`, but not `hyphenated-word`.
+    let mut word_start = true;
+    let mut negated = false;
 
     let parts_limit = words_limit.unwrap_or(usize::MAX);
 
     let mut peekable = query.peekable();
     while let Some(token) = peekable.next() {
-        // early return if word limit is exceeded
-        if primitive_query.len() >= parts_limit {
-            return primitive_query;
-        }
-
         match token.kind {
             TokenKind::Word | TokenKind::StopWord => {
+                let original = text.get(token.byte_start..token.byte_end).unwrap_or("");
+                let normalized = normalizer_options.normalize(token.word.as_ref());
+                let words = word_boundaries.split(original, &normalized);
+                word_start = false;
+                // the stop words are not indexed, negating them wouldn't exclude any document.
+                if mem::take(&mut negated) {
+                    if token.kind == TokenKind::Word {
+                        negative_words.extend(words.into_iter().map(str::to_string));
+                    }
+                    continue;
+                }
+                // the words beyond the limit are ignored, the stop words would be removed anyway.
+                if primitive_query.len() >= parts_limit {
+                    if token.kind == TokenKind::Word {
+                        ignored_words.extend(words.into_iter().map(str::to_string));
+                    }
+                    continue;
+                }
                 // 1. if the word is quoted we push it in a phrase-buffer waiting for the ending quote,
                 // 2. if the word is not the last token of the query and is not a stop_word we push it as a non-prefix word,
-                // 3. if the word is the last token of the query we push it as a prefix word.
+                // 3. if the word is the last token of the query we push it as a prefix word,
+                //    only the last of the words split on the word boundaries is a prefix.
                 if quoted {
-                    phrase.push(token.word.to_string());
+                    phrase.extend(words.into_iter().map(str::to_string));
                 } else if peekable.peek().is_some() {
                     if !stop_words
                         .as_ref()
                         .map_or(false, |swords| swords.contains(token.word.as_ref()))
                     {
-                        primitive_query
-                            .push(PrimitiveQueryPart::Word(token.word.to_string(), false));
+                        primitive_query.extend(
+                            words
+                                .into_iter()
+                                .map(|word| PrimitiveQueryPart::Word(word.to_string(), false)),
+                        );
                     }
                 } else {
-                    primitive_query.push(PrimitiveQueryPart::Word(token.word.to_string(), true));
+                    let last = words.len() - 1;
+                    primitive_query.extend(
+                        words
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, word)| PrimitiveQueryPart::Word(word.to_string(), i == last)),
+                    );
                 }
             }
             TokenKind::Separator(separator_kind) => {
@@ -684,6 +910,13 @@ fn create_primitive_query(
                 if quote_count % 2 != 0 {
                     quoted = !quoted;
                 }
+                negated = !quoted
+                    && match token.word.strip_suffix('-') {
+                        Some("") => word_start,
+                        Some(before) => before.ends_with(char::is_whitespace),
+                        None => false,
+                    };
+                word_start = token.word.ends_with(char::is_whitespace);
                 // if there is a quote or a hard separator we close the phrase.
                 if !phrase.is_empty() && (quote_count > 0 || separator_kind == SeparatorKind::Hard)
                 {
@@ -699,7 +932,54 @@ fn create_primitive_query(
         primitive_query.push(PrimitiveQueryPart::Phrase(mem::take(&mut phrase)));
     }
 
-    primitive_query
+    (primitive_query, negative_words, ignored_words)
+}
+
+/// Replaces the consecutive words of the primitive query matching the pattern of a
+/// [`QueryRewrite::Replace`] rule, the longest pattern is replaced first. The last replacing
+/// word is a prefix if the last replaced word was one. The words of the phrases are kept.
+fn rewrite_primitive_query(
+    query: PrimitiveQuery,
+    rewrites: &BTreeMap<String, QueryRewrite>,
+) -> PrimitiveQuery {
+    let mut patterns: Vec<_> = rewrites
+        .iter()
+        .filter_map(|(pattern, rewrite)| match rewrite {
+            QueryRewrite::Replace(query) => Some((pattern.split(' ').collect::<Vec<_>>(), query)),
+            QueryRewrite::Augment(_) => None,
+        })
+        .collect();
+    if patterns.is_empty() {
+        return query;
+    }
+    patterns.sort_by_key(|(pattern, _)| cmp::Reverse(pattern.len()));
+
+    let mut rewritten = Vec::with_capacity(query.len());
+    let mut i = 0;
+    'parts: while i < query.len() {
+        for (pattern, replacement) in &patterns {
+            let matches = query.get(i..i + pattern.len()).map_or(false, |parts| {
+                parts
+                    .iter()
+                    .zip(pattern)
+                    .all(|(part, word)| matches!(part, PrimitiveQueryPart::Word(w, _) if w == word))
+            });
+            if matches {
+                let is_prefix = query[i + pattern.len() - 1].is_prefix();
+                let words: Vec<_> = replacement.split(' ').filter(|w| !w.is_empty()).collect();
+                let last = words.len().saturating_sub(1);
+                rewritten.extend(words.into_iter().enumerate().map(|(j, word)| {
+                    PrimitiveQueryPart::Word(word.to_string(), is_prefix && j == last)
+                }));
+                i += pattern.len();
+                continue 'parts;
+            }
+        }
+        rewritten.push(query[i].clone());
+        i += 1;
+    }
+
+    rewritten
 }
 
 /// Returns the maximum number of typos that this Operation allows.
@@ -744,6 +1024,7 @@ mod test {
         postings: HashMap<String, RoaringBitmap>,
         // Raw bytes for the exact word fst Set
         exact_words: Vec<u8>,
+        disable_typos_on_numbers: bool,
     }
 
     impl TestContext {
@@ -754,7 +1035,14 @@ mod test {
             words_limit: Option<usize>,
             query: TokenStream,
         ) -> Result<Option<(Operation, PrimitiveQuery)>> {
-            let primitive_query = create_primitive_query(query, None, words_limit);
+            let (primitive_query, _, _) = create_primitive_query(
+                "",
+                query,
+                None,
+                WordBoundaries::default(),
+                NormalizerOptions::default(),
+                words_limit,
+            );
             if !primitive_query.is_empty() {
                 let qt =
                     create_query_tree(self, optional_words, authorize_typos, &primitive_query)?;
@@ -782,6 +1070,10 @@ mod test {
         fn exact_words(&self) -> crate::Result<fst::Set<Cow<[u8]>>> {
             Ok(fst::Set::new(Cow::Borrowed(self.exact_words.as_slice())).unwrap())
         }
+
+        fn disable_typos_on_numbers(&self) -> heed::Result<bool> {
+            Ok(self.disable_typos_on_numbers)
+        }
     }
 
     impl Default for TestContext {
@@ -840,6 +1132,7 @@ mod test {
                     String::from("morning")    => random_postings(rng,    125),
                 },
                 exact_words,
+                disable_typos_on_numbers: false,
             }
         }
     }
@@ -928,15 +1221,9 @@ mod test {
                                 prefix: false,
                                 kind: QueryKind::exact("hi".to_string()),
                             }),
-                            Operation::And(vec![
-                                Operation::Query(Query {
-                                    prefix: false,
-                                    kind: QueryKind::exact("good".to_string()),
-                                }),
-                                Operation::Query(Query {
-                                    prefix: false,
-                                    kind: QueryKind::exact("morning".to_string()),
-                                }),
+                            Operation::Phrase(vec![
+                                Some("good".to_string()),
+                                Some("morning".to_string()),
                             ]),
                             Operation::Query(Query {
                                 prefix: false,
@@ -1018,19 +1305,10 @@ mod test {
                                 prefix: false,
                                 kind: QueryKind::exact("nyc".to_string()),
                             }),
-                            Operation::And(vec![
-                                Operation::Query(Query {
-                                    prefix: false,
-                                    kind: QueryKind::exact("new".to_string()),
-                                }),
-                                Operation::Query(Query {
-                                    prefix: false,
-                                    kind: QueryKind::exact("york".to_string()),
-                                }),
-                                Operation::Query(Query {
-                                    prefix: false,
-                                    kind: QueryKind::exact("city".to_string()),
-                                }),
+                            Operation::Phrase(vec![
+                                Some("new".to_string()),
+                                Some("york".to_string()),
+                                Some("city".to_string()),
                             ]),
                             Operation::Query(Query {
                                 prefix: false,
@@ -1050,16 +1328,7 @@ mod test {
                             prefix: false,
                             kind: QueryKind::exact("nyc".to_string()),
                         }),
-                        Operation::And(vec![
-                            Operation::Query(Query {
-                                prefix: false,
-                                kind: QueryKind::exact("new".to_string()),
-                            }),
-                            Operation::Query(Query {
-                                prefix: false,
-                                kind: QueryKind::exact("york".to_string()),
-                            }),
-                        ]),
+                        Operation::Phrase(vec![Some("new".to_string()), Some("york".to_string())]),
                         Operation::Query(Query {
                             prefix: false,
                             kind: QueryKind::tolerant(1, "newyorkcity".to_string()),
@@ -1122,7 +1391,10 @@ mod test {
                     Operation::Or(
                         false,
                         vec![
-                            Operation::Phrase(vec!["word".to_string(), "split".to_string()]),
+                            Operation::Phrase(vec![
+                                Some("word".to_string()),
+                                Some("split".to_string()),
+                            ]),
                             Operation::Query(Query {
                                 prefix: false,
                                 kind: QueryKind::tolerant(2, "wordsplit".to_string()),
@@ -1155,7 +1427,7 @@ mod test {
         let tokens = result.tokens();
 
         let expected = Operation::And(vec![
-            Operation::Phrase(vec!["hey".to_string(), "friends".to_string()]),
+            Operation::Phrase(vec![Some("hey".to_string()), Some("friends".to_string())]),
             Operation::Query(Query { prefix: false, kind: QueryKind::exact("wooop".to_string()) }),
         ]);
 
@@ -1165,6 +1437,96 @@ mod test {
         assert_eq!(expected, query_tree);
     }
 
+    #[test]
+    fn rewrite_primitive_query_words() {
+        let word = |word: &str, is_prefix| PrimitiveQueryPart::Word(word.to_string(), is_prefix);
+        let rewrites: BTreeMap<_, _> = vec![
+            ("tv".to_string(), QueryRewrite::Replace("television".to_string())),
+            ("new york".to_string(), QueryRewrite::Replace("nyc".to_string())),
+            ("new".to_string(), QueryRewrite::Replace(String::new())),
+            ("radio".to_string(), QueryRewrite::Augment("wireless".to_string())),
+        ]
+        .into_iter()
+        .collect();
+
+        let query = vec![
+            word("new", false),
+            word("york", false),
+            PrimitiveQueryPart::Phrase(vec!["tv".to_string()]),
+            word("new", false),
+            word("radio", false),
+            word("tv", true),
+        ];
+        let expected = vec![
+            word("nyc", false),
+            PrimitiveQueryPart::Phrase(vec!["tv".to_string()]),
+            word("radio", false),
+            word("television", true),
+        ];
+        assert_eq!(rewrite_primitive_query(query, &rewrites), expected);
+    }
+
+    #[test]
+    fn negative_words() {
+        let query = "hey -friends wooop-wooop -lol";
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+        let result = analyzer.analyze(query);
+
+        let (primitive_query, negative_words, _) = create_primitive_query(
+            query,
+            result.tokens(),
+            None,
+            WordBoundaries::default(),
+            NormalizerOptions::default(),
+            None,
+        );
+        let expected = vec![
+            PrimitiveQueryPart::Word("hey".to_string(), false),
+            PrimitiveQueryPart::Word("wooop".to_string(), false),
+            PrimitiveQueryPart::Word("wooop".to_string(), false),
+        ];
+        assert_eq!(primitive_query, expected);
+        assert_eq!(negative_words, vec!["friends".to_string(), "lol".to_string()]);
+
+        let expected = Operation::Or(
+            false,
+            vec![
+                Operation::Query(Query {
+                    prefix: false,
+                    kind: QueryKind::exact("friends".to_string()),
+                }),
+                Operation::Query(Query {
+                    prefix: false,
+                    kind: QueryKind::exact("lol".to_string()),
+                }),
+            ],
+        );
+        assert_eq!(create_negative_query_tree(negative_words), Some(expected));
+        assert_eq!(create_negative_query_tree(Vec::new()), None);
+    }
+
+    #[test]
+    fn negative_stop_words() {
+        let query = "hey -the -friends";
+        let stop_words = fst::Set::from_iter(Some("the")).unwrap();
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+        let result = analyzer.analyze(query);
+
+        let (primitive_query, negative_words, _) = create_primitive_query(
+            query,
+            result.tokens(),
+            None,
+            WordBoundaries::default(),
+            NormalizerOptions::default(),
+            None,
+        );
+        let expected = vec![PrimitiveQueryPart::Word("hey".to_string(), false)];
+        assert_eq!(primitive_query, expected);
+        assert_eq!(negative_words, vec!["friends".to_string()]);
+    }
+
     #[test]
     fn phrase_with_hard_separator() {
         let query = "\"hey friends. wooop wooop\"";
@@ -1173,8 +1535,8 @@ mod test {
         let tokens = result.tokens();
 
         let expected = Operation::And(vec![
-            Operation::Phrase(vec!["hey".to_string(), "friends".to_string()]),
-            Operation::Phrase(vec!["wooop".to_string(), "wooop".to_string()]),
+            Operation::Phrase(vec![Some("hey".to_string()), Some("friends".to_string())]),
+            Operation::Phrase(vec![Some("wooop".to_string()), Some("wooop".to_string())]),
         ]);
 
         let (query_tree, _) =
@@ -1275,7 +1637,7 @@ mod test {
         let result = analyzer.analyze(query);
         let tokens = result.tokens();
 
-        let expected = Operation::Phrase(vec!["hey".to_string(), "my".to_string()]);
+        let expected = Operation::Phrase(vec![Some("hey".to_string()), Some("my".to_string())]);
         let (query_tree, _) =
             TestContext::default().build(true, true, None, tokens).unwrap().unwrap();
 
@@ -1393,7 +1755,7 @@ mod test {
         let tokens = result.tokens();
 
         let expected = Operation::And(vec![
-            Operation::Phrase(vec!["hey".to_string(), "my".to_string()]),
+            Operation::Phrase(vec![Some("hey".to_string()), Some("my".to_string())]),
             Operation::Query(Query { prefix: false, kind: QueryKind::exact("good".to_string()) }),
         ]);
 
@@ -1401,13 +1763,59 @@ mod test {
             TestContext::default().build(false, false, Some(2), tokens).unwrap().unwrap();
 
         assert_eq!(expected, query_tree);
+
+        let (_, _, ignored_words) = create_primitive_query(
+            query,
+            analyzer.analyze(query).tokens(),
+            None,
+            WordBoundaries::default(),
+            NormalizerOptions::default(),
+            Some(2),
+        );
+        assert_eq!(ignored_words, vec!["friend".to_string()]);
+    }
+
+    #[test]
+    fn split_query_on_word_boundaries() {
+        let query = "new iPhone13 \"PlayStation5 slim\" macBook";
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+        let result = analyzer.analyze(query);
+        let boundaries = WordBoundaries { camel_case: true, letter_digit: true };
+        let (primitive_query, _, _) = create_primitive_query(
+            query,
+            result.tokens(),
+            None,
+            boundaries,
+            NormalizerOptions::default(),
+            None,
+        );
+
+        let expected = vec![
+            PrimitiveQueryPart::Word("new".to_string(), false),
+            PrimitiveQueryPart::Word("iphone".to_string(), false),
+            PrimitiveQueryPart::Word("13".to_string(), false),
+            PrimitiveQueryPart::Phrase(vec![
+                "play".to_string(),
+                "station".to_string(),
+                "5".to_string(),
+                "slim".to_string(),
+            ]),
+            PrimitiveQueryPart::Word("mac".to_string(), false),
+            PrimitiveQueryPart::Word("book".to_string(), true),
+        ];
+        assert_eq!(primitive_query, expected);
     }
 
     #[test]
     fn test_min_word_len_typo() {
         let exact_words = fst::Set::from_iter([b""]).unwrap().map_data(Cow::Owned).unwrap();
-        let config =
-            TypoConfig { max_typos: 2, word_len_one_typo: 5, word_len_two_typo: 7, exact_words };
+        let config = TypoConfig {
+            max_typos: 2,
+            word_len_one_typo: 5,
+            word_len_two_typo: 7,
+            exact_words,
+            disable_on_numbers: false,
+        };
 
         assert_eq!(
             typos("hello".to_string(), true, config.clone()),
@@ -1441,4 +1849,30 @@ mod test {
             Operation::Query(Query { prefix: true, kind: QueryKind::Exact { .. } })
         ));
     }
+
+    #[test]
+    fn disable_typo_on_numbers() {
+        let query = "20192021";
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+        let result = analyzer.analyze(query);
+
+        let tokens = result.tokens();
+        let context = TestContext { disable_typos_on_numbers: true, ..Default::default() };
+        let (query_tree, _) = context.build(false, true, Some(2), tokens).unwrap().unwrap();
+
+        assert!(matches!(
+            query_tree,
+            Operation::Query(Query { prefix: true, kind: QueryKind::Exact { .. } })
+        ));
+
+        let result = analyzer.analyze(query);
+        let tokens = result.tokens();
+        let (query_tree, _) =
+            TestContext::default().build(false, true, Some(2), tokens).unwrap().unwrap();
+
+        assert!(matches!(
+            query_tree,
+            Operation::Query(Query { prefix: true, kind: QueryKind::Tolerant { .. } })
+        ));
+    }
 }
@@ -0,0 +1,59 @@
+use heed::RoTxn;
+
+use super::{FacetDistribution, Filter, MandatoryFilter, Search, SearchSession};
+use crate::{DocumentId, Index, Result};
+
+/// A handle on an index that only reads the documents matching a mandatory filter,
+/// e.g. the documents of a tenant or the ones a user is allowed to see.
+///
+/// It is meant to be given to the code serving a tenant instead of the [`Index`]: the
+/// searches, facet distributions, browsed documents and search sessions it creates are all
+/// restricted by the mandatory filter and none of them can be created without it.
+pub struct RestrictedIndex<'a> {
+    index: &'a Index,
+    mandatory_filter: MandatoryFilter<'a>,
+}
+
+impl<'a> RestrictedIndex<'a> {
+    pub fn new(index: &'a Index, mandatory_filter: MandatoryFilter<'a>) -> RestrictedIndex<'a> {
+        RestrictedIndex { index, mandatory_filter }
+    }
+
+    pub fn read_txn(&self) -> heed::Result<RoTxn<'a>> {
+        self.index.read_txn()
+    }
+
+    /// Creates a search that only returns the documents matching the mandatory filter,
+    /// the filters and candidates of the search are always intersected with it.
+    pub fn search<'t>(&'t self, rtxn: &'t RoTxn) -> Search<'t> {
+        Search::restricted(rtxn, self.index, self.mandatory_filter.clone())
+    }
+
+    /// Creates a facet distribution that only counts the documents matching the mandatory filter.
+    pub fn facets_distribution<'t>(&'t self, rtxn: &'t RoTxn) -> FacetDistribution<'t> {
+        FacetDistribution::restricted(rtxn, self.index, self.mandatory_filter.clone())
+    }
+
+    /// Returns the documents matching the mandatory filter and the optional filter in the
+    /// internal ids order, skipping the first `offset` ones and returning at most `limit` of them.
+    pub fn browse_documents<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+        offset: usize,
+        limit: usize,
+        filter: Option<&Filter>,
+    ) -> Result<Vec<(DocumentId, obkv::KvReaderU16<'t>)>> {
+        let mut candidates = self.index.documents_ids(rtxn)?;
+        candidates &= self.mandatory_filter.evaluate(rtxn, self.index)?;
+        if let Some(filter) = filter {
+            candidates &= filter.evaluate(rtxn, self.index)?;
+        }
+        self.index.documents(rtxn, candidates.into_iter().skip(offset).take(limit))
+    }
+
+    /// Opens a search session whose searches and facet distributions are all restricted
+    /// by the mandatory filter.
+    pub fn search_session(&self) -> Result<SearchSession<'a>> {
+        SearchSession::restricted(self.index, self.mandatory_filter.clone())
+    }
+}
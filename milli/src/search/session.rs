@@ -0,0 +1,161 @@
+use std::time::{Duration, Instant};
+
+use heed::RoTxn;
+use log::warn;
+
+use super::{FacetDistribution, MandatoryFilter, Search};
+use crate::{Index, Result};
+
+/// The default age after which a snapshot is considered old.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// Runs several searches and facet distributions against the same consistent snapshot of an
+/// index by owning a read transaction.
+///
+/// A long-lived read transaction prevents LMDB from reusing the pages freed by the writes done
+/// since it was opened, which makes the database file grow. The session warns when its snapshot
+/// is older than the maximum age and can be renewed with [`SearchSession::renew_if_stale`].
+pub struct SearchSession<'i> {
+    index: &'i Index,
+    rtxn: RoTxn<'i>,
+    opened_at: Instant,
+    max_age: Duration,
+    mandatory_filter: Option<MandatoryFilter<'i>>,
+}
+
+impl<'i> SearchSession<'i> {
+    pub fn new(index: &'i Index) -> Result<SearchSession<'i>> {
+        let rtxn = index.read_txn()?;
+        Ok(SearchSession {
+            index,
+            rtxn,
+            opened_at: Instant::now(),
+            max_age: DEFAULT_MAX_AGE,
+            mandatory_filter: None,
+        })
+    }
+
+    /// Creates a session whose searches and facet distributions are all restricted by
+    /// the mandatory filter, see [`RestrictedIndex`](crate::RestrictedIndex).
+    pub(crate) fn restricted(
+        index: &'i Index,
+        mandatory_filter: MandatoryFilter<'i>,
+    ) -> Result<SearchSession<'i>> {
+        let mut session = SearchSession::new(index)?;
+        session.mandatory_filter = Some(mandatory_filter);
+        Ok(session)
+    }
+
+    /// Sets the age after which the snapshot is considered old, one minute by default.
+    pub fn max_age(&mut self, max_age: Duration) -> &mut SearchSession<'i> {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Returns the read transaction of the snapshot.
+    pub fn rtxn(&self) -> &RoTxn<'i> {
+        &self.rtxn
+    }
+
+    /// Returns the time elapsed since the snapshot was opened.
+    pub fn age(&self) -> Duration {
+        self.opened_at.elapsed()
+    }
+
+    /// Whether the snapshot is older than the maximum age.
+    pub fn is_stale(&self) -> bool {
+        self.age() > self.max_age
+    }
+
+    /// Creates a search on the snapshot of this session.
+    pub fn search(&self) -> Search {
+        self.warn_if_stale();
+        match self.mandatory_filter {
+            Some(ref mandatory_filter) => {
+                Search::restricted(&self.rtxn, self.index, mandatory_filter.clone())
+            }
+            None => Search::new(&self.rtxn, self.index),
+        }
+    }
+
+    /// Creates a facet distribution on the snapshot of this session.
+    pub fn facets_distribution(&self) -> FacetDistribution {
+        self.warn_if_stale();
+        match self.mandatory_filter {
+            Some(ref mandatory_filter) => {
+                FacetDistribution::restricted(&self.rtxn, self.index, mandatory_filter.clone())
+            }
+            None => FacetDistribution::new(&self.rtxn, self.index),
+        }
+    }
+
+    /// Replaces the snapshot by a new one that sees the last writes to the index.
+    pub fn renew(&mut self) -> Result<()> {
+        self.rtxn = self.index.read_txn()?;
+        self.opened_at = Instant::now();
+        Ok(())
+    }
+
+    /// Renews the snapshot if it is older than the maximum age, returns whether it was renewed.
+    pub fn renew_if_stale(&mut self) -> Result<bool> {
+        if self.is_stale() {
+            self.renew()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn warn_if_stale(&self) {
+        if self.is_stale() {
+            warn!(
+                "the search session snapshot is {:.02?} old, it prevents the database \
+                from reusing the pages freed since then",
+                self.age()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::tests::TempIndex;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig};
+
+    #[test]
+    fn searches_see_the_same_snapshot() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let add_documents = |content| {
+            let mut wtxn = index.write_txn().unwrap();
+            let mut builder = IndexDocuments::new(
+                &mut wtxn,
+                &index,
+                &config,
+                IndexDocumentsConfig::default(),
+                |_| (),
+            )
+            .unwrap();
+            builder.add_documents(content).unwrap();
+            builder.execute().unwrap();
+            wtxn.commit().unwrap();
+        };
+
+        add_documents(documents!([{ "id": 0, "name": "kevin" }]));
+
+        let mut session = SearchSession::new(&index).unwrap();
+        session.max_age(Duration::from_secs(3600));
+        assert_eq!(session.search().execute().unwrap().documents_ids, vec![0]);
+
+        add_documents(documents!([{ "id": 1, "name": "kevina" }]));
+
+        // the session still sees the snapshot it was opened on.
+        assert_eq!(session.search().execute().unwrap().documents_ids, vec![0]);
+        assert!(!session.renew_if_stale().unwrap());
+
+        session.max_age(Duration::from_secs(0));
+        assert!(session.renew_if_stale().unwrap());
+        assert_eq!(session.search().execute().unwrap().documents_ids, vec![0, 1]);
+    }
+}
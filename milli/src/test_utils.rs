@@ -0,0 +1,208 @@
+//! Helpers to build temporary indexes from inline JSON documents, for the tests of the crates
+//! depending on milli. They are only compiled with the `test-utils` feature.
+//!
+//! ```ignore
+//! use milli::test_utils::TestIndex;
+//!
+//! let index = TestIndex::builder()
+//!     .settings(|settings| settings.set_filterable_fields(vec!["genre".to_string()].into_iter().collect()))
+//!     .documents(serde_json::json!([
+//!         { "id": 1, "title": "Carol", "genre": "romance" },
+//!         { "id": 2, "title": "Wonder Woman", "genre": "action" },
+//!     ]))
+//!     .build()
+//!     .unwrap();
+//!
+//! let rtxn = index.read_txn().unwrap();
+//! let result = index.search(&rtxn).query("carol").execute().unwrap();
+//! assert_eq!(result.documents_ids, vec![0]);
+//! ```
+
+use std::io::Cursor;
+use std::ops::Deref;
+
+use heed::EnvOpenOptions;
+use serde_json::Value;
+use tempfile::TempDir;
+
+use crate::documents::{DocumentBatchBuilder, DocumentBatchReader};
+use crate::error::InternalError;
+use crate::update::{
+    DocumentAdditionResult, IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings,
+};
+use crate::{Index, Result};
+
+/// The default size of the memory map of the test indexes, 10 MiB.
+const DEFAULT_MAP_SIZE: usize = 10 * 1024 * 1024;
+
+type SettingsFn = Box<dyn for<'a, 't, 'u, 'i> FnOnce(&mut Settings<'a, 't, 'u, 'i>)>;
+
+/// An index stored in a temporary directory that is removed when the index is dropped.
+pub struct TestIndex {
+    inner: Index,
+    _tempdir: TempDir,
+}
+
+impl TestIndex {
+    /// Creates an empty index without any setting.
+    pub fn new() -> Result<TestIndex> {
+        TestIndex::builder().build()
+    }
+
+    pub fn builder() -> TestIndexBuilder {
+        TestIndexBuilder {
+            map_size: DEFAULT_MAP_SIZE,
+            settings: None,
+            documents: Vec::new(),
+            indexing_config: IndexDocumentsConfig::default(),
+        }
+    }
+
+    /// Indexes the documents, a JSON object or an array of objects, in one transaction.
+    pub fn add_documents(&self, documents: Value) -> Result<DocumentAdditionResult> {
+        self.add_documents_using_config(documents, IndexDocumentsConfig::default())
+    }
+
+    pub fn add_documents_using_config(
+        &self,
+        documents: Value,
+        indexing_config: IndexDocumentsConfig,
+    ) -> Result<DocumentAdditionResult> {
+        let config = IndexerConfig::default();
+        let mut wtxn = self.inner.write_txn()?;
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &self.inner, &config, indexing_config, |_| ())?;
+        builder.add_documents(documents_batch(documents)?)?;
+        let result = builder.execute()?;
+        wtxn.commit()?;
+        Ok(result)
+    }
+
+    /// Updates the settings of the index in one transaction.
+    pub fn update_settings<F>(&self, update: F) -> Result<()>
+    where
+        F: for<'a, 't, 'u, 'i> FnOnce(&mut Settings<'a, 't, 'u, 'i>),
+    {
+        let config = IndexerConfig::default();
+        let mut wtxn = self.inner.write_txn()?;
+        let mut builder = Settings::new(&mut wtxn, &self.inner, &config);
+        update(&mut builder);
+        builder.execute(|_| ())?;
+        wtxn.commit()?;
+        Ok(())
+    }
+}
+
+impl Deref for TestIndex {
+    type Target = Index;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+/// Builds a [`TestIndex`], the settings are applied before the documents are indexed.
+pub struct TestIndexBuilder {
+    map_size: usize,
+    settings: Option<SettingsFn>,
+    documents: Vec<Value>,
+    indexing_config: IndexDocumentsConfig,
+}
+
+impl TestIndexBuilder {
+    pub fn map_size(mut self, size: usize) -> Self {
+        self.map_size = size;
+        self
+    }
+
+    pub fn settings<F>(mut self, update: F) -> Self
+    where
+        F: for<'a, 't, 'u, 'i> FnOnce(&mut Settings<'a, 't, 'u, 'i>) + 'static,
+    {
+        self.settings = Some(Box::new(update));
+        self
+    }
+
+    /// Adds the documents, a JSON object or an array of objects,
+    /// the documents ids are attributed in the order of the documents.
+    pub fn documents(mut self, documents: Value) -> Self {
+        match documents {
+            Value::Array(documents) => self.documents.extend(documents),
+            document => self.documents.push(document),
+        }
+        self
+    }
+
+    pub fn indexing_config(mut self, config: IndexDocumentsConfig) -> Self {
+        self.indexing_config = config;
+        self
+    }
+
+    pub fn build(self) -> Result<TestIndex> {
+        let TestIndexBuilder { map_size, settings, documents, indexing_config } = self;
+
+        let mut options = EnvOpenOptions::new();
+        options.map_size(map_size);
+        let tempdir = tempfile::tempdir()?;
+        let index = TestIndex { inner: Index::new(options, tempdir.path())?, _tempdir: tempdir };
+
+        if let Some(settings) = settings {
+            index.update_settings(settings)?;
+        }
+        if !documents.is_empty() {
+            index.add_documents_using_config(Value::Array(documents), indexing_config)?;
+        }
+
+        Ok(index)
+    }
+}
+
+/// Creates a batch of documents from a JSON object or an array of objects.
+pub fn documents_batch(documents: Value) -> Result<DocumentBatchReader<Cursor<Vec<u8>>>> {
+    let mut writer = Cursor::new(Vec::new());
+    let mut builder = DocumentBatchBuilder::new(&mut writer)?;
+    let documents = serde_json::to_vec(&documents).map_err(InternalError::SerdeJson)?;
+    builder.extend_from_json(Cursor::new(documents))?;
+    builder.finish()?;
+
+    writer.set_position(0);
+    Ok(DocumentBatchReader::from_reader(writer)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use maplit::hashset;
+    use serde_json::json;
+
+    use super::*;
+    use crate::Filter;
+
+    #[test]
+    fn build_test_index() {
+        let index = TestIndex::builder()
+            .settings(|settings| settings.set_filterable_fields(hashset! { S("genre") }))
+            .documents(json!([
+                { "id": 1, "title": "Carol", "genre": "romance" },
+                { "id": 2, "title": "Wonder Woman", "genre": "action" },
+            ]))
+            .documents(json!({ "id": 3, "title": "Pride and Prejudice", "genre": "romance" }))
+            .build()
+            .unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 3);
+        let filter = Filter::from_str("genre = romance").unwrap().unwrap();
+        let docids = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0, 2]);
+        drop(rtxn);
+
+        let result = index.add_documents(json!([{ "id": 4, "title": "Carol" }])).unwrap();
+        assert_eq!(result.number_of_documents, 4);
+        index.update_settings(|settings| settings.set_searchable_fields(vec![S("genre")])).unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("carol").execute().unwrap();
+        assert!(result.documents_ids.is_empty());
+    }
+}
@@ -27,12 +27,15 @@ impl<'t, 'u, 'i> ClearDocuments<'t, 'u, 'i> {
             word_prefix_pair_proximity_docids,
             word_position_docids,
             field_id_word_count_docids,
+            docid_field_id_word_count,
             word_prefix_position_docids,
             facet_id_f64_docids,
             facet_id_string_docids,
             field_id_docid_facet_f64s,
             field_id_docid_facet_strings,
             documents,
+            vectors,
+            dfa_cache: _,
         } = self.index;
 
         // We retrieve the number of documents ids that we are deleting.
@@ -42,17 +45,22 @@ impl<'t, 'u, 'i> ClearDocuments<'t, 'u, 'i> {
         // We clean some of the main engine datastructures.
         self.index.put_words_fst(self.wtxn, &fst::Set::default())?;
         self.index.put_words_prefixes_fst(self.wtxn, &fst::Set::default())?;
+        self.index.delete_deleted_words_fst(self.wtxn)?;
         self.index.put_external_documents_ids(self.wtxn, &ExternalDocumentsIds::default())?;
         self.index.put_documents_ids(self.wtxn, &RoaringBitmap::default())?;
+        self.index.put_soft_deleted_documents_ids(self.wtxn, &RoaringBitmap::default())?;
         self.index.put_field_distribution(self.wtxn, &FieldDistribution::default())?;
         self.index.delete_geo_rtree(self.wtxn)?;
         self.index.delete_geo_faceted_documents_ids(self.wtxn)?;
+        self.index.delete_pending_prefix_databases_update(self.wtxn)?;
 
         // We clean all the faceted documents ids.
         let empty = RoaringBitmap::default();
         for field_id in faceted_fields {
             self.index.put_number_faceted_documents_ids(self.wtxn, field_id, &empty)?;
             self.index.put_string_faceted_documents_ids(self.wtxn, field_id, &empty)?;
+            self.index.put_boolean_faceted_documents_ids(self.wtxn, field_id, true, &empty)?;
+            self.index.put_boolean_faceted_documents_ids(self.wtxn, field_id, false, &empty)?;
         }
 
         // Clear the other databases.
@@ -65,12 +73,14 @@ impl<'t, 'u, 'i> ClearDocuments<'t, 'u, 'i> {
         word_prefix_pair_proximity_docids.clear(self.wtxn)?;
         word_position_docids.clear(self.wtxn)?;
         field_id_word_count_docids.clear(self.wtxn)?;
+        docid_field_id_word_count.clear(self.wtxn)?;
         word_prefix_position_docids.clear(self.wtxn)?;
         facet_id_f64_docids.clear(self.wtxn)?;
         facet_id_string_docids.clear(self.wtxn)?;
         field_id_docid_facet_f64s.clear(self.wtxn)?;
         field_id_docid_facet_strings.clear(self.wtxn)?;
         documents.clear(self.wtxn)?;
+        vectors.clear(self.wtxn)?;
 
         Ok(number_of_documents)
     }
@@ -93,7 +103,7 @@ mod tests {
         let mut wtxn = index.write_txn().unwrap();
         let content = documents!([
             { "id": 0, "name": "kevin", "age": 20 },
-            { "id": 1, "name": "kevina" },
+            { "id": 1, "name": "kevina", "_vectors": { "image": [0.5, 1] } },
             { "id": 2, "name": "benoit", "country": "France", "_geo": { "lng": 42, "lat": 35 } }
         ]);
         let indexing_config = IndexDocumentsConfig::default();
@@ -111,8 +121,9 @@ mod tests {
 
         let rtxn = index.read_txn().unwrap();
 
-        // the value is 7 because there is `[id, name, age, country, _geo, _geo.lng, _geo.lat]`
-        assert_eq!(index.fields_ids_map(&rtxn).unwrap().len(), 7);
+        // the value is 9 because there is
+        // `[id, name, age, country, _geo, _geo.lng, _geo.lat, _vectors, _vectors.image]`
+        assert_eq!(index.fields_ids_map(&rtxn).unwrap().len(), 9);
 
         assert!(index.words_fst(&rtxn).unwrap().is_empty());
         assert!(index.words_prefixes_fst(&rtxn).unwrap().is_empty());
@@ -127,11 +138,13 @@ mod tests {
         assert!(index.docid_word_positions.is_empty(&rtxn).unwrap());
         assert!(index.word_pair_proximity_docids.is_empty(&rtxn).unwrap());
         assert!(index.field_id_word_count_docids.is_empty(&rtxn).unwrap());
+        assert!(index.docid_field_id_word_count.is_empty(&rtxn).unwrap());
         assert!(index.word_prefix_pair_proximity_docids.is_empty(&rtxn).unwrap());
         assert!(index.facet_id_f64_docids.is_empty(&rtxn).unwrap());
         assert!(index.facet_id_string_docids.is_empty(&rtxn).unwrap());
         assert!(index.field_id_docid_facet_f64s.is_empty(&rtxn).unwrap());
         assert!(index.field_id_docid_facet_strings.is_empty(&rtxn).unwrap());
         assert!(index.documents.is_empty(&rtxn).unwrap());
+        assert!(index.vectors.is_empty(&rtxn).unwrap());
     }
 }
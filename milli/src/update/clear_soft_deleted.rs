@@ -0,0 +1,196 @@
+use super::DeleteDocuments;
+use crate::{Index, Result};
+
+/// The number of soft deleted documents above which they are purged by default.
+pub const DEFAULT_SOFT_DELETED_THRESHOLD: u64 = 10_000;
+
+/// Purges the soft deleted documents from the databases once there are more of them than the
+/// threshold, see [`DeleteDocuments::soft_deletion`]. It is meant to be run in the background,
+/// e.g. after every soft deletion, as it walks all the posting lists when it purges.
+pub struct ClearSoftDeleted<'t, 'u, 'i> {
+    wtxn: &'t mut heed::RwTxn<'i, 'u>,
+    index: &'i Index,
+    threshold: u64,
+}
+
+impl<'t, 'u, 'i> ClearSoftDeleted<'t, 'u, 'i> {
+    pub fn new(
+        wtxn: &'t mut heed::RwTxn<'i, 'u>,
+        index: &'i Index,
+    ) -> ClearSoftDeleted<'t, 'u, 'i> {
+        ClearSoftDeleted { wtxn, index, threshold: DEFAULT_SOFT_DELETED_THRESHOLD }
+    }
+
+    /// The number of soft deleted documents above which they are purged, zero purges them
+    /// as soon as there is one.
+    pub fn threshold(&mut self, threshold: u64) {
+        self.threshold = threshold;
+    }
+
+    /// Returns the number of purged documents, zero if there were not enough of them.
+    pub fn execute(self) -> Result<u64> {
+        let soft_deleted = self.index.soft_deleted_documents_ids(self.wtxn)?;
+        if soft_deleted.len() <= self.threshold {
+            return Ok(0);
+        }
+
+        // A hard deletion purges all the soft deleted documents.
+        DeleteDocuments::new(self.wtxn, self.index)?.execute()?;
+
+        Ok(soft_deleted.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use maplit::{btreemap, hashset};
+    use roaring::RoaringBitmap;
+
+    use super::*;
+    use crate::index::tests::TempIndex;
+    use crate::update::{
+        IndexDocuments, IndexDocumentsConfig, IndexerConfig, RenameFacetValue, Settings,
+    };
+    use crate::Filter;
+
+    #[test]
+    fn soft_delete_and_purge() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "kevin" },
+            { "id": 1, "name": "tamo" },
+            { "id": 2, "name": "kevin" },
+            { "id": 3, "name": "benoit" }
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.soft_deletion(true);
+        builder.delete_external_id("2");
+        let result = builder.execute().unwrap();
+        assert_eq!(result.deleted_documents, 1);
+        assert_eq!(result.remaining_documents, 3);
+        wtxn.commit().unwrap();
+
+        // The soft deleted document is still in the posting lists but is never returned.
+        let rtxn = index.read_txn().unwrap();
+        let mut soft_deleted = RoaringBitmap::new();
+        soft_deleted.insert(2);
+        assert_eq!(index.soft_deleted_documents_ids(&rtxn).unwrap(), soft_deleted);
+        assert!(index.word_docids.get(&rtxn, "kevin").unwrap().unwrap().contains(2));
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 3);
+        assert_eq!(index.all_documents(&rtxn).unwrap().count(), 3);
+        assert!(index.external_documents_ids(&rtxn).unwrap().get("2").is_none());
+        let result = index.search(&rtxn).query("kevin").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        assert!(index.verify(&rtxn).unwrap().is_valid());
+        drop(rtxn);
+
+        // A document added again doesn't reuse the internal id of the soft deleted one.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "id": 2, "name": "kevin" }]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        assert_eq!(index.external_documents_ids(&wtxn).unwrap().get("2"), Some(4));
+
+        // There are not enough soft deleted documents to purge them...
+        let mut builder = ClearSoftDeleted::new(&mut wtxn, &index);
+        builder.threshold(1);
+        assert_eq!(builder.execute().unwrap(), 0);
+
+        // ...until the threshold is lowered.
+        let mut builder = ClearSoftDeleted::new(&mut wtxn, &index);
+        builder.threshold(0);
+        assert_eq!(builder.execute().unwrap(), 1);
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.soft_deleted_documents_ids(&rtxn).unwrap().is_empty());
+        let docids = index.word_docids.get(&rtxn, "kevin").unwrap().unwrap();
+        assert_eq!(docids.into_iter().collect::<Vec<_>>(), vec![0, 4]);
+        assert!(index.documents(&rtxn, Some(2)).is_err());
+        let result = index.search(&rtxn).query("kevin").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0, 4]);
+        assert!(index.verify(&rtxn).unwrap().is_valid());
+    }
+
+    #[test]
+    fn soft_deleted_documents_are_ignored_by_facets() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("genre"), S("live") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "genre": "rock" },
+            { "id": 1, "genre": "rock", "live": true },
+            { "id": 2, "genre": "jazz" }
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.soft_deletion(true);
+        builder.delete_external_id("1");
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // The facet distribution without candidates reads the facet databases directly.
+        let rtxn = index.read_txn().unwrap();
+        let distribution = index.facets_distribution(&rtxn).facets(["genre", "live"]).execute();
+        let distribution = distribution.unwrap();
+        assert_eq!(distribution["genre"], btreemap! { S("jazz") => 1, S("rock") => 1 });
+        assert!(distribution["live"].is_empty());
+
+        let filter = Filter::from_str("genre = rock").unwrap().unwrap();
+        let documents_ids: Vec<_> = filter.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+        assert_eq!(documents_ids, vec![0]);
+        drop(rtxn);
+
+        // The soft deleted document is neither renamed nor indexed again.
+        let mut wtxn = index.write_txn().unwrap();
+        let builder = RenameFacetValue::new(&mut wtxn, &index, &config, "genre", "rock", "pop");
+        assert_eq!(builder.execute().unwrap(), 1);
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 2);
+        assert!(index.external_documents_ids(&rtxn).unwrap().get("1").is_none());
+        let filter = Filter::from_str("genre = pop").unwrap().unwrap();
+        let documents_ids: Vec<_> = filter.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+        assert_eq!(documents_ids, vec![0]);
+        assert!(index.verify(&rtxn).unwrap().is_valid());
+    }
+}
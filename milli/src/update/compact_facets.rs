@@ -0,0 +1,175 @@
+use std::convert::TryInto;
+
+use heed::types::{ByteSlice, DecodeIgnore};
+use heed::BytesDecode;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::error::SerializationError;
+use crate::heed_codec::facet::{
+    FacetLevelValueU32Codec, FacetStringLevelZeroValueCodec, FacetStringZeroBoundsValueCodec,
+};
+use crate::heed_codec::CboRoaringBitmapCodec;
+use crate::update::Facets;
+use crate::{FieldId, Index, Result};
+
+/// Drops the entries of the facet databases that don't reference any document anymore,
+/// or that belong to fields that are no longer faceted, without reindexing the documents.
+///
+/// The facet levels are recomputed afterward, their bounds could reference dropped values.
+pub struct CompactFacets<'t, 'u, 'i> {
+    wtxn: &'t mut heed::RwTxn<'i, 'u>,
+    index: &'i Index,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FacetsCompactionResult {
+    /// The number of entries removed from the facet databases.
+    pub removed_entries: u64,
+}
+
+impl<'t, 'u, 'i> CompactFacets<'t, 'u, 'i> {
+    pub fn new(wtxn: &'t mut heed::RwTxn<'i, 'u>, index: &'i Index) -> CompactFacets<'t, 'u, 'i> {
+        CompactFacets { wtxn, index }
+    }
+
+    #[logging_timer::time("CompactFacets::{}")]
+    pub fn execute(self) -> Result<FacetsCompactionResult> {
+        self.index.set_updated_at(self.wtxn, &OffsetDateTime::now_utc())?;
+        let faceted_fields = self.index.faceted_fields_ids(self.wtxn)?;
+        let is_unused = |key: &[u8]| match field_id_from_key(key) {
+            Some(field_id) => !faceted_fields.contains(&field_id),
+            None => true,
+        };
+
+        let mut removed_entries = 0;
+
+        let db = self.index.facet_id_f64_docids.remap_key_type::<ByteSlice>();
+        let mut iter = db.iter_mut(self.wtxn)?;
+        while let Some(result) = iter.next() {
+            let (key, docids) = result?;
+            if docids.is_empty() || is_unused(key) {
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.del_current()? };
+                removed_entries += 1;
+            }
+        }
+        drop(iter);
+
+        let db_name = Some(crate::index::db_name::FACET_ID_STRING_DOCIDS);
+        let db = self.index.facet_id_string_docids.remap_types::<ByteSlice, ByteSlice>();
+        let mut iter = db.iter_mut(self.wtxn)?;
+        while let Some(result) = iter.next() {
+            let (key, val) = result?;
+            let is_empty = match FacetLevelValueU32Codec::bytes_decode(key) {
+                // The key is a facet string group level key.
+                Some(_) => {
+                    FacetStringZeroBoundsValueCodec::<CboRoaringBitmapCodec>::bytes_decode(val)
+                        .ok_or(SerializationError::Decoding { db_name })?
+                        .1
+                        .is_empty()
+                }
+                // The key corresponds to a level zero facet string.
+                None => FacetStringLevelZeroValueCodec::bytes_decode(val)
+                    .ok_or(SerializationError::Decoding { db_name })?
+                    .1
+                    .is_empty(),
+            };
+
+            if is_empty || is_unused(key) {
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.del_current()? };
+                removed_entries += 1;
+            }
+        }
+        drop(iter);
+
+        // The facet values of the documents are only kept for the faceted fields.
+        for db in [
+            self.index.field_id_docid_facet_f64s.remap_types::<ByteSlice, DecodeIgnore>(),
+            self.index.field_id_docid_facet_strings.remap_types::<ByteSlice, DecodeIgnore>(),
+        ] {
+            let mut iter = db.iter_mut(self.wtxn)?;
+            while let Some(result) = iter.next() {
+                let (key, ()) = result?;
+                if is_unused(key) {
+                    // safety: we don't keep references from inside the LMDB database.
+                    unsafe { iter.del_current()? };
+                    removed_entries += 1;
+                }
+            }
+        }
+
+        Facets::new(self.wtxn, self.index).execute()?;
+
+        Ok(FacetsCompactionResult { removed_entries })
+    }
+}
+
+/// Returns the field id that prefixes the keys of all the facet databases.
+fn field_id_from_key(key: &[u8]) -> Option<FieldId> {
+    let bytes = key.get(..std::mem::size_of::<FieldId>())?;
+    Some(FieldId::from_be_bytes(bytes.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use maplit::hashset;
+    use roaring::RoaringBitmap;
+
+    use super::*;
+    use crate::index::tests::TempIndex;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+    use crate::Filter;
+
+    #[test]
+    fn drop_orphaned_facet_entries() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("genre") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 1, "genre": "fantasy" },
+            { "id": 2, "genre": "horror" },
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // We simulate the leftovers of deletions: an emptied value and a field no longer faceted.
+        let genre_id = index.fields_ids_map(&wtxn).unwrap().id("genre").unwrap();
+        index
+            .facet_id_string_docids
+            .put(&mut wtxn, &(genre_id, "romance"), &("Romance", RoaringBitmap::new()))
+            .unwrap();
+        let docids: RoaringBitmap = (0..1).collect();
+        index
+            .facet_id_string_docids
+            .put(&mut wtxn, &(42, "unknown"), &("unknown", docids))
+            .unwrap();
+
+        let result = CompactFacets::new(&mut wtxn, &index).execute().unwrap();
+        assert_eq!(result.removed_entries, 2);
+        assert!(index.facet_id_string_docids.get(&wtxn, &(genre_id, "romance")).unwrap().is_none());
+
+        let filter = Filter::from_str("genre = horror").unwrap().unwrap();
+        let result = index.search(&wtxn).filter(filter).execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1]);
+
+        let result = CompactFacets::new(&mut wtxn, &index).execute().unwrap();
+        assert_eq!(result.removed_entries, 0);
+        wtxn.commit().unwrap();
+    }
+}
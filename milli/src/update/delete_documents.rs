@@ -2,7 +2,7 @@ use std::collections::btree_map::Entry;
 use std::collections::HashMap;
 
 use fst::IntoStreamer;
-use heed::types::{ByteSlice, Str};
+use heed::types::{ByteSlice, DecodeIgnore, Str};
 use heed::{BytesDecode, BytesEncode, Database};
 use roaring::RoaringBitmap;
 use serde::{Deserialize, Serialize};
@@ -17,8 +17,8 @@ use crate::heed_codec::facet::{
 use crate::heed_codec::CboRoaringBitmapCodec;
 use crate::index::{db_name, main_key};
 use crate::{
-    DocumentId, ExternalDocumentsIds, FieldId, Index, Result, RoaringBitmapCodec, SmallString32,
-    BEU32,
+    DocumentId, ExternalDocumentsIds, FieldId, Filter, Index, Result, RoaringBitmapCodec,
+    SmallString32, BEU32,
 };
 
 pub struct DeleteDocuments<'t, 'u, 'i> {
@@ -26,6 +26,7 @@ pub struct DeleteDocuments<'t, 'u, 'i> {
     index: &'i Index,
     external_documents_ids: ExternalDocumentsIds<'static>,
     documents_ids: RoaringBitmap,
+    soft_deletion: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -46,9 +47,19 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
             index,
             external_documents_ids,
             documents_ids: RoaringBitmap::new(),
+            soft_deletion: false,
         })
     }
 
+    /// Only removes the documents from the documents ids and the external ids and marks them
+    /// as soft deleted, without walking the posting lists. The soft deleted documents are
+    /// ignored by the searches and purged by the next hard deletion, see [`ClearSoftDeleted`].
+    ///
+    /// [`ClearSoftDeleted`]: crate::update::ClearSoftDeleted
+    pub fn soft_deletion(&mut self, value: bool) {
+        self.soft_deletion = value;
+    }
+
     pub fn delete_document(&mut self, docid: u32) {
         self.documents_ids.insert(docid);
     }
@@ -63,7 +74,15 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
         Some(docid)
     }
 
-    pub fn execute(self) -> Result<DocumentDeletionResult> {
+    /// Deletes the documents matching the filter, as they are in the index
+    /// when this method is called. Returns the number of matching documents.
+    pub fn delete_documents_by_filter(&mut self, filter: &Filter) -> Result<u64> {
+        let docids = filter.evaluate(self.wtxn, self.index)?;
+        self.delete_documents(&docids);
+        Ok(docids.len())
+    }
+
+    pub fn execute(mut self) -> Result<DocumentDeletionResult> {
         self.index.set_updated_at(self.wtxn, &OffsetDateTime::now_utc())?;
         // We retrieve the current documents ids that are in the database.
         let mut documents_ids = self.index.documents_ids(self.wtxn)?;
@@ -77,6 +96,9 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
             });
         }
 
+        // We only delete the documents that are in the database, not the soft deleted ones.
+        self.documents_ids &= &documents_ids;
+
         // We remove the documents ids that we want to delete
         // from the documents in the database and write them back.
         documents_ids -= &self.documents_ids;
@@ -117,6 +139,7 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
             docid_word_positions,
             word_pair_proximity_docids,
             field_id_word_count_docids,
+            docid_field_id_word_count,
             word_prefix_pair_proximity_docids,
             word_position_docids,
             word_prefix_position_docids,
@@ -125,21 +148,17 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
             field_id_docid_facet_f64s,
             field_id_docid_facet_strings,
             documents,
+            vectors,
+            dfa_cache: _,
         } = self.index;
 
         // Number of fields for each document that has been deleted.
         let mut fields_ids_distribution_diff = HashMap::new();
 
-        // Retrieve the words and the external documents ids contained in the documents.
-        let mut words = Vec::new();
+        // Retrieve the external documents ids and the fields contained in the documents.
         let mut external_ids = Vec::new();
         for docid in &self.documents_ids {
-            // We create an iterator to be able to get the content and delete the document
-            // content itself. It's faster to acquire a cursor to get and delete,
-            // as we avoid traversing the LMDB B-Tree two times but only once.
-            let key = BEU32::new(docid);
-            let mut iter = documents.range_mut(self.wtxn, &(key..=key))?;
-            if let Some((_key, obkv)) = iter.next().transpose()? {
+            if let Some(obkv) = documents.get(self.wtxn, &BEU32::new(docid))? {
                 for (field_id, _) in obkv.iter() {
                     *fields_ids_distribution_diff.entry(field_id).or_default() += 1;
                 }
@@ -154,20 +173,6 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
                     };
                     external_ids.push(external_id);
                 }
-                // safety: we don't keep references from inside the LMDB database.
-                unsafe { iter.del_current()? };
-            }
-            drop(iter);
-
-            // We iterate through the words positions of the document id,
-            // retrieve the word and delete the positions.
-            let mut iter = docid_word_positions.prefix_iter_mut(self.wtxn, &(docid, ""))?;
-            while let Some(result) = iter.next() {
-                let ((_docid, word), _positions) = result?;
-                // This boolean will indicate if we must remove this word from the words FST.
-                words.push((SmallString32::from(word), false));
-                // safety: we don't keep references from inside the LMDB database.
-                unsafe { iter.del_current()? };
             }
         }
 
@@ -202,6 +207,62 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
         let new_external_documents_ids = new_external_documents_ids.into_static();
         self.index.put_external_documents_ids(self.wtxn, &new_external_documents_ids)?;
 
+        // The soft deleted documents stay in the other databases until the next hard deletion.
+        let mut soft_deleted_documents_ids = self.index.soft_deleted_documents_ids(self.wtxn)?;
+        if self.soft_deletion {
+            soft_deleted_documents_ids |= &self.documents_ids;
+            self.index.put_soft_deleted_documents_ids(self.wtxn, &soft_deleted_documents_ids)?;
+            return Ok(DocumentDeletionResult {
+                deleted_documents: self.documents_ids.len(),
+                remaining_documents: documents_ids.len(),
+            });
+        }
+
+        // The posting lists are walked anyway, the soft deleted documents are purged too.
+        let deleted_documents = self.documents_ids.len();
+        self.documents_ids |= soft_deleted_documents_ids;
+        self.index.put_soft_deleted_documents_ids(self.wtxn, &RoaringBitmap::new())?;
+
+        // Retrieve the words contained in the documents.
+        let mut words = Vec::new();
+        for docid in &self.documents_ids {
+            // We delete the document content itself.
+            documents.delete(self.wtxn, &BEU32::new(docid))?;
+
+            // We iterate through the words positions of the document id,
+            // retrieve the word and delete the positions.
+            let mut iter = docid_word_positions.prefix_iter_mut(self.wtxn, &(docid, ""))?;
+            while let Some(result) = iter.next() {
+                let ((_docid, word), _positions) = result?;
+                // This boolean will indicate if we must remove this word from the words FST.
+                words.push((SmallString32::from(word), false));
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.del_current()? };
+            }
+            drop(iter);
+
+            // We delete the word counts of the fields of the document id.
+            let mut iter = docid_field_id_word_count
+                .remap_key_type::<ByteSlice>()
+                .prefix_iter_mut(self.wtxn, &docid.to_be_bytes())?;
+            while let Some(result) = iter.next() {
+                result?;
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.del_current()? };
+            }
+            drop(iter);
+
+            // We delete the vectors of the document id.
+            let mut iter = vectors
+                .remap_types::<ByteSlice, DecodeIgnore>()
+                .prefix_iter_mut(self.wtxn, &docid.to_be_bytes())?;
+            while let Some(result) = iter.next() {
+                result?;
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.del_current()? };
+            }
+        }
+
         // Maybe we can improve the get performance of the words
         // if we sort the words first, keeping the LMDB pages in cache.
         words.sort_unstable();
@@ -255,6 +316,17 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
         // We write the new words FST into the main database.
         self.index.put_words_fst(self.wtxn, &new_words_fst)?;
 
+        // The prefixes of the deleted words will be counted again by the next
+        // incremental update of the words prefixes FST.
+        if !words_to_delete.is_empty() {
+            let deleted_words_fst = self.index.deleted_words_fst(self.wtxn)?;
+            let union = deleted_words_fst.op().add(&words_to_delete).union();
+            let mut deleted_words_fst_builder = fst::SetBuilder::memory();
+            deleted_words_fst_builder.extend_stream(union.into_stream())?;
+            let deleted_words_fst = deleted_words_fst_builder.into_set();
+            self.index.put_deleted_words_fst(self.wtxn, &deleted_words_fst)?;
+        }
+
         let prefixes_to_delete =
             remove_from_word_prefix_docids(self.wtxn, word_prefix_docids, &self.documents_ids)?;
 
@@ -439,12 +511,18 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
                 &self.documents_ids,
                 |(_fid, docid, _value)| docid,
             )?;
+
+            // Remove docids from the boolean faceted documents ids
+            for value in [false, true] {
+                let mut docids =
+                    self.index.boolean_faceted_documents_ids(self.wtxn, field_id, value)?;
+                docids -= &self.documents_ids;
+                self.index
+                    .put_boolean_faceted_documents_ids(self.wtxn, field_id, value, &docids)?;
+            }
         }
 
-        Ok(DocumentDeletionResult {
-            deleted_documents: self.documents_ids.len(),
-            remaining_documents: documents_ids.len(),
-        })
+        Ok(DocumentDeletionResult { deleted_documents, remaining_documents: documents_ids.len() })
     }
 }
 
@@ -752,6 +830,58 @@ mod tests {
         wtxn.commit().unwrap();
     }
 
+    #[test]
+    fn delete_documents_by_filter() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let config = IndexerConfig::default();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("genre"), S("year") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 1, "title": "Carol", "genre": "romance", "year": 2015 },
+            { "id": 2, "title": "Wonder Woman", "genre": "action", "year": 2017 },
+            { "id": 3, "title": "Pride and Prejudice", "genre": "romance", "year": 1813 },
+            { "id": 4, "title": "Dune", "genre": "science fiction", "year": 1965 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        let filter = Filter::from_str("genre = romance AND year > 2000").unwrap().unwrap();
+        assert_eq!(builder.delete_documents_by_filter(&filter).unwrap(), 1);
+        let filter = Filter::from_str("year < 1900").unwrap().unwrap();
+        assert_eq!(builder.delete_documents_by_filter(&filter).unwrap(), 1);
+        builder.delete_external_id("4");
+        let result = builder.execute().unwrap();
+        assert_eq!(result.deleted_documents, 3);
+        assert_eq!(result.remaining_documents, 1);
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let external_ids = index.external_documents_ids(&rtxn).unwrap();
+        assert_eq!(external_ids.get("2"), Some(1));
+        assert_eq!(external_ids.get("1"), None);
+        assert_eq!(external_ids.get("3"), None);
+        let filter = Filter::from_str("genre = romance").unwrap().unwrap();
+        assert!(filter.evaluate(&rtxn, &index).unwrap().is_empty());
+        drop(rtxn);
+
+        // A filter on an attribute that is not filterable is rejected.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        let filter = Filter::from_str("title = Carol").unwrap().unwrap();
+        assert!(builder.delete_documents_by_filter(&filter).is_err());
+    }
+
     #[test]
     fn delete_documents_with_geo_points() {
         let path = tempfile::tempdir().unwrap();
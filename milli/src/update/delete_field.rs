@@ -0,0 +1,198 @@
+use std::io::Cursor;
+
+use serde_json::{Map, Value};
+
+use super::{IndexDocuments, IndexDocumentsConfig, IndexDocumentsMethod, IndexerConfig};
+use crate::documents::{DocumentBatchBuilder, DocumentBatchReader};
+use crate::error::{InternalError, UserError};
+use crate::{obkv_to_json, Index, Result};
+
+/// Removes a field from all the documents of the index, e.g. to stop storing a personal data,
+/// without sending the documents again. The field can be nested, e.g. `contact.email`.
+///
+/// The stored documents are rewritten and only the modified documents are indexed again,
+/// their words and facet values are therefore removed from all the databases.
+/// The settings that reference the field are kept untouched.
+pub struct DeleteField<'t, 'u, 'i, 'a> {
+    wtxn: &'t mut heed::RwTxn<'i, 'u>,
+    index: &'i Index,
+    indexer_config: &'a IndexerConfig,
+    field: String,
+}
+
+impl<'t, 'u, 'i, 'a> DeleteField<'t, 'u, 'i, 'a> {
+    pub fn new(
+        wtxn: &'t mut heed::RwTxn<'i, 'u>,
+        index: &'i Index,
+        indexer_config: &'a IndexerConfig,
+        field: impl Into<String>,
+    ) -> DeleteField<'t, 'u, 'i, 'a> {
+        DeleteField { wtxn, index, indexer_config, field: field.into() }
+    }
+
+    /// Returns the number of documents that were modified.
+    pub fn execute(self) -> Result<u64> {
+        if let Some(primary_key) = self.index.primary_key(self.wtxn)? {
+            if is_same_or_parent(&self.field, primary_key) {
+                return Err(UserError::PrimaryKeyCannotBeDeleted(primary_key.to_string()).into());
+            }
+        }
+
+        // Only the documents that contain the field, or one of its parents, are converted.
+        let fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+        let candidates: Vec<_> = fields_ids_map
+            .iter()
+            .filter(|(_, name)| is_same_or_parent(name, &self.field))
+            .map(|(id, _)| id)
+            .collect();
+        if candidates.is_empty() {
+            return Ok(0);
+        }
+
+        let all_fields: Vec<_> = fields_ids_map.ids().collect();
+        let mut writer = Cursor::new(Vec::new());
+        let mut builder = DocumentBatchBuilder::new(&mut writer)?;
+        let mut modified_documents = 0;
+        for result in self.index.all_documents(self.wtxn)? {
+            let (_docid, obkv) = result?;
+            if candidates.iter().all(|id| obkv.get(*id).is_none()) {
+                continue;
+            }
+
+            let mut document = obkv_to_json(&all_fields, &fields_ids_map, obkv)?;
+            if remove_in_object(&mut document, &self.field) {
+                let document = serde_json::to_vec(&document).map_err(InternalError::SerdeJson)?;
+                builder.extend_from_json(document.as_slice())?;
+                modified_documents += 1;
+            }
+        }
+        builder.finish()?;
+
+        if modified_documents == 0 {
+            return Ok(0);
+        }
+
+        // The documents keep their primary key, they replace their previous version.
+        writer.set_position(0);
+        let reader = DocumentBatchReader::from_reader(writer)?;
+        let config = IndexDocumentsConfig {
+            update_method: IndexDocumentsMethod::ReplaceDocuments,
+            ..Default::default()
+        };
+        let mut builder =
+            IndexDocuments::new(self.wtxn, self.index, self.indexer_config, config, |_| ())?;
+        builder.keep_hashed_values();
+        builder.add_documents(reader)?;
+        builder.execute()?;
+
+        Ok(modified_documents)
+    }
+}
+
+/// Returns `true` if `parent` is the dotted `path` itself or one of its parents.
+pub(crate) fn is_same_or_parent(parent: &str, path: &str) -> bool {
+    match path.strip_prefix(parent) {
+        Some(rest) => rest.is_empty() || rest.starts_with('.'),
+        None => false,
+    }
+}
+
+/// Removes the value found under the dotted `path`.
+/// Returns `true` if a value was removed.
+fn remove_in_object(object: &mut Map<String, Value>, path: &str) -> bool {
+    let mut removed = object.remove(path).is_some();
+    for (key, value) in object.iter_mut() {
+        if let Some(rest) = path.strip_prefix(key.as_str()).and_then(|r| r.strip_prefix('.')) {
+            removed |= remove_in_value(value, rest);
+        }
+    }
+    removed
+}
+
+/// Removes the values found under the dotted `path`, in the objects of arrays too.
+/// Returns `true` if a value was removed.
+fn remove_in_value(value: &mut Value, path: &str) -> bool {
+    match value {
+        Value::Array(values) => {
+            values.iter_mut().fold(false, |removed, value| remove_in_value(value, path) | removed)
+        }
+        Value::Object(object) => remove_in_object(object, path),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use maplit::hashset;
+
+    use super::*;
+    use crate::error::Error;
+    use crate::index::tests::TempIndex;
+    use crate::update::Settings;
+    use crate::Filter;
+
+    #[test]
+    fn delete_field() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_primary_key(S("id"));
+        builder.set_filterable_fields(hashset! { S("email"), S("contact.phone") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "name": "kevin", "email": "kevin@example.com" },
+            { "id": 1, "name": "bob", "contact": [{ "phone": "0123" }, { "phone": "4567" }] },
+            { "id": 2, "name": "alice", "email": "alice@example.com", "contact": { "fax": "89" } },
+            { "id": 3, "name": "john" }
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let builder = DeleteField::new(&mut wtxn, &index, &config, "email");
+        assert_eq!(builder.execute().unwrap(), 2);
+        let builder = DeleteField::new(&mut wtxn, &index, &config, "contact.phone");
+        assert_eq!(builder.execute().unwrap(), 1);
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("example").execute().unwrap();
+        assert!(result.documents_ids.is_empty());
+        let filter = Filter::from_str("email = 'kevin@example.com'").unwrap().unwrap();
+        assert!(filter.evaluate(&rtxn, &index).unwrap().is_empty());
+        let filter = Filter::from_str("contact.phone = 0123").unwrap().unwrap();
+        assert!(filter.evaluate(&rtxn, &index).unwrap().is_empty());
+
+        let field_distribution = index.field_distribution(&rtxn).unwrap();
+        assert!(!field_distribution.contains_key("email"));
+        assert!(!field_distribution.contains_key("contact.phone"));
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 4);
+
+        // the other fields are left untouched
+        let result = index.search(&rtxn).query("alice").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![2]);
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let all_fields: Vec<_> = fields_ids_map.ids().collect();
+        let (_, obkv) = index.documents(&rtxn, Some(2)).unwrap().pop().unwrap();
+        let document = obkv_to_json(&all_fields, &fields_ids_map, obkv).unwrap();
+        assert_eq!(document.get("email"), None);
+        assert_eq!(document["contact"], serde_json::json!({ "fax": "89" }));
+        drop(rtxn);
+
+        // the primary key cannot be deleted
+        let mut wtxn = index.write_txn().unwrap();
+        let error = DeleteField::new(&mut wtxn, &index, &config, "id").execute().unwrap_err();
+        assert!(matches!(error, Error::UserError(UserError::PrimaryKeyCannotBeDeleted(_))));
+    }
+}
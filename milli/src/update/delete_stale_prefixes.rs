@@ -0,0 +1,126 @@
+use heed::types::DecodeIgnore;
+use time::OffsetDateTime;
+
+use crate::{Index, Result, StalePrefixes};
+
+/// Deletes the entries of the prefix databases whose prefix is not part of the words prefixes
+/// FST anymore, the prefixes that can linger when an update of the prefixes is interrupted.
+pub struct DeleteStalePrefixes<'t, 'u, 'i> {
+    wtxn: &'t mut heed::RwTxn<'i, 'u>,
+    index: &'i Index,
+}
+
+impl<'t, 'u, 'i> DeleteStalePrefixes<'t, 'u, 'i> {
+    pub fn new(
+        wtxn: &'t mut heed::RwTxn<'i, 'u>,
+        index: &'i Index,
+    ) -> DeleteStalePrefixes<'t, 'u, 'i> {
+        DeleteStalePrefixes { wtxn, index }
+    }
+
+    /// Returns the stale prefixes that were deleted.
+    pub fn execute(self) -> Result<StalePrefixes> {
+        let stale = self.index.stale_prefixes(self.wtxn)?;
+        if stale.is_empty() {
+            return Ok(stale);
+        }
+
+        self.index.set_updated_at(self.wtxn, &OffsetDateTime::now_utc())?;
+
+        let databases = [
+            (self.index.word_prefix_docids, &stale.word_prefix_docids),
+            (self.index.exact_word_prefix_docids, &stale.exact_word_prefix_docids),
+        ];
+        for (db, stale_prefixes) in databases {
+            for prefix in stale_prefixes {
+                db.delete(self.wtxn, prefix)?;
+            }
+        }
+
+        let db = self.index.word_prefix_pair_proximity_docids.remap_data_type::<DecodeIgnore>();
+        let mut iter = db.iter_mut(self.wtxn)?;
+        while let Some(result) = iter.next() {
+            let ((_word, prefix, _proximity), ()) = result?;
+            if stale.word_prefix_pair_proximity_docids.contains(prefix) {
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.del_current()? };
+            }
+        }
+        drop(iter);
+
+        for prefix in &stale.word_prefix_position_docids {
+            let db = self.index.word_prefix_position_docids.remap_data_type::<DecodeIgnore>();
+            let left = (prefix.as_str(), u32::MIN);
+            let right = (prefix.as_str(), u32::MAX);
+            db.delete_range(self.wtxn, &(left..=right))?;
+        }
+
+        Ok(stale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use roaring::RoaringBitmap;
+
+    use super::*;
+    use crate::index::tests::TempIndex;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig};
+
+    #[test]
+    fn delete_stale_prefixes() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        // The 60 words share enough prefixes to reach the prefix threshold.
+        let content: Vec<_> = (0..60).map(|i| format!("hello{}", i)).collect();
+        let content = content.join(" ");
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(documents!([{ "id": 1, "content": content }])).unwrap();
+        builder.execute().unwrap();
+        assert!(index.stale_prefixes(&wtxn).unwrap().is_empty());
+
+        // We simulate the leftovers of an interrupted prefixes update.
+        let docids: RoaringBitmap = (0..1).collect();
+        index.word_prefix_docids.put(&mut wtxn, "wor", &docids).unwrap();
+        index
+            .word_prefix_pair_proximity_docids
+            .put(&mut wtxn, &("hello1", "wor", 1), &docids)
+            .unwrap();
+        index.word_prefix_position_docids.put(&mut wtxn, &("wor", 3), &docids).unwrap();
+        index.word_prefix_position_docids.put(&mut wtxn, &("wor", 8), &docids).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let stale = index.stale_prefixes(&rtxn).unwrap();
+        let wor = vec!["wor".to_string()].into_iter().collect();
+        assert_eq!(stale.word_prefix_docids, wor);
+        assert!(stale.exact_word_prefix_docids.is_empty());
+        assert_eq!(stale.word_prefix_pair_proximity_docids, wor);
+        assert_eq!(stale.word_prefix_position_docids, wor);
+        let pairs = index.word_prefix_pair_proximity_docids.len(&rtxn).unwrap();
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let deleted = DeleteStalePrefixes::new(&mut wtxn, &index).execute().unwrap();
+        assert_eq!(deleted, stale);
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.stale_prefixes(&rtxn).unwrap().is_empty());
+        assert!(index.word_prefix_docids.get(&rtxn, "wor").unwrap().is_none());
+        assert!(index.word_prefix_position_docids.get(&rtxn, &("wor", 8)).unwrap().is_none());
+        // the prefixes of the FST are kept
+        assert!(index.word_prefix_docids.get(&rtxn, "hell").unwrap().is_some());
+        assert_eq!(index.word_prefix_pair_proximity_docids.len(&rtxn).unwrap(), pairs - 1);
+    }
+}
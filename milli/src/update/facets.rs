@@ -3,8 +3,8 @@ use std::num::{NonZeroU8, NonZeroUsize};
 use std::{cmp, mem};
 
 use grenad::{CompressionType, Reader, Writer};
-use heed::types::{ByteSlice, DecodeIgnore};
-use heed::{BytesEncode, Error};
+use heed::types::{ByteSlice, DecodeIgnore, Str};
+use heed::{BytesDecode, BytesEncode, Error};
 use log::debug;
 use roaring::RoaringBitmap;
 use time::OffsetDateTime;
@@ -12,12 +12,18 @@ use time::OffsetDateTime;
 use crate::error::InternalError;
 use crate::heed_codec::facet::{
     FacetLevelValueF64Codec, FacetLevelValueU32Codec, FacetStringLevelZeroCodec,
-    FacetStringLevelZeroValueCodec, FacetStringZeroBoundsValueCodec,
+    FacetStringLevelZeroValueCodec, FacetStringZeroBoundsValueCodec, FieldDocIdFacetF64Codec,
+    FieldDocIdFacetStringCodec,
 };
 use crate::heed_codec::CboRoaringBitmapCodec;
 use crate::update::index_documents::{create_writer, write_into_lmdb_database, writer_into_reader};
 use crate::{FieldId, Index, Result};
 
+/// The default number of values of a level grouped in a single value of the next level.
+pub(crate) const DEFAULT_LEVEL_GROUP_SIZE: usize = 4;
+/// The default minimum number of values of a level to create the next level.
+pub(crate) const DEFAULT_MIN_LEVEL_SIZE: usize = 5;
+
 pub struct Facets<'t, 'u, 'i> {
     wtxn: &'t mut heed::RwTxn<'i, 'u>,
     index: &'i Index,
@@ -34,8 +40,8 @@ impl<'t, 'u, 'i> Facets<'t, 'u, 'i> {
             index,
             chunk_compression_type: CompressionType::None,
             chunk_compression_level: None,
-            level_group_size: NonZeroUsize::new(4).unwrap(),
-            min_level_size: NonZeroUsize::new(5).unwrap(),
+            level_group_size: NonZeroUsize::new(DEFAULT_LEVEL_GROUP_SIZE).unwrap(),
+            min_level_size: NonZeroUsize::new(DEFAULT_MIN_LEVEL_SIZE).unwrap(),
         }
     }
 
@@ -130,6 +136,216 @@ impl<'t, 'u, 'i> Facets<'t, 'u, 'i> {
 
         Ok(())
     }
+
+    /// Adds the facet values of the given documents, already written in the level 0, to the
+    /// facet levels in place instead of recomputing the levels of the whole index.
+    ///
+    /// The number groups are simply extended to the new values but a string must be within
+    /// the bounds of a group of the level 1, the string groups are keyed by the positions of
+    /// the values in the level 0. Nothing is written and `false` is returned when a string
+    /// is outside of all the groups, the levels must then be recomputed with [`Facets::execute`].
+    #[logging_timer::time("Facets::{}")]
+    pub fn execute_in_place(self, documents_ids: &RoaringBitmap) -> Result<bool> {
+        let faceted_fields = self.index.faceted_fields_ids(self.wtxn)?;
+
+        // The fields that grew enough to have levels get them from scratch.
+        let min_level_zero_size = self.level_group_size.get() * self.min_level_size.get();
+        for &field_id in &faceted_fields {
+            for db in [
+                self.index.facet_id_f64_docids.remap_types::<ByteSlice, DecodeIgnore>(),
+                self.index.facet_id_string_docids.remap_types::<ByteSlice, DecodeIgnore>(),
+            ] {
+                if misses_levels(self.wtxn, db, field_id, min_level_zero_size)? {
+                    return Ok(false);
+                }
+            }
+        }
+
+        let f64s = self.index.field_id_docid_facet_f64s.remap_types::<ByteSlice, DecodeIgnore>();
+        let strings = self.index.field_id_docid_facet_strings.remap_types::<ByteSlice, Str>();
+
+        // We first gather the facet values of the documents and the groups of the
+        // level 1 of the strings, before writing anything.
+        let mut numbers = Vec::new();
+        let mut string_groups = Vec::new();
+        let mut prefix = Vec::new();
+        for field_id in faceted_fields {
+            for docid in documents_ids {
+                prefix.clear();
+                prefix.extend_from_slice(&field_id.to_be_bytes());
+                prefix.extend_from_slice(&docid.to_be_bytes());
+
+                for result in f64s.prefix_iter(self.wtxn, &prefix)? {
+                    let (key, ()) = result?;
+                    let (_, _, value) =
+                        FieldDocIdFacetF64Codec::bytes_decode(key).ok_or(Error::Decoding)?;
+                    numbers.push((field_id, value, docid));
+                }
+
+                for result in strings.prefix_iter(self.wtxn, &prefix)? {
+                    let (key, _original) = result?;
+                    let (_, _, value) =
+                        FieldDocIdFacetStringCodec::bytes_decode(key).ok_or(Error::Decoding)?;
+                    match string_level_one_group(self.wtxn, self.index, field_id, value)? {
+                        Some(group) => string_groups.push((field_id, group, docid)),
+                        None => return Ok(false),
+                    }
+                }
+            }
+        }
+
+        self.index.set_updated_at(self.wtxn, &OffsetDateTime::now_utc())?;
+
+        for (field_id, value, docid) in numbers {
+            add_number_in_levels(
+                self.wtxn,
+                self.index.facet_id_f64_docids,
+                field_id,
+                value,
+                docid,
+            )?;
+            let mut docids = self.index.number_faceted_documents_ids(self.wtxn, field_id)?;
+            if docids.insert(docid) {
+                self.index.put_number_faceted_documents_ids(self.wtxn, field_id, &docids)?;
+            }
+        }
+
+        for (field_id, group, docid) in string_groups {
+            if let Some(position) = group {
+                let db = self.index.facet_id_string_docids;
+                add_string_in_levels(self.wtxn, db, field_id, position, docid)?;
+            }
+            let mut docids = self.index.string_faceted_documents_ids(self.wtxn, field_id)?;
+            if docids.insert(docid) {
+                self.index.put_string_faceted_documents_ids(self.wtxn, field_id, &docids)?;
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Returns `true` if the field has no levels while its level 0 is large enough to have them.
+fn misses_levels(
+    rtxn: &heed::RoTxn,
+    db: heed::Database<ByteSlice, DecodeIgnore>,
+    field_id: FieldId,
+    min_level_zero_size: usize,
+) -> heed::Result<bool> {
+    let [left, right] = field_id.to_be_bytes();
+    let has_levels = db.prefix_iter(rtxn, &[left, right, 1])?.next().is_some();
+    let level_zero_size =
+        db.prefix_iter(rtxn, &[left, right, 0])?.take(min_level_zero_size).count();
+    Ok(!has_levels && level_zero_size >= min_level_zero_size)
+}
+
+/// Returns the left position of the group of the level 1 of which the string bounds contain
+/// the value, `Some(None)` when the field has no levels and `None` when no group contains it.
+fn string_level_one_group(
+    rtxn: &heed::RoTxn,
+    index: &Index,
+    field_id: FieldId,
+    value: &str,
+) -> Result<Option<Option<u32>>> {
+    let db = index
+        .facet_id_string_docids
+        .remap_types::<FacetLevelValueU32Codec, FacetStringZeroBoundsValueCodec<DecodeIgnore>>();
+    let level = NonZeroU8::new(1).unwrap();
+    let range = (field_id, level, u32::MIN, u32::MIN)..=(field_id, level, u32::MAX, u32::MAX);
+
+    let mut has_levels = false;
+    for result in db.range(rtxn, &range)? {
+        let ((_, _, left, _), (bounds, ())) = result?;
+        has_levels = true;
+        match bounds {
+            Some((left_value, right_value)) if left_value <= value && value <= right_value => {
+                return Ok(Some(Some(left)))
+            }
+            Some((left_value, _)) if left_value > value => break,
+            _otherwise => (),
+        }
+    }
+
+    Ok(if has_levels { None } else { Some(None) })
+}
+
+/// Adds the document to the group of the value at every level, from the highest one. The
+/// group of a level is searched within the group of the next level and is extended to the value
+/// when it doesn't contain it: the group of the closest lower values or the first group.
+fn add_number_in_levels(
+    wtxn: &mut heed::RwTxn,
+    db: heed::Database<FacetLevelValueF64Codec, CboRoaringBitmapCodec>,
+    field_id: FieldId,
+    value: f64,
+    docid: u32,
+) -> Result<()> {
+    let field_range = (field_id, 0, f64::MIN, f64::MIN)..=(field_id, u8::MAX, f64::MAX, f64::MAX);
+    let highest_level = db
+        .remap_data_type::<DecodeIgnore>()
+        .rev_range(wtxn, &field_range)?
+        .next()
+        .transpose()?
+        .map_or(0, |((_, level, _, _), ())| level);
+
+    let (mut lower, mut upper) = (f64::MIN, f64::MAX);
+    for level in (1..=highest_level).rev() {
+        let first = (field_id, level, lower, f64::MIN);
+        let group = match db.rev_range(wtxn, &(first..=(field_id, level, value, f64::MAX)))?.next()
+        {
+            Some(result) => Some(result?),
+            None => {
+                db.range(wtxn, &(first..=(field_id, level, upper, f64::MAX)))?.next().transpose()?
+            }
+        };
+
+        let ((_, _, left, right), mut docids) = match group {
+            Some(group) => group,
+            None => break,
+        };
+
+        docids.insert(docid);
+        lower = left.min(value);
+        upper = right.max(value);
+        if (lower, upper) != (left, right) {
+            db.delete(wtxn, &(field_id, level, left, right))?;
+        }
+        db.put(wtxn, &(field_id, level, lower, upper), &docids)?;
+    }
+
+    Ok(())
+}
+
+type BoundsCodec = FacetStringZeroBoundsValueCodec<CboRoaringBitmapCodec>;
+
+/// Adds the document to the group containing the position at every level,
+/// the groups of a level always cover all the positions of the level 0.
+fn add_string_in_levels(
+    wtxn: &mut heed::RwTxn,
+    db: heed::Database<FacetStringLevelZeroCodec, FacetStringLevelZeroValueCodec>,
+    field_id: FieldId,
+    position: u32,
+    docid: u32,
+) -> Result<()> {
+    let db = db.remap_types::<FacetLevelValueU32Codec, BoundsCodec>();
+    for level in (1..=u8::MAX).map(|level| NonZeroU8::new(level).unwrap()) {
+        let range = (field_id, level, u32::MIN, u32::MIN)..=(field_id, level, position, u32::MAX);
+        let ((_, _, left, right), (bounds, mut docids)) =
+            match db.rev_range(wtxn, &range)?.next().transpose()? {
+                Some(group) => group,
+                None => break,
+            };
+
+        if right < position {
+            break;
+        }
+
+        docids.insert(docid);
+        let bounds = bounds.map(|(left, right)| (left.to_string(), right.to_string()));
+        let bounds = bounds.as_ref().map(|(left, right)| (left.as_str(), right.as_str()));
+        db.put(wtxn, &(field_id, level, left, right), &(bounds, docids))?;
+    }
+
+    Ok(())
 }
 
 fn clear_field_number_levels<'t>(
@@ -342,3 +558,275 @@ fn write_string_entry(
     writer.insert(&key, &data)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    use big_s::S;
+    use heed::types::ByteSlice;
+    use heed::BytesDecode;
+    use maplit::hashset;
+    use roaring::RoaringBitmap;
+
+    use crate::documents::{DocumentBatchBuilder, DocumentBatchReader};
+    use crate::heed_codec::facet::{FacetLevelValueU32Codec, FacetStringZeroBoundsValueCodec};
+    use crate::heed_codec::CboRoaringBitmapCodec;
+    use crate::index::tests::TempIndex;
+    use crate::search::{FacetNumberIter, FacetStringIter};
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+
+    type BoundsCodec = FacetStringZeroBoundsValueCodec<CboRoaringBitmapCodec>;
+
+    #[test]
+    fn facet_string_levels() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("name") });
+        builder.execute(|_| ()).unwrap();
+
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        let mut batch = DocumentBatchBuilder::new(&mut cursor).unwrap();
+        for i in 0..100u32 {
+            let document = serde_json::json!({ "id": i, "name": format!("name-{:03}", i) });
+            batch.extend_from_json(serde_json::to_vec(&document).unwrap().as_slice()).unwrap();
+        }
+        batch.finish().unwrap();
+        cursor.set_position(0);
+        let content = DocumentBatchReader::from_reader(cursor).unwrap();
+
+        let indexing_config = IndexDocumentsConfig {
+            facet_level_group_size: NonZeroUsize::new(4),
+            facet_min_level_size: NonZeroUsize::new(2),
+            ..Default::default()
+        };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let field_id = index.fields_ids_map(&rtxn).unwrap().id("name").unwrap();
+
+        // With groups of 4 values and at least 2 groups by level, we get two group levels.
+        // The groups of the level 1 store their string bounds, the higher levels don't.
+        let mut level_sizes = vec![0; 3];
+        let db = index.facet_id_string_docids.remap_types::<ByteSlice, ByteSlice>();
+        for result in db.prefix_iter(&rtxn, &field_id.to_be_bytes()).unwrap() {
+            let (key, data) = result.unwrap();
+            if key[2] == 0 {
+                level_sizes[0] += 1;
+                continue;
+            }
+
+            let (_, level, left, right) = FacetLevelValueU32Codec::bytes_decode(key).unwrap();
+            let (bounds, _docids) = BoundsCodec::bytes_decode(data).unwrap();
+            level_sizes[level.get() as usize] += 1;
+            assert!(left <= right);
+            assert_eq!(bounds.is_some(), level.get() == 1);
+        }
+        assert_eq!(level_sizes, vec![100, 25, 7]);
+
+        // The values are iterated in order through all the levels.
+        let candidates: RoaringBitmap = (0..100).collect();
+        let expected: Vec<_> = (0..100).map(|i| format!("name-{:03}", i)).collect();
+        let iter =
+            FacetStringIter::new_reducing(&rtxn, &index, field_id, candidates.clone()).unwrap();
+        let values: Vec<_> = iter.map(|r| r.unwrap().1.to_string()).collect();
+        assert_eq!(values, expected);
+
+        let iter =
+            FacetStringIter::new_reverse_reducing(&rtxn, &index, field_id, candidates).unwrap();
+        let values: Vec<_> = iter.map(|r| r.unwrap().1.to_string()).collect();
+        assert_eq!(values, expected.into_iter().rev().collect::<Vec<_>>());
+
+        // Only the values of the candidates are returned.
+        let candidates: RoaringBitmap = (10..20).collect();
+        let iter = FacetStringIter::new_non_reducing(&rtxn, &index, field_id, candidates).unwrap();
+        let values: Vec<_> = iter.map(|r| r.unwrap().2.len()).collect();
+        assert_eq!(values, vec![1; 10]);
+    }
+
+    #[test]
+    fn facet_iter_bounds() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("name"), S("value") });
+        builder.execute(|_| ()).unwrap();
+
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        let mut batch = DocumentBatchBuilder::new(&mut cursor).unwrap();
+        for i in 0..100u32 {
+            let document = serde_json::json!({
+                "id": i,
+                "name": format!("Name-{:03}", i),
+                "value": [i, 100 + i],
+            });
+            batch.extend_from_json(serde_json::to_vec(&document).unwrap().as_slice()).unwrap();
+        }
+        batch.finish().unwrap();
+        cursor.set_position(0);
+        let content = DocumentBatchReader::from_reader(cursor).unwrap();
+
+        let indexing_config = IndexDocumentsConfig {
+            facet_level_group_size: NonZeroUsize::new(4),
+            facet_min_level_size: NonZeroUsize::new(2),
+            ..Default::default()
+        };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let candidates: RoaringBitmap = (0..100).collect();
+
+        // The documents of the groups partially within the bounds are returned
+        // for their first value within the bounds.
+        let field_id = fields_ids_map.id("value").unwrap();
+        let iter = FacetNumberIter::new_reducing(&rtxn, &index, field_id, candidates.clone())
+            .unwrap()
+            .bounds(Included(50.0), Excluded(150.0));
+        let (values, docids): (Vec<_>, Vec<_>) =
+            iter.map(|r| r.unwrap()).map(|(v, d)| (v as u32, d.max().unwrap())).unzip();
+        assert_eq!(values, (50..150).collect::<Vec<_>>());
+        assert_eq!(docids, (50..100).chain(0..50).collect::<Vec<_>>());
+
+        let iter =
+            FacetNumberIter::new_reverse_reducing(&rtxn, &index, field_id, candidates.clone())
+                .unwrap()
+                .bounds(Excluded(20.0), Unbounded);
+        let values: Vec<_> = iter.map(|r| r.unwrap().0 as u32).collect();
+        assert_eq!(values, (100..200).rev().collect::<Vec<_>>());
+
+        // The string bounds are normalized like the facet values.
+        let field_id = fields_ids_map.id("name").unwrap();
+        let expected: Vec<_> = (10..20).map(|i| format!("Name-{:03}", i)).collect();
+        let iter = FacetStringIter::new_reducing(&rtxn, &index, field_id, candidates.clone())
+            .unwrap()
+            .bounds(Included("NAME-010"), Excluded("name-020"));
+        let values: Vec<_> = iter.map(|r| r.unwrap().1.to_string()).collect();
+        assert_eq!(values, expected);
+
+        let iter = FacetStringIter::new_reverse_reducing(&rtxn, &index, field_id, candidates)
+            .unwrap()
+            .bounds(Included("name-010"), Excluded("name-020"));
+        let values: Vec<_> = iter.map(|r| r.unwrap().1.to_string()).collect();
+        assert_eq!(values, expected.into_iter().rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn facet_levels_in_place() {
+        let small_batches_index = TempIndex::new();
+        let mut small_batches_config = IndexerConfig::default();
+        small_batches_config.small_batch_threshold = Some(10);
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let indexes = [(&small_batches_index, &small_batches_config), (&index, &config)];
+        for &(index, config) in indexes.iter() {
+            let mut wtxn = index.write_txn().unwrap();
+            let mut builder = Settings::new(&mut wtxn, index, config);
+            builder.set_filterable_fields(hashset! { S("name"), S("value") });
+            builder.execute(|_| ()).unwrap();
+            wtxn.commit().unwrap();
+        }
+
+        let add_documents = |documents: serde_json::Value| {
+            for &(index, config) in indexes.iter() {
+                let mut wtxn = index.write_txn().unwrap();
+                let mut cursor = std::io::Cursor::new(Vec::new());
+                let mut batch = DocumentBatchBuilder::new(&mut cursor).unwrap();
+                batch.extend_from_json(serde_json::to_vec(&documents).unwrap().as_slice()).unwrap();
+                batch.finish().unwrap();
+                cursor.set_position(0);
+                let content = DocumentBatchReader::from_reader(cursor).unwrap();
+
+                let indexing_config = IndexDocumentsConfig {
+                    facet_level_group_size: NonZeroUsize::new(4),
+                    facet_min_level_size: NonZeroUsize::new(2),
+                    ..Default::default()
+                };
+                let mut builder =
+                    IndexDocuments::new(&mut wtxn, index, config, indexing_config, |_| ()).unwrap();
+                builder.add_documents(content).unwrap();
+                builder.execute().unwrap();
+                wtxn.commit().unwrap();
+            }
+        };
+
+        // The number of groups of the level 1 of the fields and their values through the levels.
+        let facets = |index: &TempIndex| {
+            let rtxn = index.read_txn().unwrap();
+            let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+            let candidates = index.documents_ids(&rtxn).unwrap();
+            let level_one_groups = |db: heed::Database<ByteSlice, ByteSlice>, field_id: u16| {
+                let prefix = [&field_id.to_be_bytes()[..], &[1][..]].concat();
+                db.prefix_iter(&rtxn, &prefix).unwrap().count()
+            };
+
+            let value = fields_ids_map.id("value").unwrap();
+            let numbers: Vec<_> =
+                FacetNumberIter::new_reducing(&rtxn, index, value, candidates.clone())
+                    .unwrap()
+                    .chain(
+                        FacetNumberIter::new_non_reducing(&rtxn, index, value, candidates.clone())
+                            .unwrap()
+                            .bounds(Included(40.0), Excluded(101.0)),
+                    )
+                    .map(|r| r.unwrap())
+                    .collect();
+
+            let name = fields_ids_map.id("name").unwrap();
+            let strings: Vec<_> =
+                FacetStringIter::new_reverse_reducing(&rtxn, index, name, candidates)
+                    .unwrap()
+                    .map(|r| r.map(|(value, _, docids)| (value.to_string(), docids)).unwrap())
+                    .collect();
+
+            let groups = (
+                level_one_groups(index.facet_id_f64_docids.remap_types(), value),
+                level_one_groups(index.facet_id_string_docids.remap_types(), name),
+            );
+            let documents_ids = (
+                index.number_faceted_documents_ids(&rtxn, value).unwrap(),
+                index.string_faceted_documents_ids(&rtxn, name).unwrap(),
+            );
+            (groups, (numbers, strings, documents_ids))
+        };
+
+        let documents: Vec<_> = (0..100u32)
+            .map(|i| serde_json::json!({ "id": i, "name": format!("name-{:03}", i), "value": i }))
+            .collect();
+        add_documents(serde_json::json!(documents));
+        assert_eq!(facets(&small_batches_index).0, (25, 25));
+
+        // A new number above and below all the others, a known string, a new string within the
+        // bounds of a group and a replaced document are added to the levels in place.
+        add_documents(serde_json::json!([
+            { "id": 100, "name": "name-050", "value": 100.5 },
+            { "id": 101, "name": "name-0505", "value": -1 },
+        ]));
+        add_documents(serde_json::json!([{ "id": 3, "name": "name-003", "value": 42.5 }]));
+
+        let ((number_groups, string_groups), facets_in_place) = facets(&small_batches_index);
+        assert_eq!((number_groups, string_groups), (25, 25));
+        let (groups, recomputed_facets) = facets(&index);
+        assert_eq!(groups, (26, 26));
+        assert_eq!(facets_in_place, recomputed_facets);
+
+        // A string outside of all the groups requires recomputing the levels.
+        add_documents(serde_json::json!([{ "id": 102, "name": "zzz", "value": 7 }]));
+        assert_eq!(facets(&small_batches_index), facets(&index));
+    }
+}
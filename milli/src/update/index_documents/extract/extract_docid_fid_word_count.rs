@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+
+use grenad::Sorter;
+
+use super::helpers::{
+    create_extractor_sorter, keep_first, read_u32_ne_bytes, sorter_into_reader, try_split_array_at,
+    GrenadParameters, MergeFn, SpillCounter,
+};
+use crate::error::SerializationError;
+use crate::index::db_name::DOCID_WORD_POSITIONS;
+use crate::{relative_from_absolute_position, DocumentId, FieldId, Result};
+
+/// Extracts the number of words of every field of every document.
+///
+/// Returns a grenad reader with the list of extracted document ids and field ids
+/// associated with their word count from the given chunk of docid word positions.
+#[logging_timer::time]
+pub fn extract_docid_fid_word_count<R: io::Read + io::Seek>(
+    docid_word_positions: grenad::Reader<R>,
+    indexer: GrenadParameters,
+) -> Result<grenad::Reader<File>> {
+    let max_memory = indexer.max_memory_by_thread();
+
+    let mut docid_fid_word_count_sorter =
+        create_extractor_sorter("docid-field-id-wordcount", keep_first, &indexer, max_memory);
+
+    // This map is assumed to not consume a lot of memory.
+    let mut document_fid_wordcount = HashMap::new();
+    let mut current_document_id = None;
+
+    let mut cursor = docid_word_positions.into_cursor()?;
+    while let Some((key, value)) = cursor.move_on_next()? {
+        let (document_id_bytes, _word_bytes) = try_split_array_at(key)
+            .ok_or_else(|| SerializationError::Decoding { db_name: Some(DOCID_WORD_POSITIONS) })?;
+        let document_id = u32::from_be_bytes(document_id_bytes);
+
+        let curr_document_id = *current_document_id.get_or_insert(document_id);
+        if curr_document_id != document_id {
+            drain_document_fid_wordcount_into_sorter(
+                &mut docid_fid_word_count_sorter,
+                &mut document_fid_wordcount,
+                curr_document_id,
+            )?;
+            current_document_id = Some(document_id);
+        }
+
+        // Every position is a word occurrence, the count is therefore
+        // not affected by the gaps introduced by the hard separators.
+        for position in read_u32_ne_bytes(value) {
+            let (field_id, _position) = relative_from_absolute_position(position);
+            *document_fid_wordcount.entry(field_id as FieldId).or_insert(0u32) += 1;
+        }
+    }
+
+    if let Some(document_id) = current_document_id {
+        // We must make sure that don't lose the current document field id
+        // word count map if we break because we reached the end of the chunk.
+        drain_document_fid_wordcount_into_sorter(
+            &mut docid_fid_word_count_sorter,
+            &mut document_fid_wordcount,
+            document_id,
+        )?;
+    }
+
+    sorter_into_reader(docid_fid_word_count_sorter, indexer)
+}
+
+fn drain_document_fid_wordcount_into_sorter(
+    docid_fid_word_count_sorter: &mut Sorter<MergeFn, SpillCounter>,
+    document_fid_wordcount: &mut HashMap<FieldId, u32>,
+    document_id: DocumentId,
+) -> Result<()> {
+    let mut key_buffer = Vec::new();
+
+    for (fid, count) in document_fid_wordcount.drain() {
+        key_buffer.clear();
+        key_buffer.extend_from_slice(&document_id.to_be_bytes());
+        key_buffer.extend_from_slice(&fid.to_be_bytes());
+
+        docid_fid_word_count_sorter.insert(&key_buffer, count.to_be_bytes())?;
+    }
+
+    Ok(())
+}
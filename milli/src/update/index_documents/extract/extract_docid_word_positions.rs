@@ -1,6 +1,8 @@
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::fs::File;
+use std::rc::Rc;
 use std::{io, mem, str};
 
 use meilisearch_tokenizer::token::SeparatorKind;
@@ -8,43 +10,103 @@ use meilisearch_tokenizer::{Analyzer, AnalyzerConfig, Token, TokenKind};
 use roaring::RoaringBitmap;
 use serde_json::Value;
 
-use super::helpers::{concat_u32s_array, create_sorter, sorter_into_reader, GrenadParameters};
+use super::helpers::{
+    concat_u32s_array, create_extractor_sorter, sorter_into_reader, GrenadParameters,
+};
 use crate::error::{InternalError, SerializationError};
-use crate::{absolute_from_relative_position, FieldId, Result, MAX_POSITION_PER_ATTRIBUTE};
+use crate::{
+    absolute_from_relative_position, FieldId, NormalizerOptions, Result, WordBoundaries,
+    MAX_POSITION_PER_ATTRIBUTE,
+};
+
+thread_local! {
+    /// The analyzer of the extraction threads, built once by thread instead of once by chunk
+    /// of documents, and built again when it is used with other stop words.
+    static ANALYZER: RefCell<Option<Rc<StopWordsAnalyzer>>> = RefCell::new(None);
+}
+
+/// An analyzer that owns the stop words it is configured with.
+struct StopWordsAnalyzer {
+    // Declared first to be dropped before the stop words it borrows.
+    analyzer: Analyzer<'static, Vec<u8>>,
+    stop_words: Option<Box<fst::Set<Vec<u8>>>>,
+}
+
+impl StopWordsAnalyzer {
+    fn new(stop_words: Option<&fst::Set<&[u8]>>) -> Result<StopWordsAnalyzer> {
+        let stop_words = match stop_words {
+            Some(stop_words) => {
+                Some(Box::new(fst::Set::new(stop_words.as_fst().as_bytes().to_vec())?))
+            }
+            None => None,
+        };
+
+        let mut config = AnalyzerConfig::default();
+        if let Some(stop_words) = &stop_words {
+            // safety: the stop words are boxed and never modified, they don't move
+            // when the struct does and are dropped after the analyzer that borrows them.
+            let stop_words: &'static fst::Set<Vec<u8>> =
+                unsafe { &*(&**stop_words as *const fst::Set<Vec<u8>>) };
+            config.stop_words(stop_words);
+        }
+
+        Ok(StopWordsAnalyzer { analyzer: Analyzer::new(config), stop_words })
+    }
+
+    fn has_stop_words(&self, stop_words: Option<&fst::Set<&[u8]>>) -> bool {
+        let own_bytes = self.stop_words.as_ref().map(|set| set.as_fst().as_bytes());
+        own_bytes == stop_words.map(|set| set.as_fst().as_bytes())
+    }
+}
+
+/// Returns the analyzer of the current thread configured with the given stop words.
+fn thread_analyzer(stop_words: Option<&fst::Set<&[u8]>>) -> Result<Rc<StopWordsAnalyzer>> {
+    ANALYZER.with(|cached| {
+        let mut cached = cached.borrow_mut();
+        match cached.as_ref() {
+            Some(analyzer) if analyzer.has_stop_words(stop_words) => Ok(analyzer.clone()),
+            _ => {
+                let analyzer = Rc::new(StopWordsAnalyzer::new(stop_words)?);
+                *cached = Some(analyzer.clone());
+                Ok(analyzer)
+            }
+        }
+    })
+}
 
 /// Extracts the word and positions where this word appear and
 /// prefixes it by the document id.
 ///
-/// Returns the generated internal documents ids and a grenad reader
-/// with the list of extracted words from the given chunk of documents.
+/// Returns the generated internal documents ids, a grenad reader
+/// with the list of extracted words from the given chunk of documents
+/// and the attributes of each document whose positions were clamped by
+/// the `max_positions_per_attributes` cap.
 #[logging_timer::time]
 pub fn extract_docid_word_positions<R: io::Read + io::Seek>(
     obkv_documents: grenad::Reader<R>,
     indexer: GrenadParameters,
     searchable_fields: &Option<HashSet<FieldId>>,
     stop_words: Option<&fst::Set<&[u8]>>,
+    primary_key_id: FieldId,
     max_positions_per_attributes: Option<u32>,
-) -> Result<(RoaringBitmap, grenad::Reader<File>)> {
+    max_positions_per_attributes_overrides: &HashMap<FieldId, u32>,
+    attributes_positions_limits: &HashMap<FieldId, u32>,
+    word_boundaries: WordBoundaries,
+    normalizer_options: NormalizerOptions,
+) -> Result<(RoaringBitmap, grenad::Reader<File>, Vec<(String, Vec<FieldId>)>)> {
     let max_positions_per_attributes = max_positions_per_attributes
         .map_or(MAX_POSITION_PER_ATTRIBUTE, |max| max.min(MAX_POSITION_PER_ATTRIBUTE));
     let max_memory = indexer.max_memory_by_thread();
 
     let mut documents_ids = RoaringBitmap::new();
-    let mut docid_word_positions_sorter = create_sorter(
-        concat_u32s_array,
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        indexer.max_nb_chunks,
-        max_memory,
-    );
+    let mut truncated_documents = Vec::new();
+    let mut truncated_fields = Vec::new();
+    let mut docid_word_positions_sorter =
+        create_extractor_sorter("docid-word-positions", concat_u32s_array, &indexer, max_memory);
 
     let mut key_buffer = Vec::new();
     let mut field_buffer = String::new();
-    let mut config = AnalyzerConfig::default();
-    if let Some(stop_words) = stop_words {
-        config.stop_words(stop_words);
-    }
-    let analyzer = Analyzer::<Vec<u8>>::new(AnalyzerConfig::default());
+    let analyzer = thread_analyzer(stop_words)?;
 
     let mut cursor = obkv_documents.into_cursor()?;
     while let Some((key, value)) = cursor.move_on_next()? {
@@ -57,6 +119,7 @@ pub fn extract_docid_word_positions<R: io::Read + io::Seek>(
         documents_ids.push(document_id);
         key_buffer.clear();
         key_buffer.extend_from_slice(&document_id.to_be_bytes());
+        truncated_fields.clear();
 
         for (field_id, field_bytes) in obkv.iter() {
             if searchable_fields.as_ref().map_or(true, |sf| sf.contains(&field_id)) {
@@ -64,34 +127,76 @@ pub fn extract_docid_word_positions<R: io::Read + io::Seek>(
                     serde_json::from_slice(field_bytes).map_err(InternalError::SerdeJson)?;
                 field_buffer.clear();
                 if let Some(field) = json_to_string(&value, &mut field_buffer) {
-                    let analyzed = analyzer.analyze(field);
-                    let tokens = process_tokens(analyzed.tokens())
-                        .take_while(|(p, _)| (*p as u32) < max_positions_per_attributes);
-
-                    for (index, token) in tokens {
-                        let token = token.text().trim();
-                        if !token.is_empty() {
-                            key_buffer.truncate(mem::size_of::<u32>());
-                            key_buffer.extend_from_slice(token.as_bytes());
-
-                            let position: u16 = index
-                                .try_into()
-                                .map_err(|_| SerializationError::InvalidNumberSerialization)?;
-                            let position = absolute_from_relative_position(field_id, position);
-                            docid_word_positions_sorter
-                                .insert(&key_buffer, &position.to_ne_bytes())?;
+                    let max_positions_cap =
+                        match max_positions_per_attributes_overrides.get(&field_id) {
+                            Some(max) => (*max).min(MAX_POSITION_PER_ATTRIBUTE),
+                            None => max_positions_per_attributes,
+                        };
+                    // Some attributes can be limited to their first positions,
+                    // this is a user choice and must not be reported as a truncation.
+                    let limit = attributes_positions_limits.get(&field_id).copied();
+                    let max_positions =
+                        limit.map_or(max_positions_cap, |limit| limit.min(max_positions_cap));
+
+                    let analyzed = analyzer.analyzer.analyze(field);
+                    // The words split on the word boundaries take the following positions.
+                    let mut split_positions = 0;
+                    'tokens: for (index, token) in process_tokens(analyzed.tokens()) {
+                        let original = field.get(token.byte_start..token.byte_end).unwrap_or("");
+                        let normalized = normalizer_options.normalize(token.text().trim());
+                        let words = word_boundaries.split(original.trim(), &normalized);
+                        let words_count = words.len();
+
+                        for (offset, word) in words.into_iter().enumerate() {
+                            let index = index + split_positions + offset;
+                            if index as u32 >= max_positions {
+                                if limit.map_or(true, |limit| limit > max_positions_cap) {
+                                    truncated_fields.push(field_id);
+                                }
+                                break 'tokens;
+                            }
+
+                            if !word.is_empty() {
+                                key_buffer.truncate(mem::size_of::<u32>());
+                                key_buffer.extend_from_slice(word.as_bytes());
+
+                                let position: u16 = index
+                                    .try_into()
+                                    .map_err(|_| SerializationError::InvalidNumberSerialization)?;
+                                let position = absolute_from_relative_position(field_id, position);
+                                docid_word_positions_sorter
+                                    .insert(&key_buffer, &position.to_ne_bytes())?;
+                            }
                         }
+
+                        split_positions += words_count - 1;
                     }
                 }
             }
         }
+
+        if !truncated_fields.is_empty() {
+            let external_id = match obkv.get(primary_key_id) {
+                Some(bytes) => {
+                    let value: Value =
+                        serde_json::from_slice(bytes).map_err(InternalError::SerdeJson)?;
+                    match value {
+                        Value::String(string) => string,
+                        otherwise => otherwise.to_string(),
+                    }
+                }
+                None => document_id.to_string(),
+            };
+            truncated_documents.push((external_id, truncated_fields.clone()));
+        }
     }
 
-    sorter_into_reader(docid_word_positions_sorter, indexer).map(|reader| (documents_ids, reader))
+    sorter_into_reader(docid_word_positions_sorter, indexer)
+        .map(|reader| (documents_ids, reader, truncated_documents))
 }
 
 /// Transform a JSON value into a string that can be indexed.
-fn json_to_string<'a>(value: &'a Value, buffer: &'a mut String) -> Option<&'a str> {
+pub(crate) fn json_to_string<'a>(value: &'a Value, buffer: &'a mut String) -> Option<&'a str> {
     fn inner(value: &Value, output: &mut String) -> bool {
         use std::fmt::Write;
         match value {
@@ -142,7 +247,7 @@ fn json_to_string<'a>(value: &'a Value, buffer: &'a mut String) -> Option<&'a st
 /// take an iterator on tokens and compute their relative position depending on separator kinds
 /// if it's an `Hard` separator we add an additional relative proximity of 8 between words,
 /// else we keep the standart proximity of 1 between words.
-fn process_tokens<'a>(
+pub(crate) fn process_tokens<'a>(
     tokens: impl Iterator<Item = Token<'a>>,
 ) -> impl Iterator<Item = (usize, Token<'a>)> {
     tokens
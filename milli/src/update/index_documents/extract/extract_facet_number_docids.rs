@@ -4,7 +4,7 @@ use std::io;
 use heed::{BytesDecode, BytesEncode};
 
 use super::helpers::{
-    create_sorter, merge_cbo_roaring_bitmaps, sorter_into_reader, GrenadParameters,
+    create_extractor_sorter, merge_cbo_roaring_bitmaps, sorter_into_reader, GrenadParameters,
 };
 use crate::heed_codec::facet::{FacetLevelValueF64Codec, FieldDocIdFacetF64Codec};
 use crate::Result;
@@ -20,11 +20,10 @@ pub fn extract_facet_number_docids<R: io::Read + io::Seek>(
 ) -> Result<grenad::Reader<File>> {
     let max_memory = indexer.max_memory_by_thread();
 
-    let mut facet_number_docids_sorter = create_sorter(
+    let mut facet_number_docids_sorter = create_extractor_sorter(
+        "field-id-facet-number-docids",
         merge_cbo_roaring_bitmaps,
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        indexer.max_nb_chunks,
+        &indexer,
         max_memory,
     );
 
@@ -5,7 +5,7 @@ use std::{io, str};
 use roaring::RoaringBitmap;
 
 use super::helpers::{
-    create_sorter, keep_first_prefix_value_merge_roaring_bitmaps, sorter_into_reader,
+    create_extractor_sorter, keep_first_prefix_value_merge_roaring_bitmaps, sorter_into_reader,
     try_split_array_at, GrenadParameters,
 };
 use crate::heed_codec::facet::{encode_prefix_string, FacetStringLevelZeroCodec};
@@ -22,11 +22,10 @@ pub fn extract_facet_string_docids<R: io::Read + io::Seek>(
 ) -> Result<grenad::Reader<File>> {
     let max_memory = indexer.max_memory_by_thread();
 
-    let mut facet_string_docids_sorter = create_sorter(
+    let mut facet_string_docids_sorter = create_extractor_sorter(
+        "field-id-facet-string-docids",
         keep_first_prefix_value_merge_roaring_bitmaps,
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        indexer.max_nb_chunks,
+        &indexer,
         max_memory,
     );
 
@@ -1,54 +1,78 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
 use std::fs::File;
 use std::io;
 use std::mem::size_of;
 
 use heed::zerocopy::AsBytes;
 use serde_json::Value;
+use sha2::{Digest, Sha256};
 
-use super::helpers::{create_sorter, keep_first, sorter_into_reader, GrenadParameters};
-use crate::error::InternalError;
+use super::helpers::{
+    create_extractor_sorter, keep_first, merge_cbo_roaring_bitmaps, sorter_into_reader,
+    GrenadParameters,
+};
+use crate::error::{InternalError, SerializationError};
 use crate::facet::value_encoding::f64_into_bytes;
+use crate::facet::NumberCoercion;
 use crate::{DocumentId, FieldId, Result};
 
 /// Extracts the facet values of each faceted field of each document.
 ///
 /// Returns the generated grenad reader containing the docid the fid and the orginal value as key
 /// and the normalized value as value extracted from the given chunk of documents.
+/// The string values are also extracted as numbers according to the number coercion
+/// policy of their field. The content hash of the documents, computed from the values of
+/// the hashed fields, is extracted as the string value of the content hash field.
+///
+/// The booleans are not extracted as strings, the third grenad reader contains the field id
+/// and the boolean as a byte as key and the documents ids having this boolean as value.
 #[logging_timer::time]
 pub fn extract_fid_docid_facet_values<R: io::Read + io::Seek>(
     obkv_documents: grenad::Reader<R>,
     indexer: GrenadParameters,
     faceted_fields: &HashSet<FieldId>,
-) -> Result<(grenad::Reader<File>, grenad::Reader<File>)> {
+    number_coercions: &HashMap<FieldId, NumberCoercion>,
+    content_hash: &Option<(FieldId, HashSet<FieldId>)>,
+) -> Result<(grenad::Reader<File>, grenad::Reader<File>, grenad::Reader<File>)> {
     let max_memory = indexer.max_memory_by_thread();
 
-    let mut fid_docid_facet_numbers_sorter = create_sorter(
+    let mut fid_docid_facet_numbers_sorter = create_extractor_sorter(
+        "field-id-docid-facet-numbers",
         keep_first,
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        indexer.max_nb_chunks,
-        max_memory.map(|m| m / 2),
+        &indexer,
+        max_memory.map(|m| m / 3),
     );
 
-    let mut fid_docid_facet_strings_sorter = create_sorter(
+    let mut fid_docid_facet_strings_sorter = create_extractor_sorter(
+        "field-id-docid-facet-strings",
         keep_first,
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        indexer.max_nb_chunks,
-        max_memory.map(|m| m / 2),
+        &indexer,
+        max_memory.map(|m| m / 3),
+    );
+
+    let mut fid_facet_bool_docids_sorter = create_extractor_sorter(
+        "field-id-facet-bool-docids",
+        merge_cbo_roaring_bitmaps,
+        &indexer,
+        max_memory.map(|m| m / 3),
     );
 
     let mut key_buffer = Vec::new();
     let mut cursor = obkv_documents.into_cursor()?;
     while let Some((docid_bytes, value)) = cursor.move_on_next()? {
+        let document_id = docid_bytes
+            .try_into()
+            .map(DocumentId::from_be_bytes)
+            .map_err(|_| SerializationError::InvalidNumberSerialization)?;
         let obkv = obkv::KvReader::new(value);
 
         for (field_id, field_bytes) in obkv.iter() {
             if faceted_fields.contains(&field_id) {
                 let value =
                     serde_json::from_slice(field_bytes).map_err(InternalError::SerdeJson)?;
-                let (numbers, strings) = extract_facet_values(&value);
+                let coercion = number_coercions.get(&field_id).copied().unwrap_or_default();
+                let (numbers, strings, booleans) = extract_facet_values(&value, coercion);
 
                 key_buffer.clear();
 
@@ -73,39 +97,89 @@ pub fn extract_fid_docid_facet_values<R: io::Read + io::Seek>(
                     key_buffer.extend_from_slice(normalized.as_bytes());
                     fid_docid_facet_strings_sorter.insert(&key_buffer, original.as_bytes())?;
                 }
+
+                // insert the document id under the field id and the boolean in the sorter
+                for boolean in booleans {
+                    key_buffer.clear();
+                    key_buffer.extend_from_slice(&field_id.to_be_bytes());
+                    key_buffer.push(boolean as u8);
+                    fid_facet_bool_docids_sorter.insert(&key_buffer, document_id.to_ne_bytes())?;
+                }
+            }
+        }
+
+        if let Some((hash_field_id, hashed_fields)) = content_hash {
+            if let Some(hash) = content_hash_of(&obkv, hashed_fields) {
+                key_buffer.clear();
+                key_buffer.extend_from_slice(&hash_field_id.to_be_bytes());
+                key_buffer.extend_from_slice(&docid_bytes);
+                key_buffer.extend_from_slice(hash.as_bytes());
+                fid_docid_facet_strings_sorter.insert(&key_buffer, hash.as_bytes())?;
             }
         }
     }
 
     Ok((
         sorter_into_reader(fid_docid_facet_numbers_sorter, indexer.clone())?,
-        sorter_into_reader(fid_docid_facet_strings_sorter, indexer)?,
+        sorter_into_reader(fid_docid_facet_strings_sorter, indexer.clone())?,
+        sorter_into_reader(fid_facet_bool_docids_sorter, indexer)?,
     ))
 }
 
-fn extract_facet_values(value: &Value) -> (Vec<f64>, Vec<(String, String)>) {
+/// Returns the SHA-256 of the raw values of the hashed fields in hexadecimal,
+/// `None` if the document has none of these fields.
+fn content_hash_of(obkv: &obkv::KvReaderU16, hashed_fields: &HashSet<FieldId>) -> Option<String> {
+    let mut hasher = Sha256::new();
+    let mut hashed = false;
+    // the fields are iterated in the order of their ids, the hash doesn't depend on the
+    // order of the fields in the original document.
+    for (field_id, field_bytes) in obkv.iter().filter(|(id, _)| hashed_fields.contains(id)) {
+        hasher.update(field_id.to_be_bytes());
+        hasher.update((field_bytes.len() as u64).to_be_bytes());
+        hasher.update(field_bytes);
+        hashed = true;
+    }
+    hashed.then(|| format!("{:x}", hasher.finalize()))
+}
+
+fn extract_facet_values(
+    value: &Value,
+    coercion: NumberCoercion,
+) -> (Vec<f64>, Vec<(String, String)>, Vec<bool>) {
     fn inner_extract_facet_values(
         value: &Value,
         can_recurse: bool,
+        coercion: NumberCoercion,
         output_numbers: &mut Vec<f64>,
         output_strings: &mut Vec<(String, String)>,
+        output_booleans: &mut Vec<bool>,
     ) {
         match value {
             Value::Null => (),
-            Value::Bool(b) => output_strings.push((b.to_string(), b.to_string())),
+            Value::Bool(boolean) => output_booleans.push(*boolean),
             Value::Number(number) => {
                 if let Some(float) = number.as_f64() {
                     output_numbers.push(float);
                 }
             }
             Value::String(original) => {
+                if let Some(number) = coercion.coerce(original) {
+                    output_numbers.push(number);
+                }
                 let normalized = original.trim().to_lowercase();
                 output_strings.push((normalized, original.clone()));
             }
             Value::Array(values) => {
                 if can_recurse {
                     for value in values {
-                        inner_extract_facet_values(value, false, output_numbers, output_strings);
+                        inner_extract_facet_values(
+                            value,
+                            false,
+                            coercion,
+                            output_numbers,
+                            output_strings,
+                            output_booleans,
+                        );
                     }
                 }
             }
@@ -115,7 +189,15 @@ fn extract_facet_values(value: &Value) -> (Vec<f64>, Vec<(String, String)>) {
 
     let mut facet_number_values = Vec::new();
     let mut facet_string_values = Vec::new();
-    inner_extract_facet_values(value, true, &mut facet_number_values, &mut facet_string_values);
+    let mut facet_boolean_values = Vec::new();
+    inner_extract_facet_values(
+        value,
+        true,
+        coercion,
+        &mut facet_number_values,
+        &mut facet_string_values,
+        &mut facet_boolean_values,
+    );
 
-    (facet_number_values, facet_string_values)
+    (facet_number_values, facet_string_values, facet_boolean_values)
 }
@@ -5,8 +5,8 @@ use std::{cmp, io};
 use grenad::Sorter;
 
 use super::helpers::{
-    create_sorter, merge_cbo_roaring_bitmaps, read_u32_ne_bytes, sorter_into_reader,
-    try_split_array_at, GrenadParameters, MergeFn,
+    create_extractor_sorter, merge_cbo_roaring_bitmaps, read_u32_ne_bytes, sorter_into_reader,
+    try_split_array_at, GrenadParameters, MergeFn, SpillCounter,
 };
 use crate::error::SerializationError;
 use crate::index::db_name::DOCID_WORD_POSITIONS;
@@ -24,11 +24,10 @@ pub fn extract_fid_word_count_docids<R: io::Read + io::Seek>(
 ) -> Result<grenad::Reader<File>> {
     let max_memory = indexer.max_memory_by_thread();
 
-    let mut fid_word_count_docids_sorter = create_sorter(
+    let mut fid_word_count_docids_sorter = create_extractor_sorter(
+        "field-id-wordcount-docids",
         merge_cbo_roaring_bitmaps,
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        indexer.max_nb_chunks,
+        &indexer,
         max_memory,
     );
 
@@ -75,7 +74,7 @@ pub fn extract_fid_word_count_docids<R: io::Read + io::Seek>(
 }
 
 fn drain_document_fid_wordcount_into_sorter(
-    fid_word_count_docids_sorter: &mut Sorter<MergeFn>,
+    fid_word_count_docids_sorter: &mut Sorter<MergeFn, SpillCounter>,
     document_fid_wordcount: &mut HashMap<FieldId, u32>,
     document_id: DocumentId,
 ) -> Result<()> {
@@ -9,19 +9,30 @@ use super::helpers::{create_writer, writer_into_reader, GrenadParameters};
 use crate::error::GeoError;
 use crate::{FieldId, InternalError, Result};
 
+/// The ids of the flattened fields of the `_geo` object, either a `{ lat, lng }` object
+/// or a GeoJSON point, `{ "type": "Point", "coordinates": [lng, lat] }`.
+#[derive(Debug, Clone, Copy)]
+pub struct GeoFieldsIds {
+    pub lat: FieldId,
+    pub lng: FieldId,
+    pub geojson_type: FieldId,
+    pub geojson_coordinates: FieldId,
+}
+
 /// Extracts the geographical coordinates contained in each document under the `_geo` field.
 ///
-/// Returns the generated grenad reader containing the docid as key associated to the (latitude, longitude)
+/// Returns the generated grenad reader containing the docid as key associated to the
+/// (latitude, longitude) of each of its points, one after the other.
 pub fn extract_geo_points<R: io::Read + io::Seek>(
     obkv_documents: grenad::Reader<R>,
     indexer: GrenadParameters,
     primary_key_id: FieldId,
-    (lat_fid, lng_fid): (FieldId, FieldId),
+    geo_fields_ids: GeoFieldsIds,
 ) -> Result<grenad::Reader<File>> {
     let mut writer = create_writer(
         indexer.chunk_compression_type,
         indexer.chunk_compression_level,
-        tempfile::tempfile()?,
+        indexer.tempfile()?,
     );
 
     let mut cursor = obkv_documents.into_cursor()?;
@@ -34,12 +45,31 @@ pub fn extract_geo_points<R: io::Read + io::Seek>(
             serde_json::from_slice(primary_key).unwrap()
         };
 
+        // a GeoJSON point, or an array of them, is flattened into a list of coordinates
+        if let Some(coordinates) = obkv.get(geo_fields_ids.geojson_coordinates) {
+            let geojson_type = match obkv.get(geo_fields_ids.geojson_type) {
+                Some(value) => serde_json::from_slice(value).map_err(InternalError::SerdeJson)?,
+                None => Value::Null,
+            };
+            let coordinates =
+                serde_json::from_slice(coordinates).map_err(InternalError::SerdeJson)?;
+            let points = extract_geojson_points(geojson_type, coordinates)
+                .map_err(|value| GeoError::BadGeoJson { document_id: primary_key(), value })?;
+
+            let bytes: Vec<u8> = points
+                .into_iter()
+                .flat_map(|[lat, lng]| concat_arrays!(lat.to_ne_bytes(), lng.to_ne_bytes()))
+                .collect();
+            writer.insert(docid_bytes, bytes)?;
+            continue;
+        }
+
         // first we get the two fields
         let lat = obkv
-            .get(lat_fid)
+            .get(geo_fields_ids.lat)
             .ok_or_else(|| GeoError::MissingLatitude { document_id: primary_key() })?;
         let lng = obkv
-            .get(lng_fid)
+            .get(geo_fields_ids.lng)
             .ok_or_else(|| GeoError::MissingLongitude { document_id: primary_key() })?;
 
         // then we extract the values
@@ -60,6 +90,40 @@ pub fn extract_geo_points<R: io::Read + io::Seek>(
     Ok(writer_into_reader(writer)?)
 }
 
+/// Returns the (latitude, longitude) of the GeoJSON points, their flattened types must all be
+/// `Point` and their flattened coordinates the longitude and latitude of each point.
+fn extract_geojson_points(
+    geojson_type: Value,
+    coordinates: Value,
+) -> StdResult<Vec<[f64; 2]>, Value> {
+    let types = match geojson_type {
+        Value::Array(types) => types,
+        geojson_type => vec![geojson_type],
+    };
+    if types.iter().any(|t| t.as_str() != Some("Point")) {
+        return Err(Value::Array(types));
+    }
+
+    let coordinates = match coordinates {
+        Value::Array(coordinates) => coordinates,
+        coordinates => return Err(coordinates),
+    };
+    if coordinates.len() != types.len() * 2 {
+        return Err(Value::Array(coordinates));
+    }
+
+    let points: Option<Vec<_>> = coordinates
+        .chunks_exact(2)
+        .map(|pair| {
+            let lng = extract_float_from_value(pair[0].clone()).ok()?;
+            let lat = extract_float_from_value(pair[1].clone()).ok()?;
+            Some([lat, lng])
+        })
+        .collect();
+
+    points.ok_or(Value::Array(coordinates))
+}
+
 fn extract_float_from_value(value: Value) -> StdResult<f64, Value> {
     match value {
         Value::Number(ref n) => n.as_f64().ok_or(value),
@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io;
+
+use serde_json::Value;
+
+use super::helpers::{create_writer, writer_into_reader, GrenadParameters};
+use crate::error::UserError;
+use crate::{FieldId, InternalError, Result};
+
+/// Extracts the vectors contained in each document under the `_vectors` fields.
+///
+/// Returns the generated grenad reader containing the docid and the field id as key
+/// associated to the vector encoded as big-endian `f32`s.
+pub fn extract_vectors<R: io::Read + io::Seek>(
+    obkv_documents: grenad::Reader<R>,
+    indexer: GrenadParameters,
+    primary_key_id: FieldId,
+    vectors_fields_ids: &HashSet<FieldId>,
+) -> Result<grenad::Reader<File>> {
+    let mut writer = create_writer(
+        indexer.chunk_compression_type,
+        indexer.chunk_compression_level,
+        indexer.tempfile()?,
+    );
+
+    let mut key_buffer = Vec::new();
+    let mut value_buffer = Vec::new();
+    let mut cursor = obkv_documents.into_cursor()?;
+    while let Some((docid_bytes, value)) = cursor.move_on_next()? {
+        let obkv = obkv::KvReader::new(value);
+        // since we only needs the primary key when we throw an error we create this getter to
+        // lazily get it when needed
+        let primary_key = || -> Value {
+            let primary_key = obkv.get(primary_key_id).unwrap();
+            serde_json::from_slice(primary_key).unwrap()
+        };
+
+        for (field_id, field_bytes) in obkv.iter() {
+            if !vectors_fields_ids.contains(&field_id) {
+                continue;
+            }
+
+            let value = serde_json::from_slice(field_bytes).map_err(InternalError::SerdeJson)?;
+            let vector = match extract_vector_from_value(value) {
+                Ok(Some(vector)) => vector,
+                Ok(None) => continue,
+                Err(value) => {
+                    return Err(UserError::InvalidVector { document_id: primary_key(), value })?
+                }
+            };
+
+            key_buffer.clear();
+            key_buffer.extend_from_slice(docid_bytes);
+            key_buffer.extend_from_slice(&field_id.to_be_bytes());
+
+            value_buffer.clear();
+            value_buffer.extend(vector.iter().flat_map(|value| value.to_be_bytes()));
+
+            writer.insert(&key_buffer, &value_buffer)?;
+        }
+    }
+
+    Ok(writer_into_reader(writer)?)
+}
+
+/// Returns the vector stored in the value, `None` if the value is `null`,
+/// or the value itself if it is not an array of numbers.
+fn extract_vector_from_value(value: Value) -> std::result::Result<Option<Vec<f32>>, Value> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Array(ref values) => {
+            let vector: Option<Vec<f32>> =
+                values.iter().map(|value| value.as_f64().map(|f| f as f32)).collect();
+            vector.map(Some).ok_or(value)
+        }
+        value => Err(value),
+    }
+}
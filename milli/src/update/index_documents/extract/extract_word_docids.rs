@@ -6,7 +6,7 @@ use std::iter::FromIterator;
 use roaring::RoaringBitmap;
 
 use super::helpers::{
-    create_sorter, merge_roaring_bitmaps, serialize_roaring_bitmap, sorter_into_reader,
+    create_extractor_sorter, merge_roaring_bitmaps, serialize_roaring_bitmap, sorter_into_reader,
     try_split_array_at, GrenadParameters,
 };
 use crate::error::SerializationError;
@@ -29,19 +29,17 @@ pub fn extract_word_docids<R: io::Read + io::Seek>(
 ) -> Result<(grenad::Reader<File>, grenad::Reader<File>)> {
     let max_memory = indexer.max_memory_by_thread();
 
-    let mut word_docids_sorter = create_sorter(
+    let mut word_docids_sorter = create_extractor_sorter(
+        "word-docids",
         merge_roaring_bitmaps,
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        indexer.max_nb_chunks,
+        &indexer,
         max_memory.map(|x| x / 2),
     );
 
-    let mut exact_word_docids_sorter = create_sorter(
+    let mut exact_word_docids_sorter = create_extractor_sorter(
+        "exact-word-docids",
         merge_roaring_bitmaps,
-        indexer.chunk_compression_type,
-        indexer.chunk_compression_level,
-        indexer.max_nb_chunks,
+        &indexer,
         max_memory.map(|x| x / 2),
     );
 
@@ -80,7 +78,7 @@ pub fn extract_word_docids<R: io::Read + io::Seek>(
     }
 
     Ok((
-        sorter_into_reader(word_docids_sorter, indexer)?,
+        sorter_into_reader(word_docids_sorter, indexer.clone())?,
         sorter_into_reader(exact_word_docids_sorter, indexer)?,
     ))
 }
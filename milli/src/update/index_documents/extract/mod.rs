@@ -1,74 +1,156 @@
+mod extract_docid_fid_word_count;
 mod extract_docid_word_positions;
 mod extract_facet_number_docids;
 mod extract_facet_string_docids;
 mod extract_fid_docid_facet_values;
 mod extract_fid_word_count_docids;
 mod extract_geo_points;
+mod extract_vectors;
 mod extract_word_docids;
 mod extract_word_pair_proximity_docids;
 mod extract_word_position_docids;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use crossbeam_channel::Sender;
 use log::debug;
 use rayon::prelude::*;
+use tempfile::TempDir;
 
+use self::extract_docid_fid_word_count::extract_docid_fid_word_count;
 use self::extract_docid_word_positions::extract_docid_word_positions;
+pub(crate) use self::extract_docid_word_positions::{json_to_string, process_tokens};
 use self::extract_facet_number_docids::extract_facet_number_docids;
 use self::extract_facet_string_docids::extract_facet_string_docids;
 use self::extract_fid_docid_facet_values::extract_fid_docid_facet_values;
 use self::extract_fid_word_count_docids::extract_fid_word_count_docids;
 use self::extract_geo_points::extract_geo_points;
+pub(crate) use self::extract_geo_points::GeoFieldsIds;
+use self::extract_vectors::extract_vectors;
 use self::extract_word_docids::extract_word_docids;
 use self::extract_word_pair_proximity_docids::extract_word_pair_proximity_docids;
 use self::extract_word_position_docids::extract_word_position_docids;
 use super::helpers::{
-    as_cloneable_grenad, keep_first_prefix_value_merge_roaring_bitmaps, merge_cbo_roaring_bitmaps,
-    merge_roaring_bitmaps, CursorClonableMmap, GrenadParameters, MergeFn, MergeableReader,
+    as_cloneable_grenad, keep_first, keep_first_prefix_value_merge_roaring_bitmaps,
+    merge_cbo_roaring_bitmaps, merge_roaring_bitmaps, CursorClonableMmap, GrenadParameters,
+    MergeFn, MergeableReader,
 };
 use super::{helpers, TypedChunk};
-use crate::{FieldId, Result};
+use crate::error::InternalError;
+use crate::facet::NumberCoercion;
+use crate::{FieldId, NormalizerOptions, Result, WordBoundaries};
+
+/// Stops the extraction tasks of an update and removes its temporary files when dropped.
+///
+/// The extraction tasks are spawned in the background and keep creating temporary files
+/// until they are done, even if the update that received their chunks already failed. The
+/// update holds this guard to stop them as soon as it returns: the remaining chunks are not
+/// extracted and the directory in which the update creates its temporary files is removed,
+/// the tasks still running can't create new ones.
+pub(crate) struct ExtractionGuard {
+    stopped: Arc<AtomicBool>,
+    temp_dir: TempDir,
+}
+
+impl ExtractionGuard {
+    /// Creates the temporary directory of the update in `parent`, or in the default one.
+    pub(crate) fn new(parent: Option<&Path>) -> Result<ExtractionGuard> {
+        let temp_dir = match parent {
+            Some(parent) => tempfile::tempdir_in(parent)?,
+            None => tempfile::tempdir()?,
+        };
+        Ok(ExtractionGuard { stopped: Arc::default(), temp_dir })
+    }
+
+    /// The directory in which the temporary files of the update must be created.
+    pub(crate) fn temp_dir(&self) -> &Path {
+        self.temp_dir.path()
+    }
+
+    fn stopped(&self) -> Arc<AtomicBool> {
+        self.stopped.clone()
+    }
+}
+
+impl Drop for ExtractionGuard {
+    fn drop(&mut self) {
+        // The temporary directory is removed right after.
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
 
 /// Extract data for each databases from obkv documents in parallel.
-/// Send data in grenad file over provided Sender.
+/// Send data in grenad file over provided Sender, with the index of the documents chunk
+/// it is extracted from. The data merged from all the documents chunks has the index 0.
 pub(crate) fn data_from_obkv_documents(
     original_obkv_chunks: impl Iterator<Item = Result<grenad::Reader<File>>> + Send,
     flattened_obkv_chunks: impl Iterator<Item = Result<grenad::Reader<File>>> + Send,
     indexer: GrenadParameters,
-    lmdb_writer_sx: Sender<Result<TypedChunk>>,
+    lmdb_writer_sx: Sender<Result<(usize, TypedChunk)>>,
+    guard: &ExtractionGuard,
     searchable_fields: Option<HashSet<FieldId>>,
     faceted_fields: HashSet<FieldId>,
+    number_coercions: HashMap<FieldId, NumberCoercion>,
     primary_key_id: FieldId,
-    geo_fields_ids: Option<(FieldId, FieldId)>,
+    geo_fields_ids: Option<GeoFieldsIds>,
+    content_hash: Option<(FieldId, HashSet<FieldId>)>,
+    vectors_fields_ids: HashSet<FieldId>,
     stop_words: Option<fst::Set<&[u8]>>,
     max_positions_per_attributes: Option<u32>,
+    max_positions_per_attributes_overrides: HashMap<FieldId, u32>,
+    attributes_positions_limits: HashMap<FieldId, u32>,
+    word_position_precision: NonZeroU32,
+    word_boundaries: WordBoundaries,
+    normalizer_options: NormalizerOptions,
     exact_attributes: HashSet<FieldId>,
+    reproducible: bool,
 ) -> Result<()> {
-    original_obkv_chunks
-        .par_bridge()
-        .map(|original_documents_chunk| {
-            send_original_documents_data(original_documents_chunk, lmdb_writer_sx.clone())
-        })
-        .collect::<Result<()>>()?;
-
-    let result: Result<(Vec<_>, (Vec<_>, Vec<_>))> = flattened_obkv_chunks
-        .par_bridge()
-        .map(|flattened_obkv_chunks| {
-            send_and_extract_flattened_documents_data(
-                flattened_obkv_chunks,
-                indexer,
-                lmdb_writer_sx.clone(),
-                &searchable_fields,
-                &faceted_fields,
-                primary_key_id,
-                geo_fields_ids,
-                &stop_words,
-                max_positions_per_attributes,
-            )
-        })
-        .collect();
+    let send_original = |(chunk_index, original_documents_chunk)| {
+        send_original_documents_data(chunk_index, original_documents_chunk, lmdb_writer_sx.clone())
+    };
+
+    if reproducible {
+        let original_obkv_chunks: Vec<_> = original_obkv_chunks.enumerate().collect();
+        original_obkv_chunks.into_par_iter().map(send_original).collect::<Result<()>>()?;
+    } else {
+        original_obkv_chunks.enumerate().par_bridge().map(send_original).collect::<Result<()>>()?;
+    }
+
+    let send_and_extract_flattened = |(chunk_index, flattened_obkv_chunks)| {
+        send_and_extract_flattened_documents_data(
+            chunk_index,
+            flattened_obkv_chunks,
+            indexer.clone(),
+            lmdb_writer_sx.clone(),
+            &searchable_fields,
+            &faceted_fields,
+            &number_coercions,
+            primary_key_id,
+            geo_fields_ids,
+            &content_hash,
+            &vectors_fields_ids,
+            &stop_words,
+            max_positions_per_attributes,
+            &max_positions_per_attributes_overrides,
+            &attributes_positions_limits,
+            word_boundaries,
+            normalizer_options,
+        )
+    };
+
+    // The order of the extracted chunks is the order in which they are merged, keeping it
+    // stable makes the merges, and therefore the written databases, reproducible.
+    let result: Result<(Vec<_>, (Vec<_>, Vec<_>))> = if reproducible {
+        let flattened_obkv_chunks: Vec<_> = flattened_obkv_chunks.enumerate().collect();
+        flattened_obkv_chunks.into_par_iter().map(send_and_extract_flattened).collect()
+    } else {
+        flattened_obkv_chunks.enumerate().par_bridge().map(send_and_extract_flattened).collect()
+    };
 
     let (
         docid_word_positions_chunks,
@@ -79,6 +161,7 @@ pub(crate) fn data_from_obkv_documents(
         docid_word_positions_chunks.clone(),
         indexer.clone(),
         lmdb_writer_sx.clone(),
+        guard.stopped(),
         extract_word_pair_proximity_docids,
         merge_cbo_roaring_bitmaps,
         TypedChunk::WordPairProximityDocids,
@@ -89,16 +172,29 @@ pub(crate) fn data_from_obkv_documents(
         docid_word_positions_chunks.clone(),
         indexer.clone(),
         lmdb_writer_sx.clone(),
+        guard.stopped(),
         extract_fid_word_count_docids,
         merge_cbo_roaring_bitmaps,
         TypedChunk::FieldIdWordcountDocids,
         "field-id-wordcount-docids",
     );
 
+    spawn_extraction_task::<_, _, Vec<grenad::Reader<File>>>(
+        docid_word_positions_chunks.clone(),
+        indexer.clone(),
+        lmdb_writer_sx.clone(),
+        guard.stopped(),
+        extract_docid_fid_word_count,
+        keep_first,
+        TypedChunk::DocidFieldIdWordcount,
+        "docid-field-id-wordcount",
+    );
+
     spawn_extraction_task::<_, _, Vec<(grenad::Reader<File>, grenad::Reader<File>)>>(
         docid_word_positions_chunks.clone(),
         indexer.clone(),
         lmdb_writer_sx.clone(),
+        guard.stopped(),
         move |doc_word_pos, indexer| extract_word_docids(doc_word_pos, indexer, &exact_attributes),
         merge_roaring_bitmaps,
         |(word_docids_reader, exact_word_docids_reader)| TypedChunk::WordDocids {
@@ -112,7 +208,10 @@ pub(crate) fn data_from_obkv_documents(
         docid_word_positions_chunks.clone(),
         indexer.clone(),
         lmdb_writer_sx.clone(),
-        extract_word_position_docids,
+        guard.stopped(),
+        move |doc_word_pos, indexer| {
+            extract_word_position_docids(doc_word_pos, indexer, word_position_precision)
+        },
         merge_cbo_roaring_bitmaps,
         TypedChunk::WordPositionDocids,
         "word-position-docids",
@@ -122,6 +221,7 @@ pub(crate) fn data_from_obkv_documents(
         docid_fid_facet_strings_chunks.clone(),
         indexer.clone(),
         lmdb_writer_sx.clone(),
+        guard.stopped(),
         extract_facet_string_docids,
         keep_first_prefix_value_merge_roaring_bitmaps,
         TypedChunk::FieldIdFacetStringDocids,
@@ -132,6 +232,7 @@ pub(crate) fn data_from_obkv_documents(
         docid_fid_facet_numbers_chunks.clone(),
         indexer.clone(),
         lmdb_writer_sx.clone(),
+        guard.stopped(),
         extract_facet_number_docids,
         merge_cbo_roaring_bitmaps,
         TypedChunk::FieldIdFacetNumberDocids,
@@ -148,7 +249,8 @@ pub(crate) fn data_from_obkv_documents(
 fn spawn_extraction_task<FE, FS, M>(
     chunks: Vec<grenad::Reader<CursorClonableMmap>>,
     indexer: GrenadParameters,
-    lmdb_writer_sx: Sender<Result<TypedChunk>>,
+    lmdb_writer_sx: Sender<Result<(usize, TypedChunk)>>,
+    stopped: Arc<AtomicBool>,
     extract_fn: FE,
     merge_fn: MergeFn,
     serialize_fn: FS,
@@ -163,13 +265,23 @@ fn spawn_extraction_task<FE, FS, M>(
     M::Output: Send,
 {
     rayon::spawn(move || {
-        let chunks: Result<M> =
-            chunks.into_par_iter().map(|chunk| extract_fn(chunk, indexer.clone())).collect();
+        let chunks: Result<M> = chunks
+            .into_par_iter()
+            .map(|chunk| {
+                if stopped.load(Ordering::Relaxed) {
+                    Err(InternalError::StoppedExtraction.into())
+                } else {
+                    extract_fn(chunk, indexer.clone())
+                }
+            })
+            .collect();
         rayon::spawn(move || match chunks {
+            // the chunks of a stopped update are dropped with their files
+            Ok(_) if stopped.load(Ordering::Relaxed) => (),
             Ok(chunks) => {
                 debug!("merge {} database", name);
                 let reader = chunks.merge(merge_fn, &indexer);
-                let _ = lmdb_writer_sx.send(reader.map(|r| serialize_fn(r)));
+                let _ = lmdb_writer_sx.send(reader.map(|r| (0, serialize_fn(r))));
             }
             Err(e) => {
                 let _ = lmdb_writer_sx.send(Err(e));
@@ -181,32 +293,46 @@ fn spawn_extraction_task<FE, FS, M>(
 /// Extract chunked data and send it into lmdb_writer_sx sender:
 /// - documents
 fn send_original_documents_data(
+    chunk_index: usize,
     original_documents_chunk: Result<grenad::Reader<File>>,
-    lmdb_writer_sx: Sender<Result<TypedChunk>>,
+    lmdb_writer_sx: Sender<Result<(usize, TypedChunk)>>,
 ) -> Result<()> {
     let original_documents_chunk =
         original_documents_chunk.and_then(|c| unsafe { as_cloneable_grenad(&c) })?;
 
     // TODO: create a custom internal error
-    lmdb_writer_sx.send(Ok(TypedChunk::Documents(original_documents_chunk))).unwrap();
+    lmdb_writer_sx
+        .send(Ok((chunk_index, TypedChunk::Documents(original_documents_chunk))))
+        .unwrap();
     Ok(())
 }
 
 /// Extract chunked data and send it into lmdb_writer_sx sender:
 /// - documents_ids
+/// - truncated_documents
 /// - docid_word_positions
+/// - vectors
 /// - docid_fid_facet_numbers
 /// - docid_fid_facet_strings
+/// - fid_facet_bool_docids
 fn send_and_extract_flattened_documents_data(
+    chunk_index: usize,
     flattened_documents_chunk: Result<grenad::Reader<File>>,
     indexer: GrenadParameters,
-    lmdb_writer_sx: Sender<Result<TypedChunk>>,
+    lmdb_writer_sx: Sender<Result<(usize, TypedChunk)>>,
     searchable_fields: &Option<HashSet<FieldId>>,
     faceted_fields: &HashSet<FieldId>,
+    number_coercions: &HashMap<FieldId, NumberCoercion>,
     primary_key_id: FieldId,
-    geo_fields_ids: Option<(FieldId, FieldId)>,
+    geo_fields_ids: Option<GeoFieldsIds>,
+    content_hash: &Option<(FieldId, HashSet<FieldId>)>,
+    vectors_fields_ids: &HashSet<FieldId>,
     stop_words: &Option<fst::Set<&[u8]>>,
     max_positions_per_attributes: Option<u32>,
+    max_positions_per_attributes_overrides: &HashMap<FieldId, u32>,
+    attributes_positions_limits: &HashMap<FieldId, u32>,
+    word_boundaries: WordBoundaries,
+    normalizer_options: NormalizerOptions,
 ) -> Result<(
     grenad::Reader<CursorClonableMmap>,
     (grenad::Reader<CursorClonableMmap>, grenad::Reader<CursorClonableMmap>),
@@ -217,11 +343,39 @@ fn send_and_extract_flattened_documents_data(
     if let Some(geo_fields_ids) = geo_fields_ids {
         let documents_chunk_cloned = flattened_documents_chunk.clone();
         let lmdb_writer_sx_cloned = lmdb_writer_sx.clone();
+        let indexer_cloned = indexer.clone();
         rayon::spawn(move || {
-            let result =
-                extract_geo_points(documents_chunk_cloned, indexer, primary_key_id, geo_fields_ids);
+            let result = extract_geo_points(
+                documents_chunk_cloned,
+                indexer_cloned,
+                primary_key_id,
+                geo_fields_ids,
+            );
             let _ = match result {
-                Ok(geo_points) => lmdb_writer_sx_cloned.send(Ok(TypedChunk::GeoPoints(geo_points))),
+                Ok(geo_points) => {
+                    lmdb_writer_sx_cloned.send(Ok((chunk_index, TypedChunk::GeoPoints(geo_points))))
+                }
+                Err(error) => lmdb_writer_sx_cloned.send(Err(error)),
+            };
+        });
+    }
+
+    if !vectors_fields_ids.is_empty() {
+        let documents_chunk_cloned = flattened_documents_chunk.clone();
+        let lmdb_writer_sx_cloned = lmdb_writer_sx.clone();
+        let vectors_fields_ids = vectors_fields_ids.clone();
+        let indexer_cloned = indexer.clone();
+        rayon::spawn(move || {
+            let result = extract_vectors(
+                documents_chunk_cloned,
+                indexer_cloned,
+                primary_key_id,
+                &vectors_fields_ids,
+            );
+            let _ = match result {
+                Ok(vectors) => {
+                    lmdb_writer_sx_cloned.send(Ok((chunk_index, TypedChunk::Vectors(vectors))))
+                }
                 Err(error) => lmdb_writer_sx_cloned.send(Err(error)),
             };
         });
@@ -230,47 +384,77 @@ fn send_and_extract_flattened_documents_data(
     let (docid_word_positions_chunk, docid_fid_facet_values_chunks): (Result<_>, Result<_>) =
         rayon::join(
             || {
-                let (documents_ids, docid_word_positions_chunk) = extract_docid_word_positions(
-                    flattened_documents_chunk.clone(),
-                    indexer.clone(),
-                    searchable_fields,
-                    stop_words.as_ref(),
-                    max_positions_per_attributes,
-                )?;
+                let (documents_ids, docid_word_positions_chunk, truncated_documents) =
+                    extract_docid_word_positions(
+                        flattened_documents_chunk.clone(),
+                        indexer.clone(),
+                        searchable_fields,
+                        stop_words.as_ref(),
+                        primary_key_id,
+                        max_positions_per_attributes,
+                        max_positions_per_attributes_overrides,
+                        attributes_positions_limits,
+                        word_boundaries,
+                        normalizer_options,
+                    )?;
 
                 // send documents_ids to DB writer
-                let _ = lmdb_writer_sx.send(Ok(TypedChunk::NewDocumentsIds(documents_ids)));
+                let _ = lmdb_writer_sx
+                    .send(Ok((chunk_index, TypedChunk::NewDocumentsIds(documents_ids))));
+
+                // send the truncated documents to be reported
+                if !truncated_documents.is_empty() {
+                    let _ = lmdb_writer_sx.send(Ok((
+                        chunk_index,
+                        TypedChunk::TruncatedDocuments(truncated_documents),
+                    )));
+                }
 
                 // send docid_word_positions_chunk to DB writer
                 let docid_word_positions_chunk =
                     unsafe { as_cloneable_grenad(&docid_word_positions_chunk)? };
-                let _ = lmdb_writer_sx
-                    .send(Ok(TypedChunk::DocidWordPositions(docid_word_positions_chunk.clone())));
+                let _ = lmdb_writer_sx.send(Ok((
+                    chunk_index,
+                    TypedChunk::DocidWordPositions(docid_word_positions_chunk.clone()),
+                )));
 
                 Ok(docid_word_positions_chunk)
             },
             || {
-                let (docid_fid_facet_numbers_chunk, docid_fid_facet_strings_chunk) =
-                    extract_fid_docid_facet_values(
-                        flattened_documents_chunk.clone(),
-                        indexer.clone(),
-                        faceted_fields,
-                    )?;
+                let (
+                    docid_fid_facet_numbers_chunk,
+                    docid_fid_facet_strings_chunk,
+                    fid_facet_bool_docids_chunk,
+                ) = extract_fid_docid_facet_values(
+                    flattened_documents_chunk.clone(),
+                    indexer.clone(),
+                    faceted_fields,
+                    number_coercions,
+                    content_hash,
+                )?;
 
                 // send docid_fid_facet_numbers_chunk to DB writer
                 let docid_fid_facet_numbers_chunk =
                     unsafe { as_cloneable_grenad(&docid_fid_facet_numbers_chunk)? };
 
-                let _ = lmdb_writer_sx.send(Ok(TypedChunk::FieldIdDocidFacetNumbers(
-                    docid_fid_facet_numbers_chunk.clone(),
+                let _ = lmdb_writer_sx.send(Ok((
+                    chunk_index,
+                    TypedChunk::FieldIdDocidFacetNumbers(docid_fid_facet_numbers_chunk.clone()),
                 )));
 
                 // send docid_fid_facet_strings_chunk to DB writer
                 let docid_fid_facet_strings_chunk =
                     unsafe { as_cloneable_grenad(&docid_fid_facet_strings_chunk)? };
 
-                let _ = lmdb_writer_sx.send(Ok(TypedChunk::FieldIdDocidFacetStrings(
-                    docid_fid_facet_strings_chunk.clone(),
+                let _ = lmdb_writer_sx.send(Ok((
+                    chunk_index,
+                    TypedChunk::FieldIdDocidFacetStrings(docid_fid_facet_strings_chunk.clone()),
+                )));
+
+                // send fid_facet_bool_docids_chunk to DB writer
+                let _ = lmdb_writer_sx.send(Ok((
+                    chunk_index,
+                    TypedChunk::FieldIdFacetBoolDocids(fid_facet_bool_docids_chunk),
                 )));
 
                 Ok((docid_fid_facet_numbers_chunk, docid_fid_facet_strings_chunk))
@@ -279,3 +463,54 @@ fn send_and_extract_flattened_documents_data(
 
     Ok((docid_word_positions_chunk?, docid_fid_facet_values_chunks?))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+    use crate::update::index_documents::helpers::{create_writer, writer_into_reader};
+    use crate::Error;
+
+    #[test]
+    fn stop_the_extraction_tasks_of_a_returned_update() {
+        let guard = ExtractionGuard::new(None).unwrap();
+        let temp_dir = guard.temp_dir().to_path_buf();
+        let indexer = GrenadParameters { temp_dir: Some(temp_dir.clone()), ..Default::default() };
+
+        let mut writer = create_writer(
+            indexer.chunk_compression_type,
+            indexer.chunk_compression_level,
+            indexer.tempfile().unwrap(),
+        );
+        writer.insert(b"hello", b"world").unwrap();
+        let reader = writer_into_reader(writer).unwrap();
+        let chunk = unsafe { as_cloneable_grenad(&reader) }.unwrap();
+
+        // the update returns before the task extracts its chunk.
+        let stopped = guard.stopped();
+        drop(guard);
+        assert!(!temp_dir.exists());
+
+        let extractions = Arc::new(AtomicUsize::new(0));
+        let counter = extractions.clone();
+        let (lmdb_writer_sx, lmdb_writer_rx) = crossbeam_channel::unbounded();
+        spawn_extraction_task::<_, _, Vec<grenad::Reader<File>>>(
+            vec![chunk],
+            indexer,
+            lmdb_writer_sx,
+            stopped,
+            move |_, _| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                Err(InternalError::AbortedIndexation.into())
+            },
+            merge_cbo_roaring_bitmaps,
+            TypedChunk::FieldIdWordcountDocids,
+            "field-id-wordcount-docids",
+        );
+
+        let result = lmdb_writer_rx.recv().unwrap();
+        assert!(matches!(result, Err(Error::InternalError(InternalError::StoppedExtraction))));
+        assert_eq!(extractions.load(Ordering::Relaxed), 0);
+    }
+}
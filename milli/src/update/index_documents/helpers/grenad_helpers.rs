@@ -1,14 +1,18 @@
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::{self, Seek, SeekFrom};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
-use grenad::{CompressionType, Reader, Sorter};
+use grenad::{ChunkCreator, CompressionType, Reader, Sorter};
 use heed::types::ByteSlice;
 use log::debug;
 
 use super::{ClonableMmap, MergeFn};
 use crate::error::InternalError;
+use crate::update::index_documents::SorterSpills;
 use crate::Result;
 
 pub type CursorClonableMmap = io::Cursor<ClonableMmap>;
@@ -48,14 +52,37 @@ pub fn create_sorter(
     builder.build()
 }
 
-pub fn sorter_into_reader(
-    sorter: grenad::Sorter<MergeFn>,
+/// Creates a sorter that records the chunks it writes to disk in the spill metrics of the
+/// indexer, under the name of the extractor.
+pub fn create_extractor_sorter(
+    name: &'static str,
+    merge: MergeFn,
+    indexer: &GrenadParameters,
+    max_memory: Option<usize>,
+) -> grenad::Sorter<MergeFn, SpillCounter> {
+    let mut builder = grenad::Sorter::builder(merge);
+    builder.chunk_compression_type(indexer.chunk_compression_type);
+    if let Some(level) = indexer.chunk_compression_level {
+        builder.chunk_compression_level(level);
+    }
+    if let Some(nb_chunks) = indexer.max_nb_chunks {
+        builder.max_nb_chunks(nb_chunks);
+    }
+    if let Some(memory) = max_memory {
+        builder.dump_threshold(memory);
+        builder.allow_realloc(false);
+    }
+    builder.chunk_creator(indexer.spill_metrics.counter(name, indexer.temp_dir.clone())).build()
+}
+
+pub fn sorter_into_reader<CC: ChunkCreator>(
+    sorter: grenad::Sorter<MergeFn, CC>,
     indexer: GrenadParameters,
 ) -> Result<grenad::Reader<File>> {
     let mut writer = create_writer(
         indexer.chunk_compression_type,
         indexer.chunk_compression_level,
-        tempfile::tempfile()?,
+        indexer.tempfile()?,
     );
     sorter.write_into_stream_writer(&mut writer)?;
 
@@ -128,7 +155,7 @@ impl<R: io::Read + io::Seek> MergerBuilder<R> {
         let mut writer = create_writer(
             params.chunk_compression_type,
             params.chunk_compression_level,
-            tempfile::tempfile()?,
+            params.tempfile()?,
         );
         merger.write_into_stream_writer(&mut writer)?;
 
@@ -136,12 +163,15 @@ impl<R: io::Read + io::Seek> MergerBuilder<R> {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct GrenadParameters {
     pub chunk_compression_type: CompressionType,
     pub chunk_compression_level: Option<u32>,
     pub max_memory: Option<usize>,
     pub max_nb_chunks: Option<usize>,
+    pub spill_metrics: SpillMetrics,
+    /// The directory of the temporary files of the update, removed when the update returns.
+    pub temp_dir: Option<PathBuf>,
 }
 
 impl Default for GrenadParameters {
@@ -151,11 +181,98 @@ impl Default for GrenadParameters {
             chunk_compression_level: None,
             max_memory: None,
             max_nb_chunks: None,
+            spill_metrics: SpillMetrics::default(),
+            temp_dir: None,
         }
     }
 }
 
+/// Collects, by extractor, the chunks written to disk by the sorters of an indexation.
+#[derive(Debug, Default, Clone)]
+pub struct SpillMetrics {
+    extractors: Arc<Mutex<BTreeMap<&'static str, SorterSpills>>>,
+}
+
+impl SpillMetrics {
+    /// Returns the chunk creator of a new sorter of the given extractor.
+    pub fn counter(&self, name: &'static str, temp_dir: Option<PathBuf>) -> SpillCounter {
+        self.record(name, |spills| spills.sorters += 1);
+        SpillCounter { name, metrics: self.clone(), temp_dir }
+    }
+
+    /// Returns the spills of every extractor.
+    pub fn report(&self) -> BTreeMap<String, SorterSpills> {
+        let extractors = self.extractors.lock().unwrap_or_else(|e| e.into_inner());
+        extractors.iter().map(|(name, spills)| (name.to_string(), *spills)).collect()
+    }
+
+    fn record(&self, name: &'static str, f: impl FnOnce(&mut SorterSpills)) {
+        let mut extractors = self.extractors.lock().unwrap_or_else(|e| e.into_inner());
+        f(extractors.entry(name).or_default());
+    }
+}
+
+/// A chunk creator that counts the chunks, and their bytes, written by a sorter.
+pub struct SpillCounter {
+    name: &'static str,
+    metrics: SpillMetrics,
+    temp_dir: Option<PathBuf>,
+}
+
+impl ChunkCreator for SpillCounter {
+    type Chunk = SpillChunk;
+    type Error = io::Error;
+
+    fn create(&self) -> io::Result<SpillChunk> {
+        self.metrics.record(self.name, |spills| spills.spills += 1);
+        let file = match &self.temp_dir {
+            Some(temp_dir) => tempfile::tempfile_in(temp_dir)?,
+            None => tempfile::tempfile()?,
+        };
+        Ok(SpillChunk { file, name: self.name, metrics: self.metrics.clone() })
+    }
+}
+
+/// A temporary file that records the bytes written into it.
+pub struct SpillChunk {
+    file: File,
+    name: &'static str,
+    metrics: SpillMetrics,
+}
+
+impl Write for SpillChunk {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.metrics.record(self.name, |spills| spills.spilled_bytes += written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Read for SpillChunk {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for SpillChunk {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
 impl GrenadParameters {
+    /// Creates a temporary file in the directory of the update, if any.
+    pub fn tempfile(&self) -> io::Result<File> {
+        match &self.temp_dir {
+            Some(temp_dir) => tempfile::tempfile_in(temp_dir),
+            None => tempfile::tempfile(),
+        }
+    }
+
     /// This function use the number of threads in the current threadpool to compute the value.
     /// This should be called inside of a rayon thread pool,
     /// Otherwise, it will take the global number of threads.
@@ -188,7 +305,7 @@ pub fn grenad_obkv_into_chunks<R: io::Read + io::Seek>(
         let mut obkv_documents = create_writer(
             indexer_clone.chunk_compression_type,
             indexer_clone.chunk_compression_level,
-            tempfile::tempfile()?,
+            indexer_clone.tempfile()?,
         );
 
         while let Some((document_id, obkv)) = cursor.move_on_next()? {
@@ -3,46 +3,114 @@ mod helpers;
 mod transform;
 mod typed_chunk;
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::File;
 use std::io::{Cursor, Read, Seek};
-use std::iter::FromIterator;
 use std::num::{NonZeroU32, NonZeroUsize};
+use std::str;
 
 use crossbeam_channel::{Receiver, Sender};
-use heed::types::Str;
+use heed::types::{ByteSlice, DecodeIgnore, Str};
 use heed::Database;
 use log::debug;
 use roaring::RoaringBitmap;
 use serde::{Deserialize, Serialize};
 use slice_group_by::GroupBy;
+use time::OffsetDateTime;
 use typed_chunk::{write_typed_chunk_into_index, TypedChunk};
 
+pub(crate) use self::extract::{json_to_string, process_tokens};
+use self::extract::{ExtractionGuard, GeoFieldsIds};
+
 pub use self::helpers::{
     as_cloneable_grenad, create_sorter, create_writer, fst_stream_into_hashset,
     fst_stream_into_vec, merge_cbo_roaring_bitmaps, merge_roaring_bitmaps,
     sorter_into_lmdb_database, valid_lmdb_key, write_into_lmdb_database, writer_into_reader,
     ClonableMmap, MergeFn,
 };
-use self::helpers::{grenad_obkv_into_chunks, GrenadParameters};
+use self::helpers::{grenad_obkv_into_chunks, GrenadParameters, SpillMetrics};
 pub use self::transform::{Transform, TransformOutput};
 use crate::documents::DocumentBatchReader;
+use crate::error::InternalError;
 pub use crate::update::index_documents::helpers::CursorClonableMmap;
 use crate::update::{
-    self, Facets, IndexerConfig, UpdateIndexingStep, WordPrefixDocids,
-    WordPrefixPairProximityDocids, WordPrefixPositionDocids, WordsPrefixesFst,
+    self, facets, words_prefix_position_docids, words_prefixes_fst, Facets, IndexerConfig,
+    PrefixDatabases, UpdateIndexingStep, WordPrefixDocids, WordPrefixPairProximityDocids,
+    WordPrefixPositionDocids, WordsPrefixesFst, DEFAULT_MAX_VALUES_PER_WILDCARD_FACET,
+};
+use crate::{
+    is_array_position_field, is_faceted, is_vector_field, DocumentId, FieldId, Index, Result,
+    RoaringBitmapCodec, UserError, CONTENT_HASH_FIELD,
 };
-use crate::{Index, Result, RoaringBitmapCodec, UserError};
 
 static MERGED_DATABASE_COUNT: usize = 7;
 static PREFIX_DATABASE_COUNT: usize = 5;
 static TOTAL_POSTING_DATABASE_COUNT: usize = MERGED_DATABASE_COUNT + PREFIX_DATABASE_COUNT;
 
+/// The default size of the chunks of documents extracted in parallel, 4MiB.
+const DEFAULT_DOCUMENTS_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DocumentAdditionResult {
     /// The number of documents that were indexed during the update
     pub indexed_documents: u64,
     /// The total number of documents in the index after the update
     pub number_of_documents: u64,
+    /// The documents whose attributes were truncated by the `max_positions_per_attributes` cap
+    pub truncated_documents: Vec<TruncatedDocument>,
+    /// The chunks written to disk by the sorters of each extractor
+    pub sorter_spills: BTreeMap<String, SorterSpills>,
+    /// The parameters the update ran with
+    pub parameters: IndexingParameters,
+}
+
+/// The parameters an indexation ran with, once the defaults of the [`IndexDocumentsConfig`]
+/// and of the [`IndexerConfig`] are resolved and their values clamped, e.g. to audit an update
+/// after the fact.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexingParameters {
+    pub update_method: IndexDocumentsMethod,
+    pub autogenerate_docids: bool,
+    /// The size of the chunks of documents extracted in parallel, in bytes
+    pub documents_chunk_size: usize,
+    pub max_memory: Option<usize>,
+    pub max_nb_chunks: Option<usize>,
+    /// Whether the batch was small enough to be extracted in the current thread pool
+    /// and for its facet values to be added to the facet levels in place
+    pub small_batch: bool,
+    pub facet_level_group_size: usize,
+    pub facet_min_level_size: usize,
+    pub words_prefix_threshold: u32,
+    pub max_prefix_length: usize,
+    pub words_positions_level_group_size: u32,
+    pub words_positions_min_level_size: u32,
+    pub words_prefix_positions_limit: Option<u32>,
+    pub defer_prefix_databases: bool,
+}
+
+/// The chunks written to disk by the sorters of an extractor during an indexation.
+///
+/// A sorter writes its entries to disk every time they exceed its share of the `max_memory`
+/// and once more when it is finalized. An extractor that writes more chunks than it has
+/// sorters ran out of memory, a larger `max_memory` would avoid these writes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SorterSpills {
+    /// The number of sorters created by the extractor, one or two by documents chunk
+    pub sorters: u64,
+    /// The number of chunks written to disk by these sorters
+    pub spills: u64,
+    /// The total number of bytes of these chunks
+    pub spilled_bytes: u64,
+}
+
+/// A document of which some attributes were not fully indexed because
+/// they contained more words than the maximum number of positions allowed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TruncatedDocument {
+    /// The external id of the document
+    pub document_id: String,
+    /// The attributes that were truncated
+    pub attributes: Vec<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -70,7 +138,10 @@ pub struct IndexDocuments<'t, 'u, 'i, 'a, F> {
     indexer_config: &'a IndexerConfig,
     transform: Option<Transform<'a, 'i>>,
     progress: F,
+    should_abort: Option<Box<dyn Fn() -> bool + Sync + 'a>>,
     added_documents: u64,
+    /// The documents of a small batch, their facet values are added to the facet levels in place.
+    small_batch_documents_ids: Option<RoaringBitmap>,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -81,8 +152,14 @@ pub struct IndexDocumentsConfig {
     pub max_prefix_length: Option<usize>,
     pub words_positions_level_group_size: Option<NonZeroU32>,
     pub words_positions_min_level_size: Option<NonZeroU32>,
+    /// Only writes the word prefixes found in the first positions of each attribute into the
+    /// word prefix position database, it grows very large with long documents otherwise.
+    pub words_prefix_positions_limit: Option<u32>,
     pub update_method: IndexDocumentsMethod,
     pub autogenerate_docids: bool,
+    /// Marks the prefix databases as outdated instead of updating them, they must then be
+    /// rebuilt once with [`PrefixDatabases`](crate::update::PrefixDatabases).
+    pub defer_prefix_databases: bool,
 }
 
 impl<'t, 'u, 'i, 'a, F> IndexDocuments<'t, 'u, 'i, 'a, F>
@@ -109,12 +186,51 @@ where
             config,
             indexer_config,
             progress,
+            should_abort: None,
             wtxn,
             index,
             added_documents: 0,
+            small_batch_documents_ids: None,
         })
     }
 
+    /// Gives the new documents the internal ids of the given external ids, see
+    /// [`Transform::preserve_documents_ids`]. Must be called before any document is added.
+    pub fn preserve_documents_ids(
+        &mut self,
+        documents_ids: HashMap<String, DocumentId>,
+    ) -> Result<()> {
+        self.transform
+            .as_mut()
+            .expect("Invalid document addition state")
+            .preserve_documents_ids(documents_ids)
+    }
+
+    /// Stores the values of the hashed fields as they are, see [`Transform::keep_hashed_values`].
+    /// Must be called before any document is added.
+    pub(crate) fn keep_hashed_values(&mut self) {
+        self.transform.as_mut().expect("Invalid document addition state").keep_hashed_values();
+    }
+
+    /// Checks the callback between the extraction of the chunks of documents and between the
+    /// writes into the databases, the indexation stops with an
+    /// [`InternalError::AbortedIndexation`] once it returns `true`. The write transaction
+    /// then contains a partial update and must be aborted.
+    pub fn should_abort<A>(&mut self, should_abort: A) -> &mut Self
+    where
+        A: Fn() -> bool + Sync + 'a,
+    {
+        self.should_abort = Some(Box::new(should_abort));
+        self
+    }
+
+    fn check_abort(&self) -> Result<()> {
+        match &self.should_abort {
+            Some(should_abort) if should_abort() => Err(InternalError::AbortedIndexation.into()),
+            _ => Ok(()),
+        }
+    }
+
     /// Adds a batch of documents to the current builder.
     ///
     /// Since the documents are progressively added to the writer, a failure will cause a stale
@@ -146,8 +262,15 @@ where
     pub fn execute(mut self) -> Result<DocumentAdditionResult> {
         if self.added_documents == 0 {
             let number_of_documents = self.index.number_of_documents(self.wtxn)?;
-            return Ok(DocumentAdditionResult { indexed_documents: 0, number_of_documents });
+            return Ok(DocumentAdditionResult {
+                indexed_documents: 0,
+                number_of_documents,
+                truncated_documents: Vec::new(),
+                sorter_spills: BTreeMap::new(),
+                parameters: self.parameters(false),
+            });
         }
+        self.check_abort()?;
         let output = self
             .transform
             .take()
@@ -157,21 +280,37 @@ where
         let new_facets = output.compute_real_facets(self.wtxn, self.index)?;
         self.index.put_faceted_fields(self.wtxn, &new_facets)?;
 
-        let indexed_documents = output.documents_count as u64;
-        let number_of_documents = self.execute_raw(output)?;
-
-        Ok(DocumentAdditionResult { indexed_documents, number_of_documents })
+        self.execute_raw_with_report(output)
     }
 
     /// Returns the total number of documents in the index after the update.
-    #[logging_timer::time("IndexDocuments::{}")]
+    ///
+    /// The output can be produced by a [`Transform`] created by hand, e.g. one that read the
+    /// documents of a dump with [`Transform::preserve_documents_ids`] and was then turned into
+    /// an output with [`Transform::output_from_sorter`].
     pub fn execute_raw(self, output: TransformOutput) -> Result<u64>
     where
         F: Fn(UpdateIndexingStep) + Sync,
     {
+        self.execute_raw_with_report(output).map(|result| result.number_of_documents)
+    }
+
+    /// Same as [`IndexDocuments::execute_raw`] but returns the report of the update: the
+    /// number of indexed documents, the total number of documents in the index after the
+    /// update, the documents that were truncated and the parameters the update ran with.
+    #[logging_timer::time("IndexDocuments::{}")]
+    pub fn execute_raw_with_report(
+        mut self,
+        output: TransformOutput,
+    ) -> Result<DocumentAdditionResult>
+    where
+        F: Fn(UpdateIndexingStep) + Sync,
+    {
+        self.index.set_updated_at(self.wtxn, &OffsetDateTime::now_utc())?;
+
         let TransformOutput {
             primary_key,
-            fields_ids_map,
+            mut fields_ids_map,
             field_distribution,
             external_documents_ids,
             new_documents_ids,
@@ -181,60 +320,112 @@ where
             flattened_documents,
         } = output;
 
+        // The content hash of the documents is stored in a derived field, it must have an id
+        // before the fields_ids_map is stored.
+        let content_hash_fields = self.index.content_hash_fields(self.wtxn)?;
+        let content_hash = if content_hash_fields.is_empty() {
+            None
+        } else {
+            let hash_fid = fields_ids_map
+                .insert(CONTENT_HASH_FIELD)
+                .ok_or(UserError::AttributeLimitReached)?;
+            let hashed_fids = fields_ids_map
+                .iter()
+                .filter(|&(id, name)| id != hash_fid && is_faceted(name, &content_hash_fields))
+                .map(|(id, _)| id)
+                .collect();
+            Some((hash_fid, hashed_fids))
+        };
+
         // The fields_ids_map is put back to the store now so the rest of the transaction sees an
         // up to date field map.
         self.index.put_fields_ids_map(self.wtxn, &fields_ids_map)?;
 
-        let backup_pool;
+        let small_batch = self
+            .indexer_config
+            .small_batch_threshold
+            .map_or(false, |threshold| documents_count < threshold);
+        let parameters = self.parameters(small_batch);
+
+        let mut backup_pool = None;
         let pool = match self.indexer_config.thread_pool {
-            Some(ref pool) => pool,
+            Some(ref pool) => Some(pool),
+            // Tiny batches don't deserve a dedicated thread pool, they are extracted in the current one.
+            None if small_batch => None,
             #[cfg(not(test))]
             None => {
-                // We initialize a bakcup pool with the default
-                // settings if none have already been set.
-                backup_pool = rayon::ThreadPoolBuilder::new().build()?;
-                &backup_pool
+                // We initialize a bakcup pool with the default settings if none
+                // have already been set, it only lives for the extraction phase.
+                let num_threads = self.indexer_config.extraction_num_threads.unwrap_or(0);
+                let pool = rayon::ThreadPoolBuilder::new().num_threads(num_threads).build()?;
+                Some(&*backup_pool.insert(pool))
             }
             #[cfg(test)]
             None => {
                 // We initialize a bakcup pool with the default
                 // settings if none have already been set.
-                backup_pool = rayon::ThreadPoolBuilder::new().num_threads(1).build()?;
-                &backup_pool
+                let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build()?;
+                Some(&*backup_pool.insert(pool))
             }
         };
 
         let original_documents = grenad::Reader::new(original_documents)?;
         let flattened_documents = grenad::Reader::new(flattened_documents)?;
+        let reproducible = self.indexer_config.reproducible;
 
         // create LMDB writer channel
         let (lmdb_writer_sx, lmdb_writer_rx): (
-            Sender<Result<TypedChunk>>,
-            Receiver<Result<TypedChunk>>,
+            Sender<Result<(usize, TypedChunk)>>,
+            Receiver<Result<(usize, TypedChunk)>>,
         ) = crossbeam_channel::unbounded();
 
         // get the primary key field id
         let primary_key_id = fields_ids_map.id(&primary_key).unwrap();
 
+        // get the fids of the `_vectors` fields, they are stored as vectors and never as words.
+        let vectors_fields_ids: HashSet<_> = fields_ids_map
+            .iter()
+            .filter(|(_, name)| is_vector_field(name))
+            .map(|(id, _)| id)
+            .collect();
+
+        // get the fids of the fields derived from the positions in arrays, they are only faceted.
+        let positional_array_fields = self.index.positional_array_fields(self.wtxn)?;
+        let array_positions_fields_ids: HashSet<_> = fields_ids_map
+            .iter()
+            .filter(|(_, name)| is_array_position_field(name, &positional_array_fields))
+            .map(|(id, _)| id)
+            .collect();
+
         // get searchable fields for word databases
-        let searchable_fields =
-            self.index.searchable_fields_ids(self.wtxn)?.map(HashSet::from_iter);
+        let not_searchable = |id: &FieldId| {
+            vectors_fields_ids.contains(id) || array_positions_fields_ids.contains(id)
+        };
+        let searchable_fields = match self.index.searchable_fields_ids(self.wtxn)? {
+            Some(fields) => Some(fields.into_iter().filter(|id| !not_searchable(id)).collect()),
+            None if !vectors_fields_ids.is_empty() || !array_positions_fields_ids.is_empty() => {
+                Some(fields_ids_map.ids().filter(|id| !not_searchable(id)).collect())
+            }
+            None => None,
+        };
         // get filterable fields for facet databases
         let faceted_fields = self.index.faceted_fields_ids(self.wtxn)?;
-        // get the fid of the `_geo.lat` and `_geo.lng` fields.
+        // get the fid of the `_geo.lat` and `_geo.lng` fields and of the GeoJSON fields.
         let geo_fields_ids = match self.index.fields_ids_map(self.wtxn)?.id("_geo") {
             Some(gfid) => {
                 let is_sortable = self.index.sortable_fields_ids(self.wtxn)?.contains(&gfid);
                 let is_filterable = self.index.filterable_fields_ids(self.wtxn)?.contains(&gfid);
                 // if `_geo` is faceted then we get the `lat` and `lng`
                 if is_sortable || is_filterable {
-                    let field_ids = self
-                        .index
-                        .fields_ids_map(self.wtxn)?
-                        .insert("_geo.lat")
-                        .zip(self.index.fields_ids_map(self.wtxn)?.insert("_geo.lng"))
-                        .ok_or(UserError::AttributeLimitReached)?;
-                    Some(field_ids)
+                    let mut fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+                    let mut insert =
+                        |name| fields_ids_map.insert(name).ok_or(UserError::AttributeLimitReached);
+                    Some(GeoFieldsIds {
+                        lat: insert("_geo.lat")?,
+                        lng: insert("_geo.lng")?,
+                        geojson_type: insert("_geo.type")?,
+                        geojson_coordinates: insert("_geo.coordinates")?,
+                    })
                 } else {
                     None
                 }
@@ -244,29 +435,50 @@ where
 
         let stop_words = self.index.stop_words(self.wtxn)?;
         let exact_attributes = self.index.exact_attributes_ids(self.wtxn)?;
+        let attributes_positions_limits = self.index.attributes_positions_limits_ids(self.wtxn)?;
+        let word_position_precision = self.index.word_position_precision(self.wtxn)?;
+        let number_coercions = self.index.number_coercions_ids(self.wtxn)?;
+        let word_boundaries = self.index.word_boundaries(self.wtxn)?;
+        let normalizer_options = self.index.normalizer_options(self.wtxn)?;
+        let max_positions_per_attributes_overrides: HashMap<_, _> = self
+            .indexer_config
+            .max_positions_per_attributes_overrides
+            .iter()
+            .filter_map(|(name, max)| fields_ids_map.id(name).map(|id| (id, *max)))
+            .collect();
 
-        // Run extraction pipeline in parallel.
-        pool.install(|| {
+        // Run extraction pipeline in parallel, the background extraction tasks are stopped
+        // and the temporary files removed when this update returns, even with an error.
+        let extraction_guard = ExtractionGuard::new(self.indexer_config.temp_dir.as_deref())?;
+        let spill_metrics = SpillMetrics::default();
+        let extraction = || {
             let params = GrenadParameters {
                 chunk_compression_type: self.indexer_config.chunk_compression_type,
                 chunk_compression_level: self.indexer_config.chunk_compression_level,
                 max_memory: self.indexer_config.max_memory,
                 max_nb_chunks: self.indexer_config.max_nb_chunks, // default value, may be chosen.
+                spill_metrics: spill_metrics.clone(),
+                temp_dir: Some(extraction_guard.temp_dir().to_path_buf()),
             };
 
+            // the chunks are not extracted anymore once the indexation is aborted
+            let abortable = |chunk: Result<grenad::Reader<File>>| self.check_abort().and(chunk);
+
             // split obkv file into several chunks
             let original_chunk_iter = grenad_obkv_into_chunks(
                 original_documents,
                 params.clone(),
-                self.indexer_config.documents_chunk_size.unwrap_or(1024 * 1024 * 4), // 4MiB
-            );
+                parameters.documents_chunk_size,
+            )
+            .map(|chunks| chunks.map(abortable));
 
             // split obkv file into several chunks
             let flattened_chunk_iter = grenad_obkv_into_chunks(
                 flattened_documents,
                 params.clone(),
-                self.indexer_config.documents_chunk_size.unwrap_or(1024 * 1024 * 4), // 4MiB
-            );
+                parameters.documents_chunk_size,
+            )
+            .map(|chunks| chunks.map(abortable));
 
             let result = original_chunk_iter
                 .and_then(|original_chunk_iter| Ok((original_chunk_iter, flattened_chunk_iter?)))
@@ -277,13 +489,23 @@ where
                         flattened_chunk,
                         params,
                         lmdb_writer_sx.clone(),
+                        &extraction_guard,
                         searchable_fields,
                         faceted_fields,
+                        number_coercions,
                         primary_key_id,
                         geo_fields_ids,
+                        content_hash,
+                        vectors_fields_ids,
                         stop_words,
                         self.indexer_config.max_positions_per_attributes,
+                        max_positions_per_attributes_overrides,
+                        attributes_positions_limits,
+                        word_position_precision,
+                        word_boundaries,
+                        normalizer_options,
                         exact_attributes,
+                        reproducible,
                     )
                 });
 
@@ -293,7 +515,12 @@ where
 
             // needs to be droped to avoid channel waiting lock.
             drop(lmdb_writer_sx)
-        });
+        };
+
+        match pool {
+            Some(pool) => pool.install(extraction),
+            None => extraction(),
+        }
 
         // We delete the documents that this document addition replaces. This way we are
         // able to simply insert all the documents even if they already exist in the database.
@@ -312,6 +539,7 @@ where
         let mut word_position_docids = None;
         let mut word_docids = None;
         let mut exact_word_docids = None;
+        let mut truncated_documents = BTreeMap::new();
 
         let mut databases_seen = 0;
         (self.progress)(UpdateIndexingStep::MergeDataIntoFinalDatabase {
@@ -319,7 +547,23 @@ where
             total_databases: TOTAL_POSTING_DATABASE_COUNT,
         });
 
-        for result in lmdb_writer_rx {
+        // In reproducible mode the chunks are written in a stable order, whatever the order
+        // in which the extractors sent them: by kind and then by the documents chunk they are
+        // extracted from. The chunks are kept on disk, only their readers are waiting here.
+        let typed_chunks: Box<dyn Iterator<Item = Result<TypedChunk>>> = if reproducible {
+            let mut ordered = Vec::new();
+            for result in lmdb_writer_rx {
+                let (chunk_index, typed_chunk) = result?;
+                ordered.push(((typed_chunk.kind_order(), chunk_index), typed_chunk));
+            }
+            ordered.sort_unstable_by_key(|(key, _)| *key);
+            Box::new(ordered.into_iter().map(|(_, typed_chunk)| Ok(typed_chunk)))
+        } else {
+            Box::new(lmdb_writer_rx.into_iter().map(|result| result.map(|(_, chunk)| chunk)))
+        };
+
+        for result in typed_chunks {
+            self.check_abort()?;
             let typed_chunk = match result? {
                 TypedChunk::WordDocids { word_docids_reader, exact_word_docids_reader } => {
                     let cloneable_chunk = unsafe { as_cloneable_grenad(&word_docids_reader)? };
@@ -339,6 +583,16 @@ where
                     word_position_docids = Some(cloneable_chunk);
                     TypedChunk::WordPositionDocids(chunk)
                 }
+                TypedChunk::TruncatedDocuments(documents) => {
+                    for (document_id, fields_ids) in documents {
+                        let attributes = fields_ids
+                            .into_iter()
+                            .filter_map(|id| fields_ids_map.name(id).map(String::from))
+                            .collect();
+                        truncated_documents.insert(document_id, attributes);
+                    }
+                    continue;
+                }
                 otherwise => otherwise,
             };
 
@@ -365,6 +619,10 @@ where
             }
         }
 
+        // The extraction is over, the prefix databases are computed on the current
+        // thread and must not keep an idle backup pool alive.
+        drop(backup_pool);
+
         // We write the field distribution into the main database
         self.index.put_field_distribution(self.wtxn, &field_distribution)?;
 
@@ -374,9 +632,19 @@ where
         // We write the external documents ids into the main database.
         self.index.put_external_documents_ids(self.wtxn, &external_documents_ids)?;
 
+        // The facet levels of the whole index are not recomputed for a small batch, its facet
+        // values are added in place. The replaced documents are already removed from the levels.
+        if small_batch {
+            self.small_batch_documents_ids = Some(final_documents_ids.clone());
+        }
+
         let all_documents_ids = index_documents_ids | new_documents_ids | replaced_documents_ids;
         self.index.put_documents_ids(self.wtxn, &all_documents_ids)?;
 
+        if self.drop_high_cardinality_facets()? {
+            self.small_batch_documents_ids = None;
+        }
+
         self.execute_prefix_databases(
             word_docids,
             exact_word_docids,
@@ -384,12 +652,62 @@ where
             word_position_docids,
         )?;
 
-        Ok(all_documents_ids.len())
+        Ok(DocumentAdditionResult {
+            indexed_documents: documents_count as u64,
+            number_of_documents: all_documents_ids.len(),
+            truncated_documents: truncated_documents
+                .into_iter()
+                .map(|(document_id, attributes)| TruncatedDocument { document_id, attributes })
+                .collect(),
+            sorter_spills: spill_metrics.report(),
+            parameters,
+        })
+    }
+
+    /// Returns the parameters of this indexation, the builders of the
+    /// prefix databases clamp the values the same way.
+    fn parameters(&self, small_batch: bool) -> IndexingParameters {
+        let config = &self.config;
+        let indexer_config = self.indexer_config;
+        IndexingParameters {
+            update_method: config.update_method,
+            autogenerate_docids: config.autogenerate_docids,
+            documents_chunk_size: indexer_config
+                .documents_chunk_size
+                .unwrap_or(DEFAULT_DOCUMENTS_CHUNK_SIZE),
+            max_memory: indexer_config.max_memory,
+            max_nb_chunks: indexer_config.max_nb_chunks,
+            small_batch,
+            facet_level_group_size: config
+                .facet_level_group_size
+                .map_or(facets::DEFAULT_LEVEL_GROUP_SIZE, |size| size.get().max(2)),
+            facet_min_level_size: config
+                .facet_min_level_size
+                .map_or(facets::DEFAULT_MIN_LEVEL_SIZE, NonZeroUsize::get),
+            words_prefix_threshold: config
+                .words_prefix_threshold
+                .map_or(words_prefixes_fst::DEFAULT_THRESHOLD, |threshold| threshold.max(50)),
+            max_prefix_length: config
+                .max_prefix_length
+                .map_or(words_prefixes_fst::DEFAULT_MAX_PREFIX_LENGTH, |length| {
+                    length.min(25).max(1)
+                }),
+            words_positions_level_group_size: config
+                .words_positions_level_group_size
+                .map_or(words_prefix_position_docids::DEFAULT_LEVEL_GROUP_SIZE, |size| {
+                    size.get().max(2)
+                }),
+            words_positions_min_level_size: config
+                .words_positions_min_level_size
+                .map_or(words_prefix_position_docids::DEFAULT_MIN_LEVEL_SIZE, NonZeroU32::get),
+            words_prefix_positions_limit: config.words_prefix_positions_limit,
+            defer_prefix_databases: config.defer_prefix_databases,
+        }
     }
 
     #[logging_timer::time("IndexDocuments::{}")]
     pub fn execute_prefix_databases(
-        self,
+        mut self,
         word_docids: Option<grenad::Reader<CursorClonableMmap>>,
         exact_word_docids: Option<grenad::Reader<CursorClonableMmap>>,
         word_pair_proximity_docids: Option<grenad::Reader<CursorClonableMmap>>,
@@ -398,20 +716,21 @@ where
     where
         F: Fn(UpdateIndexingStep) + Sync,
     {
+        self.check_abort()?;
+
         // Merged databases are already been indexed, we start from this count;
         let mut databases_seen = MERGED_DATABASE_COUNT;
 
-        // Run the facets update operation.
-        let mut builder = Facets::new(self.wtxn, self.index);
-        builder.chunk_compression_type = self.indexer_config.chunk_compression_type;
-        builder.chunk_compression_level = self.indexer_config.chunk_compression_level;
-        if let Some(value) = self.config.facet_level_group_size {
-            builder.level_group_size(value);
-        }
-        if let Some(value) = self.config.facet_min_level_size {
-            builder.min_level_size(value);
+        // Run the facets update operation, the levels are only recomputed when
+        // the facet values of a small batch can't be added to them in place.
+        let in_place = match self.small_batch_documents_ids.take() {
+            Some(documents_ids) => self.facets_builder().execute_in_place(&documents_ids)?,
+            None => false,
+        };
+
+        if !in_place {
+            self.facets_builder().execute()?;
         }
-        builder.execute()?;
 
         databases_seen += 1;
         (self.progress)(UpdateIndexingStep::MergeDataIntoFinalDatabase {
@@ -419,9 +738,36 @@ where
             total_databases: TOTAL_POSTING_DATABASE_COUNT,
         });
 
+        if self.config.defer_prefix_databases {
+            self.index.put_pending_prefix_databases_update(self.wtxn)?;
+            return Ok(());
+        }
+
+        // The prefix databases are outdated, updating them incrementally is not enough.
+        if self.index.pending_prefix_databases_update(self.wtxn)? {
+            return PrefixDatabases::new(self.wtxn, self.index, self.indexer_config, self.config)
+                .execute();
+        }
+
         let previous_words_prefixes_fst =
             self.index.words_prefixes_fst(self.wtxn)?.map_data(|cow| cow.into_owned())?;
 
+        // The words added by this update are the ones of the word docids chunks, the removed
+        // ones are recorded by the documents deletions.
+        let mut changed_words = BTreeSet::new();
+        for reader in word_docids.iter().chain(&exact_word_docids) {
+            let mut cursor = reader.clone().into_cursor()?;
+            while let Some((word, _)) = cursor.move_on_next()? {
+                changed_words.insert(str::from_utf8(word)?.to_string());
+            }
+        }
+
+        // Counting the words of the prefixes of the changed words is only
+        // worth it when they are a small part of the words of the index.
+        let words_count = self.index.words_fst(self.wtxn)?.len();
+        let incremental =
+            !previous_words_prefixes_fst.is_empty() && changed_words.len() < words_count / 4;
+
         // Run the words prefixes update operation.
         let mut builder = WordsPrefixesFst::new(self.wtxn, self.index);
         if let Some(value) = self.config.words_prefix_threshold {
@@ -430,7 +776,12 @@ where
         if let Some(value) = self.config.max_prefix_length {
             builder.max_prefix_length(value);
         }
-        builder.execute()?;
+
+        if incremental {
+            builder.execute_incremental(&changed_words)?;
+        } else {
+            builder.execute()?;
+        }
 
         let current_prefix_fst = self.index.words_prefixes_fst(self.wtxn)?;
 
@@ -525,6 +876,9 @@ where
             if let Some(value) = self.config.words_positions_min_level_size {
                 builder.min_level_size(value);
             }
+            if let Some(value) = self.config.words_prefix_positions_limit {
+                builder.positions_limit(value);
+            }
             builder.execute(
                 word_position_docids,
                 &new_prefix_fst_words,
@@ -541,6 +895,92 @@ where
 
         Ok(())
     }
+
+    fn facets_builder(&mut self) -> Facets<'_, 'u, 'i> {
+        let mut builder = Facets::new(self.wtxn, self.index);
+        builder.chunk_compression_type = self.indexer_config.chunk_compression_type;
+        builder.chunk_compression_level = self.indexer_config.chunk_compression_level;
+        if let Some(value) = self.config.facet_level_group_size {
+            builder.level_group_size(value);
+        }
+        if let Some(value) = self.config.facet_min_level_size {
+            builder.min_level_size(value);
+        }
+        builder
+    }
+
+    /// Stops faceting the fields faceted by the wildcard that have more distinct values than
+    /// the `max_values_per_wildcard_facet` limit, returns `true` if any field was dropped.
+    fn drop_high_cardinality_facets(&mut self) -> Result<bool> {
+        let mut user_defined_facets = self.index.user_defined_faceted_fields(self.wtxn)?;
+        if !user_defined_facets.remove(crate::ALL_FIELDS_FACET) {
+            return Ok(false);
+        }
+
+        let max_values = self
+            .indexer_config
+            .max_values_per_wildcard_facet
+            .unwrap_or(DEFAULT_MAX_VALUES_PER_WILDCARD_FACET);
+        let fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+        let f64s = self.index.facet_id_f64_docids.remap_types::<ByteSlice, DecodeIgnore>();
+        let strings = self.index.facet_id_string_docids.remap_types::<ByteSlice, DecodeIgnore>();
+
+        let mut faceted_fields = self.index.faceted_fields(self.wtxn)?;
+        let mut high_cardinality_facets = self.index.high_cardinality_faceted_fields(self.wtxn)?;
+        let mut dropped_fields_ids = Vec::new();
+        for field in &faceted_fields {
+            // The fields explicitly faceted by the user and the content hash are always kept.
+            if is_faceted(field, &user_defined_facets) || field == CONTENT_HASH_FIELD {
+                continue;
+            }
+            let field_id = match fields_ids_map.id(field) {
+                Some(field_id) => field_id,
+                None => continue,
+            };
+
+            // The distinct values are the entries of the level 0.
+            let mut prefix = field_id.to_be_bytes().to_vec();
+            prefix.push(0);
+            let count = f64s.prefix_iter(self.wtxn, &prefix)?.count()
+                + strings.prefix_iter(self.wtxn, &prefix)?.count();
+            if count > max_values {
+                debug!("The field {} has {} values and is no longer faceted", field, count);
+                high_cardinality_facets.insert(field.clone());
+                dropped_fields_ids.push(field_id);
+            }
+        }
+
+        if dropped_fields_ids.is_empty() {
+            return Ok(false);
+        }
+
+        faceted_fields.retain(|field| !high_cardinality_facets.contains(field));
+        self.index.put_faceted_fields(self.wtxn, &faceted_fields)?;
+        self.index.put_high_cardinality_faceted_fields(self.wtxn, &high_cardinality_facets)?;
+
+        let empty = RoaringBitmap::new();
+        for field_id in dropped_fields_ids {
+            let prefix = field_id.to_be_bytes();
+            for db in [
+                f64s,
+                strings,
+                self.index.field_id_docid_facet_f64s.remap_types::<ByteSlice, DecodeIgnore>(),
+                self.index.field_id_docid_facet_strings.remap_types::<ByteSlice, DecodeIgnore>(),
+            ] {
+                let mut iter = db.prefix_iter_mut(self.wtxn, &prefix)?;
+                while iter.next().transpose()?.is_some() {
+                    // safety: we don't keep references from inside the LMDB database.
+                    unsafe { iter.del_current()? };
+                }
+            }
+            self.index.put_number_faceted_documents_ids(self.wtxn, field_id, &empty)?;
+            self.index.put_string_faceted_documents_ids(self.wtxn, field_id, &empty)?;
+            self.index.put_boolean_faceted_documents_ids(self.wtxn, field_id, true, &empty)?;
+            self.index.put_boolean_faceted_documents_ids(self.wtxn, field_id, false, &empty)?;
+        }
+
+        Ok(true)
+    }
 }
 
 /// Run the word prefix docids update operation.
@@ -575,7 +1015,7 @@ mod tests {
 
     use big_s::S;
     use heed::EnvOpenOptions;
-    use maplit::hashset;
+    use maplit::{hashmap, hashset};
 
     use super::*;
     use crate::documents::DocumentBatchBuilder;
@@ -1135,6 +1575,186 @@ mod tests {
         );
     }
 
+    #[test]
+    fn index_geojson_points() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+
+        builder.set_filterable_fields(hashset!(S("_geo")));
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let documents = documents!([
+          { "id": 0, "_geo": { "type": "Point", "coordinates": [42, 31] } },
+          { "id": 1, "_geo": [
+              { "type": "Point", "coordinates": [2.35, 48.85] },
+              { "type": "Point", "coordinates": ["42", "31"] },
+          ] },
+          { "id": 2, "_geo": { "lat": 31, "lng": 42 } },
+          { "id": 3, "_geo": { "type": "Point", "coordinates": [2.35, 48.85] } },
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(documents).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.filter(crate::Filter::from_str("_geoRadius(31, 42, 0.000001)").unwrap().unwrap());
+        let crate::SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 1, 2]);
+
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.filter(crate::Filter::from_str("_geoRadius(48.85, 2.35, 10)").unwrap().unwrap());
+        let crate::SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![1, 3]);
+        drop(rtxn);
+
+        // A document with several points is only returned once when sorting by distance.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_sortable_fields(hashset!(S("_geo")));
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.sort_criteria(vec!["_geoPoint(48.85, 2.35):asc".parse().unwrap()]);
+        let crate::SearchResult { mut documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids.len(), 4);
+        documents_ids[..2].sort_unstable();
+        assert_eq!(&documents_ids[..2], &[1, 3]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let documents = documents!([
+          { "id": 4, "_geo": { "type": "LineString", "coordinates": [[42, 31], [43, 32]] } }
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(documents).unwrap();
+        let error = builder.execute().unwrap_err();
+        assert_eq!(
+            &error.to_string(),
+            r#"Could not parse the GeoJSON `_geo` field in the document with the id: `4`. Was expecting a `Point`, or an array of them, with `[longitude, latitude]` coordinates but instead got `["LineString"]`."#
+        );
+
+        let documents = documents!([
+          { "id": 5, "_geo": { "type": "Point", "coordinates": [42] } }
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(documents).unwrap();
+        let error = builder.execute().unwrap_err();
+        assert_eq!(
+            &error.to_string(),
+            r#"Could not parse the GeoJSON `_geo` field in the document with the id: `5`. Was expecting a `Point`, or an array of them, with `[longitude, latitude]` coordinates but instead got `42`."#
+        );
+    }
+
+    #[test]
+    fn reimport_documents_with_preserved_ids() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": "a", "title": "Pride and Prejudice" },
+            { "id": "b", "title": "Le Petit Prince" },
+            { "id": "c", "title": "Alice In Wonderland" },
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.delete_external_id("a");
+        builder.execute().unwrap();
+
+        let documents_ids = index.external_documents_ids(&wtxn).unwrap().to_hash_map();
+        assert_eq!(documents_ids, hashmap! { S("b") => 1, S("c") => 2 });
+        update::ClearDocuments::new(&mut wtxn, &index).execute().unwrap();
+
+        // The dump lists the documents in another order and contains a new document.
+        let content = documents!([
+            { "id": "d", "title": "Hamlet" },
+            { "id": "c", "title": "Alice In Wonderland" },
+            { "id": "b", "title": "Le Petit Prince" },
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.preserve_documents_ids(documents_ids).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let external_documents_ids = index.external_documents_ids(&wtxn).unwrap();
+        assert_eq!(external_documents_ids.get("b"), Some(1));
+        assert_eq!(external_documents_ids.get("c"), Some(2));
+        assert_eq!(external_documents_ids.get("d"), Some(0));
+
+        // A preserved id that is used by another document is rejected.
+        let content = documents!([{ "id": "e", "title": "Ulysses" }]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.preserve_documents_ids(hashmap! { S("e") => 0 }).unwrap();
+        let error = builder.add_documents(content).unwrap_err();
+        assert!(matches!(
+            error,
+            crate::Error::UserError(UserError::InternalDocumentIdAlreadyUsed {
+                internal_id: 0,
+                ..
+            })
+        ));
+    }
+
     #[test]
     fn delete_documents_then_insert() {
         let path = tempfile::tempdir().unwrap();
@@ -1276,7 +1896,7 @@ mod tests {
     }
 
     #[test]
-    fn index_documents_with_zeroes() {
+    fn report_documents_truncated_by_max_positions() {
         let path = tempfile::tempdir().unwrap();
         let mut options = EnvOpenOptions::new();
         options.map_size(10 * 1024 * 1024); // 10 MB
@@ -1284,60 +1904,283 @@ mod tests {
 
         let mut wtxn = index.write_txn().unwrap();
         let content = documents!([
-            {
-                "id": 2,
-                "title": "Prideand Prejudice",
-                "au{hor": "Jane Austin",
-                "genre": "romance",
-                "price$": "3.5$",
-            },
-            {
-                "id": 456,
-                "title": "Le Petit Prince",
-                "au{hor": "Antoine de Saint-Exupéry",
-                "genre": "adventure",
-                "price$": "10.0$",
-            },
-            {
-                "id": 1,
-                "title": "Wonderland",
-                "au{hor": "Lewis Carroll",
-                "genre": "fantasy",
-                "price$": "25.99$",
-            },
-            {
-                "id": 4,
-                "title": "Harry Potter ing fantasy\0lood Prince",
-                "au{hor": "J. K. Rowling",
-                "genre": "fantasy\0",
-            },
+            { "id": 1, "title": "one two three four five", "text": "one two three four five" },
+            { "id": 2, "title": "one two", "text": "one two" }
         ]);
 
-        let config = IndexerConfig::default();
+        let mut config = IndexerConfig::default();
+        config.max_positions_per_attributes = Some(3);
+        config.max_positions_per_attributes_overrides.insert(S("title"), 10);
         let indexing_config = IndexDocumentsConfig::default();
         let mut builder =
-            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ())
-                .unwrap();
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
         builder.add_documents(content).unwrap();
-        builder.execute().unwrap();
-
+        let result = builder.execute().unwrap();
         wtxn.commit().unwrap();
+
+        assert_eq!(
+            result.truncated_documents,
+            vec![TruncatedDocument { document_id: S("1"), attributes: vec![S("text")] }]
+        );
+
+        let rtxn = index.read_txn().unwrap();
+        let five = index.word_docids.get(&rtxn, "five").unwrap().unwrap();
+        assert_eq!(five.iter().collect::<Vec<_>>(), vec![0]);
+        let four = index.word_docids.get(&rtxn, "four").unwrap().unwrap();
+        assert_eq!(four.iter().collect::<Vec<_>>(), vec![0]);
     }
 
     #[test]
-    fn index_documents_with_nested_fields() {
+    fn store_documents_fields_word_counts() {
         let path = tempfile::tempdir().unwrap();
         let mut options = EnvOpenOptions::new();
         options.map_size(10 * 1024 * 1024); // 10 MB
         let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
 
         let mut wtxn = index.write_txn().unwrap();
         let content = documents!([
-            {
-                "id": 0,
-                "title": "The zeroth document",
-            },
-            {
+            { "id": 1, "title": "the little prince", "text": "a story. about a prince" },
+            { "id": 2, "title": "hamlet" }
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let title = fields_ids_map.id("title").unwrap();
+        let text = fields_ids_map.id("text").unwrap();
+        assert_eq!(index.document_field_word_count(&rtxn, 0, title).unwrap(), Some(3));
+        // The hard separator must not be counted as words.
+        assert_eq!(index.document_field_word_count(&rtxn, 0, text).unwrap(), Some(5));
+        assert_eq!(index.document_field_word_count(&rtxn, 1, title).unwrap(), Some(1));
+        assert_eq!(index.document_field_word_count(&rtxn, 1, text).unwrap(), None);
+        drop(rtxn);
+
+        // Replacing a document replaces its word counts.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "id": 1, "title": "the prince" }]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.delete_external_id("2");
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let word_counts = index.document_word_counts(&rtxn, 0).unwrap();
+        assert_eq!(word_counts.get(&title), Some(&2));
+        assert_eq!(word_counts.get(&text), None);
+        assert!(index.document_word_counts(&rtxn, 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn store_documents_vectors() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "title": "the little prince", "_vectors": { "image": [0.5, -1, 2] } },
+            { "id": 2, "title": "hamlet", "_vectors": { "image": null, "text": [42] } }
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let vectors = index.document_vectors(&rtxn, 0).unwrap();
+        assert_eq!(vectors.len(), 1);
+        assert_eq!(vectors["_vectors.image"], vec![0.5, -1.0, 2.0]);
+        let vectors = index.document_vectors(&rtxn, 1).unwrap();
+        assert_eq!(vectors.len(), 1);
+        assert_eq!(vectors["_vectors.text"], vec![42.0]);
+
+        // The numbers of the vectors must not be indexed as words.
+        assert!(index.word_docids.get(&rtxn, "42").unwrap().is_none());
+
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.query("hamlet").retrieve_vectors(true);
+        let crate::SearchResult { documents_ids, documents_vectors, .. } =
+            search.execute().unwrap();
+        assert_eq!(documents_ids, vec![1]);
+        assert_eq!(documents_vectors, vec![vectors]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.delete_external_id("2");
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.document_vectors(&rtxn, 1).unwrap().is_empty());
+        drop(rtxn);
+
+        // A vector that is not an array of numbers is rejected.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "id": 3, "_vectors": { "image": ["hello"] } }]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        let error = builder.execute().unwrap_err();
+        assert_eq!(
+            &error.to_string(),
+            r#"Could not parse the vector in the document with the id: `3`. Was expecting an array of numbers but instead got `["hello"]`."#
+        );
+    }
+
+    #[test]
+    fn index_small_batches() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut config = IndexerConfig::default();
+        config.small_batch_threshold = Some(10);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset!(S("age")));
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let batches = vec![
+            documents!([{ "id": 1, "name": "kevin", "age": 20 }, { "id": 2, "name": "bob" }]),
+            documents!([{ "id": 3, "name": "kevina" }]),
+            documents!([{ "id": 4, "name": "benoit", "age": 30 }]),
+            documents!([{ "id": 5, "name": "john", "age": 40 }, { "id": 4, "name": "benoit" }]),
+        ];
+        for content in batches {
+            let mut wtxn = index.write_txn().unwrap();
+            let mut builder = IndexDocuments::new(
+                &mut wtxn,
+                &index,
+                &config,
+                IndexDocumentsConfig::default(),
+                |_| (),
+            )
+            .unwrap();
+            builder.add_documents(content).unwrap();
+            builder.execute().unwrap();
+            wtxn.commit().unwrap();
+        }
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 5);
+
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.query("kevin");
+        let crate::SearchResult { mut documents_ids, .. } = search.execute().unwrap();
+        documents_ids.sort_unstable();
+        assert_eq!(documents_ids, vec![0, 2]);
+
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.filter(crate::Filter::from_str("age > 10").unwrap().unwrap());
+        let crate::SearchResult { mut documents_ids, .. } = search.execute().unwrap();
+        documents_ids.sort_unstable();
+        assert_eq!(documents_ids, vec![0, 4]);
+    }
+
+    #[test]
+    fn index_documents_with_zeroes() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            {
+                "id": 2,
+                "title": "Prideand Prejudice",
+                "au{hor": "Jane Austin",
+                "genre": "romance",
+                "price$": "3.5$",
+            },
+            {
+                "id": 456,
+                "title": "Le Petit Prince",
+                "au{hor": "Antoine de Saint-Exupéry",
+                "genre": "adventure",
+                "price$": "10.0$",
+            },
+            {
+                "id": 1,
+                "title": "Wonderland",
+                "au{hor": "Lewis Carroll",
+                "genre": "fantasy",
+                "price$": "25.99$",
+            },
+            {
+                "id": 4,
+                "title": "Harry Potter ing fantasy\0lood Prince",
+                "au{hor": "J. K. Rowling",
+                "genre": "fantasy\0",
+            },
+        ]);
+
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn index_documents_with_nested_fields() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            {
+                "id": 0,
+                "title": "The zeroth document",
+            },
+            {
                 "id": 1,
                 "title": "The first document",
                 "nested": {
@@ -1832,4 +2675,713 @@ mod tests {
 
         wtxn.commit().unwrap();
     }
+
+    #[test]
+    fn index_positions_in_arrays() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("authors"), S("editors"), S("book.authors") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 1, "authors": ["Hugo", "Dumas"], "editors": [{ "name": "Hetzel" }] },
+            { "id": 2, "authors": ["Dumas", "Maquet"], "editors": [{ "name": "Lévy" }, { "name": "Hetzel" }] },
+            { "id": 3, "authors": "Dumas" },
+            { "id": 4, "book": { "authors": ["Verne"] },
+              "authors": ["A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L"] }
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // The positions are not indexed by default.
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.fields_ids_map(&rtxn).unwrap().id("authors[0]"), None);
+        drop(rtxn);
+
+        // Setting the positional array fields reindexes the documents.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_positional_array_fields(
+            hashset! { S("authors"), S("editors"), S("book.authors") },
+        );
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let filter_docids = |filter: &str| {
+            let mut search = crate::Search::new(&rtxn, &index);
+            search.filter(crate::Filter::from_str(filter).unwrap().unwrap());
+            search.execute().unwrap().documents_ids
+        };
+        assert_eq!(filter_docids("authors[0] = Dumas"), vec![1]);
+        assert_eq!(filter_docids("authors[1] = Dumas"), vec![0]);
+        assert_eq!(filter_docids("authors = Dumas"), vec![0, 1, 2]);
+        assert_eq!(filter_docids("editors[0].name = Hetzel"), vec![0]);
+        assert_eq!(filter_docids("editors.name = Hetzel"), vec![0, 1]);
+        assert_eq!(filter_docids("book.authors[0] = Verne"), vec![3]);
+
+        // Only the first positions of the arrays create a field.
+        assert_eq!(filter_docids("authors[9] = J"), vec![3]);
+        assert_eq!(filter_docids("authors = L"), vec![3]);
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        assert_eq!(fields_ids_map.id("authors[10]"), None);
+
+        // The positions are only faceted, not searchable.
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.query("maquet");
+        assert_eq!(search.execute().unwrap().documents_ids, vec![1]);
+        let authors_position = fields_ids_map.id("authors[1]").unwrap();
+        let positions = index.docid_word_positions.get(&rtxn, &(1, "maquet")).unwrap().unwrap();
+        let fields_ids: Vec<_> =
+            positions.iter().map(|pos| crate::relative_from_absolute_position(pos).0).collect();
+        assert!(!fields_ids.contains(&authors_position));
+
+        // The documents are returned untouched.
+        let (_, document) = index.documents(&rtxn, Some(0)).unwrap().pop().unwrap();
+        assert_eq!(document.get(authors_position), None);
+    }
+
+    #[test]
+    fn reproducible_indexing() {
+        fn dump(index: &Index) -> Vec<Vec<(Vec<u8>, Vec<u8>)>> {
+            let rtxn = index.read_txn().unwrap();
+            let databases = [
+                index.documents.remap_types::<ByteSlice, ByteSlice>(),
+                index.docid_word_positions.remap_types(),
+                index.word_docids.remap_types(),
+                index.word_pair_proximity_docids.remap_types(),
+                index.word_position_docids.remap_types(),
+                index.facet_id_string_docids.remap_types(),
+                index.field_id_docid_facet_strings.remap_types(),
+            ];
+            databases
+                .iter()
+                .map(|db| {
+                    db.iter(&rtxn)
+                        .unwrap()
+                        .map(|result| result.map(|(k, v)| (k.to_vec(), v.to_vec())).unwrap())
+                        .collect()
+                })
+                .collect()
+        }
+
+        let build_index = || {
+            let path = tempfile::tempdir().unwrap();
+            let mut options = EnvOpenOptions::new();
+            options.map_size(10 * 1024 * 1024); // 10 MB
+            let index = Index::new(options, &path).unwrap();
+
+            let thread_pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+            let config = IndexerConfig {
+                thread_pool: Some(thread_pool),
+                documents_chunk_size: Some(64),
+                reproducible: true,
+                ..Default::default()
+            };
+
+            let mut wtxn = index.write_txn().unwrap();
+            let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+            builder.set_filterable_fields(hashset!(S("genre")));
+            builder.execute(|_| ()).unwrap();
+
+            let content = documents!([
+                { "id": 1, "title": "the quick brown fox", "genre": "Fable" },
+                { "id": 2, "title": "jumps over the lazy dog", "genre": "fable" },
+                { "id": 3, "title": "a brown dog and a fox", "genre": "FABLE" },
+                { "id": 4, "title": "the lazy fox sleeps", "genre": "Tale" },
+                { "id": 5, "title": "quick quick dog", "genre": "tale" },
+                { "id": 6, "title": "over the brown hills", "genre": "TALE" }
+            ]);
+            let mut builder = IndexDocuments::new(
+                &mut wtxn,
+                &index,
+                &config,
+                IndexDocumentsConfig::default(),
+                |_| (),
+            )
+            .unwrap();
+            builder.add_documents(content).unwrap();
+            builder.execute().unwrap();
+            wtxn.commit().unwrap();
+
+            (path, index)
+        };
+
+        let (_first_path, first) = build_index();
+        let (_second_path, second) = build_index();
+        assert_eq!(dump(&first), dump(&second));
+    }
+
+    #[test]
+    fn append_unordered_chunks_into_empty_index() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        // small chunks are written in an arbitrary order, only the ones
+        // following the entries already written can be appended.
+        let thread_pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        let config = IndexerConfig {
+            thread_pool: Some(thread_pool),
+            documents_chunk_size: Some(64),
+            ..Default::default()
+        };
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset!(S("genre")));
+        builder.execute(|_| ()).unwrap();
+
+        let documents: Vec<_> = (0..100)
+            .map(
+                |i| serde_json::json!({ "id": i, "title": format!("title {}", i), "genre": i % 3 }),
+            )
+            .collect();
+        let mut cursor = Cursor::new(Vec::new());
+        let mut batch = DocumentBatchBuilder::new(&mut cursor).unwrap();
+        let documents = Cursor::new(serde_json::to_vec(&documents).unwrap());
+        batch.extend_from_json(documents).unwrap();
+        batch.finish().unwrap();
+        cursor.set_position(0);
+        let content = DocumentBatchReader::from_reader(cursor).unwrap();
+
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 100);
+        assert_eq!(index.documents.len(&rtxn).unwrap(), 100);
+        assert_eq!(index.field_id_docid_facet_f64s.len(&rtxn).unwrap(), 100);
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.filter(crate::Filter::from_str("genre = 1").unwrap().unwrap());
+        let crate::SearchResult { candidates, .. } = search.execute().unwrap();
+        assert_eq!(candidates.len(), 33);
+    }
+
+    #[test]
+    fn limit_the_word_prefix_positions() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        // the prefixes are only computed for the words shared by enough documents.
+        let documents: Vec<_> = (0..60)
+            .map(|i| serde_json::json!({ "id": i, "title": "hello a b c d help" }))
+            .collect();
+        let mut cursor = Cursor::new(Vec::new());
+        let mut batch = DocumentBatchBuilder::new(&mut cursor).unwrap();
+        let documents = Cursor::new(serde_json::to_vec(&documents).unwrap());
+        batch.extend_from_json(documents).unwrap();
+        batch.finish().unwrap();
+        cursor.set_position(0);
+        let content = DocumentBatchReader::from_reader(cursor).unwrap();
+
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig {
+            words_prefix_threshold: Some(50),
+            words_prefix_positions_limit: Some(3),
+            ..Default::default()
+        };
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let positions: Vec<_> = index
+            .word_prefix_position_docids
+            .iter(&rtxn)
+            .unwrap()
+            .map(|result| {
+                let ((prefix, position), _) = result.unwrap();
+                (prefix.to_string(), crate::relative_from_absolute_position(position).1)
+            })
+            .collect();
+        assert!(positions.contains(&(S("hel"), 0)));
+        assert!(positions.iter().all(|(_, position)| *position < 3));
+
+        // the words are still found at every position.
+        let mut words_positions = index.word_position_docids.iter(&rtxn).unwrap();
+        assert!(words_positions.any(|result| {
+            let ((word, position), _) = result.unwrap();
+            word == "help" && crate::relative_from_absolute_position(position).1 >= 3
+        }));
+    }
+
+    #[test]
+    fn report_the_sorter_spills() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "title": "hello world" },
+            { "id": 2, "title": "the quick brown fox" }
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        let result = builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        for name in
+            ["docid-word-positions", "word-docids", "exact-word-docids", "word-position-docids"]
+        {
+            let spills = result.sorter_spills.get(name).unwrap();
+            assert!(spills.sorters > 0, "{}: {:?}", name, spills);
+        }
+
+        // a document addition without documents doesn't extract anything.
+        let mut wtxn = index.write_txn().unwrap();
+        let builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        assert!(builder.execute().unwrap().sorter_spills.is_empty());
+    }
+
+    #[test]
+    fn incremental_words_prefixes_fst() {
+        // Generates `count` different words of 5 letters starting with `prefix`.
+        fn words(prefix: &str, count: usize) -> String {
+            let letter = |i: usize| (b'a' + (i % 26) as u8) as char;
+            let words: Vec<_> = (0..count)
+                .map(|i| format!("{}{}{}{}", prefix, letter(i / 676), letter(i / 26), letter(i)))
+                .collect();
+            words.join(" ")
+        }
+
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
+
+        let index_documents = |documents: DocumentBatchReader<Cursor<Vec<u8>>>| {
+            let mut wtxn = index.write_txn().unwrap();
+            let mut builder = IndexDocuments::new(
+                &mut wtxn,
+                &index,
+                &config,
+                IndexDocumentsConfig::default(),
+                |_| (),
+            )
+            .unwrap();
+            builder.add_documents(documents).unwrap();
+            builder.execute().unwrap();
+
+            // The incremental update must give the same prefixes as a full rebuild.
+            let prefixes = fst_stream_into_vec(&index.words_prefixes_fst(&wtxn).unwrap());
+            WordsPrefixesFst::new(&mut wtxn, &index).execute().unwrap();
+            let full_prefixes = fst_stream_into_vec(&index.words_prefixes_fst(&wtxn).unwrap());
+            assert_eq!(prefixes, full_prefixes);
+            wtxn.commit().unwrap();
+            full_prefixes
+        };
+
+        let prefixes = index_documents(documents!([
+            { "id": 1, "text": words("ba", 80) },
+            { "id": 2, "text": words("ca", 150) },
+            { "id": 3, "text": words("da", 1000) },
+        ]));
+        assert!(prefixes.contains(&S("ca")));
+        assert!(!prefixes.contains(&S("ba")));
+
+        // The new words make the `ba` prefix frequent enough.
+        let prefixes = index_documents(documents!([{ "id": 4, "text": words("bab", 40) }]));
+        assert!(prefixes.contains(&S("b")));
+        assert!(prefixes.contains(&S("ba")));
+
+        // The words of the replaced document are removed, `ca` isn't frequent anymore.
+        let prefixes = index_documents(documents!([{ "id": 2, "text": words("ea", 10) }]));
+        assert!(!prefixes.contains(&S("ca")));
+        assert!(prefixes.contains(&S("da")));
+
+        // The words of a plain deletion are removed too, `ba` isn't frequent anymore
+        // once the next addition updates the prefixes.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.delete_external_id("4");
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+        let prefixes = index_documents(documents!([{ "id": 5, "text": words("fa", 10) }]));
+        assert!(!prefixes.contains(&S("ba")));
+        assert!(prefixes.contains(&S("da")));
+
+        // The prefixes are entirely rebuilt with a different threshold.
+        let mut wtxn = index.write_txn().unwrap();
+        let indexing_config =
+            IndexDocumentsConfig { words_prefix_threshold: Some(500), ..Default::default() };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(documents!([{ "id": 6, "text": words("ga", 10) }])).unwrap();
+        builder.execute().unwrap();
+        let prefixes = fst_stream_into_vec(&index.words_prefixes_fst(&wtxn).unwrap());
+        let mut builder = WordsPrefixesFst::new(&mut wtxn, &index);
+        builder.threshold(500);
+        builder.execute().unwrap();
+        let full_prefixes = fst_stream_into_vec(&index.words_prefixes_fst(&wtxn).unwrap());
+        assert_eq!(prefixes, full_prefixes);
+        assert!(prefixes.contains(&S("da")));
+        assert!(!prefixes.contains(&S("b")));
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn abort_the_indexation() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use crate::Error;
+
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(documents!([{ "id": 1, "title": "hello world" }])).unwrap();
+        builder.should_abort(|| false);
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // the indexation is aborted before, during and after the writes into the databases.
+        for checks_before_abort in [0, 1, 4, 8] {
+            let checks = AtomicUsize::new(0);
+            let mut wtxn = index.write_txn().unwrap();
+            let mut builder = IndexDocuments::new(
+                &mut wtxn,
+                &index,
+                &config,
+                IndexDocumentsConfig::default(),
+                |_| (),
+            )
+            .unwrap();
+            builder
+                .add_documents(documents!([
+                    { "id": 2, "title": "the quick brown fox" },
+                    { "id": 3, "title": "jumps over the lazy dog" },
+                ]))
+                .unwrap();
+            builder.should_abort(|| checks.fetch_add(1, Ordering::Relaxed) >= checks_before_abort);
+            let error = builder.execute().unwrap_err();
+            assert!(
+                matches!(error, Error::InternalError(InternalError::AbortedIndexation)),
+                "{:?}",
+                error
+            );
+            drop(wtxn);
+
+            // the aborted transaction left the index untouched.
+            let rtxn = index.read_txn().unwrap();
+            assert_eq!(index.number_of_documents(&rtxn).unwrap(), 1);
+            let result = index.search(&rtxn).query("fox").execute().unwrap();
+            assert!(result.documents_ids.is_empty());
+        }
+    }
+
+    #[test]
+    fn remove_the_temporary_files_of_a_failed_update() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use crate::Error;
+
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = IndexerConfig {
+            temp_dir: Some(temp_dir.path().to_path_buf()),
+            documents_chunk_size: Some(1),
+            ..Default::default()
+        };
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder
+            .add_documents(documents!([
+                { "id": 1, "title": "hello world" },
+                { "id": 2, "title": "the quick brown fox" },
+                { "id": 3, "title": "jumps over the lazy dog" },
+            ]))
+            .unwrap();
+
+        // the update fails while the first chunk of documents is extracted.
+        let checks = AtomicUsize::new(0);
+        builder.should_abort(|| checks.fetch_add(1, Ordering::Relaxed) >= 1);
+        let error = builder.execute().unwrap_err();
+        assert!(
+            matches!(error, Error::InternalError(InternalError::AbortedIndexation)),
+            "{:?}",
+            error
+        );
+
+        // the directory of the temporary files of the update is removed when it returns.
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn report_the_indexing_parameters() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(documents!([{ "id": 1, "title": "hello world" }])).unwrap();
+        let result = builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        assert_eq!(
+            result.parameters,
+            IndexingParameters {
+                update_method: IndexDocumentsMethod::ReplaceDocuments,
+                autogenerate_docids: false,
+                documents_chunk_size: 4 * 1024 * 1024,
+                max_memory: None,
+                max_nb_chunks: None,
+                small_batch: false,
+                facet_level_group_size: 4,
+                facet_min_level_size: 5,
+                words_prefix_threshold: 100,
+                max_prefix_length: 4,
+                words_positions_level_group_size: 4,
+                words_positions_min_level_size: 5,
+                words_prefix_positions_limit: None,
+                defer_prefix_databases: false,
+            }
+        );
+
+        // the values are clamped like the builders of the prefix databases do.
+        let config = IndexerConfig::builder()
+            .documents_chunk_size(1024)
+            .extraction_num_threads(1)
+            .max_memory(100 * 1024 * 1024)
+            .small_batch_threshold(10)
+            .build()
+            .unwrap();
+        let indexing_config = IndexDocumentsConfig {
+            update_method: IndexDocumentsMethod::UpdateDocuments,
+            facet_level_group_size: NonZeroUsize::new(1),
+            words_prefix_threshold: Some(10),
+            max_prefix_length: Some(30),
+            words_positions_min_level_size: NonZeroU32::new(8),
+            words_prefix_positions_limit: Some(20),
+            ..Default::default()
+        };
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(documents!([{ "id": 2, "title": "the quick brown fox" }])).unwrap();
+        let parameters = builder.execute().unwrap().parameters;
+        wtxn.commit().unwrap();
+
+        assert_eq!(parameters.update_method, IndexDocumentsMethod::UpdateDocuments);
+        assert_eq!(parameters.documents_chunk_size, 1024);
+        assert_eq!(parameters.max_memory, Some(100 * 1024 * 1024));
+        assert!(parameters.small_batch);
+        assert_eq!(parameters.facet_level_group_size, 2);
+        assert_eq!(parameters.words_prefix_threshold, 50);
+        assert_eq!(parameters.max_prefix_length, 25);
+        assert_eq!(parameters.words_positions_min_level_size, 8);
+        assert_eq!(parameters.words_prefix_positions_limit, Some(20));
+    }
+
+    #[test]
+    fn reject_documents_over_the_limits() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let index_documents = |config: &IndexerConfig| {
+            let content = documents!([
+                { "id": 1, "title": "hello", "body": "a small world" },
+                { "id": 2, "title": "the world", "body": "is a bigger place", "tags": "none" }
+            ]);
+            let mut wtxn = index.write_txn().unwrap();
+            let mut builder = IndexDocuments::new(
+                &mut wtxn,
+                &index,
+                config,
+                IndexDocumentsConfig::default(),
+                |_| (),
+            )
+            .unwrap();
+            builder.add_documents(content).unwrap();
+            builder.execute()
+        };
+
+        let config = IndexerConfig { max_fields_per_document: Some(3), ..Default::default() };
+        match index_documents(&config) {
+            Err(crate::Error::UserError(UserError::DocumentFieldsLimitReached {
+                document_id,
+                field,
+                limit,
+            })) => {
+                assert_eq!((document_id.as_str(), field.as_str(), limit), ("2", "tags", 3));
+            }
+            otherwise => panic!("unexpected result {:?}", otherwise),
+        }
+
+        let config = IndexerConfig { max_document_size: Some(30), ..Default::default() };
+        match index_documents(&config) {
+            Err(crate::Error::UserError(UserError::DocumentSizeLimitReached {
+                document_id,
+                field,
+                limit,
+            })) => {
+                assert_eq!((document_id.as_str(), field.as_str(), limit), ("2", "body", 30));
+            }
+            otherwise => panic!("unexpected result {:?}", otherwise),
+        }
+
+        let config = IndexerConfig { max_words_per_document: Some(5), ..Default::default() };
+        match index_documents(&config) {
+            Err(crate::Error::UserError(UserError::DocumentWordsLimitReached {
+                document_id,
+                field,
+                limit,
+            })) => {
+                assert_eq!((document_id.as_str(), field.as_str(), limit), ("2", "body", 5));
+            }
+            otherwise => panic!("unexpected result {:?}", otherwise),
+        }
+
+        let config = IndexerConfig {
+            max_fields_per_document: Some(4),
+            max_document_size: Some(100),
+            max_words_per_document: Some(10),
+            ..Default::default()
+        };
+        assert!(index_documents(&config).is_ok());
+    }
+
+    #[test]
+    fn facet_all_fields_with_a_wildcard() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::builder().max_values_per_wildcard_facet(2).build().unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset!(S("*")));
+        builder.set_sortable_fields(hashset!(S("rank")));
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 1, "title": "the first", "genre": "fable", "rank": 3 },
+            { "id": 2, "title": "the second", "genre": "tale", "rank": 2 },
+            { "id": 3, "title": "the third", "genre": "tale", "rank": 1 },
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // The ids and titles have too many values, the rank is explicitly sortable.
+        let rtxn = index.read_txn().unwrap();
+        let facets = index.faceted_fields(&rtxn).unwrap();
+        assert_eq!(facets, hashset!(S("genre"), S("rank")));
+        let high_cardinality = index.high_cardinality_faceted_fields(&rtxn).unwrap();
+        assert_eq!(high_cardinality, hashset!(S("id"), S("title")));
+
+        let filter = crate::Filter::from_str("genre = tale").unwrap().unwrap();
+        let result = index.search(&rtxn).filter(filter).execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1, 2]);
+        let filter = crate::Filter::from_str("title = 'the first'").unwrap().unwrap();
+        let result = index.search(&rtxn).filter(filter).execute().unwrap();
+        assert!(result.documents_ids.is_empty());
+        drop(rtxn);
+
+        // The high cardinality fields are checked again when the faceted fields change.
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset!(S("*"), S("genre")));
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.high_cardinality_faceted_fields(&rtxn).unwrap().is_empty());
+        let filter = crate::Filter::from_str("title = 'the first'").unwrap().unwrap();
+        let result = index.search(&rtxn).filter(filter).execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+    }
 }
@@ -8,6 +8,7 @@ use byteorder::ReadBytesExt;
 use fxhash::FxHashMap;
 use heed::RoTxn;
 use itertools::Itertools;
+use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
 use obkv::{KvReader, KvWriter};
 use roaring::RoaringBitmap;
 use serde_json::{Map, Value};
@@ -17,11 +18,12 @@ use super::helpers::{create_sorter, create_writer, keep_latest_obkv, merge_obkvs
 use super::{IndexDocumentsMethod, IndexerConfig};
 use crate::documents::{DocumentBatchReader, DocumentsBatchIndex};
 use crate::error::{Error, InternalError, UserError};
+use crate::hashed_fields::hash_json_value;
 use crate::index::db_name;
 use crate::update::{AvailableDocumentsIds, UpdateIndexingStep};
 use crate::{
-    ExternalDocumentsIds, FieldDistribution, FieldId, FieldIdMapMissingEntry, FieldsIdsMap, Index,
-    Result, BEU32,
+    array_position_field_name, json_to_string, DocumentId, ExternalDocumentsIds, FieldDistribution,
+    FieldId, FieldIdMapMissingEntry, FieldsIdsMap, Index, Result, BEU32,
 };
 
 const DEFAULT_PRIMARY_KEY_NAME: &str = "id";
@@ -47,6 +49,13 @@ pub struct TransformOutput {
 pub struct Transform<'a, 'i> {
     pub index: &'i Index,
     fields_ids_map: FieldsIdsMap,
+    positional_array_fields: HashSet<String>,
+    hashed_fields: HashSet<String>,
+    hashed_fields_salt: Option<String>,
+    // The fields that were hashed before, the documents that store them in plain text are tracked.
+    unhashed_fields: HashSet<String>,
+    // The documents come from the stored documents, their values are already hashed.
+    keep_hashed_values: bool,
 
     indexer_settings: &'a IndexerConfig,
     pub autogenerate_docids: bool,
@@ -58,6 +67,8 @@ pub struct Transform<'a, 'i> {
     new_documents_ids: RoaringBitmap,
     // To increase the cache locality and the heap usage we use smartstring.
     new_external_documents_ids_builder: FxHashMap<SmartString<smartstring::Compact>, u64>,
+    // The internal ids that must be given to the new documents with these external ids.
+    preserved_documents_ids: HashMap<String, DocumentId>,
     documents_count: usize,
 }
 
@@ -129,9 +140,23 @@ impl<'a, 'i> Transform<'a, 'i> {
             indexer_settings.max_memory.map(|mem| mem / 2),
         );
 
+        let hashed_fields: HashSet<String> =
+            index.hashed_fields(wtxn)?.into_iter().map(String::from).collect();
+        let unhashed_fields = index
+            .previously_hashed_fields(wtxn)?
+            .into_iter()
+            .filter(|field| !hashed_fields.contains(*field))
+            .map(String::from)
+            .collect();
+
         Ok(Transform {
             index,
             fields_ids_map: index.fields_ids_map(wtxn)?,
+            positional_array_fields: index.positional_array_fields(wtxn)?,
+            hashed_fields,
+            hashed_fields_salt: index.hashed_fields_salt(wtxn)?.map(String::from),
+            unhashed_fields,
+            keep_hashed_values: false,
             indexer_settings,
             autogenerate_docids,
             original_sorter,
@@ -140,10 +165,42 @@ impl<'a, 'i> Transform<'a, 'i> {
             replaced_documents_ids: RoaringBitmap::new(),
             new_documents_ids: RoaringBitmap::new(),
             new_external_documents_ids_builder: FxHashMap::default(),
+            preserved_documents_ids: HashMap::new(),
             documents_count: 0,
         })
     }
 
+    /// Gives the documents that are not yet in the index the internal ids of the given
+    /// external ids instead of the first available ones, the documents already in the index
+    /// keep their internal ids. Must be called before any document is read.
+    ///
+    /// Re-importing a dump with the mapping returned by [`ExternalDocumentsIds::to_hash_map`]
+    /// before the dump keeps the bitmaps of internal ids stored outside of the index valid.
+    pub fn preserve_documents_ids(
+        &mut self,
+        documents_ids: HashMap<String, DocumentId>,
+    ) -> Result<()> {
+        let mut internal_ids = RoaringBitmap::new();
+        for (external_id, &internal_id) in &documents_ids {
+            if !internal_ids.insert(internal_id) {
+                return Err(UserError::InternalDocumentIdAlreadyUsed {
+                    document_id: external_id.clone(),
+                    internal_id,
+                }
+                .into());
+            }
+        }
+
+        self.preserved_documents_ids = documents_ids;
+        Ok(())
+    }
+
+    /// Stores the values of the hashed fields as they are, for the documents read from the
+    /// stored documents of the index: hashing their values again would make them unfilterable.
+    pub(crate) fn keep_hashed_values(&mut self) {
+        self.keep_hashed_values = true;
+    }
+
     pub fn read_documents<R, F>(
         &mut self,
         mut reader: DocumentBatchReader<R>,
@@ -157,7 +214,13 @@ impl<'a, 'i> Transform<'a, 'i> {
         let fields_index = reader.index();
         let external_documents_ids = self.index.external_documents_ids(wtxn)?;
         let documents_ids = self.index.documents_ids(wtxn)?;
-        let mut available_documents_ids = AvailableDocumentsIds::from_documents_ids(&documents_ids);
+        // The preserved internal ids and the ids of the soft deleted documents, that are
+        // still in the databases, are never given to the other documents.
+        let mut reserved_documents_ids: RoaringBitmap =
+            self.preserved_documents_ids.values().copied().collect();
+        reserved_documents_ids |= self.index.soft_deleted_documents_ids(wtxn)?;
+        let mut available_documents_ids =
+            AvailableDocumentsIds::from_documents_ids(&(&documents_ids | &reserved_documents_ids));
 
         let mapping = create_fields_mapping(&mut self.fields_ids_map, fields_index)?;
 
@@ -176,6 +239,33 @@ impl<'a, 'i> Transform<'a, 'i> {
 
         let primary_key_id_nested = primary_key_name.contains('.');
 
+        // The values of the hashed fields are never stored in plain text, the primary key
+        // must be stored as is to identify the documents and is therefore never hashed.
+        // The values of the documents read from the stored documents are already hashed.
+        let hashed_fields_ids: HashSet<FieldId> = self
+            .hashed_fields
+            .iter()
+            .filter(|_| !self.keep_hashed_values)
+            .filter_map(|name| self.fields_ids_map.id(name))
+            .filter(|id| *id != primary_key_id)
+            .collect();
+        let hashed_fields_salt = self.hashed_fields_salt.clone().unwrap_or_default();
+
+        // The documents that store the values of the previously hashed fields in plain text.
+        let mut unhashed_documents_ids: HashMap<FieldId, RoaringBitmap> = self
+            .unhashed_fields
+            .iter()
+            .filter(|_| !self.keep_hashed_values)
+            .filter_map(|name| self.fields_ids_map.id(name))
+            .map(|id| (id, RoaringBitmap::new()))
+            .collect();
+
+        // The analyzer is only needed to count the words of the documents.
+        let analyzer = self
+            .indexer_settings
+            .max_words_per_document
+            .map(|_| Analyzer::<Vec<u8>>::new(AnalyzerConfig::default()));
+
         let mut flattened_document = None;
         let mut obkv_buffer = Vec::new();
         let mut flattened_obkv_buffer = Vec::new();
@@ -236,11 +326,25 @@ impl<'a, 'i> Transform<'a, 'i> {
                 )?
             };
 
+            for (field_id, value) in field_buffer_cache.iter_mut() {
+                if hashed_fields_ids.contains(field_id) {
+                    let json: Value =
+                        serde_json::from_slice(value).map_err(InternalError::SerdeJson)?;
+                    let hashed = hash_json_value(&hashed_fields_salt, &json);
+                    *value =
+                        Cow::from(serde_json::to_vec(&hashed).map_err(InternalError::SerdeJson)?);
+                    // The flattened version of the document must be computed from the hashed one.
+                    flattened_document = None;
+                }
+            }
+
             // Insertion in a obkv need to be done with keys ordered. For now they are ordered
             // according to the document addition key order, so we sort it according to the
             // fieldids map keys order.
             field_buffer_cache.sort_unstable_by(|(f1, _), (f2, _)| f1.cmp(&f2));
 
+            self.check_document_limits(&external_id, &field_buffer_cache, analyzer.as_ref())?;
+
             // Build the new obkv document.
             let mut writer = obkv::KvWriter::new(&mut obkv_buffer);
             for (k, v) in field_buffer_cache.iter() {
@@ -258,12 +362,24 @@ impl<'a, 'i> Transform<'a, 'i> {
                         // batch we need to get its docid
                         match self.new_external_documents_ids_builder.entry(external_id.into()) {
                             Entry::Occupied(entry) => (*entry.get() as u32, false),
-                            // if the document has never been encountered we give it a new docid
-                            // and push this new docid to the external documents ids builder
+                            // if the document has never been encountered we give it its preserved
+                            // docid or a new one and push this docid to the external documents
+                            // ids builder
                             Entry::Vacant(entry) => {
-                                let new_docid = available_documents_ids
-                                    .next()
-                                    .ok_or(UserError::DocumentLimitReached)?;
+                                let new_docid =
+                                    match self.preserved_documents_ids.get(entry.key().as_str()) {
+                                        Some(&docid) if documents_ids.contains(docid) => {
+                                            return Err(UserError::InternalDocumentIdAlreadyUsed {
+                                                document_id: entry.key().to_string(),
+                                                internal_id: docid,
+                                            }
+                                            .into())
+                                        }
+                                        Some(&docid) => docid,
+                                        None => available_documents_ids
+                                            .next()
+                                            .ok_or(UserError::DocumentLimitReached)?,
+                                    };
                                 entry.insert(new_docid as u64);
                                 (new_docid, false)
                             }
@@ -271,6 +387,12 @@ impl<'a, 'i> Transform<'a, 'i> {
                     }
                 };
 
+            for (field_id, _) in field_buffer_cache.iter() {
+                if let Some(docids) = unhashed_documents_ids.get_mut(field_id) {
+                    docids.insert(docid);
+                }
+            }
+
             if should_insert_original_document {
                 self.replaced_documents_ids.insert(docid);
 
@@ -322,6 +444,16 @@ impl<'a, 'i> Transform<'a, 'i> {
             documents_seen: documents_count,
         });
 
+        for (field_id, docids) in unhashed_documents_ids {
+            if docids.is_empty() {
+                continue;
+            }
+            if let Some(name) = self.fields_ids_map.name(field_id) {
+                let docids = self.index.unhashed_documents_ids(wtxn, name)? | docids;
+                self.index.put_unhashed_documents_ids(wtxn, name, &docids)?;
+            }
+        }
+
         self.index.put_fields_ids_map(wtxn, &self.fields_ids_map)?;
         self.index.put_primary_key(wtxn, &primary_key_name)?;
         self.documents_count += documents_count;
@@ -333,10 +465,10 @@ impl<'a, 'i> Transform<'a, 'i> {
     // Flatten a document from the fields ids map contained in self and insert the new
     // created fields. Returns `None` if the document doesn't need to be flattened.
     fn flatten_from_fields_ids_map(&mut self, obkv: KvReader<FieldId>) -> Result<Option<Vec<u8>>> {
-        if obkv
-            .iter()
-            .all(|(_, value)| !json_depth_checker::should_flatten_from_unchecked_slice(value))
-        {
+        if obkv.iter().all(|(key, value)| {
+            !json_depth_checker::should_flatten_from_unchecked_slice(value)
+                && !self.is_positional_array_field(key)
+        }) {
             return Ok(None);
         }
 
@@ -352,7 +484,9 @@ impl<'a, 'i> Transform<'a, 'i> {
         // we recreate a json containing only the fields that needs to be flattened.
         // all the raw values get inserted directly in the `key_value` vec.
         for (key, value) in obkv.iter() {
-            if json_depth_checker::should_flatten_from_unchecked_slice(value) {
+            if json_depth_checker::should_flatten_from_unchecked_slice(value)
+                || self.is_positional_array_field(key)
+            {
                 let key = self.fields_ids_map.name(key).ok_or(FieldIdMapMissingEntry::FieldId {
                     field_id: key,
                     process: "Flatten from fields ids map.",
@@ -366,7 +500,8 @@ impl<'a, 'i> Transform<'a, 'i> {
             }
         }
 
-        let flattened = flatten_serde_json::flatten(&doc);
+        let mut flattened = flatten_serde_json::flatten(&doc);
+        insert_array_positions(&doc, &mut flattened, &self.positional_array_fields);
 
         // Once we have the flattened version we insert all the new generated fields_ids
         // (if any) in the fields ids map and serialize the value.
@@ -402,14 +537,17 @@ impl<'a, 'i> Transform<'a, 'i> {
         // we recreate a json containing only the fields that needs to be flattened.
         // all the raw values get inserted directly in the `key_value` vec.
         for (key, value) in obkv.iter() {
-            if json_depth_checker::should_flatten_from_unchecked_slice(value) {
-                let key =
-                    mapping.get(&key).ok_or(InternalError::FieldIdMappingMissingEntry { key })?;
-                let key =
-                    self.fields_ids_map.name(*key).ok_or(FieldIdMapMissingEntry::FieldId {
-                        field_id: *key,
+            let mapped_key =
+                mapping.get(&key).ok_or(InternalError::FieldIdMappingMissingEntry { key })?;
+            if json_depth_checker::should_flatten_from_unchecked_slice(value)
+                || self.is_positional_array_field(*mapped_key)
+            {
+                let key = self.fields_ids_map.name(*mapped_key).ok_or(
+                    FieldIdMapMissingEntry::FieldId {
+                        field_id: *mapped_key,
                         process: "Flatten from field mapping.",
-                    })?;
+                    },
+                )?;
                 let value = serde_json::from_slice::<serde_json::Value>(value)
                     .map_err(InternalError::SerdeJson)?;
                 doc.insert(key.to_string(), value);
@@ -418,7 +556,8 @@ impl<'a, 'i> Transform<'a, 'i> {
             }
         }
 
-        let flattened = flatten_serde_json::flatten(&doc);
+        let mut flattened = flatten_serde_json::flatten(&doc);
+        insert_array_positions(&doc, &mut flattened, &self.positional_array_fields);
 
         // Once we have the flattened version we insert all the new generated fields_ids
         // (if any) in the fields ids map and serialize the value.
@@ -440,6 +579,69 @@ impl<'a, 'i> Transform<'a, 'i> {
         Ok(())
     }
 
+    /// Checks that the document respects the limits defined in the indexer settings.
+    fn check_document_limits(
+        &self,
+        document_id: &str,
+        fields: &[(FieldId, Cow<[u8]>)],
+        analyzer: Option<&Analyzer<Vec<u8>>>,
+    ) -> Result<()> {
+        let max_fields = self.indexer_settings.max_fields_per_document;
+        let max_size = self.indexer_settings.max_document_size;
+        let max_words = self.indexer_settings.max_words_per_document;
+        if max_fields.is_none() && max_size.is_none() && max_words.is_none() {
+            return Ok(());
+        }
+
+        let field_name = |field_id: FieldId| {
+            self.fields_ids_map.name(field_id).map(ToString::to_string).ok_or(
+                FieldIdMapMissingEntry::FieldId { field_id, process: "Check the document limits." },
+            )
+        };
+
+        let mut size = 0;
+        let mut words = 0;
+        for (index, (field_id, value)) in fields.iter().enumerate() {
+            if let Some(limit) = max_fields.filter(|limit| index >= *limit) {
+                let field = field_name(*field_id)?;
+                let document_id = document_id.to_string();
+                return Err(UserError::DocumentFieldsLimitReached { document_id, field, limit })?;
+            }
+
+            size += value.len();
+            if let Some(limit) = max_size.filter(|limit| size > *limit) {
+                let field = field_name(*field_id)?;
+                let document_id = document_id.to_string();
+                return Err(UserError::DocumentSizeLimitReached { document_id, field, limit })?;
+            }
+
+            if let Some((limit, analyzer)) = max_words.zip(analyzer) {
+                let value = serde_json::from_slice(value).map_err(InternalError::SerdeJson)?;
+                if let Some(text) = json_to_string(&value) {
+                    words += analyzer.analyze(&text).tokens().filter(|t| t.is_word()).count();
+                }
+                if words > limit {
+                    let field = field_name(*field_id)?;
+                    let document_id = document_id.to_string();
+                    return Err(UserError::DocumentWordsLimitReached {
+                        document_id,
+                        field,
+                        limit,
+                    })?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if the position of the values in the arrays of this field must be indexed.
+    fn is_positional_array_field(&self, field_id: FieldId) -> bool {
+        self.fields_ids_map
+            .name(field_id)
+            .map_or(false, |name| self.positional_array_fields.contains(name))
+    }
+
     /// Generate an obkv from a slice of key / value sorted by key.
     fn create_obkv_from_key_value(
         key_value: &mut [(FieldId, Cow<[u8]>)],
@@ -507,7 +709,7 @@ impl<'a, 'i> Transform<'a, 'i> {
     /// Generate the `TransformOutput` based on the given sorter that can be generated from any
     /// format like CSV, JSON or JSON stream. This sorter must contain a key that is the document
     /// id for the user side and the value must be an obkv where keys are valid fields ids.
-    pub(crate) fn output_from_sorter<F>(
+    pub fn output_from_sorter<F>(
         self,
         wtxn: &mut heed::RwTxn,
         progress_callback: F,
@@ -665,6 +867,10 @@ impl<'a, 'i> Transform<'a, 'i> {
         for result in self.index.documents.iter(wtxn)? {
             let (docid, obkv) = result?;
             let docid = docid.get();
+            // The soft deleted documents are still stored but must not be reindexed.
+            if !documents_ids.contains(docid) {
+                continue;
+            }
 
             obkv_buffer.clear();
             let mut obkv_writer = obkv::KvWriter::<_, FieldId>::new(&mut obkv_buffer);
@@ -694,7 +900,8 @@ impl<'a, 'i> Transform<'a, 'i> {
                 doc.insert(key.to_string(), value);
             }
 
-            let flattened = flatten_serde_json::flatten(&doc);
+            let mut flattened = flatten_serde_json::flatten(&doc);
+            insert_array_positions(&doc, &mut flattened, &self.positional_array_fields);
 
             // Once we have the flattened version we can convert it back to obkv and
             // insert all the new generated fields_ids (if any) in the fields ids map.
@@ -849,17 +1056,70 @@ fn update_primary_key<'a>(
     }
 }
 
+/// The number of first positions of the arrays that are indexed, every position creates a field.
+const MAX_ARRAY_POSITIONS: usize = 10;
+
+/// Inserts the fields derived from the position of the values in the arrays of the
+/// positional array fields, e.g. `authors[0]` for the first value of the `authors` array.
+/// Only the [`MAX_ARRAY_POSITIONS`] first values of an array are given a field.
+fn insert_array_positions(
+    document: &Map<String, Value>,
+    flattened: &mut Map<String, Value>,
+    positional_array_fields: &HashSet<String>,
+) {
+    for field in positional_array_fields {
+        if let Some(Value::Array(values)) = value_by_path(document, field) {
+            for (position, value) in values.iter().take(MAX_ARRAY_POSITIONS).enumerate() {
+                let mut object = Map::new();
+                object.insert(array_position_field_name(field, position), value.clone());
+                flattened.extend(flatten_serde_json::flatten(&object));
+            }
+        }
+    }
+}
+
+/// Returns the value found under the dotted `path`, e.g. `book.authors`, through the objects.
+fn value_by_path<'a>(object: &'a Map<String, Value>, path: &str) -> Option<&'a Value> {
+    if let Some(value) = object.get(path) {
+        return Some(value);
+    }
+
+    object.iter().find_map(|(key, value)| {
+        let rest = path.strip_prefix(key.as_str())?.strip_prefix('.')?;
+        match value {
+            Value::Object(object) => value_by_path(object, rest),
+            _ => None,
+        }
+    })
+}
+
 impl TransformOutput {
     // find and insert the new field ids
     pub fn compute_real_facets(&self, rtxn: &RoTxn, index: &Index) -> Result<HashSet<String>> {
-        let user_defined_facets = index.user_defined_faceted_fields(rtxn)?;
+        let mut user_defined_facets = index.user_defined_faceted_fields(rtxn)?;
+        let wildcard = user_defined_facets.remove(crate::ALL_FIELDS_FACET);
+        let high_cardinality_facets = index.high_cardinality_faceted_fields(rtxn)?;
+
+        // The wildcard neither facets the vectors nor the fields that have too many values.
+        let is_faceted_by_wildcard = |field: &str| {
+            wildcard && !crate::is_vector_field(field) && !high_cardinality_facets.contains(field)
+        };
 
-        Ok(self
+        let mut facets: HashSet<_> = self
             .fields_ids_map
             .names()
-            .filter(|&field| crate::is_faceted(field, &user_defined_facets))
+            .filter(|&field| {
+                crate::is_faceted(field, &user_defined_facets) || is_faceted_by_wildcard(field)
+            })
             .map(|field| field.to_string())
-            .collect())
+            .collect();
+
+        // The content hash of the documents is faceted to be deduplicated like a distinct field.
+        if !index.content_hash_fields(rtxn)?.is_empty() {
+            facets.insert(crate::CONTENT_HASH_FIELD.to_string());
+        }
+
+        Ok(facets)
     }
 }
 
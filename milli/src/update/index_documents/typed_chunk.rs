@@ -16,8 +16,8 @@ use super::{ClonableMmap, MergeFn};
 use crate::heed_codec::facet::{decode_prefix_string, encode_prefix_string};
 use crate::update::index_documents::helpers::as_cloneable_grenad;
 use crate::{
-    lat_lng_to_xyz, BoRoaringBitmapCodec, CboRoaringBitmapCodec, DocumentId, GeoPoint, Index,
-    Result,
+    lat_lng_to_xyz, BoRoaringBitmapCodec, CboRoaringBitmapCodec, DocumentId, FieldId, GeoPoint,
+    Index, Result,
 };
 
 pub(crate) enum TypedChunk {
@@ -26,7 +26,9 @@ pub(crate) enum TypedChunk {
     FieldIdDocidFacetNumbers(grenad::Reader<CursorClonableMmap>),
     Documents(grenad::Reader<CursorClonableMmap>),
     FieldIdWordcountDocids(grenad::Reader<File>),
+    DocidFieldIdWordcount(grenad::Reader<File>),
     NewDocumentsIds(RoaringBitmap),
+    TruncatedDocuments(Vec<(String, Vec<FieldId>)>),
     WordDocids {
         word_docids_reader: grenad::Reader<File>,
         exact_word_docids_reader: grenad::Reader<File>,
@@ -35,7 +37,34 @@ pub(crate) enum TypedChunk {
     WordPairProximityDocids(grenad::Reader<File>),
     FieldIdFacetStringDocids(grenad::Reader<File>),
     FieldIdFacetNumberDocids(grenad::Reader<File>),
+    FieldIdFacetBoolDocids(grenad::Reader<File>),
     GeoPoints(grenad::Reader<File>),
+    Vectors(grenad::Reader<File>),
+}
+
+impl TypedChunk {
+    /// Returns the rank of the kind of this chunk in the order in which the chunks are
+    /// written in reproducible mode, with the index of the documents chunk they come from.
+    pub(crate) fn kind_order(&self) -> u8 {
+        match self {
+            TypedChunk::DocidWordPositions(_) => 0,
+            TypedChunk::FieldIdDocidFacetStrings(_) => 1,
+            TypedChunk::FieldIdDocidFacetNumbers(_) => 2,
+            TypedChunk::Documents(_) => 3,
+            TypedChunk::FieldIdWordcountDocids(_) => 4,
+            TypedChunk::DocidFieldIdWordcount(_) => 5,
+            TypedChunk::NewDocumentsIds(_) => 6,
+            TypedChunk::TruncatedDocuments(_) => 7,
+            TypedChunk::WordDocids { .. } => 8,
+            TypedChunk::WordPositionDocids(_) => 9,
+            TypedChunk::WordPairProximityDocids(_) => 10,
+            TypedChunk::FieldIdFacetStringDocids(_) => 11,
+            TypedChunk::FieldIdFacetNumberDocids(_) => 12,
+            TypedChunk::GeoPoints(_) => 13,
+            TypedChunk::Vectors(_) => 14,
+            TypedChunk::FieldIdFacetBoolDocids(_) => 15,
+        }
+    }
 }
 
 /// Write typed chunk in the corresponding LMDB database of the provided index.
@@ -49,7 +78,7 @@ pub(crate) fn write_typed_chunk_into_index(
     let mut is_merged_database = false;
     match typed_chunk {
         TypedChunk::DocidWordPositions(docid_word_positions_iter) => {
-            write_entries_into_database(
+            append_entries_into_database(
                 docid_word_positions_iter,
                 &index.docid_word_positions,
                 wtxn,
@@ -72,10 +101,7 @@ pub(crate) fn write_typed_chunk_into_index(
             )?;
         }
         TypedChunk::Documents(obkv_documents_iter) => {
-            let mut cursor = obkv_documents_iter.into_cursor()?;
-            while let Some((key, value)) = cursor.move_on_next()? {
-                index.documents.remap_types::<ByteSlice, ByteSlice>().put(wtxn, key, value)?;
-            }
+            put_entries_into_database(obkv_documents_iter, &index.documents, wtxn)?;
         }
         TypedChunk::FieldIdWordcountDocids(fid_word_count_docids_iter) => {
             append_entries_into_database(
@@ -88,9 +114,18 @@ pub(crate) fn write_typed_chunk_into_index(
             )?;
             is_merged_database = true;
         }
+        TypedChunk::DocidFieldIdWordcount(docid_fid_word_count) => {
+            put_entries_into_database(
+                docid_fid_word_count,
+                &index.docid_field_id_word_count,
+                wtxn,
+            )?;
+        }
         TypedChunk::NewDocumentsIds(documents_ids) => {
             return Ok((documents_ids, is_merged_database))
         }
+        // Truncations are only reported to the user, there is nothing to write.
+        TypedChunk::TruncatedDocuments(_) => (),
         TypedChunk::WordDocids { word_docids_reader, exact_word_docids_reader } => {
             let word_docids_iter = unsafe { as_cloneable_grenad(&word_docids_reader) }?;
             append_entries_into_database(
@@ -158,24 +193,18 @@ pub(crate) fn write_typed_chunk_into_index(
             is_merged_database = true;
         }
         TypedChunk::FieldIdDocidFacetNumbers(fid_docid_facet_number) => {
-            let index_fid_docid_facet_numbers =
-                index.field_id_docid_facet_f64s.remap_types::<ByteSlice, ByteSlice>();
-            let mut cursor = fid_docid_facet_number.into_cursor()?;
-            while let Some((key, value)) = cursor.move_on_next()? {
-                if valid_lmdb_key(key) {
-                    index_fid_docid_facet_numbers.put(wtxn, key, &value)?;
-                }
-            }
+            put_entries_into_database(
+                fid_docid_facet_number,
+                &index.field_id_docid_facet_f64s,
+                wtxn,
+            )?;
         }
         TypedChunk::FieldIdDocidFacetStrings(fid_docid_facet_string) => {
-            let index_fid_docid_facet_strings =
-                index.field_id_docid_facet_strings.remap_types::<ByteSlice, ByteSlice>();
-            let mut cursor = fid_docid_facet_string.into_cursor()?;
-            while let Some((key, value)) = cursor.move_on_next()? {
-                if valid_lmdb_key(key) {
-                    index_fid_docid_facet_strings.put(wtxn, key, &value)?;
-                }
-            }
+            put_entries_into_database(
+                fid_docid_facet_string,
+                &index.field_id_docid_facet_strings,
+                wtxn,
+            )?;
         }
         TypedChunk::FieldIdFacetStringDocids(facet_id_string_docids) => {
             append_entries_into_database(
@@ -196,6 +225,19 @@ pub(crate) fn write_typed_chunk_into_index(
             )?;
             is_merged_database = true;
         }
+        TypedChunk::FieldIdFacetBoolDocids(facet_bool_docids_iter) => {
+            let mut cursor = facet_bool_docids_iter.into_cursor()?;
+            while let Some((key, value)) = cursor.move_on_next()? {
+                // the key is the field id followed by the boolean as a byte
+                let (field_id, tail) = helpers::try_split_array_at::<u8, 2>(key).unwrap();
+                let field_id = FieldId::from_be_bytes(field_id);
+                let boolean = tail.first() == Some(&1);
+
+                let mut docids = index.boolean_faceted_documents_ids(wtxn, field_id, boolean)?;
+                docids |= CboRoaringBitmapCodec::deserialize_from(value)?;
+                index.put_boolean_faceted_documents_ids(wtxn, field_id, boolean, &docids)?;
+            }
+        }
         TypedChunk::GeoPoints(geo_points) => {
             let mut rtree = index.geo_rtree(wtxn)?.unwrap_or_default();
             let mut geo_faceted_docids = index.geo_faceted_documents_ids(wtxn)?;
@@ -205,18 +247,24 @@ pub(crate) fn write_typed_chunk_into_index(
                 // convert the key back to a u32 (4 bytes)
                 let docid = key.try_into().map(DocumentId::from_be_bytes).unwrap();
 
-                // convert the latitude and longitude back to a f64 (8 bytes)
-                let (lat, tail) = helpers::try_split_array_at::<u8, 8>(value).unwrap();
-                let (lng, _) = helpers::try_split_array_at::<u8, 8>(tail).unwrap();
-                let point = [f64::from_ne_bytes(lat), f64::from_ne_bytes(lng)];
-                let xyz_point = lat_lng_to_xyz(&point);
+                // a document can have several points, each one is a latitude and
+                // a longitude converted back to a f64 (8 bytes)
+                for value in value.chunks_exact(16) {
+                    let (lat, tail) = helpers::try_split_array_at::<u8, 8>(value).unwrap();
+                    let (lng, _) = helpers::try_split_array_at::<u8, 8>(tail).unwrap();
+                    let point = [f64::from_ne_bytes(lat), f64::from_ne_bytes(lng)];
+                    let xyz_point = lat_lng_to_xyz(&point);
 
-                rtree.insert(GeoPoint::new(xyz_point, (docid, point)));
+                    rtree.insert(GeoPoint::new(xyz_point, (docid, point)));
+                }
                 geo_faceted_docids.insert(docid);
             }
             index.put_geo_rtree(wtxn, &rtree)?;
             index.put_geo_faceted_documents_ids(wtxn, &geo_faceted_docids)?;
         }
+        TypedChunk::Vectors(vectors) => {
+            put_entries_into_database(vectors, &index.vectors, wtxn)?;
+        }
     }
 
     Ok((RoaringBitmap::new(), is_merged_database))
@@ -297,10 +345,60 @@ where
     Ok(())
 }
 
+/// Returns whether the first key of a chunk is greater than the last key of the database.
+/// The entries of a chunk are ordered, all of them can then be appended to the database.
+fn chunk_follows_database(
+    first_key: Option<&[u8]>,
+    database: &heed::Database<ByteSlice, ByteSlice>,
+    wtxn: &RwTxn,
+) -> Result<bool> {
+    match (first_key, database.last(wtxn)?) {
+        (Some(first_key), Some((last_key, _))) => Ok(first_key > last_key),
+        _ => Ok(true),
+    }
+}
+
+/// Write provided entries in database, the existing entries are replaced.
+/// The entries are appended when the chunk follows the entries of the database,
+/// e.g. when the index is empty, put one by one otherwise.
+fn put_entries_into_database<R, K, V>(
+    data: grenad::Reader<R>,
+    database: &heed::Database<K, V>,
+    wtxn: &mut RwTxn,
+) -> Result<()>
+where
+    R: io::Read + io::Seek,
+{
+    let database = database.remap_types::<ByteSlice, ByteSlice>();
+
+    let mut cursor = data.into_cursor()?;
+    let mut entry = cursor.move_on_first()?;
+    if chunk_follows_database(entry.map(|(key, _)| key), &database, wtxn)? {
+        let mut database = database.iter_mut(wtxn)?;
+        while let Some((key, value)) = entry {
+            if valid_lmdb_key(key) {
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { database.append(key, value)? };
+            }
+            entry = cursor.move_on_next()?;
+        }
+    } else {
+        while let Some((key, value)) = entry {
+            if valid_lmdb_key(key) {
+                database.put(wtxn, key, value)?;
+            }
+            entry = cursor.move_on_next()?;
+        }
+    }
+
+    Ok(())
+}
+
 /// Write provided entries in database using serialize_value function.
 /// merge_values function is used if an entry already exist in the database.
 /// All provided entries must be ordered.
-/// If the index is not empty, write_entries_into_database is called instead.
+/// If the chunk doesn't follow the entries of the database,
+/// write_entries_into_database is called instead.
 fn append_entries_into_database<R, K, V, FS, FM>(
     data: grenad::Reader<R>,
     database: &heed::Database<K, V>,
@@ -314,27 +412,31 @@ where
     FS: for<'a> Fn(&'a [u8], &'a mut Vec<u8>) -> Result<&'a [u8]>,
     FM: Fn(&[u8], &[u8], &mut Vec<u8>) -> Result<()>,
 {
-    if !index_is_empty {
+    let mut cursor = data.into_cursor()?;
+    let first_key = cursor.move_on_first()?.map(|(key, _)| key);
+    let bytes_database = database.remap_types::<ByteSlice, ByteSlice>();
+    if !chunk_follows_database(first_key, &bytes_database, wtxn)? {
         return write_entries_into_database(
-            data,
+            cursor.into_reader(),
             database,
             wtxn,
-            false,
+            index_is_empty,
             serialize_value,
             merge_values,
         );
     }
 
     let mut buffer = Vec::new();
-    let mut database = database.iter_mut(wtxn)?.remap_types::<ByteSlice, ByteSlice>();
+    let mut database = bytes_database.iter_mut(wtxn)?;
 
-    let mut cursor = data.into_cursor()?;
-    while let Some((key, value)) = cursor.move_on_next()? {
+    let mut entry = cursor.current();
+    while let Some((key, value)) = entry {
         if valid_lmdb_key(key) {
             buffer.clear();
             let value = serialize_value(value, &mut buffer)?;
             unsafe { database.append(key, value)? };
         }
+        entry = cursor.move_on_next()?;
     }
 
     Ok(())
@@ -1,16 +1,62 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
 use grenad::CompressionType;
 use rayon::ThreadPool;
 
+use crate::{Result, UserError};
+
+/// The minimum amount of memory an extraction thread can be given, below it the sorters
+/// would dump their chunks on disk every few documents.
+const MIN_MEMORY_BY_THREAD: usize = 1024 * 1024; // 1MiB
+
+/// The number of distinct values above which a field faceted by the wildcard stops being faceted.
+pub const DEFAULT_MAX_VALUES_PER_WILDCARD_FACET: usize = 1000;
+
+/// The settings of the indexer, it is built with an [`IndexerConfigBuilder`] that validates
+/// the combinations of settings, i.e. `IndexerConfig::builder().max_memory(m).build()?`.
 #[derive(Debug)]
 pub struct IndexerConfig {
-    pub log_every_n: Option<usize>,
-    pub max_nb_chunks: Option<usize>,
-    pub documents_chunk_size: Option<usize>,
-    pub max_memory: Option<usize>,
-    pub chunk_compression_type: CompressionType,
-    pub chunk_compression_level: Option<u32>,
-    pub thread_pool: Option<ThreadPool>,
-    pub max_positions_per_attributes: Option<u32>,
+    pub(crate) log_every_n: Option<usize>,
+    pub(crate) max_nb_chunks: Option<usize>,
+    pub(crate) documents_chunk_size: Option<usize>,
+    pub(crate) max_memory: Option<usize>,
+    pub(crate) chunk_compression_type: CompressionType,
+    pub(crate) chunk_compression_level: Option<u32>,
+    /// The thread pool of the extraction phase, used with its own number of threads. It
+    /// can't be given with an `extraction_num_threads`, the builder rejects them.
+    pub(crate) thread_pool: Option<ThreadPool>,
+    /// The directory in which every update creates the directory of its temporary files,
+    /// defaults to the temporary directory of the system.
+    pub(crate) temp_dir: Option<PathBuf>,
+    /// The number of threads of the pool built for the extraction phase, can't be given with
+    /// a `thread_pool`. The prefix databases are always updated on the calling thread.
+    pub(crate) extraction_num_threads: Option<usize>,
+    /// Batches of less documents than this threshold are extracted in the current thread
+    /// pool instead of a dedicated one, and their facet values are added to the facet levels
+    /// in place instead of recomputing them. The posting lists are merged like the other batches.
+    pub(crate) small_batch_threshold: Option<usize>,
+    pub(crate) max_positions_per_attributes: Option<u32>,
+    /// Overrides the `max_positions_per_attributes` cap for the given attributes.
+    pub(crate) max_positions_per_attributes_overrides: HashMap<String, u32>,
+    /// Rejects the documents that contain more than this number of top-level fields.
+    pub(crate) max_fields_per_document: Option<usize>,
+    /// Rejects the documents whose values contain more than this number of words.
+    pub(crate) max_words_per_document: Option<usize>,
+    /// Rejects the documents whose serialized values are larger than this number of bytes.
+    pub(crate) max_document_size: Option<usize>,
+    /// The fields faceted by the `*` wildcard with more distinct values than this limit stop
+    /// being faceted, defaults to `DEFAULT_MAX_VALUES_PER_WILDCARD_FACET`.
+    pub(crate) max_values_per_wildcard_facet: Option<usize>,
+    /// Extracts and writes the chunks in a stable order so that indexing the same documents
+    /// with the same settings always produces the same databases, at the cost of parallelism.
+    pub(crate) reproducible: bool,
+}
+
+impl IndexerConfig {
+    pub fn builder() -> IndexerConfigBuilder {
+        IndexerConfigBuilder::default()
+    }
 }
 
 impl Default for IndexerConfig {
@@ -23,7 +69,226 @@ impl Default for IndexerConfig {
             chunk_compression_type: CompressionType::None,
             chunk_compression_level: None,
             thread_pool: None,
+            temp_dir: None,
+            extraction_num_threads: None,
+            small_batch_threshold: None,
             max_positions_per_attributes: None,
+            max_positions_per_attributes_overrides: HashMap::new(),
+            max_fields_per_document: None,
+            max_words_per_document: None,
+            max_document_size: None,
+            max_values_per_wildcard_facet: None,
+            reproducible: false,
         }
     }
 }
+
+/// Builds an [`IndexerConfig`], the settings that are not given are left to the defaults
+/// of the indexer.
+#[derive(Debug, Default)]
+pub struct IndexerConfigBuilder {
+    config: IndexerConfig,
+}
+
+impl IndexerConfigBuilder {
+    /// Logs the progress of the indexation every `n` documents.
+    pub fn log_every_n(mut self, n: usize) -> Self {
+        self.config.log_every_n = Some(n);
+        self
+    }
+
+    /// The number of chunks the sorters dump on disk before merging them.
+    pub fn max_nb_chunks(mut self, max_nb_chunks: usize) -> Self {
+        self.config.max_nb_chunks = Some(max_nb_chunks);
+        self
+    }
+
+    /// The size in bytes of the chunks of documents extracted in parallel.
+    pub fn documents_chunk_size(mut self, size: usize) -> Self {
+        self.config.documents_chunk_size = Some(size);
+        self
+    }
+
+    /// The memory in bytes shared by all the extraction threads.
+    pub fn max_memory(mut self, max_memory: usize) -> Self {
+        self.config.max_memory = Some(max_memory);
+        self
+    }
+
+    pub fn chunk_compression_type(mut self, compression_type: CompressionType) -> Self {
+        self.config.chunk_compression_type = compression_type;
+        self
+    }
+
+    /// Requires a `chunk_compression_type` other than `CompressionType::None`.
+    pub fn chunk_compression_level(mut self, level: u32) -> Self {
+        self.config.chunk_compression_level = Some(level);
+        self
+    }
+
+    /// The thread pool used for the extraction, conflicts with `extraction_num_threads`.
+    pub fn thread_pool(mut self, thread_pool: ThreadPool) -> Self {
+        self.config.thread_pool = Some(thread_pool);
+        self
+    }
+
+    pub fn temp_dir(mut self, temp_dir: impl Into<PathBuf>) -> Self {
+        self.config.temp_dir = Some(temp_dir.into());
+        self
+    }
+
+    /// The number of threads of the extraction pool, defaults to the available parallelism.
+    pub fn extraction_num_threads(mut self, num_threads: usize) -> Self {
+        self.config.extraction_num_threads = Some(num_threads);
+        self
+    }
+
+    /// The number of documents under which a batch is extracted in the current thread pool
+    /// and its facet values are added to the facet levels in place.
+    pub fn small_batch_threshold(mut self, threshold: usize) -> Self {
+        self.config.small_batch_threshold = Some(threshold);
+        self
+    }
+
+    pub fn max_positions_per_attributes(mut self, max_positions: u32) -> Self {
+        self.config.max_positions_per_attributes = Some(max_positions);
+        self
+    }
+
+    pub fn max_positions_per_attributes_override(
+        mut self,
+        attribute: impl Into<String>,
+        max_positions: u32,
+    ) -> Self {
+        self.config.max_positions_per_attributes_overrides.insert(attribute.into(), max_positions);
+        self
+    }
+
+    pub fn max_fields_per_document(mut self, max_fields: usize) -> Self {
+        self.config.max_fields_per_document = Some(max_fields);
+        self
+    }
+
+    pub fn max_words_per_document(mut self, max_words: usize) -> Self {
+        self.config.max_words_per_document = Some(max_words);
+        self
+    }
+
+    pub fn max_document_size(mut self, max_size: usize) -> Self {
+        self.config.max_document_size = Some(max_size);
+        self
+    }
+
+    pub fn max_values_per_wildcard_facet(mut self, max_values: usize) -> Self {
+        self.config.max_values_per_wildcard_facet = Some(max_values);
+        self
+    }
+
+    pub fn reproducible(mut self, reproducible: bool) -> Self {
+        self.config.reproducible = reproducible;
+        self
+    }
+
+    /// Validates the combination of the settings that are given.
+    pub fn build(self) -> Result<IndexerConfig> {
+        let config = self.config;
+
+        let non_zero_settings = [
+            ("log_every_n", config.log_every_n),
+            ("max_nb_chunks", config.max_nb_chunks),
+            ("documents_chunk_size", config.documents_chunk_size),
+            ("extraction_num_threads", config.extraction_num_threads),
+        ];
+        for (name, value) in non_zero_settings {
+            if value == Some(0) {
+                return Err(invalid(format!("`{}` must be greater than zero.", name)));
+            }
+        }
+
+        if config.chunk_compression_level.is_some()
+            && config.chunk_compression_type == CompressionType::None
+        {
+            return Err(invalid(
+                "`chunk_compression_level` requires a `chunk_compression_type`.".to_string(),
+            ));
+        }
+
+        let num_threads = match (&config.thread_pool, config.extraction_num_threads) {
+            (Some(_), Some(_)) => {
+                return Err(invalid(
+                    "`thread_pool` and `extraction_num_threads` cannot be used together."
+                        .to_string(),
+                ))
+            }
+            (Some(pool), None) => pool.current_num_threads(),
+            (None, Some(num_threads)) => num_threads,
+            // The number of threads is only known once the extraction pool is built,
+            // the memory must at least be enough for one of them.
+            (None, None) => 1,
+        };
+
+        if let Some(max_memory) = config.max_memory {
+            // Every extraction thread owns its sorters and every sorter keeps
+            // up to `max_nb_chunks` chunks, the memory must be enough for all of them.
+            let nb_chunks = config.max_nb_chunks.unwrap_or(1);
+            let required =
+                num_threads.saturating_mul(nb_chunks).saturating_mul(MIN_MEMORY_BY_THREAD);
+            if max_memory < required {
+                return Err(invalid(format!(
+                    "`max_memory` must be at least {} bytes for {} threads and {} chunks, found {} bytes.",
+                    required, num_threads, nb_chunks, max_memory,
+                )));
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn invalid(reason: String) -> crate::Error {
+    UserError::InvalidIndexerConfig(reason).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_indexer_config() {
+        let config = IndexerConfig::builder()
+            .max_memory(100 * 1024 * 1024)
+            .max_nb_chunks(4)
+            .extraction_num_threads(2)
+            .chunk_compression_type(CompressionType::Zlib)
+            .chunk_compression_level(3)
+            .max_positions_per_attributes_override("title", 10)
+            .build()
+            .unwrap();
+        assert_eq!(config.max_memory, Some(100 * 1024 * 1024));
+        assert_eq!(config.extraction_num_threads, Some(2));
+        assert_eq!(config.max_positions_per_attributes_overrides.get("title"), Some(&10));
+
+        // The settings that are not given are left to the defaults of the indexer.
+        let config = IndexerConfig::builder().max_memory(10 * 1024 * 1024).build().unwrap();
+        assert_eq!(config.extraction_num_threads, None);
+        assert!(config.thread_pool.is_none());
+
+        // The memory is too small for the number of chunks of every thread.
+        let result = IndexerConfig::builder()
+            .max_memory(4 * 1024 * 1024)
+            .max_nb_chunks(4)
+            .extraction_num_threads(2)
+            .build();
+        assert!(matches!(result, Err(crate::Error::UserError(UserError::InvalidIndexerConfig(_)))));
+
+        let result = IndexerConfig::builder().chunk_compression_level(3).build();
+        assert!(result.is_err());
+
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let result = IndexerConfig::builder().thread_pool(pool).extraction_num_threads(2).build();
+        assert!(result.is_err());
+
+        let result = IndexerConfig::builder().max_nb_chunks(0).build();
+        assert!(result.is_err());
+    }
+}
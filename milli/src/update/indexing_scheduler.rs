@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::{panic, thread};
+
+use crate::documents::DocumentBatchReader;
+use crate::update::{DocumentAdditionResult, IndexDocuments, IndexDocumentsConfig, IndexerConfig};
+use crate::{Index, Result};
+
+/// Indexes document batches into several indexes at once.
+///
+/// The extractions of every index share the thread pool and the memory budget of the given
+/// [`IndexerConfig`], a single pool is built when the config doesn't contain one. Only the
+/// LMDB commits are serialized. The batches sent to the same index are indexed one after
+/// the other, in the order they were added.
+pub struct IndexingScheduler<R> {
+    indexer_config: IndexerConfig,
+    jobs: Vec<IndexingJob<R>>,
+}
+
+struct IndexingJob<R> {
+    index: Index,
+    config: IndexDocumentsConfig,
+    documents: DocumentBatchReader<R>,
+}
+
+impl<R> IndexingScheduler<R>
+where
+    R: Read + Seek + Send + 'static,
+{
+    /// Creates a scheduler that splits the `max_memory` of the indexer config
+    /// between the indexes that are indexed at the same time.
+    pub fn new(indexer_config: IndexerConfig) -> IndexingScheduler<R> {
+        IndexingScheduler { indexer_config, jobs: Vec::new() }
+    }
+
+    /// Schedules a batch of documents to be indexed in the given index.
+    pub fn add_documents(
+        &mut self,
+        index: &Index,
+        config: IndexDocumentsConfig,
+        documents: DocumentBatchReader<R>,
+    ) {
+        self.jobs.push(IndexingJob { index: index.clone(), config, documents });
+    }
+
+    /// Indexes all the scheduled batches and returns their results in the order they were added.
+    pub fn execute(self) -> Result<Vec<Result<DocumentAdditionResult>>> {
+        let IndexingScheduler { mut indexer_config, jobs } = self;
+
+        // Every index would build its own extraction pool, we build it once for all of them.
+        if indexer_config.thread_pool.is_none() {
+            let num_threads = indexer_config.extraction_num_threads.take().unwrap_or(0);
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(num_threads).build()?;
+            indexer_config.thread_pool = Some(pool);
+        }
+
+        // We group the jobs by index, the jobs of an index can't run concurrently.
+        let mut groups: Vec<Vec<(usize, IndexingJob<R>)>> = Vec::new();
+        let mut groups_ids: HashMap<PathBuf, usize> = HashMap::new();
+        for (job_id, job) in jobs.into_iter().enumerate() {
+            let group_id = *groups_ids.entry(job.index.path().to_path_buf()).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[group_id].push((job_id, job));
+        }
+
+        let groups_count = groups.len().max(1);
+        indexer_config.max_memory =
+            indexer_config.max_memory.map(|max_memory| max_memory / groups_count);
+        let indexer_config = Arc::new(indexer_config);
+        let commit_lock = Arc::new(Mutex::new(()));
+
+        // The groups are run on their own threads as the LMDB writes must not
+        // block the shared thread pool in which the extractions are done.
+        let handles: Vec<_> = groups
+            .into_iter()
+            .map(|group| {
+                let indexer_config = indexer_config.clone();
+                let commit_lock = commit_lock.clone();
+                thread::spawn(move || {
+                    group
+                        .into_iter()
+                        .map(|(job_id, job)| {
+                            (job_id, index_documents(job, &indexer_config, &commit_lock))
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut results = Vec::new();
+        for handle in handles {
+            match handle.join() {
+                Ok(group_results) => results.extend(group_results),
+                Err(error) => panic::resume_unwind(error),
+            }
+        }
+
+        results.sort_unstable_by_key(|(job_id, _)| *job_id);
+        Ok(results.into_iter().map(|(_, result)| result).collect())
+    }
+}
+
+fn index_documents<R: Read + Seek>(
+    job: IndexingJob<R>,
+    indexer_config: &IndexerConfig,
+    commit_lock: &Mutex<()>,
+) -> Result<DocumentAdditionResult> {
+    let IndexingJob { index, config, documents } = job;
+    let mut wtxn = index.write_txn()?;
+    let mut builder = IndexDocuments::new(&mut wtxn, &index, indexer_config, config, |_| ())?;
+    builder.add_documents(documents)?;
+    let result = builder.execute()?;
+
+    // A job that panicked while committing doesn't prevent the other ones from committing.
+    let _guard = commit_lock.lock().unwrap_or_else(|e| e.into_inner());
+    wtxn.commit()?;
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::tests::TempIndex;
+
+    #[test]
+    fn index_several_indexes() {
+        let first = TempIndex::new();
+        let second = TempIndex::new();
+        let thread_pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+        let indexer_config = IndexerConfig {
+            thread_pool: Some(thread_pool),
+            max_memory: Some(10 * 1024 * 1024),
+            ..Default::default()
+        };
+
+        let mut scheduler = IndexingScheduler::new(indexer_config);
+        scheduler.add_documents(
+            &first,
+            IndexDocumentsConfig::default(),
+            documents!([{ "id": 1, "name": "kevin" }, { "id": 2, "name": "kevina" }]),
+        );
+        scheduler.add_documents(
+            &second,
+            IndexDocumentsConfig::default(),
+            documents!([{ "id": 1, "name": "benoit" }]),
+        );
+        scheduler.add_documents(
+            &first,
+            IndexDocumentsConfig::default(),
+            documents!([{ "id": 3, "name": "bob" }]),
+        );
+
+        let results: Vec<_> =
+            scheduler.execute().unwrap().into_iter().map(|r| r.unwrap()).collect();
+        assert_eq!(results[0].number_of_documents, 2);
+        assert_eq!(results[1].number_of_documents, 1);
+        assert_eq!(results[2].number_of_documents, 3);
+
+        let rtxn = first.read_txn().unwrap();
+        assert_eq!(first.number_of_documents(&rtxn).unwrap(), 3);
+        assert!(first.word_docids.get(&rtxn, "bob").unwrap().is_some());
+        drop(rtxn);
+
+        let rtxn = second.read_txn().unwrap();
+        assert_eq!(second.number_of_documents(&rtxn).unwrap(), 1);
+        assert!(second.word_docids.get(&rtxn, "benoit").unwrap().is_some());
+    }
+
+    #[test]
+    fn index_several_indexes_without_thread_pool() {
+        let first = TempIndex::new();
+        let second = TempIndex::new();
+        let indexer_config =
+            IndexerConfig { extraction_num_threads: Some(2), ..Default::default() };
+
+        let mut scheduler = IndexingScheduler::new(indexer_config);
+        scheduler.add_documents(
+            &first,
+            IndexDocumentsConfig::default(),
+            documents!([{ "id": 1, "name": "kevin" }]),
+        );
+        scheduler.add_documents(
+            &second,
+            IndexDocumentsConfig::default(),
+            documents!([{ "id": 1, "name": "benoit" }, { "id": 2, "name": "bob" }]),
+        );
+
+        let results: Vec<_> =
+            scheduler.execute().unwrap().into_iter().map(|r| r.unwrap()).collect();
+        assert_eq!(results[0].number_of_documents, 1);
+        assert_eq!(results[1].number_of_documents, 2);
+
+        let rtxn = second.read_txn().unwrap();
+        assert!(second.word_docids.get(&rtxn, "bob").unwrap().is_some());
+    }
+}
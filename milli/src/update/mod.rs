@@ -1,12 +1,25 @@
 pub use self::available_documents_ids::AvailableDocumentsIds;
 pub use self::clear_documents::ClearDocuments;
+pub use self::clear_soft_deleted::{ClearSoftDeleted, DEFAULT_SOFT_DELETED_THRESHOLD};
+pub use self::compact_facets::{CompactFacets, FacetsCompactionResult};
 pub use self::delete_documents::{DeleteDocuments, DocumentDeletionResult};
+pub use self::delete_field::DeleteField;
+pub use self::delete_stale_prefixes::DeleteStalePrefixes;
 pub use self::facets::Facets;
+pub(crate) use self::index_documents::{json_to_string, process_tokens};
 pub use self::index_documents::{
     DocumentAdditionResult, IndexDocuments, IndexDocumentsConfig, IndexDocumentsMethod,
+    IndexingParameters, SorterSpills, TruncatedDocument,
 };
-pub use self::indexer_config::IndexerConfig;
+pub use self::indexer_config::{
+    IndexerConfig, IndexerConfigBuilder, DEFAULT_MAX_VALUES_PER_WILDCARD_FACET,
+};
+pub use self::indexing_scheduler::IndexingScheduler;
+pub use self::prefix_databases::PrefixDatabases;
+pub use self::rename_facet_value::RenameFacetValue;
+pub use self::repair_index::{IndexRepairResult, RepairIndex};
 pub use self::settings::{Setting, Settings};
+pub use self::update_batch::{UpdateBatch, UpdateBatchResult};
 pub use self::update_step::UpdateIndexingStep;
 pub use self::word_prefix_docids::WordPrefixDocids;
 pub use self::word_prefix_pair_proximity_docids::WordPrefixPairProximityDocids;
@@ -15,11 +28,20 @@ pub use self::words_prefixes_fst::WordsPrefixesFst;
 
 mod available_documents_ids;
 mod clear_documents;
+mod clear_soft_deleted;
+mod compact_facets;
 mod delete_documents;
+mod delete_field;
+mod delete_stale_prefixes;
 mod facets;
 mod index_documents;
 mod indexer_config;
+mod indexing_scheduler;
+mod prefix_databases;
+mod rename_facet_value;
+mod repair_index;
 mod settings;
+mod update_batch;
 mod update_step;
 mod word_prefix_docids;
 mod word_prefix_pair_proximity_docids;
@@ -0,0 +1,164 @@
+use std::collections::HashSet;
+
+use crate::update::index_documents::{
+    as_cloneable_grenad, create_writer, fst_stream_into_vec, writer_into_reader, CursorClonableMmap,
+};
+use crate::update::{
+    IndexDocumentsConfig, IndexerConfig, WordPrefixDocids, WordPrefixPairProximityDocids,
+    WordPrefixPositionDocids, WordsPrefixesFst,
+};
+use crate::{Index, Result};
+
+/// Rebuilds the words prefixes FST and the prefix databases from scratch.
+///
+/// This must be run once after the document additions that deferred
+/// their prefix databases update, see [`IndexDocumentsConfig::defer_prefix_databases`].
+pub struct PrefixDatabases<'t, 'u, 'i, 'a> {
+    wtxn: &'t mut heed::RwTxn<'i, 'u>,
+    index: &'i Index,
+    indexer_config: &'a IndexerConfig,
+    config: IndexDocumentsConfig,
+}
+
+impl<'t, 'u, 'i, 'a> PrefixDatabases<'t, 'u, 'i, 'a> {
+    pub fn new(
+        wtxn: &'t mut heed::RwTxn<'i, 'u>,
+        index: &'i Index,
+        indexer_config: &'a IndexerConfig,
+        config: IndexDocumentsConfig,
+    ) -> PrefixDatabases<'t, 'u, 'i, 'a> {
+        PrefixDatabases { wtxn, index, indexer_config, config }
+    }
+
+    #[logging_timer::time("PrefixDatabases::{}")]
+    pub fn execute(self) -> Result<()> {
+        let mut builder = WordsPrefixesFst::new(self.wtxn, self.index);
+        if let Some(value) = self.config.words_prefix_threshold {
+            builder.threshold(value);
+        }
+        if let Some(value) = self.config.max_prefix_length {
+            builder.max_prefix_length(value);
+        }
+        builder.execute()?;
+
+        // Every prefix is considered as new, the prefix docids are
+        // therefore entirely computed from the words databases.
+        let new_prefix_fst_words = fst_stream_into_vec(&self.index.words_prefixes_fst(self.wtxn)?);
+        let common_prefix_fst_words = Vec::new();
+        let del_prefix_fst_words = HashSet::new();
+
+        self.index.word_prefix_docids.clear(self.wtxn)?;
+        self.index.exact_word_prefix_docids.clear(self.wtxn)?;
+        self.index.word_prefix_pair_proximity_docids.clear(self.wtxn)?;
+        self.index.word_prefix_position_docids.clear(self.wtxn)?;
+
+        let databases = [
+            (self.index.word_docids, self.index.word_prefix_docids),
+            (self.index.exact_word_docids, self.index.exact_word_prefix_docids),
+        ];
+        for (word_docids, word_prefix_docids) in databases {
+            let mut builder = WordPrefixDocids::new(self.wtxn, word_docids, word_prefix_docids);
+            builder.chunk_compression_type = self.indexer_config.chunk_compression_type;
+            builder.chunk_compression_level = self.indexer_config.chunk_compression_level;
+            builder.max_nb_chunks = self.indexer_config.max_nb_chunks;
+            builder.max_memory = self.indexer_config.max_memory;
+            builder.execute(
+                empty_reader(self.indexer_config)?.into_cursor()?,
+                &new_prefix_fst_words,
+                &common_prefix_fst_words,
+                &del_prefix_fst_words,
+            )?;
+        }
+
+        let mut builder = WordPrefixPairProximityDocids::new(self.wtxn, self.index);
+        builder.chunk_compression_type = self.indexer_config.chunk_compression_type;
+        builder.chunk_compression_level = self.indexer_config.chunk_compression_level;
+        builder.max_nb_chunks = self.indexer_config.max_nb_chunks;
+        builder.max_memory = self.indexer_config.max_memory;
+        builder.execute(
+            empty_reader(self.indexer_config)?,
+            &new_prefix_fst_words,
+            &common_prefix_fst_words,
+            &del_prefix_fst_words,
+        )?;
+
+        let mut builder = WordPrefixPositionDocids::new(self.wtxn, self.index);
+        builder.chunk_compression_type = self.indexer_config.chunk_compression_type;
+        builder.chunk_compression_level = self.indexer_config.chunk_compression_level;
+        builder.max_nb_chunks = self.indexer_config.max_nb_chunks;
+        builder.max_memory = self.indexer_config.max_memory;
+        if let Some(value) = self.config.words_positions_level_group_size {
+            builder.level_group_size(value);
+        }
+        if let Some(value) = self.config.words_positions_min_level_size {
+            builder.min_level_size(value);
+        }
+        if let Some(value) = self.config.words_prefix_positions_limit {
+            builder.positions_limit(value);
+        }
+        builder.execute(
+            empty_reader(self.indexer_config)?,
+            &new_prefix_fst_words,
+            &common_prefix_fst_words,
+            &del_prefix_fst_words,
+        )?;
+
+        self.index.delete_pending_prefix_databases_update(self.wtxn)?;
+
+        Ok(())
+    }
+}
+
+/// Returns a reader without any entry, there is no new data to merge with the databases.
+fn empty_reader(indexer_config: &IndexerConfig) -> Result<grenad::Reader<CursorClonableMmap>> {
+    let writer = create_writer(
+        indexer_config.chunk_compression_type,
+        indexer_config.chunk_compression_level,
+        tempfile::tempfile()?,
+    );
+    let reader = writer_into_reader(writer)?;
+    unsafe { as_cloneable_grenad(&reader) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::tests::TempIndex;
+    use crate::update::IndexDocuments;
+
+    #[test]
+    fn rebuild_deferred_prefix_databases() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        // The 60 words share enough prefixes to reach the prefix threshold.
+        let content: Vec<_> = (0..60).map(|i| format!("hello{}", i)).collect();
+        let content = content.join(" ");
+
+        let mut wtxn = index.write_txn().unwrap();
+        let indexing_config =
+            IndexDocumentsConfig { defer_prefix_databases: true, ..Default::default() };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(documents!([{ "id": 1, "content": content }])).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.pending_prefix_databases_update(&rtxn).unwrap());
+        assert!(index.word_prefix_docids.get(&rtxn, "hell").unwrap().is_none());
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        PrefixDatabases::new(&mut wtxn, &index, &config, IndexDocumentsConfig::default())
+            .execute()
+            .unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(!index.pending_prefix_databases_update(&rtxn).unwrap());
+        assert!(index.words_prefixes_fst(&rtxn).unwrap().contains("hell"));
+        let docids = index.word_prefix_docids.get(&rtxn, "hell").unwrap().unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0]);
+    }
+}
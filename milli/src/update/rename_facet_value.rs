@@ -0,0 +1,281 @@
+use heed::types::ByteSlice;
+use roaring::RoaringBitmap;
+use serde_json::{Map, Value};
+
+use super::delete_field::is_same_or_parent;
+use super::{Facets, IndexerConfig};
+use crate::error::{InternalError, UserError};
+use crate::hashed_fields::hash_facet_value;
+use crate::{is_faceted, Index, Result, BEU32};
+
+/// Renames a string facet value of a field in all the documents of the index, e.g. to fix a typo
+/// in a category. Renaming a value into another existing value merges both values.
+///
+/// The facet databases and the stored documents are rewritten in place, the documents are not
+/// indexed again: the words and the numbers extracted from the old value and the content hash
+/// of the documents are only updated the next time the documents are indexed.
+pub struct RenameFacetValue<'t, 'u, 'i, 'a> {
+    wtxn: &'t mut heed::RwTxn<'i, 'u>,
+    index: &'i Index,
+    indexer_config: &'a IndexerConfig,
+    field: String,
+    old_value: String,
+    new_value: String,
+}
+
+impl<'t, 'u, 'i, 'a> RenameFacetValue<'t, 'u, 'i, 'a> {
+    pub fn new(
+        wtxn: &'t mut heed::RwTxn<'i, 'u>,
+        index: &'i Index,
+        indexer_config: &'a IndexerConfig,
+        field: impl Into<String>,
+        old_value: impl Into<String>,
+        new_value: impl Into<String>,
+    ) -> RenameFacetValue<'t, 'u, 'i, 'a> {
+        RenameFacetValue {
+            wtxn,
+            index,
+            indexer_config,
+            field: field.into(),
+            old_value: old_value.into(),
+            new_value: new_value.into(),
+        }
+    }
+
+    /// Returns the number of documents that were modified.
+    pub fn execute(self) -> Result<u64> {
+        let faceted_fields = self.index.faceted_fields(self.wtxn)?;
+        if !is_faceted(&self.field, &faceted_fields) {
+            return Err(UserError::AttributeNotFaceted { field: self.field }.into());
+        }
+
+        let fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+        let field_id = match fields_ids_map.id(&self.field) {
+            Some(field_id) => field_id,
+            None => return Ok(0),
+        };
+
+        // The values of the hashed fields are stored and indexed hashed, they are renamed hashed.
+        let top_level_field = self.field.split('.').next().unwrap_or_default();
+        let (old_value, new_value) =
+            if self.index.hashed_fields(self.wtxn)?.contains(&top_level_field) {
+                let salt = self.index.hashed_fields_salt(self.wtxn)?.unwrap_or_default();
+                (hash_facet_value(salt, &self.old_value), hash_facet_value(salt, &self.new_value))
+            } else {
+                (self.old_value, self.new_value)
+            };
+
+        // The facet values are stored normalized, like they are when they are extracted.
+        let old_normalized = old_value.trim().to_lowercase();
+        let new_normalized = new_value.trim().to_lowercase();
+        let facet_db = self.index.facet_id_string_docids;
+        let (original, all_docids) =
+            match facet_db.get(self.wtxn, &(field_id, old_normalized.as_str()))? {
+                Some((original, docids)) => (original.to_string(), docids),
+                None => return Ok(0),
+            };
+        let mut docids = all_docids.clone();
+        self.index.remove_soft_deleted_documents(self.wtxn, &mut docids)?;
+
+        // The value is stored under the field itself or under one of its parents.
+        let stored_fields: Vec<_> = fields_ids_map
+            .iter()
+            .filter(|(_, name)| is_same_or_parent(name, &self.field))
+            .map(|(id, name)| (id, name.to_string()))
+            .collect();
+
+        let documents_db = self.index.documents.remap_data_type::<ByteSlice>();
+        let mut renamed_docids = RoaringBitmap::new();
+        let mut buffer = Vec::new();
+        for docid in &docids {
+            let obkv = match self.index.documents.get(self.wtxn, &BEU32::new(docid))? {
+                Some(obkv) => obkv,
+                None => continue,
+            };
+
+            let mut renamed = false;
+            buffer.clear();
+            let mut writer = obkv::KvWriter::new(&mut buffer);
+            for (id, bytes) in obkv.iter() {
+                match stored_fields.iter().find(|(stored_id, _)| *stored_id == id) {
+                    Some((_, name)) => {
+                        let value =
+                            serde_json::from_slice(bytes).map_err(InternalError::SerdeJson)?;
+                        let mut object = Map::new();
+                        object.insert(name.clone(), value);
+                        if rename_in_object(&mut object, &self.field, &old_normalized, &new_value) {
+                            let value = serde_json::to_vec(&object[name.as_str()])
+                                .map_err(InternalError::SerdeJson)?;
+                            writer.insert(id, value)?;
+                            renamed = true;
+                        } else {
+                            writer.insert(id, bytes)?;
+                        }
+                    }
+                    None => writer.insert(id, bytes)?,
+                }
+            }
+            writer.finish()?;
+
+            if renamed {
+                documents_db.put(self.wtxn, &BEU32::new(docid), &buffer)?;
+                renamed_docids.insert(docid);
+            }
+        }
+
+        if renamed_docids.is_empty() {
+            return Ok(0);
+        }
+
+        // The renamed documents move from the old facet value to the new one.
+        let strings_db = self.index.field_id_docid_facet_strings;
+        for docid in &renamed_docids {
+            strings_db.delete(self.wtxn, &(field_id, docid, old_normalized.as_str()))?;
+            if !new_normalized.is_empty() {
+                let key = (field_id, docid, new_normalized.as_str());
+                strings_db.put(self.wtxn, &key, new_value.as_str())?;
+            }
+        }
+
+        let old_docids = all_docids - &renamed_docids;
+        if old_docids.is_empty() {
+            facet_db.delete(self.wtxn, &(field_id, old_normalized.as_str()))?;
+        } else {
+            let key = (field_id, old_normalized.as_str());
+            facet_db.put(self.wtxn, &key, &(original.as_str(), old_docids))?;
+        }
+
+        if !new_normalized.is_empty() {
+            let key = (field_id, new_normalized.as_str());
+            let (new_original, new_docids) = match facet_db.get(self.wtxn, &key)? {
+                Some((original, docids)) => (original.to_string(), docids | &renamed_docids),
+                None => (new_value.clone(), renamed_docids.clone()),
+            };
+            facet_db.put(self.wtxn, &key, &(new_original.as_str(), new_docids))?;
+        }
+
+        // The facet levels are computed again from the rewritten level zero.
+        let mut builder = Facets::new(self.wtxn, self.index);
+        builder.chunk_compression_type = self.indexer_config.chunk_compression_type;
+        builder.chunk_compression_level = self.indexer_config.chunk_compression_level;
+        builder.execute()?;
+
+        Ok(renamed_docids.len())
+    }
+}
+
+/// Renames the values of the object found under the dotted `path`.
+/// Returns `true` if a value was renamed.
+fn rename_in_object(object: &mut Map<String, Value>, path: &str, old: &str, new: &str) -> bool {
+    let mut renamed = false;
+    for (key, value) in object.iter_mut() {
+        if let Some(rest) = path.strip_prefix(key.as_str()) {
+            if rest.is_empty() {
+                renamed |= rename_in_value(value, None, old, new);
+            } else if let Some(rest) = rest.strip_prefix('.') {
+                renamed |= rename_in_value(value, Some(rest), old, new);
+            }
+        }
+    }
+    renamed
+}
+
+/// Renames the strings equal to the normalized `old` value, in arrays too.
+/// Returns `true` if a value was renamed.
+fn rename_in_value(value: &mut Value, path: Option<&str>, old: &str, new: &str) -> bool {
+    match (value, path) {
+        (Value::String(string), None) if string.trim().to_lowercase() == old => {
+            *string = new.to_string();
+            true
+        }
+        (Value::Array(values), path) => values
+            .iter_mut()
+            .fold(false, |renamed, value| rename_in_value(value, path, old, new) | renamed),
+        (Value::Object(object), Some(path)) => rename_in_object(object, path, old, new),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use heed::EnvOpenOptions;
+    use maplit::{btreemap, hashset};
+
+    use super::*;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, Settings};
+    use crate::{obkv_to_json, Filter};
+
+    #[test]
+    fn rename_facet_value() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_primary_key(S("id"));
+        builder.set_filterable_fields(hashset! { S("category"), S("meta.tags") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "category": "Electronix", "title": "phone" },
+            { "id": 1, "category": ["electronix ", "Books"], "meta": { "tags": ["electronix"] } },
+            { "id": 2, "category": "Electronics" },
+            { "id": 3, "category": "Books" }
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let builder = RenameFacetValue::new(
+            &mut wtxn,
+            &index,
+            &config,
+            "category",
+            "ELECTRONIX",
+            "Electronics",
+        );
+        assert_eq!(builder.execute().unwrap(), 2);
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let filter = Filter::from_str("category = electronics").unwrap().unwrap();
+        let documents_ids: Vec<_> = filter.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+        assert_eq!(documents_ids, vec![0, 1, 2]);
+        let filter = Filter::from_str("category = electronix").unwrap().unwrap();
+        assert!(filter.evaluate(&rtxn, &index).unwrap().is_empty());
+        let distribution = index.facets_distribution(&rtxn).facets(["category"]).execute();
+        let expected = btreemap! { S("Books") => 2, S("Electronics") => 3 };
+        assert_eq!(distribution.unwrap()["category"], expected);
+        assert!(index.verify(&rtxn).unwrap().is_valid());
+        // the other fields are left untouched
+        let filter = Filter::from_str("meta.tags = electronix").unwrap().unwrap();
+        let documents_ids: Vec<_> = filter.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+        assert_eq!(documents_ids, vec![1]);
+
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let all_fields: Vec<_> = fields_ids_map.ids().collect();
+        let (_, obkv) = index.documents(&rtxn, Some(1)).unwrap().pop().unwrap();
+        let document = obkv_to_json(&all_fields, &fields_ids_map, obkv).unwrap();
+        assert_eq!(document["category"], serde_json::json!(["Electronics", "Books"]));
+        drop(rtxn);
+
+        // only the faceted fields values can be renamed
+        let mut wtxn = index.write_txn().unwrap();
+        let builder = RenameFacetValue::new(&mut wtxn, &index, &config, "title", "phone", "mobile");
+        assert_eq!(
+            builder.execute().unwrap_err().to_string(),
+            "Attribute `title` is not filterable or sortable, its facet values cannot be renamed."
+        );
+    }
+}
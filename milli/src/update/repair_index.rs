@@ -0,0 +1,502 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+
+use heed::types::{ByteSlice, DecodeIgnore};
+use heed::{BytesDecode, BytesEncode, Database};
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::OffsetDateTime;
+
+use crate::error::{InternalError, SerializationError};
+use crate::heed_codec::facet::{
+    FacetLevelValueU32Codec, FacetStringLevelZeroValueCodec, FacetStringZeroBoundsValueCodec,
+};
+use crate::heed_codec::CboRoaringBitmapCodec;
+use crate::{ExternalDocumentsIds, FieldDistribution, Index, Result, BEU32};
+
+/// Salvages an index after a partial write or a disk issue, without reimporting the documents.
+///
+/// The documents database is considered as the source of truth: the documents ids and the
+/// external documents ids are rebuilt from it and the entries of the other databases that
+/// reference unknown documents are dropped.
+pub struct RepairIndex<'t, 'u, 'i> {
+    wtxn: &'t mut heed::RwTxn<'i, 'u>,
+    index: &'i Index,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexRepairResult {
+    /// The number of documents in the index after the repair.
+    pub number_of_documents: u64,
+    /// The number of documents that were removed because a more recent
+    /// document was stored under the same external id.
+    pub removed_duplicated_documents: u64,
+    /// The number of database entries that were updated or removed
+    /// because they referenced unknown documents.
+    pub repaired_entries: u64,
+}
+
+impl<'t, 'u, 'i> RepairIndex<'t, 'u, 'i> {
+    pub fn new(wtxn: &'t mut heed::RwTxn<'i, 'u>, index: &'i Index) -> RepairIndex<'t, 'u, 'i> {
+        RepairIndex { wtxn, index }
+    }
+
+    pub fn execute(self) -> Result<IndexRepairResult> {
+        self.index.set_updated_at(self.wtxn, &OffsetDateTime::now_utc())?;
+        let Index {
+            env: _env,
+            main: _main,
+            word_docids,
+            exact_word_docids,
+            word_prefix_docids,
+            exact_word_prefix_docids,
+            docid_word_positions,
+            word_pair_proximity_docids,
+            word_prefix_pair_proximity_docids,
+            word_position_docids,
+            field_id_word_count_docids,
+            docid_field_id_word_count,
+            word_prefix_position_docids,
+            facet_id_f64_docids,
+            facet_id_string_docids,
+            field_id_docid_facet_f64s,
+            field_id_docid_facet_strings,
+            documents,
+            vectors,
+            dfa_cache: _,
+        } = self.index;
+
+        let fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+        let primary_key_id = self
+            .index
+            .primary_key(self.wtxn)?
+            .and_then(|primary_key| fields_ids_map.id(primary_key));
+
+        // We keep the previous external ids for the documents that do not
+        // store their primary key at the top level, e.g. nested primary keys.
+        let previous_external_ids: HashMap<_, _> = self
+            .index
+            .external_documents_ids(self.wtxn)?
+            .to_hash_map()
+            .into_iter()
+            .map(|(external_id, docid)| (docid, external_id))
+            .collect();
+
+        // The soft deleted documents are purged like the other unreachable entries.
+        for docid in &self.index.soft_deleted_documents_ids(self.wtxn)? {
+            documents.delete(self.wtxn, &BEU32::new(docid))?;
+        }
+        self.index.put_soft_deleted_documents_ids(self.wtxn, &RoaringBitmap::new())?;
+
+        // We retrieve the external id of every stored document, the most recent
+        // document (the highest document id) wins when an external id is duplicated.
+        let mut documents_ids = RoaringBitmap::new();
+        let mut external_ids = BTreeMap::new();
+        let mut duplicated_documents_ids = RoaringBitmap::new();
+        for result in documents.iter(self.wtxn)? {
+            let (docid, obkv) = result?;
+            let docid = docid.get();
+            documents_ids.insert(docid);
+
+            let external_id = match primary_key_id.and_then(|id| obkv.get(id)) {
+                Some(bytes) => match serde_json::from_slice::<Value>(bytes)
+                    .map_err(InternalError::SerdeJson)?
+                {
+                    Value::String(string) => Some(string),
+                    otherwise => Some(otherwise.to_string()),
+                },
+                None => previous_external_ids.get(&docid).cloned(),
+            };
+
+            if let Some(external_id) = external_id {
+                if let Some(previous) = external_ids.insert(external_id, docid) {
+                    duplicated_documents_ids.insert(previous);
+                }
+            }
+        }
+
+        for docid in &duplicated_documents_ids {
+            documents.delete(self.wtxn, &BEU32::new(docid))?;
+        }
+        documents_ids -= &duplicated_documents_ids;
+
+        let mut builder = fst::MapBuilder::memory();
+        for (external_id, docid) in external_ids {
+            if documents_ids.contains(docid) {
+                builder.insert(external_id, docid as u64)?;
+            }
+        }
+        let hard = builder.into_map().map_data(Cow::Owned)?;
+        let external_documents_ids =
+            ExternalDocumentsIds::new(hard, fst::Map::default().map_data(Cow::Owned)?);
+        self.index.put_external_documents_ids(self.wtxn, &external_documents_ids)?;
+        self.index.put_documents_ids(self.wtxn, &documents_ids)?;
+
+        // We recompute the field distribution from the remaining documents.
+        let mut field_distribution = FieldDistribution::new();
+        for result in documents.iter(self.wtxn)? {
+            let (_docid, obkv) = result?;
+            for (field_id, _) in obkv.iter() {
+                if let Some(name) = fields_ids_map.name(field_id) {
+                    *field_distribution.entry(name.to_string()).or_default() += 1;
+                }
+            }
+        }
+        self.index.put_field_distribution(self.wtxn, &field_distribution)?;
+
+        // We drop the entries that reference unknown documents.
+        let mut repaired_entries = 0;
+        for db in [word_docids, exact_word_docids, word_prefix_docids, exact_word_prefix_docids] {
+            repaired_entries +=
+                retain_docids(self.wtxn, db.remap_key_type::<ByteSlice>(), &documents_ids)?;
+        }
+
+        repaired_entries += retain_docids(
+            self.wtxn,
+            word_pair_proximity_docids.remap_key_type::<ByteSlice>(),
+            &documents_ids,
+        )?;
+        repaired_entries += retain_docids(
+            self.wtxn,
+            word_prefix_pair_proximity_docids.remap_key_type::<ByteSlice>(),
+            &documents_ids,
+        )?;
+        repaired_entries += retain_docids(
+            self.wtxn,
+            word_position_docids.remap_key_type::<ByteSlice>(),
+            &documents_ids,
+        )?;
+        repaired_entries += retain_docids(
+            self.wtxn,
+            word_prefix_position_docids.remap_key_type::<ByteSlice>(),
+            &documents_ids,
+        )?;
+        repaired_entries += retain_docids(
+            self.wtxn,
+            field_id_word_count_docids.remap_key_type::<ByteSlice>(),
+            &documents_ids,
+        )?;
+        repaired_entries += retain_docids(
+            self.wtxn,
+            facet_id_f64_docids.remap_key_type::<ByteSlice>(),
+            &documents_ids,
+        )?;
+        repaired_entries += retain_docids_in_facet_id_string_docids(
+            self.wtxn,
+            facet_id_string_docids,
+            &documents_ids,
+        )?;
+
+        let mut iter =
+            docid_word_positions.remap_data_type::<DecodeIgnore>().iter_mut(self.wtxn)?;
+        while let Some(result) = iter.next() {
+            let ((docid, _word), ()) = result?;
+            if !documents_ids.contains(docid) {
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.del_current()? };
+                repaired_entries += 1;
+            }
+        }
+        drop(iter);
+
+        let mut iter =
+            docid_field_id_word_count.remap_data_type::<DecodeIgnore>().iter_mut(self.wtxn)?;
+        while let Some(result) = iter.next() {
+            let ((docid, _fid), ()) = result?;
+            if !documents_ids.contains(docid) {
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.del_current()? };
+                repaired_entries += 1;
+            }
+        }
+        drop(iter);
+
+        let mut iter = vectors.remap_data_type::<DecodeIgnore>().iter_mut(self.wtxn)?;
+        while let Some(result) = iter.next() {
+            let ((docid, _fid), ()) = result?;
+            if !documents_ids.contains(docid) {
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.del_current()? };
+                repaired_entries += 1;
+            }
+        }
+        drop(iter);
+
+        let mut iter =
+            field_id_docid_facet_f64s.remap_data_type::<DecodeIgnore>().iter_mut(self.wtxn)?;
+        while let Some(result) = iter.next() {
+            let ((_fid, docid, _value), ()) = result?;
+            if !documents_ids.contains(docid) {
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.del_current()? };
+                repaired_entries += 1;
+            }
+        }
+        drop(iter);
+
+        let mut iter =
+            field_id_docid_facet_strings.remap_data_type::<DecodeIgnore>().iter_mut(self.wtxn)?;
+        while let Some(result) = iter.next() {
+            let ((_fid, docid, _value), ()) = result?;
+            if !documents_ids.contains(docid) {
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.del_current()? };
+                repaired_entries += 1;
+            }
+        }
+        drop(iter);
+
+        // We restrict the faceted documents ids to the known documents.
+        for field_id in fields_ids_map.ids() {
+            let docids = self.index.number_faceted_documents_ids(self.wtxn, field_id)?;
+            if !docids.is_subset(&documents_ids) {
+                let docids = docids & &documents_ids;
+                self.index.put_number_faceted_documents_ids(self.wtxn, field_id, &docids)?;
+                repaired_entries += 1;
+            }
+
+            let docids = self.index.string_faceted_documents_ids(self.wtxn, field_id)?;
+            if !docids.is_subset(&documents_ids) {
+                let docids = docids & &documents_ids;
+                self.index.put_string_faceted_documents_ids(self.wtxn, field_id, &docids)?;
+                repaired_entries += 1;
+            }
+
+            for value in [false, true] {
+                let docids =
+                    self.index.boolean_faceted_documents_ids(self.wtxn, field_id, value)?;
+                if !docids.is_subset(&documents_ids) {
+                    let docids = docids & &documents_ids;
+                    self.index
+                        .put_boolean_faceted_documents_ids(self.wtxn, field_id, value, &docids)?;
+                    repaired_entries += 1;
+                }
+            }
+        }
+
+        if let Some(mut rtree) = self.index.geo_rtree(self.wtxn)? {
+            let points_to_remove: Vec<_> = rtree
+                .iter()
+                .filter(|&point| !documents_ids.contains(point.data.0))
+                .cloned()
+                .collect();
+            for point in &points_to_remove {
+                rtree.remove(point);
+            }
+            repaired_entries += points_to_remove.len() as u64;
+
+            let geo_faceted_doc_ids = self.index.geo_faceted_documents_ids(self.wtxn)?;
+            self.index.put_geo_rtree(self.wtxn, &rtree)?;
+            self.index.put_geo_faceted_documents_ids(
+                self.wtxn,
+                &(geo_faceted_doc_ids & &documents_ids),
+            )?;
+        }
+
+        // We rebuild the words and prefixes FSTs from the remaining words.
+        let words_fst = keys_union_fst(self.wtxn, word_docids, exact_word_docids)?;
+        self.index.put_words_fst(self.wtxn, &words_fst)?;
+        let prefixes_fst = keys_union_fst(self.wtxn, word_prefix_docids, exact_word_prefix_docids)?;
+        self.index.put_words_prefixes_fst(self.wtxn, &prefixes_fst)?;
+        // The parameters of the rebuilt prefixes are unknown, the next update rebuilds them.
+        self.index.delete_words_prefixes_fst_parameters(self.wtxn)?;
+        self.index.delete_deleted_words_fst(self.wtxn)?;
+
+        Ok(IndexRepairResult {
+            number_of_documents: documents_ids.len(),
+            removed_duplicated_documents: duplicated_documents_ids.len(),
+            repaired_entries,
+        })
+    }
+}
+
+/// Removes the unknown documents ids from the bitmaps of this database and deletes
+/// the entries that become empty, returns the number of updated or removed entries.
+fn retain_docids<DC>(
+    wtxn: &mut heed::RwTxn,
+    db: Database<ByteSlice, DC>,
+    documents_ids: &RoaringBitmap,
+) -> heed::Result<u64>
+where
+    DC: for<'a> BytesDecode<'a, DItem = RoaringBitmap>
+        + for<'a> BytesEncode<'a, EItem = RoaringBitmap>,
+{
+    let mut repaired_entries = 0;
+    let mut iter = db.iter_mut(wtxn)?;
+    while let Some(result) = iter.next() {
+        let (key, mut docids) = result?;
+        let previous_len = docids.len();
+        docids &= documents_ids;
+        if docids.is_empty() {
+            // safety: we don't keep references from inside the LMDB database.
+            unsafe { iter.del_current()? };
+            repaired_entries += 1;
+        } else if docids.len() != previous_len {
+            let key = key.to_owned();
+            // safety: we don't keep references from inside the LMDB database.
+            unsafe { iter.put_current(&key, &docids)? };
+            repaired_entries += 1;
+        }
+    }
+
+    Ok(repaired_entries)
+}
+
+fn retain_docids_in_facet_id_string_docids<C, D>(
+    wtxn: &mut heed::RwTxn,
+    db: &Database<C, D>,
+    documents_ids: &RoaringBitmap,
+) -> Result<u64> {
+    let db_name = Some(crate::index::db_name::FACET_ID_STRING_DOCIDS);
+    let mut repaired_entries = 0;
+    let mut iter = db.remap_types::<ByteSlice, ByteSlice>().iter_mut(wtxn)?;
+    while let Some(result) = iter.next() {
+        let (key, val) = result?;
+        let value_bytes = match FacetLevelValueU32Codec::bytes_decode(key) {
+            Some(_) => {
+                // If we are able to parse this key it means it is a facet string group
+                // level key. We must then parse the value using the appropriate codec.
+                let (group, mut docids) =
+                    FacetStringZeroBoundsValueCodec::<CboRoaringBitmapCodec>::bytes_decode(val)
+                        .ok_or(SerializationError::Decoding { db_name })?;
+
+                let previous_len = docids.len();
+                docids &= documents_ids;
+                if docids.is_empty() {
+                    None
+                } else if docids.len() != previous_len {
+                    let val = &(group, docids);
+                    let bytes =
+                        FacetStringZeroBoundsValueCodec::<CboRoaringBitmapCodec>::bytes_encode(val)
+                            .ok_or(SerializationError::Encoding { db_name })?;
+                    Some(bytes.into_owned())
+                } else {
+                    continue;
+                }
+            }
+            None => {
+                // The key corresponds to a level zero facet string.
+                let (original_value, mut docids) =
+                    FacetStringLevelZeroValueCodec::bytes_decode(val)
+                        .ok_or(SerializationError::Decoding { db_name })?;
+
+                let previous_len = docids.len();
+                docids &= documents_ids;
+                if docids.is_empty() {
+                    None
+                } else if docids.len() != previous_len {
+                    let val = &(original_value, docids);
+                    let bytes = FacetStringLevelZeroValueCodec::bytes_encode(val)
+                        .ok_or(SerializationError::Encoding { db_name })?;
+                    Some(bytes.into_owned())
+                } else {
+                    continue;
+                }
+            }
+        };
+
+        match value_bytes {
+            // safety: we don't keep references from inside the LMDB database.
+            None => unsafe { iter.del_current()? },
+            Some(value_bytes) => {
+                let key = key.to_owned();
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.put_current(&key, &value_bytes)? }
+            }
+        };
+        repaired_entries += 1;
+    }
+
+    Ok(repaired_entries)
+}
+
+/// Builds an FST of the union of the keys of both databases.
+fn keys_union_fst<DC>(
+    rtxn: &heed::RoTxn,
+    left: &Database<heed::types::Str, DC>,
+    right: &Database<heed::types::Str, DC>,
+) -> Result<fst::Set<Vec<u8>>> {
+    let mut left_keys = fst::SetBuilder::memory();
+    for result in left.remap_data_type::<DecodeIgnore>().iter(rtxn)? {
+        let (key, ()) = result?;
+        left_keys.insert(key)?;
+    }
+
+    let mut right_keys = fst::SetBuilder::memory();
+    for result in right.remap_data_type::<DecodeIgnore>().iter(rtxn)? {
+        let (key, ()) = result?;
+        right_keys.insert(key)?;
+    }
+
+    let left_keys = left_keys.into_set();
+    let right_keys = right_keys.into_set();
+    let mut builder = fst::SetBuilder::memory();
+    builder.extend_stream(left_keys.op().add(&right_keys).union())?;
+    Ok(builder.into_set())
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+
+    use super::*;
+    use crate::index::tests::TempIndex;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig};
+
+    #[test]
+    fn repair_dangling_documents() {
+        let index = TempIndex::new();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "name": "kevin" },
+            { "id": 2, "name": "bob" },
+            { "id": 3, "name": "kevina" },
+        ]);
+        let config = IndexerConfig::default();
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // We simulate a partial write by removing the stored document of bob.
+        let mut wtxn = index.write_txn().unwrap();
+        index.documents.delete(&mut wtxn, &BEU32::new(1)).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(!index.verify(&rtxn).unwrap().is_valid());
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let result = RepairIndex::new(&mut wtxn, &index).execute().unwrap();
+        wtxn.commit().unwrap();
+
+        assert_eq!(result.number_of_documents, 2);
+        assert_eq!(result.removed_duplicated_documents, 0);
+        assert!(result.repaired_entries > 0);
+
+        let rtxn = index.read_txn().unwrap();
+        let report = index.verify(&rtxn).unwrap();
+        assert!(report.is_valid(), "{:?}", report);
+        assert!(index.word_docids.get(&rtxn, "bob").unwrap().is_none());
+        assert!(!index.words_fst(&rtxn).unwrap().contains("bob"));
+
+        let external_ids = index.external_documents_ids(&rtxn).unwrap();
+        assert_eq!(external_ids.get("1"), Some(0));
+        assert_eq!(external_ids.get("2"), None);
+        assert_eq!(external_ids.get("3"), Some(2));
+        assert_eq!(index.field_distribution(&rtxn).unwrap().get("name"), Some(&2));
+
+        let mut results = index.search(&rtxn).query(S("kevin")).execute().unwrap();
+        results.documents_ids.sort_unstable();
+        assert_eq!(results.documents_ids, vec![0, 2]);
+    }
+}
@@ -1,19 +1,28 @@
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::mem;
+use std::num::NonZeroU32;
 use std::result::Result as StdResult;
 
+use heed::types::ByteSlice;
 use itertools::Itertools;
-use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
+use meilisearch_tokenizer::{Analyzer, AnalyzerConfig, TokenKind};
+use roaring::RoaringBitmap;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use time::OffsetDateTime;
 
 use super::index_documents::{IndexDocumentsConfig, Transform};
 use super::IndexerConfig;
 use crate::criterion::Criterion;
-use crate::error::UserError;
+use crate::error::{InternalError, UserError};
+use crate::facet::NumberCoercion;
+use crate::hashed_fields::hash_json_value;
 use crate::index::{DEFAULT_MIN_WORD_LEN_ONE_TYPO, DEFAULT_MIN_WORD_LEN_TWO_TYPOS};
 use crate::update::index_documents::IndexDocumentsMethod;
 use crate::update::{ClearDocuments, IndexDocuments, UpdateIndexingStep};
-use crate::{FieldsIdsMap, Index, Result};
+use crate::{
+    FieldId, FieldsIdsMap, GeoDistanceFormula, Index, NormalizerOptions, QueryRewrite, Result,
+    WordBoundaries, ALL_FIELDS_FACET,
+};
 
 #[derive(Debug, Clone, PartialEq, Copy)]
 pub enum Setting<T> {
@@ -96,6 +105,7 @@ pub struct Settings<'a, 't, 'u, 'i> {
     criteria: Setting<Vec<String>>,
     stop_words: Setting<BTreeSet<String>>,
     distinct_field: Setting<String>,
+    distinct_fields: Setting<Vec<String>>,
     synonyms: Setting<HashMap<String, Vec<String>>>,
     primary_key: Setting<String>,
     authorize_typos: Setting<bool>,
@@ -104,6 +114,32 @@ pub struct Settings<'a, 't, 'u, 'i> {
     exact_words: Setting<BTreeSet<String>>,
     /// Attributes on which typo tolerance is disabled.
     exact_attributes: Setting<HashSet<String>>,
+    /// Attributes that are ignored when computing the exactness criterion.
+    exactness_excluded_attributes: Setting<HashSet<String>>,
+    transposition_counts_as_one_typo: Setting<bool>,
+    disable_typos_on_numbers: Setting<bool>,
+    /// Whether the words of a phrase must follow each other in a single attribute.
+    phrases_within_attribute: Setting<bool>,
+    /// The maximum number of positions indexed for some attributes.
+    attributes_positions_limits: Setting<BTreeMap<String, u32>>,
+    /// Attributes for which the position of the values in arrays is indexed.
+    positional_array_fields: Setting<HashSet<String>>,
+    /// Attributes whose values are hashed together to deduplicate the documents.
+    content_hash_fields: Setting<HashSet<String>>,
+    /// Whether the numeric strings of some faceted attributes are also indexed as numbers.
+    number_coercions: Setting<BTreeMap<String, NumberCoercion>>,
+    /// The boundaries on which the words are split in addition to the separators.
+    word_boundaries: Setting<WordBoundaries>,
+    /// The language specific normalizations applied to the words.
+    normalizer_options: Setting<NormalizerOptions>,
+    /// Attributes whose values are only stored and indexed hashed.
+    hashed_fields: Setting<HashSet<String>>,
+    /// The formula used to compute the distances of the geo filters and sorts.
+    geo_distance_formula: Setting<GeoDistanceFormula>,
+    /// The multiple to which the positions of the word position databases are rounded down.
+    word_position_precision: Setting<NonZeroU32>,
+    /// The rules rewriting the queries, by pattern.
+    query_rewrites: Setting<BTreeMap<String, QueryRewrite>>,
 }
 
 impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
@@ -122,6 +158,7 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
             criteria: Setting::NotSet,
             stop_words: Setting::NotSet,
             distinct_field: Setting::NotSet,
+            distinct_fields: Setting::NotSet,
             synonyms: Setting::NotSet,
             primary_key: Setting::NotSet,
             authorize_typos: Setting::NotSet,
@@ -129,6 +166,20 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
             min_word_len_two_typos: Setting::NotSet,
             min_word_len_one_typo: Setting::NotSet,
             exact_attributes: Setting::NotSet,
+            exactness_excluded_attributes: Setting::NotSet,
+            transposition_counts_as_one_typo: Setting::NotSet,
+            disable_typos_on_numbers: Setting::NotSet,
+            phrases_within_attribute: Setting::NotSet,
+            attributes_positions_limits: Setting::NotSet,
+            positional_array_fields: Setting::NotSet,
+            content_hash_fields: Setting::NotSet,
+            number_coercions: Setting::NotSet,
+            word_boundaries: Setting::NotSet,
+            normalizer_options: Setting::NotSet,
+            hashed_fields: Setting::NotSet,
+            geo_distance_formula: Setting::NotSet,
+            word_position_precision: Setting::NotSet,
+            query_rewrites: Setting::NotSet,
             indexer_config,
         }
     }
@@ -190,14 +241,44 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         self.distinct_field = Setting::Set(distinct_field);
     }
 
+    pub fn reset_distinct_fields(&mut self) {
+        self.distinct_fields = Setting::Reset;
+    }
+
+    /// Collapses the documents on the tuple of values of the given fields,
+    /// it replaces the distinct field if there is one.
+    pub fn set_distinct_fields(&mut self, distinct_fields: Vec<String>) {
+        self.distinct_fields = Setting::Set(distinct_fields);
+    }
+
     pub fn reset_synonyms(&mut self) {
         self.synonyms = Setting::Reset;
     }
 
+    /// Sets one-way synonyms, the words or phrases of the keys also match their synonyms but
+    /// the synonyms don't match the keys, e.g. `"nyc" => ["new york city"]`.
     pub fn set_synonyms(&mut self, synonyms: HashMap<String, Vec<String>>) {
         self.synonyms = if synonyms.is_empty() { Setting::Reset } else { Setting::Set(synonyms) }
     }
 
+    /// Sets groups of words or phrases that are all synonyms of each other, e.g.
+    /// `["tv", "television"]`. They are merged into the one-way synonyms already set.
+    pub fn set_synonym_groups(&mut self, groups: Vec<Vec<String>>) {
+        let mut synonyms = match mem::take(&mut self.synonyms) {
+            Setting::Set(synonyms) => synonyms,
+            Setting::Reset | Setting::NotSet => HashMap::new(),
+        };
+
+        for group in groups {
+            for word in &group {
+                let others = group.iter().filter(|other| *other != word).cloned();
+                synonyms.entry(word.clone()).or_insert_with(Vec::new).extend(others);
+            }
+        }
+
+        self.set_synonyms(synonyms);
+    }
+
     pub fn reset_primary_key(&mut self) {
         self.primary_key = Setting::Reset;
     }
@@ -214,6 +295,8 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         self.authorize_typos = Setting::Reset;
     }
 
+    /// The query words shorter than this number of characters accept at most one typo,
+    /// it must be greater than or equal to the minimum length for one typo.
     pub fn set_min_word_len_two_typos(&mut self, val: u8) {
         self.min_word_len_two_typos = Setting::Set(val);
     }
@@ -222,6 +305,7 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         self.min_word_len_two_typos = Setting::Reset;
     }
 
+    /// The query words shorter than this number of characters must match without typo.
     pub fn set_min_word_len_one_typo(&mut self, val: u8) {
         self.min_word_len_one_typo = Setting::Set(val);
     }
@@ -246,6 +330,130 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         self.exact_attributes = Setting::Reset;
     }
 
+    pub fn set_exactness_excluded_attributes(&mut self, attrs: HashSet<String>) {
+        self.exactness_excluded_attributes = Setting::Set(attrs);
+    }
+
+    pub fn reset_exactness_excluded_attributes(&mut self) {
+        self.exactness_excluded_attributes = Setting::Reset;
+    }
+
+    pub fn set_transposition_counts_as_one_typo(&mut self, val: bool) {
+        self.transposition_counts_as_one_typo = Setting::Set(val);
+    }
+
+    pub fn reset_transposition_counts_as_one_typo(&mut self) {
+        self.transposition_counts_as_one_typo = Setting::Reset;
+    }
+
+    pub fn set_disable_typos_on_numbers(&mut self, val: bool) {
+        self.disable_typos_on_numbers = Setting::Set(val);
+    }
+
+    pub fn reset_disable_typos_on_numbers(&mut self) {
+        self.disable_typos_on_numbers = Setting::Reset;
+    }
+
+    pub fn set_phrases_within_attribute(&mut self, val: bool) {
+        self.phrases_within_attribute = Setting::Set(val);
+    }
+
+    pub fn reset_phrases_within_attribute(&mut self) {
+        self.phrases_within_attribute = Setting::Reset;
+    }
+
+    pub fn set_attributes_positions_limits(&mut self, limits: BTreeMap<String, u32>) {
+        self.attributes_positions_limits = Setting::Set(limits);
+    }
+
+    pub fn reset_attributes_positions_limits(&mut self) {
+        self.attributes_positions_limits = Setting::Reset;
+    }
+
+    /// The position of the values in the arrays of these fields are indexed as facets, e.g.
+    /// `authors[0]` or `book.authors[0]`, for the first ten values of the arrays.
+    pub fn set_positional_array_fields(&mut self, names: HashSet<String>) {
+        self.positional_array_fields = Setting::Set(names);
+    }
+
+    pub fn reset_positional_array_fields(&mut self) {
+        self.positional_array_fields = Setting::Reset;
+    }
+
+    pub fn set_content_hash_fields(&mut self, names: HashSet<String>) {
+        self.content_hash_fields = Setting::Set(names);
+    }
+
+    pub fn reset_content_hash_fields(&mut self) {
+        self.content_hash_fields = Setting::Reset;
+    }
+
+    pub fn set_number_coercions(&mut self, coercions: BTreeMap<String, NumberCoercion>) {
+        self.number_coercions = Setting::Set(coercions);
+    }
+
+    pub fn reset_number_coercions(&mut self) {
+        self.number_coercions = Setting::Reset;
+    }
+
+    pub fn set_word_boundaries(&mut self, boundaries: WordBoundaries) {
+        self.word_boundaries = Setting::Set(boundaries);
+    }
+
+    pub fn reset_word_boundaries(&mut self) {
+        self.word_boundaries = Setting::Reset;
+    }
+
+    pub fn set_normalizer_options(&mut self, options: NormalizerOptions) {
+        self.normalizer_options = Setting::Set(options);
+    }
+
+    pub fn reset_normalizer_options(&mut self) {
+        self.normalizer_options = Setting::Reset;
+    }
+
+    pub fn set_geo_distance_formula(&mut self, formula: GeoDistanceFormula) {
+        self.geo_distance_formula = Setting::Set(formula);
+    }
+
+    pub fn reset_geo_distance_formula(&mut self) {
+        self.geo_distance_formula = Setting::Reset;
+    }
+
+    /// Rounds the positions of the words in the attributes down to a multiple of the precision
+    /// in the word position databases, e.g. with a precision of 8 the words at positions 0 to 7
+    /// are all indexed at position 0. It shrinks these databases at the cost of a coarser
+    /// ranking by the attribute and exactness criteria, the proximity between the words and
+    /// the `EXACT` filter stay exact.
+    pub fn set_word_position_precision(&mut self, precision: NonZeroU32) {
+        self.word_position_precision = Setting::Set(precision);
+    }
+
+    pub fn reset_word_position_precision(&mut self) {
+        self.word_position_precision = Setting::Reset;
+    }
+
+    /// Rewrites the queries containing the words of a pattern before they are searched,
+    /// e.g. to also search for `"television"` when a query contains `tv`.
+    pub fn set_query_rewrites(&mut self, rewrites: BTreeMap<String, QueryRewrite>) {
+        self.query_rewrites = Setting::Set(rewrites);
+    }
+
+    pub fn reset_query_rewrites(&mut self) {
+        self.query_rewrites = Setting::Reset;
+    }
+
+    /// The values of the hashed fields can only be filtered with an equality, hashing the
+    /// values is irreversible: the values stay hashed when the fields are no longer hashed.
+    /// Only the top-level fields can be hashed, the values of their nested fields are hashed too.
+    pub fn set_hashed_fields(&mut self, fields: HashSet<String>) {
+        self.hashed_fields = Setting::Set(fields);
+    }
+
+    pub fn reset_hashed_fields(&mut self) {
+        self.hashed_fields = Setting::Reset;
+    }
+
     fn reindex<F>(&mut self, cb: &F, old_fields_ids_map: FieldsIdsMap) -> Result<()>
     where
         F: Fn(UpdateIndexingStep) + Sync,
@@ -311,6 +519,7 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         match self.distinct_field {
             Setting::Set(ref attr) => {
                 self.index.put_distinct_field(self.wtxn, &attr)?;
+                self.index.delete_composite_distinct_fields(self.wtxn)?;
             }
             Setting::Reset => {
                 self.index.delete_distinct_field(self.wtxn)?;
@@ -320,6 +529,20 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         Ok(true)
     }
 
+    fn update_distinct_fields(&mut self) -> Result<bool> {
+        match self.distinct_fields {
+            Setting::Set(ref fields) => {
+                self.index.put_composite_distinct_fields(self.wtxn, fields)?;
+                self.index.delete_distinct_field(self.wtxn)?;
+            }
+            Setting::Reset => {
+                self.index.delete_composite_distinct_fields(self.wtxn)?;
+            }
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(true)
+    }
+
     /// Updates the index's searchable attributes. This causes the field map to be recomputed to
     /// reflect the order of the searchable attributes.
     fn update_searchable(&mut self) -> Result<bool> {
@@ -381,20 +604,6 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
     fn update_synonyms(&mut self) -> Result<bool> {
         match self.synonyms {
             Setting::Set(ref synonyms) => {
-                fn normalize(analyzer: &Analyzer<&[u8]>, text: &str) -> Vec<String> {
-                    analyzer
-                        .analyze(text)
-                        .tokens()
-                        .filter_map(|token| {
-                            if token.is_word() {
-                                Some(token.text().to_string())
-                            } else {
-                                None
-                            }
-                        })
-                        .collect::<Vec<_>>()
-                }
-
                 let mut config = AnalyzerConfig::default();
                 let stop_words = self.index.stop_words(self.wtxn)?;
                 if let Some(stop_words) = &stop_words {
@@ -404,10 +613,11 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
 
                 let mut new_synonyms = HashMap::new();
                 for (word, synonyms) in synonyms {
-                    // Normalize both the word and associated synonyms.
+                    // Normalize both the word and associated synonyms, the stop words
+                    // of the synonyms are kept to be searched as gaps of their phrase.
                     let normalized_word = normalize(&analyzer, word);
                     let normalized_synonyms =
-                        synonyms.iter().map(|synonym| normalize(&analyzer, synonym));
+                        synonyms.iter().map(|synonym| normalize_synonym(&analyzer, synonym));
 
                     // Store the normalized synonyms under the normalized word,
                     // merging the possible duplicate words.
@@ -435,17 +645,318 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         }
     }
 
+    fn update_query_rewrites(&mut self) -> Result<()> {
+        match self.query_rewrites {
+            Setting::Set(ref rewrites) => {
+                let mut config = AnalyzerConfig::default();
+                let stop_words = self.index.stop_words(self.wtxn)?;
+                if let Some(stop_words) = &stop_words {
+                    config.stop_words(stop_words);
+                }
+                let analyzer = Analyzer::new(config);
+
+                // The patterns and the rewritten queries are normalized like the
+                // query words, the last rule of the duplicate patterns is kept.
+                let mut new_rewrites = BTreeMap::new();
+                for (pattern, rewrite) in rewrites {
+                    let pattern = normalize(&analyzer, pattern).join(" ");
+                    let rewrite = match rewrite {
+                        QueryRewrite::Replace(query) => {
+                            QueryRewrite::Replace(normalize(&analyzer, query).join(" "))
+                        }
+                        QueryRewrite::Augment(query) => {
+                            QueryRewrite::Augment(normalize(&analyzer, query).join(" "))
+                        }
+                    };
+                    if !pattern.is_empty() {
+                        new_rewrites.insert(pattern, rewrite);
+                    }
+                }
+
+                self.index.put_query_rewrites(self.wtxn, &new_rewrites)?;
+                Ok(())
+            }
+            Setting::Reset => {
+                self.index.delete_query_rewrites(self.wtxn)?;
+                Ok(())
+            }
+            Setting::NotSet => Ok(()),
+        }
+    }
+
     fn update_exact_attributes(&mut self) -> Result<bool> {
         match self.exact_attributes {
             Setting::Set(ref attrs) => {
+                // A name with an empty segment, e.g. `author..name`, can't match a flattened field.
+                let invalid = attrs
+                    .iter()
+                    .find(|attr| *attr != ALL_FIELDS_FACET && attr.split('.').any(str::is_empty));
+                if let Some(field) = invalid {
+                    return Err(UserError::InvalidExactAttribute { field: field.clone() }.into());
+                }
+
+                let old_attrs: HashSet<_> =
+                    self.index.exact_attributes(self.wtxn)?.into_iter().map(String::from).collect();
+                if *attrs == old_attrs {
+                    return Ok(false);
+                }
                 let attrs = attrs.iter().map(String::as_str).collect::<Vec<_>>();
                 self.index.put_exact_attributes(&mut self.wtxn, &attrs)?;
                 Ok(true)
             }
             Setting::Reset => {
+                let was_set = !self.index.exact_attributes(self.wtxn)?.is_empty();
                 self.index.delete_exact_attributes(&mut self.wtxn)?;
-                Ok(true)
+                Ok(was_set)
+            }
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    fn update_hashed_fields(&mut self) -> Result<bool> {
+        match self.hashed_fields {
+            Setting::Set(ref fields) => {
+                // The values are hashed in the top-level fields of the stored documents,
+                // a nested field would never match them and would be stored in plain text.
+                if let Some(field) = fields.iter().find(|field| field.contains('.')) {
+                    return Err(UserError::InvalidHashedAttribute { field: field.clone() }.into());
+                }
+
+                let old_fields: HashSet<String> =
+                    self.index.hashed_fields(self.wtxn)?.into_iter().map(String::from).collect();
+                let new_fields: HashSet<String> = fields.difference(&old_fields).cloned().collect();
+                let names: Vec<_> = fields.iter().map(String::as_str).collect();
+                self.index.put_hashed_fields(self.wtxn, &names)?;
+
+                // The stored values of the fields that were hashed before are partly hashed,
+                // the record is kept when the fields are reset to never hash them twice.
+                let mut previous_fields: HashSet<String> = self
+                    .index
+                    .previously_hashed_fields(self.wtxn)?
+                    .into_iter()
+                    .map(String::from)
+                    .collect();
+                let (rehashed_fields, new_fields): (HashSet<_>, HashSet<_>) =
+                    new_fields.into_iter().partition(|field| previous_fields.contains(field));
+                previous_fields.extend(fields.iter().cloned());
+                let previous_names: Vec<_> = previous_fields.iter().map(String::as_str).collect();
+                self.index.put_previously_hashed_fields(self.wtxn, &previous_names)?;
+
+                let salt = match self.index.hashed_fields_salt(self.wtxn)? {
+                    Some(salt) => salt.to_string(),
+                    None => {
+                        let salt = uuid::Uuid::new_v4().to_string();
+                        self.index.put_hashed_fields_salt(self.wtxn, &salt)?;
+                        salt
+                    }
+                };
+
+                // Only the values stored since the fields were hashed before are in plain text.
+                let mut rehashed_documents_ids = HashMap::new();
+                for field in rehashed_fields {
+                    let docids = self.index.unhashed_documents_ids(self.wtxn, &field)?;
+                    self.index.delete_unhashed_documents_ids(self.wtxn, &field)?;
+                    rehashed_documents_ids.insert(field, docids);
+                }
+
+                // The values already stored must be hashed before the documents are reindexed.
+                self.hash_stored_values(&new_fields, &rehashed_documents_ids, &salt)
+            }
+            Setting::Reset => {
+                self.index.delete_hashed_fields(self.wtxn)?;
+                Ok(false)
+            }
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    /// Hashes the values of the fields in the stored documents, only the values of the given
+    /// documents for the `rehashed_fields`. Returns `true` if a document was modified.
+    fn hash_stored_values(
+        &mut self,
+        fields: &HashSet<String>,
+        rehashed_fields: &HashMap<String, RoaringBitmap>,
+        salt: &str,
+    ) -> Result<bool> {
+        let fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+        let primary_key = self.index.primary_key(self.wtxn)?.map(String::from);
+        let is_hashable = |field: &&String| Some(field.as_str()) != primary_key.as_deref();
+        let fields_ids: HashSet<FieldId> = fields
+            .iter()
+            .filter(is_hashable)
+            .filter_map(|field| fields_ids_map.id(field))
+            .collect();
+        let rehashed_fields_ids: HashMap<FieldId, &RoaringBitmap> = rehashed_fields
+            .iter()
+            .filter(|(field, _)| is_hashable(field))
+            .filter_map(|(field, docids)| fields_ids_map.id(field).map(|id| (id, docids)))
+            .collect();
+        if fields_ids.is_empty() && rehashed_fields_ids.is_empty() {
+            return Ok(false);
+        }
+
+        let mut modified = false;
+        let mut buffer = Vec::new();
+        let db = self.index.documents.remap_data_type::<ByteSlice>();
+        let mut iter = db.iter_mut(self.wtxn)?;
+        while let Some(result) = iter.next() {
+            let (docid, bytes) = result?;
+            let must_hash = |field_id: &FieldId| {
+                fields_ids.contains(field_id)
+                    || rehashed_fields_ids
+                        .get(field_id)
+                        .map_or(false, |docids| docids.contains(docid.get()))
+            };
+            let obkv = obkv::KvReaderU16::new(bytes);
+            if !obkv.iter().any(|(field_id, _)| must_hash(&field_id)) {
+                continue;
+            }
+
+            buffer.clear();
+            let mut writer = obkv::KvWriter::new(&mut buffer);
+            for (field_id, value) in obkv.iter() {
+                if must_hash(&field_id) {
+                    let value = serde_json::from_slice(value).map_err(InternalError::SerdeJson)?;
+                    let value = hash_json_value(salt, &value);
+                    let value = serde_json::to_vec(&value).map_err(InternalError::SerdeJson)?;
+                    writer.insert(field_id, value)?;
+                } else {
+                    writer.insert(field_id, value)?;
+                }
+            }
+            writer.finish()?;
+
+            // safety: we don't keep references from inside the LMDB database.
+            unsafe { iter.put_current(&docid, &buffer)? };
+            modified = true;
+        }
+
+        Ok(modified)
+    }
+
+    fn update_exactness_excluded_attributes(&mut self) -> Result<()> {
+        match self.exactness_excluded_attributes {
+            Setting::Set(ref attrs) => {
+                let attrs = attrs.iter().map(String::as_str).collect::<Vec<_>>();
+                self.index.put_exactness_excluded_attributes(&mut self.wtxn, &attrs)?;
+            }
+            Setting::Reset => {
+                self.index.delete_exactness_excluded_attributes(&mut self.wtxn)?;
+            }
+            Setting::NotSet => (),
+        }
+
+        Ok(())
+    }
+
+    fn update_attributes_positions_limits(&mut self) -> Result<bool> {
+        match self.attributes_positions_limits {
+            Setting::Set(ref limits) => {
+                let old_limits = self.index.attributes_positions_limits(self.wtxn)?;
+                if *limits != old_limits {
+                    self.index.put_attributes_positions_limits(self.wtxn, limits)?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            Setting::Reset => Ok(self.index.delete_attributes_positions_limits(self.wtxn)?),
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    fn update_number_coercions(&mut self) -> Result<bool> {
+        match self.number_coercions {
+            Setting::Set(ref coercions) => {
+                let old_coercions = self.index.number_coercions(self.wtxn)?;
+                if *coercions != old_coercions {
+                    self.index.put_number_coercions(self.wtxn, coercions)?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            Setting::Reset => Ok(self.index.delete_number_coercions(self.wtxn)?),
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    fn update_word_boundaries(&mut self) -> Result<bool> {
+        match self.word_boundaries {
+            Setting::Set(ref boundaries) => {
+                let old_boundaries = self.index.word_boundaries(self.wtxn)?;
+                if *boundaries != old_boundaries {
+                    self.index.put_word_boundaries(self.wtxn, boundaries)?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            Setting::Reset => Ok(self.index.delete_word_boundaries(self.wtxn)?),
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    fn update_normalizer_options(&mut self) -> Result<bool> {
+        match self.normalizer_options {
+            Setting::Set(ref options) => {
+                let old_options = self.index.normalizer_options(self.wtxn)?;
+                if *options != old_options {
+                    self.index.put_normalizer_options(self.wtxn, options)?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            Setting::Reset => Ok(self.index.delete_normalizer_options(self.wtxn)?),
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    fn update_word_position_precision(&mut self) -> Result<bool> {
+        match self.word_position_precision {
+            Setting::Set(precision) => {
+                let old_precision = self.index.word_position_precision(self.wtxn)?;
+                if precision != old_precision {
+                    self.index.put_word_position_precision(self.wtxn, precision)?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            Setting::Reset => Ok(self.index.delete_word_position_precision(self.wtxn)?),
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    fn update_positional_array_fields(&mut self) -> Result<bool> {
+        match self.positional_array_fields {
+            Setting::Set(ref fields) => {
+                let old_fields = self.index.positional_array_fields(self.wtxn)?;
+                if *fields != old_fields {
+                    self.index.put_positional_array_fields(self.wtxn, fields)?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            Setting::Reset => Ok(self.index.delete_positional_array_fields(self.wtxn)?),
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    fn update_content_hash_fields(&mut self) -> Result<bool> {
+        match self.content_hash_fields {
+            Setting::Set(ref fields) => {
+                let old_fields = self.index.content_hash_fields(self.wtxn)?;
+                if *fields != old_fields {
+                    self.index.put_content_hash_fields(self.wtxn, fields)?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
             }
+            Setting::Reset => Ok(self.index.delete_content_hash_fields(self.wtxn)?),
             Setting::NotSet => Ok(false),
         }
     }
@@ -543,6 +1054,62 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         }
     }
 
+    fn update_transposition_counts_as_one_typo(&mut self) -> Result<()> {
+        match self.transposition_counts_as_one_typo {
+            Setting::Set(flag) => {
+                self.index.put_transposition_counts_as_one_typo(self.wtxn, flag)?;
+                Ok(())
+            }
+            Setting::Reset => {
+                self.index.put_transposition_counts_as_one_typo(self.wtxn, true)?;
+                Ok(())
+            }
+            Setting::NotSet => Ok(()),
+        }
+    }
+
+    fn update_disable_typos_on_numbers(&mut self) -> Result<()> {
+        match self.disable_typos_on_numbers {
+            Setting::Set(flag) => {
+                self.index.put_disable_typos_on_numbers(self.wtxn, flag)?;
+                Ok(())
+            }
+            Setting::Reset => {
+                self.index.put_disable_typos_on_numbers(self.wtxn, false)?;
+                Ok(())
+            }
+            Setting::NotSet => Ok(()),
+        }
+    }
+
+    fn update_phrases_within_attribute(&mut self) -> Result<()> {
+        match self.phrases_within_attribute {
+            Setting::Set(flag) => {
+                self.index.put_phrases_within_attribute(self.wtxn, flag)?;
+                Ok(())
+            }
+            Setting::Reset => {
+                self.index.put_phrases_within_attribute(self.wtxn, false)?;
+                Ok(())
+            }
+            Setting::NotSet => Ok(()),
+        }
+    }
+
+    fn update_geo_distance_formula(&mut self) -> Result<()> {
+        match self.geo_distance_formula {
+            Setting::Set(formula) => {
+                self.index.put_geo_distance_formula(self.wtxn, formula)?;
+            }
+            Setting::Reset => {
+                self.index.delete_geo_distance_formula(self.wtxn)?;
+            }
+            Setting::NotSet => (),
+        }
+
+        Ok(())
+    }
+
     fn update_min_typo_word_len(&mut self) -> Result<()> {
         let one = self.min_word_len_one_typo.or_reset(DEFAULT_MIN_WORD_LEN_ONE_TYPO);
         let two = self.min_word_len_two_typos.or_reset(DEFAULT_MIN_WORD_LEN_TWO_TYPOS);
@@ -622,28 +1189,55 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         self.update_filterable()?;
         self.update_sortable()?;
         self.update_distinct_field()?;
+        self.update_distinct_fields()?;
         self.update_criteria()?;
         self.update_primary_key()?;
         self.update_authorize_typos()?;
+        self.update_transposition_counts_as_one_typo()?;
+        self.update_disable_typos_on_numbers()?;
+        self.update_phrases_within_attribute()?;
+        self.update_geo_distance_formula()?;
+        self.update_query_rewrites()?;
         self.update_min_typo_word_len()?;
         self.update_exact_words()?;
+        self.update_exactness_excluded_attributes()?;
 
         // If there is new faceted fields we indicate that we must reindex as we must
         // index new fields as facets. It means that the distinct attribute,
         // an Asc/Desc criterion or a filtered attribute as be added or removed.
         let new_faceted_fields = self.index.user_defined_faceted_fields(&self.wtxn)?;
         let faceted_updated = old_faceted_fields != new_faceted_fields;
+        if faceted_updated {
+            // The cardinality of the fields faceted by the wildcard is checked again.
+            self.index.delete_high_cardinality_faceted_fields(self.wtxn)?;
+        }
 
         let stop_words_updated = self.update_stop_words()?;
         let synonyms_updated = self.update_synonyms()?;
         let searchable_updated = self.update_searchable()?;
         let exact_attributes_updated = self.update_exact_attributes()?;
+        let positions_limits_updated = self.update_attributes_positions_limits()?;
+        let positional_array_fields_updated = self.update_positional_array_fields()?;
+        let content_hash_fields_updated = self.update_content_hash_fields()?;
+        let number_coercions_updated = self.update_number_coercions()?;
+        let word_boundaries_updated = self.update_word_boundaries()?;
+        let normalizer_options_updated = self.update_normalizer_options()?;
+        let hashed_fields_updated = self.update_hashed_fields()?;
+        let word_position_precision_updated = self.update_word_position_precision()?;
 
         if stop_words_updated
             || faceted_updated
             || synonyms_updated
             || searchable_updated
             || exact_attributes_updated
+            || positions_limits_updated
+            || positional_array_fields_updated
+            || content_hash_fields_updated
+            || number_coercions_updated
+            || word_boundaries_updated
+            || normalizer_options_updated
+            || hashed_fields_updated
+            || word_position_precision_updated
         {
             self.reindex(&progress_callback, old_fields_ids_map)?;
         }
@@ -652,18 +1246,38 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
     }
 }
 
+/// Returns the words of the text, as they are indexed, e.g. to normalize the synonyms.
+fn normalize(analyzer: &Analyzer<&[u8]>, text: &str) -> Vec<String> {
+    analyzer
+        .analyze(text)
+        .tokens()
+        .filter_map(|token| if token.is_word() { Some(token.text().to_string()) } else { None })
+        .collect::<Vec<_>>()
+}
+
+/// Returns the words of the synonym with its stop words, they aren't indexed but keep
+/// the distance between the words of the synonym when it is searched as a phrase.
+fn normalize_synonym(analyzer: &Analyzer<&[u8]>, text: &str) -> Vec<String> {
+    analyzer
+        .analyze(text)
+        .tokens()
+        .filter(|token| token.is_word() || token.kind == TokenKind::StopWord)
+        .map(|token| token.text().to_string())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use big_s::S;
-    use heed::types::ByteSlice;
+    use heed::types::{ByteSlice, Str};
     use heed::EnvOpenOptions;
-    use maplit::{btreeset, hashmap, hashset};
+    use maplit::{btreemap, btreeset, hashmap, hashset};
 
     use super::*;
     use crate::error::Error;
     use crate::index::tests::TempIndex;
-    use crate::update::IndexDocuments;
-    use crate::{Criterion, Filter, SearchResult};
+    use crate::update::{DeleteField, IndexDocuments};
+    use crate::{Criterion, Filter, RoaringBitmapCodec, SearchResult};
 
     #[test]
     fn set_and_reset_searchable_fields() {
@@ -859,23 +1473,482 @@ mod tests {
     }
 
     #[test]
-    fn set_filterable_fields() {
-        let path = tempfile::tempdir().unwrap();
-        let mut options = EnvOpenOptions::new();
-        options.map_size(10 * 1024 * 1024); // 10 MB
-        let index = Index::new(options, &path).unwrap();
-
+    fn set_and_reset_number_coercions() {
+        let index = TempIndex::new();
         let config = IndexerConfig::default();
 
-        // Set the filterable fields to be the age.
         let mut wtxn = index.write_txn().unwrap();
         let mut builder = Settings::new(&mut wtxn, &index, &config);
-        builder.set_filterable_fields(hashset! { S("age") });
+        builder.set_filterable_fields(hashset! { S("zip") });
+        builder.set_number_coercions(btreemap! { S("zip") => NumberCoercion::Strict });
         builder.execute(|_| ()).unwrap();
 
-        // Then index some documents.
         let content = documents!([
-            { "name": "kevin", "age": 23},
+            { "id": 1, "zip": "01234" },
+            { "id": 2, "zip": "75012" },
+            { "id": 3, "zip": 1500 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // `01234` isn't the exact representation of a number.
+        let rtxn = index.read_txn().unwrap();
+        let filter = Filter::from_str("zip > 1000").unwrap().unwrap();
+        let mut result = index.search(&rtxn).filter(filter.clone()).execute().unwrap();
+        result.documents_ids.sort_unstable();
+        assert_eq!(result.documents_ids, vec![1, 2]);
+        // The values are still filterable as strings.
+        let string_filter = Filter::from_str("zip = 01234").unwrap().unwrap();
+        let result = index.search(&rtxn).filter(string_filter).execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_number_coercions(btreemap! { S("zip") => NumberCoercion::Lenient });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut result = index.search(&rtxn).filter(filter.clone()).execute().unwrap();
+        result.documents_ids.sort_unstable();
+        assert_eq!(result.documents_ids, vec![0, 1, 2]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_number_coercions();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.number_coercions(&rtxn).unwrap().is_empty());
+        let result = index.search(&rtxn).filter(filter).execute().unwrap();
+        assert_eq!(result.documents_ids, vec![2]);
+    }
+
+    #[test]
+    fn set_and_reset_exactness_excluded_attributes() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_searchable_fields(vec![S("title"), S("body")]);
+        builder.set_criteria(vec![S("words"), S("exactness")]);
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 1, "title": "the kevin show", "body": "kevin" },
+            { "id": 2, "title": "kevin rocks", "body": "hello" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // The body exactly matches the query.
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("kevin").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0, 1]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_exactness_excluded_attributes(hashset! { S("body") });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // Only the title that starts with the query is taken into account.
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.exactness_excluded_attributes(&rtxn).unwrap(), vec!["body"]);
+        let result = index.search(&rtxn).query("kevin").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1, 0]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_exactness_excluded_attributes();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.exactness_excluded_attributes(&rtxn).unwrap().is_empty());
+        let result = index.search(&rtxn).query("kevin").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0, 1]);
+    }
+
+    #[test]
+    fn set_and_reset_word_position_precision() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "title": "the quick brown fox jumps over the lazy dog again" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let positions = |rtxn: &heed::RoTxn, word: &str| {
+            let mut positions = Vec::new();
+            for result in index.word_position_docids.iter(rtxn).unwrap() {
+                let ((w, position), _) = result.unwrap();
+                if w == word {
+                    positions.push(crate::relative_from_absolute_position(position).1);
+                }
+            }
+            positions
+        };
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.word_position_precision(&rtxn).unwrap().get(), 1);
+        assert_eq!(positions(&rtxn, "the"), vec![0, 6]);
+        assert_eq!(positions(&rtxn, "again"), vec![9]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_word_position_precision(NonZeroU32::new(8).unwrap());
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The documents have been reindexed with the rounded positions.
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.word_position_precision(&rtxn).unwrap().get(), 8);
+        assert_eq!(positions(&rtxn, "the"), vec![0]);
+        assert_eq!(positions(&rtxn, "again"), vec![8]);
+        let result = index.search(&rtxn).query("lazy dog").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_word_position_precision();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.word_position_precision(&rtxn).unwrap().get(), 1);
+        assert_eq!(positions(&rtxn, "the"), vec![0, 6]);
+        assert_eq!(positions(&rtxn, "again"), vec![9]);
+    }
+
+    #[test]
+    fn starts_with_the_query_with_a_word_position_precision() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_criteria(vec![S("words"), S("exactness")]);
+        builder.set_word_position_precision(NonZeroU32::new(8).unwrap());
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 1, "title": "a dog and a cat and a bird saw the quick brown fox" },
+            { "id": 2, "title": "quick brown fox" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // The second word is looked up at its rounded position, like the first one.
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("quick brown").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1, 0]);
+        let result = index.search(&rtxn).query("quick bro").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1, 0]);
+    }
+
+    #[test]
+    fn set_and_reset_word_boundaries() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "name": "iPhone13" },
+            { "id": 2, "name": "PlayStation" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("station").execute().unwrap();
+        assert!(result.documents_ids.is_empty());
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_word_boundaries(WordBoundaries { camel_case: true, letter_digit: true });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The documents have been reindexed with the split words.
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.word_boundaries(&rtxn).unwrap().is_enabled());
+        let result = index.search(&rtxn).query("station").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1]);
+        let result = index.search(&rtxn).query("iphone 13").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_word_boundaries();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.word_boundaries(&rtxn).unwrap(), WordBoundaries::default());
+        let result = index.search(&rtxn).query("station").execute().unwrap();
+        assert!(result.documents_ids.is_empty());
+    }
+
+    #[test]
+    fn set_and_reset_normalizer_options() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "name": "كَتَبَ" },
+            { "id": 2, "name": "hello" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("كتب").execute().unwrap();
+        assert!(result.documents_ids.is_empty());
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_normalizer_options(NormalizerOptions {
+            arabic_diacritics: true,
+            ..Default::default()
+        });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The documents have been reindexed without the diacritics.
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.normalizer_options(&rtxn).unwrap().arabic_diacritics);
+        let result = index.search(&rtxn).query("كتب").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        let result = index.search(&rtxn).query("كَتَبَ").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_normalizer_options();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.normalizer_options(&rtxn).unwrap(), NormalizerOptions::default());
+        let result = index.search(&rtxn).query("كتب").execute().unwrap();
+        assert!(result.documents_ids.is_empty());
+    }
+
+    #[test]
+    fn set_and_reset_hashed_fields() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("ssn") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 1, "name": "kevin", "ssn": "123-45" },
+            { "id": 2, "name": "bob", "ssn": "678-90" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // The values already stored are hashed.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_hashed_fields(hashset! { S("ssn") });
+        builder.execute(|_| ()).unwrap();
+
+        // The values of the new documents are hashed too.
+        let content = documents!([{ "id": 3, "name": "alice", "ssn": "ABC-99" }]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.hashed_fields(&rtxn).unwrap(), vec!["ssn"]);
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let ssn_id = fields_ids_map.id("ssn").unwrap();
+        for (_, obkv) in index.all_documents(&rtxn).unwrap().map(Result::unwrap) {
+            let value: String = serde_json::from_slice(obkv.get(ssn_id).unwrap()).unwrap();
+            assert_eq!(value.len(), 64);
+        }
+
+        let filter = Filter::from_str("ssn = '123-45'").unwrap().unwrap();
+        let documents_ids: Vec<_> = filter.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+        assert_eq!(documents_ids, vec![0]);
+        let filter = Filter::from_str("ssn = 'abc-99'").unwrap().unwrap();
+        let documents_ids: Vec<_> = filter.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+        assert_eq!(documents_ids, vec![2]);
+        let filter = Filter::from_str("ssn != '123-45'").unwrap().unwrap();
+        let documents_ids: Vec<_> = filter.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+        assert_eq!(documents_ids, vec![1, 2]);
+        drop(rtxn);
+
+        // The values stay hashed once the field is no longer hashed.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_hashed_fields();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.hashed_fields(&rtxn).unwrap().is_empty());
+        let filter = Filter::from_str("ssn = '123-45'").unwrap().unwrap();
+        assert!(filter.evaluate(&rtxn, &index).unwrap().is_empty());
+        drop(rtxn);
+
+        // Hashing the field again only hashes the values added in between.
+        let mut wtxn = index.write_txn().unwrap();
+        // A plain value shaped like a hash is hashed too, the hashed values are tracked.
+        let content = documents!([
+            { "id": 4, "name": "john", "ssn": "XYZ-00" },
+            { "id": 5, "name": "jane", "ssn": "abababababababababababababababababababababababababababababababab" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_hashed_fields(hashset! { S("ssn") });
+        builder.execute(|_| ()).unwrap();
+
+        // The documents rewritten from the stored documents aren't hashed twice.
+        DeleteField::new(&mut wtxn, &index, &config, "name").execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let filter = Filter::from_str("ssn = '123-45'").unwrap().unwrap();
+        let documents_ids: Vec<_> = filter.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+        assert_eq!(documents_ids, vec![0]);
+        let filter = Filter::from_str("ssn = 'xyz-00'").unwrap().unwrap();
+        let documents_ids: Vec<_> = filter.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+        assert_eq!(documents_ids, vec![3]);
+        let filter = Filter::from_str(
+            "ssn = 'abababababababababababababababababababababababababababababababab'",
+        )
+        .unwrap()
+        .unwrap();
+        let documents_ids: Vec<_> = filter.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+        assert_eq!(documents_ids, vec![4]);
+        drop(rtxn);
+
+        // The nested fields can't be hashed.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_hashed_fields(hashset! { S("contact.email") });
+        let error = builder.execute(|_| ()).unwrap_err();
+        assert!(matches!(error, Error::UserError(UserError::InvalidHashedAttribute { .. })));
+    }
+
+    #[test]
+    fn set_and_reset_attributes_positions_limits() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "title": "hello", "body": "one two three four five six" },
+            { "id": 2, "title": "world", "body": "six five four three two one" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // Only the first two words of the body can now be matched.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_attributes_positions_limits(btreemap! { S("body") => 2 });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("six ").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1]);
+        // The documents are still stored entirely.
+        let documents = index.documents(&rtxn, Some(0)).unwrap();
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let body_id = fields_ids_map.id("body").unwrap();
+        assert_eq!(documents[0].1.get(body_id), Some(&br#""one two three four five six""#[..]));
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_attributes_positions_limits();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.attributes_positions_limits(&rtxn).unwrap().is_empty());
+        let mut result = index.search(&rtxn).query("six ").execute().unwrap();
+        result.documents_ids.sort_unstable();
+        assert_eq!(result.documents_ids, vec![0, 1]);
+    }
+
+    #[test]
+    fn set_filterable_fields() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+
+        // Set the filterable fields to be the age.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("age") });
+        builder.execute(|_| ()).unwrap();
+
+        // Then index some documents.
+        let content = documents!([
+            { "name": "kevin", "age": 23},
             { "name": "kevina", "age": 21 },
             { "name": "benoit", "age": 34 }
         ]);
@@ -1029,6 +2102,55 @@ mod tests {
         assert_eq!(documents_ids.len(), 3);
     }
 
+    #[test]
+    fn set_composite_distinct_fields() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_distinct_fields(vec![S("brand"), S("color")]);
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "brand": "apple", "color": "red" },
+            { "id": 1, "brand": "apple", "color": "red" },
+            { "id": 2, "brand": "apple", "color": "blue" },
+            { "id": 3, "brand": "samsung", "color": "red" },
+            { "id": 4, "brand": "samsung", "color": "red" }
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.distinct_fields(&rtxn).unwrap(), vec![S("brand"), S("color")]);
+        let SearchResult { documents_ids, .. } = index.search(&rtxn).execute().unwrap();
+        // There is one document for each (brand, color) tuple.
+        assert_eq!(documents_ids, vec![0, 2, 3]);
+        drop(rtxn);
+
+        // The distinct field replaces the composite distinct fields.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_distinct_field(S("brand"));
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.distinct_fields(&rtxn).unwrap(), vec![S("brand")]);
+        let SearchResult { documents_ids, .. } = index.search(&rtxn).execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 3]);
+    }
+
     #[test]
     fn set_nested_distinct_field() {
         let path = tempfile::tempdir().unwrap();
@@ -1248,6 +2370,95 @@ mod tests {
         assert!(result.documents_ids.is_empty());
     }
 
+    #[test]
+    fn one_way_synonyms_and_synonym_groups() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_synonyms(hashmap! { S("NYC") => vec![S("New York City")] });
+        builder.set_synonym_groups(vec![vec![S("tv"), S("television")]]);
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "text": "the new york city guide" },
+            { "id": 1, "text": "a city in new england near york" },
+            { "id": 2, "text": "nyc at night" },
+            { "id": 3, "text": "my television" },
+            { "id": 4, "text": "tv shows" },
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let search = |query: &str| {
+            let mut documents_ids =
+                index.search(&rtxn).query(query).execute().unwrap().documents_ids;
+            documents_ids.sort_unstable();
+            documents_ids
+        };
+
+        // The multi-word synonyms are phrases and the mappings are one-way.
+        assert_eq!(search("nyc"), vec![0, 2]);
+        assert_eq!(search("new york city"), vec![0, 1]);
+        // The words of a group are synonyms of each other.
+        assert_eq!(search("tv"), vec![3, 4]);
+        assert_eq!(search("television"), vec![3, 4]);
+    }
+
+    #[test]
+    fn synonyms_with_stop_words() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_stop_words(btreeset! { S("of"), S("the") });
+        builder.set_synonyms(hashmap! { S("monument") => vec![S("Statue of Liberty")] });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "text": "the statue of liberty" },
+            { "id": 1, "text": "statue liberty" },
+            { "id": 2, "text": "statue of the liberty" },
+            { "id": 3, "text": "monument valley" },
+        ]);
+        let mut builder = IndexDocuments::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        )
+        .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // The stop words of the synonyms are kept.
+        let rtxn = index.read_txn().unwrap();
+        let synonyms = index.synonyms(&rtxn).unwrap();
+        let expected = vec![vec![S("statue"), S("of"), S("liberty")]];
+        assert_eq!(synonyms.get(&vec![S("monument")]), Some(&expected));
+
+        // They are gaps of the phrase, its words are searched at the distance they have
+        // in the synonym.
+        let mut documents_ids =
+            index.search(&rtxn).query("monument").execute().unwrap().documents_ids;
+        documents_ids.sort_unstable();
+        assert_eq!(documents_ids, vec![0, 3]);
+    }
+
     #[test]
     fn setting_searchable_recomputes_other_settings() {
         let path = tempfile::tempdir().unwrap();
@@ -1418,6 +2629,233 @@ mod tests {
         assert_eq!(line, r#""Star Wars""#);
     }
 
+    #[test]
+    fn set_and_reset_geo_distance_formula() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("_geo") });
+        builder.execute(|_| ()).unwrap();
+
+        // The second document is about 4604 kilometers away from the first one,
+        // the planar approximation gives about 5004 kilometers at this latitude.
+        let content = documents!([
+            { "id": 1, "_geo": { "lat": 60, "lng": 0 } },
+            { "id": 2, "_geo": { "lat": 60, "lng": 90 } },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let filter = Filter::from_str("_geoRadius(60, 0, 4800000)").unwrap().unwrap();
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.geo_distance_formula(&rtxn).unwrap(), GeoDistanceFormula::Haversine);
+        let docids: Vec<_> = filter.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+        assert_eq!(docids, vec![0, 1]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_geo_distance_formula(GeoDistanceFormula::Planar);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.geo_distance_formula(&rtxn).unwrap(), GeoDistanceFormula::Planar);
+        let docids: Vec<_> = filter.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+        assert_eq!(docids, vec![0]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_geo_distance_formula();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.geo_distance_formula(&rtxn).unwrap(), GeoDistanceFormula::Haversine);
+        let docids: Vec<_> = filter.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+        assert_eq!(docids, vec![0, 1]);
+    }
+
+    #[test]
+    fn set_and_reset_query_rewrites() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "title": "a new tv" },
+            { "id": 2, "title": "an old television set" },
+            { "id": 3, "title": "a radio" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let search = |rtxn: &heed::RoTxn, query: &str| {
+            let mut documents_ids =
+                index.search(rtxn).query(query).execute().unwrap().documents_ids;
+            documents_ids.sort_unstable();
+            documents_ids
+        };
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(search(&rtxn, "tv"), vec![0]);
+        assert!(search(&rtxn, "telly").is_empty());
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_query_rewrites(btreemap! {
+            S("TV") => QueryRewrite::Augment(S("Television")),
+            S("telly") => QueryRewrite::Replace(S("television")),
+        });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The rules are normalized and applied without reindexing the documents.
+        let rtxn = index.read_txn().unwrap();
+        let expected = btreemap! {
+            S("tv") => QueryRewrite::Augment(S("television")),
+            S("telly") => QueryRewrite::Replace(S("television")),
+        };
+        assert_eq!(index.query_rewrites(&rtxn).unwrap(), expected);
+        assert_eq!(search(&rtxn, "tv"), vec![0, 1]);
+        assert_eq!(search(&rtxn, "telly"), vec![1]);
+        assert_eq!(search(&rtxn, "radio"), vec![2]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_query_rewrites();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.query_rewrites(&rtxn).unwrap().is_empty());
+        assert_eq!(search(&rtxn, "tv"), vec![0]);
+        assert!(search(&rtxn, "telly").is_empty());
+    }
+
+    #[test]
+    fn set_nested_exact_attributes() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_exact_attributes(hashset! { S("author") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "author": { "name": "kevin" }, "title": "hello" },
+            { "id": 1, "author": { "name": "bob" }, "title": "kevin" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // The words of the fields nested in an exact attribute are exact words.
+        let docids = |rtxn: &heed::RoTxn, db: heed::Database<Str, RoaringBitmapCodec>, word| {
+            let docids = db.get(rtxn, word).unwrap().unwrap_or_default();
+            docids.into_iter().collect::<Vec<_>>()
+        };
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(docids(&rtxn, index.exact_word_docids, "kevin"), vec![0]);
+        assert_eq!(docids(&rtxn, index.word_docids, "kevin"), vec![1]);
+        assert_eq!(docids(&rtxn, index.exact_word_docids, "bob"), vec![1]);
+        assert!(docids(&rtxn, index.exact_word_docids, "hello").is_empty());
+        drop(rtxn);
+
+        // The wildcard makes every attribute exact.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_exact_attributes(hashset! { S("*") });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(docids(&rtxn, index.exact_word_docids, "kevin"), vec![0, 1]);
+        assert_eq!(docids(&rtxn, index.exact_word_docids, "hello"), vec![0]);
+        assert!(docids(&rtxn, index.word_docids, "kevin").is_empty());
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_exact_attributes(hashset! { S("author..name") });
+        let error = builder.execute(|_| ()).unwrap_err();
+        assert!(matches!(error, Error::UserError(UserError::InvalidExactAttribute { .. })));
+    }
+
+    #[test]
+    fn set_and_reset_phrases_within_attribute() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            {
+                "id": 1,
+                "title": "hello world",
+                "description": "world peace",
+                "note": "hello and peace"
+            },
+            { "id": 2, "title": "hello world peace", "description": "nothing" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // The pairs of words of the phrase, at the right distances, are found in
+        // different attributes.
+        let rtxn = index.read_txn().unwrap();
+        assert!(!index.phrases_within_attribute(&rtxn).unwrap());
+        let mut result = index.search(&rtxn).query("\"hello world peace\"").execute().unwrap();
+        result.documents_ids.sort_unstable();
+        assert_eq!(result.documents_ids, vec![0, 1]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_phrases_within_attribute(true);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.phrases_within_attribute(&rtxn).unwrap());
+        let result = index.search(&rtxn).query("\"hello world peace\"").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1]);
+        let result = index.search(&rtxn).query("\"world peace\"").execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 2);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_phrases_within_attribute();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(!index.phrases_within_attribute(&rtxn).unwrap());
+        let result = index.search(&rtxn).query("\"hello world peace\"").execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 2);
+    }
+
     #[test]
     fn test_disable_typo() {
         let index = TempIndex::new();
@@ -1516,6 +2954,7 @@ mod tests {
             criteria,
             stop_words,
             distinct_field,
+            distinct_fields,
             synonyms,
             primary_key,
             authorize_typos,
@@ -1523,6 +2962,20 @@ mod tests {
             min_word_len_one_typo,
             exact_words,
             exact_attributes,
+            exactness_excluded_attributes,
+            transposition_counts_as_one_typo,
+            disable_typos_on_numbers,
+            phrases_within_attribute,
+            attributes_positions_limits,
+            positional_array_fields,
+            content_hash_fields,
+            number_coercions,
+            word_boundaries,
+            normalizer_options,
+            hashed_fields,
+            geo_distance_formula,
+            word_position_precision,
+            query_rewrites,
         } = builder;
 
         assert!(matches!(searchable_fields, Setting::NotSet));
@@ -1532,6 +2985,7 @@ mod tests {
         assert!(matches!(criteria, Setting::NotSet));
         assert!(matches!(stop_words, Setting::NotSet));
         assert!(matches!(distinct_field, Setting::NotSet));
+        assert!(matches!(distinct_fields, Setting::NotSet));
         assert!(matches!(synonyms, Setting::NotSet));
         assert!(matches!(primary_key, Setting::NotSet));
         assert!(matches!(authorize_typos, Setting::NotSet));
@@ -1539,5 +2993,19 @@ mod tests {
         assert!(matches!(min_word_len_one_typo, Setting::NotSet));
         assert!(matches!(exact_words, Setting::NotSet));
         assert!(matches!(exact_attributes, Setting::NotSet));
+        assert!(matches!(exactness_excluded_attributes, Setting::NotSet));
+        assert!(matches!(transposition_counts_as_one_typo, Setting::NotSet));
+        assert!(matches!(disable_typos_on_numbers, Setting::NotSet));
+        assert!(matches!(phrases_within_attribute, Setting::NotSet));
+        assert!(matches!(attributes_positions_limits, Setting::NotSet));
+        assert!(matches!(positional_array_fields, Setting::NotSet));
+        assert!(matches!(content_hash_fields, Setting::NotSet));
+        assert!(matches!(number_coercions, Setting::NotSet));
+        assert!(matches!(word_boundaries, Setting::NotSet));
+        assert!(matches!(normalizer_options, Setting::NotSet));
+        assert!(matches!(hashed_fields, Setting::NotSet));
+        assert!(matches!(geo_distance_formula, Setting::NotSet));
+        assert!(matches!(word_position_precision, Setting::NotSet));
+        assert!(matches!(query_rewrites, Setting::NotSet));
     }
 }
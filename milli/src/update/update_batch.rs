@@ -0,0 +1,168 @@
+use std::io::{Read, Seek};
+
+use crate::documents::DocumentBatchReader;
+use crate::update::{
+    DocumentAdditionResult, IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings,
+    UpdateIndexingStep,
+};
+use crate::{Index, Result};
+
+type SettingsUpdate<'b> = Box<dyn for<'a, 't, 'u, 'i> FnOnce(&mut Settings<'a, 't, 'u, 'i>) + 'b>;
+
+/// Applies several settings changes and document additions in a single write transaction.
+///
+/// The settings changes are merged in the order they were added and applied once, before the
+/// documents are indexed: the new documents are therefore indexed with the final settings and
+/// the documents already in the index are reindexed at most once. The primary key set by the
+/// settings is also known before the documents are read. The documents batches are indexed
+/// together, in the order they were added, with the same [`IndexDocumentsConfig`].
+pub struct UpdateBatch<'t, 'u, 'i, 'a, R, F> {
+    wtxn: &'t mut heed::RwTxn<'i, 'u>,
+    index: &'i Index,
+    indexer_config: &'a IndexerConfig,
+    config: IndexDocumentsConfig,
+    progress: F,
+    settings: Vec<SettingsUpdate<'a>>,
+    documents: Vec<DocumentBatchReader<R>>,
+}
+
+/// The result of an [`UpdateBatch`].
+#[derive(Debug)]
+pub struct UpdateBatchResult {
+    /// Whether some settings were applied.
+    pub settings_updated: bool,
+    pub documents: DocumentAdditionResult,
+}
+
+impl<'t, 'u, 'i, 'a, R, F> UpdateBatch<'t, 'u, 'i, 'a, R, F>
+where
+    R: Read + Seek,
+    F: Fn(UpdateIndexingStep) + Sync,
+{
+    pub fn new(
+        wtxn: &'t mut heed::RwTxn<'i, 'u>,
+        index: &'i Index,
+        indexer_config: &'a IndexerConfig,
+        config: IndexDocumentsConfig,
+        progress: F,
+    ) -> UpdateBatch<'t, 'u, 'i, 'a, R, F> {
+        UpdateBatch {
+            wtxn,
+            index,
+            indexer_config,
+            config,
+            progress,
+            settings: Vec::new(),
+            documents: Vec::new(),
+        }
+    }
+
+    /// Adds a settings change, it is applied on the changes added before it,
+    /// e.g. a setting reset after being set is reset.
+    pub fn update_settings<S>(&mut self, update: S)
+    where
+        S: for<'b, 'c, 'd, 'e> FnOnce(&mut Settings<'b, 'c, 'd, 'e>) + 'a,
+    {
+        self.settings.push(Box::new(update));
+    }
+
+    /// Adds a batch of documents, the batches are indexed in the order they were added.
+    pub fn add_documents(&mut self, reader: DocumentBatchReader<R>) {
+        self.documents.push(reader);
+    }
+
+    pub fn execute(self) -> Result<UpdateBatchResult> {
+        let UpdateBatch { wtxn, index, indexer_config, config, progress, settings, documents } =
+            self;
+
+        let settings_updated = !settings.is_empty();
+        if settings_updated {
+            let mut builder = Settings::new(wtxn, index, indexer_config);
+            for update in settings {
+                update(&mut builder);
+            }
+            builder.execute(&progress)?;
+        }
+
+        let mut builder = IndexDocuments::new(wtxn, index, indexer_config, config, &progress)?;
+        for reader in documents {
+            builder.add_documents(reader)?;
+        }
+        let documents = builder.execute()?;
+
+        Ok(UpdateBatchResult { settings_updated, documents })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use maplit::hashset;
+
+    use super::*;
+    use crate::index::tests::TempIndex;
+    use crate::Filter;
+
+    #[test]
+    fn settings_and_documents_in_one_batch() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        // The primary key is set before the documents are read.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut batch =
+            UpdateBatch::new(&mut wtxn, &index, &config, IndexDocumentsConfig::default(), |_| ());
+        batch.add_documents(documents!([
+            { "code": 1, "id": 10, "name": "kevin", "age": 20 },
+            { "code": 2, "id": 10, "name": "bob", "age": 30 },
+        ]));
+        batch.update_settings(|settings| settings.set_primary_key(S("code")));
+        batch.update_settings(|settings| settings.set_searchable_fields(vec![S("age")]));
+        batch.add_documents(documents!([{ "code": 3, "name": "alice", "age": 40 }]));
+        let result = batch.execute().unwrap();
+        assert!(result.settings_updated);
+        assert_eq!(result.documents.indexed_documents, 3);
+        assert_eq!(result.documents.number_of_documents, 3);
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.primary_key(&rtxn).unwrap(), Some("code"));
+        assert!(index.search(&rtxn).query("kevin").execute().unwrap().documents_ids.is_empty());
+        drop(rtxn);
+
+        // The settings are applied on the documents already indexed and on the new ones,
+        // the later changes override the previous ones.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut batch =
+            UpdateBatch::new(&mut wtxn, &index, &config, IndexDocumentsConfig::default(), |_| ());
+        batch.update_settings(|settings| settings.set_filterable_fields(hashset! { S("name") }));
+        batch.add_documents(documents!([{ "code": 4, "name": "tamo", "age": 50 }]));
+        batch.update_settings(|settings| settings.set_filterable_fields(hashset! { S("age") }));
+        batch.update_settings(|settings| settings.reset_searchable_fields());
+        let result = batch.execute().unwrap();
+        assert_eq!(result.documents.number_of_documents, 4);
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.filterable_fields(&rtxn).unwrap(), hashset! { S("age") });
+        let filter = Filter::from_str("age > 25").unwrap().unwrap();
+        let docids: Vec<_> = filter.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+        assert_eq!(docids, vec![1, 2, 3]);
+        let result = index.search(&rtxn).query("kevin").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        drop(rtxn);
+
+        // A batch without settings doesn't touch them.
+        let mut wtxn = index.write_txn().unwrap();
+        let batch = UpdateBatch::<std::io::Cursor<Vec<u8>>, _>::new(
+            &mut wtxn,
+            &index,
+            &config,
+            IndexDocumentsConfig::default(),
+            |_| (),
+        );
+        let result = batch.execute().unwrap();
+        assert!(!result.settings_updated);
+        assert_eq!(result.documents.indexed_documents, 0);
+    }
+}
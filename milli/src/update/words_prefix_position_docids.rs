@@ -14,7 +14,12 @@ use crate::update::index_documents::{
     create_sorter, merge_cbo_roaring_bitmaps, sorter_into_lmdb_database, valid_lmdb_key,
     CursorClonableMmap, MergeFn,
 };
-use crate::{Index, Result};
+use crate::{relative_from_absolute_position, Index, Result};
+
+/// The default number of positions of a level grouped in a single position of the next level.
+pub(crate) const DEFAULT_LEVEL_GROUP_SIZE: u32 = 4;
+/// The default minimum number of positions of a level to create the next level.
+pub(crate) const DEFAULT_MIN_LEVEL_SIZE: u32 = 5;
 
 pub struct WordPrefixPositionDocids<'t, 'u, 'i> {
     wtxn: &'t mut heed::RwTxn<'i, 'u>,
@@ -25,6 +30,7 @@ pub struct WordPrefixPositionDocids<'t, 'u, 'i> {
     pub(crate) max_memory: Option<usize>,
     level_group_size: NonZeroU32,
     min_level_size: NonZeroU32,
+    positions_limit: Option<u32>,
 }
 
 impl<'t, 'u, 'i> WordPrefixPositionDocids<'t, 'u, 'i> {
@@ -39,8 +45,9 @@ impl<'t, 'u, 'i> WordPrefixPositionDocids<'t, 'u, 'i> {
             chunk_compression_level: None,
             max_nb_chunks: None,
             max_memory: None,
-            level_group_size: NonZeroU32::new(4).unwrap(),
-            min_level_size: NonZeroU32::new(5).unwrap(),
+            level_group_size: NonZeroU32::new(DEFAULT_LEVEL_GROUP_SIZE).unwrap(),
+            min_level_size: NonZeroU32::new(DEFAULT_MIN_LEVEL_SIZE).unwrap(),
+            positions_limit: None,
         }
     }
 
@@ -54,6 +61,20 @@ impl<'t, 'u, 'i> WordPrefixPositionDocids<'t, 'u, 'i> {
         self
     }
 
+    /// Only writes the prefixes found in the first `value` positions of each attribute,
+    /// the prefixes found further away are ranked by the attribute criterion as if they
+    /// were not found in the attribute at all.
+    pub fn positions_limit(&mut self, value: u32) -> &mut Self {
+        self.positions_limit = Some(value);
+        self
+    }
+
+    /// Whether the prefixes found at this absolute position must be written.
+    fn is_position_kept(&self, position: u32) -> bool {
+        let (_field_id, relative) = relative_from_absolute_position(position);
+        self.positions_limit.map_or(true, |limit| (relative as u32) < limit)
+    }
+
     #[logging_timer::time("WordPrefixPositionDocids::{}")]
     pub fn execute(
         self,
@@ -81,6 +102,9 @@ impl<'t, 'u, 'i> WordPrefixPositionDocids<'t, 'u, 'i> {
             let mut prefixes_cache = HashMap::new();
             while let Some((key, data)) = new_word_position_docids_iter.move_on_next()? {
                 let (word, pos) = StrBEU32Codec::bytes_decode(key).ok_or(heed::Error::Decoding)?;
+                if !self.is_position_kept(pos) {
+                    continue;
+                }
 
                 current_prefixes = match current_prefixes.take() {
                     Some(prefixes) if word.starts_with(&prefixes[0]) => Some(prefixes),
@@ -129,7 +153,7 @@ impl<'t, 'u, 'i> WordPrefixPositionDocids<'t, 'u, 'i> {
                 .remap_key_type::<StrBEU32Codec>();
             for result in iter {
                 let ((word, pos), data) = result?;
-                if word.starts_with(prefix) {
+                if word.starts_with(prefix) && self.is_position_kept(pos) {
                     let key = (prefix, pos);
                     let bytes = StrBEU32Codec::bytes_encode(&key).unwrap();
                     prefix_position_docids_sorter.insert(bytes, data)?;
@@ -139,16 +163,16 @@ impl<'t, 'u, 'i> WordPrefixPositionDocids<'t, 'u, 'i> {
 
         // We remove all the entries that are no more required in this word prefix position
         // docids database.
-        let mut iter =
-            self.index.word_prefix_position_docids.iter_mut(self.wtxn)?.lazily_decode_data();
-        while let Some(((prefix, _), _)) = iter.next().transpose()? {
-            if del_prefix_fst_words.contains(prefix.as_bytes()) {
-                unsafe { iter.del_current()? };
+        if !del_prefix_fst_words.is_empty() {
+            let mut iter =
+                self.index.word_prefix_position_docids.iter_mut(self.wtxn)?.lazily_decode_data();
+            while let Some(((prefix, _), _)) = iter.next().transpose()? {
+                if del_prefix_fst_words.contains(prefix.as_bytes()) {
+                    unsafe { iter.del_current()? };
+                }
             }
         }
 
-        drop(iter);
-
         // We finally write all the word prefix position docids into the LMDB database.
         sorter_into_lmdb_database(
             self.wtxn,
@@ -1,10 +1,17 @@
+use std::collections::BTreeSet;
 use std::iter::{repeat_with, FromIterator};
 use std::str;
 
-use fst::{SetBuilder, Streamer};
+use fst::automaton::{Automaton, Str};
+use fst::{IntoStreamer, SetBuilder, Streamer};
 
 use crate::{Index, Result, SmallString32};
 
+/// The default number of words a prefix must match to be part of the words prefixes.
+pub(crate) const DEFAULT_THRESHOLD: u32 = 100;
+/// The default maximum length of the prefixes, in bytes.
+pub(crate) const DEFAULT_MAX_PREFIX_LENGTH: usize = 4;
+
 pub struct WordsPrefixesFst<'t, 'u, 'i> {
     wtxn: &'t mut heed::RwTxn<'i, 'u>,
     index: &'i Index,
@@ -17,7 +24,12 @@ impl<'t, 'u, 'i> WordsPrefixesFst<'t, 'u, 'i> {
         wtxn: &'t mut heed::RwTxn<'i, 'u>,
         index: &'i Index,
     ) -> WordsPrefixesFst<'t, 'u, 'i> {
-        WordsPrefixesFst { wtxn, index, threshold: 100, max_prefix_length: 4 }
+        WordsPrefixesFst {
+            wtxn,
+            index,
+            threshold: DEFAULT_THRESHOLD,
+            max_prefix_length: DEFAULT_MAX_PREFIX_LENGTH,
+        }
     }
 
     /// Set the number of words required to make a prefix be part of the words prefixes
@@ -90,6 +102,83 @@ impl<'t, 'u, 'i> WordsPrefixesFst<'t, 'u, 'i> {
 
         // Set the words prefixes FST in the dtabase.
         self.index.put_words_prefixes_fst(self.wtxn, &prefix_fst)?;
+        self.index.put_words_prefixes_fst_parameters(
+            self.wtxn,
+            self.threshold,
+            self.max_prefix_length,
+        )?;
+        self.index.delete_deleted_words_fst(self.wtxn)?;
+
+        Ok(())
+    }
+
+    /// Updates the words prefixes FST by only counting again the words of the prefixes of the
+    /// given words, the words added to or removed from the words FST since the words prefixes
+    /// FST was built. It is faster than [`Self::execute`] when there are few changed words.
+    ///
+    /// The words removed by the documents deletions since the words prefixes FST was built
+    /// are changed words too. The other prefixes are kept as they are, the FST is therefore
+    /// entirely rebuilt when the threshold or the maximum length of the prefixes are not the
+    /// ones it was built with.
+    #[logging_timer::time("WordsPrefixesFst::{}")]
+    pub fn execute_incremental<I, S>(self, changed_words: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let parameters = self.index.words_prefixes_fst_parameters(self.wtxn)?;
+        if parameters != Some((self.threshold, self.max_prefix_length)) {
+            return self.execute();
+        }
+
+        let words_fst = self.index.words_fst(&self.wtxn)?;
+        let previous_prefix_fst = self.index.words_prefixes_fst(&self.wtxn)?;
+        let deleted_words_fst = self.index.deleted_words_fst(&self.wtxn)?;
+
+        // Only the prefixes of the changed words can have a different number of words.
+        let mut changed_prefixes = BTreeSet::new();
+        let mut insert_prefixes = |word: &str| {
+            for n in 0..self.max_prefix_length {
+                if let Some(prefix) = word.get(..=n) {
+                    changed_prefixes.insert(SmallString32::from(prefix));
+                }
+            }
+        };
+        for word in changed_words {
+            insert_prefixes(word.as_ref());
+        }
+        let mut stream = deleted_words_fst.stream();
+        while let Some(word) = stream.next() {
+            insert_prefixes(str::from_utf8(word)?);
+        }
+
+        // We count the words of these prefixes, up to the threshold.
+        let mut builder = SetBuilder::memory();
+        for prefix in &changed_prefixes {
+            let mut stream =
+                words_fst.search(Str::new(prefix.as_str()).starts_with()).into_stream();
+            let mut count = 0;
+            while count < self.threshold && stream.next().is_some() {
+                count += 1;
+            }
+            if count >= self.threshold {
+                builder.insert(prefix.as_str())?;
+            }
+        }
+        let counted_prefix_fst = builder.into_set();
+
+        // The changed prefixes of the previous FST are replaced by the counted ones.
+        let changed_prefix_fst = fst::Set::from_iter(changed_prefixes.iter().map(|p| p.as_str()))?;
+        let mut builder = SetBuilder::memory();
+        builder.extend_stream(previous_prefix_fst.op().add(&changed_prefix_fst).difference())?;
+        let kept_prefix_fst = builder.into_set();
+
+        let mut builder = SetBuilder::memory();
+        builder.extend_stream(kept_prefix_fst.op().add(&counted_prefix_fst).union())?;
+        let prefix_fst = builder.into_set();
+
+        self.index.put_words_prefixes_fst(self.wtxn, &prefix_fst)?;
+        self.index.delete_deleted_words_fst(self.wtxn)?;
 
         Ok(())
     }
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+/// The boundaries, other than the separators, on which the words are split both when the
+/// documents are indexed and when the queries are analyzed, e.g. for product catalogs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WordBoundaries {
+    /// Splits the words on the lowercase to uppercase transitions,
+    /// `PlayStation` gives `play` and `station`.
+    pub camel_case: bool,
+    /// Splits the words on the transitions between letters and digits,
+    /// `iPhone13` gives `iphone` and `13`.
+    pub letter_digit: bool,
+}
+
+impl WordBoundaries {
+    pub fn is_enabled(&self) -> bool {
+        self.camel_case || self.letter_digit
+    }
+
+    /// Splits the normalized word on the enabled boundaries.
+    ///
+    /// The case transitions are read from the original word, the normalization lowercases it.
+    /// They are ignored when the normalization changed the number of characters of the word,
+    /// and after a single letter so that brands like `iPhone` or `eBay` are kept whole.
+    pub fn split<'a>(&self, original: &str, normalized: &'a str) -> Vec<&'a str> {
+        if !self.is_enabled() {
+            return vec![normalized];
+        }
+
+        let original: Vec<char> = original.chars().collect();
+        let camel_case = self.camel_case && original.len() == normalized.chars().count();
+
+        let mut words = Vec::new();
+        let mut start = 0;
+        let mut start_char = 0;
+        let mut previous = None;
+        for (i, (byte_index, c)) in normalized.char_indices().enumerate() {
+            if let Some(previous) = previous {
+                let letter_digit = self.letter_digit
+                    && ((is_letter(previous) && c.is_numeric())
+                        || (previous.is_numeric() && is_letter(c)));
+                let camel_case = camel_case
+                    && i - start_char >= 2
+                    && original[i - 1].is_lowercase()
+                    && original[i].is_uppercase();
+                if letter_digit || camel_case {
+                    words.push(&normalized[start..byte_index]);
+                    start = byte_index;
+                    start_char = i;
+                }
+            }
+            previous = Some(c);
+        }
+        words.push(&normalized[start..]);
+
+        words
+    }
+}
+
+fn is_letter(c: char) -> bool {
+    c.is_alphabetic() && !c.is_numeric()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_words() {
+        let all = WordBoundaries { camel_case: true, letter_digit: true };
+        assert_eq!(all.split("iPhone13", "iphone13"), vec!["iphone", "13"]);
+        assert_eq!(all.split("PlayStation5", "playstation5"), vec!["play", "station", "5"]);
+        assert_eq!(all.split("HTML5", "html5"), vec!["html", "5"]);
+        assert_eq!(all.split("hello", "hello"), vec!["hello"]);
+
+        let letter_digit = WordBoundaries { camel_case: false, letter_digit: true };
+        assert_eq!(letter_digit.split("iPhone13", "iphone13"), vec!["iphone", "13"]);
+        assert_eq!(letter_digit.split("A4B", "a4b"), vec!["a", "4", "b"]);
+
+        let camel_case = WordBoundaries { camel_case: true, letter_digit: false };
+        assert_eq!(camel_case.split("PlayStation5", "playstation5"), vec!["play", "station5"]);
+        // The normalization changed the number of characters.
+        assert_eq!(camel_case.split("ŒuvreComplète", "oeuvrecomplete"), vec!["oeuvrecomplete"]);
+
+        let none = WordBoundaries::default();
+        assert_eq!(none.split("iPhone13", "iphone13"), vec!["iphone13"]);
+    }
+}
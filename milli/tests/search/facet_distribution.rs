@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::io::Cursor;
 
 use big_s::S;
@@ -5,7 +6,7 @@ use heed::EnvOpenOptions;
 use maplit::hashset;
 use milli::documents::{DocumentBatchBuilder, DocumentBatchReader};
 use milli::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
-use milli::{FacetDistribution, Index};
+use milli::{FacetDistribution, Index, OrderBy};
 
 #[test]
 fn test_facet_distribution_with_no_facet_values() {
@@ -25,7 +26,7 @@ fn test_facet_distribution_with_no_facet_values() {
     builder.execute(|_| ()).unwrap();
 
     // index documents
-    let config = IndexerConfig { max_memory: Some(10 * 1024 * 1024), ..Default::default() };
+    let config = IndexerConfig::builder().max_memory(10 * 1024 * 1024).build().unwrap();
     let indexing_config = IndexDocumentsConfig { autogenerate_docids: true, ..Default::default() };
 
     let mut builder =
@@ -75,3 +76,302 @@ fn test_facet_distribution_with_no_facet_values() {
     let result = distrib.execute().unwrap();
     assert_eq!(result["tags"].len(), 2);
 }
+
+#[test]
+fn test_facet_distribution_max_values_per_facet() {
+    let path = tempfile::tempdir().unwrap();
+    let mut options = EnvOpenOptions::new();
+    options.map_size(10 * 1024 * 1024); // 10 MB
+    let index = Index::new(options, &path).unwrap();
+
+    let mut wtxn = index.write_txn().unwrap();
+    let config = IndexerConfig::default();
+    let mut builder = Settings::new(&mut wtxn, &index, &config);
+    builder.set_filterable_fields(hashset! { S("tags") });
+    builder.execute(|_| ()).unwrap();
+
+    let indexing_config = IndexDocumentsConfig::default();
+    let mut builder =
+        IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+    let mut cursor = Cursor::new(Vec::new());
+    let mut documents_builder = DocumentBatchBuilder::new(&mut cursor).unwrap();
+
+    // the tag `tag-i` is shared by the documents with an id greater or equal to `i`
+    for id in 0..10 {
+        let tags: Vec<_> = (0..=id).map(|i| format!("tag-{}", i)).collect();
+        let doc = serde_json::json!({ "id": id, "tags": tags });
+        documents_builder.extend_from_json(Cursor::new(serde_json::to_vec(&doc).unwrap())).unwrap();
+    }
+
+    documents_builder.finish().unwrap();
+    cursor.set_position(0);
+
+    let content = DocumentBatchReader::from_reader(cursor).unwrap();
+    builder.add_documents(content).unwrap();
+    builder.execute().unwrap();
+
+    wtxn.commit().unwrap();
+
+    let txn = index.read_txn().unwrap();
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    let result = distrib.execute_with_truncation().unwrap();
+    assert!(!result.truncated);
+    assert_eq!(result.distribution["tags"].len(), 10);
+
+    distrib.max_values_per_facet(3);
+    let result = distrib.execute_with_truncation().unwrap();
+    assert!(result.truncated);
+    let expected: BTreeMap<_, _> =
+        vec![(S("tag-0"), 10), (S("tag-1"), 9), (S("tag-2"), 8)].into_iter().collect();
+    assert_eq!(result.distribution["tags"], expected);
+
+    distrib.candidates(index.documents_ids(&txn).unwrap());
+    let result = distrib.execute_with_truncation().unwrap();
+    assert!(result.truncated);
+    assert_eq!(result.distribution["tags"], expected);
+
+    distrib.max_values_per_facet(10);
+    let result = distrib.execute_with_truncation().unwrap();
+    assert!(!result.truncated);
+    assert_eq!(result.distribution["tags"].len(), 10);
+}
+
+#[test]
+fn test_facet_distribution_max_values_per_facet_exact_counts() {
+    let path = tempfile::tempdir().unwrap();
+    let mut options = EnvOpenOptions::new();
+    options.map_size(10 * 1024 * 1024); // 10 MB
+    let index = Index::new(options, &path).unwrap();
+
+    let mut wtxn = index.write_txn().unwrap();
+    let config = IndexerConfig::default();
+    let mut builder = Settings::new(&mut wtxn, &index, &config);
+    builder.set_filterable_fields(hashset! { S("tags") });
+    builder.execute(|_| ()).unwrap();
+
+    let indexing_config = IndexDocumentsConfig::default();
+    let mut builder =
+        IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+    let mut cursor = Cursor::new(Vec::new());
+    let mut documents_builder = DocumentBatchBuilder::new(&mut cursor).unwrap();
+
+    // the `z-common` tag is seen first, then a lot of unique tags, and then it comes back
+    for id in 0..10 {
+        let tags = match id {
+            0 => vec![S("z-common"), S("tag-0")],
+            1..=4 => vec![format!("tag-{}", id)],
+            _ => vec![S("z-common")],
+        };
+        let doc = serde_json::json!({ "id": id, "tags": tags });
+        documents_builder.extend_from_json(Cursor::new(serde_json::to_vec(&doc).unwrap())).unwrap();
+    }
+
+    documents_builder.finish().unwrap();
+    cursor.set_position(0);
+
+    let content = DocumentBatchReader::from_reader(cursor).unwrap();
+    builder.add_documents(content).unwrap();
+    builder.execute().unwrap();
+
+    wtxn.commit().unwrap();
+
+    let txn = index.read_txn().unwrap();
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    distrib.max_values_per_facet(1);
+
+    // placeholder search, and then a search with candidates
+    for _ in 0..2 {
+        let result = distrib.execute_with_truncation().unwrap();
+        assert!(result.truncated);
+        let expected: BTreeMap<_, _> = vec![(S("z-common"), 6)].into_iter().collect();
+        assert_eq!(result.distribution["tags"], expected);
+
+        distrib.candidates(index.documents_ids(&txn).unwrap());
+    }
+}
+
+#[test]
+fn test_facet_distribution_order_by() {
+    let path = tempfile::tempdir().unwrap();
+    let mut options = EnvOpenOptions::new();
+    options.map_size(10 * 1024 * 1024); // 10 MB
+    let index = Index::new(options, &path).unwrap();
+
+    let mut wtxn = index.write_txn().unwrap();
+    let config = IndexerConfig::default();
+    let mut builder = Settings::new(&mut wtxn, &index, &config);
+    builder.set_filterable_fields(hashset! { S("tags") });
+    builder.execute(|_| ()).unwrap();
+
+    let indexing_config = IndexDocumentsConfig::default();
+    let mut builder =
+        IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+    let mut cursor = Cursor::new(Vec::new());
+    let mut documents_builder = DocumentBatchBuilder::new(&mut cursor).unwrap();
+
+    // the tag `tag-i` is shared by the documents with an id lower or equal to `i`
+    for id in 0..10 {
+        let tags: Vec<_> = (id..10).map(|i| format!("tag-{}", i)).collect();
+        let doc = serde_json::json!({ "id": id, "tags": tags });
+        documents_builder.extend_from_json(Cursor::new(serde_json::to_vec(&doc).unwrap())).unwrap();
+    }
+
+    documents_builder.finish().unwrap();
+    cursor.set_position(0);
+
+    let content = DocumentBatchReader::from_reader(cursor).unwrap();
+    builder.add_documents(content).unwrap();
+    builder.execute().unwrap();
+
+    wtxn.commit().unwrap();
+
+    let txn = index.read_txn().unwrap();
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    distrib.max_values_per_facet(3);
+
+    // placeholder search, and then a search with candidates
+    for _ in 0..2 {
+        let result = distrib.order_by(OrderBy::Count).execute_ordered().unwrap();
+        let expected = vec![(S("tag-9"), 10), (S("tag-8"), 9), (S("tag-7"), 8)];
+        assert_eq!(result["tags"], expected);
+
+        let result = distrib.order_by(OrderBy::Lexicographic).execute_ordered().unwrap();
+        let expected = vec![(S("tag-0"), 1), (S("tag-1"), 2), (S("tag-2"), 3)];
+        assert_eq!(result["tags"], expected);
+
+        distrib.candidates(index.documents_ids(&txn).unwrap());
+    }
+}
+
+#[test]
+fn test_facet_distribution_on_arrays_of_objects() {
+    let path = tempfile::tempdir().unwrap();
+    let mut options = EnvOpenOptions::new();
+    options.map_size(10 * 1024 * 1024); // 10 MB
+    let index = Index::new(options, &path).unwrap();
+
+    let mut wtxn = index.write_txn().unwrap();
+    let config = IndexerConfig::default();
+    let mut builder = Settings::new(&mut wtxn, &index, &config);
+    builder.set_filterable_fields(hashset! { S("variants") });
+    builder.execute(|_| ()).unwrap();
+
+    let indexing_config = IndexDocumentsConfig::default();
+    let mut builder =
+        IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+    let mut cursor = Cursor::new(Vec::new());
+    let mut documents_builder = DocumentBatchBuilder::new(&mut cursor).unwrap();
+    let reader = Cursor::new(
+        r#"[
+        {
+            "id": 0,
+            "variants": [{ "color": "red", "size": 1 }, { "color": "Red", "size": 2 }]
+        },
+        {
+            "id": 1,
+            "variants": [{ "color": "blue", "size": "1" }],
+            "variants.color": "red"
+        },
+        {
+            "id": 2,
+            "variants.color": ["blue", "green"],
+            "variants": { "color": "green" }
+        }
+    ]"#,
+    );
+
+    for doc in serde_json::Deserializer::from_reader(reader).into_iter::<serde_json::Value>() {
+        let doc = Cursor::new(serde_json::to_vec(&doc.unwrap()).unwrap());
+        documents_builder.extend_from_json(doc).unwrap();
+    }
+
+    documents_builder.finish().unwrap();
+    cursor.set_position(0);
+
+    let content = DocumentBatchReader::from_reader(cursor).unwrap();
+    builder.add_documents(content).unwrap();
+    builder.execute().unwrap();
+
+    wtxn.commit().unwrap();
+
+    let txn = index.read_txn().unwrap();
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    distrib.facets(vec!["variants.color", "variants.size"]);
+
+    // placeholder search, and then a search with candidates
+    for _ in 0..2 {
+        let result = distrib.execute().unwrap();
+
+        let colors: Vec<_> =
+            result["variants.color"].iter().map(|(v, c)| (v.to_lowercase(), *c)).collect();
+        assert_eq!(colors, vec![(S("blue"), 2), (S("green"), 1), (S("red"), 2)]);
+
+        // the number `1` and the string `"1"` are returned under the same key
+        let sizes: Vec<_> = result["variants.size"].iter().map(|(v, c)| (v.as_str(), *c)).collect();
+        assert_eq!(sizes, vec![("1", 2), ("2", 1)]);
+
+        distrib.candidates(index.documents_ids(&txn).unwrap());
+    }
+}
+
+#[test]
+fn test_facet_distribution_sampling() {
+    let path = tempfile::tempdir().unwrap();
+    let mut options = EnvOpenOptions::new();
+    options.map_size(10 * 1024 * 1024); // 10 MB
+    let index = Index::new(options, &path).unwrap();
+
+    let mut wtxn = index.write_txn().unwrap();
+    let config = IndexerConfig::default();
+    let mut builder = Settings::new(&mut wtxn, &index, &config);
+    builder.set_filterable_fields(hashset! { S("parity") });
+    builder.execute(|_| ()).unwrap();
+
+    let indexing_config = IndexDocumentsConfig::default();
+    let mut builder =
+        IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+    let mut cursor = Cursor::new(Vec::new());
+    let mut documents_builder = DocumentBatchBuilder::new(&mut cursor).unwrap();
+
+    for id in 0..1000 {
+        let parity = if id % 2 == 0 { "even" } else { "odd" };
+        let doc = serde_json::json!({ "id": id, "parity": parity });
+        documents_builder.extend_from_json(Cursor::new(serde_json::to_vec(&doc).unwrap())).unwrap();
+    }
+
+    documents_builder.finish().unwrap();
+    cursor.set_position(0);
+
+    let content = DocumentBatchReader::from_reader(cursor).unwrap();
+    builder.add_documents(content).unwrap();
+    builder.execute().unwrap();
+
+    wtxn.commit().unwrap();
+
+    let txn = index.read_txn().unwrap();
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    distrib.sample(200);
+
+    // placeholder search, and then a search with candidates
+    for candidates in [1000, 800] {
+        let result = distrib.execute_with_truncation().unwrap();
+        let sampling = result.sampling.unwrap();
+        assert_eq!(sampling.sample_size, 200);
+        assert_eq!(sampling.candidates, candidates);
+        assert!(sampling.confident);
+
+        // the counts are extrapolated to all the candidates
+        let parity = &result.distribution["parity"];
+        assert_eq!(parity["even"] + parity["odd"], candidates);
+        assert!((candidates * 3 / 10..=candidates * 7 / 10).contains(&parity["even"]));
+
+        distrib.candidates((0..800).collect());
+    }
+
+    // there are fewer candidates than the sample size, the counts are exact
+    distrib.sample(2000);
+    let result = distrib.execute_with_truncation().unwrap();
+    assert_eq!(result.sampling, None);
+    let expected: BTreeMap<_, _> = vec![(S("even"), 400), (S("odd"), 400)].into_iter().collect();
+    assert_eq!(result.distribution["parity"], expected);
+}
@@ -57,7 +57,7 @@ pub fn setup_search_index_with_criteria(criteria: &[Criterion]) -> Index {
     builder.execute(|_| ()).unwrap();
 
     // index documents
-    let config = IndexerConfig { max_memory: Some(10 * 1024 * 1024), ..Default::default() };
+    let config = IndexerConfig::builder().max_memory(10 * 1024 * 1024).build().unwrap();
     let indexing_config = IndexDocumentsConfig { autogenerate_docids: true, ..Default::default() };
 
     let mut builder =
@@ -137,6 +137,18 @@ pub fn expected_order(
                     group.sort_by_key(|d| d.word_rank);
                     new_groups.extend(group.linear_group_by_key(|d| d.word_rank).map(Vec::from));
                 }
+                Criterion::WordsMinMatched(min_matched) => {
+                    let number_words = TEST_QUERY.split_whitespace().count() as u32;
+                    let min_matched = (*min_matched as u32).min(number_words);
+                    group.retain(|d| d.word_rank <= number_words - min_matched);
+                    group.sort_by_key(|d| d.word_rank);
+                    new_groups.extend(group.linear_group_by_key(|d| d.word_rank).map(Vec::from));
+                }
+                Criterion::TypoMax(max) => {
+                    group.retain(|d| d.typo_rank <= *max as u32);
+                    group.sort_by_key(|d| d.typo_rank);
+                    new_groups.extend(group.linear_group_by_key(|d| d.typo_rank).map(Vec::from));
+                }
                 Criterion::Asc(field_name) if field_name == "asc_desc_rank" => {
                     group.sort_by_key(|d| d.asc_desc_rank);
                     new_groups
@@ -54,6 +54,20 @@ macro_rules! test_criterion {
 test_criterion!(none_allow_typo, ALLOW_OPTIONAL_WORDS, ALLOW_TYPOS, vec![], vec![]);
 test_criterion!(none_disallow_typo, DISALLOW_OPTIONAL_WORDS, DISALLOW_TYPOS, vec![], vec![]);
 test_criterion!(words_allow_typo, ALLOW_OPTIONAL_WORDS, ALLOW_TYPOS, vec![Words], vec![]);
+test_criterion!(
+    words_min_matched_allow_typo,
+    ALLOW_OPTIONAL_WORDS,
+    ALLOW_TYPOS,
+    vec![WordsMinMatched(2)],
+    vec![]
+);
+test_criterion!(
+    typo_max_allow_typo,
+    ALLOW_OPTIONAL_WORDS,
+    ALLOW_TYPOS,
+    vec![Words, TypoMax(1)],
+    vec![]
+);
 test_criterion!(
     attribute_allow_typo,
     DISALLOW_OPTIONAL_WORDS,
@@ -369,6 +383,38 @@ fn criteria_mixup() {
     }
 }
 
+#[test]
+fn ranking_rules_override() {
+    let index = search::setup_search_index_with_criteria(&[Words, Typo]);
+    let rtxn = index.read_txn().unwrap();
+
+    let criteria = vec![Words, Desc(S("asc_desc_rank")), Typo];
+    let mut search = Search::new(&rtxn, &index);
+    search.query(search::TEST_QUERY);
+    search.limit(EXTERNAL_DOCUMENTS_IDS.len());
+    search.ranking_rules(criteria.clone());
+
+    let SearchResult { documents_ids, .. } = search.execute().unwrap();
+
+    let expected_external_ids: Vec<_> =
+        search::expected_order(&criteria, ALLOW_TYPOS, ALLOW_OPTIONAL_WORDS, &[])
+            .into_iter()
+            .map(|d| d.id)
+            .collect();
+    let documents_ids = search::internal_to_external_ids(&index, &documents_ids);
+    assert_eq!(documents_ids, expected_external_ids);
+
+    // the settings of the index are left untouched
+    assert_eq!(index.criteria(&rtxn).unwrap(), vec![Words, Typo]);
+
+    // the fields of the ranking rules must be faceted
+    let mut search = Search::new(&rtxn, &index);
+    search.query(search::TEST_QUERY);
+    search.ranking_rules(vec![Words, Asc(S("title"))]);
+    let error = search.execute().unwrap_err();
+    assert!(error.to_string().starts_with("Attribute `title` cannot be used in a ranking rule."));
+}
+
 #[test]
 fn criteria_ascdesc() {
     let path = tempfile::tempdir().unwrap();
@@ -388,7 +434,7 @@ fn criteria_ascdesc() {
     builder.execute(|_| ()).unwrap();
 
     // index documents
-    let config = IndexerConfig { max_memory: Some(10 * 1024 * 1024), ..Default::default() };
+    let config = IndexerConfig::builder().max_memory(10 * 1024 * 1024).build().unwrap();
     let indexing_config = IndexDocumentsConfig { autogenerate_docids: true, ..Default::default() };
     let mut builder =
         IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
@@ -1,6 +1,8 @@
+use std::collections::HashMap;
+
 use big_s::S;
-use milli::Criterion::{Attribute, Exactness, Proximity, Typo, Words};
-use milli::{AscDesc, Error, Member, Search, UserError};
+use milli::Criterion::{Attribute, Exactness, Proximity, Sort, Typo, Words};
+use milli::{AscDesc, Error, Member, Search, SearchResult, UserError};
 
 use crate::search::{self, EXTERNAL_DOCUMENTS_IDS};
 
@@ -21,3 +23,30 @@ fn sort_ranking_rule_missing() {
     let result = search.execute();
     assert!(matches!(result, Err(Error::UserError(UserError::SortRankingRuleMissing))));
 }
+
+#[test]
+fn sort_by_external_sort_key() {
+    let criteria = vec![Sort];
+    let index = search::setup_search_index_with_criteria(&criteria);
+    let rtxn = index.read_txn().unwrap();
+
+    let documents_ids = index.documents_ids(&rtxn).unwrap();
+    // only the even documents have a popularity
+    let popularity: HashMap<_, _> = documents_ids
+        .iter()
+        .filter(|docid| docid % 2 == 0)
+        .map(|docid| (docid, docid as f64))
+        .collect();
+
+    let mut search = Search::new(&rtxn, &index);
+    search.limit(EXTERNAL_DOCUMENTS_IDS.len());
+    search.external_sort_key("popularity", popularity);
+    search.sort_criteria(vec![AscDesc::Desc(Member::Field(S("popularity")))]);
+
+    let SearchResult { documents_ids: result, .. } = search.execute().unwrap();
+
+    let mut expected: Vec<_> = documents_ids.iter().filter(|docid| docid % 2 == 0).collect();
+    expected.reverse();
+    expected.extend(documents_ids.iter().filter(|docid| docid % 2 == 1));
+    assert_eq!(result, expected);
+}